@@ -6,6 +6,10 @@ use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 use std::str::FromStr;
 
+mod quantity;
+
+pub use quantity::{format_quantity_nanos, parse_quantity_nanos, QuantityError, QuantityFormat};
+
 /// Quantity is a representation of a numeric value with an optional SI suffix.
 ///
 /// Examples: "100m", "1Gi", "500Mi", "1.5"
@@ -97,6 +101,30 @@ impl fmt::Display for IntOrString {
     }
 }
 
+impl IntOrString {
+    /// Returns the value scaled to `total` if it is a percentage like
+    /// `25%`, rounding up or down, and the integer value otherwise, like
+    /// `intstr.GetScaledValueFromIntOrPercent`. Strings that are not
+    /// percentages are invalid.
+    pub fn get_scaled_value_from_int_or_percent(&self, total: i32, round_up: bool) -> Result<i32, String> {
+        let percent = match self {
+            IntOrString::Int(value) => return Ok(*value),
+            IntOrString::String(value) => value,
+        };
+        let Some(digits) = percent.strip_suffix('%') else {
+            return Err("invalid value for IntOrString: invalid type: string is not a percentage".to_string());
+        };
+        let value: i32 = digits.parse().map_err(|_| {
+            format!(
+                "invalid value for IntOrString: invalid value {:?}: strconv.Atoi: parsing {:?}: invalid syntax",
+                percent, digits
+            )
+        })?;
+        let scaled = f64::from(value) * f64::from(total) / 100.0;
+        Ok(if round_up { scaled.ceil() } else { scaled.floor() } as i32)
+    }
+}
+
 impl Serialize for IntOrString {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -181,4 +209,18 @@ mod tests {
         assert_eq!(parsed_int, IntOrString::Int(8080));
         assert_eq!(parsed_str, IntOrString::String("http".to_string()));
     }
+
+    #[test]
+    fn test_get_scaled_value_from_int_or_percent() {
+        let scaled = |value: IntOrString, round_up| value.get_scaled_value_from_int_or_percent(10, round_up);
+        assert_eq!(scaled(3.into(), true), Ok(3));
+        assert_eq!(scaled("25%".into(), true), Ok(3));
+        assert_eq!(scaled("25%".into(), false), Ok(2));
+        assert_eq!(scaled("100%".into(), false), Ok(10));
+        assert_eq!(
+            scaled("3".into(), true),
+            Err("invalid value for IntOrString: invalid type: string is not a percentage".to_string())
+        );
+        assert!(scaled("a%".into(), true).is_err());
+    }
 }
@@ -0,0 +1,329 @@
+//! Numeric handling of Quantity values
+//!
+//! Parses quantities into an exact count of nano units, compares and adds
+//! them, and formats results in canonical form the way `resource.Quantity`
+//! does in Go: values finer than `1n` round up, binary quantities are written
+//! with the largest exact `Ki`..`Ei` suffix, and decimal quantities with the
+//! largest exact `n`..`E` suffix.
+
+use super::Quantity;
+use std::cmp::Ordering;
+use thiserror::Error;
+
+const NANOS_PER_UNIT: i128 = 1_000_000_000;
+const MAX_MANTISSA: i128 = 10i128.pow(30);
+
+/// QuantityFormat is the suffix family a quantity is written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuantityFormat {
+    /// e.g. `12e6`
+    DecimalExponent,
+    /// e.g. `12Mi`
+    BinarySI,
+    /// e.g. `12M`
+    DecimalSI,
+}
+
+/// QuantityError is returned for strings that are not valid quantities.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum QuantityError {
+    #[error("quantities must match the regular expression '^([+-]?[0-9.]+)([eEinumkKMGTP]*[-+]?[0-9]*)$'")]
+    Format,
+    #[error("unable to parse quantity's suffix")]
+    Suffix,
+    #[error("quantity is too large")]
+    Overflow,
+}
+
+fn pow10(exp: u32) -> Result<i128, QuantityError> {
+    10i128.checked_pow(exp).ok_or(QuantityError::Overflow)
+}
+
+/// Splits a quantity string into its numeric part and suffix.
+fn split_quantity(input: &str) -> Result<(&str, &str), QuantityError> {
+    let number_end = input
+        .char_indices()
+        .find(|(idx, ch)| !(ch.is_ascii_digit() || *ch == '.' || (*idx == 0 && (*ch == '+' || *ch == '-'))))
+        .map(|(idx, _)| idx)
+        .unwrap_or(input.len());
+    let (number, suffix) = input.split_at(number_end);
+    let digits = number.trim_start_matches(['+', '-']);
+    if digits.is_empty() || digits == "." || digits.matches('.').count() > 1 {
+        return Err(QuantityError::Format);
+    }
+    Ok((number, suffix))
+}
+
+/// Returns (base, exponent, format) of a suffix.
+fn parse_suffix(suffix: &str) -> Result<(i128, i32, QuantityFormat), QuantityError> {
+    let binary = |exp| Ok((1024, exp, QuantityFormat::BinarySI));
+    let decimal = |exp| Ok((10, exp, QuantityFormat::DecimalSI));
+    match suffix {
+        "" => decimal(0),
+        "Ki" => binary(1),
+        "Mi" => binary(2),
+        "Gi" => binary(3),
+        "Ti" => binary(4),
+        "Pi" => binary(5),
+        "Ei" => binary(6),
+        "n" => decimal(-9),
+        "u" => decimal(-6),
+        "m" => decimal(-3),
+        "k" => decimal(3),
+        "M" => decimal(6),
+        "G" => decimal(9),
+        "T" => decimal(12),
+        "P" => decimal(15),
+        "E" => decimal(18),
+        _ => {
+            let exponent = suffix
+                .strip_prefix('e')
+                .or_else(|| suffix.strip_prefix('E'))
+                .ok_or(QuantityError::Suffix)?;
+            let exponent: i32 = exponent.parse().map_err(|_| QuantityError::Suffix)?;
+            Ok((10, exponent, QuantityFormat::DecimalExponent))
+        }
+    }
+}
+
+/// Parses `input` into nano units, rounding up values finer than `1n`.
+pub fn parse_quantity_nanos(input: &str) -> Result<(i128, QuantityFormat), QuantityError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(QuantityError::Format);
+    }
+    let (number, suffix) = split_quantity(input)?;
+    let (base, exponent, format) = parse_suffix(suffix)?;
+
+    let negative = number.starts_with('-');
+    let digits = number.trim_start_matches(['+', '-']);
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+    // mantissa / 10^scale is the exact number; digits that do not fit are
+    // only remembered for rounding.
+    let mut mantissa: i128 = 0;
+    let mut scale: u32 = 0;
+    let mut truncated = false;
+    for ch in integer.chars() {
+        let digit = ch.to_digit(10).ok_or(QuantityError::Format)? as i128;
+        mantissa = mantissa
+            .checked_mul(10)
+            .and_then(|m| m.checked_add(digit))
+            .filter(|m| *m < MAX_MANTISSA)
+            .ok_or(QuantityError::Overflow)?;
+    }
+    for ch in fraction.chars() {
+        let digit = ch.to_digit(10).ok_or(QuantityError::Format)? as i128;
+        if mantissa >= MAX_MANTISSA / 10 {
+            truncated |= digit != 0;
+            continue;
+        }
+        mantissa = mantissa * 10 + digit;
+        scale += 1;
+    }
+
+    // value = mantissa / 10^scale * base^exponent * 10^9
+    let mut numerator = mantissa;
+    let mut denominator: i128 = pow10(scale)?;
+    let apply = |value: i128, factor: i128| value.checked_mul(factor).ok_or(QuantityError::Overflow);
+    if base == 1024 {
+        numerator = apply(numerator, 1024i128.pow(exponent as u32))?;
+    } else if exponent >= 0 {
+        numerator = apply(numerator, pow10(exponent as u32)?)?;
+    } else {
+        denominator = apply(denominator, pow10(exponent.unsigned_abs())?)?;
+    }
+    numerator = apply(numerator, NANOS_PER_UNIT)?;
+
+    let mut nanos = numerator / denominator;
+    if numerator % denominator != 0 || truncated {
+        nanos += 1;
+    }
+    if negative {
+        nanos = -nanos;
+    }
+    Ok((nanos, format))
+}
+
+const DECIMAL_SUFFIXES: &[(i32, &str)] = &[
+    (18, "E"),
+    (15, "P"),
+    (12, "T"),
+    (9, "G"),
+    (6, "M"),
+    (3, "k"),
+    (0, ""),
+    (-3, "m"),
+    (-6, "u"),
+    (-9, "n"),
+];
+
+const BINARY_SUFFIXES: &[&str] = &["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"];
+
+/// Formats a nano-unit value in canonical form for `format`.
+pub fn format_quantity_nanos(nanos: i128, format: QuantityFormat) -> String {
+    if nanos == 0 {
+        return "0".to_string();
+    }
+    let sign = if nanos < 0 { "-" } else { "" };
+    let magnitude = nanos.unsigned_abs();
+
+    if format == QuantityFormat::BinarySI
+        && magnitude.is_multiple_of(NANOS_PER_UNIT as u128)
+        && magnitude / NANOS_PER_UNIT as u128 >= 1024
+    {
+        let mut value = magnitude / NANOS_PER_UNIT as u128;
+        let mut idx = 0;
+        while value.is_multiple_of(1024) && idx < BINARY_SUFFIXES.len() - 1 {
+            value /= 1024;
+            idx += 1;
+        }
+        return format!("{}{}{}", sign, value, BINARY_SUFFIXES[idx]);
+    }
+
+    // Express the value as mantissa * 10^exponent with the exponent a
+    // multiple of three, starting at nano units.
+    let mut mantissa = magnitude;
+    let mut exponent = -9;
+    while mantissa.is_multiple_of(1000) && exponent < 18 {
+        mantissa /= 1000;
+        exponent += 3;
+    }
+    if format == QuantityFormat::DecimalExponent {
+        return if exponent == 0 {
+            format!("{}{}", sign, mantissa)
+        } else {
+            format!("{}{}e{}", sign, mantissa, exponent)
+        };
+    }
+    let suffix = DECIMAL_SUFFIXES
+        .iter()
+        .find(|(exp, _)| *exp == exponent)
+        .map(|(_, suffix)| *suffix)
+        .unwrap_or_default();
+    format!("{}{}{}", sign, mantissa, suffix)
+}
+
+impl Quantity {
+    /// Returns the exact value in nano units, rounding up values finer than `1n`.
+    pub fn to_nanos(&self) -> Result<i128, QuantityError> {
+        parse_quantity_nanos(self.as_str()).map(|(nanos, _)| nanos)
+    }
+
+    /// Returns the suffix family the quantity is written in.
+    pub fn format(&self) -> Result<QuantityFormat, QuantityError> {
+        parse_quantity_nanos(self.as_str()).map(|(_, format)| format)
+    }
+
+    /// Builds a canonical quantity from a nano-unit value.
+    pub fn from_nanos(nanos: i128, format: QuantityFormat) -> Self {
+        Quantity(format_quantity_nanos(nanos, format))
+    }
+
+    /// Returns the canonical form of the quantity, e.g. `1536Mi` for `1.5Gi`.
+    pub fn canonicalize(&self) -> Result<Self, QuantityError> {
+        let (nanos, format) = parse_quantity_nanos(self.as_str())?;
+        Ok(Self::from_nanos(nanos, format))
+    }
+
+    /// Returns the value rounded up to an integer, like `Quantity.Value()`.
+    pub fn value(&self) -> Result<i64, QuantityError> {
+        let nanos = self.to_nanos()?;
+        let value = nanos.div_euclid(NANOS_PER_UNIT) + i128::from(nanos.rem_euclid(NANOS_PER_UNIT) != 0);
+        i64::try_from(value).map_err(|_| QuantityError::Overflow)
+    }
+
+    /// Returns the value in milli units rounded up, like `Quantity.MilliValue()`.
+    pub fn milli_value(&self) -> Result<i64, QuantityError> {
+        let nanos = self.to_nanos()?;
+        let value = nanos.div_euclid(1_000_000) + i128::from(nanos.rem_euclid(1_000_000) != 0);
+        i64::try_from(value).map_err(|_| QuantityError::Overflow)
+    }
+
+    /// Returns the value as a float, which may lose precision.
+    pub fn as_approximate_float(&self) -> Result<f64, QuantityError> {
+        Ok(self.to_nanos()? as f64 / NANOS_PER_UNIT as f64)
+    }
+
+    /// Compares the values of two quantities.
+    pub fn cmp_value(&self, other: &Quantity) -> Result<Ordering, QuantityError> {
+        Ok(self.to_nanos()?.cmp(&other.to_nanos()?))
+    }
+
+    /// Returns `self + other` in the format of `self`.
+    pub fn checked_add(&self, other: &Quantity) -> Result<Quantity, QuantityError> {
+        let (left, format) = parse_quantity_nanos(self.as_str())?;
+        let sum = left.checked_add(other.to_nanos()?).ok_or(QuantityError::Overflow)?;
+        Ok(Self::from_nanos(sum, format))
+    }
+
+    /// Returns `self - other` in the format of `self`.
+    pub fn checked_sub(&self, other: &Quantity) -> Result<Quantity, QuantityError> {
+        let (left, format) = parse_quantity_nanos(self.as_str())?;
+        let difference = left.checked_sub(other.to_nanos()?).ok_or(QuantityError::Overflow)?;
+        Ok(Self::from_nanos(difference, format))
+    }
+
+    /// Returns true if the quantity is zero.
+    pub fn is_zero(&self) -> bool {
+        self.to_nanos() == Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nanos(input: &str) -> i128 {
+        Quantity::new(input).to_nanos().unwrap()
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(nanos("1"), 1_000_000_000);
+        assert_eq!(nanos("100m"), 100_000_000);
+        assert_eq!(nanos("1.5"), 1_500_000_000);
+        assert_eq!(nanos("1Ki"), 1024 * 1_000_000_000);
+        assert_eq!(nanos("2e3"), 2_000 * 1_000_000_000);
+        assert_eq!(nanos("-5n"), -5);
+        assert_eq!(nanos("0.1n"), 1);
+        assert_eq!(nanos(".5"), 500_000_000);
+        assert_eq!(nanos("+1k"), 1_000_000_000_000);
+
+        for invalid in ["", "abc", "1.2.3", "1Ko", "1e", "."] {
+            assert!(Quantity::new(invalid).to_nanos().is_err(), "{} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_quantity() {
+        let cases = [
+            ("1.5Gi", "1536Mi"),
+            ("1024", "1024"),
+            ("1024Ki", "1Mi"),
+            ("0.5", "500m"),
+            ("1000m", "1"),
+            ("1500m", "1500m"),
+            ("1e3", "1e3"),
+            ("1000", "1k"),
+            ("0", "0"),
+            ("0.5Ki", "512"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Quantity::new(input).canonicalize().unwrap().as_str(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_quantity_arithmetic() {
+        let a = Quantity::new("100m");
+        let b = Quantity::new("200m");
+        assert_eq!(a.checked_add(&b).unwrap().as_str(), "300m");
+        assert_eq!(a.checked_sub(&b).unwrap().as_str(), "-100m");
+        assert_eq!(Quantity::new("1Gi").checked_add(&Quantity::new("1Gi")).unwrap().as_str(), "2Gi");
+        assert_eq!(a.cmp_value(&b).unwrap(), Ordering::Less);
+        assert_eq!(Quantity::new("1500m").value().unwrap(), 2);
+        assert_eq!(Quantity::new("1.5").milli_value().unwrap(), 1500);
+        assert!(Quantity::new("0m").is_zero());
+    }
+}
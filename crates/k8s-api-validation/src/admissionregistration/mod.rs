@@ -2,6 +2,7 @@
 //!
 //! This module provides validation for admissionregistration API types.
 
+use crate::cel::{validate_admission_expression, ExpectedType};
 use crate::common::{validate_dns_label, validate_dns_subdomain_name, validate_label_key, validate_object_meta};
 use crate::{ValidationError, ValidationResult};
 
@@ -133,6 +134,11 @@ fn validate_match_condition_fields(name: &str, expression: &str, field: &str) ->
         &format!("{}.expression", field),
         "expression is required",
     ));
+    errors.extend(validate_admission_expression(
+        expression,
+        &format!("{}.expression", field),
+        ExpectedType::Bool,
+    ));
     errors
 }
 
@@ -661,6 +667,18 @@ pub mod v1beta1 {
             &format!("{}.expression", field),
             "expression is required",
         ));
+        errors.extend(validate_admission_expression(
+            &validation.expression,
+            &format!("{}.expression", field),
+            ExpectedType::Bool,
+        ));
+        if let Some(message_expression) = &validation.message_expression {
+            errors.extend(validate_admission_expression(
+                message_expression,
+                &format!("{}.messageExpression", field),
+                ExpectedType::String,
+            ));
+        }
 
         errors
     }
@@ -688,6 +706,11 @@ pub mod v1beta1 {
             &format!("{}.valueExpression", field),
             "valueExpression is required",
         ));
+        errors.extend(validate_admission_expression(
+            &annotation.value_expression,
+            &format!("{}.valueExpression", field),
+            ExpectedType::StringOrNull,
+        ));
 
         errors
     }
@@ -712,6 +735,11 @@ pub mod v1beta1 {
             &format!("{}.expression", field),
             "expression is required",
         ));
+        errors.extend(validate_admission_expression(
+            &variable.expression,
+            &format!("{}.expression", field),
+            ExpectedType::Any,
+        ));
 
         errors
     }
@@ -1143,6 +1171,11 @@ pub mod v1alpha1 {
                 &format!("{}.applyConfiguration.expression", field),
                 "expression is required",
             ));
+            errors.extend(validate_admission_expression(
+                &apply.expression,
+                &format!("{}.applyConfiguration.expression", field),
+                ExpectedType::Any,
+            ));
         }
 
         if let Some(json_patch) = &mutation.json_patch {
@@ -1151,6 +1184,11 @@ pub mod v1alpha1 {
                 &format!("{}.jsonPatch.expression", field),
                 "expression is required",
             ));
+            errors.extend(validate_admission_expression(
+                &json_patch.expression,
+                &format!("{}.jsonPatch.expression", field),
+                ExpectedType::Any,
+            ));
         }
 
         errors
@@ -1258,6 +1296,16 @@ pub mod v1alpha1 {
             &format!("{}.expression", field),
             "expression is required",
         ));
+        errors.extend(validate_admission_expression(
+            &validation.expression,
+            &format!("{}.expression", field),
+            ExpectedType::Bool,
+        ));
+        errors.extend(validate_admission_expression(
+            &validation.message_expression,
+            &format!("{}.messageExpression", field),
+            ExpectedType::String,
+        ));
 
         errors
     }
@@ -1285,6 +1333,11 @@ pub mod v1alpha1 {
             &format!("{}.valueExpression", field),
             "valueExpression is required",
         ));
+        errors.extend(validate_admission_expression(
+            &annotation.value_expression,
+            &format!("{}.valueExpression", field),
+            ExpectedType::StringOrNull,
+        ));
 
         errors
     }
@@ -1309,6 +1362,11 @@ pub mod v1alpha1 {
             &format!("{}.expression", field),
             "expression is required",
         ));
+        errors.extend(validate_admission_expression(
+            &variable.expression,
+            &format!("{}.expression", field),
+            ExpectedType::Any,
+        ));
 
         errors
     }
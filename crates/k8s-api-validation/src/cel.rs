@@ -0,0 +1,125 @@
+//! CEL expression validation
+//!
+//! Compiles the CEL expressions of admission policies, webhook match
+//! conditions and DRA device selectors against the variables the API server
+//! declares for them, and checks the type they evaluate to.
+
+use k8s_cel::types::{AUTHORIZER_TYPE, RESOURCE_CHECK_TYPE};
+use k8s_cel::{Env, Type};
+use once_cell::sync::Lazy;
+
+use crate::{ValidationError, ValidationResult};
+
+/// The type an expression must evaluate to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExpectedType {
+    Bool,
+    String,
+    /// A string or null, as for audit annotation values.
+    StringOrNull,
+    Any,
+}
+
+/// Variables available to admission expressions. Composited variables are
+/// referenced as fields of `variables`, whose names are checked at
+/// admission time. Mutations construct `Object` apply configurations and
+/// `JSONPatch` operations.
+static ADMISSION_ENV: Lazy<Env> = Lazy::new(|| {
+    Env::new()
+        .with_variable("object", Type::Dyn)
+        .with_variable("oldObject", Type::Dyn)
+        .with_variable("request", Type::Dyn)
+        .with_variable("params", Type::Dyn)
+        .with_variable("namespaceObject", Type::Dyn)
+        .with_variable("variables", Type::Dyn)
+        .with_variable("authorizer", Type::Opaque(AUTHORIZER_TYPE))
+        .with_variable("authorizer.requestResource", Type::Opaque(RESOURCE_CHECK_TYPE))
+        .with_dynamic_object_type("Object")
+        .with_dynamic_object_type("JSONPatch")
+});
+
+/// Variables available to DRA device selectors.
+static DEVICE_ENV: Lazy<Env> = Lazy::new(|| Env::new().with_variable("device", Type::Dyn));
+
+fn validate_expression(env: &Env, expression: &str, field: &str, expected: ExpectedType) -> ValidationResult {
+    if expression.is_empty() {
+        return Vec::new();
+    }
+    let program = match env.compile(expression) {
+        Ok(program) => program,
+        Err(err) => {
+            return vec![ValidationError::invalid(field, format!("compilation failed: {}", err))];
+        }
+    };
+    let result_type = program.result_type();
+    let valid = match expected {
+        ExpectedType::Bool => Type::Bool.is_assignable_from(result_type),
+        ExpectedType::String => Type::String.is_assignable_from(result_type),
+        ExpectedType::StringOrNull => {
+            Type::String.is_assignable_from(result_type) || *result_type == Type::Null
+        }
+        ExpectedType::Any => true,
+    };
+    if valid {
+        return Vec::new();
+    }
+    let expected = match expected {
+        ExpectedType::Bool => "bool",
+        ExpectedType::String => "string",
+        ExpectedType::StringOrNull => "string or null",
+        ExpectedType::Any => unreachable!("any type is valid"),
+    };
+    vec![ValidationError::invalid(
+        field,
+        format!("must evaluate to {} but got {}", expected, result_type),
+    )]
+}
+
+/// Compiles an admission policy or webhook expression.
+pub(crate) fn validate_admission_expression(
+    expression: &str,
+    field: &str,
+    expected: ExpectedType,
+) -> ValidationResult {
+    validate_expression(&ADMISSION_ENV, expression, field, expected)
+}
+
+/// Compiles a DRA device selector, which must evaluate to a bool.
+pub(crate) fn validate_device_selector_expression(expression: &str, field: &str) -> ValidationResult {
+    validate_expression(&DEVICE_ENV, expression, field, ExpectedType::Bool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_admission_expression() {
+        assert!(validate_admission_expression("object.spec.replicas < 5", "f", ExpectedType::Bool).is_empty());
+        assert!(validate_admission_expression(
+            "authorizer.requestResource.check('get').allowed()",
+            "f",
+            ExpectedType::Bool
+        )
+        .is_empty());
+
+        let errors = validate_admission_expression("object.spec.replicas <", "f", ExpectedType::Bool);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.starts_with("compilation failed: ERROR: <input>:1:23"), "{}", errors[0].message);
+
+        let errors = validate_admission_expression("'a'", "f", ExpectedType::Bool);
+        assert_eq!(errors[0].message, "must evaluate to bool but got string");
+        assert!(validate_admission_expression("null", "f", ExpectedType::StringOrNull).is_empty());
+    }
+
+    #[test]
+    fn test_validate_device_selector_expression() {
+        assert!(validate_device_selector_expression(
+            "device.driver == 'gpu.example.com' && device.attributes['gpu.example.com'].model == 'a100'",
+            "f"
+        )
+        .is_empty());
+        let errors = validate_device_selector_expression("object.spec", "f");
+        assert!(errors[0].message.contains("undeclared reference to 'object'"));
+    }
+}
@@ -14,6 +14,7 @@ pub mod autoscaling;
 pub mod authentication;
 pub mod authorization;
 pub mod batch;
+mod cel;
 pub mod certificates;
 pub mod common;
 pub mod coordination;
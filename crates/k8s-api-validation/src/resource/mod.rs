@@ -2,6 +2,7 @@
 //!
 //! This module provides validation for resource API types.
 
+use crate::cel::validate_device_selector_expression;
 use crate::common::{
     validate_dns_label, validate_dns_subdomain_name, validate_label_key, validate_object_meta,
     validate_quantity,
@@ -388,6 +389,10 @@ pub mod v1beta1 {
                     "expression is required",
                 ));
             }
+            errors.extend(validate_device_selector_expression(
+                &cel.expression,
+                &format!("{}.cel.expression", field),
+            ));
         } else {
             errors.push(ValidationError::required(
                 &format!("{}.cel", field),
@@ -1075,6 +1080,10 @@ pub mod v1beta2 {
                     "expression is required",
                 ));
             }
+            errors.extend(validate_device_selector_expression(
+                &cel.expression,
+                &format!("{}.cel.expression", field),
+            ));
         } else {
             errors.push(ValidationError::required(
                 &format!("{}.cel", field),
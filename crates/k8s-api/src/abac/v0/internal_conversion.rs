@@ -1,5 +1,57 @@
 use super::*;
 
+use crate::abac::internal::PolicySpec;
+
+/// The group v0 policies without a user or group, or with `*` for either,
+/// apply to.
+const ALL_AUTHENTICATED: &str = "system:authenticated";
+
 impl InternalConversion for Policy {
     type Internal = crate::abac::internal::Policy;
+
+    fn into_internal(&self) -> Result<Self::Internal, serde_json::Error> {
+        let mut spec = PolicySpec {
+            user: self.user.clone(),
+            group: self.group.clone(),
+            read_only: self.read_only,
+            // v0 policies match all API groups.
+            api_group: "*".to_string(),
+            resource: self.resource.clone(),
+            namespace: self.namespace.clone(),
+            non_resource_path: String::new(),
+        };
+        if self.user.is_empty() && self.group.is_empty() {
+            spec.group = ALL_AUTHENTICATED.to_string();
+        }
+        if self.user == "*" || self.group == "*" {
+            spec.user = String::new();
+            spec.group = ALL_AUTHENTICATED.to_string();
+        }
+        // An empty namespace or resource matches all of them, and with both
+        // empty all non-resource paths too.
+        if self.namespace.is_empty() {
+            spec.namespace = "*".to_string();
+        }
+        if self.resource.is_empty() {
+            spec.resource = "*".to_string();
+        }
+        if self.namespace.is_empty() && self.resource.is_empty() {
+            spec.non_resource_path = "*".to_string();
+        }
+        Ok(Self::Internal {
+            type_meta: self.type_meta.clone(),
+            spec,
+        })
+    }
+
+    fn from_internal(internal: &Self::Internal) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            type_meta: internal.type_meta.clone(),
+            user: internal.spec.user.clone(),
+            group: internal.spec.group.clone(),
+            read_only: internal.spec.read_only,
+            resource: internal.spec.resource.clone(),
+            namespace: internal.spec.namespace.clone(),
+        })
+    }
 }
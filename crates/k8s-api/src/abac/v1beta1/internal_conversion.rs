@@ -1,9 +1,55 @@
 use super::*;
 
+/// The group v1beta1 policies with `*` for the user or group apply to.
+const ALL_AUTHENTICATED: &str = "system:authenticated";
+
 impl InternalConversion for Policy {
     type Internal = crate::abac::internal::Policy;
+
+    fn into_internal(&self) -> Result<Self::Internal, serde_json::Error> {
+        let mut spec = self.spec.into_internal()?;
+        if spec.user == "*" || spec.group == "*" {
+            spec.user = String::new();
+            spec.group = ALL_AUTHENTICATED.to_string();
+        }
+        Ok(Self::Internal {
+            type_meta: self.type_meta.clone(),
+            spec,
+        })
+    }
+
+    fn from_internal(internal: &Self::Internal) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            type_meta: internal.type_meta.clone(),
+            spec: PolicySpec::from_internal(&internal.spec)?,
+        })
+    }
 }
 
 impl InternalConversion for PolicySpec {
     type Internal = crate::abac::internal::PolicySpec;
+
+    fn into_internal(&self) -> Result<Self::Internal, serde_json::Error> {
+        Ok(Self::Internal {
+            user: self.user.clone(),
+            group: self.group.clone(),
+            read_only: self.read_only,
+            api_group: self.api_group.clone(),
+            resource: self.resource.clone(),
+            namespace: self.namespace.clone(),
+            non_resource_path: self.non_resource_path.clone(),
+        })
+    }
+
+    fn from_internal(internal: &Self::Internal) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            user: internal.user.clone(),
+            group: internal.group.clone(),
+            read_only: internal.read_only,
+            api_group: internal.api_group.clone(),
+            resource: internal.resource.clone(),
+            namespace: internal.namespace.clone(),
+            non_resource_path: internal.non_resource_path.clone(),
+        })
+    }
 }
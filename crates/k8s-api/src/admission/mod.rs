@@ -3,5 +3,6 @@
 //! Types for admission webhook requests and responses.
 
 pub mod internal;
+pub mod review;
 pub mod v1;
 pub mod v1beta1;
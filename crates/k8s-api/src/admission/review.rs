@@ -0,0 +1,132 @@
+//! AdmissionReviews in either served version
+//!
+//! Webhooks may be called with `admission.k8s.io/v1` or the deprecated
+//! `admission.k8s.io/v1beta1` AdmissionReview and must answer in the version
+//! they were called with. `VersionedAdmissionReview` decodes either one,
+//! exposes the request as v1, and wraps a v1 response back into the
+//! incoming version.
+
+use serde::de::Error as _;
+use k8s_apimachinery::apis::meta::v1::TypeMeta;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{v1, v1beta1};
+
+/// An AdmissionReview in the version it was received in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VersionedAdmissionReview {
+    V1(v1::AdmissionReview),
+    V1beta1(v1beta1::AdmissionReview),
+}
+
+impl VersionedAdmissionReview {
+    pub fn api_version(&self) -> &'static str {
+        match self {
+            VersionedAdmissionReview::V1(_) => v1::AdmissionReview::API_VERSION,
+            VersionedAdmissionReview::V1beta1(_) => v1beta1::AdmissionReview::API_VERSION,
+        }
+    }
+
+    /// Returns the request, converted to v1.
+    pub fn request(&self) -> Option<v1::AdmissionRequest> {
+        match self {
+            VersionedAdmissionReview::V1(review) => review.request.clone(),
+            VersionedAdmissionReview::V1beta1(review) => review.request.clone().map(Into::into),
+        }
+    }
+
+    /// Returns the review answering this one with `response`, in the same
+    /// version. The response `uid` is set to that of the request.
+    pub fn respond(&self, mut response: v1::AdmissionResponse) -> Self {
+        if let Some(request) = self.request() {
+            response.uid = request.uid;
+        }
+        let review = v1::AdmissionReview {
+            type_meta: TypeMeta::new(v1::AdmissionReview::API_VERSION, v1::AdmissionReview::KIND),
+            request: None,
+            response: Some(response),
+        };
+        match self {
+            VersionedAdmissionReview::V1(_) => VersionedAdmissionReview::V1(review),
+            VersionedAdmissionReview::V1beta1(_) => VersionedAdmissionReview::V1beta1(review.into()),
+        }
+    }
+}
+
+impl Serialize for VersionedAdmissionReview {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            VersionedAdmissionReview::V1(review) => review.serialize(serializer),
+            VersionedAdmissionReview::V1beta1(review) => review.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionedAdmissionReview {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let api_version = value.get("apiVersion").and_then(|v| v.as_str()).unwrap_or_default();
+        match api_version {
+            v1::AdmissionReview::API_VERSION => serde_json::from_value(value)
+                .map(VersionedAdmissionReview::V1)
+                .map_err(D::Error::custom),
+            v1beta1::AdmissionReview::API_VERSION => serde_json::from_value(value)
+                .map(VersionedAdmissionReview::V1beta1)
+                .map_err(D::Error::custom),
+            other => Err(D::Error::custom(format!(
+                "unsupported AdmissionReview apiVersion {:?}, expected {:?} or {:?}",
+                other,
+                v1::AdmissionReview::API_VERSION,
+                v1beta1::AdmissionReview::API_VERSION
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn review(api_version: &str) -> serde_json::Value {
+        json!({
+            "apiVersion": api_version,
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "kind": {"group": "", "version": "v1", "kind": "Pod"},
+                "resource": {"group": "", "version": "v1", "resource": "pods"},
+                "operation": "CREATE",
+                "userInfo": {"username": "admin"},
+                "object": {"metadata": {"name": "web"}},
+            },
+        })
+    }
+
+    #[test]
+    fn test_versioned_admission_review() {
+        for api_version in ["admission.k8s.io/v1", "admission.k8s.io/v1beta1"] {
+            let incoming: VersionedAdmissionReview = serde_json::from_value(review(api_version)).unwrap();
+            assert_eq!(incoming.api_version(), api_version);
+            let request = incoming.request().unwrap();
+            assert_eq!(request.user_info.username, "admin");
+
+            let outgoing = incoming.respond(v1::AdmissionResponse::allow().with_warnings(["checked"]));
+            assert_eq!(
+                serde_json::to_value(&outgoing).unwrap(),
+                json!({
+                    "apiVersion": api_version,
+                    "kind": "AdmissionReview",
+                    "response": {
+                        "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                        "allowed": true,
+                        "warnings": ["checked"],
+                    },
+                })
+            );
+        }
+
+        let err = serde_json::from_value::<VersionedAdmissionReview>(review("admission.k8s.io/v2")).unwrap_err();
+        assert!(err.to_string().starts_with("unsupported AdmissionReview apiVersion \"admission.k8s.io/v2\""));
+    }
+}
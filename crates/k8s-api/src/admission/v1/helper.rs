@@ -0,0 +1,147 @@
+//! Helpers for writing admission webhooks
+//!
+//! Decoding the objects of a request into API types and building the
+//! response, including the base64-encoded JSON Patch of mutating webhooks.
+
+use base64::Engine;
+use k8s_apimachinery::apis::meta::v1::Status;
+use k8s_apimachinery::util::jsonpatch::{self, Patch};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::*;
+
+impl AdmissionRequest {
+    /// Decodes the object of the request, e.g. into a `Pod` for requests
+    /// with kind `v1/Pod`. Returns `None` if the request carries no object,
+    /// as for DELETE.
+    pub fn decode_object<T: DeserializeOwned>(&self) -> Result<Option<T>, serde_json::Error> {
+        self.object.clone().map(serde_json::from_value).transpose()
+    }
+
+    /// Decodes the existing object of UPDATE and DELETE requests.
+    pub fn decode_old_object<T: DeserializeOwned>(&self) -> Result<Option<T>, serde_json::Error> {
+        self.old_object.clone().map(serde_json::from_value).transpose()
+    }
+}
+
+impl AdmissionResponse {
+    /// Returns a response admitting the request. The `uid` is filled in
+    /// when the response is returned in an `AdmissionReview`.
+    pub fn allow() -> Self {
+        Self {
+            allowed: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns a response rejecting the request with `status`.
+    pub fn deny(status: Status) -> Self {
+        Self {
+            allowed: false,
+            status: Some(status),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the JSON Patch that mutates the object. An empty patch leaves
+    /// the object unchanged and is omitted.
+    pub fn with_patch(mut self, patch: &Patch) -> Self {
+        if patch.is_empty() {
+            self.patch = None;
+            self.patch_type = None;
+            return self;
+        }
+        let json = serde_json::to_vec(patch).expect("JSON patch serialization");
+        self.patch = Some(base64::engine::general_purpose::STANDARD.encode(json));
+        self.patch_type = Some(PATCH_TYPE_JSON_PATCH.to_string());
+        self
+    }
+
+    /// Sets the patch that turns `original` into `mutated`.
+    pub fn with_mutation<T: Serialize>(self, original: &T, mutated: &T) -> Result<Self, serde_json::Error> {
+        let patch = jsonpatch::diff(&serde_json::to_value(original)?, &serde_json::to_value(mutated)?);
+        Ok(self.with_patch(&patch))
+    }
+
+    pub fn with_warnings<I, S>(mut self, warnings: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.warnings.extend(warnings.into_iter().map(Into::into));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    struct Pod {
+        metadata: ObjectMeta,
+    }
+
+    #[test]
+    fn test_decode_object() {
+        let request = AdmissionRequest {
+            kind: GroupVersionKind::new("", "v1", "Pod"),
+            object: Some(json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": {"name": "web", "labels": {"app": "web"}},
+            })),
+            ..Default::default()
+        };
+        let pod: Pod = request.decode_object().unwrap().unwrap();
+        assert_eq!(pod.metadata.name, "web");
+        assert!(request.decode_old_object::<Pod>().unwrap().is_none());
+
+        let invalid = AdmissionRequest {
+            object: Some(json!({"metadata": "web"})),
+            ..Default::default()
+        };
+        assert!(invalid.decode_object::<Pod>().is_err());
+    }
+
+    #[test]
+    fn test_response() {
+        let request = AdmissionRequest {
+            object: Some(json!({"apiVersion": "v1", "kind": "Pod", "metadata": {"name": "web"}})),
+            ..Default::default()
+        };
+        let original: Pod = request.decode_object().unwrap().unwrap();
+        let mut mutated = original.clone();
+        mutated.metadata.labels.insert("injected".to_string(), "true".to_string());
+
+        let response = AdmissionResponse::allow()
+            .with_mutation(&original, &mutated)
+            .unwrap()
+            .with_warnings(["label added"]);
+        assert!(response.allowed);
+        assert_eq!(response.patch_type.as_deref(), Some(PATCH_TYPE_JSON_PATCH));
+        let patch = base64::engine::general_purpose::STANDARD
+            .decode(response.patch.unwrap())
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&patch).unwrap(),
+            json!([{"op": "add", "path": "/metadata/labels", "value": {"injected": "true"}}])
+        );
+        assert_eq!(response.warnings, ["label added"]);
+
+        let unchanged = AdmissionResponse::allow().with_mutation(&original, &original).unwrap();
+        assert!(unchanged.patch.is_none());
+
+        let denied = AdmissionResponse::deny(Status {
+            message: "no".to_string(),
+            code: 403,
+            ..Default::default()
+        });
+        assert!(!denied.allowed);
+        assert_eq!(denied.status.unwrap().code, 403);
+    }
+}
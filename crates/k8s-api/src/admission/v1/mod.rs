@@ -2,6 +2,7 @@
 
 mod types;
 mod internal_conversion;
+mod helper;
 
 pub use types::*;
 
@@ -37,6 +37,11 @@ pub struct AdmissionReview {
     pub response: Option<AdmissionResponse>,
 }
 
+impl AdmissionReview {
+    pub const KIND: &'static str = "AdmissionReview";
+    pub const API_VERSION: &'static str = "admission.k8s.io/v1";
+}
+
 /// AdmissionRequest describes the admission.Attributes for the admission request.
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
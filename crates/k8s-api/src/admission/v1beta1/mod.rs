@@ -2,6 +2,7 @@
 
 mod types;
 mod internal_conversion;
+mod v1_conversion;
 
 pub use types::*;
 
@@ -0,0 +1,100 @@
+//! Conversion between admission v1beta1 and v1
+//!
+//! The two versions have the same fields; the API server sends a webhook
+//! whichever version it lists first in `admissionReviewVersions`.
+
+use super::*;
+use k8s_apimachinery::apis::meta::v1::TypeMeta;
+use crate::admission::v1;
+
+impl From<AdmissionRequest> for v1::AdmissionRequest {
+    fn from(request: AdmissionRequest) -> Self {
+        Self {
+            uid: request.uid,
+            kind: request.kind,
+            resource: request.resource,
+            sub_resource: request.sub_resource,
+            request_kind: request.request_kind,
+            request_resource: request.request_resource,
+            request_sub_resource: request.request_sub_resource,
+            name: request.name,
+            namespace: request.namespace,
+            operation: request.operation,
+            user_info: request.user_info,
+            object: request.object,
+            old_object: request.old_object,
+            dry_run: request.dry_run,
+            options: request.options,
+        }
+    }
+}
+
+impl From<v1::AdmissionRequest> for AdmissionRequest {
+    fn from(request: v1::AdmissionRequest) -> Self {
+        Self {
+            uid: request.uid,
+            kind: request.kind,
+            resource: request.resource,
+            sub_resource: request.sub_resource,
+            request_kind: request.request_kind,
+            request_resource: request.request_resource,
+            request_sub_resource: request.request_sub_resource,
+            name: request.name,
+            namespace: request.namespace,
+            operation: request.operation,
+            user_info: request.user_info,
+            object: request.object,
+            old_object: request.old_object,
+            dry_run: request.dry_run,
+            options: request.options,
+        }
+    }
+}
+
+impl From<AdmissionResponse> for v1::AdmissionResponse {
+    fn from(response: AdmissionResponse) -> Self {
+        Self {
+            uid: response.uid,
+            allowed: response.allowed,
+            status: response.status,
+            patch: response.patch,
+            patch_type: response.patch_type,
+            audit_annotations: response.audit_annotations,
+            warnings: response.warnings,
+        }
+    }
+}
+
+impl From<v1::AdmissionResponse> for AdmissionResponse {
+    fn from(response: v1::AdmissionResponse) -> Self {
+        Self {
+            uid: response.uid,
+            allowed: response.allowed,
+            status: response.status,
+            patch: response.patch,
+            patch_type: response.patch_type,
+            audit_annotations: response.audit_annotations,
+            warnings: response.warnings,
+        }
+    }
+}
+
+impl From<AdmissionReview> for v1::AdmissionReview {
+    fn from(review: AdmissionReview) -> Self {
+        Self {
+            type_meta: TypeMeta::new(v1::AdmissionReview::API_VERSION, v1::AdmissionReview::KIND),
+            request: review.request.map(Into::into),
+            response: review.response.map(Into::into),
+        }
+    }
+}
+
+impl From<v1::AdmissionReview> for AdmissionReview {
+    fn from(review: v1::AdmissionReview) -> Self {
+        Self {
+            type_meta: TypeMeta::new(Self::API_VERSION, Self::KIND),
+            request: review.request.map(Into::into),
+            response: review.response.map(Into::into),
+        }
+    }
+}
@@ -0,0 +1,448 @@
+//! Deployment rollouts
+//!
+//! The revisions of a deployment's ReplicaSets, which of them runs its
+//! current pod template, how far a rolling update may go above or below
+//! the desired replicas, and the progress `kubectl rollout status` and
+//! `kubectl rollout history` report.
+
+use std::collections::BTreeMap;
+
+use k8s_api_core::IntOrString;
+use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+
+use crate::core::v1::PodTemplateSpec;
+
+use super::*;
+
+/// The annotation holding the revision of a deployment and its
+/// ReplicaSets.
+pub const REVISION_ANNOTATION: &str = "deployment.kubernetes.io/revision";
+/// The annotation recording why a revision was made.
+pub const CHANGE_CAUSE_ANNOTATION: &str = "kubernetes.io/change-cause";
+/// The reason of the `Progressing` condition of a deployment that exceeded
+/// its progress deadline.
+pub const TIMED_OUT_REASON: &str = "ProgressDeadlineExceeded";
+
+const DEFAULT_MAX_SURGE_AND_UNAVAILABLE: &str = "25%";
+
+/// Returns the revision in the annotations of a deployment or ReplicaSet,
+/// zero if it has none.
+pub fn revision(metadata: &ObjectMeta) -> Result<i64, String> {
+    match metadata.annotations.get(REVISION_ANNOTATION) {
+        Some(revision) => revision
+            .parse()
+            .map_err(|_| format!("strconv.ParseInt: parsing {:?}: invalid syntax", revision)),
+        None => Ok(0),
+    }
+}
+
+/// Returns the pods a rolling update may create above and take down below
+/// `desired` replicas. Percentages of surge round up and of unavailability
+/// down; as the rollout could not progress if both came to zero, one pod may
+/// then be unavailable.
+pub fn resolve_fenceposts(
+    max_surge: Option<&IntOrString>,
+    max_unavailable: Option<&IntOrString>,
+    desired: i32,
+) -> Result<(i32, i32), String> {
+    let zero = IntOrString::Int(0);
+    let surge = max_surge.unwrap_or(&zero).get_scaled_value_from_int_or_percent(desired, true)?;
+    let mut unavailable = max_unavailable.unwrap_or(&zero).get_scaled_value_from_int_or_percent(desired, false)?;
+    if surge == 0 && unavailable == 0 {
+        unavailable = 1;
+    }
+    Ok((surge, unavailable))
+}
+
+/// Returns whether two pod templates are equal but for the
+/// `pod-template-hash` label the deployment controller adds.
+pub fn equal_ignore_hash(a: &PodTemplateSpec, b: &PodTemplateSpec) -> bool {
+    let mut a = a.clone();
+    let mut b = b.clone();
+    a.metadata.labels.remove(DEFAULT_DEPLOYMENT_UNIQUE_LABEL_KEY);
+    b.metadata.labels.remove(DEFAULT_DEPLOYMENT_UNIQUE_LABEL_KEY);
+    a == b
+}
+
+/// The progress of a rollout, as `kubectl rollout status` reports it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RolloutStatus {
+    pub message: String,
+    /// Whether the rollout finished.
+    pub done: bool,
+}
+
+/// A revision of a deployment in its rollout history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RolloutRevision {
+    pub revision: i64,
+    pub change_cause: Option<String>,
+    pub template: PodTemplateSpec,
+}
+
+impl Deployment {
+    pub fn revision(&self) -> Result<i64, String> {
+        revision(&self.metadata)
+    }
+
+    pub fn get_condition(&self, condition_type: &str) -> Option<&DeploymentCondition> {
+        self.status
+            .as_ref()?
+            .conditions
+            .iter()
+            .find(|condition| condition.condition_type == condition_type)
+    }
+
+    fn replicas(&self) -> i32 {
+        self.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1)
+    }
+
+    /// Returns whether the deployment replaces its pods with a rolling
+    /// update, the default strategy.
+    pub fn is_rolling_update(&self) -> bool {
+        let strategy = self.spec.as_ref().and_then(|spec| spec.strategy.as_ref());
+        strategy.is_none_or(|strategy| {
+            strategy.strategy_type.is_empty() || strategy.strategy_type == DEPLOYMENT_STRATEGY_ROLLING_UPDATE
+        })
+    }
+
+    /// Returns `maxSurge` and `maxUnavailable` resolved against the desired
+    /// replicas, with unset values taking their default of 25%.
+    fn fenceposts(&self) -> (i32, i32) {
+        let rolling_update = self
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.strategy.as_ref())
+            .and_then(|strategy| strategy.rolling_update.as_ref());
+        let default = IntOrString::from(DEFAULT_MAX_SURGE_AND_UNAVAILABLE);
+        let max_surge = rolling_update.and_then(|rolling_update| rolling_update.max_surge.as_ref());
+        let max_unavailable = rolling_update.and_then(|rolling_update| rolling_update.max_unavailable.as_ref());
+        // Validation rejects values that do not resolve.
+        resolve_fenceposts(
+            Some(max_surge.unwrap_or(&default)),
+            Some(max_unavailable.unwrap_or(&default)),
+            self.replicas(),
+        )
+        .unwrap_or_default()
+    }
+
+    /// Returns the pods a rolling update may create above the desired
+    /// replicas, zero for other strategies.
+    pub fn max_surge(&self) -> i32 {
+        if !self.is_rolling_update() {
+            return 0;
+        }
+        self.fenceposts().0
+    }
+
+    /// Returns the pods a rolling update may take down below the desired
+    /// replicas, at most all of them and zero for other strategies.
+    pub fn max_unavailable(&self) -> i32 {
+        let replicas = self.replicas();
+        if !self.is_rolling_update() || replicas == 0 {
+            return 0;
+        }
+        self.fenceposts().1.min(replicas)
+    }
+
+    /// Returns the ReplicaSet of the deployment running its pod template,
+    /// the oldest if several do, among the ReplicaSets it owns.
+    pub fn find_new_replica_set<'a>(&self, replica_sets: &'a [ReplicaSet]) -> Option<&'a ReplicaSet> {
+        let template = &self.spec.as_ref()?.template;
+        let mut replica_sets: Vec<&ReplicaSet> = replica_sets.iter().collect();
+        replica_sets.sort_by(|a, b| {
+            (a.metadata.creation_timestamp.0, &a.metadata.name).cmp(&(b.metadata.creation_timestamp.0, &b.metadata.name))
+        });
+        replica_sets.into_iter().find(|replica_set| {
+            replica_set
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.template.as_ref())
+                .is_some_and(|rs_template| equal_ignore_hash(rs_template, template))
+        })
+    }
+
+    /// Returns the old ReplicaSets of the deployment among the ReplicaSets
+    /// it owns: those with replicas, and all of them.
+    pub fn find_old_replica_sets<'a>(&self, replica_sets: &'a [ReplicaSet]) -> (Vec<&'a ReplicaSet>, Vec<&'a ReplicaSet>) {
+        let new_replica_set = self.find_new_replica_set(replica_sets);
+        let all: Vec<&ReplicaSet> = replica_sets
+            .iter()
+            .filter(|replica_set| new_replica_set.is_none_or(|new| new.metadata.uid != replica_set.metadata.uid))
+            .collect();
+        let required = all
+            .iter()
+            .copied()
+            .filter(|replica_set| replica_set.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1) != 0)
+            .collect();
+        (required, all)
+    }
+
+    /// Returns the progress of the rollout of the deployment, failing if
+    /// it exceeded its progress deadline or, for a `revision` above zero,
+    /// if the deployment is at another revision.
+    pub fn rollout_status(&self, revision: i64) -> Result<RolloutStatus, String> {
+        let name = &self.metadata.name;
+        if revision > 0 {
+            let deployment_revision = self
+                .revision()
+                .map_err(|err| format!("cannot get the revision of deployment {:?}: {}", name, err))?;
+            if revision != deployment_revision {
+                return Err(format!(
+                    "desired revision ({}) is different from the running revision ({})",
+                    revision, deployment_revision
+                ));
+            }
+        }
+        let status = self.status.clone().unwrap_or_default();
+        let waiting = |message: String| {
+            Ok(RolloutStatus {
+                message: format!("Waiting for deployment {:?} rollout to finish: {}", name, message),
+                done: false,
+            })
+        };
+        if self.metadata.generation > status.observed_generation.unwrap_or_default() {
+            return Ok(RolloutStatus {
+                message: "Waiting for deployment spec update to be observed...".to_string(),
+                done: false,
+            });
+        }
+        if self
+            .get_condition(DEPLOYMENT_CONDITION_PROGRESSING)
+            .is_some_and(|condition| condition.reason == TIMED_OUT_REASON)
+        {
+            return Err(format!("deployment {:?} exceeded its progress deadline", name));
+        }
+        let replicas = status.replicas.unwrap_or_default();
+        let updated = status.updated_replicas.unwrap_or_default();
+        let available = status.available_replicas.unwrap_or_default();
+        if let Some(desired) = self.spec.as_ref().and_then(|spec| spec.replicas) {
+            if updated < desired {
+                return waiting(format!("{} out of {} new replicas have been updated...", updated, desired));
+            }
+        }
+        if replicas > updated {
+            return waiting(format!("{} old replicas are pending termination...", replicas - updated));
+        }
+        if available < updated {
+            return waiting(format!("{} of {} updated replicas are available...", available, updated));
+        }
+        Ok(RolloutStatus {
+            message: format!("deployment {:?} successfully rolled out", name),
+            done: true,
+        })
+    }
+}
+
+/// Returns the rollout history of a deployment from the ReplicaSets it
+/// owns, ordered by revision. ReplicaSets with an invalid revision are left
+/// out, and the latest ReplicaSet of a revision is kept. Like kubectl, the
+/// change cause is read from the ReplicaSet's own annotations.
+pub fn rollout_history(replica_sets: &[ReplicaSet]) -> Vec<RolloutRevision> {
+    let mut history: BTreeMap<i64, RolloutRevision> = BTreeMap::new();
+    for replica_set in replica_sets {
+        let Ok(revision) = revision(&replica_set.metadata) else {
+            continue;
+        };
+        let template = replica_set
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.template.clone())
+            .unwrap_or_default();
+        let change_cause = replica_set
+            .metadata
+            .annotations
+            .get(CHANGE_CAUSE_ANNOTATION)
+            .filter(|cause| !cause.is_empty())
+            .cloned();
+        history.insert(
+            revision,
+            RolloutRevision {
+                revision,
+                change_cause,
+                template,
+            },
+        );
+    }
+    history.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::{Container, PodSpec};
+    use k8s_apimachinery::apis::meta::v1::Time;
+
+    fn template(image: &str) -> PodTemplateSpec {
+        PodTemplateSpec {
+            metadata: ObjectMeta {
+                labels: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                ..Default::default()
+            },
+            spec: Some(PodSpec {
+                containers: vec![Container {
+                    name: "web".to_string(),
+                    image: image.to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn deployment(replicas: i32, max_surge: Option<IntOrString>, max_unavailable: Option<IntOrString>) -> Deployment {
+        Deployment {
+            metadata: ObjectMeta {
+                name: "web".to_string(),
+                generation: 2,
+                ..Default::default()
+            },
+            spec: Some(DeploymentSpec {
+                replicas: Some(replicas),
+                template: template("web:2"),
+                strategy: Some(DeploymentStrategy {
+                    strategy_type: DEPLOYMENT_STRATEGY_ROLLING_UPDATE.to_string(),
+                    rolling_update: Some(RollingUpdateDeployment {
+                        max_surge,
+                        max_unavailable,
+                    }),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn replica_set(name: &str, revision: &str, image: &str, replicas: i32, created: i64) -> ReplicaSet {
+        let mut template = template(image);
+        template
+            .metadata
+            .labels
+            .insert(DEFAULT_DEPLOYMENT_UNIQUE_LABEL_KEY.to_string(), name.to_string());
+        ReplicaSet {
+            metadata: ObjectMeta {
+                name: name.to_string(),
+                uid: name.to_string(),
+                annotations: BTreeMap::from([(REVISION_ANNOTATION.to_string(), revision.to_string())]),
+                creation_timestamp: Time(chrono::DateTime::from_timestamp(created, 0)),
+                ..Default::default()
+            },
+            spec: Some(ReplicaSetSpec {
+                replicas: Some(replicas),
+                template: Some(template),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fenceposts() {
+        assert_eq!(deployment(10, None, None).max_surge(), 3);
+        assert_eq!(deployment(10, None, None).max_unavailable(), 2);
+        assert_eq!(deployment(3, Some("50%".into()), Some("20%".into())).max_surge(), 2);
+        assert_eq!(deployment(3, Some(0.into()), Some("20%".into())).max_unavailable(), 1);
+        assert_eq!(deployment(2, Some(0.into()), Some(5.into())).max_unavailable(), 2);
+        assert_eq!(deployment(0, None, None).max_unavailable(), 0);
+        let mut recreate = deployment(10, None, None);
+        recreate.spec.as_mut().unwrap().strategy.as_mut().unwrap().strategy_type = DEPLOYMENT_STRATEGY_RECREATE.to_string();
+        assert_eq!((recreate.max_surge(), recreate.max_unavailable()), (0, 0));
+        assert!(resolve_fenceposts(Some(&"x".into()), None, 1).is_err());
+    }
+
+    #[test]
+    fn test_replica_sets() {
+        let deployment = deployment(3, None, None);
+        let replica_sets = [
+            replica_set("web-3", "3", "web:2", 3, 300),
+            replica_set("web-1", "1", "web:1", 0, 100),
+            replica_set("web-2", "2", "web:2", 1, 200),
+            replica_set("web-0", "x", "web:0", 1, 50),
+        ];
+        let new = deployment.find_new_replica_set(&replica_sets).unwrap();
+        assert_eq!(new.metadata.name, "web-2");
+        let (required, all) = deployment.find_old_replica_sets(&replica_sets);
+        let names = |replica_sets: Vec<&ReplicaSet>| -> Vec<String> {
+            replica_sets.iter().map(|replica_set| replica_set.metadata.name.clone()).collect()
+        };
+        assert_eq!(names(required), ["web-3", "web-0"]);
+        assert_eq!(names(all), ["web-3", "web-1", "web-0"]);
+
+        let mut replica_sets = replica_sets.to_vec();
+        replica_sets[0]
+            .metadata
+            .annotations
+            .insert(CHANGE_CAUSE_ANNOTATION.to_string(), "kubectl set image".to_string());
+        let template = replica_sets[1].spec.as_mut().unwrap().template.get_or_insert_with(Default::default);
+        template
+            .metadata
+            .annotations
+            .insert(CHANGE_CAUSE_ANNOTATION.to_string(), "from the template".to_string());
+        let history = rollout_history(&replica_sets);
+        let revisions: Vec<(i64, Option<&str>)> = history
+            .iter()
+            .map(|revision| (revision.revision, revision.change_cause.as_deref()))
+            .collect();
+        assert_eq!(revisions, [(1, None), (2, None), (3, Some("kubectl set image"))]);
+    }
+
+    #[test]
+    fn test_rollout_status() {
+        let mut deployment = deployment(3, None, None);
+        deployment
+            .metadata
+            .annotations
+            .insert(REVISION_ANNOTATION.to_string(), "2".to_string());
+        let status = |deployment: &Deployment| deployment.rollout_status(0).map(|status| status.message);
+        let mut set_status = |observed: i64, replicas: i32, updated: i32, available: i32| {
+            deployment.status = Some(DeploymentStatus {
+                observed_generation: Some(observed),
+                replicas: Some(replicas),
+                updated_replicas: Some(updated),
+                available_replicas: Some(available),
+                ..Default::default()
+            });
+            deployment.clone()
+        };
+        assert_eq!(
+            status(&set_status(1, 3, 3, 3)).unwrap(),
+            "Waiting for deployment spec update to be observed..."
+        );
+        assert_eq!(
+            status(&set_status(2, 4, 1, 3)).unwrap(),
+            "Waiting for deployment \"web\" rollout to finish: 1 out of 3 new replicas have been updated..."
+        );
+        assert_eq!(
+            status(&set_status(2, 4, 3, 3)).unwrap(),
+            "Waiting for deployment \"web\" rollout to finish: 1 old replicas are pending termination..."
+        );
+        assert_eq!(
+            status(&set_status(2, 3, 3, 2)).unwrap(),
+            "Waiting for deployment \"web\" rollout to finish: 2 of 3 updated replicas are available..."
+        );
+        let done = set_status(2, 3, 3, 3);
+        assert_eq!(
+            done.rollout_status(2),
+            Ok(RolloutStatus {
+                message: "deployment \"web\" successfully rolled out".to_string(),
+                done: true,
+            })
+        );
+        assert_eq!(
+            done.rollout_status(1),
+            Err("desired revision (1) is different from the running revision (2)".to_string())
+        );
+
+        let mut timed_out = set_status(2, 4, 1, 3);
+        timed_out.status.as_mut().unwrap().conditions.push(DeploymentCondition {
+            condition_type: DEPLOYMENT_CONDITION_PROGRESSING.to_string(),
+            status: "False".to_string(),
+            reason: TIMED_OUT_REASON.to_string(),
+            ..Default::default()
+        });
+        assert_eq!(
+            status(&timed_out),
+            Err("deployment \"web\" exceeded its progress deadline".to_string())
+        );
+    }
+}
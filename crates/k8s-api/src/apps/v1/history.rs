@@ -0,0 +1,465 @@
+//! Pod template hashes and controller history
+//!
+//! The `pod-template-hash` of ReplicaSets and the ControllerRevisions of
+//! DaemonSets and StatefulSets, named and labeled as kube-controller-manager
+//! does so that Rust controllers agree with it on which revision is which.
+//! Templates must be hashed as the API server returns them, defaults
+//! applied; see `DeepHash` for what the encoding covers.
+
+use std::collections::BTreeMap;
+
+use k8s_apimachinery::apis::meta::v1::{ObjectMeta, OwnerReference, TypeMeta};
+use serde_json::Value;
+
+use crate::core::v1::{DeepHash, PodTemplateSpec};
+
+use super::*;
+
+/// Revision names are truncated so that the hash and its dash fit in 253
+/// characters.
+const MAX_REVISION_PREFIX_LEN: usize = 223;
+
+/// Consonants and digits that cannot spell words, nor look alike.
+const SAFE_ALPHABET: &[u8] = b"bcdfghjklmnpqrstvwxz2456789";
+
+const FNV32_OFFSET_BASIS: u32 = 2_166_136_261;
+const FNV32_PRIME: u32 = 16_777_619;
+
+fn fnv32(data: &[u8], mut hash: u32) -> u32 {
+    for byte in data {
+        hash = hash.wrapping_mul(FNV32_PRIME);
+        hash ^= *byte as u32;
+    }
+    hash
+}
+
+fn fnv32a(data: &[u8], mut hash: u32) -> u32 {
+    for byte in data {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(FNV32_PRIME);
+    }
+    hash
+}
+
+/// Maps each byte of `s` into an alphabet without vowels or look-alike
+/// characters, as `rand.SafeEncodeString` does to keep hashes out of
+/// offensive words.
+pub fn safe_encode_string(s: &str) -> String {
+    s.bytes()
+        .map(|byte| SAFE_ALPHABET[byte as usize % SAFE_ALPHABET.len()] as char)
+        .collect()
+}
+
+/// Returns the hash of a pod template the deployment controller puts in the
+/// `pod-template-hash` label, salted with the collision count of the
+/// deployment when it has one.
+pub fn compute_hash(template: &PodTemplateSpec, collision_count: Option<i32>) -> String {
+    let mut hash = fnv32a(template.deep_hash_string().as_bytes(), FNV32_OFFSET_BASIS);
+    if let Some(collision_count) = collision_count {
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&(collision_count as u32).to_le_bytes());
+        hash = fnv32a(&bytes, hash);
+    }
+    safe_encode_string(&hash.to_string())
+}
+
+/// Returns the name of a revision of `prefix`, its owner's name.
+pub fn controller_revision_name(prefix: &str, hash: &str) -> String {
+    let prefix = match prefix.char_indices().nth(MAX_REVISION_PREFIX_LEN) {
+        Some((end, _)) => &prefix[..end],
+        None => prefix,
+    };
+    format!("{}-{}", prefix, hash)
+}
+
+/// Returns the hash of the data of a revision, salted with `probe` when a
+/// previous name collided.
+pub fn hash_controller_revision(revision: &ControllerRevision, probe: Option<i32>) -> String {
+    let mut hash = FNV32_OFFSET_BASIS;
+    if let Some(data) = revision.data.as_ref().filter(|data| !data.is_null()) {
+        hash = fnv32(&go_json(data), hash);
+    }
+    if let Some(probe) = probe {
+        hash = fnv32(probe.to_string().as_bytes(), hash);
+    }
+    safe_encode_string(&hash.to_string())
+}
+
+/// Returns a revision of the object `parent` of type `parent_type` holding
+/// `data`, labeled with the pod template labels of the object and the hash
+/// of the revision, which also names it.
+pub fn new_controller_revision(
+    parent: &ObjectMeta,
+    parent_type: &TypeMeta,
+    template_labels: &BTreeMap<String, String>,
+    data: Value,
+    revision: i64,
+    collision_count: Option<i32>,
+) -> ControllerRevision {
+    let mut controller_revision = ControllerRevision {
+        type_meta: TypeMeta::new(ControllerRevision::API_VERSION, ControllerRevision::KIND),
+        metadata: ObjectMeta {
+            namespace: parent.namespace.clone(),
+            labels: template_labels.clone(),
+            owner_references: vec![controller_ref(parent, parent_type)],
+            ..Default::default()
+        },
+        data: Some(data),
+        revision,
+    };
+    let hash = hash_controller_revision(&controller_revision, collision_count);
+    controller_revision.metadata.name = controller_revision_name(&parent.name, &hash);
+    controller_revision
+        .metadata
+        .labels
+        .insert(CONTROLLER_REVISION_HASH_LABEL_KEY.to_string(), hash);
+    controller_revision
+}
+
+fn controller_ref(owner: &ObjectMeta, owner_type: &TypeMeta) -> OwnerReference {
+    OwnerReference {
+        api_version: owner_type.api_version.clone(),
+        kind: owner_type.kind.clone(),
+        name: owner.name.clone(),
+        uid: owner.uid.clone(),
+        controller: Some(true),
+        block_owner_deletion: Some(true),
+    }
+}
+
+/// Returns whether two revisions hold the same data. Revisions whose hash
+/// labels are both numbers differ if the numbers do.
+pub fn equal_revision(a: &ControllerRevision, b: &ControllerRevision) -> bool {
+    let hash = |revision: &ControllerRevision| {
+        revision
+            .metadata
+            .labels
+            .get(CONTROLLER_REVISION_HASH_LABEL_KEY)
+            .and_then(|hash| hash.parse::<i32>().ok())
+    };
+    if let (Some(a), Some(b)) = (hash(a), hash(b)) {
+        if a != b {
+            return false;
+        }
+    }
+    a.data == b.data
+}
+
+/// Returns the revisions holding the same data as `needle`.
+pub fn find_equal_revisions<'a>(
+    revisions: &'a [ControllerRevision],
+    needle: &ControllerRevision,
+) -> Vec<&'a ControllerRevision> {
+    revisions
+        .iter()
+        .filter(|revision| equal_revision(revision, needle))
+        .collect()
+}
+
+/// Returns the number of the revision to create after `revisions`.
+pub fn next_revision(revisions: &[ControllerRevision]) -> i64 {
+    revisions.iter().map(|revision| revision.revision).max().unwrap_or(0) + 1
+}
+
+/// Returns the patch a DaemonSet or StatefulSet revision records: the pod
+/// template, replacing the current one when applied.
+pub fn template_patch(template: &PodTemplateSpec) -> Value {
+    let mut template = serde_json::to_value(template).unwrap_or_default();
+    normalize_template(&mut template);
+    template["$patch"] = Value::from("replace");
+    serde_json::json!({ "spec": { "template": template } })
+}
+
+impl StatefulSet {
+    /// Returns revision `revision` of the StatefulSet, with its
+    /// annotations.
+    pub fn new_controller_revision(&self, revision: i64, collision_count: Option<i32>) -> ControllerRevision {
+        let template = self.spec.as_ref().map(|spec| &spec.template).cloned().unwrap_or_default();
+        let mut controller_revision = new_controller_revision(
+            &self.metadata,
+            &TypeMeta::new(Self::API_VERSION, Self::KIND),
+            &template.metadata.labels,
+            template_patch(&template),
+            revision,
+            collision_count,
+        );
+        controller_revision.metadata.annotations.extend(self.metadata.annotations.clone());
+        controller_revision
+    }
+}
+
+impl DaemonSet {
+    /// Returns revision `revision` of the DaemonSet, with its annotations.
+    /// Unlike StatefulSet revisions, these are named after the pod template
+    /// hash.
+    pub fn new_controller_revision(&self, revision: i64) -> ControllerRevision {
+        let template = self.spec.as_ref().map(|spec| &spec.template).cloned().unwrap_or_default();
+        let collision_count = self.status.as_ref().and_then(|status| status.collision_count);
+        let hash = compute_hash(&template, collision_count);
+        let mut labels = template.metadata.labels.clone();
+        labels.insert(CONTROLLER_REVISION_HASH_LABEL_KEY.to_string(), hash.clone());
+        ControllerRevision {
+            type_meta: TypeMeta::new(ControllerRevision::API_VERSION, ControllerRevision::KIND),
+            metadata: ObjectMeta {
+                name: format!("{}-{}", self.metadata.name, hash),
+                namespace: self.metadata.namespace.clone(),
+                labels,
+                annotations: self.metadata.annotations.clone(),
+                owner_references: vec![controller_ref(&self.metadata, &TypeMeta::new(Self::API_VERSION, Self::KIND))],
+                ..Default::default()
+            },
+            data: Some(template_patch(&template)),
+            revision,
+        }
+    }
+}
+
+// =============================================================================
+// Go JSON
+// =============================================================================
+
+/// Makes the JSON of a template what Go writes for it: Go always writes the
+/// creation timestamp, container resources and resource field divisors,
+/// and quantities in canonical form.
+fn normalize_template(template: &mut Value) {
+    normalize_metadata(&mut template["metadata"]);
+    let spec = &mut template["spec"];
+    if !spec.is_object() {
+        *spec = Value::Object(Default::default());
+    }
+    if spec.get("containers").is_none() {
+        spec["containers"] = Value::Null;
+    }
+    for field in ["initContainers", "containers", "ephemeralContainers"] {
+        for container in items_mut(spec, field) {
+            if container.get("resources").is_none() {
+                container["resources"] = Value::Object(Default::default());
+            }
+            normalize_requirements(&mut container["resources"]);
+            for env in items_mut(container, "env") {
+                if let Some(selector) = env.pointer_mut("/valueFrom/resourceFieldRef") {
+                    normalize_divisor(selector);
+                }
+            }
+        }
+    }
+    if let Some(overhead) = spec.get_mut("overhead") {
+        canonicalize_quantities(overhead);
+    }
+    if let Some(resources) = spec.get_mut("resources") {
+        normalize_requirements(resources);
+    }
+    for volume in items_mut(spec, "volumes") {
+        if let Some(size_limit) = volume.pointer_mut("/emptyDir/sizeLimit") {
+            canonicalize_quantity(size_limit);
+        }
+        if let Some(downward_api) = volume.get_mut("downwardAPI") {
+            normalize_downward_api(downward_api);
+        }
+        if let Some(projected) = volume.get_mut("projected") {
+            for source in items_mut(projected, "sources") {
+                if let Some(downward_api) = source.get_mut("downwardAPI") {
+                    normalize_downward_api(downward_api);
+                }
+            }
+        }
+        if let Some(claim_template) = volume.pointer_mut("/ephemeral/volumeClaimTemplate") {
+            normalize_metadata(&mut claim_template["metadata"]);
+            let claim_spec = &mut claim_template["spec"];
+            if claim_spec.get("resources").is_none() {
+                claim_spec["resources"] = Value::Object(Default::default());
+            }
+            normalize_requirements(&mut claim_spec["resources"]);
+        }
+    }
+}
+
+fn items_mut<'a>(value: &'a mut Value, field: &str) -> impl Iterator<Item = &'a mut Value> {
+    value.get_mut(field).and_then(Value::as_array_mut).into_iter().flatten()
+}
+
+fn normalize_metadata(metadata: &mut Value) {
+    let timestamp = match metadata.get("creationTimestamp").and_then(Value::as_i64) {
+        Some(seconds) => chrono::DateTime::from_timestamp(seconds, 0)
+            .map(|time| Value::from(time.format("%Y-%m-%dT%H:%M:%SZ").to_string()))
+            .unwrap_or_default(),
+        None => Value::Null,
+    };
+    metadata["creationTimestamp"] = timestamp;
+}
+
+fn normalize_requirements(requirements: &mut Value) {
+    for field in ["limits", "requests"] {
+        if let Some(list) = requirements.get_mut(field) {
+            canonicalize_quantities(list);
+        }
+    }
+}
+
+fn normalize_downward_api(downward_api: &mut Value) {
+    for item in items_mut(downward_api, "items") {
+        if let Some(selector) = item.get_mut("resourceFieldRef") {
+            normalize_divisor(selector);
+        }
+    }
+}
+
+fn normalize_divisor(selector: &mut Value) {
+    if selector.get("divisor").is_none() {
+        selector["divisor"] = Value::from("0");
+    }
+    canonicalize_quantity(&mut selector["divisor"]);
+}
+
+fn canonicalize_quantities(list: &mut Value) {
+    if let Some(list) = list.as_object_mut() {
+        list.values_mut().for_each(canonicalize_quantity);
+    }
+}
+
+fn canonicalize_quantity(quantity: &mut Value) {
+    if let Some(canonical) = quantity
+        .as_str()
+        .and_then(|s| k8s_api_core::resource::Quantity::new(s).canonicalize().ok())
+    {
+        *quantity = Value::from(canonical.0);
+    }
+}
+
+/// Encodes a value as Go's `json.Marshal` does: keys sorted and HTML
+/// characters escaped.
+fn go_json(value: &Value) -> Vec<u8> {
+    let mut out = String::new();
+    write_go_json(value, &mut out);
+    out.into_bytes()
+}
+
+fn write_go_json(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(value) => out.push_str(&value.to_string()),
+        Value::Number(value) => out.push_str(&value.to_string()),
+        Value::String(value) => write_go_string(value, out),
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_go_json(value, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_go_string(key, out);
+                out.push(':');
+                write_go_json(value, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_go_string(s: &str, out: &mut String) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '<' | '>' | '&' | '\u{2028}' | '\u{2029}' => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::{Container, PodSpec};
+
+    fn template(image: &str) -> PodTemplateSpec {
+        PodTemplateSpec {
+            metadata: ObjectMeta {
+                labels: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                ..Default::default()
+            },
+            spec: Some(PodSpec {
+                containers: vec![Container {
+                    name: "web".to_string(),
+                    image: image.to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_compute_hash() {
+        assert_eq!(safe_encode_string("0123456789"), "456789bcdf");
+        assert_eq!(fnv32a(b"a", FNV32_OFFSET_BASIS), 0xe40c292c);
+        assert_eq!(fnv32(b"a", FNV32_OFFSET_BASIS), 0x050c5d7e);
+
+        let hash = compute_hash(&template("nginx:1.25"), None);
+        assert_eq!(hash, compute_hash(&template("nginx:1.25"), None));
+        assert_ne!(hash, compute_hash(&template("nginx:1.26"), None));
+        assert_ne!(hash, compute_hash(&template("nginx:1.25"), Some(1)));
+        assert!(hash.bytes().all(|byte| SAFE_ALPHABET.contains(&byte)));
+    }
+
+    #[test]
+    fn test_template_patch() {
+        let mut template = template("nginx:1.25");
+        template.metadata.annotations.insert("note".to_string(), "a<b".to_string());
+        assert_eq!(
+            String::from_utf8(go_json(&template_patch(&template))).unwrap(),
+            r#"{"spec":{"template":{"$patch":"replace","metadata":{"annotations":{"note":"a\u003cb"},"creationTimestamp":null,"labels":{"app":"web"}},"spec":{"containers":[{"image":"nginx:1.25","name":"web","resources":{}}]}}}}"#
+        );
+    }
+
+    #[test]
+    fn test_controller_revisions() {
+        let set = StatefulSet {
+            metadata: ObjectMeta {
+                name: "web".to_string(),
+                namespace: "default".to_string(),
+                uid: "uid-1".to_string(),
+                ..Default::default()
+            },
+            spec: Some(StatefulSetSpec {
+                template: template("nginx:1.25"),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let first = set.new_controller_revision(1, None);
+        let hash = &first.metadata.labels[CONTROLLER_REVISION_HASH_LABEL_KEY];
+        assert_eq!(first.metadata.name, format!("web-{}", hash));
+        assert_eq!(first.metadata.labels["app"], "web");
+        assert_eq!(first.metadata.owner_references[0].kind, "StatefulSet");
+        assert_ne!(set.new_controller_revision(1, Some(1)).metadata.name, first.metadata.name);
+
+        let mut updated = set.clone();
+        updated.spec.as_mut().unwrap().template = template("nginx:1.26");
+        let second = updated.new_controller_revision(2, None);
+        let revisions = vec![first.clone(), second.clone()];
+        assert_eq!(find_equal_revisions(&revisions, &set.new_controller_revision(3, None)), [&first]);
+        assert!(!equal_revision(&first, &second));
+        assert_eq!(next_revision(&revisions), 3);
+        assert_eq!(controller_revision_name(&"x".repeat(300), "abc").len(), 227);
+    }
+}
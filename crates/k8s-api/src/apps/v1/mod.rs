@@ -2,7 +2,13 @@
 
 mod types;
 mod internal_conversion;
+mod deployment;
+mod history;
+mod stateful_set;
 
+pub use deployment::*;
+pub use history::*;
+pub use stateful_set::*;
 pub use types::*;
 
 pub trait InternalConversion: Sized + serde::Serialize + serde::de::DeserializeOwned {
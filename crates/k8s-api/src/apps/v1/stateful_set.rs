@@ -0,0 +1,370 @@
+//! StatefulSet pod identity and ordering
+//!
+//! The stable identity a StatefulSet gives each of its pods (ordinal, name,
+//! hostname and claims) and the order in which the StatefulSet controller
+//! creates, updates and deletes them, following `stateful_set_utils.go` and
+//! `stateful_set_control.go`.
+
+use chrono::{DateTime, Utc};
+use k8s_api_core::IntOrString;
+
+use crate::core::v1::{PersistentVolumeClaim, Pod, POD_PHASE_RUNNING};
+
+use super::*;
+
+/// Returns the name of the StatefulSet a pod belongs to and its ordinal,
+/// parsed from a pod name ending in `-<ordinal>`.
+pub fn parent_name_and_ordinal(pod_name: &str) -> Option<(&str, i32)> {
+    let (parent, ordinal) = pod_name.rsplit_once('-')?;
+    if ordinal.is_empty() || !ordinal.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    Some((parent, ordinal.parse().ok()?))
+}
+
+/// Returns the ordinal of a StatefulSet pod, if its name has one.
+pub fn pod_ordinal(pod: &Pod) -> Option<i32> {
+    parent_name_and_ordinal(&pod.metadata.name).map(|(_, ordinal)| ordinal)
+}
+
+/// Returns the revision a StatefulSet pod runs.
+pub fn pod_revision(pod: &Pod) -> Option<&str> {
+    pod.metadata.labels.get(STATEFUL_SET_REVISION_LABEL).map(String::as_str)
+}
+
+fn is_terminating(pod: &Pod) -> bool {
+    pod.metadata.deletion_timestamp.is_some()
+}
+
+/// Whether the controller counts a pod as available: running, ready for
+/// `min_ready_seconds` and not terminating.
+fn is_healthy(pod: &Pod, min_ready_seconds: i32, now: DateTime<Utc>) -> bool {
+    let running = pod
+        .status
+        .as_ref()
+        .is_some_and(|status| status.phase == POD_PHASE_RUNNING);
+    running && pod.is_available(min_ready_seconds, now) && !is_terminating(pod)
+}
+
+impl StatefulSet {
+    fn replicas(&self) -> i32 {
+        self.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1)
+    }
+
+    fn min_ready_seconds(&self) -> i32 {
+        self.spec.as_ref().and_then(|spec| spec.min_ready_seconds).unwrap_or_default()
+    }
+
+    /// Returns the ordinal of the first replica, `spec.ordinals.start`.
+    pub fn start_ordinal(&self) -> i32 {
+        self.spec
+            .as_ref()
+            .and_then(|spec| spec.ordinals.as_ref())
+            .map(|ordinals| ordinals.start)
+            .unwrap_or_default()
+    }
+
+    /// Returns the ordinal of the last replica, below the start ordinal if
+    /// the set is scaled to zero.
+    pub fn end_ordinal(&self) -> i32 {
+        self.start_ordinal() + self.replicas() - 1
+    }
+
+    /// Returns whether an ordinal is one of the replicas of the set.
+    pub fn is_ordinal_in_range(&self, ordinal: i32) -> bool {
+        (self.start_ordinal()..=self.end_ordinal()).contains(&ordinal)
+    }
+
+    /// Returns the name of the pod with `ordinal`.
+    pub fn pod_name(&self, ordinal: i32) -> String {
+        format!("{}-{}", self.metadata.name, ordinal)
+    }
+
+    /// Returns the stable DNS name of the pod with `ordinal` under the
+    /// governing service of the set, `<pod>.<serviceName>.<namespace>.svc`.
+    pub fn pod_dns_name(&self, ordinal: i32) -> String {
+        let service_name = self.spec.as_ref().map(|spec| spec.service_name.as_str()).unwrap_or_default();
+        format!("{}.{}.{}.svc", self.pod_name(ordinal), service_name, self.metadata.namespace)
+    }
+
+    /// Returns whether a pod belongs to the set by its name.
+    pub fn is_member_of(&self, pod: &Pod) -> bool {
+        parent_name_and_ordinal(&pod.metadata.name).is_some_and(|(parent, _)| parent == self.metadata.name)
+    }
+
+    /// Returns whether a pod has the name, namespace and pod name label the
+    /// set gives the pod with its ordinal.
+    pub fn identity_matches(&self, pod: &Pod) -> bool {
+        let Some((parent, ordinal)) = parent_name_and_ordinal(&pod.metadata.name) else {
+            return false;
+        };
+        parent == self.metadata.name
+            && pod.metadata.name == self.pod_name(ordinal)
+            && pod.metadata.namespace == self.metadata.namespace
+            && pod.metadata.labels.get(STATEFUL_SET_POD_NAME_LABEL) == Some(&pod.metadata.name)
+    }
+
+    /// Gives a pod the identity of `ordinal`: its name, namespace, pod name
+    /// and index labels, and the hostname and subdomain resolving to
+    /// `pod_dns_name`.
+    pub fn init_identity(&self, pod: &mut Pod, ordinal: i32) {
+        pod.metadata.name = self.pod_name(ordinal);
+        pod.metadata.namespace = self.metadata.namespace.clone();
+        let labels = &mut pod.metadata.labels;
+        labels.insert(STATEFUL_SET_POD_NAME_LABEL.to_string(), pod.metadata.name.clone());
+        labels.insert(POD_INDEX_LABEL.to_string(), ordinal.to_string());
+        let spec = pod.spec.get_or_insert_with(Default::default);
+        spec.hostname = pod.metadata.name.clone();
+        spec.subdomain = self.spec.as_ref().map(|spec| spec.service_name.clone()).unwrap_or_default();
+    }
+
+    /// Returns the name of the claim from `claim_template` for the pod with
+    /// `ordinal`.
+    pub fn claim_name(&self, claim_template: &str, ordinal: i32) -> String {
+        format!("{}-{}-{}", claim_template, self.metadata.name, ordinal)
+    }
+
+    /// Returns the claims of the pod with `ordinal`, built from the volume
+    /// claim templates and labeled with the selector of the set.
+    pub fn pod_claims(&self, ordinal: i32) -> Vec<PersistentVolumeClaim> {
+        let Some(spec) = self.spec.as_ref() else {
+            return vec![];
+        };
+        let selector_labels = spec.selector.as_ref().map(|selector| selector.match_labels.clone());
+        spec.volume_claim_templates
+            .iter()
+            .map(|template| {
+                let mut claim = template.clone();
+                claim.metadata.name = self.claim_name(&template.metadata.name, ordinal);
+                claim.metadata.namespace = self.metadata.namespace.clone();
+                claim.metadata.labels.extend(selector_labels.clone().unwrap_or_default());
+                claim
+            })
+            .collect()
+    }
+
+    /// Returns the claim retention policy, `Retain` for unset rules.
+    pub fn claim_retention_policy(&self) -> StatefulSetPersistentVolumeClaimRetentionPolicy {
+        let policy = self
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.persistent_volume_claim_retention_policy.clone())
+            .unwrap_or_default();
+        let or_retain = |rule: String| if rule.is_empty() { PVC_RETENTION_POLICY_RETAIN.to_string() } else { rule };
+        StatefulSetPersistentVolumeClaimRetentionPolicy {
+            when_deleted: or_retain(policy.when_deleted),
+            when_scaled: or_retain(policy.when_scaled),
+        }
+    }
+
+    /// Returns whether a pod is beyond the replicas of the set, to be
+    /// deleted by a scale down.
+    pub fn is_condemned(&self, pod: &Pod) -> bool {
+        pod_ordinal(pod).is_some_and(|ordinal| !self.is_ordinal_in_range(ordinal))
+    }
+
+    /// Returns whether the claims of a pod should be owned by the set, and
+    /// so be deleted with it: `whenDeleted` is `Delete` and the claims are
+    /// not already handed to a condemned pod.
+    pub fn claims_owned_by_set(&self, pod: &Pod) -> bool {
+        let policy = self.claim_retention_policy();
+        policy.when_deleted == PVC_RETENTION_POLICY_DELETE && !self.claims_owned_by_pod(pod)
+    }
+
+    /// Returns whether the claims of a pod should be owned by the pod, and
+    /// so be deleted with it: `whenScaled` is `Delete` and the pod is
+    /// condemned.
+    pub fn claims_owned_by_pod(&self, pod: &Pod) -> bool {
+        self.claim_retention_policy().when_scaled == PVC_RETENTION_POLICY_DELETE && self.is_condemned(pod)
+    }
+
+    /// Returns whether the set may create and delete pods without waiting
+    /// for their predecessors, the `Parallel` pod management policy.
+    pub fn allows_burst(&self) -> bool {
+        self.spec
+            .as_ref()
+            .is_some_and(|spec| spec.pod_management_policy == POD_MANAGEMENT_POLICY_PARALLEL)
+    }
+
+    /// Returns the ordinal of the pod an `OrderedReady` set waits for before
+    /// creating, updating or deleting any other: the lowest replica that is
+    /// missing, terminating or not yet available, else the highest condemned
+    /// pod that is unhealthy. `None` for `Parallel` sets and healthy ones.
+    pub fn blocking_ordinal(&self, pods: &[Pod], now: DateTime<Utc>) -> Option<i32> {
+        if self.allows_burst() {
+            return None;
+        }
+        let min_ready_seconds = self.min_ready_seconds();
+        let pod = |ordinal: i32| pods.iter().find(|pod| pod_ordinal(pod) == Some(ordinal) && self.is_member_of(pod));
+        let replica = (self.start_ordinal()..=self.end_ordinal())
+            .find(|ordinal| pod(*ordinal).is_none_or(|pod| !is_healthy(pod, min_ready_seconds, now)));
+        if replica.is_some() {
+            return replica;
+        }
+        let mut condemned: Vec<&Pod> = pods
+            .iter()
+            .filter(|pod| self.is_member_of(pod) && self.is_condemned(pod))
+            .collect();
+        condemned.sort_by_key(|pod| std::cmp::Reverse(pod_ordinal(pod)));
+        condemned
+            .into_iter()
+            .find(|pod| !is_healthy(pod, min_ready_seconds, now))
+            .and_then(pod_ordinal)
+    }
+
+    fn rolling_update(&self) -> Option<&RollingUpdateStatefulSetStrategy> {
+        self.spec
+            .as_ref()
+            .and_then(|spec| spec.update_strategy.as_ref())
+            .and_then(|strategy| strategy.rolling_update.as_ref())
+    }
+
+    /// Returns whether the controller replaces outdated pods itself, the
+    /// default `RollingUpdate` strategy, rather than waiting for them to be
+    /// deleted.
+    pub fn is_rolling_update(&self) -> bool {
+        let strategy = self.spec.as_ref().and_then(|spec| spec.update_strategy.as_ref());
+        strategy.is_none_or(|strategy| {
+            strategy.strategy_type.is_empty() || strategy.strategy_type == STATEFUL_SET_UPDATE_STRATEGY_ROLLING_UPDATE
+        })
+    }
+
+    /// Returns the pods a rolling update may take down at once,
+    /// `maxUnavailable` of the replicas rounded down and at least one.
+    pub fn max_unavailable(&self) -> i32 {
+        let default = IntOrString::Int(1);
+        let max_unavailable = self
+            .rolling_update()
+            .and_then(|rolling_update| rolling_update.max_unavailable.as_ref())
+            .unwrap_or(&default);
+        // Validation rejects values that do not resolve.
+        max_unavailable
+            .get_scaled_value_from_int_or_percent(self.replicas(), false)
+            .unwrap_or_default()
+            .max(1)
+    }
+
+    /// Returns the ordinals a rolling update replaces, in the order it does:
+    /// from the last replica down to the `partition`th, which stay on the
+    /// current revision below it. Empty for `OnDelete`.
+    pub fn rolling_update_ordinals(&self) -> Vec<i32> {
+        if !self.is_rolling_update() {
+            return vec![];
+        }
+        let partition = self
+            .rolling_update()
+            .and_then(|rolling_update| rolling_update.partition)
+            .unwrap_or_default();
+        ((self.start_ordinal() + partition)..=self.end_ordinal()).rev().collect()
+    }
+
+    /// Returns the pods a rolling update deletes next to replace them with
+    /// `status.updateRevision`: outdated pods in update order, as many as
+    /// `maxUnavailable` leaves room for once the replicas already missing or
+    /// unavailable are counted.
+    pub fn pods_to_update<'a>(&self, pods: &'a [Pod], now: DateTime<Utc>) -> Vec<&'a Pod> {
+        let update_revision = self.status.as_ref().map(|status| status.update_revision.as_str()).unwrap_or_default();
+        let min_ready_seconds = self.min_ready_seconds();
+        let pod = |ordinal: i32| pods.iter().find(|pod| pod_ordinal(pod) == Some(ordinal) && self.is_member_of(pod));
+        let unavailable = (self.start_ordinal()..=self.end_ordinal())
+            .filter(|ordinal| pod(*ordinal).is_none_or(|pod| !is_healthy(pod, min_ready_seconds, now)))
+            .count() as i32;
+        let room = (self.max_unavailable() - unavailable).max(0) as usize;
+        self.rolling_update_ordinals()
+            .into_iter()
+            .filter_map(pod)
+            .filter(|pod| pod_revision(pod) != Some(update_revision) && !is_terminating(pod))
+            .take(room)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::{PodCondition, PodStatus, CONDITION_TRUE, POD_CONDITION_READY};
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+
+    fn set(replicas: i32) -> StatefulSet {
+        StatefulSet {
+            metadata: ObjectMeta::namespaced("default", "web"),
+            spec: Some(StatefulSetSpec {
+                replicas: Some(replicas),
+                service_name: "nginx".to_string(),
+                ..Default::default()
+            }),
+            status: Some(StatefulSetStatus {
+                update_revision: "web-v2".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn pod(set: &StatefulSet, ordinal: i32, revision: &str, ready: bool) -> Pod {
+        let mut pod = Pod::default();
+        set.init_identity(&mut pod, ordinal);
+        pod.metadata.labels.insert(STATEFUL_SET_REVISION_LABEL.to_string(), revision.to_string());
+        pod.status = Some(PodStatus {
+            phase: POD_PHASE_RUNNING.to_string(),
+            conditions: vec![PodCondition {
+                condition_type: POD_CONDITION_READY.to_string(),
+                status: if ready { CONDITION_TRUE.to_string() } else { "False".to_string() },
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        pod
+    }
+
+    #[test]
+    fn test_identity() {
+        assert_eq!(parent_name_and_ordinal("web-1-12"), Some(("web-1", 12)));
+        assert_eq!(parent_name_and_ordinal("web-a"), None);
+        assert_eq!(parent_name_and_ordinal("web-99999999999"), None);
+
+        let mut set = set(3);
+        set.spec.as_mut().unwrap().ordinals = Some(StatefulSetOrdinals { start: 5 });
+        assert_eq!((set.start_ordinal(), set.end_ordinal()), (5, 7));
+        assert_eq!(set.pod_dns_name(5), "web-5.nginx.default.svc");
+
+        let pod = pod(&set, 8, "web-v1", true);
+        assert!(set.identity_matches(&pod));
+        assert_eq!(pod.metadata.labels[POD_INDEX_LABEL], "8");
+        assert!(set.is_condemned(&pod));
+        assert_eq!(set.claim_name("data", 8), "data-web-8");
+
+        assert!(!set.claims_owned_by_set(&pod) && !set.claims_owned_by_pod(&pod));
+        set.spec.as_mut().unwrap().persistent_volume_claim_retention_policy =
+            Some(StatefulSetPersistentVolumeClaimRetentionPolicy {
+                when_deleted: PVC_RETENTION_POLICY_DELETE.to_string(),
+                when_scaled: PVC_RETENTION_POLICY_DELETE.to_string(),
+            });
+        assert!(!set.claims_owned_by_set(&pod) && set.claims_owned_by_pod(&pod));
+    }
+
+    #[test]
+    fn test_update_ordering() {
+        let now = Utc::now();
+        let mut set = set(5);
+        set.spec.as_mut().unwrap().update_strategy = Some(StatefulSetUpdateStrategy {
+            rolling_update: Some(RollingUpdateStatefulSetStrategy {
+                partition: Some(1),
+                max_unavailable: Some(IntOrString::from("50%")),
+            }),
+            ..Default::default()
+        });
+        assert_eq!(set.rolling_update_ordinals(), [4, 3, 2, 1]);
+        assert_eq!(set.max_unavailable(), 2);
+
+        let mut pods: Vec<Pod> = (0..5).map(|ordinal| pod(&set, ordinal, "web-v1", true)).collect();
+        let names = |pods: Vec<&Pod>| pods.iter().map(|pod| pod.metadata.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(set.pods_to_update(&pods, now)), ["web-4", "web-3"]);
+
+        pods[4] = pod(&set, 4, "web-v2", false);
+        assert_eq!(names(set.pods_to_update(&pods, now)), ["web-3"]);
+        assert_eq!(set.blocking_ordinal(&pods, now), Some(4));
+        set.spec.as_mut().unwrap().pod_management_policy = POD_MANAGEMENT_POLICY_PARALLEL.to_string();
+        assert_eq!(set.blocking_ordinal(&pods, now), None);
+    }
+}
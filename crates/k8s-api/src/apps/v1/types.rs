@@ -594,6 +594,13 @@ pub const DAEMON_SET_UPDATE_STRATEGY_ON_DELETE: &str = "OnDelete";
 // ReplicaSetConditionType constants
 pub const REPLICA_SET_CONDITION_REPLICA_FAILURE: &str = "ReplicaFailure";
 
+// Label constants
+pub const CONTROLLER_REVISION_HASH_LABEL_KEY: &str = "controller-revision-hash";
+pub const STATEFUL_SET_REVISION_LABEL: &str = CONTROLLER_REVISION_HASH_LABEL_KEY;
+pub const STATEFUL_SET_POD_NAME_LABEL: &str = "statefulset.kubernetes.io/pod-name";
+pub const POD_INDEX_LABEL: &str = "apps.kubernetes.io/pod-index";
+pub const DEFAULT_DEPLOYMENT_UNIQUE_LABEL_KEY: &str = "pod-template-hash";
+
 // =============================================================================
 // ControllerRevision
 // =============================================================================
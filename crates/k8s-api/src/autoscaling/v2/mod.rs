@@ -2,8 +2,10 @@
 
 mod types;
 mod internal_conversion;
+mod replica_calculator;
 
 pub use types::*;
+pub use replica_calculator::*;
 
 pub trait InternalConversion: Sized + serde::Serialize + serde::de::DeserializeOwned {
     type Internal: serde::Serialize + serde::de::DeserializeOwned;
@@ -0,0 +1,1155 @@
+//! HorizontalPodAutoscaler replica calculation
+//!
+//! The replica recommendation of the HPA controller, computed from a
+//! synthetic snapshot of the metrics APIs instead of live clients: per-metric
+//! proposals with tolerance and missing or unready pod handling
+//! (`replica_calculator.go`), the maximum across metrics, and the
+//! stabilization windows and scaling policies of `horizontal.go`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Duration, Utc};
+use k8s_api_core::resource::{Quantity, QuantityFormat};
+use k8s_apimachinery::apis::meta::v1::Time;
+
+use crate::core::v1::{
+    Pod, CONDITION_TRUE, CONTAINER_RESTART_POLICY_ALWAYS, POD_CONDITION_READY, POD_PHASE_FAILED, POD_PHASE_PENDING,
+    POD_PHASE_RUNNING,
+};
+
+use super::*;
+
+/// The tolerance applied when the scaling rules do not set one.
+pub const DEFAULT_TOLERANCE: f64 = 0.1;
+
+/// Resource usage of one pod, as reported by the resource metrics API.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PodResourceMetrics {
+    pub timestamp: DateTime<Utc>,
+    pub window: Duration,
+    /// Usage keyed by container name and then resource name.
+    pub containers: BTreeMap<String, BTreeMap<String, Quantity>>,
+}
+
+/// The value of a metric describing a single object.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjectMetricValue {
+    pub described_object: CrossVersionObjectReference,
+    pub metric: String,
+    pub value: Quantity,
+}
+
+/// What the metrics APIs would return for an HPA at one point in time.
+/// Metric selectors are not evaluated; the snapshot holds the values the
+/// APIs would return for them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// The pods selected by the scale target.
+    pub pods: Vec<Pod>,
+    /// Resource metrics keyed by pod name.
+    pub resources: BTreeMap<String, PodResourceMetrics>,
+    /// Pods metrics keyed by metric name and then pod name.
+    pub pods_metrics: BTreeMap<String, BTreeMap<String, Quantity>>,
+    pub object_metrics: Vec<ObjectMetricValue>,
+    /// External metric values keyed by metric name.
+    pub external_metrics: BTreeMap<String, Vec<Quantity>>,
+}
+
+/// The replica count an HPA recommends for its scale target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScaleRecommendation {
+    pub desired_replicas: i32,
+    /// Why the replica count changes, empty when it does not.
+    pub reason: String,
+    pub current_metrics: Vec<MetricStatus>,
+    pub conditions: Vec<HorizontalPodAutoscalerCondition>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Tolerances {
+    scale_down: f64,
+    scale_up: f64,
+}
+
+impl Tolerances {
+    fn is_within(&self, usage_ratio: f64) -> bool {
+        1.0 - self.scale_down <= usage_ratio && usage_ratio <= 1.0 + self.scale_up
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PodSample {
+    timestamp: DateTime<Utc>,
+    window: Duration,
+    value: i64,
+}
+
+impl PodSample {
+    fn value(value: i64) -> Self {
+        Self { timestamp: DateTime::default(), window: Duration::zero(), value }
+    }
+}
+
+#[derive(Default)]
+struct PodGroups {
+    ready: i32,
+    unready: BTreeSet<String>,
+    missing: BTreeSet<String>,
+    ignored: BTreeSet<String>,
+}
+
+struct Proposal {
+    replicas: i32,
+    metric: String,
+    status: MetricStatus,
+}
+
+/// Computes HPA replica recommendations, keeping the recent recommendations
+/// and scale events one HPA needs for stabilization and rate limiting.
+#[derive(Clone, Debug)]
+pub struct ReplicaCalculator {
+    /// Used when the scaling rules do not set a tolerance.
+    pub tolerance: f64,
+    /// How long after a pod starts its CPU samples may be skewed by start-up.
+    pub cpu_initialization_period: Duration,
+    /// How long after a pod starts it may still be unready without having
+    /// been ready before.
+    pub delay_of_initial_readiness_status: Duration,
+    /// Used when the HPA has no behavior or no scale-down window.
+    pub downscale_stabilization_window: Duration,
+    recommendations: Vec<(DateTime<Utc>, i32)>,
+    scale_up_events: Vec<(DateTime<Utc>, i32)>,
+    scale_down_events: Vec<(DateTime<Utc>, i32)>,
+}
+
+impl Default for ReplicaCalculator {
+    fn default() -> Self {
+        Self {
+            tolerance: DEFAULT_TOLERANCE,
+            cpu_initialization_period: Duration::minutes(5),
+            delay_of_initial_readiness_status: Duration::seconds(30),
+            downscale_stabilization_window: Duration::minutes(5),
+            recommendations: Vec::new(),
+            scale_up_events: Vec::new(),
+            scale_down_events: Vec::new(),
+        }
+    }
+}
+
+impl ReplicaCalculator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recommends a replica count for an HPA whose target has `spec_replicas`
+    /// desired and `status_replicas` current replicas.
+    ///
+    /// The recommendation is assumed to be applied: it is recorded for later
+    /// stabilization and, when it changes the scale, for rate limiting. Fails
+    /// when no metric can be computed, or when some cannot and the others
+    /// would scale down.
+    pub fn reconcile(
+        &mut self,
+        hpa: &HorizontalPodAutoscaler,
+        spec_replicas: i32,
+        status_replicas: i32,
+        snapshot: &MetricsSnapshot,
+        now: DateTime<Utc>,
+    ) -> Result<ScaleRecommendation, String> {
+        let default_spec = HorizontalPodAutoscalerSpec::default();
+        let spec = hpa.spec.as_ref().unwrap_or(&default_spec);
+        let min_replicas = spec.min_replicas.unwrap_or(1);
+        let mut recommendation = ScaleRecommendation { desired_replicas: spec_replicas, ..Default::default() };
+        let mut set_condition = |condition_type: &str, status: bool, reason: &str, message: String| {
+            recommendation.conditions.push(HorizontalPodAutoscalerCondition {
+                condition_type: condition_type.to_string(),
+                status: if status { CONDITION_TRUE } else { "False" }.to_string(),
+                last_transition_time: Some(Time(Some(now))),
+                reason: reason.to_string(),
+                message,
+            });
+        };
+
+        if spec_replicas == 0 && min_replicas != 0 {
+            set_condition(
+                HPA_CONDITION_SCALING_ACTIVE,
+                false,
+                "ScalingDisabled",
+                "scaling is disabled since the replica count of the target is zero".to_string(),
+            );
+            return Ok(recommendation);
+        }
+        let (desired, reason) = if spec_replicas > spec.max_replicas {
+            (spec.max_replicas, "Current number of replicas above Spec.MaxReplicas".to_string())
+        } else if spec_replicas < min_replicas {
+            (min_replicas, "Current number of replicas below Spec.MinReplicas".to_string())
+        } else {
+            let tolerances = self.tolerances(spec.behavior.as_ref());
+            let mut metric_replicas = 0;
+            let mut metric_name = String::new();
+            let mut first_error = None;
+            let mut invalid = 0;
+            let default_metrics = [default_metric()];
+            let metrics = if spec.metrics.is_empty() { &default_metrics[..] } else { &spec.metrics[..] };
+            for metric in metrics {
+                match self.compute_for_metric(metric, spec_replicas, status_replicas, tolerances, snapshot, now) {
+                    Ok(proposal) => {
+                        if metric_replicas == 0 || proposal.replicas > metric_replicas {
+                            metric_replicas = proposal.replicas;
+                            metric_name = proposal.metric;
+                        }
+                        recommendation.current_metrics.push(proposal.status);
+                    }
+                    Err(err) => {
+                        invalid += 1;
+                        first_error.get_or_insert(err);
+                        recommendation.current_metrics.push(MetricStatus::default());
+                    }
+                }
+            }
+            if let Some(err) = first_error {
+                if invalid >= metrics.len() || metric_replicas < spec_replicas {
+                    return Err(format!(
+                        "invalid metrics ({} invalid out of {}), first error is: {}",
+                        invalid,
+                        metrics.len(),
+                        err
+                    ));
+                }
+            }
+            set_condition(
+                HPA_CONDITION_SCALING_ACTIVE,
+                true,
+                "ValidMetricFound",
+                format!("the HPA was able to successfully calculate a replica count from {}", metric_name),
+            );
+            // The reason reflects the metrics' proposal, before stabilization
+            // and limits are applied.
+            let reason = match metric_replicas.cmp(&spec_replicas) {
+                std::cmp::Ordering::Greater => format!("{} above target", metric_name),
+                std::cmp::Ordering::Less => "All metrics below target".to_string(),
+                std::cmp::Ordering::Equal => String::new(),
+            };
+
+            let normalized = match &spec.behavior {
+                None => self.normalize(spec, spec_replicas, metric_replicas, min_replicas, now),
+                Some(behavior) => self.normalize_with_behavior(behavior, spec, spec_replicas, metric_replicas, min_replicas, now),
+            };
+            let (desired, stabilized, able_reason, able_message, limit_reason, limit_message) = normalized;
+            set_condition(HPA_CONDITION_ABLE_TO_SCALE, true, able_reason, able_message.to_string());
+            set_condition(HPA_CONDITION_SCALING_LIMITED, desired != stabilized, limit_reason, limit_message.to_string());
+            (desired, reason)
+        };
+
+        if desired != spec_replicas {
+            self.store_scale_event(spec.behavior.as_ref(), spec_replicas, desired, now);
+        }
+        recommendation.desired_replicas = desired;
+        recommendation.reason = reason;
+        Ok(recommendation)
+    }
+
+    fn tolerances(&self, behavior: Option<&HorizontalPodAutoscalerBehavior>) -> Tolerances {
+        let tolerance = |rules: Option<&HPAScalingRules>| {
+            rules
+                .and_then(|rules| rules.tolerance.as_ref())
+                .and_then(|tolerance| tolerance.as_approximate_float().ok())
+                .unwrap_or(self.tolerance)
+        };
+        Tolerances {
+            scale_down: tolerance(behavior.and_then(|behavior| behavior.scale_down.as_ref())),
+            scale_up: tolerance(behavior.and_then(|behavior| behavior.scale_up.as_ref())),
+        }
+    }
+
+    fn compute_for_metric(
+        &self,
+        metric: &MetricSpec,
+        spec_replicas: i32,
+        status_replicas: i32,
+        tolerances: Tolerances,
+        snapshot: &MetricsSnapshot,
+        now: DateTime<Utc>,
+    ) -> Result<Proposal, String> {
+        let missing = |source: &str| format!("missing {} metric source", source);
+        match metric.type_.as_str() {
+            METRIC_SOURCE_TYPE_RESOURCE => {
+                let source = metric.resource.as_ref().ok_or_else(|| missing("resource"))?;
+                self.compute_for_resource(&source.name, "", &source.target, spec_replicas, tolerances, snapshot, now)
+            }
+            METRIC_SOURCE_TYPE_CONTAINER_RESOURCE => {
+                let source = metric.container_resource.as_ref().ok_or_else(|| missing("container resource"))?;
+                self.compute_for_resource(&source.name, &source.container, &source.target, spec_replicas, tolerances, snapshot, now)
+            }
+            METRIC_SOURCE_TYPE_PODS => {
+                let source = metric.pods.as_ref().ok_or_else(|| missing("pods"))?;
+                self.compute_for_pods(source, spec_replicas, tolerances, snapshot, now)
+            }
+            METRIC_SOURCE_TYPE_OBJECT => {
+                let source = metric.object.as_ref().ok_or_else(|| missing("object"))?;
+                compute_for_object(source, spec_replicas, status_replicas, tolerances, snapshot)
+            }
+            METRIC_SOURCE_TYPE_EXTERNAL => {
+                let source = metric.external.as_ref().ok_or_else(|| missing("external"))?;
+                compute_for_external(source, spec_replicas, status_replicas, tolerances, snapshot)
+            }
+            source_type => Err(format!("unknown metric source type {:?}", source_type)),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compute_for_resource(
+        &self,
+        resource: &str,
+        container: &str,
+        target: &MetricTarget,
+        current_replicas: i32,
+        tolerances: Tolerances,
+        snapshot: &MetricsSnapshot,
+        now: DateTime<Utc>,
+    ) -> Result<Proposal, String> {
+        let (label, kind) = if container.is_empty() { ("", "resource") } else { (" container", "container resource") };
+        if target.average_value.is_none() && target.average_utilization.is_none() {
+            return Err(format!(
+                "invalid {} metric source: neither an average utilization target nor an average value target was set",
+                kind
+            ));
+        }
+        let mut metrics = resource_metrics(snapshot, resource, container)
+            .map_err(|err| format!("unable to get metrics for resource {}: {}", resource, err))?;
+        let pods = &snapshot.pods;
+        let Some(target_utilization) = target.average_utilization.filter(|_| target.average_value.is_none()) else {
+            let target_value = target.average_value.as_ref().map(milli_value).transpose()?.unwrap_or_default();
+            let (replicas, usage) = self.plain_metric_replicas(metrics, current_replicas, target_value, tolerances, pods, resource, now)?;
+            let current = MetricValueStatus { average_value: Some(milli_quantity(usage)), ..Default::default() };
+            return Ok(resource_proposal(replicas, format!("{}{} resource", resource, label), resource, container, current));
+        };
+
+        if pods.is_empty() {
+            return Err("no pods returned by selector while calculating replica count".to_string());
+        }
+        let groups = self.group_pods(pods, &metrics, resource, now);
+        metrics.retain(|pod, _| !groups.ignored.contains(pod) && !groups.unready.contains(pod));
+        if metrics.is_empty() {
+            return Err("did not receive metrics for targeted pods (pods might be unready)".to_string());
+        }
+        let requests = pod_requests(pods, container, resource)?;
+        let (usage_ratio, utilization, raw) = utilization_ratio(&metrics, &requests, target_utilization)?;
+        let current = MetricValueStatus {
+            average_utilization: Some(utilization),
+            average_value: Some(milli_quantity(raw)),
+            ..Default::default()
+        };
+        let name = format!("{}{} resource utilization (percentage of request)", resource, label);
+
+        let scale_up_with_unready = !groups.unready.is_empty() && usage_ratio > 1.0;
+        if !scale_up_with_unready && groups.missing.is_empty() {
+            let replicas = if tolerances.is_within(usage_ratio) {
+                current_replicas
+            } else {
+                (usage_ratio * f64::from(groups.ready)).ceil() as i32
+            };
+            return Ok(resource_proposal(replicas, name, resource, container, current));
+        }
+        if usage_ratio < 1.0 {
+            // On a scale-down, missing pods are treated as using exactly the target.
+            let fallback = i64::from(target_utilization.max(100));
+            for pod in &groups.missing {
+                let request = requests.get(pod).copied().unwrap_or_default();
+                metrics.insert(pod.clone(), PodSample::value(request * fallback / 100));
+            }
+        } else if usage_ratio > 1.0 {
+            // On a scale-up, missing pods are treated as using nothing.
+            for pod in &groups.missing {
+                metrics.insert(pod.clone(), PodSample::value(0));
+            }
+        }
+        if scale_up_with_unready {
+            for pod in &groups.unready {
+                metrics.insert(pod.clone(), PodSample::value(0));
+            }
+        }
+        let (new_ratio, _, _) = utilization_ratio(&metrics, &requests, target_utilization)?;
+        let replicas = settle_replicas(usage_ratio, new_ratio, metrics.len(), current_replicas, tolerances);
+        Ok(resource_proposal(replicas, name, resource, container, current))
+    }
+
+    fn compute_for_pods(
+        &self,
+        source: &PodsMetricSource,
+        current_replicas: i32,
+        tolerances: Tolerances,
+        snapshot: &MetricsSnapshot,
+        now: DateTime<Utc>,
+    ) -> Result<Proposal, String> {
+        let name = &source.metric.name;
+        let values = snapshot
+            .pods_metrics
+            .get(name)
+            .filter(|values| !values.is_empty())
+            .ok_or_else(|| format!("unable to get metric {}: no metrics returned from custom metrics API", name))?;
+        let metrics = values
+            .iter()
+            .map(|(pod, value)| Ok((pod.clone(), PodSample::value(milli_value(value)?))))
+            .collect::<Result<BTreeMap<_, _>, String>>()?;
+        let target = source
+            .target
+            .average_value
+            .as_ref()
+            .ok_or_else(|| "invalid pods metric source: no average value target was set".to_string())?;
+        let (replicas, usage) = self.plain_metric_replicas(metrics, current_replicas, milli_value(target)?, tolerances, &snapshot.pods, "", now)?;
+        Ok(Proposal {
+            replicas,
+            metric: format!("pods metric {}", name),
+            status: MetricStatus {
+                type_: METRIC_SOURCE_TYPE_PODS.to_string(),
+                pods: Some(PodsMetricStatus {
+                    metric: source.metric.clone(),
+                    current: MetricValueStatus { average_value: Some(milli_quantity(usage)), ..Default::default() },
+                }),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Buckets pods into ready, unready, missing a metric and ignored. For
+    /// CPU, samples of pods that are starting up, or were never ready, are
+    /// treated as unready since they may not reflect steady state usage.
+    fn group_pods(&self, pods: &[Pod], metrics: &BTreeMap<String, PodSample>, resource: &str, now: DateTime<Utc>) -> PodGroups {
+        let mut groups = PodGroups::default();
+        for pod in pods {
+            let name = &pod.metadata.name;
+            let status = pod.status.clone().unwrap_or_default();
+            if pod.metadata.deletion_timestamp.is_some() || status.phase == POD_PHASE_FAILED {
+                groups.ignored.insert(name.clone());
+                continue;
+            }
+            if status.phase == POD_PHASE_PENDING {
+                groups.unready.insert(name.clone());
+                continue;
+            }
+            let Some(metric) = metrics.get(name) else {
+                groups.missing.insert(name.clone());
+                continue;
+            };
+            if resource == "cpu" {
+                let condition = pod.get_condition(POD_CONDITION_READY);
+                let start = status.start_time.as_ref().and_then(|time| time.0);
+                let unready = match (condition, start) {
+                    (Some(condition), Some(start)) => {
+                        let transition = condition.last_transition_time.as_ref().and_then(|time| time.0).unwrap_or_default();
+                        if start + self.cpu_initialization_period > now {
+                            condition.status == "False" || metric.timestamp < transition + metric.window
+                        } else {
+                            condition.status == "False" && start + self.delay_of_initial_readiness_status > transition
+                        }
+                    }
+                    _ => true,
+                };
+                if unready {
+                    groups.unready.insert(name.clone());
+                    continue;
+                }
+            }
+            groups.ready += 1;
+        }
+        groups
+    }
+
+    /// Proposes replicas for a per-pod average target, filling in missing and
+    /// unready pods conservatively before settling on a count.
+    #[allow(clippy::too_many_arguments)]
+    fn plain_metric_replicas(
+        &self,
+        mut metrics: BTreeMap<String, PodSample>,
+        current_replicas: i32,
+        target: i64,
+        tolerances: Tolerances,
+        pods: &[Pod],
+        resource: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(i32, i64), String> {
+        if pods.is_empty() {
+            return Err("no pods returned by selector while calculating replica count".to_string());
+        }
+        let groups = self.group_pods(pods, &metrics, resource, now);
+        metrics.retain(|pod, _| !groups.ignored.contains(pod) && !groups.unready.contains(pod));
+        if metrics.is_empty() {
+            return Err("did not receive metrics for targeted pods (pods might be unready)".to_string());
+        }
+        let (usage_ratio, usage) = average_usage_ratio(&metrics, target);
+        let scale_up_with_unready = !groups.unready.is_empty() && usage_ratio > 1.0;
+        if !scale_up_with_unready && groups.missing.is_empty() {
+            if tolerances.is_within(usage_ratio) {
+                return Ok((current_replicas, usage));
+            }
+            return Ok(((usage_ratio * f64::from(groups.ready)).ceil() as i32, usage));
+        }
+        for pod in &groups.missing {
+            let value = if usage_ratio < 1.0 { target } else { 0 };
+            metrics.insert(pod.clone(), PodSample::value(value));
+        }
+        if scale_up_with_unready {
+            for pod in &groups.unready {
+                metrics.insert(pod.clone(), PodSample::value(0));
+            }
+        }
+        let (new_ratio, _) = average_usage_ratio(&metrics, target);
+        Ok((settle_replicas(usage_ratio, new_ratio, metrics.len(), current_replicas, tolerances), usage))
+    }
+
+    /// Applies the default downscale stabilization and scale-up limit used
+    /// when the HPA has no behavior.
+    fn normalize(
+        &mut self,
+        spec: &HorizontalPodAutoscalerSpec,
+        current: i32,
+        desired: i32,
+        min_replicas: i32,
+        now: DateTime<Utc>,
+    ) -> (i32, i32, &'static str, &'static str, &'static str, &'static str) {
+        let cutoff = now - self.downscale_stabilization_window;
+        let stabilized = self
+            .recommendations
+            .iter()
+            .filter(|(time, _)| *time >= cutoff)
+            .map(|(_, replicas)| *replicas)
+            .fold(desired, i32::max);
+        self.recommendations.retain(|(time, _)| *time >= cutoff);
+        self.recommendations.push((now, desired));
+        let (able_reason, able_message) = if stabilized != desired {
+            (
+                "ScaleDownStabilized",
+                "recent recommendations were higher than current one, applying the highest recent recommendation",
+            )
+        } else {
+            ("ReadyForNewScale", "recommended size matches current size")
+        };
+
+        let scale_up_limit = (2 * current).max(4);
+        let (maximum, max_reason, max_message) = if spec.max_replicas > scale_up_limit {
+            (scale_up_limit, "ScaleUpLimit", "the desired replica count is increasing faster than the maximum scale rate")
+        } else {
+            (spec.max_replicas, "TooManyReplicas", "the desired replica count is more than the maximum replica count")
+        };
+        let (normalized, limit_reason, limit_message) = if stabilized < min_replicas {
+            (min_replicas, "TooFewReplicas", "the desired replica count is less than the minimum replica count")
+        } else if stabilized > maximum {
+            (maximum, max_reason, max_message)
+        } else {
+            (stabilized, "DesiredWithinRange", "the desired count is within the acceptable range")
+        };
+        (normalized, stabilized, able_reason, able_message, limit_reason, limit_message)
+    }
+
+    /// Applies the stabilization windows and scaling policies of the HPA's
+    /// behavior, with unset fields defaulted as the API server does.
+    #[allow(clippy::too_many_arguments)]
+    fn normalize_with_behavior(
+        &mut self,
+        behavior: &HorizontalPodAutoscalerBehavior,
+        spec: &HorizontalPodAutoscalerSpec,
+        current: i32,
+        desired: i32,
+        min_replicas: i32,
+        now: DateTime<Utc>,
+    ) -> (i32, i32, &'static str, &'static str, &'static str, &'static str) {
+        let scale_up = scale_up_rules(behavior.scale_up.as_ref());
+        let scale_down = scale_down_rules(behavior.scale_down.as_ref(), self.downscale_stabilization_window);
+        let up_cutoff = now - Duration::seconds(scale_up.stabilization_window_seconds.unwrap_or_default().into());
+        let down_cutoff = now - Duration::seconds(scale_down.stabilization_window_seconds.unwrap_or_default().into());
+
+        let (mut up_recommendation, mut down_recommendation) = (desired, desired);
+        for (time, replicas) in &self.recommendations {
+            if *time > up_cutoff {
+                up_recommendation = up_recommendation.min(*replicas);
+            }
+            if *time > down_cutoff {
+                down_recommendation = down_recommendation.max(*replicas);
+            }
+        }
+        let stabilized = current.max(up_recommendation).min(down_recommendation);
+        let oldest = up_cutoff.min(down_cutoff);
+        self.recommendations.retain(|(time, _)| *time >= oldest);
+        self.recommendations.push((now, desired));
+        let (able_reason, able_message) = if stabilized == desired {
+            ("ReadyForNewScale", "recommended size matches current size")
+        } else if desired >= current {
+            ("ScaleUpStabilized", "recent recommendations were lower than current one, applying the lowest recent recommendation")
+        } else {
+            (
+                "ScaleDownStabilized",
+                "recent recommendations were higher than current one, applying the highest recent recommendation",
+            )
+        };
+
+        let within = (stabilized, "DesiredWithinRange", "the desired count is within the acceptable range");
+        let (normalized, limit_reason, limit_message) = if stabilized > current {
+            let limit = self.scale_up_limit(current, &scale_up, now).max(current);
+            let (maximum, reason, message) = if spec.max_replicas > limit {
+                (limit, "ScaleUpLimit", "the desired replica count is increasing faster than the maximum scale rate")
+            } else {
+                (spec.max_replicas, "TooManyReplicas", "the desired replica count is more than the maximum replica count")
+            };
+            if stabilized > maximum {
+                (maximum, reason, message)
+            } else {
+                within
+            }
+        } else if stabilized < current {
+            let limit = self.scale_down_limit(current, &scale_down, now).min(current);
+            let (minimum, reason, message) = if min_replicas < limit {
+                (limit, "ScaleDownLimit", "the desired replica count is decreasing faster than the maximum scale rate")
+            } else {
+                (min_replicas, "TooFewReplicas", "the desired replica count is less than the minimum replica count")
+            };
+            if stabilized < minimum {
+                (minimum, reason, message)
+            } else {
+                within
+            }
+        } else {
+            within
+        };
+        (normalized, stabilized, able_reason, able_message, limit_reason, limit_message)
+    }
+
+    /// The replica count each policy's period started from, after undoing
+    /// the scale events within the period.
+    fn period_start_replicas(&self, current: i32, period_seconds: i32, now: DateTime<Utc>) -> i32 {
+        let cutoff = now - Duration::seconds(period_seconds.into());
+        let change = |events: &[(DateTime<Utc>, i32)]| -> i32 {
+            events.iter().filter(|(time, _)| *time > cutoff).map(|(_, change)| change).sum()
+        };
+        current - change(&self.scale_up_events) + change(&self.scale_down_events)
+    }
+
+    fn scale_up_limit(&self, current: i32, rules: &HPAScalingRules, now: DateTime<Utc>) -> i32 {
+        let proposals = rules.policies.iter().map(|policy| {
+            let start = self.period_start_replicas(current, policy.period_seconds, now);
+            match policy.type_.as_str() {
+                HPA_SCALING_POLICY_PERCENT => (f64::from(start) * (1.0 + f64::from(policy.value) / 100.0)).ceil() as i32,
+                _ => start + policy.value,
+            }
+        });
+        match rules.select_policy.as_deref() {
+            Some(SCALING_POLICY_SELECT_DISABLED) => current,
+            Some(SCALING_POLICY_SELECT_MIN) => proposals.fold(i32::MAX, i32::min),
+            _ => proposals.fold(i32::MIN, i32::max),
+        }
+    }
+
+    fn scale_down_limit(&self, current: i32, rules: &HPAScalingRules, now: DateTime<Utc>) -> i32 {
+        let proposals = rules.policies.iter().map(|policy| {
+            let start = self.period_start_replicas(current, policy.period_seconds, now);
+            match policy.type_.as_str() {
+                HPA_SCALING_POLICY_PERCENT => (f64::from(start) * (1.0 - f64::from(policy.value) / 100.0)) as i32,
+                _ => start - policy.value,
+            }
+        });
+        match rules.select_policy.as_deref() {
+            Some(SCALING_POLICY_SELECT_DISABLED) => current,
+            Some(SCALING_POLICY_SELECT_MIN) => proposals.fold(i32::MIN, i32::max),
+            _ => proposals.fold(i32::MAX, i32::min),
+        }
+    }
+
+    /// Records a scale change for the rate limits, dropping events older than
+    /// the longest policy period of that direction.
+    fn store_scale_event(&mut self, behavior: Option<&HorizontalPodAutoscalerBehavior>, previous: i32, new: i32, now: DateTime<Utc>) {
+        let Some(behavior) = behavior else {
+            return;
+        };
+        let (rules, events, change) = if new > previous {
+            (scale_up_rules(behavior.scale_up.as_ref()), &mut self.scale_up_events, new - previous)
+        } else {
+            (
+                scale_down_rules(behavior.scale_down.as_ref(), self.downscale_stabilization_window),
+                &mut self.scale_down_events,
+                previous - new,
+            )
+        };
+        let longest = rules.policies.iter().map(|policy| policy.period_seconds).max().unwrap_or_default();
+        let cutoff = now - Duration::seconds(longest.into());
+        events.retain(|(time, _)| *time > cutoff);
+        events.push((now, change));
+    }
+}
+
+/// The metric the API server defaults an HPA without metrics to: 80% CPU
+/// utilization.
+fn default_metric() -> MetricSpec {
+    MetricSpec {
+        type_: METRIC_SOURCE_TYPE_RESOURCE.to_string(),
+        resource: Some(ResourceMetricSource {
+            name: "cpu".to_string(),
+            target: MetricTarget {
+                type_: METRIC_TARGET_TYPE_UTILIZATION.to_string(),
+                average_utilization: Some(80),
+                ..Default::default()
+            },
+        }),
+        ..Default::default()
+    }
+}
+
+/// Scale-up rules with defaults: no stabilization, and the larger of 4 pods
+/// or 100% every 15 seconds.
+fn scale_up_rules(rules: Option<&HPAScalingRules>) -> HPAScalingRules {
+    let mut rules = rules.cloned().unwrap_or_default();
+    if rules.policies.is_empty() {
+        rules.policies = vec![
+            HPAScalingPolicy { type_: HPA_SCALING_POLICY_PODS.to_string(), value: 4, period_seconds: 15 },
+            HPAScalingPolicy { type_: HPA_SCALING_POLICY_PERCENT.to_string(), value: 100, period_seconds: 15 },
+        ];
+    }
+    rules.select_policy.get_or_insert_with(|| SCALING_POLICY_SELECT_MAX.to_string());
+    rules.stabilization_window_seconds.get_or_insert(0);
+    rules
+}
+
+/// Scale-down rules with defaults: the controller's stabilization window,
+/// and up to 100% every 15 seconds.
+fn scale_down_rules(rules: Option<&HPAScalingRules>, window: Duration) -> HPAScalingRules {
+    let mut rules = rules.cloned().unwrap_or_default();
+    if rules.policies.is_empty() {
+        rules.policies = vec![HPAScalingPolicy {
+            type_: HPA_SCALING_POLICY_PERCENT.to_string(),
+            value: 100,
+            period_seconds: 15,
+        }];
+    }
+    rules.select_policy.get_or_insert_with(|| SCALING_POLICY_SELECT_MAX.to_string());
+    rules.stabilization_window_seconds.get_or_insert(window.num_seconds() as i32);
+    rules
+}
+
+fn milli_value(quantity: &Quantity) -> Result<i64, String> {
+    quantity.milli_value().map_err(|err| format!("invalid quantity {}: {}", quantity.as_str(), err))
+}
+
+fn milli_quantity(milli: i64) -> Quantity {
+    Quantity::from_nanos(i128::from(milli) * 1_000_000, QuantityFormat::DecimalSI)
+}
+
+/// Per-pod usage of a resource, summed over containers or taken from one.
+/// Pods missing usage for any container they report are left out, and a
+/// pod without the named container is an error.
+fn resource_metrics(snapshot: &MetricsSnapshot, resource: &str, container: &str) -> Result<BTreeMap<String, PodSample>, String> {
+    let mut metrics = BTreeMap::new();
+    for (pod, usage) in &snapshot.resources {
+        let mut value = 0;
+        let mut found = false;
+        let mut missing = false;
+        for (name, resources) in &usage.containers {
+            if !container.is_empty() && name != container {
+                continue;
+            }
+            found = true;
+            match resources.get(resource) {
+                Some(quantity) => value += milli_value(quantity)?,
+                None => missing = true,
+            }
+        }
+        if !container.is_empty() && !found {
+            return Err(format!("container {} not present in metrics for pod {}", container, pod));
+        }
+        if found && !missing {
+            metrics.insert(pod.clone(), PodSample { timestamp: usage.timestamp, window: usage.window, value });
+        }
+    }
+    if metrics.is_empty() {
+        return Err("no metrics returned from resource metrics API".to_string());
+    }
+    Ok(metrics)
+}
+
+/// Requests of each pod for a resource in milli-units, from pod-level
+/// resources when set or else its containers and sidecars.
+fn pod_requests(pods: &[Pod], container: &str, resource: &str) -> Result<BTreeMap<String, i64>, String> {
+    let mut requests = BTreeMap::new();
+    for pod in pods {
+        let name = &pod.metadata.name;
+        let spec = pod.spec.clone().unwrap_or_default();
+        let pod_level = spec.resources.as_ref().map(|resources| &resources.requests).filter(|requests| !requests.is_empty());
+        if let (true, Some(pod_requests)) = (container.is_empty(), pod_level) {
+            let request = pod_requests
+                .get(resource)
+                .ok_or_else(|| format!("missing pod-level request for {} in Pod {}", resource, name))?;
+            requests.insert(name.clone(), milli_value(request)?);
+            continue;
+        }
+        let sidecars = spec
+            .init_containers
+            .iter()
+            .filter(|init| init.restart_policy.as_deref() == Some(CONTAINER_RESTART_POLICY_ALWAYS));
+        let mut sum = 0;
+        for c in spec.containers.iter().chain(sidecars) {
+            if !container.is_empty() && c.name != container {
+                continue;
+            }
+            let request = c
+                .resources
+                .as_ref()
+                .and_then(|resources| resources.requests.get(resource))
+                .ok_or_else(|| format!("missing request for {} in container {} of Pod {}", resource, c.name, name))?;
+            sum += milli_value(request)?;
+        }
+        requests.insert(name.clone(), sum);
+    }
+    Ok(requests)
+}
+
+/// Returns the usage to target ratio, the utilization in percent of the
+/// requests and the average raw usage.
+fn utilization_ratio(
+    metrics: &BTreeMap<String, PodSample>,
+    requests: &BTreeMap<String, i64>,
+    target_utilization: i32,
+) -> Result<(f64, i32, i64), String> {
+    let (mut metrics_total, mut requests_total, mut entries) = (0i64, 0i64, 0i64);
+    for (pod, metric) in metrics {
+        if let Some(request) = requests.get(pod) {
+            metrics_total += metric.value;
+            requests_total += request;
+            entries += 1;
+        }
+    }
+    if requests_total == 0 {
+        return Err("no metrics returned matched known pods".to_string());
+    }
+    let utilization = (metrics_total * 100 / requests_total) as i32;
+    Ok((f64::from(utilization) / f64::from(target_utilization), utilization, metrics_total / entries))
+}
+
+fn average_usage_ratio(metrics: &BTreeMap<String, PodSample>, target: i64) -> (f64, i64) {
+    let total: i64 = metrics.values().map(|metric| metric.value).sum();
+    let usage = total / metrics.len() as i64;
+    (usage as f64 / target as f64, usage)
+}
+
+/// Settles on a replica count after missing and unready pods were filled
+/// in, keeping the current count when the change is within tolerance or the
+/// filled-in values would reverse the direction of scaling.
+fn settle_replicas(usage_ratio: f64, new_ratio: f64, pods: usize, current: i32, tolerances: Tolerances) -> i32 {
+    if tolerances.is_within(new_ratio) || (usage_ratio < 1.0 && new_ratio > 1.0) || (usage_ratio > 1.0 && new_ratio < 1.0) {
+        return current;
+    }
+    let replicas = (new_ratio * pods as f64).ceil() as i32;
+    if (new_ratio < 1.0 && replicas > current) || (new_ratio > 1.0 && replicas < current) {
+        return current;
+    }
+    replicas
+}
+
+fn resource_proposal(replicas: i32, metric: String, resource: &str, container: &str, current: MetricValueStatus) -> Proposal {
+    let status = if container.is_empty() {
+        MetricStatus {
+            type_: METRIC_SOURCE_TYPE_RESOURCE.to_string(),
+            resource: Some(ResourceMetricStatus { name: resource.to_string(), current }),
+            ..Default::default()
+        }
+    } else {
+        MetricStatus {
+            type_: METRIC_SOURCE_TYPE_CONTAINER_RESOURCE.to_string(),
+            container_resource: Some(ContainerResourceMetricStatus {
+                name: resource.to_string(),
+                container: container.to_string(),
+                current,
+            }),
+            ..Default::default()
+        }
+    };
+    Proposal { replicas, metric, status }
+}
+
+fn ready_pod_count(pods: &[Pod]) -> Result<i32, String> {
+    if pods.is_empty() {
+        return Err("unable to calculate ready pods: no pods returned by selector while calculating replica count".to_string());
+    }
+    Ok(pods
+        .iter()
+        .filter(|pod| pod.status.as_ref().is_some_and(|status| status.phase == POD_PHASE_RUNNING) && pod.is_ready())
+        .count() as i32)
+}
+
+/// Proposes replicas for a metric describing the whole target: scaled by the
+/// ready pods for a value target, or divided by the per-pod target for an
+/// average value target.
+fn ratio_replicas(
+    usage: i64,
+    target: &MetricTarget,
+    spec_replicas: i32,
+    status_replicas: i32,
+    tolerances: Tolerances,
+    pods: &[Pod],
+    kind: &str,
+) -> Result<(i32, MetricValueStatus), String> {
+    if let (METRIC_TARGET_TYPE_VALUE, Some(value)) = (target.type_.as_str(), &target.value) {
+        let usage_ratio = usage as f64 / milli_value(value)? as f64;
+        let replicas = if spec_replicas == 0 {
+            usage_ratio.ceil() as i32
+        } else if tolerances.is_within(usage_ratio) {
+            spec_replicas
+        } else {
+            (usage_ratio * f64::from(ready_pod_count(pods)?)).ceil() as i32
+        };
+        Ok((replicas, MetricValueStatus { value: Some(milli_quantity(usage)), ..Default::default() }))
+    } else if let (METRIC_TARGET_TYPE_AVERAGE_VALUE, Some(average)) = (target.type_.as_str(), &target.average_value) {
+        let average = milli_value(average)? as f64;
+        let usage_ratio = usage as f64 / (average * f64::from(status_replicas));
+        let replicas = if tolerances.is_within(usage_ratio) { status_replicas } else { (usage as f64 / average).ceil() as i32 };
+        let per_pod = if status_replicas > 0 { (usage as f64 / f64::from(status_replicas)).ceil() as i64 } else { usage };
+        Ok((replicas, MetricValueStatus { average_value: Some(milli_quantity(per_pod)), ..Default::default() }))
+    } else {
+        Err(format!("invalid {} metric source: neither a value target nor an average value target was set", kind))
+    }
+}
+
+fn compute_for_object(
+    source: &ObjectMetricSource,
+    spec_replicas: i32,
+    status_replicas: i32,
+    tolerances: Tolerances,
+    snapshot: &MetricsSnapshot,
+) -> Result<Proposal, String> {
+    let object = &source.described_object;
+    let value = snapshot
+        .object_metrics
+        .iter()
+        .find(|value| value.metric == source.metric.name && value.described_object == *object)
+        .ok_or_else(|| format!("unable to get metric {}: no metrics returned on {} {}", source.metric.name, object.kind, object.name))?;
+    let usage = milli_value(&value.value)?;
+    let (replicas, current) = ratio_replicas(usage, &source.target, spec_replicas, status_replicas, tolerances, &snapshot.pods, "object")?;
+    Ok(Proposal {
+        replicas,
+        metric: format!("{} metric {}", object.kind, source.metric.name),
+        status: MetricStatus {
+            type_: METRIC_SOURCE_TYPE_OBJECT.to_string(),
+            object: Some(ObjectMetricStatus { metric: source.metric.clone(), current, described_object: object.clone() }),
+            ..Default::default()
+        },
+    })
+}
+
+fn compute_for_external(
+    source: &ExternalMetricSource,
+    spec_replicas: i32,
+    status_replicas: i32,
+    tolerances: Tolerances,
+    snapshot: &MetricsSnapshot,
+) -> Result<Proposal, String> {
+    let name = &source.metric.name;
+    let values = snapshot
+        .external_metrics
+        .get(name)
+        .filter(|values| !values.is_empty())
+        .ok_or_else(|| format!("unable to get external metric {}: no metrics returned from external metrics API", name))?;
+    let usage = values.iter().map(milli_value).sum::<Result<i64, String>>()?;
+    let (replicas, current) = ratio_replicas(usage, &source.target, spec_replicas, status_replicas, tolerances, &snapshot.pods, "external")?;
+    Ok(Proposal {
+        replicas,
+        metric: format!("external metric {}", name),
+        status: MetricStatus {
+            type_: METRIC_SOURCE_TYPE_EXTERNAL.to_string(),
+            external: Some(ExternalMetricStatus { metric: source.metric.clone(), current }),
+            ..Default::default()
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::{Container, PodCondition, PodSpec, PodStatus, ResourceRequirements};
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    fn pod(name: &str, ready: bool) -> Pod {
+        Pod {
+            metadata: ObjectMeta::named(name),
+            spec: Some(PodSpec {
+                containers: vec![Container {
+                    name: "app".to_string(),
+                    resources: Some(ResourceRequirements {
+                        requests: [("cpu".to_string(), Quantity::new("1"))].into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            status: Some(PodStatus {
+                phase: POD_PHASE_RUNNING.to_string(),
+                start_time: Some(Time(Some(now() - Duration::hours(1)))),
+                conditions: vec![PodCondition {
+                    condition_type: POD_CONDITION_READY.to_string(),
+                    status: if ready { CONDITION_TRUE } else { "False" }.to_string(),
+                    last_transition_time: Some(Time(Some(now() - Duration::minutes(50)))),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn snapshot(usage: &[(&str, &str)], unready: &[&str]) -> MetricsSnapshot {
+        let mut snapshot = MetricsSnapshot::default();
+        for (name, cpu) in usage {
+            snapshot.pods.push(pod(name, true));
+            snapshot.resources.insert(
+                name.to_string(),
+                PodResourceMetrics {
+                    timestamp: now(),
+                    window: Duration::seconds(30),
+                    containers: [("app".to_string(), [("cpu".to_string(), Quantity::new(*cpu))].into())].into(),
+                },
+            );
+        }
+        snapshot.pods.extend(unready.iter().map(|name| pod(name, false)));
+        snapshot
+    }
+
+    fn cpu_hpa(utilization: i32, behavior: Option<HorizontalPodAutoscalerBehavior>) -> HorizontalPodAutoscaler {
+        HorizontalPodAutoscaler {
+            spec: Some(HorizontalPodAutoscalerSpec {
+                min_replicas: Some(1),
+                max_replicas: 10,
+                metrics: vec![MetricSpec {
+                    type_: METRIC_SOURCE_TYPE_RESOURCE.to_string(),
+                    resource: Some(ResourceMetricSource {
+                        name: "cpu".to_string(),
+                        target: MetricTarget {
+                            type_: METRIC_TARGET_TYPE_UTILIZATION.to_string(),
+                            average_utilization: Some(utilization),
+                            ..Default::default()
+                        },
+                    }),
+                    ..Default::default()
+                }],
+                behavior,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resource_utilization() {
+        let hpa = cpu_hpa(50, None);
+        let mut calculator = ReplicaCalculator::new();
+
+        // 900m of 1 CPU across three pods is 90% against a 50% target.
+        let recommendation = calculator.reconcile(&hpa, 3, 3, &snapshot(&[("a", "900m"), ("b", "900m"), ("c", "900m")], &[]), now()).unwrap();
+        assert_eq!(recommendation.desired_replicas, 6);
+        assert_eq!(recommendation.reason, "cpu resource utilization (percentage of request) above target");
+        let current = &recommendation.current_metrics[0].resource.as_ref().unwrap().current;
+        assert_eq!((current.average_utilization, current.average_value.as_ref().map(Quantity::as_str)), (Some(90), Some("900m")));
+
+        // Within tolerance nothing changes.
+        let recommendation = calculator.reconcile(&hpa, 6, 6, &snapshot(&[("a", "520m"), ("b", "530m")], &[]), now()).unwrap();
+        assert_eq!((recommendation.desired_replicas, recommendation.reason.as_str()), (6, ""));
+
+        // A pod without metrics counts as idle on a scale-up: 1.8 / (3 * 0.5).
+        let mut calculator = ReplicaCalculator::new();
+        let recommendation = calculator.reconcile(&hpa, 3, 3, &snapshot(&[("a", "900m"), ("b", "900m")], &["c"]), now()).unwrap();
+        assert_eq!(recommendation.desired_replicas, 4);
+
+        // A container resource metric needs the container in every pod's metrics.
+        let mut hpa = cpu_hpa(50, None);
+        let metric = &mut hpa.spec.as_mut().unwrap().metrics[0];
+        let resource = metric.resource.take().unwrap();
+        metric.type_ = METRIC_SOURCE_TYPE_CONTAINER_RESOURCE.to_string();
+        metric.container_resource =
+            Some(ContainerResourceMetricSource { name: resource.name, container: "sidecar".to_string(), target: resource.target });
+        let err = ReplicaCalculator::new().reconcile(&hpa, 3, 3, &snapshot(&[("a", "900m")], &[]), now()).unwrap_err();
+        assert!(err.contains("container sidecar not present in metrics for pod a"), "{}", err);
+    }
+
+    #[test]
+    fn test_stabilization_and_policies() {
+        let behavior = HorizontalPodAutoscalerBehavior {
+            scale_up: Some(HPAScalingRules {
+                policies: vec![HPAScalingPolicy { type_: HPA_SCALING_POLICY_PODS.to_string(), value: 1, period_seconds: 60 }],
+                ..Default::default()
+            }),
+            scale_down: Some(HPAScalingRules { stabilization_window_seconds: Some(60), ..Default::default() }),
+        };
+        let hpa = cpu_hpa(50, Some(behavior));
+        let mut calculator = ReplicaCalculator::new();
+
+        let busy = snapshot(&[("a", "1"), ("b", "1")], &[]);
+        let recommendation = calculator.reconcile(&hpa, 2, 2, &busy, now()).unwrap();
+        assert_eq!(recommendation.desired_replicas, 3);
+        let limited = recommendation.conditions.iter().find(|condition| condition.condition_type == HPA_CONDITION_SCALING_LIMITED).unwrap();
+        assert_eq!((limited.status.as_str(), limited.reason.as_str()), (CONDITION_TRUE, "ScaleUpLimit"));
+        // One pod per minute: the next scale-up has to wait.
+        let busy = snapshot(&[("a", "1"), ("b", "1"), ("c", "1")], &[]);
+        assert_eq!(calculator.reconcile(&hpa, 3, 3, &busy, now() + Duration::seconds(30)).unwrap().desired_replicas, 3);
+
+        // Scale-down waits for the higher recommendations to leave the window.
+        let idle = snapshot(&[("a", "100m"), ("b", "100m"), ("c", "100m")], &[]);
+        let recommendation = calculator.reconcile(&hpa, 3, 3, &idle, now() + Duration::seconds(45)).unwrap();
+        assert_eq!(recommendation.desired_replicas, 3);
+        let able = recommendation.conditions.iter().find(|condition| condition.condition_type == HPA_CONDITION_ABLE_TO_SCALE).unwrap();
+        assert_eq!(able.reason, "ScaleDownStabilized");
+        // The reason still reports what the metrics proposed.
+        assert_eq!(recommendation.reason, "All metrics below target");
+        let recommendation = calculator.reconcile(&hpa, 3, 3, &idle, now() + Duration::seconds(120)).unwrap();
+        assert_eq!((recommendation.desired_replicas, recommendation.reason.as_str()), (1, "All metrics below target"));
+    }
+
+    #[test]
+    fn test_object_and_external_metrics() {
+        let target = |type_: &str, value: &str| MetricTarget {
+            type_: type_.to_string(),
+            value: (type_ == METRIC_TARGET_TYPE_VALUE).then(|| Quantity::new(value)),
+            average_value: (type_ == METRIC_TARGET_TYPE_AVERAGE_VALUE).then(|| Quantity::new(value)),
+            ..Default::default()
+        };
+        let ingress = CrossVersionObjectReference { kind: "Ingress".to_string(), name: "main".to_string(), ..Default::default() };
+        let hpa = HorizontalPodAutoscaler {
+            spec: Some(HorizontalPodAutoscalerSpec {
+                max_replicas: 20,
+                metrics: vec![
+                    MetricSpec {
+                        type_: METRIC_SOURCE_TYPE_OBJECT.to_string(),
+                        object: Some(ObjectMetricSource {
+                            described_object: ingress.clone(),
+                            target: target(METRIC_TARGET_TYPE_VALUE, "100"),
+                            metric: MetricIdentifier { name: "requests".to_string(), selector: None },
+                        }),
+                        ..Default::default()
+                    },
+                    MetricSpec {
+                        type_: METRIC_SOURCE_TYPE_EXTERNAL.to_string(),
+                        external: Some(ExternalMetricSource {
+                            metric: MetricIdentifier { name: "queue".to_string(), selector: None },
+                            target: target(METRIC_TARGET_TYPE_AVERAGE_VALUE, "10"),
+                        }),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut snapshot = snapshot(&[("a", "1"), ("b", "1")], &[]);
+        snapshot.object_metrics.push(ObjectMetricValue { described_object: ingress, metric: "requests".to_string(), value: Quantity::new("150") });
+        snapshot.external_metrics.insert("queue".to_string(), vec![Quantity::new("25"), Quantity::new("20")]);
+
+        // The object proposes 2 * 1.5 = 3 pods and the queue ceil(45 / 10) = 5.
+        let recommendation = ReplicaCalculator::new().reconcile(&hpa, 2, 2, &snapshot, now()).unwrap();
+        assert_eq!((recommendation.desired_replicas, recommendation.reason.as_str()), (4, "external metric queue above target"));
+        let current = &recommendation.current_metrics[1].external.as_ref().unwrap().current;
+        assert_eq!(current.average_value.as_ref().map(Quantity::as_str), Some("22500m"));
+
+        snapshot.external_metrics.clear();
+        let err = ReplicaCalculator::new().reconcile(&hpa, 4, 4, &snapshot, now()).unwrap_err();
+        assert!(err.starts_with("invalid metrics (1 invalid out of 2), first error is:"), "{}", err);
+    }
+}
@@ -0,0 +1,615 @@
+//! CronJob schedules
+//!
+//! Parsing of CronJob schedules with the standard five-field syntax and the
+//! `@hourly`/`@every` style descriptors accepted by `robfig/cron`, which the
+//! CronJob controller uses, together with the controller's schedule time
+//! arithmetic from `cronjob/utils.go` and the schedule and time zone checks
+//! of CronJob validation.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+use super::*;
+
+pub const CRON_JOB_REASON_TOO_MANY_MISSED_TIMES: &str = "TooManyMissedTimes";
+pub const CRON_JOB_REASON_MISS_SCHEDULE: &str = "MissSchedule";
+/// Warning recorded when more than 100 start times were missed.
+pub const CRON_JOB_TOO_MANY_MISSED_TIMES_MESSAGE: &str =
+    "too many missed start times. Set or decrease .spec.startingDeadlineSeconds or check clock skew";
+
+const STAR_BIT: u64 = 1 << 63;
+/// How far `next` and `prev` search before giving up, as `robfig/cron` does.
+const SEARCH_YEARS: i32 = 5;
+
+struct Bounds {
+    min: u32,
+    max: u32,
+    names: &'static [(&'static str, u32)],
+}
+
+const MINUTES: Bounds = Bounds { min: 0, max: 59, names: &[] };
+const HOURS: Bounds = Bounds { min: 0, max: 23, names: &[] };
+const DOM: Bounds = Bounds { min: 1, max: 31, names: &[] };
+const MONTHS: Bounds = Bounds {
+    min: 1,
+    max: 12,
+    names: &[
+        ("jan", 1),
+        ("feb", 2),
+        ("mar", 3),
+        ("apr", 4),
+        ("may", 5),
+        ("jun", 6),
+        ("jul", 7),
+        ("aug", 8),
+        ("sep", 9),
+        ("oct", 10),
+        ("nov", 11),
+        ("dec", 12),
+    ],
+};
+const DOW: Bounds = Bounds {
+    min: 0,
+    max: 6,
+    names: &[("sun", 0), ("mon", 1), ("tue", 2), ("wed", 3), ("thu", 4), ("fri", 5), ("sat", 6)],
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ScheduleKind {
+    Spec { minute: u64, hour: u64, dom: u64, month: u64, dow: u64 },
+    /// `@every <duration>`, rounded down to whole seconds and at least one.
+    Every(Duration),
+}
+
+/// A parsed cron schedule, evaluated in a time zone (UTC unless the schedule
+/// starts with `TZ=` or `CRON_TZ=`, or one is set with `with_time_zone`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    kind: ScheduleKind,
+    time_zone: Tz,
+}
+
+impl CronSchedule {
+    /// Parses a schedule the way `cron.ParseStandard` does.
+    pub fn parse(schedule: &str) -> Result<Self, String> {
+        if schedule.is_empty() {
+            return Err("empty spec string".to_string());
+        }
+        let mut spec = schedule;
+        let mut time_zone = Tz::UTC;
+        if spec.starts_with("TZ=") || spec.starts_with("CRON_TZ=") {
+            let end = spec.find(' ').unwrap_or(spec.len());
+            let name = &spec[spec.find('=').unwrap_or_default() + 1..end];
+            time_zone = name
+                .parse()
+                .map_err(|_| format!("provided bad location {}: unknown time zone {}", name, name))?;
+            spec = spec[end..].trim();
+        }
+        if spec.starts_with('@') {
+            return Ok(Self { kind: parse_descriptor(spec)?, time_zone });
+        }
+
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("expected exactly 5 fields, found {}: [{}]", fields.len(), fields.join(" ")));
+        }
+        let kind = ScheduleKind::Spec {
+            minute: parse_field(fields[0], &MINUTES)?,
+            hour: parse_field(fields[1], &HOURS)?,
+            dom: parse_field(fields[2], &DOM)?,
+            month: parse_field(fields[3], &MONTHS)?,
+            dow: parse_field(fields[4], &DOW)?,
+        };
+        Ok(Self { kind, time_zone })
+    }
+
+    /// Returns the schedule evaluated in `time_zone` instead.
+    pub fn with_time_zone(self, time_zone: Tz) -> Self {
+        Self { time_zone, ..self }
+    }
+
+    pub fn time_zone(&self) -> Tz {
+        self.time_zone
+    }
+
+    /// Returns the first activation strictly after `after`, or `None` if the
+    /// schedule does not fire within five years.
+    ///
+    /// Local times skipped by a daylight saving transition never fire, and
+    /// local times repeated by one fire once, at their first occurrence.
+    pub fn next(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let (minute, hour, dom, month, dow) = match &self.kind {
+            ScheduleKind::Every(delay) => {
+                return Some(after + *delay - Duration::nanoseconds(i64::from(after.nanosecond())));
+            }
+            ScheduleKind::Spec { minute, hour, dom, month, dow } => (*minute, *hour, *dom, *month, *dow),
+        };
+        let start = truncate_to_minute(after.with_timezone(&self.time_zone).naive_local()) + Duration::minutes(1);
+        let mut date = start.date();
+        while date.year() <= start.year() + SEARCH_YEARS {
+            if has(month, date.month()) && day_matches(dom, dow, date) {
+                for h in (0..24).filter(|h| has(hour, *h)) {
+                    for m in (0..60).filter(|m| has(minute, *m)) {
+                        let local = date.and_hms_opt(h, m, 0)?;
+                        if local < start {
+                            continue;
+                        }
+                        if let Some(time) = self.time_zone.from_local_datetime(&local).earliest() {
+                            if time.with_timezone(&Utc) > after {
+                                return Some(time.with_timezone(&Utc));
+                            }
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    /// Returns the last activation strictly before `before`, or `None` if the
+    /// schedule did not fire within five years. For `@every` schedules this is
+    /// one delay earlier.
+    pub fn prev(&self, before: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let (minute, hour, dom, month, dow) = match &self.kind {
+            ScheduleKind::Every(delay) => {
+                return Some(before - *delay - Duration::nanoseconds(i64::from(before.nanosecond())));
+            }
+            ScheduleKind::Spec { minute, hour, dom, month, dow } => (*minute, *hour, *dom, *month, *dow),
+        };
+        let local = before.with_timezone(&self.time_zone).naive_local();
+        let end = match truncate_to_minute(local) {
+            end if end == local => end - Duration::minutes(1),
+            end => end,
+        };
+        let mut date = end.date();
+        while date.year() >= end.year() - SEARCH_YEARS {
+            if has(month, date.month()) && day_matches(dom, dow, date) {
+                for h in (0..24).rev().filter(|h| has(hour, *h)) {
+                    for m in (0..60).rev().filter(|m| has(minute, *m)) {
+                        let local = date.and_hms_opt(h, m, 0)?;
+                        if local > end {
+                            continue;
+                        }
+                        if let Some(time) = self.time_zone.from_local_datetime(&local).earliest() {
+                            if time.with_timezone(&Utc) < before {
+                                return Some(time.with_timezone(&Utc));
+                            }
+                        }
+                    }
+                }
+            }
+            date = date.pred_opt()?;
+        }
+        None
+    }
+}
+
+fn truncate_to_minute(time: NaiveDateTime) -> NaiveDateTime {
+    time.with_second(0).and_then(|time| time.with_nanosecond(0)).unwrap_or(time)
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// Day of month and day of week are both required to match when either is
+/// `*`, and either is enough otherwise.
+fn day_matches(dom: u64, dow: u64, date: NaiveDate) -> bool {
+    let dom_match = has(dom, date.day());
+    let dow_match = has(dow, date.weekday().num_days_from_sunday());
+    if dom & STAR_BIT != 0 || dow & STAR_BIT != 0 {
+        dom_match && dow_match
+    } else {
+        dom_match || dow_match
+    }
+}
+
+fn all(bounds: &Bounds) -> u64 {
+    bits(bounds.min, bounds.max, 1) | STAR_BIT
+}
+
+fn bits(min: u32, max: u32, step: u32) -> u64 {
+    (min..=max).step_by(step as usize).fold(0, |bits, value| bits | 1 << value)
+}
+
+fn parse_descriptor(descriptor: &str) -> Result<ScheduleKind, String> {
+    let spec = |minute, hour, dom, month, dow| ScheduleKind::Spec { minute, hour, dom, month, dow };
+    match descriptor {
+        "@yearly" | "@annually" => Ok(spec(1, 1, 1 << 1, 1 << 1, all(&DOW))),
+        "@monthly" => Ok(spec(1, 1, 1 << 1, all(&MONTHS), all(&DOW))),
+        "@weekly" => Ok(spec(1, 1, all(&DOM), all(&MONTHS), 1)),
+        "@daily" | "@midnight" => Ok(spec(1, 1, all(&DOM), all(&MONTHS), all(&DOW))),
+        "@hourly" => Ok(spec(1, all(&HOURS), all(&DOM), all(&MONTHS), all(&DOW))),
+        _ => {
+            let Some(duration) = descriptor.strip_prefix("@every ") else {
+                return Err(format!("unrecognized descriptor: {}", descriptor));
+            };
+            let nanos = parse_go_duration(duration)
+                .map_err(|err| format!("failed to parse duration {}: {}", descriptor, err))?;
+            Ok(ScheduleKind::Every(Duration::seconds((nanos / 1_000_000_000).max(1))))
+        }
+    }
+}
+
+fn parse_field(field: &str, bounds: &Bounds) -> Result<u64, String> {
+    field
+        .split(',')
+        .filter(|expr| !expr.is_empty())
+        .try_fold(0, |bits, expr| Ok(bits | parse_range(expr, bounds)?))
+}
+
+fn parse_range(expr: &str, bounds: &Bounds) -> Result<u64, String> {
+    let range_and_step: Vec<&str> = expr.split('/').collect();
+    let low_and_high: Vec<&str> = range_and_step[0].split('-').collect();
+    let (start, mut end, mut extra) = if low_and_high[0] == "*" || low_and_high[0] == "?" {
+        (bounds.min, bounds.max, STAR_BIT)
+    } else {
+        let start = parse_int_or_name(low_and_high[0], bounds)?;
+        let end = match low_and_high.len() {
+            1 => start,
+            2 => parse_int_or_name(low_and_high[1], bounds)?,
+            _ => return Err(format!("too many hyphens: {}", expr)),
+        };
+        (start, end, 0)
+    };
+    let step = match range_and_step.len() {
+        1 => 1,
+        2 => {
+            let step = parse_int(range_and_step[1])?;
+            // "N/step" means "N-max/step".
+            if low_and_high.len() == 1 {
+                end = bounds.max;
+            }
+            if step > 1 {
+                extra = 0;
+            }
+            step
+        }
+        _ => return Err(format!("too many slashes: {}", expr)),
+    };
+    if start < bounds.min {
+        return Err(format!("beginning of range ({}) below minimum ({}): {}", start, bounds.min, expr));
+    }
+    if end > bounds.max {
+        return Err(format!("end of range ({}) above maximum ({}): {}", end, bounds.max, expr));
+    }
+    if start > end {
+        return Err(format!("beginning of range ({}) beyond end of range ({}): {}", start, end, expr));
+    }
+    if step == 0 {
+        return Err(format!("step of range should be a positive number: {}", expr));
+    }
+    Ok(bits(start, end, step) | extra)
+}
+
+fn parse_int_or_name(expr: &str, bounds: &Bounds) -> Result<u32, String> {
+    let lower = expr.to_lowercase();
+    match bounds.names.iter().find(|(name, _)| *name == lower) {
+        Some((_, value)) => Ok(*value),
+        None => parse_int(expr),
+    }
+}
+
+fn parse_int(expr: &str) -> Result<u32, String> {
+    let num: i64 = expr
+        .parse()
+        .map_err(|_| format!("failed to parse int from {}: strconv.Atoi: parsing {:?}: invalid syntax", expr, expr))?;
+    if num < 0 {
+        return Err(format!("negative number ({}) not allowed: {}", num, expr));
+    }
+    // Anything this large is out of every field's bounds anyway.
+    Ok(num.min(i64::from(u32::MAX)) as u32)
+}
+
+/// Parses a duration with Go's `time.ParseDuration` syntax, such as `1h30m`
+/// or `1.5s`, into nanoseconds.
+fn parse_go_duration(duration: &str) -> Result<i64, String> {
+    let invalid = || format!("time: invalid duration {:?}", duration);
+    let (negative, mut rest) = match duration.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, duration.strip_prefix('+').unwrap_or(duration)),
+    };
+    if rest == "0" {
+        return Ok(0);
+    }
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut total: u128 = 0;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (whole, after) = rest.split_at(digits);
+        let (fraction, after) = match after.strip_prefix('.') {
+            Some(after) => after.split_at(after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len())),
+            None => ("", after),
+        };
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        let unit_len = after.find(|c: char| c == '.' || c.is_ascii_digit()).unwrap_or(after.len());
+        if unit_len == 0 {
+            return Err(format!("time: missing unit in duration {:?}", duration));
+        }
+        let (unit, after) = after.split_at(unit_len);
+        let unit: u128 = match unit {
+            "ns" => 1,
+            "us" | "µs" | "μs" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60_000_000_000,
+            "h" => 3_600_000_000_000,
+            _ => return Err(format!("time: unknown unit {:?} in duration {:?}", unit, duration)),
+        };
+        let whole: u128 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid())? };
+        let fraction = match fraction.get(..18.min(fraction.len())) {
+            Some(digits) if !digits.is_empty() => {
+                digits.parse::<u128>().map_err(|_| invalid())? * unit / 10u128.pow(digits.len() as u32)
+            }
+            _ => 0,
+        };
+        total = whole.checked_mul(unit).and_then(|value| value.checked_add(fraction)).and_then(|value| total.checked_add(value)).ok_or_else(invalid)?;
+        rest = after;
+    }
+    let total = i64::try_from(total).map_err(|_| invalid())?;
+    Ok(if negative { -total } else { total })
+}
+
+/// How many start times a CronJob missed since it was last scheduled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissedSchedules {
+    None,
+    Few,
+    /// More than 100, which the controller warns about with
+    /// `CRON_JOB_TOO_MANY_MISSED_TIMES_MESSAGE`.
+    Many,
+}
+
+/// The result of looking for the most recent time a CronJob should have run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MostRecentSchedule {
+    /// The last schedule time, or the creation time, moved forward to the
+    /// starting deadline when requested.
+    pub earliest: DateTime<Utc>,
+    /// The most recent activation at or before now, if any.
+    pub most_recent: Option<DateTime<Utc>>,
+    pub missed: MissedSchedules,
+}
+
+impl CronJobSpec {
+    /// Validates `schedule` and `timeZone` as CronJob creation does; `TZ=`
+    /// and `CRON_TZ=` prefixes are rejected in favour of `timeZone`.
+    pub fn validate_schedule(&self) -> Result<(), Vec<String>> {
+        let invalid = |field: &str, value: &str, message: &str| format!("spec.{}: Invalid value: {:?}: {}", field, value, message);
+        let mut errors = Vec::new();
+        if self.schedule.is_empty() {
+            errors.push("spec.schedule: Required value".to_string());
+        } else {
+            if let Err(err) = CronSchedule::parse(&self.schedule) {
+                errors.push(invalid("schedule", &self.schedule, &err));
+            }
+            if self.schedule.contains("TZ") {
+                errors.push(invalid(
+                    "schedule",
+                    &self.schedule,
+                    "cannot use TZ or CRON_TZ in schedule, use timeZone field instead",
+                ));
+            }
+        }
+        if let Some(time_zone) = &self.time_zone {
+            if time_zone.is_empty() {
+                errors.push(invalid("timeZone", time_zone, "timeZone must be nil or non-empty string"));
+            } else if time_zone.split('/').any(|part| !is_valid_time_zone_part(part)) {
+                errors.push(invalid("timeZone", time_zone, &format!("unknown time zone {}", time_zone)));
+            } else {
+                if time_zone.eq_ignore_ascii_case("Local") {
+                    errors.push(invalid(
+                        "timeZone",
+                        time_zone,
+                        "timeZone must be an explicit time zone as defined in https://www.iana.org/time-zones",
+                    ));
+                }
+                if time_zone != "Local" && time_zone.parse::<Tz>().is_err() {
+                    errors.push(invalid("timeZone", time_zone, &format!("unknown time zone {}", time_zone)));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn is_valid_time_zone_part(part: &str) -> bool {
+    part != "."
+        && part != ".."
+        && !part.starts_with('-')
+        && (1..=14).contains(&part.len())
+        && part.bytes().all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'-' | b'_' | b'+'))
+}
+
+impl CronJob {
+    /// Parses the schedule in the CronJob's time zone. A schedule with its
+    /// own `TZ=` prefix keeps it, and an unknown `timeZone` is ignored, as
+    /// the controller does; without either the schedule runs in UTC.
+    pub fn schedule(&self) -> Result<CronSchedule, String> {
+        let spec = self.spec.as_ref().ok_or_else(|| "CronJob has no spec".to_string())?;
+        let schedule = CronSchedule::parse(&spec.schedule)?;
+        if spec.schedule.contains("TZ") {
+            return Ok(schedule);
+        }
+        match spec.time_zone.as_deref().map(str::parse::<Tz>) {
+            Some(Ok(time_zone)) => Ok(schedule.with_time_zone(time_zone)),
+            _ => Ok(schedule),
+        }
+    }
+
+    /// Finds the most recent time the CronJob should have started a Job at or
+    /// before `now`, and how many start times were missed since the last one.
+    /// With `include_starting_deadline`, times older than
+    /// `startingDeadlineSeconds` are not considered.
+    ///
+    /// Fails for schedules that never fire, such as `0 0 31 2 *`.
+    pub fn most_recent_schedule_time(
+        &self,
+        schedule: &CronSchedule,
+        now: DateTime<Utc>,
+        include_starting_deadline: bool,
+    ) -> Result<MostRecentSchedule, String> {
+        let spec = self.spec.as_ref();
+        let mut earliest = self
+            .status
+            .as_ref()
+            .and_then(|status| status.last_schedule_time.as_ref())
+            .and_then(|time| time.0)
+            .or(self.metadata.creation_timestamp.0)
+            .unwrap_or(now);
+        if let Some(deadline) = spec.and_then(|spec| spec.starting_deadline_seconds).filter(|_| include_starting_deadline) {
+            // A deadline reaching past the representable times is unbounded.
+            if let Some(start) = Duration::try_seconds(deadline).and_then(|deadline| now.checked_sub_signed(deadline)) {
+                earliest = earliest.max(start);
+            }
+        }
+        let mut result = MostRecentSchedule { earliest, most_recent: None, missed: MissedSchedules::None };
+
+        let invalid = || "time difference between two schedules is less than 1 second".to_string();
+        let first = schedule.next(earliest);
+        if first.is_some_and(|first| now < first) {
+            return Ok(result);
+        }
+        let first = first.ok_or_else(invalid)?;
+        let second = schedule.next(first);
+        if second.is_some_and(|second| now < second) {
+            result.most_recent = Some(first);
+            return Ok(result);
+        }
+        let interval = second.map(|second| (second - first).num_seconds()).filter(|interval| *interval >= 1).ok_or_else(invalid)?;
+
+        // The number of missed schedules is approximated from the interval
+        // between the first two; the most recent time is then found by
+        // stepping through the schedule from just before the estimate.
+        let missed = (now - first).num_seconds() / interval + 1;
+        let mut time = schedule.next(first + Duration::seconds((missed - 2) * interval));
+        while let Some(next) = time.filter(|time| *time <= now) {
+            result.most_recent = Some(next);
+            time = schedule.next(next);
+        }
+        result.missed = if missed > 100 { MissedSchedules::Many } else { MissedSchedules::Few };
+        Ok(result)
+    }
+
+    /// Returns the next time the CronJob will start a Job after `now`.
+    pub fn next_schedule_time(&self, schedule: &CronSchedule, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let recent = self.most_recent_schedule_time(schedule, now, false).ok()?;
+        schedule.next(recent.most_recent.unwrap_or(recent.earliest).max(now))
+    }
+
+    /// Returns the warning the controller records when `scheduled` can no
+    /// longer start because `startingDeadlineSeconds` passed.
+    pub fn missed_schedule_warning(&self, scheduled: DateTime<Utc>, now: DateTime<Utc>) -> Option<String> {
+        let deadline = self.spec.as_ref()?.starting_deadline_seconds?;
+        let expires = Duration::try_seconds(deadline).and_then(|deadline| scheduled.checked_add_signed(deadline))?;
+        (expires < now).then(|| {
+            format!(
+                "Missed scheduled time to start a job: {}",
+                scheduled.format("%a, %d %b %Y %H:%M:%S %z")
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("", "empty spec string"),
+            ("* * *", "expected exactly 5 fields, found 3: [* * *]"),
+            ("60 * * * *", "end of range (60) above maximum (59): 60"),
+            ("5-1 * * * *", "beginning of range (5) beyond end of range (1): 5-1"),
+            ("*/0 * * * *", "step of range should be a positive number: */0"),
+            ("* * * foo *", "failed to parse int from foo: strconv.Atoi: parsing \"foo\": invalid syntax"),
+            ("@weekdays", "unrecognized descriptor: @weekdays"),
+            ("@every 1d", "failed to parse duration @every 1d: time: unknown unit \"d\" in duration \"1d\""),
+        ];
+        for (schedule, expected) in cases {
+            assert_eq!(CronSchedule::parse(schedule).unwrap_err(), expected, "{}", schedule);
+        }
+        assert!(CronSchedule::parse("0 9-17/2 * JAN-mar mon,fri").is_ok());
+
+        let spec = CronJobSpec {
+            schedule: "TZ=UTC 0 * * * *".to_string(),
+            time_zone: Some("Local".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            spec.validate_schedule().unwrap_err(),
+            vec![
+                "spec.schedule: Invalid value: \"TZ=UTC 0 * * * *\": cannot use TZ or CRON_TZ in schedule, use timeZone field instead",
+                "spec.timeZone: Invalid value: \"Local\": timeZone must be an explicit time zone as defined in https://www.iana.org/time-zones",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_next_and_prev() {
+        let schedule = CronSchedule::parse("30 2 * * *").unwrap();
+        assert_eq!(schedule.next(time("2024-03-01T02:30:00Z")), Some(time("2024-03-02T02:30:00Z")));
+        assert_eq!(schedule.prev(time("2024-03-01T02:30:00Z")), Some(time("2024-02-29T02:30:00Z")));
+
+        // Day of month and day of week match either way unless one is `*`.
+        let schedule = CronSchedule::parse("0 0 13 * 5").unwrap();
+        assert_eq!(schedule.next(time("2024-09-01T00:00:00Z")), Some(time("2024-09-06T00:00:00Z")));
+        assert_eq!(CronSchedule::parse("0 0 31 2 *").unwrap().next(time("2024-01-01T00:00:00Z")), None);
+
+        // 02:30 does not exist in New York on the day clocks spring forward.
+        let schedule = CronSchedule::parse("CRON_TZ=America/New_York 30 2 * * *").unwrap();
+        assert_eq!(schedule.next(time("2024-03-10T00:00:00Z")), Some(time("2024-03-11T06:30:00Z")));
+
+        let schedule = CronSchedule::parse("@every 1h30m").unwrap();
+        assert_eq!(schedule.next(time("2024-03-01T00:00:00.5Z")), Some(time("2024-03-01T01:30:00Z")));
+    }
+
+    #[test]
+    fn test_most_recent_schedule_time() {
+        let mut cron_job = CronJob::new("hourly");
+        cron_job.spec = Some(CronJobSpec { schedule: "0 * * * *".to_string(), ..Default::default() });
+        cron_job.metadata.creation_timestamp.0 = Some(time("2024-03-01T00:00:00Z"));
+        let schedule = cron_job.schedule().unwrap();
+
+        let recent = cron_job.most_recent_schedule_time(&schedule, time("2024-03-01T00:30:00Z"), false).unwrap();
+        assert_eq!((recent.most_recent, recent.missed), (None, MissedSchedules::None));
+
+        let recent = cron_job.most_recent_schedule_time(&schedule, time("2024-03-01T05:10:00Z"), false).unwrap();
+        assert_eq!((recent.most_recent, recent.missed), (Some(time("2024-03-01T05:00:00Z")), MissedSchedules::Few));
+        assert_eq!(cron_job.next_schedule_time(&schedule, time("2024-03-01T05:10:00Z")), Some(time("2024-03-01T06:00:00Z")));
+
+        let now = time("2024-03-10T00:10:00Z");
+        let recent = cron_job.most_recent_schedule_time(&schedule, now, false).unwrap();
+        assert_eq!((recent.most_recent, recent.missed), (Some(time("2024-03-10T00:00:00Z")), MissedSchedules::Many));
+
+        cron_job.spec.as_mut().unwrap().starting_deadline_seconds = Some(900);
+        let recent = cron_job.most_recent_schedule_time(&schedule, now, true).unwrap();
+        assert_eq!((recent.most_recent, recent.missed), (Some(time("2024-03-10T00:00:00Z")), MissedSchedules::None));
+        assert_eq!(cron_job.missed_schedule_warning(time("2024-03-10T00:00:00Z"), now), None);
+        assert_eq!(
+            cron_job.missed_schedule_warning(time("2024-03-09T23:00:00Z"), now).as_deref(),
+            Some("Missed scheduled time to start a job: Sat, 09 Mar 2024 23:00:00 +0000")
+        );
+
+        // Deadlines too large to add to a time never expire.
+        cron_job.spec.as_mut().unwrap().starting_deadline_seconds = Some(i64::MAX);
+        let recent = cron_job.most_recent_schedule_time(&schedule, now, true).unwrap();
+        assert_eq!(recent.earliest, time("2024-03-01T00:00:00Z"));
+        assert_eq!((recent.most_recent, recent.missed), (Some(time("2024-03-10T00:00:00Z")), MissedSchedules::Many));
+        assert_eq!(cron_job.missed_schedule_warning(time("2024-03-09T23:00:00Z"), now), None);
+    }
+}
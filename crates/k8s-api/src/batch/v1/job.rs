@@ -0,0 +1,694 @@
+//! Job pod failure and success policy evaluation
+//!
+//! How the Job controller classifies failed pods against a pod failure
+//! policy, tracks completed and failed indexes as compressed interval
+//! strings, and decides when a Job has met its success criteria or failed,
+//! following `pod_failure_policy.go`, `indexed_job_utils.go`,
+//! `success_policy.go` and `job_controller.go`.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use k8s_apimachinery::apis::meta::v1::Time;
+
+use crate::core::v1::{
+    ContainerStatus, Pod, PodStatus, CONDITION_TRUE, POD_PHASE_FAILED, POD_PHASE_PENDING, POD_PHASE_RUNNING,
+    POD_PHASE_SUCCEEDED, RESTART_POLICY_ON_FAILURE,
+};
+
+use super::*;
+
+/// Finalizer the Job controller adds to its pods until it has counted them.
+pub const JOB_TRACKING_FINALIZER: &str = "batch.kubernetes.io/job-tracking";
+/// Annotation holding the completion index of a pod of an Indexed Job.
+pub const JOB_COMPLETION_INDEX_ANNOTATION: &str = "batch.kubernetes.io/job-completion-index";
+/// Annotation holding how many pods failed before for the same index.
+pub const JOB_INDEX_FAILURE_COUNT_ANNOTATION: &str = "batch.kubernetes.io/job-index-failure-count";
+
+pub const JOB_REASON_POD_FAILURE_POLICY: &str = "PodFailurePolicy";
+pub const JOB_REASON_BACKOFF_LIMIT_EXCEEDED: &str = "BackoffLimitExceeded";
+pub const JOB_REASON_DEADLINE_EXCEEDED: &str = "DeadlineExceeded";
+pub const JOB_REASON_MAX_FAILED_INDEXES_EXCEEDED: &str = "MaxFailedIndexesExceeded";
+pub const JOB_REASON_FAILED_INDEXES: &str = "FailedIndexes";
+pub const JOB_REASON_SUCCESS_POLICY: &str = "SuccessPolicy";
+pub const JOB_REASON_COMPLETIONS_REACHED: &str = "CompletionsReached";
+
+const DEFAULT_BACKOFF_LIMIT: i32 = 6;
+
+/// A closed range of completion indexes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexInterval {
+    pub first: i32,
+    pub last: i32,
+}
+
+/// Sorted, non-overlapping completion index intervals, formatted the way
+/// `completedIndexes` and `failedIndexes` store them, e.g. `1,3-5,7`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OrderedIntervals(pub Vec<IndexInterval>);
+
+impl OrderedIntervals {
+    /// Parses a compressed index string, keeping only indexes below
+    /// `completions`. Malformed intervals are skipped, as the controller does.
+    pub fn parse(indexes: &str, completions: i32) -> Self {
+        let mut result: Vec<IndexInterval> = Vec::new();
+        for interval in indexes.split(',') {
+            let mut limits = interval.split('-');
+            let Some(Ok(first)) = limits.next().map(str::parse::<i32>) else {
+                continue;
+            };
+            if first >= completions {
+                break;
+            }
+            let last = match limits.next() {
+                Some(last) => match last.parse::<i32>() {
+                    Ok(last) => last.min(completions - 1),
+                    Err(_) => continue,
+                },
+                None => first,
+            };
+            match result.last_mut() {
+                Some(previous) if previous.last == first - 1 => previous.last = last,
+                _ => result.push(IndexInterval { first, last }),
+            }
+        }
+        Self(result)
+    }
+
+    /// Returns the union of these intervals and `other`.
+    pub fn merge(&self, other: &OrderedIntervals) -> Self {
+        let mut result: Vec<IndexInterval> = Vec::new();
+        let mut push = |interval: IndexInterval| match result.last_mut() {
+            Some(previous) if interval.first <= previous.last + 1 => previous.last = previous.last.max(interval.last),
+            _ => result.push(interval),
+        };
+        let (mut i, mut j) = (0, 0);
+        while i < self.0.len() && j < other.0.len() {
+            if self.0[i].first < other.0[j].first {
+                push(self.0[i]);
+                i += 1;
+            } else {
+                push(other.0[j]);
+                j += 1;
+            }
+        }
+        self.0[i..].iter().chain(&other.0[j..]).for_each(|interval| push(*interval));
+        Self(result)
+    }
+
+    /// Returns these intervals with `indexes` added.
+    pub fn with_indexes(&self, indexes: impl IntoIterator<Item = i32>) -> Self {
+        let indexes: BTreeSet<i32> = indexes.into_iter().collect();
+        self.merge(&Self(indexes.into_iter().map(|index| IndexInterval { first: index, last: index }).collect()))
+    }
+
+    pub fn has(&self, index: i32) -> bool {
+        self.0.iter().any(|interval| interval.first <= index && index <= interval.last)
+    }
+
+    /// Returns the number of indexes in the intervals.
+    pub fn total(&self) -> i32 {
+        self.0.iter().map(|interval| interval.last - interval.first + 1).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for OrderedIntervals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, interval) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", interval.first)?;
+            if interval.last > interval.first {
+                let separator = if interval.last == interval.first + 1 { ',' } else { '-' };
+                write!(f, "{}{}", separator, interval.last)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of matching a failed pod against a pod failure policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PodFailurePolicyMatch {
+    /// The action of the first matching rule, if any.
+    pub action: Option<PodFailurePolicyAction>,
+    /// Whether the failure counts towards the backoff limit.
+    pub count_failed: bool,
+    /// The Job failure message, set for `FailJob` matches.
+    pub message: Option<String>,
+}
+
+impl PodFailurePolicyMatch {
+    fn new(action: &str, count_failed: bool, message: Option<String>) -> Self {
+        Self { action: Some(action.to_string()), count_failed, message }
+    }
+}
+
+/// Matches a failed pod against the rules of a pod failure policy; the first
+/// matching rule wins. Exit code rules look at terminated containers with a
+/// non-zero exit code, regular containers first, then init containers.
+pub fn match_pod_failure_policy(policy: Option<&PodFailurePolicy>, pod: &Pod) -> PodFailurePolicyMatch {
+    let unmatched = PodFailurePolicyMatch { action: None, count_failed: true, message: None };
+    let (Some(policy), Some(status)) = (policy, pod.status.as_ref()) else {
+        return unmatched;
+    };
+    for (index, rule) in policy.rules.iter().enumerate() {
+        let matched = if let Some(requirement) = &rule.on_exit_codes {
+            match_on_exit_codes(status, requirement).map(|container| {
+                let exit_code = container.state.as_ref().and_then(|state| state.terminated.as_ref()).map(|terminated| terminated.exit_code);
+                format!(
+                    "Container {} for pod {}/{} failed with exit code {} matching {} rule at index {}",
+                    container.name,
+                    pod.metadata.namespace,
+                    pod.metadata.name,
+                    exit_code.unwrap_or_default(),
+                    rule.action,
+                    index
+                )
+            })
+        } else {
+            match_on_pod_conditions(status, &rule.on_pod_conditions).map(|condition_type| {
+                format!(
+                    "Pod {}/{} has condition {} matching {} rule at index {}",
+                    pod.metadata.namespace, pod.metadata.name, condition_type, rule.action, index
+                )
+            })
+        };
+        let Some(message) = matched else {
+            continue;
+        };
+        match rule.action.as_str() {
+            POD_FAILURE_POLICY_ACTION_IGNORE => return PodFailurePolicyMatch::new(&rule.action, false, None),
+            POD_FAILURE_POLICY_ACTION_FAIL_INDEX | POD_FAILURE_POLICY_ACTION_COUNT => {
+                return PodFailurePolicyMatch::new(&rule.action, true, None)
+            }
+            POD_FAILURE_POLICY_ACTION_FAIL_JOB => return PodFailurePolicyMatch::new(&rule.action, true, Some(message)),
+            _ => {}
+        }
+    }
+    unmatched
+}
+
+fn match_on_exit_codes<'a>(
+    status: &'a PodStatus,
+    requirement: &PodFailurePolicyOnExitCodesRequirement,
+) -> Option<&'a ContainerStatus> {
+    status.container_statuses.iter().chain(&status.init_container_statuses).find(|container| {
+        let Some(terminated) = container.state.as_ref().and_then(|state| state.terminated.as_ref()) else {
+            return false;
+        };
+        if requirement.container_name.as_ref().is_some_and(|name| *name != container.name) || terminated.exit_code == 0 {
+            return false;
+        }
+        match requirement.operator.as_str() {
+            POD_FAILURE_POLICY_ON_EXIT_CODES_OP_IN => requirement.values.contains(&terminated.exit_code),
+            POD_FAILURE_POLICY_ON_EXIT_CODES_OP_NOT_IN => !requirement.values.contains(&terminated.exit_code),
+            _ => false,
+        }
+    })
+}
+
+/// A pattern without a status matches `True`, the value the API server
+/// defaults it to.
+fn match_on_pod_conditions<'a>(
+    status: &'a PodStatus,
+    patterns: &[PodFailurePolicyOnPodConditionsPattern],
+) -> Option<&'a str> {
+    status
+        .conditions
+        .iter()
+        .find(|condition| {
+            patterns.iter().any(|pattern| {
+                let pattern_status = if pattern.status.is_empty() { CONDITION_TRUE } else { pattern.status.as_str() };
+                condition.condition_type == pattern.condition_type && condition.status == pattern_status
+            })
+        })
+        .map(|condition| condition.condition_type.as_str())
+}
+
+/// Matches the succeeded indexes of an Indexed Job against a success policy,
+/// returning the `SuccessCriteriaMet` message of the first matching rule.
+pub fn match_success_policy(
+    policy: Option<&SuccessPolicy>,
+    completions: i32,
+    succeeded_indexes: &OrderedIntervals,
+) -> Option<String> {
+    let policy = policy?;
+    if succeeded_indexes.is_empty() {
+        return None;
+    }
+    policy
+        .rules
+        .iter()
+        .position(|rule| match &rule.succeeded_indexes {
+            Some(indexes) => {
+                let required = OrderedIntervals::parse(indexes, completions);
+                !required.is_empty() && match_succeeded_indexes_rule(&required, succeeded_indexes, rule.succeeded_count)
+            }
+            None => rule.succeeded_count.is_some_and(|count| succeeded_indexes.total() >= count),
+        })
+        .map(|index| format!("Matched rules at index {}", index))
+}
+
+fn match_succeeded_indexes_rule(
+    required: &OrderedIntervals,
+    succeeded: &OrderedIntervals,
+    succeeded_count: Option<i32>,
+) -> bool {
+    let (mut contains, mut r, mut s) = (0, 0, 0);
+    while r < required.0.len() && s < succeeded.0.len() {
+        let (rule, done) = (required.0[r], succeeded.0[s]);
+        contains += (rule.last.min(done.last) - rule.first.max(done.first) + 1).max(0);
+        if done.last <= rule.last {
+            s += 1;
+        }
+        if done.last >= rule.last {
+            r += 1;
+        }
+    }
+    contains == required.total() || succeeded_count.is_some_and(|count| contains >= count)
+}
+
+/// Returns the completion index of a pod of an Indexed Job, if it has a valid
+/// one below `completions`.
+pub fn pod_completion_index(pod: &Pod, completions: i32) -> Option<i32> {
+    let index = pod.metadata.annotations.get(JOB_COMPLETION_INDEX_ANNOTATION)?.parse::<i32>().ok()?;
+    (0..completions).contains(&index).then_some(index)
+}
+
+/// Returns how many pods failed before this one for the same index.
+pub fn pod_index_failure_count(pod: &Pod) -> i32 {
+    pod.metadata
+        .annotations
+        .get(JOB_INDEX_FAILURE_COUNT_ANNOTATION)
+        .and_then(|count| count.parse::<i32>().ok())
+        .filter(|count| *count >= 0)
+        .unwrap_or_default()
+}
+
+fn pod_phase(pod: &Pod) -> &str {
+    pod.status.as_ref().map(|status| status.phase.as_str()).unwrap_or_default()
+}
+
+/// Pod counts, completed and failed indexes and the finished condition of a
+/// Job, as the Job controller would compute them from its pods.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JobEvaluation {
+    pub active: i32,
+    pub ready: i32,
+    pub terminating: i32,
+    pub succeeded: i32,
+    pub failed: i32,
+    pub completed_indexes: Option<String>,
+    pub failed_indexes: Option<String>,
+    /// `SuccessCriteriaMet` or `FailureTarget` while pods are still running,
+    /// terminating or waiting to be counted, then `Complete` or `Failed`.
+    pub condition: Option<JobCondition>,
+}
+
+impl Job {
+    pub fn is_indexed(&self) -> bool {
+        self.spec.as_ref().and_then(|spec| spec.completion_mode.as_deref()) == Some(COMPLETION_MODE_INDEXED)
+    }
+
+    pub fn get_condition(&self, condition_type: &str) -> Option<&JobCondition> {
+        self.status.as_ref()?.conditions.iter().find(|condition| condition.condition_type == condition_type)
+    }
+
+    fn has_condition(&self, condition_type: &str) -> bool {
+        self.get_condition(condition_type).is_some_and(|condition| condition.status == CONDITION_TRUE)
+    }
+
+    /// Whether the Job has a `Complete` or `Failed` condition.
+    pub fn is_finished(&self) -> bool {
+        self.has_condition(JOB_CONDITION_COMPLETE) || self.has_condition(JOB_CONDITION_FAILED)
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.has_condition(JOB_CONDITION_SUSPENDED)
+    }
+
+    /// The backoff limit, defaulting to 6, or unlimited when the Job has a
+    /// per-index backoff limit.
+    pub fn backoff_limit(&self) -> i32 {
+        let spec = self.spec.as_ref();
+        match spec.and_then(|spec| spec.backoff_limit) {
+            Some(limit) => limit,
+            None if spec.is_some_and(|spec| spec.backoff_limit_per_index.is_some()) => i32::MAX,
+            None => DEFAULT_BACKOFF_LIMIT,
+        }
+    }
+
+    /// Whether failed pods are only replaced once fully terminated, in which
+    /// case terminating pods are not counted as failed.
+    fn only_replace_failed_pods(&self) -> bool {
+        self.spec.as_ref().is_some_and(|spec| {
+            spec.pod_replacement_policy.as_deref() == Some(POD_REPLACEMENT_POLICY_FAILED) || spec.pod_failure_policy.is_some()
+        })
+    }
+
+    /// Whether the controller counts a pod as failed; deleted pods that never
+    /// reach a terminal phase count unless only failed pods are replaced.
+    pub fn is_pod_failed(&self, pod: &Pod) -> bool {
+        match pod_phase(pod) {
+            POD_PHASE_FAILED => true,
+            POD_PHASE_SUCCEEDED => false,
+            _ => !self.only_replace_failed_pods() && pod.metadata.deletion_timestamp.is_some(),
+        }
+    }
+
+    /// Classifies a failed pod against the Job's pod failure policy.
+    pub fn match_pod_failure_policy(&self, pod: &Pod) -> PodFailurePolicyMatch {
+        match_pod_failure_policy(self.spec.as_ref().and_then(|spec| spec.pod_failure_policy.as_ref()), pod)
+    }
+
+    /// Whether a failed pod fails its index: its failure matches a `FailIndex`
+    /// rule or exhausts the per-index backoff limit.
+    fn is_index_failed(&self, pod: &Pod) -> bool {
+        let Some(limit) = self.spec.as_ref().and_then(|spec| spec.backoff_limit_per_index) else {
+            return false;
+        };
+        if !self.is_pod_failed(pod) {
+            return false;
+        }
+        let matched = self.match_pod_failure_policy(pod);
+        if matched.action.as_deref() == Some(POD_FAILURE_POLICY_ACTION_FAIL_INDEX) {
+            return true;
+        }
+        matched.count_failed && pod_index_failure_count(pod) >= limit
+    }
+
+    /// Whether containers of running pods restarted more often than the
+    /// backoff limit allows, for pods restarted in place.
+    fn past_backoff_limit_on_failure(&self, pods: &[Pod]) -> bool {
+        let Some(spec) = self.spec.as_ref() else {
+            return false;
+        };
+        if spec.template.spec.as_ref().map(|spec| spec.restart_policy.as_str()) != Some(RESTART_POLICY_ON_FAILURE) {
+            return false;
+        }
+        let restarts: i32 = pods
+            .iter()
+            .filter(|pod| matches!(pod_phase(pod), POD_PHASE_RUNNING | POD_PHASE_PENDING))
+            .filter_map(|pod| pod.status.as_ref())
+            .flat_map(|status| status.init_container_statuses.iter().chain(&status.container_statuses))
+            .map(|container| container.restart_count)
+            .sum();
+        match self.backoff_limit() {
+            0 => restarts > 0,
+            limit => restarts >= limit,
+        }
+    }
+
+    fn past_active_deadline(&self, now: DateTime<Utc>) -> bool {
+        let deadline = self.spec.as_ref().and_then(|spec| spec.active_deadline_seconds);
+        let start = self.status.as_ref().and_then(|status| status.start_time.as_ref()).and_then(|time| time.0);
+        match (deadline, start) {
+            (Some(deadline), Some(start)) if !self.is_suspended() => (now - start).num_seconds() >= deadline,
+            _ => false,
+        }
+    }
+
+    /// Evaluates the Job against its pods: counts them, tracks completed and
+    /// failed indexes, and determines the condition the Job has reached.
+    ///
+    /// Terminated pods still holding the tracking finalizer are counted on top
+    /// of the status counts; pods without it are assumed to be counted
+    /// already. A Job that is already finished keeps its condition.
+    pub fn evaluate(&self, pods: &[Pod], now: DateTime<Utc>) -> JobEvaluation {
+        let default_spec = JobSpec::default();
+        let default_status = JobStatus::default();
+        let spec = self.spec.as_ref().unwrap_or(&default_spec);
+        let status = self.status.as_ref().unwrap_or(&default_status);
+        let (uncounted_succeeded, uncounted_failed) = status
+            .uncounted_terminated_pods
+            .as_ref()
+            .map(|uncounted| (uncounted.succeeded.as_slice(), uncounted.failed.as_slice()))
+            .unwrap_or_default();
+        let uncounted = |pod: &Pod, uids: &[String]| {
+            pod.metadata.finalizers.iter().any(|finalizer| finalizer == JOB_TRACKING_FINALIZER) && !uids.contains(&pod.metadata.uid)
+        };
+
+        let mut evaluation = JobEvaluation::default();
+        let mut newly_finished = 0;
+        for pod in pods {
+            let phase = pod_phase(pod);
+            let terminal = phase == POD_PHASE_SUCCEEDED || phase == POD_PHASE_FAILED;
+            if !terminal && pod.metadata.deletion_timestamp.is_some() {
+                evaluation.terminating += 1;
+            } else if !terminal {
+                evaluation.active += 1;
+                evaluation.ready += i32::from(pod.is_ready());
+            }
+            if phase == POD_PHASE_SUCCEEDED && uncounted(pod, uncounted_succeeded) {
+                evaluation.succeeded += 1;
+                newly_finished += 1;
+            } else if self.is_pod_failed(pod) && uncounted(pod, uncounted_failed) {
+                evaluation.failed += i32::from(self.match_pod_failure_policy(pod).count_failed);
+                newly_finished += 1;
+            }
+        }
+        evaluation.succeeded += status.succeeded.unwrap_or_default() + uncounted_succeeded.len() as i32;
+        evaluation.failed += status.failed.unwrap_or_default() + uncounted_failed.len() as i32;
+
+        let fail_job_message = pods
+            .iter()
+            .filter(|pod| self.is_pod_failed(pod))
+            .find_map(|pod| self.match_pod_failure_policy(pod).message);
+        let mut finished = if let Some(condition) = [JOB_CONDITION_COMPLETE, JOB_CONDITION_FAILED]
+            .into_iter()
+            .find_map(|condition_type| self.get_condition(condition_type).filter(|condition| condition.status == CONDITION_TRUE))
+        {
+            Some(condition.clone())
+        } else if let Some(condition) = self.get_condition(JOB_CONDITION_FAILURE_TARGET).or_else(|| self.get_condition(JOB_CONDITION_SUCCESS_CRITERIA_MET)) {
+            Some(condition.clone())
+        } else if let Some(message) = fail_job_message {
+            Some(new_condition(JOB_CONDITION_FAILURE_TARGET, JOB_REASON_POD_FAILURE_POLICY, &message, now))
+        } else if evaluation.failed > self.backoff_limit() || self.past_backoff_limit_on_failure(pods) {
+            Some(new_condition(
+                JOB_CONDITION_FAILURE_TARGET,
+                JOB_REASON_BACKOFF_LIMIT_EXCEEDED,
+                "Job has reached the specified backoff limit",
+                now,
+            ))
+        } else if self.past_active_deadline(now) {
+            Some(new_condition(
+                JOB_CONDITION_FAILURE_TARGET,
+                JOB_REASON_DEADLINE_EXCEEDED,
+                "Job was active longer than specified deadline",
+                now,
+            ))
+        } else {
+            None
+        };
+
+        if let (true, Some(completions)) = (self.is_indexed(), spec.completions) {
+            let index_of = |pod: &&Pod| pod_completion_index(pod, completions);
+            let succeeded_indexes = OrderedIntervals::parse(status.completed_indexes.as_deref().unwrap_or_default(), completions)
+                .with_indexes(pods.iter().filter(|pod| pod_phase(pod) == POD_PHASE_SUCCEEDED).filter_map(|pod| index_of(&pod)));
+            evaluation.succeeded = succeeded_indexes.total();
+            evaluation.completed_indexes = Some(succeeded_indexes.to_string());
+            if spec.backoff_limit_per_index.is_some() {
+                let failed_indexes = OrderedIntervals::parse(status.failed_indexes.as_deref().unwrap_or_default(), completions)
+                    .with_indexes(
+                        pods.iter()
+                            .filter(|pod| self.is_index_failed(pod))
+                            .filter_map(|pod| index_of(&pod))
+                            .filter(|index| !succeeded_indexes.has(*index)),
+                    );
+                if finished.is_none() {
+                    if spec.max_failed_indexes.is_some_and(|max| failed_indexes.total() > max) {
+                        finished = Some(new_condition(
+                            JOB_CONDITION_FAILURE_TARGET,
+                            JOB_REASON_MAX_FAILED_INDEXES_EXCEEDED,
+                            "Job has exceeded the specified maximal number of failed indexes",
+                            now,
+                        ));
+                    } else if !failed_indexes.is_empty() && failed_indexes.total() + succeeded_indexes.total() >= completions {
+                        finished = Some(new_condition(JOB_CONDITION_FAILURE_TARGET, JOB_REASON_FAILED_INDEXES, "Job has failed indexes", now));
+                    }
+                }
+                evaluation.failed_indexes = Some(failed_indexes.to_string());
+            }
+            if finished.is_none() {
+                if let Some(message) = match_success_policy(spec.success_policy.as_ref(), completions, &succeeded_indexes) {
+                    finished = Some(new_condition(JOB_CONDITION_SUCCESS_CRITERIA_MET, JOB_REASON_SUCCESS_POLICY, &message, now));
+                }
+            }
+        }
+
+        let complete = match spec.completions {
+            None => evaluation.succeeded > 0 && evaluation.active == 0,
+            Some(completions) => evaluation.succeeded >= completions && evaluation.active == 0,
+        };
+        if finished.is_none() && complete {
+            finished = Some(new_condition(
+                JOB_CONDITION_SUCCESS_CRITERIA_MET,
+                JOB_REASON_COMPLETIONS_REACHED,
+                "Reached expected number of succeeded pods",
+                now,
+            ));
+        }
+
+        let settled = evaluation.active == 0
+            && evaluation.terminating == 0
+            && newly_finished == 0
+            && uncounted_succeeded.is_empty()
+            && uncounted_failed.is_empty();
+        if let Some(condition) = finished.as_mut().filter(|_| settled) {
+            let final_type = match condition.condition_type.as_str() {
+                JOB_CONDITION_FAILURE_TARGET => Some(JOB_CONDITION_FAILED),
+                JOB_CONDITION_SUCCESS_CRITERIA_MET => Some(JOB_CONDITION_COMPLETE),
+                _ => None,
+            };
+            if let Some(final_type) = final_type {
+                *condition = new_condition(final_type, &condition.reason, &condition.message, now);
+            }
+        }
+        evaluation.condition = finished;
+        evaluation
+    }
+}
+
+fn new_condition(condition_type: &str, reason: &str, message: &str, now: DateTime<Utc>) -> JobCondition {
+    JobCondition {
+        condition_type: condition_type.to_string(),
+        status: CONDITION_TRUE.to_string(),
+        last_probe_time: Some(Time(Some(now))),
+        last_transition_time: Some(Time(Some(now))),
+        reason: reason.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::{ContainerState, ContainerStateTerminated, PodCondition, PodStatus};
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+
+    fn failed_pod(name: &str, exit_code: i32) -> Pod {
+        Pod {
+            metadata: ObjectMeta::namespaced("default", name),
+            status: Some(PodStatus {
+                phase: POD_PHASE_FAILED.to_string(),
+                container_statuses: vec![ContainerStatus {
+                    name: "main".to_string(),
+                    state: Some(ContainerState {
+                        terminated: Some(ContainerStateTerminated { exit_code, ..Default::default() }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                conditions: vec![PodCondition {
+                    condition_type: "DisruptionTarget".to_string(),
+                    status: CONDITION_TRUE.to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn indexed_pod(index: i32, phase: &str) -> Pod {
+        let mut pod = Pod::default();
+        pod.metadata.annotations.insert(JOB_COMPLETION_INDEX_ANNOTATION.to_string(), index.to_string());
+        pod.status = Some(PodStatus { phase: phase.to_string(), ..Default::default() });
+        pod
+    }
+
+    #[test]
+    fn test_ordered_intervals() {
+        let intervals = OrderedIntervals::parse("0,2-3,5-9,x,12", 8);
+        assert_eq!(intervals.to_string(), "0,2,3,5-7");
+        assert_eq!(intervals.total(), 6);
+        assert!(intervals.has(6) && !intervals.has(4));
+        assert_eq!(intervals.with_indexes([4, 1]).to_string(), "0-7");
+        assert_eq!(OrderedIntervals::parse("", 3).to_string(), "");
+    }
+
+    #[test]
+    fn test_match_pod_failure_policy() {
+        let policy = PodFailurePolicy {
+            rules: vec![
+                PodFailurePolicyRule {
+                    action: POD_FAILURE_POLICY_ACTION_FAIL_JOB.to_string(),
+                    on_exit_codes: Some(PodFailurePolicyOnExitCodesRequirement {
+                        container_name: Some("main".to_string()),
+                        operator: POD_FAILURE_POLICY_ON_EXIT_CODES_OP_IN.to_string(),
+                        values: vec![42],
+                    }),
+                    ..Default::default()
+                },
+                PodFailurePolicyRule {
+                    action: POD_FAILURE_POLICY_ACTION_IGNORE.to_string(),
+                    on_pod_conditions: vec![PodFailurePolicyOnPodConditionsPattern {
+                        condition_type: "DisruptionTarget".to_string(),
+                        status: String::new(),
+                    }],
+                    ..Default::default()
+                },
+            ],
+        };
+        let matched = match_pod_failure_policy(Some(&policy), &failed_pod("x", 42));
+        assert_eq!(matched.action.as_deref(), Some(POD_FAILURE_POLICY_ACTION_FAIL_JOB));
+        assert_eq!(
+            matched.message.as_deref(),
+            Some("Container main for pod default/x failed with exit code 42 matching FailJob rule at index 0")
+        );
+        let matched = match_pod_failure_policy(Some(&policy), &failed_pod("x", 1));
+        assert_eq!((matched.action.as_deref(), matched.count_failed), (Some(POD_FAILURE_POLICY_ACTION_IGNORE), false));
+        assert!(match_pod_failure_policy(None, &failed_pod("x", 1)).count_failed);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let now = Utc::now();
+        let mut job = Job::new("pi");
+        job.spec = Some(JobSpec {
+            completions: Some(5),
+            completion_mode: Some(COMPLETION_MODE_INDEXED.to_string()),
+            success_policy: Some(SuccessPolicy {
+                rules: vec![SuccessPolicyRule { succeeded_indexes: Some("0,2-4".to_string()), succeeded_count: Some(3) }],
+            }),
+            ..Default::default()
+        });
+        job.status = Some(JobStatus { completed_indexes: Some("0".to_string()), ..Default::default() });
+
+        let pods = vec![indexed_pod(2, POD_PHASE_SUCCEEDED), indexed_pod(1, POD_PHASE_RUNNING)];
+        let evaluation = job.evaluate(&pods, now);
+        assert_eq!((evaluation.active, evaluation.succeeded), (1, 2));
+        assert_eq!(evaluation.completed_indexes.as_deref(), Some("0,2"));
+        assert!(evaluation.condition.is_none());
+
+        let pods = vec![indexed_pod(2, POD_PHASE_SUCCEEDED), indexed_pod(3, POD_PHASE_SUCCEEDED), indexed_pod(1, POD_PHASE_RUNNING)];
+        let condition = job.evaluate(&pods, now).condition.unwrap();
+        assert_eq!(condition.condition_type, JOB_CONDITION_SUCCESS_CRITERIA_MET);
+        assert_eq!((condition.reason.as_str(), condition.message.as_str()), (JOB_REASON_SUCCESS_POLICY, "Matched rules at index 0"));
+
+        job.status.as_mut().unwrap().conditions.push(condition);
+        let condition = job.evaluate(&pods[..2], now).condition.unwrap();
+        assert_eq!((condition.condition_type.as_str(), condition.reason.as_str()), (JOB_CONDITION_COMPLETE, JOB_REASON_SUCCESS_POLICY));
+
+        let mut job = Job::new("pi");
+        job.spec = Some(JobSpec { backoff_limit: Some(1), ..Default::default() });
+        let pods = vec![failed_pod("a", 1), failed_pod("b", 1)]
+            .into_iter()
+            .map(|mut pod| {
+                pod.metadata.finalizers.push(JOB_TRACKING_FINALIZER.to_string());
+                pod
+            })
+            .collect::<Vec<_>>();
+        let evaluation = job.evaluate(&pods, now);
+        assert_eq!(evaluation.failed, 2);
+        let condition = evaluation.condition.unwrap();
+        assert_eq!((condition.condition_type.as_str(), condition.reason.as_str()), (JOB_CONDITION_FAILURE_TARGET, JOB_REASON_BACKOFF_LIMIT_EXCEEDED));
+    }
+}
@@ -2,8 +2,12 @@
 
 mod types;
 mod internal_conversion;
+mod job;
+mod cron;
 
 pub use types::*;
+pub use job::*;
+pub use cron::*;
 
 pub trait InternalConversion: Sized + serde::Serialize + serde::de::DeserializeOwned {
     type Internal: serde::Serialize + serde::de::DeserializeOwned;
@@ -0,0 +1,1768 @@
+//! Deep hashing of pod templates
+//!
+//! Writes objects the way `hashutil.DeepHashObject` prints them before
+//! hashing: go-spew's `%#v` with sorted map keys and methods disabled, i.e.
+//! every field of the Go struct in declaration order with its Go type. The
+//! `pod-template-hash` and DaemonSet revision hashes are taken over this
+//! text, so a template hashes as in kube-controller-manager only if it is
+//! written here exactly as Go lays it out.
+//!
+//! Objects must be hashed as the API server returns them, with defaults
+//! applied and quantities in canonical form. Go fields the Rust types lack
+//! are written as their zero value, and timestamps as UTC.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use k8s_api_core::resource::{IntOrString, Quantity};
+use k8s_apimachinery::apis::meta::v1::{
+    LabelSelector, LabelSelectorRequirement, ManagedFieldsEntry, ObjectMeta, OwnerReference, Time,
+};
+
+use super::*;
+
+/// A value that can be written as go-spew's `%#v` prints its Go
+/// counterpart.
+pub trait DeepHash {
+    /// Writes the value, without its type.
+    fn deep_hash(&self, w: &mut DeepHashWriter);
+
+    /// Returns the text `DeepHashObject` hashes for the value.
+    fn deep_hash_string(&self) -> String {
+        let mut w = DeepHashWriter::default();
+        self.deep_hash(&mut w);
+        w.out
+    }
+}
+
+/// Accumulates spew output. Like spew, it shows the type of each value
+/// unless the value is a slice element, map entry or pointee.
+#[derive(Debug, Default)]
+pub struct DeepHashWriter {
+    out: String,
+    ignore_next_type: bool,
+}
+
+/// Writes the fields of a struct.
+pub struct DeepHashFields<'a> {
+    w: &'a mut DeepHashWriter,
+    first: bool,
+}
+
+impl DeepHashFields<'_> {
+    /// Starts the next field, returning the writer for its value.
+    pub fn field(&mut self, name: &str) -> &mut DeepHashWriter {
+        if !std::mem::take(&mut self.first) {
+            self.w.out.push(' ');
+        }
+        self.w.out.push_str(name);
+        self.w.out.push(':');
+        &mut *self.w
+    }
+}
+
+impl DeepHashWriter {
+    fn type_name(&mut self, type_name: &str) {
+        if !std::mem::take(&mut self.ignore_next_type) {
+            self.out.push('(');
+            self.out.push_str(type_name);
+            self.out.push(')');
+        }
+    }
+
+    fn scalar(&mut self, type_name: &str, value: impl Display) {
+        self.type_name(type_name);
+        self.out.push_str(&value.to_string());
+    }
+
+    /// Writes the fields of a struct between braces.
+    pub fn structure(&mut self, f: impl FnOnce(&mut DeepHashFields<'_>)) {
+        self.out.push('{');
+        f(&mut DeepHashFields { w: self, first: true });
+        self.out.push('}');
+    }
+
+    /// Writes a string, unquoted.
+    pub fn string(&mut self, type_name: &str, value: &str) {
+        self.scalar(type_name, value);
+    }
+
+    pub fn int(&mut self, type_name: &str, value: impl Into<i64>) {
+        self.scalar(type_name, value.into());
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.scalar("bool", value);
+    }
+
+    pub fn value<T: DeepHash + ?Sized>(&mut self, type_name: &str, value: &T) {
+        self.type_name(type_name);
+        value.deep_hash(self);
+    }
+
+    /// Writes a pointer to a `type_name` value.
+    pub fn ptr<T: ?Sized>(&mut self, type_name: &str, value: Option<&T>, f: impl FnOnce(&mut Self, &T)) {
+        let ignore_type = std::mem::take(&mut self.ignore_next_type);
+        match value {
+            // spew leaves the flag set after a nil element.
+            None if ignore_type => {
+                self.out.push_str("<nil>");
+                self.ignore_next_type = true;
+            }
+            None => self.out.push_str(&format!("(*{})<nil>", type_name)),
+            Some(value) => {
+                if ignore_type {
+                    self.out.push_str("<*>");
+                } else {
+                    self.out.push_str(&format!("(*{})", type_name));
+                }
+                self.ignore_next_type = true;
+                f(self, value);
+            }
+        }
+    }
+
+    pub fn opt_string(&mut self, type_name: &str, value: Option<&String>) {
+        self.ptr(type_name, value, |w, value| w.string(type_name, value));
+    }
+
+    pub fn opt_int<T: Into<i64> + Copy>(&mut self, type_name: &str, value: Option<T>) {
+        self.ptr(type_name, value.as_ref(), |w, value| w.int(type_name, *value));
+    }
+
+    pub fn opt_bool(&mut self, value: Option<bool>) {
+        self.ptr("bool", value.as_ref(), |w, value| w.bool(*value));
+    }
+
+    pub fn opt_value<T: DeepHash>(&mut self, type_name: &str, value: Option<&T>) {
+        self.ptr(type_name, value, |w, value| w.value(type_name, value));
+    }
+
+    /// Writes a slice of `elem_type`. Empty slices are written as nil, as
+    /// they decode in Go.
+    pub fn slice<T>(&mut self, elem_type: &str, values: &[T], f: impl Fn(&mut Self, &T)) {
+        self.type_name(&format!("[]{}", elem_type));
+        if values.is_empty() {
+            self.out.push_str("<nil>");
+            return;
+        }
+        self.out.push('[');
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                self.out.push(' ');
+            }
+            self.ignore_next_type = true;
+            f(self, value);
+        }
+        self.out.push(']');
+    }
+
+    pub fn values<T: DeepHash>(&mut self, elem_type: &str, values: &[T]) {
+        self.slice(elem_type, values, |w, value| w.value(elem_type, value));
+    }
+
+    pub fn strings(&mut self, elem_type: &str, values: &[String]) {
+        self.slice(elem_type, values, |w, value| w.string(elem_type, value));
+    }
+
+    /// Writes a map with string keys, in key order.
+    pub fn map<V>(&mut self, type_name: &str, map: &BTreeMap<String, V>, f: impl Fn(&mut Self, &V)) {
+        self.type_name(type_name);
+        if map.is_empty() {
+            self.out.push_str("<nil>");
+            return;
+        }
+        self.out.push_str("map[");
+        for (i, (key, value)) in map.iter().enumerate() {
+            if i > 0 {
+                self.out.push(' ');
+            }
+            self.out.push_str(key);
+            self.out.push(':');
+            self.ignore_next_type = true;
+            f(self, value);
+        }
+        self.out.push(']');
+    }
+
+    pub fn string_map(&mut self, map: &BTreeMap<String, String>) {
+        self.map("map[string]string", map, |w, value| w.string("string", value));
+    }
+
+    /// Writes the `LocalObjectReference` embedded in config map and secret
+    /// references.
+    fn local_object_reference(&mut self, name: &str) {
+        self.type_name("v1.LocalObjectReference");
+        self.structure(|s| s.field("Name").string("string", name));
+    }
+
+    pub fn resource_list(&mut self, list: &ResourceList) {
+        self.map("v1.ResourceList", list, |w, quantity| w.value("resource.Quantity", quantity));
+    }
+}
+
+// =============================================================================
+// Quantity
+// =============================================================================
+
+/// The amount of a `resource.Quantity`: `int64Amount` when it fits, else
+/// the magnitude and scale of an `inf.Dec`.
+enum Amount {
+    Int { value: i64, scale: i32 },
+    Dec { negative: bool, unscaled: u128, scale: i32 },
+}
+
+/// A quantity as `resource.ParseQuantity` builds it.
+struct ParsedQuantity<'a> {
+    amount: Amount,
+    /// The string cached when it was already canonical.
+    s: &'a str,
+    format: &'static str,
+}
+
+/// Returns (base, exponent, format) of a suffix, as the Go suffixer
+/// interprets it.
+fn interpret_suffix(suffix: &str) -> Option<(u32, i32, &'static str)> {
+    let decimal = |exponent| Some((10, exponent, "DecimalSI"));
+    let binary = |exponent| Some((2, exponent, "BinarySI"));
+    match suffix {
+        "n" => decimal(-9),
+        "u" => decimal(-6),
+        "m" => decimal(-3),
+        "" => decimal(0),
+        "k" => decimal(3),
+        "M" => decimal(6),
+        "G" => decimal(9),
+        "T" => decimal(12),
+        "P" => decimal(15),
+        "E" => decimal(18),
+        "Ki" => binary(10),
+        "Mi" => binary(20),
+        "Gi" => binary(30),
+        "Ti" => binary(40),
+        "Pi" => binary(50),
+        "Ei" => binary(60),
+        _ if suffix.len() > 1 && (suffix.starts_with('e') || suffix.starts_with('E')) => {
+            Some((10, suffix[1..].parse().ok()?, "DecimalExponent"))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a quantity through the fast path of `ParseQuantity`, falling back
+/// to its `inf.Dec` path for fractions of binary quantities, more than 18
+/// digits and values finer than nano units.
+fn parse_quantity(s: &str) -> Option<ParsedQuantity<'_>> {
+    if s == "0" {
+        return Some(ParsedQuantity {
+            amount: Amount::Int { value: 0, scale: 0 },
+            s,
+            format: "DecimalSI",
+        });
+    }
+    let (positive, unsigned) = match s.as_bytes().first()? {
+        b'-' => (false, &s[1..]),
+        b'+' => (true, &s[1..]),
+        _ => (true, s),
+    };
+    let number_len = unsigned.find(|ch: char| !ch.is_ascii_digit() && ch != '.').unwrap_or(unsigned.len());
+    let (number, suffix) = unsigned.split_at(number_len);
+    let (num, denom) = number.split_once('.').unwrap_or((number, ""));
+    if denom.contains('.') {
+        return None;
+    }
+    let num = match num.trim_start_matches('0') {
+        "" => "0",
+        num => num,
+    };
+    let (base, exponent, format) = interpret_suffix(suffix)?;
+
+    let mut mantissa: i64 = 1;
+    let mut scale = 0;
+    let precision = if base == 10 {
+        scale = exponent;
+        18 - (num.len() + denom.len()) as i32
+    } else if exponent >= 0 && denom.is_empty() {
+        mantissa <<= exponent;
+        15 - num.len() as i32 - exponent * 3 / 10 - 1
+    } else {
+        -1
+    };
+    if precision >= 0 {
+        scale -= denom.len() as i32;
+        if scale >= -9 {
+            let shifted = format!("{}{}", num, denom);
+            let value: i64 = shifted.parse().ok()?;
+            if let Some(result) = value.checked_mul(mantissa) {
+                let canonical = if base == 2 {
+                    exponent % 10 == 0 && value & 0x07 != 0
+                } else {
+                    scale % 3 == 0 && !shifted.ends_with("000") && !shifted.starts_with('0')
+                };
+                return Some(ParsedQuantity {
+                    amount: Amount::Int {
+                        value: if positive { result } else { -result },
+                        scale,
+                    },
+                    s: if canonical { s } else { "" },
+                    format,
+                });
+            }
+        }
+    }
+
+    // inf.Dec path: the digits, scaled by the suffix, rounded up to nano
+    // units.
+    let mut unscaled: u128 = format!("{}{}", num, denom).parse().ok()?;
+    let mut scale = denom.len() as i32;
+    if base == 10 {
+        scale -= exponent;
+    } else {
+        unscaled = unscaled.checked_mul(1u128.checked_shl(exponent as u32)?)?;
+    }
+    if unscaled != 0 {
+        if scale <= 9 {
+            unscaled = unscaled.checked_mul(10u128.checked_pow((9 - scale) as u32)?)?;
+        } else {
+            unscaled = unscaled.div_ceil(10u128.checked_pow((scale - 9) as u32)?);
+        }
+        scale = 9;
+    }
+    let mut format = format;
+    if format == "BinarySI" {
+        let one = 10u128.pow(scale.max(0) as u32);
+        if unscaled > (i64::MAX as u128) * one {
+            unscaled = i64::MAX as u128;
+            scale = 0;
+        } else if unscaled > 0 && unscaled < one {
+            format = "DecimalSI";
+        }
+    }
+    Some(ParsedQuantity {
+        amount: Amount::Dec {
+            negative: !positive && unscaled != 0,
+            unscaled,
+            scale,
+        },
+        s: "",
+        format,
+    })
+}
+
+impl DeepHash for Quantity {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        // The API server stores quantities in canonical form.
+        let canonical = self.canonicalize().unwrap_or_else(|_| self.clone());
+        let parsed = parse_quantity(canonical.as_str()).unwrap_or(ParsedQuantity {
+            amount: Amount::Int { value: 0, scale: 0 },
+            s: "",
+            format: "",
+        });
+        let (value, scale) = match parsed.amount {
+            Amount::Int { value, scale } => (value, scale),
+            Amount::Dec { .. } => (0, 0),
+        };
+        w.structure(|s| {
+            s.field("i").type_name("resource.int64Amount");
+            s.w.structure(|s| {
+                s.field("value").int("int64", value);
+                s.field("scale").int("resource.Scale", scale);
+            });
+            s.field("d").type_name("resource.infDecAmount");
+            s.w.structure(|s| {
+                let dec = match parsed.amount {
+                    Amount::Dec {
+                        negative,
+                        unscaled,
+                        scale,
+                    } => Some((negative, unscaled, scale)),
+                    Amount::Int { .. } => None,
+                };
+                s.field("Dec").ptr("inf.Dec", dec.as_ref(), |w, (negative, unscaled, scale)| {
+                    w.type_name("inf.Dec");
+                    w.structure(|s| {
+                        s.field("unscaled").type_name("big.Int");
+                        s.w.structure(|s| {
+                            s.field("neg").bool(*negative);
+                            let words: Vec<u64> = match (*unscaled as u64, (*unscaled >> 64) as u64) {
+                                (0, 0) => vec![],
+                                (low, 0) => vec![low],
+                                (low, high) => vec![low, high],
+                            };
+                            s.field("abs").type_name("big.nat");
+                            if words.is_empty() {
+                                s.w.out.push_str("<nil>");
+                            } else {
+                                let words: Vec<String> = words.iter().map(u64::to_string).collect();
+                                s.w.out.push_str(&format!("[{}]", words.join(" ")));
+                            }
+                        });
+                        s.field("scale").int("inf.Scale", *scale);
+                    });
+                });
+            });
+            s.field("s").string("string", parsed.s);
+            s.field("Format").string("resource.Format", parsed.format);
+        });
+    }
+}
+
+impl DeepHash for IntOrString {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        let (int_or_string, int_val, str_val) = match self {
+            IntOrString::Int(value) => (0, *value, ""),
+            IntOrString::String(value) => (1, 0, value.as_str()),
+        };
+        w.structure(|s| {
+            s.field("Type").int("intstr.Type", int_or_string);
+            s.field("IntVal").int("int32", int_val);
+            s.field("StrVal").string("string", str_val);
+        });
+    }
+}
+
+// =============================================================================
+// meta/v1
+// =============================================================================
+
+/// Seconds from January 1 of year 1 to the Unix epoch.
+const UNIX_TO_INTERNAL: i64 = 62_135_596_800;
+
+impl DeepHash for Time {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        let (wall, ext) = match self.0 {
+            Some(time) => (time.timestamp_subsec_nanos() as i64, time.timestamp() + UNIX_TO_INTERNAL),
+            None => (0, 0),
+        };
+        w.structure(|s| {
+            s.field("Time").type_name("time.Time");
+            s.w.structure(|s| {
+                s.field("wall").int("uint64", wall);
+                s.field("ext").int("int64", ext);
+                s.field("loc").ptr::<()>("time.Location", None, |_, _| {});
+            });
+        });
+    }
+}
+
+impl DeepHash for ObjectMeta {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("GenerateName").string("string", &self.generate_name);
+            s.field("Namespace").string("string", &self.namespace);
+            s.field("SelfLink").string("string", &self.self_link);
+            s.field("UID").string("types.UID", &self.uid);
+            s.field("ResourceVersion").string("string", &self.resource_version);
+            s.field("Generation").int("int64", self.generation);
+            s.field("CreationTimestamp").value("v1.Time", &self.creation_timestamp);
+            s.field("DeletionTimestamp").opt_value("v1.Time", self.deletion_timestamp.as_ref());
+            s.field("DeletionGracePeriodSeconds").opt_int("int64", self.deletion_grace_period_seconds);
+            s.field("Labels").string_map(&self.labels);
+            s.field("Annotations").string_map(&self.annotations);
+            s.field("OwnerReferences").values("v1.OwnerReference", &self.owner_references);
+            s.field("Finalizers").strings("string", &self.finalizers);
+            s.field("ManagedFields").values("v1.ManagedFieldsEntry", &self.managed_fields);
+        });
+    }
+}
+
+impl DeepHash for OwnerReference {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("APIVersion").string("string", &self.api_version);
+            s.field("Kind").string("string", &self.kind);
+            s.field("Name").string("string", &self.name);
+            s.field("UID").string("types.UID", &self.uid);
+            s.field("Controller").opt_bool(self.controller);
+            s.field("BlockOwnerDeletion").opt_bool(self.block_owner_deletion);
+        });
+    }
+}
+
+impl DeepHash for ManagedFieldsEntry {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        let raw = self.fields_v1.as_ref().map(|fields| fields.to_string().into_bytes());
+        w.structure(|s| {
+            s.field("Manager").string("string", &self.manager);
+            s.field("Operation").string("v1.ManagedFieldsOperationType", &self.operation);
+            s.field("APIVersion").string("string", &self.api_version);
+            s.field("Time").opt_value("v1.Time", self.time.as_ref());
+            s.field("FieldsType").string("string", &self.fields_type);
+            s.field("FieldsV1").ptr("v1.FieldsV1", raw.as_ref(), |w, raw| {
+                w.type_name("v1.FieldsV1");
+                w.structure(|s| {
+                    s.field("Raw").slice("uint8", raw, |w, byte| w.int("uint8", *byte));
+                });
+            });
+            s.field("Subresource").string("string", &self.subresource);
+        });
+    }
+}
+
+impl DeepHash for LabelSelector {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("MatchLabels").string_map(&self.match_labels);
+            s.field("MatchExpressions").values("v1.LabelSelectorRequirement", &self.match_expressions);
+        });
+    }
+}
+
+impl DeepHash for LabelSelectorRequirement {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Key").string("string", &self.key);
+            s.field("Operator").string("v1.LabelSelectorOperator", &self.operator);
+            s.field("Values").strings("string", &self.values);
+        });
+    }
+}
+
+// =============================================================================
+// Pod template
+// =============================================================================
+
+impl DeepHash for PodTemplateSpec {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        let default_spec = PodSpec::default();
+        w.structure(|s| {
+            s.field("ObjectMeta").value("v1.ObjectMeta", &self.metadata);
+            s.field("Spec").value("v1.PodSpec", self.spec.as_ref().unwrap_or(&default_spec));
+        });
+    }
+}
+
+impl DeepHash for PodSpec {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Volumes").values("v1.Volume", &self.volumes);
+            s.field("InitContainers").values("v1.Container", &self.init_containers);
+            s.field("Containers").values("v1.Container", &self.containers);
+            s.field("EphemeralContainers").values("v1.EphemeralContainer", &self.ephemeral_containers);
+            s.field("RestartPolicy").string("v1.RestartPolicy", &self.restart_policy);
+            s.field("TerminationGracePeriodSeconds").opt_int("int64", self.termination_grace_period_seconds);
+            s.field("ActiveDeadlineSeconds").opt_int("int64", self.active_deadline_seconds);
+            s.field("DNSPolicy").string("v1.DNSPolicy", &self.dns_policy);
+            s.field("NodeSelector").string_map(&self.node_selector);
+            s.field("ServiceAccountName").string("string", &self.service_account_name);
+            s.field("DeprecatedServiceAccount").string("string", &self.service_account);
+            s.field("AutomountServiceAccountToken").opt_bool(self.automount_service_account_token);
+            s.field("NodeName").string("string", &self.node_name);
+            s.field("HostNetwork").bool(self.host_network);
+            s.field("HostPID").bool(self.host_p_i_d);
+            s.field("HostIPC").bool(self.host_i_p_c);
+            s.field("ShareProcessNamespace").opt_bool(self.share_process_namespace);
+            s.field("SecurityContext").opt_value("v1.PodSecurityContext", self.security_context.as_ref());
+            s.field("ImagePullSecrets").values("v1.LocalObjectReference", &self.image_pull_secrets);
+            s.field("Hostname").string("string", &self.hostname);
+            s.field("Subdomain").string("string", &self.subdomain);
+            s.field("Affinity").opt_value("v1.Affinity", self.affinity.as_ref());
+            s.field("SchedulerName").string("string", &self.scheduler_name);
+            s.field("Tolerations").values("v1.Toleration", &self.tolerations);
+            s.field("HostAliases").values("v1.HostAlias", &self.host_aliases);
+            s.field("PriorityClassName").string("string", &self.priority_class_name);
+            s.field("Priority").opt_int("int32", self.priority);
+            s.field("DNSConfig").opt_value("v1.PodDNSConfig", self.dns_config.as_ref());
+            s.field("ReadinessGates").values("v1.PodReadinessGate", &self.readiness_gates);
+            s.field("RuntimeClassName").opt_string("string", self.runtime_class_name.as_ref());
+            s.field("EnableServiceLinks").opt_bool(self.enable_service_links);
+            s.field("PreemptionPolicy").opt_string("v1.PreemptionPolicy", self.preemption_policy.as_ref());
+            s.field("Overhead").resource_list(&self.overhead);
+            s.field("TopologySpreadConstraints")
+                .values("v1.TopologySpreadConstraint", &self.topology_spread_constraints);
+            s.field("SetHostnameAsFQDN").opt_bool(self.set_hostname_as_fqdn);
+            s.field("OS").opt_value("v1.PodOS", self.os.as_ref());
+            s.field("HostUsers").opt_bool(self.host_users);
+            s.field("SchedulingGates").values("v1.PodSchedulingGate", &self.scheduling_gates);
+            s.field("ResourceClaims").values("v1.PodResourceClaim", &self.resource_claims);
+            s.field("Resources").opt_value("v1.ResourceRequirements", self.resources.as_ref());
+            s.field("HostnameOverride").opt_string("string", self.hostname_override.as_ref());
+        });
+    }
+}
+
+/// Writes the fields `Container` and `EphemeralContainerCommon` share.
+macro_rules! container_fields {
+    ($s:expr, $container:expr) => {{
+        let s = $s;
+        let c = $container;
+        let default_resources = ResourceRequirements::default();
+        s.field("Name").string("string", &c.name);
+        s.field("Image").string("string", &c.image);
+        s.field("Command").strings("string", &c.command);
+        s.field("Args").strings("string", &c.args);
+        s.field("WorkingDir").string("string", &c.working_dir);
+        s.field("Ports").values("v1.ContainerPort", &c.ports);
+        s.field("EnvFrom").values("v1.EnvFromSource", &c.env_from);
+        s.field("Env").values("v1.EnvVar", &c.env);
+        s.field("Resources")
+            .value("v1.ResourceRequirements", c.resources.as_ref().unwrap_or(&default_resources));
+        s.field("ResizePolicy").values("v1.ContainerResizePolicy", &c.resize_policy);
+        s.field("RestartPolicy").opt_string("v1.ContainerRestartPolicy", c.restart_policy.as_ref());
+        s.field("RestartPolicyRules").values("v1.ContainerRestartRule", &c.restart_policy_rules);
+        s.field("VolumeMounts").values("v1.VolumeMount", &c.volume_mounts);
+        s.field("VolumeDevices").values("v1.VolumeDevice", &c.volume_devices);
+        s.field("LivenessProbe").opt_value("v1.Probe", c.liveness_probe.as_ref());
+        s.field("ReadinessProbe").opt_value("v1.Probe", c.readiness_probe.as_ref());
+        s.field("StartupProbe").opt_value("v1.Probe", c.startup_probe.as_ref());
+        s.field("Lifecycle").opt_value("v1.Lifecycle", c.lifecycle.as_ref());
+        s.field("TerminationMessagePath").string("string", &c.termination_message_path);
+        s.field("TerminationMessagePolicy").string("v1.TerminationMessagePolicy", &c.termination_message_policy);
+        s.field("ImagePullPolicy").string("v1.PullPolicy", &c.image_pull_policy);
+        s.field("SecurityContext").opt_value("v1.SecurityContext", c.security_context.as_ref());
+        s.field("Stdin").bool(c.stdin);
+        s.field("StdinOnce").bool(c.stdin_once);
+        s.field("TTY").bool(c.tty);
+    }};
+}
+
+impl DeepHash for Container {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| container_fields!(s, self));
+    }
+}
+
+impl DeepHash for EphemeralContainer {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("EphemeralContainerCommon").type_name("v1.EphemeralContainerCommon");
+            s.w.structure(|s| container_fields!(s, self));
+            s.field("TargetContainerName").string("string", &self.target_container_name);
+        });
+    }
+}
+
+impl DeepHash for ContainerPort {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("HostPort").int("int32", self.host_port.unwrap_or_default());
+            s.field("ContainerPort").int("int32", self.container_port);
+            s.field("Protocol").string("v1.Protocol", &self.protocol);
+            s.field("HostIP").string("string", &self.host_i_p);
+        });
+    }
+}
+
+impl DeepHash for EnvFromSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Prefix").string("string", &self.prefix);
+            s.field("ConfigMapRef").ptr("v1.ConfigMapEnvSource", self.config_map_ref.as_ref(), |w, source| {
+                w.type_name("v1.ConfigMapEnvSource");
+                w.structure(|s| {
+                    s.field("LocalObjectReference").local_object_reference(&source.name);
+                    s.field("Optional").opt_bool(source.optional);
+                });
+            });
+            s.field("SecretRef").ptr("v1.SecretEnvSource", self.secret_ref.as_ref(), |w, source| {
+                w.type_name("v1.SecretEnvSource");
+                w.structure(|s| {
+                    s.field("LocalObjectReference").local_object_reference(&source.name);
+                    s.field("Optional").opt_bool(source.optional);
+                });
+            });
+        });
+    }
+}
+
+impl DeepHash for EnvVar {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("Value").string("string", &self.value);
+            s.field("ValueFrom").opt_value("v1.EnvVarSource", self.value_from.as_ref());
+        });
+    }
+}
+
+impl DeepHash for EnvVarSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("FieldRef").opt_value("v1.ObjectFieldSelector", self.field_ref.as_ref());
+            s.field("ResourceFieldRef").opt_value("v1.ResourceFieldSelector", self.resource_field_ref.as_ref());
+            s.field("ConfigMapKeyRef").ptr("v1.ConfigMapKeySelector", self.config_map_key_ref.as_ref(), |w, selector| {
+                w.type_name("v1.ConfigMapKeySelector");
+                w.structure(|s| {
+                    s.field("LocalObjectReference").local_object_reference(&selector.name);
+                    s.field("Key").string("string", &selector.key);
+                    s.field("Optional").opt_bool(selector.optional);
+                });
+            });
+            s.field("SecretKeyRef").ptr("v1.SecretKeySelector", self.secret_key_ref.as_ref(), |w, selector| {
+                w.type_name("v1.SecretKeySelector");
+                w.structure(|s| {
+                    s.field("LocalObjectReference").local_object_reference(&selector.name);
+                    s.field("Key").string("string", &selector.key);
+                    s.field("Optional").opt_bool(selector.optional);
+                });
+            });
+            s.field("FileKeyRef").opt_value("v1.FileKeySelector", self.file_key_ref.as_ref());
+        });
+    }
+}
+
+impl DeepHash for LocalObjectReference {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| s.field("Name").string("string", &self.name));
+    }
+}
+
+impl DeepHash for ObjectFieldSelector {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("APIVersion").string("string", &self.api_version);
+            s.field("FieldPath").string("string", &self.field_path);
+        });
+    }
+}
+
+impl DeepHash for ResourceFieldSelector {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        let zero = Quantity::new("0");
+        w.structure(|s| {
+            s.field("ContainerName").string("string", &self.container_name);
+            s.field("Resource").string("string", &self.resource);
+            s.field("Divisor").value("resource.Quantity", self.divisor.as_ref().unwrap_or(&zero));
+        });
+    }
+}
+
+impl DeepHash for FileKeySelector {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("VolumeName").string("string", &self.volume_name);
+            s.field("Path").string("string", &self.path);
+            s.field("Key").string("string", &self.key);
+            s.field("Optional").opt_bool(self.optional);
+        });
+    }
+}
+
+impl DeepHash for ResourceRequirements {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Limits").resource_list(&self.limits);
+            s.field("Requests").resource_list(&self.requests);
+            s.field("Claims").values("v1.ResourceClaim", &self.claims);
+        });
+    }
+}
+
+impl DeepHash for ResourceClaim {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("Request").string("string", self.request.as_deref().unwrap_or_default());
+        });
+    }
+}
+
+impl DeepHash for ContainerResizePolicy {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("ResourceName").string("v1.ResourceName", &self.resource_name);
+            s.field("RestartPolicy").string("v1.ResourceResizeRestartPolicy", &self.restart_policy);
+        });
+    }
+}
+
+impl DeepHash for ContainerRestartRule {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Action").string("v1.ContainerRestartRuleAction", &self.action);
+            s.field("ExitCodes").ptr("v1.ContainerRestartRuleOnExitCodes", self.exit_codes.as_ref(), |w, codes| {
+                w.type_name("v1.ContainerRestartRuleOnExitCodes");
+                w.structure(|s| {
+                    s.field("Operator").string("v1.ContainerRestartRuleOnExitCodesOperator", &codes.operator);
+                    s.field("Values").slice("int32", &codes.values, |w, code| w.int("int32", *code));
+                });
+            });
+        });
+    }
+}
+
+impl DeepHash for VolumeMount {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("ReadOnly").bool(self.read_only);
+            s.field("RecursiveReadOnly").opt_string("v1.RecursiveReadOnlyMode", self.recursive_read_only.as_ref());
+            s.field("MountPath").string("string", &self.mount_path);
+            s.field("SubPath").string("string", &self.sub_path);
+            s.field("MountPropagation").opt_string("v1.MountPropagationMode", self.mount_propagation.as_ref());
+            s.field("SubPathExpr").string("string", &self.sub_path_expr);
+        });
+    }
+}
+
+impl DeepHash for VolumeDevice {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("DevicePath").string("string", &self.device_path);
+        });
+    }
+}
+
+impl DeepHash for Probe {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("ProbeHandler").value("v1.ProbeHandler", &self.probe_handler);
+            s.field("InitialDelaySeconds").int("int32", self.initial_delay_seconds.unwrap_or_default());
+            s.field("TimeoutSeconds").int("int32", self.timeout_seconds.unwrap_or_default());
+            s.field("PeriodSeconds").int("int32", self.period_seconds.unwrap_or_default());
+            s.field("SuccessThreshold").int("int32", self.success_threshold.unwrap_or_default());
+            s.field("FailureThreshold").int("int32", self.failure_threshold.unwrap_or_default());
+            s.field("TerminationGracePeriodSeconds").opt_int("int64", self.termination_grace_period_seconds);
+        });
+    }
+}
+
+impl DeepHash for ProbeHandler {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Exec").opt_value("v1.ExecAction", self.exec.as_ref());
+            s.field("HTTPGet").opt_value("v1.HTTPGetAction", self.http_get.as_ref());
+            s.field("TCPSocket").opt_value("v1.TCPSocketAction", self.tcp_socket.as_ref());
+            s.field("GRPC").opt_value("v1.GRPCAction", self.grpc.as_ref());
+        });
+    }
+}
+
+impl DeepHash for ExecAction {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| s.field("Command").strings("string", &self.command));
+    }
+}
+
+impl DeepHash for HTTPGetAction {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Path").string("string", &self.path);
+            s.field("Port").value("intstr.IntOrString", &self.port);
+            s.field("Host").string("string", &self.host);
+            s.field("Scheme").string("v1.URIScheme", &self.scheme);
+            s.field("HTTPHeaders").values("v1.HTTPHeader", &self.http_headers);
+        });
+    }
+}
+
+impl DeepHash for HTTPHeader {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("Value").string("string", &self.value);
+        });
+    }
+}
+
+impl DeepHash for TCPSocketAction {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Port").value("intstr.IntOrString", &self.port);
+            s.field("Host").string("string", &self.host);
+        });
+    }
+}
+
+impl DeepHash for GRPCAction {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Port").int("int32", self.port);
+            s.field("Service").opt_string("string", self.service.as_ref());
+        });
+    }
+}
+
+impl DeepHash for Lifecycle {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("PostStart").opt_value("v1.LifecycleHandler", self.post_start.as_ref());
+            s.field("PreStop").opt_value("v1.LifecycleHandler", self.pre_stop.as_ref());
+            s.field("StopSignal").opt_string("v1.Signal", self.stop_signal.as_ref());
+        });
+    }
+}
+
+impl DeepHash for LifecycleHandler {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Exec").opt_value("v1.ExecAction", self.exec.as_ref());
+            s.field("HTTPGet").opt_value("v1.HTTPGetAction", self.http_get.as_ref());
+            s.field("TCPSocket").opt_value("v1.TCPSocketAction", self.tcp_socket.as_ref());
+            s.field("Sleep").ptr("v1.SleepAction", self.sleep.as_ref(), |w, sleep| {
+                w.type_name("v1.SleepAction");
+                w.structure(|s| s.field("Seconds").int("int64", sleep.seconds));
+            });
+        });
+    }
+}
+
+impl DeepHash for SecurityContext {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Capabilities").opt_value("v1.Capabilities", self.capabilities.as_ref());
+            s.field("Privileged").opt_bool(self.privileged);
+            s.field("SELinuxOptions").opt_value("v1.SELinuxOptions", self.se_linux_options.as_ref());
+            s.field("WindowsOptions")
+                .opt_value("v1.WindowsSecurityContextOptions", self.windows_options.as_ref());
+            s.field("RunAsUser").opt_int("int64", self.run_as_user);
+            s.field("RunAsGroup").opt_int("int64", self.run_as_group);
+            s.field("RunAsNonRoot").opt_bool(self.run_as_non_root);
+            s.field("ReadOnlyRootFilesystem").opt_bool(self.read_only_root_filesystem);
+            s.field("AllowPrivilegeEscalation").opt_bool(self.allow_privilege_escalation);
+            s.field("ProcMount").opt_string("v1.ProcMountType", self.proc_mount.as_ref());
+            s.field("SeccompProfile").opt_value("v1.SeccompProfile", self.seccomp_profile.as_ref());
+            s.field("AppArmorProfile").opt_value("v1.AppArmorProfile", self.app_armor_profile.as_ref());
+        });
+    }
+}
+
+impl DeepHash for Capabilities {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Add").strings("v1.Capability", &self.add);
+            s.field("Drop").strings("v1.Capability", &self.drop);
+        });
+    }
+}
+
+impl DeepHash for SELinuxOptions {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("User").string("string", &self.user);
+            s.field("Role").string("string", &self.role);
+            s.field("Type").string("string", &self.se_type);
+            s.field("Level").string("string", &self.level);
+        });
+    }
+}
+
+impl DeepHash for WindowsSecurityContextOptions {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("GMSACredentialSpecName").opt_string("string", self.gmsa_credential_spec_name.as_ref());
+            s.field("GMSACredentialSpec").opt_string("string", self.gmsa_credential_spec.as_ref());
+            s.field("RunAsUserName").opt_string("string", self.run_as_user_name.as_ref());
+            s.field("HostProcess").opt_bool(self.host_process);
+        });
+    }
+}
+
+impl DeepHash for SeccompProfile {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Type").string("v1.SeccompProfileType", &self.profile_type);
+            s.field("LocalhostProfile").opt_string("string", self.localhost_profile.as_ref());
+        });
+    }
+}
+
+impl DeepHash for AppArmorProfile {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Type").string("v1.AppArmorProfileType", &self.profile_type);
+            s.field("LocalhostProfile").opt_string("string", self.localhost_profile.as_ref());
+        });
+    }
+}
+
+impl DeepHash for PodSecurityContext {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("SELinuxOptions").opt_value("v1.SELinuxOptions", self.se_linux_options.as_ref());
+            s.field("WindowsOptions")
+                .opt_value("v1.WindowsSecurityContextOptions", self.windows_options.as_ref());
+            s.field("RunAsUser").opt_int("int64", self.run_as_user);
+            s.field("RunAsGroup").opt_int("int64", self.run_as_group);
+            s.field("RunAsNonRoot").opt_bool(self.run_as_non_root);
+            s.field("SupplementalGroups").slice("int64", &self.supplemental_groups, |w, group| w.int("int64", *group));
+            s.field("SupplementalGroupsPolicy")
+                .opt_string("v1.SupplementalGroupsPolicy", self.supplemental_groups_policy.as_ref());
+            s.field("FSGroup").opt_int("int64", self.fs_group);
+            s.field("Sysctls").values("v1.Sysctl", &self.sysctls);
+            s.field("FSGroupChangePolicy")
+                .opt_string("v1.PodFSGroupChangePolicy", self.fs_group_change_policy.as_ref());
+            s.field("SeccompProfile").opt_value("v1.SeccompProfile", self.seccomp_profile.as_ref());
+            s.field("AppArmorProfile").opt_value("v1.AppArmorProfile", self.app_armor_profile.as_ref());
+            s.field("SELinuxChangePolicy")
+                .opt_string("v1.PodSELinuxChangePolicy", self.se_linux_change_policy.as_ref());
+        });
+    }
+}
+
+impl DeepHash for Sysctl {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("Value").string("string", &self.value);
+        });
+    }
+}
+
+impl DeepHash for Affinity {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("NodeAffinity").opt_value("v1.NodeAffinity", self.node_affinity.as_ref());
+            s.field("PodAffinity").ptr("v1.PodAffinity", self.pod_affinity.as_ref(), |w, affinity| {
+                w.type_name("v1.PodAffinity");
+                pod_affinity_fields(
+                    w,
+                    &affinity.required_during_scheduling_ignored_during_execution,
+                    &affinity.preferred_during_scheduling_ignored_during_execution,
+                );
+            });
+            s.field("PodAntiAffinity").ptr("v1.PodAntiAffinity", self.pod_anti_affinity.as_ref(), |w, affinity| {
+                w.type_name("v1.PodAntiAffinity");
+                pod_affinity_fields(
+                    w,
+                    &affinity.required_during_scheduling_ignored_during_execution,
+                    &affinity.preferred_during_scheduling_ignored_during_execution,
+                );
+            });
+        });
+    }
+}
+
+fn pod_affinity_fields(w: &mut DeepHashWriter, required: &[PodAffinityTerm], preferred: &[WeightedPodAffinityTerm]) {
+    w.structure(|s| {
+        s.field("RequiredDuringSchedulingIgnoredDuringExecution").values("v1.PodAffinityTerm", required);
+        s.field("PreferredDuringSchedulingIgnoredDuringExecution").values("v1.WeightedPodAffinityTerm", preferred);
+    });
+}
+
+impl DeepHash for NodeAffinity {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("RequiredDuringSchedulingIgnoredDuringExecution")
+                .opt_value("v1.NodeSelector", self.required_during_scheduling_ignored_during_execution.as_ref());
+            s.field("PreferredDuringSchedulingIgnoredDuringExecution").values(
+                "v1.PreferredSchedulingTerm",
+                &self.preferred_during_scheduling_ignored_during_execution,
+            );
+        });
+    }
+}
+
+impl DeepHash for NodeSelector {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| s.field("NodeSelectorTerms").values("v1.NodeSelectorTerm", &self.node_selector_terms));
+    }
+}
+
+impl DeepHash for NodeSelectorTerm {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("MatchExpressions").values("v1.NodeSelectorRequirement", &self.match_expressions);
+            s.field("MatchFields").values("v1.NodeSelectorRequirement", &self.match_fields);
+        });
+    }
+}
+
+impl DeepHash for NodeSelectorRequirement {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Key").string("string", &self.key);
+            s.field("Operator").string("v1.NodeSelectorOperator", &self.operator);
+            s.field("Values").strings("string", &self.values);
+        });
+    }
+}
+
+impl DeepHash for PreferredSchedulingTerm {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Weight").int("int32", self.weight);
+            s.field("Preference").value("v1.NodeSelectorTerm", &self.preference);
+        });
+    }
+}
+
+impl DeepHash for PodAffinityTerm {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("LabelSelector").opt_value("v1.LabelSelector", self.label_selector.as_ref());
+            s.field("Namespaces").strings("string", &self.namespaces);
+            s.field("TopologyKey").string("string", &self.topology_key);
+            s.field("NamespaceSelector").opt_value("v1.LabelSelector", self.namespace_selector.as_ref());
+            s.field("MatchLabelKeys").strings("string", &self.match_label_keys);
+            s.field("MismatchLabelKeys").strings("string", &self.mismatch_label_keys);
+        });
+    }
+}
+
+impl DeepHash for WeightedPodAffinityTerm {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Weight").int("int32", self.weight);
+            s.field("PodAffinityTerm").value("v1.PodAffinityTerm", &self.pod_affinity_term);
+        });
+    }
+}
+
+impl DeepHash for Toleration {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Key").string("string", &self.key);
+            s.field("Operator").string("v1.TolerationOperator", &self.operator);
+            s.field("Value").string("string", &self.value);
+            s.field("Effect").string("v1.TaintEffect", &self.effect);
+            s.field("TolerationSeconds").opt_int("int64", self.toleration_seconds);
+        });
+    }
+}
+
+impl DeepHash for HostAlias {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("IP").string("string", &self.ip);
+            s.field("Hostnames").strings("string", &self.hostnames);
+        });
+    }
+}
+
+impl DeepHash for PodDNSConfig {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Nameservers").strings("string", &self.nameservers);
+            s.field("Searches").strings("string", &self.searches);
+            s.field("Options").values("v1.PodDNSConfigOption", &self.options);
+        });
+    }
+}
+
+impl DeepHash for PodDNSConfigOption {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("Value").opt_string("string", self.value.as_ref());
+        });
+    }
+}
+
+impl DeepHash for PodReadinessGate {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| s.field("ConditionType").string("v1.PodConditionType", &self.condition_type));
+    }
+}
+
+impl DeepHash for TopologySpreadConstraint {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("MaxSkew").int("int32", self.max_skew);
+            s.field("TopologyKey").string("string", &self.topology_key);
+            s.field("WhenUnsatisfiable").string("v1.UnsatisfiableConstraintAction", &self.when_unsatisfiable);
+            s.field("LabelSelector").opt_value("v1.LabelSelector", self.label_selector.as_ref());
+            s.field("MinDomains").opt_int("int32", self.min_domains);
+            s.field("NodeAffinityPolicy").opt_string("v1.NodeInclusionPolicy", self.node_affinity_policy.as_ref());
+            s.field("NodeTaintsPolicy").opt_string("v1.NodeInclusionPolicy", self.node_taints_policy.as_ref());
+            s.field("MatchLabelKeys").strings("string", &self.match_label_keys);
+        });
+    }
+}
+
+impl DeepHash for PodOS {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| s.field("Name").string("v1.OSName", &self.name));
+    }
+}
+
+impl DeepHash for PodSchedulingGate {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| s.field("Name").string("string", &self.name));
+    }
+}
+
+impl DeepHash for PodResourceClaim {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("ResourceClaimName").opt_string("string", self.resource_claim_name.as_ref());
+            s.field("ResourceClaimTemplateName").opt_string("string", self.resource_claim_template_name.as_ref());
+        });
+    }
+}
+
+// =============================================================================
+// Volumes
+// =============================================================================
+
+impl DeepHash for Volume {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").string("string", &self.name);
+            s.field("VolumeSource").value("v1.VolumeSource", &self.volume_source);
+        });
+    }
+}
+
+impl DeepHash for VolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("HostPath").opt_value("v1.HostPathVolumeSource", self.host_path.as_ref());
+            s.field("EmptyDir").opt_value("v1.EmptyDirVolumeSource", self.empty_dir.as_ref());
+            s.field("GCEPersistentDisk")
+                .opt_value("v1.GCEPersistentDiskVolumeSource", self.gce_persistent_disk.as_ref());
+            s.field("AWSElasticBlockStore")
+                .opt_value("v1.AWSElasticBlockStoreVolumeSource", self.aws_elastic_block_store.as_ref());
+            s.field("GitRepo").opt_value("v1.GitRepoVolumeSource", self.git_repo.as_ref());
+            s.field("Secret").opt_value("v1.SecretVolumeSource", self.secret.as_ref());
+            s.field("NFS").opt_value("v1.NFSVolumeSource", self.nfs.as_ref());
+            s.field("ISCSI").opt_value("v1.ISCSIVolumeSource", self.iscsi.as_ref());
+            s.field("Glusterfs").opt_value("v1.GlusterfsVolumeSource", self.glusterfs.as_ref());
+            s.field("PersistentVolumeClaim")
+                .opt_value("v1.PersistentVolumeClaimVolumeSource", self.persistent_volume_claim.as_ref());
+            s.field("RBD").opt_value("v1.RBDVolumeSource", self.rbd.as_ref());
+            s.field("FlexVolume").opt_value("v1.FlexVolumeSource", self.flex_volume.as_ref());
+            s.field("Cinder").opt_value("v1.CinderVolumeSource", self.cinder.as_ref());
+            s.field("CephFS").opt_value("v1.CephFSVolumeSource", self.cephfs.as_ref());
+            s.field("Flocker").opt_value("v1.FlockerVolumeSource", self.flocker.as_ref());
+            s.field("DownwardAPI").opt_value("v1.DownwardAPIVolumeSource", self.downward_a_p_i.as_ref());
+            s.field("FC").opt_value("v1.FCVolumeSource", self.fc.as_ref());
+            s.field("AzureFile").opt_value("v1.AzureFileVolumeSource", self.azure_file.as_ref());
+            s.field("ConfigMap").opt_value("v1.ConfigMapVolumeSource", self.config_map.as_ref());
+            s.field("VsphereVolume").opt_value("v1.VsphereVirtualDiskVolumeSource", self.vsphere_volume.as_ref());
+            s.field("Quobyte").opt_value("v1.QuobyteVolumeSource", self.quobyte.as_ref());
+            s.field("AzureDisk").opt_value("v1.AzureDiskVolumeSource", self.azure_disk.as_ref());
+            s.field("PhotonPersistentDisk")
+                .opt_value("v1.PhotonPersistentDiskVolumeSource", self.photon_persistent_disk.as_ref());
+            s.field("Projected").opt_value("v1.ProjectedVolumeSource", self.projected.as_ref());
+            s.field("PortworxVolume").opt_value("v1.PortworxVolumeSource", self.portworx_volume.as_ref());
+            s.field("ScaleIO").opt_value("v1.ScaleIOVolumeSource", self.scale_io.as_ref());
+            s.field("StorageOS").opt_value("v1.StorageOSVolumeSource", self.storageos.as_ref());
+            s.field("CSI").opt_value("v1.CSIVolumeSource", self.csi.as_ref());
+            s.field("Ephemeral").opt_value("v1.EphemeralVolumeSource", self.ephemeral.as_ref());
+            s.field("Image").opt_value("v1.ImageVolumeSource", self.image.as_ref());
+        });
+    }
+}
+
+impl DeepHash for HostPathVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Path").string("string", &self.path);
+            s.field("Type").opt_string("v1.HostPathType", self.host_path_type.as_ref());
+        });
+    }
+}
+
+impl DeepHash for EmptyDirVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Medium").string("v1.StorageMedium", &self.medium);
+            s.field("SizeLimit").opt_value("resource.Quantity", self.size_limit.as_ref());
+        });
+    }
+}
+
+impl DeepHash for GCEPersistentDiskVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("PDName").string("string", &self.pd_name);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("Partition").int("int32", self.partition.unwrap_or_default());
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for AWSElasticBlockStoreVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("VolumeID").string("string", &self.volume_id);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("Partition").int("int32", self.partition.unwrap_or_default());
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for GitRepoVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Repository").string("string", &self.repository);
+            s.field("Revision").string("string", &self.revision);
+            s.field("Directory").string("string", &self.directory);
+        });
+    }
+}
+
+impl DeepHash for SecretVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("SecretName").string("string", &self.secret_name);
+            s.field("Items").values("v1.KeyToPath", &self.items);
+            s.field("DefaultMode").opt_int("int32", self.default_mode);
+            s.field("Optional").opt_bool(self.optional);
+        });
+    }
+}
+
+impl DeepHash for NFSVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Server").string("string", &self.server);
+            s.field("Path").string("string", &self.path);
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for ISCSIVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("TargetPortal").string("string", &self.target_portal);
+            s.field("IQN").string("string", &self.iqn);
+            s.field("Lun").int("int32", self.lun);
+            s.field("ISCSIInterface").string("string", &self.iscsi_interface);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("ReadOnly").bool(self.read_only);
+            s.field("Portals").strings("string", &self.portals);
+            s.field("DiscoveryCHAPAuth").bool(self.chap_auth_discovery);
+            s.field("SessionCHAPAuth").bool(self.chap_auth_session);
+            s.field("SecretRef").opt_value("v1.LocalObjectReference", self.secret_ref.as_ref());
+            s.field("InitiatorName").opt_string("string", self.initiator_name.as_ref());
+        });
+    }
+}
+
+impl DeepHash for GlusterfsVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("EndpointsName").string("string", &self.endpoints);
+            s.field("Path").string("string", &self.path);
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for PersistentVolumeClaimVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("ClaimName").string("string", &self.claim_name);
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for RBDVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("CephMonitors").strings("string", &self.monitors);
+            s.field("RBDImage").string("string", &self.image);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("RBDPool").string("string", &self.pool);
+            s.field("RadosUser").string("string", &self.user);
+            s.field("Keyring").string("string", &self.keyring);
+            s.field("SecretRef").opt_value("v1.LocalObjectReference", self.secret_ref.as_ref());
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for FlexVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Driver").string("string", &self.driver);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("SecretRef").opt_value("v1.LocalObjectReference", self.secret_ref.as_ref());
+            s.field("ReadOnly").bool(self.read_only);
+            s.field("Options").string_map(&self.options);
+        });
+    }
+}
+
+impl DeepHash for CinderVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("VolumeID").string("string", &self.volume_id);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("ReadOnly").bool(self.read_only);
+            s.field("SecretRef").opt_value("v1.LocalObjectReference", self.secret_ref.as_ref());
+        });
+    }
+}
+
+impl DeepHash for CephFSVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Monitors").strings("string", &self.monitors);
+            s.field("Path").string("string", &self.path);
+            s.field("User").string("string", &self.user);
+            s.field("SecretFile").string("string", &self.secret_file);
+            s.field("SecretRef").opt_value("v1.LocalObjectReference", self.secret_ref.as_ref());
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for FlockerVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("DatasetName").string("string", &self.dataset_name);
+            s.field("DatasetUUID").string("string", &self.dataset_uuid);
+        });
+    }
+}
+
+impl DeepHash for DownwardAPIVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Items").values("v1.DownwardAPIVolumeFile", &self.items);
+            s.field("DefaultMode").opt_int("int32", self.default_mode);
+        });
+    }
+}
+
+impl DeepHash for DownwardAPIVolumeFile {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Path").string("string", &self.path);
+            s.field("FieldRef").opt_value("v1.ObjectFieldSelector", self.field_ref.as_ref());
+            s.field("ResourceFieldRef").opt_value("v1.ResourceFieldSelector", self.resource_field_ref.as_ref());
+            s.field("Mode").opt_int("int32", self.mode);
+        });
+    }
+}
+
+impl DeepHash for FCVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("TargetWWNs").strings("string", &self.target_wwns);
+            s.field("Lun").opt_int("int32", self.lun);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("ReadOnly").bool(self.read_only);
+            s.field("WWIDs").strings("string", &self.wwids);
+        });
+    }
+}
+
+impl DeepHash for AzureFileVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("SecretName").string("string", &self.secret_name);
+            s.field("ShareName").string("string", &self.share_name);
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for ConfigMapVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("LocalObjectReference").local_object_reference(&self.name);
+            s.field("Items").values("v1.KeyToPath", &self.items);
+            s.field("DefaultMode").opt_int("int32", self.default_mode);
+            s.field("Optional").opt_bool(self.optional);
+        });
+    }
+}
+
+impl DeepHash for KeyToPath {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Key").string("string", &self.key);
+            s.field("Path").string("string", &self.path);
+            s.field("Mode").opt_int("int32", self.mode);
+        });
+    }
+}
+
+impl DeepHash for VsphereVirtualDiskVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("VolumePath").string("string", &self.volume_path);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("StoragePolicyName").string("string", &self.storage_policy_name);
+            s.field("StoragePolicyID").string("string", &self.storage_policy_id);
+        });
+    }
+}
+
+impl DeepHash for QuobyteVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Registry").string("string", &self.registry);
+            s.field("Volume").string("string", &self.volume);
+            s.field("ReadOnly").bool(self.read_only);
+            s.field("User").string("string", &self.user);
+            s.field("Group").string("string", &self.group);
+            s.field("Tenant").string("string", &self.tenant);
+        });
+    }
+}
+
+impl DeepHash for AzureDiskVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("DiskName").string("string", &self.disk_name);
+            s.field("DataDiskURI").string("string", &self.disk_uri);
+            s.field("CachingMode").opt_string("v1.AzureDataDiskCachingMode", self.caching_mode.as_ref());
+            s.field("FSType").opt_string("string", self.fs_type.as_ref());
+            // Defaulted to false by the API server.
+            s.field("ReadOnly").opt_bool(Some(self.read_only));
+            s.field("Kind").opt_string("v1.AzureDataDiskKind", self.kind.as_ref());
+        });
+    }
+}
+
+impl DeepHash for PhotonPersistentDiskVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("PdID").string("string", &self.pd_id);
+            s.field("FSType").string("string", &self.fs_type);
+        });
+    }
+}
+
+impl DeepHash for ProjectedVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Sources").values("v1.VolumeProjection", &self.sources);
+            s.field("DefaultMode").opt_int("int32", self.default_mode);
+        });
+    }
+}
+
+impl DeepHash for VolumeProjection {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Secret").ptr("v1.SecretProjection", self.secret.as_ref(), |w, projection| {
+                w.type_name("v1.SecretProjection");
+                w.structure(|s| {
+                    s.field("LocalObjectReference").local_object_reference(&projection.name);
+                    s.field("Items").values("v1.KeyToPath", &projection.items);
+                    s.field("Optional").opt_bool(projection.optional);
+                });
+            });
+            s.field("DownwardAPI").ptr("v1.DownwardAPIProjection", self.downward_a_p_i.as_ref(), |w, projection| {
+                w.type_name("v1.DownwardAPIProjection");
+                w.structure(|s| s.field("Items").values("v1.DownwardAPIVolumeFile", &projection.items));
+            });
+            s.field("ConfigMap").ptr("v1.ConfigMapProjection", self.config_map.as_ref(), |w, projection| {
+                w.type_name("v1.ConfigMapProjection");
+                w.structure(|s| {
+                    s.field("LocalObjectReference").local_object_reference(&projection.name);
+                    s.field("Items").values("v1.KeyToPath", &projection.items);
+                    s.field("Optional").opt_bool(projection.optional);
+                });
+            });
+            s.field("ServiceAccountToken").opt_value(
+                "v1.ServiceAccountTokenProjection",
+                self.service_account_token.as_ref(),
+            );
+            s.field("ClusterTrustBundle")
+                .opt_value("v1.ClusterTrustBundleProjection", self.cluster_trust_bundle.as_ref());
+            s.field("PodCertificate")
+                .opt_value("v1.PodCertificateProjection", self.pod_certificate.as_ref());
+        });
+    }
+}
+
+impl DeepHash for ServiceAccountTokenProjection {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Audience").string("string", &self.audience);
+            s.field("ExpirationSeconds").opt_int("int64", self.expiration_seconds);
+            s.field("Path").string("string", &self.path);
+        });
+    }
+}
+
+impl DeepHash for ClusterTrustBundleProjection {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Name").opt_string("string", self.name.as_ref());
+            s.field("SignerName").opt_string("string", self.signer_name.as_ref());
+            s.field("LabelSelector").opt_value("v1.LabelSelector", self.label_selector.as_ref());
+            s.field("Optional").opt_bool(self.optional);
+            s.field("Path").string("string", &self.path);
+        });
+    }
+}
+
+impl DeepHash for PodCertificateProjection {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("SignerName").string("string", &self.signer_name);
+            s.field("KeyType").string("string", &self.key_type);
+            s.field("MaxExpirationSeconds").opt_int("int32", self.max_expiration_seconds);
+            s.field("CredentialBundlePath").string("string", &self.credential_bundle_path);
+            s.field("KeyPath").string("string", &self.key_path);
+            s.field("CertificateChainPath").string("string", &self.certificate_chain_path);
+        });
+    }
+}
+
+impl DeepHash for PortworxVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("VolumeID").string("string", &self.volume_id);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for ScaleIOVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Gateway").string("string", &self.gateway);
+            s.field("System").string("string", &self.system);
+            s.field("SecretRef").opt_value("v1.LocalObjectReference", Some(&self.secret_ref));
+            s.field("SSLEnabled").bool(self.ssl_enabled);
+            s.field("ProtectionDomain").string("string", &self.protection_domain);
+            s.field("StoragePool").string("string", &self.storage_pool);
+            s.field("StorageMode").string("string", &self.storage_mode);
+            s.field("VolumeName").string("string", &self.volume_name);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("ReadOnly").bool(self.read_only);
+        });
+    }
+}
+
+impl DeepHash for StorageOSVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("VolumeName").string("string", &self.volume_name);
+            s.field("VolumeNamespace").string("string", &self.volume_namespace);
+            s.field("FSType").string("string", &self.fs_type);
+            s.field("ReadOnly").bool(self.read_only);
+            s.field("SecretRef").opt_value("v1.LocalObjectReference", self.secret_ref.as_ref());
+        });
+    }
+}
+
+impl DeepHash for CSIVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Driver").string("string", &self.driver);
+            s.field("ReadOnly").opt_bool(self.read_only.then_some(true));
+            s.field("FSType").opt_string("string", self.fs_type.as_ref());
+            s.field("VolumeAttributes").string_map(&self.volume_attributes);
+            s.field("NodePublishSecretRef").opt_value("v1.LocalObjectReference", self.node_publish_secret_ref.as_ref());
+        });
+    }
+}
+
+impl DeepHash for EphemeralVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("VolumeClaimTemplate")
+                .opt_value("v1.PersistentVolumeClaimTemplate", self.volume_claim_template.as_ref());
+        });
+    }
+}
+
+impl DeepHash for PersistentVolumeClaimTemplate {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("ObjectMeta").value("v1.ObjectMeta", &self.metadata);
+            s.field("Spec").value("v1.PersistentVolumeClaimSpec", &self.spec);
+        });
+    }
+}
+
+impl DeepHash for PersistentVolumeClaimSpec {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        let default_resources = VolumeResourceRequirements::default();
+        w.structure(|s| {
+            s.field("AccessModes").strings("v1.PersistentVolumeAccessMode", &self.access_modes);
+            s.field("Selector").opt_value("v1.LabelSelector", self.selector.as_ref());
+            s.field("Resources").type_name("v1.VolumeResourceRequirements");
+            let resources = self.resources.as_ref().unwrap_or(&default_resources);
+            s.w.structure(|s| {
+                s.field("Limits").resource_list(&resources.limits);
+                s.field("Requests").resource_list(&resources.requests);
+            });
+            s.field("VolumeName").string("string", &self.volume_name);
+            s.field("StorageClassName").opt_string("string", self.storage_class_name.as_ref());
+            s.field("VolumeMode").opt_string("v1.PersistentVolumeMode", self.volume_mode.as_ref());
+            s.field("DataSource").ptr("v1.TypedLocalObjectReference", self.data_source.as_ref(), |w, source| {
+                w.type_name("v1.TypedLocalObjectReference");
+                w.structure(|s| {
+                    s.field("APIGroup").opt_string("string", source.api_group.as_ref());
+                    s.field("Kind").string("string", &source.kind);
+                    s.field("Name").string("string", &source.name);
+                });
+            });
+            s.field("DataSourceRef").ptr("v1.TypedObjectReference", self.data_source_ref.as_ref(), |w, source| {
+                w.type_name("v1.TypedObjectReference");
+                w.structure(|s| {
+                    s.field("APIGroup").opt_string("string", source.api_group.as_ref());
+                    s.field("Kind").string("string", &source.kind);
+                    s.field("Name").string("string", &source.name);
+                    s.field("Namespace").opt_string("string", source.namespace.as_ref());
+                });
+            });
+            s.field("VolumeAttributesClassName").opt_string("string", self.volume_attributes_class_name.as_ref());
+        });
+    }
+}
+
+impl DeepHash for ImageVolumeSource {
+    fn deep_hash(&self, w: &mut DeepHashWriter) {
+        w.structure(|s| {
+            s.field("Reference").string("string", &self.reference);
+            s.field("PullPolicy").string("v1.PullPolicy", &self.pull_policy);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deep_hash_string() {
+        let selector = LabelSelector {
+            match_labels: [("app".to_string(), "web".to_string())].into(),
+            match_expressions: vec![LabelSelectorRequirement {
+                key: "tier".to_string(),
+                operator: "In".to_string(),
+                values: vec!["a".to_string(), "b".to_string()],
+            }],
+        };
+        assert_eq!(
+            selector.deep_hash_string(),
+            "{MatchLabels:(map[string]string)map[app:web] MatchExpressions:([]v1.LabelSelectorRequirement)\
+             [{Key:(string)tier Operator:(v1.LabelSelectorOperator)In Values:([]string)[a b]}]}"
+        );
+
+        let action = TCPSocketAction {
+            port: IntOrString::Int(8080),
+            ..Default::default()
+        };
+        assert_eq!(
+            action.deep_hash_string(),
+            "{Port:(intstr.IntOrString){Type:(intstr.Type)0 IntVal:(int32)8080 StrVal:(string)} Host:(string)}"
+        );
+
+        let probe = GRPCAction {
+            port: 9090,
+            service: Some("health".to_string()),
+        };
+        assert_eq!(probe.deep_hash_string(), "{Port:(int32)9090 Service:(*string)health}");
+    }
+
+    #[test]
+    fn test_quantity_deep_hash() {
+        let quantity = |s: &str| Quantity::new(s).deep_hash_string();
+        let int_amount = |value: i64, scale: i32, s: &str, format: &str| {
+            format!(
+                "{{i:(resource.int64Amount){{value:(int64){} scale:(resource.Scale){}}} \
+                 d:(resource.infDecAmount){{Dec:(*inf.Dec)<nil>}} s:(string){} Format:(resource.Format){}}}",
+                value, scale, s, format
+            )
+        };
+        assert_eq!(quantity("100m"), int_amount(100, -3, "100m", "DecimalSI"));
+        assert_eq!(quantity("1Gi"), int_amount(1 << 30, 0, "1Gi", "BinarySI"));
+        // Only canonical strings with a mantissa not divisible by 8 are kept.
+        assert_eq!(quantity("512Mi"), int_amount(512 << 20, 0, "", "BinarySI"));
+        // Quantities are hashed in the canonical form the API server stores.
+        assert_eq!(quantity("0.5"), int_amount(500, -3, "500m", "DecimalSI"));
+        assert_eq!(quantity("0"), int_amount(0, 0, "0", "DecimalSI"));
+    }
+}
@@ -1,6 +1,5 @@
 use super::*;
 use k8s_api_core::{IntOrString, Quantity};
-use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 #[cfg(test)]
@@ -142,7 +141,7 @@ fn default_container_resource_requests(containers: &mut [Container]) {
 }
 
 fn default_pod_requests(pod: &mut Pod) {
-    let aggr_ctr_reqs = aggregate_container_requests(pod);
+    let aggr_ctr_reqs = aggregate_container_requests(pod, &PodResourcesOptions::default());
     let resources = match pod.spec.as_mut().and_then(|spec| spec.resources.as_mut()) {
         Some(resources) => resources,
         None => return,
@@ -177,7 +176,7 @@ fn default_pod_requests(pod: &mut Pod) {
 }
 
 fn default_huge_page_pod_limits(pod: &mut Pod) {
-    let aggr_ctr_limits = aggregate_container_limits(pod);
+    let aggr_ctr_limits = aggregate_container_limits(pod, &PodResourcesOptions::default());
     let resources = match pod.spec.as_mut().and_then(|spec| spec.resources.as_mut()) {
         Some(resources) => resources,
         None => return,
@@ -221,478 +220,6 @@ fn is_overcommit_allowed(name: &str) -> bool {
     is_native_resource(name) && !is_huge_page_resource_name(name)
 }
 
-fn aggregate_container_requests(pod: &Pod) -> ResourceList {
-    aggregate_container_resources(pod, |resources| &resources.requests)
-}
-
-fn aggregate_container_limits(pod: &Pod) -> ResourceList {
-    aggregate_container_resources(pod, |resources| &resources.limits)
-}
-
-fn aggregate_container_resources(
-    pod: &Pod,
-    select: fn(&ResourceRequirements) -> &ResourceList,
-) -> ResourceList {
-    let spec = match pod.spec.as_ref() {
-        Some(spec) => spec,
-        None => return ResourceList::new(),
-    };
-    let mut total = QuantityMap::new();
-    for container in &spec.containers {
-        if let Some(resources) = container.resources.as_ref() {
-            add_resource_list(&mut total, select(resources));
-        }
-    }
-
-    let mut restartable_init = QuantityMap::new();
-    let mut init_max = QuantityMap::new();
-    for container in &spec.init_containers {
-        let mut container_resources = QuantityMap::new();
-        if let Some(resources) = container.resources.as_ref() {
-            add_resource_list(&mut container_resources, select(resources));
-        }
-
-        if is_restartable_init(container) {
-            add_quantity_map(&mut total, &container_resources);
-            add_quantity_map(&mut restartable_init, &container_resources);
-            container_resources = restartable_init.clone();
-        } else {
-            let mut tmp = container_resources.clone();
-            add_quantity_map(&mut tmp, &restartable_init);
-            container_resources = tmp;
-        }
-
-        max_quantity_map(&mut init_max, &container_resources);
-    }
-
-    max_quantity_map(&mut total, &init_max);
-    resource_list_from_quantity_map(total)
-}
-
-fn is_restartable_init(container: &Container) -> bool {
-    matches!(
-        container.restart_policy.as_deref(),
-        Some(CONTAINER_RESTART_POLICY_ALWAYS)
-    )
-}
-
-type QuantityMap = BTreeMap<ResourceName, ParsedQuantity>;
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum QuantityFormat {
-    Decimal,
-    Binary,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct DecimalNumber {
-    digits: String,
-    scale: i32,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct ParsedQuantity {
-    value: DecimalNumber,
-    format: QuantityFormat,
-}
-
-impl ParsedQuantity {
-    fn parse(quantity: &Quantity) -> Option<Self> {
-        parse_quantity_str(quantity.as_str())
-    }
-
-    fn add_assign(&mut self, other: &ParsedQuantity) {
-        self.value = add_decimal(&self.value, &other.value);
-    }
-
-    fn cmp(&self, other: &ParsedQuantity) -> Ordering {
-        cmp_decimal(&self.value, &other.value)
-    }
-
-    fn to_quantity(&self) -> Quantity {
-        Quantity::new(format_quantity(&self.value, self.format))
-    }
-}
-
-fn add_resource_list(target: &mut QuantityMap, list: &ResourceList) {
-    for (name, quantity) in list {
-        let parsed = match ParsedQuantity::parse(quantity) {
-            Some(parsed) => parsed,
-            None => continue,
-        };
-        match target.get_mut(name) {
-            Some(existing) => existing.add_assign(&parsed),
-            None => {
-                target.insert(name.clone(), parsed);
-            }
-        }
-    }
-}
-
-fn add_quantity_map(target: &mut QuantityMap, list: &QuantityMap) {
-    for (name, quantity) in list {
-        match target.get_mut(name) {
-            Some(existing) => existing.add_assign(quantity),
-            None => {
-                target.insert(name.clone(), quantity.clone());
-            }
-        }
-    }
-}
-
-fn max_quantity_map(target: &mut QuantityMap, list: &QuantityMap) {
-    for (name, quantity) in list {
-        let replace = match target.get(name) {
-            Some(existing) => quantity.cmp(existing) == Ordering::Greater,
-            None => true,
-        };
-        if replace {
-            target.insert(name.clone(), quantity.clone());
-        }
-    }
-}
-
-fn resource_list_from_quantity_map(map: QuantityMap) -> ResourceList {
-    map.into_iter()
-        .map(|(name, quantity)| (name, quantity.to_quantity()))
-        .collect()
-}
-
-fn parse_quantity_str(input: &str) -> Option<ParsedQuantity> {
-    let input = input.trim();
-    if input.is_empty() {
-        return None;
-    }
-    let input = input.strip_prefix('+').unwrap_or(input);
-    if input.starts_with('-') {
-        return None;
-    }
-    let (number, suffix) = split_number_suffix(input)?;
-    let mut value = parse_number(number)?;
-    let format = match suffix {
-        "Ki" | "Mi" | "Gi" | "Ti" | "Pi" | "Ei" => QuantityFormat::Binary,
-        _ => QuantityFormat::Decimal,
-    };
-    match suffix {
-        "" => {}
-        "n" => value.scale += -9,
-        "u" => value.scale += -6,
-        "m" => value.scale += -3,
-        "k" => value.scale += 3,
-        "M" => value.scale += 6,
-        "G" => value.scale += 9,
-        "T" => value.scale += 12,
-        "P" => value.scale += 15,
-        "E" => value.scale += 18,
-        "Ki" => value.digits = mul_str_small(&value.digits, 1u128 << 10),
-        "Mi" => value.digits = mul_str_small(&value.digits, 1u128 << 20),
-        "Gi" => value.digits = mul_str_small(&value.digits, 1u128 << 30),
-        "Ti" => value.digits = mul_str_small(&value.digits, 1u128 << 40),
-        "Pi" => value.digits = mul_str_small(&value.digits, 1u128 << 50),
-        "Ei" => value.digits = mul_str_small(&value.digits, 1u128 << 60),
-        _ => {
-            if let Some(exp) = suffix.strip_prefix('e').or_else(|| suffix.strip_prefix('E')) {
-                let exp = exp.parse::<i32>().ok()?;
-                value.scale += exp;
-            } else {
-                return None;
-            }
-        }
-    }
-    value.normalize();
-    Some(ParsedQuantity { value, format })
-}
-
-fn split_number_suffix(input: &str) -> Option<(&str, &str)> {
-    let mut idx = 0;
-    for (i, ch) in input.char_indices() {
-        if ch.is_ascii_digit() || ch == '.' {
-            idx = i + ch.len_utf8();
-            continue;
-        }
-        break;
-    }
-    if idx == 0 {
-        return None;
-    }
-    Some(input.split_at(idx))
-}
-
-fn parse_number(input: &str) -> Option<DecimalNumber> {
-    let mut int_part = String::new();
-    let mut frac_part = String::new();
-    let mut iter = input.char_indices().peekable();
-    while let Some((_, ch)) = iter.peek() {
-        if ch.is_ascii_digit() {
-            int_part.push(*ch);
-            iter.next();
-        } else {
-            break;
-        }
-    }
-    if matches!(iter.peek().map(|(_, ch)| *ch), Some('.')) {
-        iter.next();
-        while let Some((_, ch)) = iter.peek() {
-            if ch.is_ascii_digit() {
-                frac_part.push(*ch);
-                iter.next();
-            } else {
-                break;
-            }
-        }
-    }
-    if int_part.is_empty() && frac_part.is_empty() {
-        return None;
-    }
-    let mut digits = String::new();
-    digits.push_str(&int_part);
-    digits.push_str(&frac_part);
-    let scale = -(frac_part.len() as i32);
-    if digits.is_empty() {
-        digits.push('0');
-    }
-    let mut value = DecimalNumber { digits, scale };
-    value.normalize();
-    Some(value)
-}
-
-impl DecimalNumber {
-    fn normalize(&mut self) {
-        let trimmed = self.digits.trim_start_matches('0');
-        if trimmed.is_empty() {
-            self.digits = "0".to_string();
-            self.scale = 0;
-            return;
-        }
-        self.digits = trimmed.to_string();
-        while self.digits.len() > 1 && self.digits.ends_with('0') {
-            self.digits.pop();
-            self.scale += 1;
-        }
-    }
-}
-
-fn add_decimal(a: &DecimalNumber, b: &DecimalNumber) -> DecimalNumber {
-    let (lhs, rhs, scale) = align_scales(a, b);
-    let digits = add_digit_strings(&lhs.digits, &rhs.digits);
-    let mut value = DecimalNumber { digits, scale };
-    value.normalize();
-    value
-}
-
-fn cmp_decimal(a: &DecimalNumber, b: &DecimalNumber) -> Ordering {
-    let (lhs, rhs, _) = align_scales(a, b);
-    cmp_digit_strings(&lhs.digits, &rhs.digits)
-}
-
-fn align_scales(a: &DecimalNumber, b: &DecimalNumber) -> (DecimalNumber, DecimalNumber, i32) {
-    let target = a.scale.min(b.scale);
-    let mut lhs = a.clone();
-    let mut rhs = b.clone();
-    if lhs.scale > target {
-        let shift = (lhs.scale - target) as usize;
-        lhs.digits = mul_pow10(&lhs.digits, shift);
-        lhs.scale = target;
-    }
-    if rhs.scale > target {
-        let shift = (rhs.scale - target) as usize;
-        rhs.digits = mul_pow10(&rhs.digits, shift);
-        rhs.scale = target;
-    }
-    (lhs, rhs, target)
-}
-
-fn add_digit_strings(a: &str, b: &str) -> String {
-    let mut result = Vec::new();
-    let mut carry = 0u8;
-    let mut a_iter = a.as_bytes().iter().rev();
-    let mut b_iter = b.as_bytes().iter().rev();
-    loop {
-        let da = a_iter.next().map(|c| *c - b'0');
-        let db = b_iter.next().map(|c| *c - b'0');
-        if da.is_none() && db.is_none() && carry == 0 {
-            break;
-        }
-        let sum = da.unwrap_or(0) + db.unwrap_or(0) + carry;
-        carry = sum / 10;
-        result.push((sum % 10) + b'0');
-    }
-    result.reverse();
-    String::from_utf8(result).unwrap_or_else(|_| "0".to_string())
-}
-
-fn cmp_digit_strings(a: &str, b: &str) -> Ordering {
-    let a = a.trim_start_matches('0');
-    let b = b.trim_start_matches('0');
-    let a = if a.is_empty() { "0" } else { a };
-    let b = if b.is_empty() { "0" } else { b };
-    match a.len().cmp(&b.len()) {
-        Ordering::Equal => a.cmp(b),
-        other => other,
-    }
-}
-
-fn mul_pow10(digits: &str, exp: usize) -> String {
-    if digits == "0" {
-        return "0".to_string();
-    }
-    let mut out = String::with_capacity(digits.len() + exp);
-    out.push_str(digits);
-    out.extend(std::iter::repeat('0').take(exp));
-    out
-}
-
-fn mul_str_small(digits: &str, factor: u128) -> String {
-    if digits == "0" {
-        return "0".to_string();
-    }
-    if factor == 0 {
-        return "0".to_string();
-    }
-    let mut carry = 0u128;
-    let mut out = Vec::with_capacity(digits.len() + 1);
-    for ch in digits.as_bytes().iter().rev() {
-        let digit = (*ch - b'0') as u128;
-        let prod = digit * factor + carry;
-        out.push(((prod % 10) as u8) + b'0');
-        carry = prod / 10;
-    }
-    while carry > 0 {
-        out.push(((carry % 10) as u8) + b'0');
-        carry /= 10;
-    }
-    out.reverse();
-    String::from_utf8(out).unwrap_or_else(|_| "0".to_string())
-}
-
-fn format_quantity(value: &DecimalNumber, format: QuantityFormat) -> String {
-    match format {
-        QuantityFormat::Decimal => format_decimal_quantity(value),
-        QuantityFormat::Binary => format_binary_quantity(value).unwrap_or_else(|| {
-            format_decimal_plain(value)
-        }),
-    }
-}
-
-fn format_decimal_quantity(value: &DecimalNumber) -> String {
-    if value.digits == "0" {
-        return "0".to_string();
-    }
-    if value.scale >= 0 {
-        return mul_pow10(&value.digits, value.scale as usize);
-    }
-
-    for (exp, suffix) in [(-3, "m"), (-6, "u"), (-9, "n")] {
-        if exp <= value.scale {
-            let shift = (value.scale - exp) as usize;
-            let digits = mul_pow10(&value.digits, shift);
-            return format!("{digits}{suffix}");
-        }
-    }
-
-    format_decimal_plain(value)
-}
-
-fn format_binary_quantity(value: &DecimalNumber) -> Option<String> {
-    let integer = decimal_to_integer_string(value)?;
-    if integer == "0" {
-        return Some("0".to_string());
-    }
-    for (exp, suffix) in [
-        (60u32, "Ei"),
-        (50u32, "Pi"),
-        (40u32, "Ti"),
-        (30u32, "Gi"),
-        (20u32, "Mi"),
-        (10u32, "Ki"),
-    ] {
-        let divisor = 1u128 << exp;
-        if is_divisible_by(&integer, divisor) {
-            let (quotient, _) = div_decimal_str_by_u128(&integer, divisor);
-            return Some(format!("{quotient}{suffix}"));
-        }
-    }
-    Some(integer)
-}
-
-fn decimal_to_integer_string(value: &DecimalNumber) -> Option<String> {
-    if value.digits == "0" {
-        return Some("0".to_string());
-    }
-    if value.scale >= 0 {
-        return Some(mul_pow10(&value.digits, value.scale as usize));
-    }
-    let scale = (-value.scale) as usize;
-    if value.digits.len() <= scale {
-        return None;
-    }
-    if !ends_with_zeros(&value.digits, scale) {
-        return None;
-    }
-    let cut = value.digits.len() - scale;
-    let trimmed = value.digits[..cut].to_string();
-    if trimmed.is_empty() {
-        Some("0".to_string())
-    } else {
-        Some(trimmed)
-    }
-}
-
-fn ends_with_zeros(value: &str, count: usize) -> bool {
-    if count == 0 {
-        return true;
-    }
-    value
-        .as_bytes()
-        .iter()
-        .rev()
-        .take(count)
-        .all(|ch| *ch == b'0')
-}
-
-fn format_decimal_plain(value: &DecimalNumber) -> String {
-    if value.scale >= 0 {
-        return mul_pow10(&value.digits, value.scale as usize);
-    }
-    let scale = (-value.scale) as usize;
-    if value.digits.len() > scale {
-        let split = value.digits.len() - scale;
-        format!(
-            "{}.{}",
-            &value.digits[..split],
-            &value.digits[split..]
-        )
-    } else {
-        let mut out = String::from("0.");
-        out.extend(std::iter::repeat('0').take(scale - value.digits.len()));
-        out.push_str(&value.digits);
-        out
-    }
-}
-
-fn is_divisible_by(value: &str, divisor: u128) -> bool {
-    let (_, rem) = div_decimal_str_by_u128(value, divisor);
-    rem == 0
-}
-
-fn div_decimal_str_by_u128(value: &str, divisor: u128) -> (String, u128) {
-    let mut rem = 0u128;
-    let mut out = String::new();
-    for ch in value.chars() {
-        let digit = ch.to_digit(10).unwrap_or(0) as u128;
-        rem = rem * 10 + digit;
-        let q = rem / divisor;
-        rem %= divisor;
-        if !out.is_empty() || q != 0 {
-            out.push(std::char::from_digit(q as u32, 10).unwrap_or('0'));
-        }
-    }
-    if out.is_empty() {
-        out.push('0');
-    }
-    (out, rem)
-}
-
 pub fn apply_defaults_pod_spec(spec: &mut PodSpec) {
     if spec.dns_policy.is_empty() {
         spec.dns_policy = DNS_POLICY_CLUSTER_FIRST.to_string();
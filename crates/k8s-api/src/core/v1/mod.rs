@@ -3,8 +3,18 @@
 //! This module contains all the core/v1 Kubernetes API types.
 
 mod types;
+mod deep_hash;
 mod internal_conversion;
-
+mod node_affinity;
+mod pod_resources;
+mod pod_status;
+mod toleration;
+
+pub use deep_hash::*;
+pub use node_affinity::*;
+pub use pod_resources::*;
+pub use pod_status::*;
+pub use toleration::*;
 pub use types::*;
 
 pub trait InternalConversion: Sized + serde::Serialize + serde::de::DeserializeOwned {
@@ -0,0 +1,411 @@
+//! Node selectors and node affinity
+//!
+//! Evaluates `NodeSelector`s against nodes the way the scheduler's
+//! NodeAffinity plugin and the volume binder do: terms are ORed, the
+//! requirements of a term ANDed, and a term with invalid requirements
+//! matches no node, its errors being reported only if no other term
+//! matches.
+
+use std::collections::BTreeMap;
+
+use super::*;
+
+/// The node field `matchFields` requirements may select on.
+pub const NODE_FIELD_SELECTOR_KEY_NODE_NAME: &str = "metadata.name";
+
+impl NodeSelectorRequirement {
+    /// Returns whether the node `labels` satisfy the requirement. `NotIn`
+    /// and `DoesNotExist` are satisfied by nodes without the label, `Gt`
+    /// and `Lt` compare the label and the value as integers.
+    pub fn matches_labels(&self, labels: &BTreeMap<String, String>) -> Result<bool, String> {
+        self.validate_label_requirement()?;
+        let label = labels.get(&self.key);
+        Ok(match self.operator.as_str() {
+            NODE_SELECTOR_OP_IN => label.is_some_and(|label| self.values.contains(label)),
+            NODE_SELECTOR_OP_NOT_IN => label.is_none_or(|label| !self.values.contains(label)),
+            NODE_SELECTOR_OP_EXISTS => label.is_some(),
+            NODE_SELECTOR_OP_DOES_NOT_EXIST => label.is_none(),
+            operator => {
+                let Some(label) = label.and_then(|label| label.parse::<i64>().ok()) else {
+                    return Ok(false);
+                };
+                // Validated to be an integer above.
+                let value: i64 = self.values[0].parse().unwrap_or_default();
+                if operator == NODE_SELECTOR_OP_GT {
+                    label > value
+                } else {
+                    label < value
+                }
+            }
+        })
+    }
+
+    fn validate_label_requirement(&self) -> Result<(), String> {
+        match self.operator.as_str() {
+            NODE_SELECTOR_OP_IN | NODE_SELECTOR_OP_NOT_IN if self.values.is_empty() => {
+                Err("for 'in', 'notin' operators, values set can't be empty".to_string())
+            }
+            NODE_SELECTOR_OP_EXISTS | NODE_SELECTOR_OP_DOES_NOT_EXIST if !self.values.is_empty() => {
+                Err("values set must be empty for exists and does not exist".to_string())
+            }
+            NODE_SELECTOR_OP_GT | NODE_SELECTOR_OP_LT if self.values.len() != 1 => {
+                Err("for 'Gt', 'Lt' operators, exactly one value is required".to_string())
+            }
+            NODE_SELECTOR_OP_GT | NODE_SELECTOR_OP_LT if self.values[0].parse::<i64>().is_err() => {
+                Err("for 'Gt', 'Lt' operators, the value must be an integer".to_string())
+            }
+            NODE_SELECTOR_OP_IN
+            | NODE_SELECTOR_OP_NOT_IN
+            | NODE_SELECTOR_OP_EXISTS
+            | NODE_SELECTOR_OP_DOES_NOT_EXIST
+            | NODE_SELECTOR_OP_GT
+            | NODE_SELECTOR_OP_LT => Ok(()),
+            operator => Err(format!("{:?} is not a valid label selector operator", operator)),
+        }
+    }
+
+    /// Returns whether the node satisfies the requirement as a `matchFields`
+    /// requirement, which only supports `In` and `NotIn` with a single
+    /// value. Fields other than `metadata.name` are empty.
+    pub fn matches_fields(&self, node: &Node) -> Result<bool, String> {
+        if self.values.len() != 1 {
+            let values: Vec<String> = self.values.iter().map(|value| format!("{:?}", value)).collect();
+            return Err(format!("values: Invalid value: [{}]: must have one element", values.join(", ")));
+        }
+        let field = match self.key.as_str() {
+            NODE_FIELD_SELECTOR_KEY_NODE_NAME => node.metadata.name.as_str(),
+            _ => "",
+        };
+        match self.operator.as_str() {
+            NODE_SELECTOR_OP_IN => Ok(field == self.values[0]),
+            NODE_SELECTOR_OP_NOT_IN => Ok(field != self.values[0]),
+            operator => Err(format!(
+                "operator: Unsupported value: {:?}: supported values: \"In\", \"NotIn\"",
+                operator
+            )),
+        }
+    }
+}
+
+impl NodeSelectorTerm {
+    /// Returns whether the term has no requirements, which makes it match
+    /// no node.
+    pub fn is_empty(&self) -> bool {
+        self.match_expressions.is_empty() && self.match_fields.is_empty()
+    }
+
+    /// Returns whether the node satisfies every requirement of the term.
+    /// All invalid requirements are reported.
+    pub fn matches(&self, node: &Node) -> Result<bool, Vec<String>> {
+        if self.is_empty() {
+            return Ok(false);
+        }
+        let mut errors = Vec::new();
+        let mut matched = true;
+        for (idx, requirement) in self.match_expressions.iter().enumerate() {
+            match requirement.matches_labels(&node.metadata.labels) {
+                Ok(ok) => matched &= ok,
+                Err(err) => errors.push(format!("matchExpressions[{}]: {}", idx, err)),
+            }
+        }
+        for (idx, requirement) in self.match_fields.iter().enumerate() {
+            match requirement.matches_fields(node) {
+                Ok(ok) => matched &= ok,
+                Err(err) => errors.push(format!("matchFields[{}].{}", idx, err)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(matched)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl NodeSelector {
+    /// Returns whether the node matches any term. Invalid terms are
+    /// skipped; their errors are returned if no valid term matches.
+    pub fn matches(&self, node: &Node) -> Result<bool, String> {
+        let mut errors = Vec::new();
+        for (idx, term) in self.node_selector_terms.iter().enumerate() {
+            match term.matches(node) {
+                Ok(true) => return Ok(true),
+                Ok(false) => {}
+                Err(term_errors) => errors.extend(
+                    term_errors
+                        .into_iter()
+                        .map(|err| format!("nodeSelectorTerms[{}].{}", idx, err)),
+                ),
+            }
+        }
+        if errors.is_empty() {
+            Ok(false)
+        } else {
+            Err(aggregate(errors))
+        }
+    }
+}
+
+impl PreferredSchedulingTerm {
+    /// Returns the weight the term adds to the score of a node it matches.
+    /// Terms of weight zero and empty terms match no node and are not
+    /// checked; invalid requirements of other terms are all reported.
+    pub fn score(&self, node: &Node) -> Result<i64, Vec<String>> {
+        if self.weight == 0 || self.preference.is_empty() {
+            return Ok(0);
+        }
+        match self.preference.matches(node) {
+            Ok(true) => Ok(i64::from(self.weight)),
+            Ok(false) => Ok(0),
+            Err(errors) => Err(errors.into_iter().map(|err| format!("preference.{}", err)).collect()),
+        }
+    }
+}
+
+/// Returns the NodeAffinity score of a node for `terms`, the sum of the
+/// weights of the terms it matches. Like building Go's
+/// `PreferredSchedulingTerms`, any invalid term makes the whole set an
+/// error rather than scoring zero.
+pub fn preferred_scheduling_terms_score(terms: &[PreferredSchedulingTerm], node: &Node) -> Result<i64, String> {
+    let mut errors = Vec::new();
+    let mut score = 0;
+    for (idx, term) in terms.iter().enumerate() {
+        match term.score(node) {
+            Ok(weight) => score += weight,
+            Err(term_errors) => errors.extend(term_errors.into_iter().map(|err| format!("[{}].{}", idx, err))),
+        }
+    }
+    if errors.is_empty() {
+        Ok(score)
+    } else {
+        Err(aggregate(errors))
+    }
+}
+
+/// Returns whether a pod may be scheduled to a node: the node must have
+/// every label of the pod's `nodeSelector` and match its required node
+/// affinity, if any.
+pub fn required_node_affinity_matches(pod: &Pod, node: &Node) -> Result<bool, String> {
+    let Some(spec) = &pod.spec else {
+        return Ok(true);
+    };
+    let selected = spec
+        .node_selector
+        .iter()
+        .all(|(key, value)| node.metadata.labels.get(key) == Some(value));
+    if !selected {
+        return Ok(false);
+    }
+    let required = spec
+        .affinity
+        .as_ref()
+        .and_then(|affinity| affinity.node_affinity.as_ref())
+        .and_then(|node_affinity| node_affinity.required_during_scheduling_ignored_during_execution.as_ref());
+    match required {
+        Some(required) => required.matches(node),
+        None => Ok(true),
+    }
+}
+
+/// Returns the NodeAffinity score of a node for a pod, from its preferred
+/// node affinity terms.
+pub fn preferred_node_affinity_score(pod: &Pod, node: &Node) -> Result<i64, String> {
+    pod.spec
+        .as_ref()
+        .and_then(|spec| spec.affinity.as_ref())
+        .and_then(|affinity| affinity.node_affinity.as_ref())
+        .map_or(Ok(0), |node_affinity| {
+            preferred_scheduling_terms_score(&node_affinity.preferred_during_scheduling_ignored_during_execution, node)
+        })
+}
+
+/// Checks that a persistent volume may be used on a node, as required by
+/// its node affinity.
+pub fn check_volume_node_affinity(volume: &PersistentVolume, node: &Node) -> Result<(), String> {
+    let required = volume
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.node_affinity.as_ref())
+        .and_then(|node_affinity| node_affinity.required.as_ref());
+    match required {
+        Some(required) if !required.matches(node)? => Err("no matching NodeSelectorTerms".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Joins errors the way Go prints an aggregate: a single error as is,
+/// several in brackets.
+fn aggregate(mut errors: Vec<String>) -> String {
+    if errors.len() == 1 {
+        errors.remove(0)
+    } else {
+        format!("[{}]", errors.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, labels: &[(&str, &str)]) -> Node {
+        let mut node = Node::default();
+        node.metadata.name = name.to_string();
+        node.metadata.labels = labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        node
+    }
+
+    fn requirement(key: &str, operator: &str, values: &[&str]) -> NodeSelectorRequirement {
+        NodeSelectorRequirement {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+        }
+    }
+
+    fn term(expressions: Vec<NodeSelectorRequirement>, fields: Vec<NodeSelectorRequirement>) -> NodeSelectorTerm {
+        NodeSelectorTerm {
+            match_expressions: expressions,
+            match_fields: fields,
+        }
+    }
+
+    #[test]
+    fn test_requirement_matches() {
+        let labels = node("n", &[("zone", "a"), ("gpus", "4")]).metadata.labels;
+        let cases = [
+            (requirement("zone", "In", &["a", "b"]), true),
+            (requirement("zone", "NotIn", &["a"]), false),
+            (requirement("region", "NotIn", &["a"]), true),
+            (requirement("zone", "Exists", &[]), true),
+            (requirement("region", "DoesNotExist", &[]), true),
+            (requirement("gpus", "Gt", &["2"]), true),
+            (requirement("gpus", "Lt", &["4"]), false),
+            (requirement("zone", "Gt", &["2"]), false),
+            (requirement("region", "Lt", &["2"]), false),
+        ];
+        for (requirement, expected) in cases {
+            assert_eq!(requirement.matches_labels(&labels), Ok(expected), "{:?}", requirement);
+        }
+        assert_eq!(
+            requirement("gpus", "Gt", &["two"]).matches_labels(&labels),
+            Err("for 'Gt', 'Lt' operators, the value must be an integer".to_string())
+        );
+        assert!(requirement("zone", "In", &[]).matches_labels(&labels).is_err());
+        assert!(requirement("zone", "Exists", &["a"]).matches_labels(&labels).is_err());
+        assert!(requirement("zone", "Equals", &["a"]).matches_labels(&labels).is_err());
+    }
+
+    #[test]
+    fn test_node_selector_matches() {
+        let node = node("node-1", &[("zone", "a")]);
+        let selector = |terms| NodeSelector {
+            node_selector_terms: terms,
+        };
+        assert_eq!(selector(vec![]).matches(&node), Ok(false));
+        assert_eq!(selector(vec![term(vec![], vec![])]).matches(&node), Ok(false));
+        assert_eq!(
+            selector(vec![term(vec![], vec![requirement("metadata.name", "In", &["node-1"])])]).matches(&node),
+            Ok(true)
+        );
+        assert_eq!(
+            selector(vec![term(
+                vec![requirement("zone", "In", &["a"])],
+                vec![requirement("metadata.name", "NotIn", &["node-1"])]
+            )])
+            .matches(&node),
+            Ok(false)
+        );
+        // Invalid terms are skipped if another term matches.
+        let invalid = term(vec![requirement("zone", "In", &[])], vec![requirement("metadata.name", "Exists", &["x"])]);
+        assert_eq!(
+            selector(vec![invalid.clone(), term(vec![requirement("zone", "Exists", &[])], vec![])]).matches(&node),
+            Ok(true)
+        );
+        assert_eq!(
+            selector(vec![invalid]).matches(&node),
+            Err("[nodeSelectorTerms[0].matchExpressions[0]: for 'in', 'notin' operators, values set can't be empty, \
+                 nodeSelectorTerms[0].matchFields[0].operator: Unsupported value: \"Exists\": supported values: \"In\", \"NotIn\"]"
+                .to_string())
+        );
+        let two_names = term(vec![], vec![requirement("metadata.name", "In", &["node-1", "node-2"])]);
+        assert_eq!(
+            selector(vec![two_names]).matches(&node),
+            Err("nodeSelectorTerms[0].matchFields[0].values: Invalid value: [\"node-1\", \"node-2\"]: must have one element"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn test_pod_node_affinity() {
+        let mut pod = Pod::default();
+        let spec = pod.spec.get_or_insert_with(Default::default);
+        spec.node_selector.insert("disk".to_string(), "ssd".to_string());
+        spec.affinity = Some(Affinity {
+            node_affinity: Some(NodeAffinity {
+                required_during_scheduling_ignored_during_execution: Some(NodeSelector {
+                    node_selector_terms: vec![term(vec![requirement("zone", "In", &["a", "b"])], vec![])],
+                }),
+                preferred_during_scheduling_ignored_during_execution: vec![
+                    PreferredSchedulingTerm {
+                        weight: 10,
+                        preference: term(vec![requirement("zone", "In", &["a"])], vec![]),
+                    },
+                    PreferredSchedulingTerm {
+                        weight: 5,
+                        preference: term(vec![requirement("gpu", "Exists", &[])], vec![]),
+                    },
+                    PreferredSchedulingTerm {
+                        weight: 50,
+                        preference: term(vec![], vec![]),
+                    },
+                ],
+            }),
+            ..Default::default()
+        });
+        let a = node("a", &[("disk", "ssd"), ("zone", "a"), ("gpu", "")]);
+        let b = node("b", &[("disk", "ssd"), ("zone", "b")]);
+        let c = node("c", &[("zone", "a")]);
+        assert_eq!(required_node_affinity_matches(&pod, &a), Ok(true));
+        assert_eq!(required_node_affinity_matches(&pod, &b), Ok(true));
+        assert_eq!(required_node_affinity_matches(&pod, &c), Ok(false));
+        assert_eq!(preferred_node_affinity_score(&pod, &a), Ok(15));
+        assert_eq!(preferred_node_affinity_score(&pod, &b), Ok(0));
+
+        // A malformed preference is an error, not a term that never matches.
+        let terms = [
+            PreferredSchedulingTerm {
+                weight: 10,
+                preference: term(vec![requirement("zone", "In", &["a"])], vec![]),
+            },
+            PreferredSchedulingTerm {
+                weight: 5,
+                preference: term(vec![requirement("gpus", "Gt", &["many"])], vec![]),
+            },
+            PreferredSchedulingTerm {
+                weight: 0,
+                preference: term(vec![requirement("zone", "Near", &[])], vec![]),
+            },
+        ];
+        assert_eq!(
+            preferred_scheduling_terms_score(&terms, &a),
+            Err("[1].preference.matchExpressions[0]: for 'Gt', 'Lt' operators, the value must be an integer".to_string())
+        );
+        assert_eq!(preferred_scheduling_terms_score(&terms[..1], &a), Ok(10));
+        assert_eq!(preferred_scheduling_terms_score(&terms[2..], &a), Ok(0));
+
+        let volume = PersistentVolume {
+            spec: Some(PersistentVolumeSpec {
+                node_affinity: Some(VolumeNodeAffinity {
+                    required: Some(NodeSelector {
+                        node_selector_terms: vec![term(vec![requirement("zone", "NotIn", &["b"])], vec![])],
+                    }),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(check_volume_node_affinity(&volume, &a), Ok(()));
+        assert_eq!(check_volume_node_affinity(&volume, &b), Err("no matching NodeSelectorTerms".to_string()));
+        assert_eq!(check_volume_node_affinity(&PersistentVolume::default(), &b), Ok(()));
+    }
+}
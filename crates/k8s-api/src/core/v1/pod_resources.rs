@@ -0,0 +1,404 @@
+//! Pod resource requests and limits
+//!
+//! The effective requests and limits of a pod, as `PodRequests` and
+//! `PodLimits` of `k8s.io/component-helpers/resource` compute them for the
+//! scheduler, the kubelet and quota: init containers run one at a time, so
+//! a pod needs the largest of them or the sum of its regular containers
+//! and sidecars, whichever is more.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use super::*;
+
+/// Options of `pod_requests` and `pod_limits`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PodResourcesOptions {
+    /// Accounts for in-place resizes with the resources reported in the
+    /// container statuses of the pod.
+    pub use_status_resources: bool,
+    /// Leaves the pod overhead out.
+    pub exclude_overhead: bool,
+    /// Requests used for containers not setting requests for these
+    /// resources. Requests set explicitly, even to zero, are kept.
+    pub non_missing_container_requests: ResourceList,
+    /// Ignores the pod-level resources of the pod.
+    pub skip_pod_level_resources: bool,
+}
+
+/// Resources that can be set at the pod level.
+pub const SUPPORTED_POD_LEVEL_RESOURCES: [&str; 2] = [RESOURCE_CPU, RESOURCE_MEMORY];
+
+pub fn is_supported_pod_level_resource(name: &str) -> bool {
+    SUPPORTED_POD_LEVEL_RESOURCES.contains(&name)
+}
+
+fn pod_level(pod: &Pod, select: fn(&ResourceRequirements) -> &ResourceList) -> Option<&ResourceList> {
+    let resources = pod.spec.as_ref()?.resources.as_ref()?;
+    let list = select(resources);
+    list.keys().any(|name| is_supported_pod_level_resource(name)).then_some(list)
+}
+
+/// Returns whether the pod sets pod-level requests or limits for a
+/// supported resource.
+pub fn is_pod_level_resources_set(pod: &Pod) -> bool {
+    is_pod_level_requests_set(pod) || is_pod_level_limits_set(pod)
+}
+
+/// Returns whether the pod sets pod-level requests for a supported
+/// resource.
+pub fn is_pod_level_requests_set(pod: &Pod) -> bool {
+    pod_level(pod, |resources| &resources.requests).is_some()
+}
+
+/// Returns whether the pod sets pod-level limits for a supported resource.
+pub fn is_pod_level_limits_set(pod: &Pod) -> bool {
+    pod_level(pod, |resources| &resources.limits).is_some()
+}
+
+fn resize_pending_reason(pod: &Pod) -> Option<&str> {
+    let status = pod.status.as_ref()?;
+    let condition = status
+        .conditions
+        .iter()
+        .find(|condition| condition.condition_type == POD_CONDITION_RESIZE_PENDING)?;
+    Some(&condition.reason)
+}
+
+/// Returns whether a resize of the pod is pending as infeasible.
+pub fn is_pod_resize_infeasible(pod: &Pod) -> bool {
+    resize_pending_reason(pod) == Some(POD_REASON_INFEASIBLE)
+}
+
+/// Returns whether a resize of the pod is pending as deferred.
+pub fn is_pod_resize_deferred(pod: &Pod) -> bool {
+    resize_pending_reason(pod) == Some(POD_REASON_DEFERRED)
+}
+
+/// Returns the requests of a pod: those of its containers, replaced by its
+/// pod-level requests for the resources it sets them for, plus its
+/// overhead.
+pub fn pod_requests(pod: &Pod, opts: &PodResourcesOptions) -> ResourceList {
+    let mut requests = aggregate_container_requests(pod, opts);
+    let Some(spec) = &pod.spec else {
+        return requests;
+    };
+    if !opts.skip_pod_level_resources {
+        if let Some(pod_requests) = pod_level(pod, |resources| &resources.requests) {
+            for (name, quantity) in pod_requests {
+                if is_supported_pod_level_resource(name) {
+                    requests.insert(name.clone(), quantity.clone());
+                }
+            }
+        }
+    }
+    if !opts.exclude_overhead {
+        add_resource_list(&mut requests, &spec.overhead);
+    }
+    requests
+}
+
+/// Returns the limits of a pod: those of its containers, replaced by its
+/// pod-level limits for the resources it sets them for. The overhead is
+/// added to the resources with a limit only, as the others are unbounded.
+pub fn pod_limits(pod: &Pod, opts: &PodResourcesOptions) -> ResourceList {
+    let mut limits = aggregate_container_limits(pod, opts);
+    let Some(spec) = &pod.spec else {
+        return limits;
+    };
+    if !opts.skip_pod_level_resources {
+        if let Some(pod_limits) = pod_level(pod, |resources| &resources.limits) {
+            for (name, quantity) in pod_limits {
+                if is_supported_pod_level_resource(name) {
+                    limits.insert(name.clone(), quantity.clone());
+                }
+            }
+        }
+    }
+    if !opts.exclude_overhead {
+        for (name, quantity) in &spec.overhead {
+            if let Some(limit) = limits.get_mut(name) {
+                if let Ok(sum) = limit.checked_add(quantity) {
+                    *limit = sum;
+                }
+            }
+        }
+    }
+    limits
+}
+
+/// Returns the requests of the containers of a pod, per the sidecar
+/// containers KEP: the sum of the regular containers and sidecars, or the
+/// most an init container needs alongside the sidecars started before it.
+pub fn aggregate_container_requests(pod: &Pod, opts: &PodResourcesOptions) -> ResourceList {
+    aggregate_container_resources(pod, opts, |pod, container, status| {
+        let requests = container_requests(container);
+        let Some((status, actual)) = status.and_then(|status| Some((status, status.resources.as_ref()?))) else {
+            return requests.clone();
+        };
+        if is_pod_resize_infeasible(pod) {
+            max_resource_lists(&[&actual.requests, &status.allocated_resources])
+        } else {
+            max_resource_lists(&[requests, &actual.requests, &status.allocated_resources])
+        }
+    })
+}
+
+/// Returns the limits of the containers of a pod, aggregated like
+/// `aggregate_container_requests`.
+pub fn aggregate_container_limits(pod: &Pod, opts: &PodResourcesOptions) -> ResourceList {
+    let opts = PodResourcesOptions {
+        non_missing_container_requests: ResourceList::new(),
+        ..opts.clone()
+    };
+    aggregate_container_resources(pod, &opts, |pod, container, status| {
+        let limits = container_limits(container);
+        let Some(actual) = status.and_then(|status| status.resources.as_ref()) else {
+            return limits.clone();
+        };
+        if is_pod_resize_infeasible(pod) {
+            actual.limits.clone()
+        } else {
+            max_resource_lists(&[limits, &actual.limits])
+        }
+    })
+}
+
+static EMPTY_RESOURCE_LIST: ResourceList = ResourceList::new();
+
+fn container_requests(container: &Container) -> &ResourceList {
+    container.resources.as_ref().map_or(&EMPTY_RESOURCE_LIST, |resources| &resources.requests)
+}
+
+fn container_limits(container: &Container) -> &ResourceList {
+    container.resources.as_ref().map_or(&EMPTY_RESOURCE_LIST, |resources| &resources.limits)
+}
+
+fn is_restartable_init_container(container: &Container) -> bool {
+    container.restart_policy.as_deref() == Some(CONTAINER_RESTART_POLICY_ALWAYS)
+}
+
+/// Aggregates the resources `effective` returns for each container given
+/// its status, which is only passed when status resources are used. The
+/// status of init containers that are not sidecars is never used, as they
+/// cannot be resized.
+fn aggregate_container_resources(
+    pod: &Pod,
+    opts: &PodResourcesOptions,
+    effective: impl Fn(&Pod, &Container, Option<&ContainerStatus>) -> ResourceList,
+) -> ResourceList {
+    let Some(spec) = &pod.spec else {
+        return ResourceList::new();
+    };
+    let mut statuses: BTreeMap<&str, &ContainerStatus> = BTreeMap::new();
+    if opts.use_status_resources {
+        if let Some(status) = &pod.status {
+            for container_status in status.container_statuses.iter().chain(&status.init_container_statuses) {
+                statuses.insert(&container_status.name, container_status);
+            }
+        }
+    }
+    let resources = |container: &Container, resizable: bool| {
+        let status = statuses.get(container.name.as_str()).copied().filter(|_| resizable);
+        let resources = effective(pod, container, status);
+        apply_non_missing(resources, &opts.non_missing_container_requests)
+    };
+
+    let mut total = ResourceList::new();
+    for container in &spec.containers {
+        add_resource_list(&mut total, &resources(container, true));
+    }
+
+    let mut sidecars = ResourceList::new();
+    let mut init_max = ResourceList::new();
+    for container in &spec.init_containers {
+        let restartable = is_restartable_init_container(container);
+        let mut container_resources = resources(container, restartable);
+        if restartable {
+            add_resource_list(&mut total, &container_resources);
+            add_resource_list(&mut sidecars, &container_resources);
+            container_resources = sidecars.clone();
+        } else {
+            add_resource_list(&mut container_resources, &sidecars);
+        }
+        max_resource_list(&mut init_max, &container_resources);
+    }
+
+    max_resource_list(&mut total, &init_max);
+    total
+}
+
+/// Returns `list` with the resources it lacks taken from `non_missing`.
+fn apply_non_missing(mut list: ResourceList, non_missing: &ResourceList) -> ResourceList {
+    for (name, quantity) in non_missing {
+        list.entry(name.clone()).or_insert_with(|| quantity.clone());
+    }
+    list
+}
+
+/// Adds the quantities of `other` to `list`. Quantities that do not parse
+/// are left out, as validation rejects them.
+pub fn add_resource_list(list: &mut ResourceList, other: &ResourceList) {
+    for (name, quantity) in other {
+        match list.get_mut(name) {
+            Some(value) => {
+                if let Ok(sum) = value.checked_add(quantity) {
+                    *value = sum;
+                }
+            }
+            None => {
+                list.insert(name.clone(), quantity.clone());
+            }
+        }
+    }
+}
+
+/// Raises the quantities of `list` to those of `other` where they are
+/// lower.
+pub fn max_resource_list(list: &mut ResourceList, other: &ResourceList) {
+    for (name, quantity) in other {
+        let greater = match list.get(name) {
+            Some(value) => quantity.cmp_value(value) == Ok(Ordering::Greater),
+            None => true,
+        };
+        if greater {
+            list.insert(name.clone(), quantity.clone());
+        }
+    }
+}
+
+fn max_resource_lists(lists: &[&ResourceList]) -> ResourceList {
+    let mut result = ResourceList::new();
+    for list in lists {
+        max_resource_list(&mut result, list);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_api_core::resource::Quantity;
+
+    fn list(entries: &[(&str, &str)]) -> ResourceList {
+        entries
+            .iter()
+            .map(|(name, quantity)| (name.to_string(), Quantity::new(*quantity)))
+            .collect()
+    }
+
+    fn container(name: &str, requests: &[(&str, &str)], limits: &[(&str, &str)], sidecar: bool) -> Container {
+        Container {
+            name: name.to_string(),
+            resources: Some(ResourceRequirements {
+                requests: list(requests),
+                limits: list(limits),
+                ..Default::default()
+            }),
+            restart_policy: sidecar.then(|| CONTAINER_RESTART_POLICY_ALWAYS.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn pod() -> Pod {
+        Pod {
+            spec: Some(PodSpec {
+                containers: vec![
+                    container("app", &[("cpu", "500m"), ("memory", "1Gi")], &[("cpu", "1"), ("memory", "1Gi")], false),
+                    container("metrics", &[("cpu", "100m")], &[], false),
+                ],
+                init_containers: vec![
+                    container("proxy", &[("cpu", "200m"), ("memory", "128Mi")], &[("memory", "128Mi")], true),
+                    container("migrate", &[("cpu", "1"), ("memory", "256Mi")], &[("cpu", "2")], false),
+                ],
+                overhead: list(&[("cpu", "50m"), ("memory", "64Mi")]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pod_requests() {
+        let pod = pod();
+        let opts = PodResourcesOptions::default();
+        // The migration needs 1200m alongside the proxy, more than the
+        // 800m of the containers and the proxy.
+        assert_eq!(pod_requests(&pod, &opts), list(&[("cpu", "1250m"), ("memory", "1216Mi")]));
+        let exclude_overhead = PodResourcesOptions {
+            exclude_overhead: true,
+            ..Default::default()
+        };
+        assert_eq!(pod_requests(&pod, &exclude_overhead), list(&[("cpu", "1200m"), ("memory", "1152Mi")]));
+        // Only resources with limits get the overhead.
+        assert_eq!(pod_limits(&pod, &opts), list(&[("cpu", "2050m"), ("memory", "1216Mi")]));
+
+        let non_missing = PodResourcesOptions {
+            exclude_overhead: true,
+            non_missing_container_requests: list(&[("memory", "200Mi")]),
+            ..Default::default()
+        };
+        assert_eq!(pod_requests(&pod, &non_missing), list(&[("cpu", "1200m"), ("memory", "1352Mi")]));
+
+        let mut pod_level = pod.clone();
+        pod_level.spec.as_mut().unwrap().resources = Some(ResourceRequirements {
+            requests: list(&[("cpu", "2"), ("example.com/gpu", "1")]),
+            limits: list(&[("memory", "4Gi")]),
+            ..Default::default()
+        });
+        assert!(is_pod_level_resources_set(&pod_level));
+        assert_eq!(
+            pod_requests(&pod_level, &exclude_overhead),
+            list(&[("cpu", "2"), ("memory", "1152Mi")])
+        );
+        assert_eq!(pod_limits(&pod_level, &exclude_overhead), list(&[("cpu", "2"), ("memory", "4Gi")]));
+        let skip = PodResourcesOptions {
+            skip_pod_level_resources: true,
+            ..exclude_overhead.clone()
+        };
+        assert_eq!(pod_requests(&pod_level, &skip), pod_requests(&pod, &exclude_overhead));
+    }
+
+    #[test]
+    fn test_pod_requests_resize() {
+        let mut pod = pod();
+        let app_status = ContainerStatus {
+            name: "app".to_string(),
+            allocated_resources: list(&[("cpu", "500m"), ("memory", "1Gi")]),
+            resources: Some(ResourceRequirements {
+                requests: list(&[("cpu", "700m"), ("memory", "512Mi")]),
+                limits: list(&[("cpu", "1500m")]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        pod.spec.as_mut().unwrap().init_containers.truncate(1);
+        pod.status = Some(PodStatus {
+            container_statuses: vec![app_status],
+            ..Default::default()
+        });
+        let opts = PodResourcesOptions {
+            exclude_overhead: true,
+            ..Default::default()
+        };
+        assert_eq!(pod_requests(&pod, &opts), list(&[("cpu", "800m"), ("memory", "1152Mi")]));
+
+        let opts = PodResourcesOptions {
+            use_status_resources: true,
+            ..opts
+        };
+        // The larger of the desired, actual and allocated resources.
+        assert_eq!(pod_requests(&pod, &opts), list(&[("cpu", "1"), ("memory", "1152Mi")]));
+        assert_eq!(pod_limits(&pod, &opts), list(&[("cpu", "1500m"), ("memory", "1152Mi")]));
+
+        // Infeasible resizes do not happen, so the desired resources count
+        // for nothing.
+        pod.status.as_mut().unwrap().conditions.push(PodCondition {
+            condition_type: POD_CONDITION_RESIZE_PENDING.to_string(),
+            reason: POD_REASON_INFEASIBLE.to_string(),
+            ..Default::default()
+        });
+        assert!(is_pod_resize_infeasible(&pod));
+        assert_eq!(pod_limits(&pod, &opts), list(&[("cpu", "1500m"), ("memory", "128Mi")]));
+    }
+}
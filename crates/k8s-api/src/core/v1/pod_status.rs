@@ -0,0 +1,357 @@
+//! Pod status
+//!
+//! Readiness, availability and the lifecycle of a pod as its status tells
+//! them, and the READY, STATUS and RESTARTS columns of `kubectl get pods`.
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::*;
+
+/// The `status.reason` of pods on a node that stopped responding.
+pub const NODE_UNREACHABLE_POD_REASON: &str = "NodeLost";
+
+impl PodStatus {
+    /// Returns the condition of the given type.
+    pub fn get_condition(&self, condition_type: &str) -> Option<&PodCondition> {
+        self.conditions
+            .iter()
+            .find(|condition| condition.condition_type == condition_type)
+    }
+
+    /// Returns the status of the container, init container or ephemeral
+    /// container with the given name.
+    pub fn container_status_by_name(&self, name: &str) -> Option<&ContainerStatus> {
+        self.container_statuses
+            .iter()
+            .chain(&self.init_container_statuses)
+            .chain(&self.ephemeral_container_statuses)
+            .find(|status| status.name == name)
+    }
+}
+
+impl Pod {
+    pub fn get_condition(&self, condition_type: &str) -> Option<&PodCondition> {
+        self.status.as_ref()?.get_condition(condition_type)
+    }
+
+    pub fn container_status_by_name(&self, name: &str) -> Option<&ContainerStatus> {
+        self.status.as_ref()?.container_status_by_name(name)
+    }
+
+    fn is_condition_true(&self, condition_type: &str) -> bool {
+        self.get_condition(condition_type)
+            .is_some_and(|condition| condition.status == CONDITION_TRUE)
+    }
+
+    /// Returns whether the `Ready` condition of the pod is true.
+    pub fn is_ready(&self) -> bool {
+        self.is_condition_true(POD_CONDITION_READY)
+    }
+
+    /// Returns whether the pod succeeded or failed, after which none of its
+    /// containers run again.
+    pub fn is_terminal(&self) -> bool {
+        let phase = self.status.as_ref().map(|status| status.phase.as_str());
+        matches!(phase, Some(POD_PHASE_SUCCEEDED | POD_PHASE_FAILED))
+    }
+
+    /// Returns whether the pod is bound to a node.
+    pub fn is_scheduled(&self) -> bool {
+        self.spec.as_ref().is_some_and(|spec| !spec.node_name.is_empty())
+    }
+
+    /// Returns whether the pod has been ready for at least
+    /// `min_ready_seconds` at `now`, as workload controllers count available
+    /// replicas.
+    pub fn is_available(&self, min_ready_seconds: i32, now: DateTime<Utc>) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+        if min_ready_seconds == 0 {
+            return true;
+        }
+        self.get_condition(POD_CONDITION_READY)
+            .and_then(|condition| condition.last_transition_time.as_ref())
+            .and_then(|time| time.0)
+            .is_some_and(|ready| ready + Duration::seconds(i64::from(min_ready_seconds)) < now)
+    }
+
+    /// Returns the STATUS column of `kubectl get pods` for the pod, e.g.
+    /// `Running`, `Init:1/2`, `CrashLoopBackOff`, `Completed`, `NotReady` or
+    /// `Terminating`.
+    pub fn display_status(&self) -> String {
+        self.display().reason
+    }
+
+    /// Returns the READY column of `kubectl get pods` for the pod: ready
+    /// containers out of the containers and sidecars, e.g. `1/2`.
+    pub fn display_ready(&self) -> String {
+        let display = self.display();
+        format!("{}/{}", display.ready, display.total)
+    }
+
+    /// Returns the RESTARTS column of `kubectl get pods` for the pod: the
+    /// restarts of its init containers while it initializes, and of its
+    /// containers and sidecars after.
+    pub fn restart_count(&self) -> i32 {
+        self.display().restarts
+    }
+
+    /// Evaluates the pod as kubectl's pod printer does.
+    fn display(&self) -> PodDisplay {
+        let empty = PodStatus::default();
+        let status = self.status.as_ref().unwrap_or(&empty);
+        let init_containers = self.spec.as_ref().map(|spec| spec.init_containers.as_slice()).unwrap_or_default();
+        let is_sidecar = |name: &str| {
+            init_containers
+                .iter()
+                .any(|container| container.name == name && container.restart_policy.as_deref() == Some(CONTAINER_RESTART_POLICY_ALWAYS))
+        };
+
+        let mut display = PodDisplay {
+            reason: if status.reason.is_empty() { status.phase.clone() } else { status.reason.clone() },
+            total: self.spec.as_ref().map_or(0, |spec| spec.containers.len())
+                + init_containers.iter().filter(|container| is_sidecar(&container.name)).count(),
+            ..Default::default()
+        };
+        let scheduling_gated = status.conditions.iter().any(|condition| {
+            condition.condition_type == POD_CONDITION_SCHEDULED && condition.reason == POD_REASON_SCHEDULING_GATED
+        });
+        if scheduling_gated {
+            display.reason = POD_REASON_SCHEDULING_GATED.to_string();
+        }
+
+        // Init containers run in order, so the first one that has not
+        // completed, or sidecar that has not started, tells how far
+        // initialization got.
+        let mut initializing = false;
+        let mut sidecar_restarts = 0;
+        for (i, container) in status.init_container_statuses.iter().enumerate() {
+            display.restarts += container.restart_count;
+            let sidecar = is_sidecar(&container.name);
+            if sidecar {
+                sidecar_restarts += container.restart_count;
+            }
+            let state = container.state.clone().unwrap_or_default();
+            match (&state.terminated, &state.waiting) {
+                (Some(terminated), _) if terminated.exit_code == 0 => continue,
+                _ if sidecar && container.started == Some(true) => {
+                    display.ready += usize::from(container.ready);
+                    continue;
+                }
+                (Some(terminated), _) => {
+                    display.reason = if !terminated.reason.is_empty() {
+                        format!("Init:{}", terminated.reason)
+                    } else if let Some(signal) = terminated.signal.filter(|signal| *signal != 0) {
+                        format!("Init:Signal:{}", signal)
+                    } else {
+                        format!("Init:ExitCode:{}", terminated.exit_code)
+                    };
+                }
+                (None, Some(waiting)) if !waiting.reason.is_empty() && waiting.reason != "PodInitializing" => {
+                    display.reason = format!("Init:{}", waiting.reason);
+                }
+                _ => display.reason = format!("Init:{}/{}", i, init_containers.len()),
+            }
+            initializing = true;
+            break;
+        }
+
+        if !initializing || self.is_condition_true(POD_CONDITION_INITIALIZED) {
+            display.restarts = sidecar_restarts;
+            let mut has_running = false;
+            for container in status.container_statuses.iter().rev() {
+                display.restarts += container.restart_count;
+                let state = container.state.clone().unwrap_or_default();
+                if let Some(waiting) = state.waiting.as_ref().filter(|waiting| !waiting.reason.is_empty()) {
+                    display.reason = waiting.reason.clone();
+                } else if let Some(terminated) = &state.terminated {
+                    display.reason = if !terminated.reason.is_empty() {
+                        terminated.reason.clone()
+                    } else if let Some(signal) = terminated.signal.filter(|signal| *signal != 0) {
+                        format!("Signal:{}", signal)
+                    } else {
+                        format!("ExitCode:{}", terminated.exit_code)
+                    };
+                } else if container.ready && state.running.is_some() {
+                    has_running = true;
+                    display.ready += 1;
+                }
+            }
+            // A pod whose last container completed while others still run
+            // is not completed.
+            if display.reason == "Completed" && has_running {
+                display.reason = if self.is_condition_true(POD_CONDITION_READY) { "Running" } else { "NotReady" }.to_string();
+            }
+        }
+
+        if self.metadata.deletion_timestamp.is_some() {
+            if status.reason == NODE_UNREACHABLE_POD_REASON {
+                display.reason = "Unknown".to_string();
+            } else if !self.is_terminal() {
+                display.reason = "Terminating".to_string();
+            }
+        }
+        display
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct PodDisplay {
+    ready: usize,
+    total: usize,
+    reason: String,
+    restarts: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_apimachinery::apis::meta::v1::Time;
+
+    fn container(name: &str, sidecar: bool) -> Container {
+        Container {
+            name: name.to_string(),
+            restart_policy: sidecar.then(|| CONTAINER_RESTART_POLICY_ALWAYS.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn running(name: &str, ready: bool, restarts: i32) -> ContainerStatus {
+        ContainerStatus {
+            name: name.to_string(),
+            ready,
+            started: Some(true),
+            restart_count: restarts,
+            state: Some(ContainerState {
+                running: Some(Default::default()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn waiting(name: &str, reason: &str, restarts: i32) -> ContainerStatus {
+        ContainerStatus {
+            name: name.to_string(),
+            restart_count: restarts,
+            state: Some(ContainerState {
+                waiting: Some(ContainerStateWaiting {
+                    reason: reason.to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn terminated(name: &str, reason: &str, exit_code: i32) -> ContainerStatus {
+        ContainerStatus {
+            name: name.to_string(),
+            state: Some(ContainerState {
+                terminated: Some(ContainerStateTerminated {
+                    reason: reason.to_string(),
+                    exit_code,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn condition(condition_type: &str, status: &str) -> PodCondition {
+        PodCondition {
+            condition_type: condition_type.to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn pod(init: Vec<ContainerStatus>, containers: Vec<ContainerStatus>) -> Pod {
+        Pod {
+            spec: Some(PodSpec {
+                init_containers: vec![container("proxy", true), container("migrate", false)],
+                containers: vec![container("app", false), container("worker", false)],
+                ..Default::default()
+            }),
+            status: Some(PodStatus {
+                phase: POD_PHASE_RUNNING.to_string(),
+                init_container_statuses: init,
+                container_statuses: containers,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_display_status() {
+        let initialized = || vec![running("proxy", true, 1), terminated("migrate", "Completed", 0)];
+
+        let mut ready = pod(initialized(), vec![running("app", true, 2), running("worker", true, 0)]);
+        ready.status.as_mut().unwrap().conditions = vec![condition(POD_CONDITION_READY, CONDITION_TRUE)];
+        assert_eq!(ready.display_status(), "Running");
+        assert_eq!(ready.display_ready(), "3/3");
+        assert_eq!(ready.restart_count(), 3);
+        assert!(ready.is_ready());
+
+        let cases = [
+            (pod(vec![], vec![]), "Running"),
+            (pod(vec![waiting("proxy", "PodInitializing", 0)], vec![]), "Init:0/2"),
+            (pod(vec![running("proxy", false, 0), waiting("migrate", "", 0)], vec![]), "Init:1/2"),
+            (pod(vec![running("proxy", true, 0), waiting("migrate", "CrashLoopBackOff", 4)], vec![]), "Init:CrashLoopBackOff"),
+            (pod(vec![running("proxy", true, 0), terminated("migrate", "", 3)], vec![]), "Init:ExitCode:3"),
+            (pod(initialized(), vec![running("app", true, 0), waiting("worker", "CrashLoopBackOff", 5)]), "CrashLoopBackOff"),
+            (pod(initialized(), vec![running("app", true, 0), terminated("worker", "Completed", 0)]), "NotReady"),
+            (pod(initialized(), vec![terminated("app", "", 137), running("worker", true, 0)]), "ExitCode:137"),
+        ];
+        for (pod, expected) in cases {
+            assert_eq!(pod.display_status(), expected, "{:?}", pod.status);
+        }
+
+        // A sidecar that has not started holds up initialization, and the
+        // restarts of the init containers count while initializing.
+        let crashing = pod(vec![running("proxy", true, 0), waiting("migrate", "CrashLoopBackOff", 4)], vec![]);
+        assert_eq!(crashing.restart_count(), 4);
+        assert_eq!(crashing.display_ready(), "1/3");
+
+        let mut completed = pod(initialized(), vec![terminated("app", "Completed", 0), terminated("worker", "Completed", 0)]);
+        completed.status.as_mut().unwrap().phase = POD_PHASE_SUCCEEDED.to_string();
+        assert_eq!(completed.display_status(), "Completed");
+        assert!(completed.is_terminal());
+
+        let mut gated = pod(vec![], vec![]);
+        gated.status.as_mut().unwrap().phase = POD_PHASE_PENDING.to_string();
+        gated.status.as_mut().unwrap().conditions = vec![PodCondition {
+            reason: POD_REASON_SCHEDULING_GATED.to_string(),
+            ..condition(POD_CONDITION_SCHEDULED, CONDITION_FALSE)
+        }];
+        assert_eq!(gated.display_status(), "SchedulingGated");
+        assert!(!gated.is_scheduled());
+
+        ready.metadata.deletion_timestamp = Some(Time::now());
+        assert_eq!(ready.display_status(), "Terminating");
+        completed.metadata.deletion_timestamp = Some(Time::now());
+        assert_eq!(completed.display_status(), "Completed");
+        ready.status.as_mut().unwrap().reason = NODE_UNREACHABLE_POD_REASON.to_string();
+        assert_eq!(ready.display_status(), "Unknown");
+    }
+
+    #[test]
+    fn test_is_available() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut pod = pod(vec![], vec![running("app", true, 0)]);
+        assert!(!pod.is_available(0, now));
+        pod.status.as_mut().unwrap().conditions = vec![PodCondition {
+            last_transition_time: Some(Time(Some(now - Duration::seconds(10)))),
+            ..condition(POD_CONDITION_READY, CONDITION_TRUE)
+        }];
+        assert!(pod.is_available(0, now));
+        assert!(pod.is_available(9, now));
+        assert!(!pod.is_available(10, now));
+        assert_eq!(pod.container_status_by_name("app").map(|status| status.ready), Some(true));
+        assert!(pod.container_status_by_name("proxy").is_none());
+    }
+}
@@ -0,0 +1,203 @@
+//! Taints and tolerations
+//!
+//! Whether the tolerations of a pod tolerate the taints of a node, as the
+//! scheduler's TaintToleration plugin and the taint eviction controller
+//! decide.
+
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::*;
+
+impl Toleration {
+    /// Returns whether the toleration tolerates `taint`. An empty key with
+    /// operator `Exists` tolerates every taint, an empty effect every
+    /// effect, and an empty operator means `Equal`.
+    pub fn tolerates(&self, taint: &Taint) -> bool {
+        if !self.effect.is_empty() && self.effect != taint.effect {
+            return false;
+        }
+        if !self.key.is_empty() && self.key != taint.key {
+            return false;
+        }
+        match self.operator.as_str() {
+            "" | TOLERATION_OP_EQUAL => self.value == taint.value,
+            TOLERATION_OP_EXISTS => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the toleration has the key, effect, operator and
+    /// value of `other`, regardless of `tolerationSeconds`.
+    pub fn matches(&self, other: &Toleration) -> bool {
+        self.key == other.key
+            && self.effect == other.effect
+            && self.operator == other.operator
+            && self.value == other.value
+    }
+}
+
+impl Taint {
+    /// Returns whether the taint has the key and effect of `other`, which
+    /// makes them the same taint of a node.
+    pub fn matches(&self, other: &Taint) -> bool {
+        self.key == other.key && self.effect == other.effect
+    }
+}
+
+impl fmt::Display for Taint {
+    /// Formats the taint as `kubectl taint` takes it, `key=value:effect`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.value.is_empty() {
+            write!(f, "{}:{}", self.key, self.effect)
+        } else {
+            write!(f, "{}={}:{}", self.key, self.value, self.effect)
+        }
+    }
+}
+
+/// Returns whether any of `tolerations` tolerates `taint`.
+pub fn tolerations_tolerate_taint(tolerations: &[Toleration], taint: &Taint) -> bool {
+    tolerations.iter().any(|toleration| toleration.tolerates(taint))
+}
+
+/// Returns the first of the `taints` passing `filter` that none of
+/// `tolerations` tolerates. The scheduler only considers `NoSchedule` and
+/// `NoExecute` taints, see `do_not_schedule_taints`.
+pub fn find_untolerated_taint<'a>(
+    taints: &'a [Taint],
+    tolerations: &[Toleration],
+    filter: impl Fn(&Taint) -> bool,
+) -> Option<&'a Taint> {
+    taints
+        .iter()
+        .filter(|taint| filter(taint))
+        .find(|taint| !tolerations_tolerate_taint(tolerations, taint))
+}
+
+/// The taints filter of the scheduler: taints with effect `NoSchedule` or
+/// `NoExecute`, which keep pods not tolerating them off a node.
+pub fn do_not_schedule_taints(taint: &Taint) -> bool {
+    taint.effect == TAINT_EFFECT_NO_SCHEDULE || taint.effect == TAINT_EFFECT_NO_EXECUTE
+}
+
+/// Returns how long a pod with `tolerations` may keep running on a node
+/// with `taints`: `None` if it is not evicted, zero if it is evicted right
+/// away because it does not tolerate every `NoExecute` taint, and otherwise
+/// the smallest `tolerationSeconds` of the tolerations tolerating them.
+pub fn no_execute_toleration_seconds(taints: &[Taint], tolerations: &[Toleration]) -> Option<i64> {
+    let mut seconds: Option<i64> = None;
+    for taint in taints.iter().filter(|taint| taint.effect == TAINT_EFFECT_NO_EXECUTE) {
+        // Like the eviction controller, only the first toleration
+        // tolerating a taint counts.
+        let Some(toleration) = tolerations.iter().find(|toleration| toleration.tolerates(taint)) else {
+            return Some(0);
+        };
+        if let Some(toleration_seconds) = toleration.toleration_seconds {
+            let toleration_seconds = toleration_seconds.max(0);
+            seconds = Some(seconds.map_or(toleration_seconds, |seconds| seconds.min(toleration_seconds)));
+        }
+    }
+    seconds
+}
+
+/// Returns when the taint eviction controller evicts a pod with
+/// `tolerations` from a node with `taints`, counting from `start`, when it
+/// observed the taints; `None` if the pod is never evicted.
+pub fn no_execute_eviction_time(
+    taints: &[Taint],
+    tolerations: &[Toleration],
+    start: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    no_execute_toleration_seconds(taints, tolerations).map(|seconds| start + Duration::seconds(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taint(key: &str, value: &str, effect: &str) -> Taint {
+        Taint {
+            key: key.to_string(),
+            value: value.to_string(),
+            effect: effect.to_string(),
+            time_added: None,
+        }
+    }
+
+    fn toleration(key: &str, operator: &str, value: &str, effect: &str, seconds: Option<i64>) -> Toleration {
+        Toleration {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            value: value.to_string(),
+            effect: effect.to_string(),
+            toleration_seconds: seconds,
+        }
+    }
+
+    #[test]
+    fn test_tolerates() {
+        let dedicated = taint("dedicated", "gpu", TAINT_EFFECT_NO_SCHEDULE);
+        let cases = [
+            (toleration("dedicated", "Equal", "gpu", "NoSchedule", None), true),
+            (toleration("dedicated", "", "gpu", "", None), true),
+            (toleration("dedicated", "Equal", "cpu", "NoSchedule", None), false),
+            (toleration("dedicated", "Exists", "", "NoSchedule", None), true),
+            (toleration("dedicated", "Exists", "", "NoExecute", None), false),
+            (toleration("", "Exists", "", "", None), true),
+            (toleration("", "Exists", "", "PreferNoSchedule", None), false),
+            (toleration("other", "Exists", "", "", None), false),
+            (toleration("dedicated", "Lt", "gpu", "", None), false),
+        ];
+        for (toleration, expected) in cases {
+            assert_eq!(toleration.tolerates(&dedicated), expected, "{:?}", toleration);
+        }
+        assert_eq!(dedicated.to_string(), "dedicated=gpu:NoSchedule");
+        assert_eq!(taint("node.kubernetes.io/unreachable", "", "NoExecute").to_string(), "node.kubernetes.io/unreachable:NoExecute");
+        assert!(dedicated.matches(&taint("dedicated", "other", "NoSchedule")));
+
+        let evict_later = toleration("dedicated", "Exists", "", "NoExecute", Some(300));
+        assert!(evict_later.matches(&toleration("dedicated", "Exists", "", "NoExecute", None)));
+        assert!(!evict_later.matches(&toleration("dedicated", "Equal", "", "NoExecute", Some(300))));
+    }
+
+    #[test]
+    fn test_find_untolerated_taint() {
+        let taints = [
+            taint("soft", "", TAINT_EFFECT_PREFER_NO_SCHEDULE),
+            taint("dedicated", "gpu", TAINT_EFFECT_NO_SCHEDULE),
+            taint("unreachable", "", TAINT_EFFECT_NO_EXECUTE),
+        ];
+        let tolerations = [toleration("dedicated", "Equal", "gpu", "", None)];
+        assert_eq!(
+            find_untolerated_taint(&taints, &tolerations, do_not_schedule_taints),
+            Some(&taints[2])
+        );
+        assert_eq!(find_untolerated_taint(&taints, &tolerations, |_| true), Some(&taints[0]));
+        assert_eq!(find_untolerated_taint(&taints, &[toleration("", "Exists", "", "", None)], |_| true), None);
+    }
+
+    #[test]
+    fn test_no_execute_eviction() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let taints = [
+            taint("unreachable", "", TAINT_EFFECT_NO_EXECUTE),
+            taint("not-ready", "", TAINT_EFFECT_NO_EXECUTE),
+            taint("dedicated", "", TAINT_EFFECT_NO_SCHEDULE),
+        ];
+        let tolerations = [
+            toleration("unreachable", "Exists", "", "NoExecute", Some(300)),
+            toleration("not-ready", "Exists", "", "NoExecute", Some(60)),
+        ];
+        assert_eq!(no_execute_toleration_seconds(&taints, &tolerations), Some(60));
+        assert_eq!(no_execute_eviction_time(&taints, &tolerations, start), Some(start + Duration::seconds(60)));
+        assert_eq!(no_execute_toleration_seconds(&taints, &tolerations[..1]), Some(0));
+        assert_eq!(no_execute_eviction_time(&taints, &tolerations[..1], start), Some(start));
+        let forever = [toleration("", "Exists", "", "", None)];
+        assert_eq!(no_execute_eviction_time(&taints, &forever, start), None);
+        assert_eq!(no_execute_toleration_seconds(&taints[2..], &[]), None);
+        let negative = [toleration("", "Exists", "", "", Some(-5))];
+        assert_eq!(no_execute_toleration_seconds(&taints, &negative), Some(0));
+    }
+}
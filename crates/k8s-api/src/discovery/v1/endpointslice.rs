@@ -0,0 +1,577 @@
+//! EndpointSlice generation from Services and Pods
+//!
+//! How the EndpointSlice controller turns a Service and the Pods it selects
+//! into EndpointSlices: target port resolution, endpoint conditions,
+//! per-family addresses, traffic distribution hints, slice labels and the
+//! per-slice endpoint limit, following `endpointslice/reconciler.go`,
+//! `endpointslice/utils.go`, `endpointslice/trafficdist` and
+//! `pkg/api/v1/pod/util.go`.
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use k8s_api_core::IntOrString;
+use k8s_apimachinery::apis::meta::v1::{ObjectMeta, OwnerReference, TypeMeta};
+
+use crate::core::v1::{
+    Node, ObjectReference, Pod, Service, ServicePort, CONTAINER_RESTART_POLICY_ALWAYS, IP_FAMILY_IPV4,
+    IP_FAMILY_IPV6, POD_PHASE_FAILED, POD_PHASE_SUCCEEDED, PROTOCOL_TCP, SERVICE_TRAFFIC_DISTRIBUTION_PREFER_CLOSE,
+    SERVICE_TRAFFIC_DISTRIBUTION_PREFER_SAME_NODE, SERVICE_TRAFFIC_DISTRIBUTION_PREFER_SAME_ZONE,
+    SERVICE_TYPE_EXTERNAL_NAME,
+};
+
+use super::*;
+
+/// Label naming the Service an EndpointSlice belongs to.
+pub const LABEL_SERVICE_NAME: &str = "kubernetes.io/service-name";
+/// Label naming the controller or entity managing an EndpointSlice.
+pub const LABEL_MANAGED_BY: &str = "endpointslice.kubernetes.io/managed-by";
+/// Label set on the EndpointSlices of headless Services.
+pub const LABEL_HEADLESS_SERVICE: &str = "service.kubernetes.io/headless";
+/// Value of the managed-by label on slices owned by the EndpointSlice controller.
+pub const ENDPOINT_SLICE_CONTROLLER_NAME: &str = "endpointslice-controller.k8s.io";
+/// Default number of endpoints the controller places in a single slice.
+pub const MAX_ENDPOINTS_PER_SLICE: usize = 100;
+
+const LABEL_TOPOLOGY_ZONE: &str = "topology.kubernetes.io/zone";
+const CLUSTER_IP_NONE: &str = "None";
+
+/// Generates the EndpointSlices the controller would maintain for
+/// `service`, given the Pods and Nodes in the cluster.
+///
+/// Services without a selector and ExternalName Services get no slices.
+/// Pods whose Node is not in `nodes` are left out, as the controller does
+/// until the Node shows up, unless the Service publishes not-ready
+/// addresses, in which case they get an endpoint without a zone. Every
+/// address type of the Service without endpoints gets an empty placeholder
+/// slice.
+pub fn generate_endpoint_slices(service: &Service, pods: &[Pod], nodes: &[Node]) -> Vec<EndpointSlice> {
+    let Some(spec) = service.spec.as_ref() else {
+        return Vec::new();
+    };
+    if spec.selector.is_empty() || spec.service_type == SERVICE_TYPE_EXTERNAL_NAME {
+        return Vec::new();
+    }
+
+    let address_types = service_address_types(service);
+    // Endpoints grouped by address type and by the ports they resolve to.
+    let mut groups: Vec<(&str, Vec<EndpointPort>, Vec<Endpoint>)> = Vec::new();
+    for pod in pods {
+        if pod.metadata.namespace != service.metadata.namespace
+            || !spec.selector.iter().all(|(key, value)| pod.metadata.labels.get(key) == Some(value))
+            || !should_pod_be_in_endpoints(pod)
+        {
+            continue;
+        }
+        let node_name = pod.spec.as_ref().map(|spec| spec.node_name.as_str()).unwrap_or_default();
+        let node = nodes.iter().find(|node| node.metadata.name == node_name);
+        if node.is_none() && !spec.publish_not_ready_addresses {
+            continue;
+        }
+        let ports = endpoint_ports(service, pod);
+        for address_type in &address_types {
+            let endpoint = pod_to_endpoint(pod, node, service, address_type);
+            if endpoint.addresses.is_empty() {
+                continue;
+            }
+            match groups.iter_mut().find(|group| group.0 == *address_type && group.1 == ports) {
+                Some(group) => group.2.push(endpoint),
+                None => groups.push((address_type, ports.clone(), vec![endpoint])),
+            }
+        }
+    }
+
+    let mut slices = Vec::new();
+    for address_type in &address_types {
+        let first = slices.len();
+        for (_, ports, endpoints) in groups.iter().filter(|group| group.0 == *address_type) {
+            for chunk in endpoints.chunks(MAX_ENDPOINTS_PER_SLICE) {
+                let mut slice = new_endpoint_slice(service, address_type, ports.clone());
+                slice.endpoints = chunk.to_vec();
+                slices.push(slice);
+            }
+        }
+        if slices.len() == first {
+            slices.push(new_endpoint_slice(service, address_type, Vec::new()));
+        }
+    }
+
+    let traffic_distribution = spec.traffic_distribution.as_deref();
+    for slice in &mut slices {
+        set_traffic_distribution_hints(slice, traffic_distribution);
+        apply_defaults_endpoint_slice(slice);
+    }
+    slices
+}
+
+/// Resolves the container port a Service port targets on `pod`, like
+/// `podutil.FindPort`. Named target ports are looked up among the ports of
+/// the pod's containers and sidecar init containers with the same protocol.
+pub fn find_port(pod: &Pod, service_port: &ServicePort) -> Result<i32, String> {
+    let name = match &service_port.target_port {
+        None => return Ok(service_port.port),
+        Some(IntOrString::Int(port)) => return Ok(*port),
+        Some(IntOrString::String(name)) => name,
+    };
+    let protocol = protocol_or_tcp(&service_port.protocol);
+    if let Some(spec) = pod.spec.as_ref() {
+        let sidecars = spec
+            .init_containers
+            .iter()
+            .filter(|container| container.restart_policy.as_deref() == Some(CONTAINER_RESTART_POLICY_ALWAYS));
+        for container in spec.containers.iter().chain(sidecars) {
+            if let Some(port) = container
+                .ports
+                .iter()
+                .find(|port| port.name == *name && protocol_or_tcp(&port.protocol) == protocol)
+            {
+                return Ok(port.container_port);
+            }
+        }
+    }
+    Err(format!("no suitable port for manifest: {}", pod.metadata.uid))
+}
+
+/// Address types the Service's slices carry: its IP families, or for
+/// Services predating dual-stack the family of the cluster IP, or both
+/// families for headless Services.
+fn service_address_types(service: &Service) -> Vec<&'static str> {
+    let Some(spec) = service.spec.as_ref() else {
+        return Vec::new();
+    };
+    let mut address_types = Vec::new();
+    for (family, address_type) in [(IP_FAMILY_IPV4, ADDRESS_TYPE_IPV4), (IP_FAMILY_IPV6, ADDRESS_TYPE_IPV6)] {
+        if spec.ip_families.iter().any(|f| f == family) {
+            address_types.push(address_type);
+        }
+    }
+    if !address_types.is_empty() {
+        return address_types;
+    }
+    if is_service_ip_set(service) {
+        if is_ipv6(&spec.cluster_i_p) {
+            return vec![ADDRESS_TYPE_IPV6];
+        }
+        return vec![ADDRESS_TYPE_IPV4];
+    }
+    vec![ADDRESS_TYPE_IPV4, ADDRESS_TYPE_IPV6]
+}
+
+/// Reports whether the pod gets an endpoint: it must not be terminal and
+/// must have an IP. Terminating pods are kept with the terminating
+/// condition set.
+fn should_pod_be_in_endpoints(pod: &Pod) -> bool {
+    let Some(status) = pod.status.as_ref() else {
+        return false;
+    };
+    if status.phase == POD_PHASE_SUCCEEDED || status.phase == POD_PHASE_FAILED {
+        return false;
+    }
+    !status.pod_i_p.is_empty() || !status.pod_i_ps.is_empty()
+}
+
+fn endpoint_ports(service: &Service, pod: &Pod) -> Vec<EndpointPort> {
+    let Some(spec) = service.spec.as_ref() else {
+        return Vec::new();
+    };
+    spec.ports
+        .iter()
+        .filter_map(|service_port| {
+            let port = find_port(pod, service_port).ok()?;
+            Some(EndpointPort {
+                name: Some(service_port.name.clone()),
+                protocol: Some(service_port.protocol.clone()).filter(|protocol| !protocol.is_empty()),
+                port: Some(port),
+                app_protocol: service_port.app_protocol.clone(),
+            })
+        })
+        .collect()
+}
+
+fn pod_to_endpoint(pod: &Pod, node: Option<&Node>, service: &Service, address_type: &str) -> Endpoint {
+    let spec = service.spec.as_ref();
+    let publish_not_ready_addresses = spec.is_some_and(|spec| spec.publish_not_ready_addresses);
+    let serving = pod.is_ready();
+    let terminating = pod.metadata.deletion_timestamp.as_ref().is_some_and(|time| !time.is_zero());
+    // A terminating pod is never ready unless not-ready addresses are published.
+    let ready = publish_not_ready_addresses || (serving && !terminating);
+
+    let addresses = pod
+        .status
+        .iter()
+        .flat_map(|status| &status.pod_i_ps)
+        .filter(|pod_ip| (address_type == ADDRESS_TYPE_IPV6) == is_ipv6(&pod_ip.ip))
+        .map(|pod_ip| pod_ip.ip.clone())
+        .collect();
+
+    let pod_spec = pod.spec.as_ref();
+    let node_name = pod_spec.map(|spec| spec.node_name.clone()).filter(|name| !name.is_empty());
+    let zone = node
+        .and_then(|node| node.metadata.labels.get(LABEL_TOPOLOGY_ZONE))
+        .filter(|zone| !zone.is_empty())
+        .cloned();
+    let hostname = pod_spec
+        .filter(|spec| {
+            !spec.hostname.is_empty()
+                && spec.subdomain == service.metadata.name
+                && pod.metadata.namespace == service.metadata.namespace
+        })
+        .map(|spec| spec.hostname.clone());
+
+    Endpoint {
+        addresses,
+        conditions: Some(EndpointConditions {
+            ready: Some(ready),
+            serving: Some(serving),
+            terminating: Some(terminating),
+        }),
+        hostname,
+        target_ref: Some(ObjectReference {
+            kind: "Pod".to_string(),
+            namespace: pod.metadata.namespace.clone(),
+            name: pod.metadata.name.clone(),
+            uid: pod.metadata.uid.clone(),
+            ..Default::default()
+        }),
+        node_name,
+        zone,
+        ..Default::default()
+    }
+}
+
+fn new_endpoint_slice(service: &Service, address_type: &str, ports: Vec<EndpointPort>) -> EndpointSlice {
+    EndpointSlice {
+        type_meta: TypeMeta::new("discovery.k8s.io/v1", "EndpointSlice"),
+        metadata: ObjectMeta {
+            generate_name: format!("{}-", service.metadata.name),
+            namespace: service.metadata.namespace.clone(),
+            labels: endpoint_slice_labels(service),
+            owner_references: vec![OwnerReference {
+                api_version: "v1".to_string(),
+                kind: "Service".to_string(),
+                name: service.metadata.name.clone(),
+                uid: service.metadata.uid.clone(),
+                controller: Some(true),
+                block_owner_deletion: Some(true),
+            }],
+            ..Default::default()
+        },
+        address_type: address_type.to_string(),
+        endpoints: Vec::new(),
+        ports,
+    }
+}
+
+/// The Service's labels with the reserved EndpointSlice labels set by the
+/// controller, like `setEndpointSliceLabels`.
+fn endpoint_slice_labels(service: &Service) -> BTreeMap<String, String> {
+    let mut labels: BTreeMap<String, String> = service
+        .metadata
+        .labels
+        .iter()
+        .filter(|(key, _)| ![LABEL_SERVICE_NAME, LABEL_MANAGED_BY, LABEL_HEADLESS_SERVICE].contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if !is_service_ip_set(service) {
+        labels.insert(LABEL_HEADLESS_SERVICE.to_string(), String::new());
+    }
+    labels.insert(LABEL_SERVICE_NAME.to_string(), service.metadata.name.clone());
+    labels.insert(LABEL_MANAGED_BY.to_string(), ENDPOINT_SLICE_CONTROLLER_NAME.to_string());
+    labels
+}
+
+/// Sets the zone and node hints requested by the Service's traffic
+/// distribution, or clears them when it asks for none.
+fn set_traffic_distribution_hints(slice: &mut EndpointSlice, traffic_distribution: Option<&str>) {
+    for endpoint in &mut slice.endpoints {
+        endpoint.hints = match (traffic_distribution, endpoint.zone.as_ref()) {
+            (
+                Some(SERVICE_TRAFFIC_DISTRIBUTION_PREFER_CLOSE | SERVICE_TRAFFIC_DISTRIBUTION_PREFER_SAME_ZONE),
+                Some(zone),
+            ) => Some(EndpointHints {
+                for_zones: vec![ForZone { name: zone.clone() }],
+                ..Default::default()
+            }),
+            (Some(SERVICE_TRAFFIC_DISTRIBUTION_PREFER_SAME_NODE), Some(zone)) => Some(EndpointHints {
+                for_zones: vec![ForZone { name: zone.clone() }],
+                for_nodes: endpoint
+                    .node_name
+                    .iter()
+                    .map(|name| ForNode { name: name.clone() })
+                    .collect(),
+            }),
+            _ => None,
+        };
+    }
+}
+
+fn is_service_ip_set(service: &Service) -> bool {
+    service
+        .spec
+        .as_ref()
+        .is_some_and(|spec| !spec.cluster_i_p.is_empty() && spec.cluster_i_p != CLUSTER_IP_NONE)
+}
+
+/// Like `utilnet.IsIPv6String`: IPv4-mapped addresses count as IPv4.
+fn is_ipv6(ip: &str) -> bool {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => ip.to_ipv4_mapped().is_none(),
+        _ => false,
+    }
+}
+
+fn protocol_or_tcp(protocol: &str) -> &str {
+    if protocol.is_empty() {
+        PROTOCOL_TCP
+    } else {
+        protocol
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::{
+        Container, ContainerPort, PodCondition, PodIP, PodSpec, PodStatus, ServiceSpec, CONDITION_FALSE,
+        CONDITION_TRUE, POD_CONDITION_READY, POD_PHASE_RUNNING,
+    };
+    use k8s_apimachinery::apis::meta::v1::Time;
+
+    fn service(spec: ServiceSpec) -> Service {
+        Service {
+            metadata: ObjectMeta {
+                name: "web".to_string(),
+                namespace: "default".to_string(),
+                uid: "svc-uid".to_string(),
+                labels: BTreeMap::from([
+                    ("team".to_string(), "a".to_string()),
+                    (LABEL_MANAGED_BY.to_string(), "someone-else".to_string()),
+                ]),
+                ..Default::default()
+            },
+            spec: Some(ServiceSpec {
+                selector: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                ..spec
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn pod(name: &str, ips: &[&str], ready: bool) -> Pod {
+        Pod {
+            metadata: ObjectMeta {
+                name: name.to_string(),
+                namespace: "default".to_string(),
+                uid: format!("{}-uid", name),
+                labels: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                ..Default::default()
+            },
+            spec: Some(PodSpec {
+                node_name: "node-a".to_string(),
+                containers: vec![Container {
+                    name: "app".to_string(),
+                    ports: vec![ContainerPort {
+                        name: "http".to_string(),
+                        container_port: 8080,
+                        protocol: PROTOCOL_TCP.to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            status: Some(PodStatus {
+                phase: POD_PHASE_RUNNING.to_string(),
+                pod_i_p: ips[0].to_string(),
+                pod_i_ps: ips.iter().map(|ip| PodIP { ip: ip.to_string() }).collect(),
+                conditions: vec![PodCondition {
+                    condition_type: POD_CONDITION_READY.to_string(),
+                    status: if ready { CONDITION_TRUE } else { CONDITION_FALSE }.to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn nodes() -> Vec<Node> {
+        vec![Node {
+            metadata: ObjectMeta {
+                name: "node-a".to_string(),
+                labels: BTreeMap::from([(LABEL_TOPOLOGY_ZONE.to_string(), "zone-1".to_string())]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }]
+    }
+
+    fn http_port() -> ServicePort {
+        ServicePort {
+            name: "http".to_string(),
+            protocol: PROTOCOL_TCP.to_string(),
+            port: 80,
+            target_port: Some(IntOrString::String("http".to_string())),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_dual_stack_slices_with_conditions() {
+        let svc = service(ServiceSpec {
+            cluster_i_p: "10.0.0.1".to_string(),
+            ip_families: vec![IP_FAMILY_IPV4.to_string(), IP_FAMILY_IPV6.to_string()],
+            ports: vec![http_port()],
+            traffic_distribution: Some(SERVICE_TRAFFIC_DISTRIBUTION_PREFER_CLOSE.to_string()),
+            ..Default::default()
+        });
+        let mut terminating = pod("b", &["10.1.0.2", "fd00::2"], true);
+        terminating.metadata.deletion_timestamp = Some(Time::now());
+        let mut stray = pod("c", &["10.1.0.3"], true);
+        stray.metadata.labels.clear();
+        let pods = vec![pod("a", &["10.1.0.1", "fd00::1"], false), terminating, stray];
+
+        let slices = generate_endpoint_slices(&svc, &pods, &nodes());
+        assert_eq!(slices.len(), 2);
+        assert_eq!(slices[0].address_type, ADDRESS_TYPE_IPV4);
+        assert_eq!(slices[1].address_type, ADDRESS_TYPE_IPV6);
+        assert_eq!(slices[1].endpoints[0].addresses, vec!["fd00::1"]);
+
+        // Every pod IP of the family becomes an address of the endpoint.
+        let multi = generate_endpoint_slices(&svc, &[pod("m", &["10.1.0.9", "fd00::9", "fd00::a"], true)], &nodes());
+        assert_eq!(multi[1].endpoints[0].addresses, vec!["fd00::9", "fd00::a"]);
+
+        let slice = &slices[0];
+        assert_eq!(slice.metadata.generate_name, "web-");
+        assert_eq!(slice.metadata.labels[LABEL_SERVICE_NAME], "web");
+        assert_eq!(slice.metadata.labels[LABEL_MANAGED_BY], ENDPOINT_SLICE_CONTROLLER_NAME);
+        assert_eq!(slice.metadata.labels["team"], "a");
+        assert!(!slice.metadata.labels.contains_key(LABEL_HEADLESS_SERVICE));
+        assert_eq!(slice.metadata.owner_references[0].uid, "svc-uid");
+        assert_eq!(
+            slice.ports,
+            vec![EndpointPort {
+                name: Some("http".to_string()),
+                protocol: Some(PROTOCOL_TCP.to_string()),
+                port: Some(8080),
+                app_protocol: None,
+            }]
+        );
+
+        let endpoints = &slice.endpoints;
+        assert_eq!(endpoints.len(), 2);
+        assert_eq!(endpoints[0].addresses, vec!["10.1.0.1"]);
+        assert_eq!(
+            endpoints[0].conditions,
+            Some(EndpointConditions {
+                ready: Some(false),
+                serving: Some(false),
+                terminating: Some(false),
+            })
+        );
+        assert_eq!(
+            endpoints[1].conditions,
+            Some(EndpointConditions {
+                ready: Some(false),
+                serving: Some(true),
+                terminating: Some(true),
+            })
+        );
+        assert_eq!(endpoints[0].zone.as_deref(), Some("zone-1"));
+        assert_eq!(endpoints[0].node_name.as_deref(), Some("node-a"));
+        assert_eq!(endpoints[0].hints.as_ref().unwrap().for_zones[0].name, "zone-1");
+        assert_eq!(endpoints[0].target_ref.as_ref().unwrap().name, "a");
+    }
+
+    #[test]
+    fn test_generate_headless_slices_split_and_placeholder() {
+        let svc = service(ServiceSpec {
+            cluster_i_p: CLUSTER_IP_NONE.to_string(),
+            publish_not_ready_addresses: true,
+            ..Default::default()
+        });
+        let pods: Vec<Pod> = (0..150).map(|i| pod(&format!("p{}", i), &[&format!("10.1.0.{}", i)], false)).collect();
+
+        let slices = generate_endpoint_slices(&svc, &pods, &nodes());
+        assert_eq!(slices.len(), 3);
+        assert_eq!(slices[0].endpoints.len(), MAX_ENDPOINTS_PER_SLICE);
+        assert_eq!(slices[1].endpoints.len(), 50);
+        assert!(slices[0].ports.is_empty());
+        assert_eq!(slices[0].metadata.labels[LABEL_HEADLESS_SERVICE], "");
+        assert_eq!(slices[0].endpoints[0].conditions.as_ref().unwrap().ready, Some(true));
+        // Headless Services serve both families; IPv6 has no endpoints.
+        assert_eq!(slices[2].address_type, ADDRESS_TYPE_IPV6);
+        assert!(slices[2].endpoints.is_empty());
+    }
+
+    #[test]
+    fn test_generate_placeholder_per_address_type() {
+        let svc = service(ServiceSpec {
+            cluster_i_p: "fd00::1".to_string(),
+            ip_families: vec![IP_FAMILY_IPV6.to_string()],
+            ..Default::default()
+        });
+        let slices = generate_endpoint_slices(&svc, &[], &nodes());
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].address_type, ADDRESS_TYPE_IPV6);
+        assert!(slices[0].endpoints.is_empty());
+
+        let svc = service(ServiceSpec {
+            cluster_i_p: "10.0.0.1".to_string(),
+            ip_families: vec![IP_FAMILY_IPV4.to_string(), IP_FAMILY_IPV6.to_string()],
+            ..Default::default()
+        });
+        let slices = generate_endpoint_slices(&svc, &[pod("a", &["10.1.0.1"], true)], &nodes());
+        assert_eq!(slices.len(), 2);
+        assert_eq!((slices[0].address_type.as_str(), slices[0].endpoints.len()), (ADDRESS_TYPE_IPV4, 1));
+        assert_eq!((slices[1].address_type.as_str(), slices[1].endpoints.len()), (ADDRESS_TYPE_IPV6, 0));
+    }
+
+    #[test]
+    fn test_generate_pods_on_missing_nodes() {
+        let mut svc = service(ServiceSpec {
+            cluster_i_p: "10.0.0.1".to_string(),
+            publish_not_ready_addresses: true,
+            ..Default::default()
+        });
+        let pods = vec![pod("a", &["10.1.0.1"], false)];
+
+        // Published not-ready addresses keep the pod, without a zone.
+        let slices = generate_endpoint_slices(&svc, &pods, &[]);
+        assert_eq!(slices.len(), 1);
+        let endpoint = &slices[0].endpoints[0];
+        assert_eq!(endpoint.addresses, vec!["10.1.0.1"]);
+        assert_eq!(endpoint.node_name.as_deref(), Some("node-a"));
+        assert_eq!(endpoint.zone, None);
+        assert_eq!(endpoint.conditions.as_ref().unwrap().ready, Some(true));
+
+        // Otherwise the pod is left out, leaving only the placeholder.
+        svc.spec.as_mut().unwrap().publish_not_ready_addresses = false;
+        let slices = generate_endpoint_slices(&svc, &pods, &[]);
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].address_type, ADDRESS_TYPE_IPV4);
+        assert!(slices[0].endpoints.is_empty());
+    }
+
+    #[test]
+    fn test_find_port() {
+        let mut target = pod("a", &["10.1.0.1"], true);
+        let mut port = http_port();
+        assert_eq!(find_port(&target, &port), Ok(8080));
+
+        port.protocol = "UDP".to_string();
+        assert_eq!(find_port(&target, &port), Err("no suitable port for manifest: a-uid".to_string()));
+
+        let spec = target.spec.as_mut().unwrap();
+        let mut sidecar = spec.containers.remove(0);
+        sidecar.ports[0].protocol = "UDP".to_string();
+        spec.init_containers.push(sidecar.clone());
+        assert!(find_port(&target, &port).is_err());
+        sidecar.restart_policy = Some(CONTAINER_RESTART_POLICY_ALWAYS.to_string());
+        target.spec.as_mut().unwrap().init_containers = vec![sidecar];
+        assert_eq!(find_port(&target, &port), Ok(8080));
+
+        port.target_port = None;
+        assert_eq!(find_port(&target, &port), Ok(80));
+    }
+}
@@ -3,9 +3,11 @@
 mod types;
 mod internal_conversion;
 mod defaults;
+mod endpointslice;
 
 pub use types::*;
 pub use defaults::*;
+pub use endpointslice::*;
 
 pub trait InternalConversion: Sized + serde::Serialize + serde::de::DeserializeOwned {
     type Internal: serde::Serialize + serde::de::DeserializeOwned;
@@ -0,0 +1,557 @@
+//! PodDisruptionBudget disruption computation and eviction decisions
+//!
+//! How the disruption controller derives a budget's expected, desired and
+//! current healthy pod counts, and how the eviction subresource decides
+//! whether a pod may be evicted, following `pkg/controller/disruption`,
+//! `pkg/apis/policy/helper` and `pkg/registry/core/pod/storage/eviction.go`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use k8s_api_core::IntOrString;
+use k8s_apimachinery::apis::meta::v1::{Condition, OwnerReference, Status, StatusCause, StatusDetails, Time, TypeMeta};
+use serde_json::Value;
+
+use crate::core::v1::{Pod, CONDITION_FALSE, CONDITION_TRUE, POD_PHASE_FAILED, POD_PHASE_PENDING, POD_PHASE_SUCCEEDED};
+
+use super::*;
+
+/// Condition type reporting whether the budget currently allows disruptions.
+pub const DISRUPTION_ALLOWED_CONDITION: &str = "DisruptionAllowed";
+/// Reason used when the disruption controller could not compute the status.
+pub const SYNC_FAILED_REASON: &str = "SyncFailed";
+/// Reason used when there are enough healthy pods to allow a disruption.
+pub const SUFFICIENT_PODS_REASON: &str = "SufficientPods";
+/// Reason used when there are not enough healthy pods to allow a disruption.
+pub const INSUFFICIENT_PODS_REASON: &str = "InsufficientPods";
+/// Status cause type attached to evictions refused by a budget.
+pub const DISRUPTION_BUDGET_CAUSE: &str = "DisruptionBudget";
+
+/// How long a pod listed in `disruptedPods` is expected to take to be deleted
+/// before the controller counts it as healthy again.
+const DELETION_TIMEOUT: Duration = Duration::minutes(2);
+
+/// Upper bound on `disruptedPods` the eviction subresource tolerates.
+const MAX_DISRUPTED_POD_SIZE: usize = 2000;
+
+const EVICTION_REFUSED_MESSAGE: &str = "Cannot evict pod as it would violate the pod's disruption budget.";
+
+/// Counts the disruption controller derives for a budget before it writes
+/// the status.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpectedPodCount {
+    /// Pods the budget expects to exist: the summed scale of the owning
+    /// controllers, or the number of selected pods for an integer
+    /// `minAvailable`.
+    pub expected: i32,
+    /// Healthy pods the budget requires.
+    pub desired_healthy: i32,
+    /// Selected pods without a controller, which do not contribute to the
+    /// expected count.
+    pub unmanaged_pods: Vec<String>,
+}
+
+impl PodDisruptionBudget {
+    /// Returns `minAvailable` as an int-or-percent value.
+    pub fn min_available(&self) -> Result<Option<IntOrString>, String> {
+        self.spec
+            .as_ref()
+            .and_then(|spec| spec.min_available.as_ref())
+            .map(int_or_string)
+            .transpose()
+    }
+
+    /// Returns `maxUnavailable` as an int-or-percent value.
+    pub fn max_unavailable(&self) -> Result<Option<IntOrString>, String> {
+        self.spec
+            .as_ref()
+            .and_then(|spec| spec.max_unavailable.as_ref())
+            .map(int_or_string)
+            .transpose()
+    }
+
+    /// Returns the unhealthy pod eviction policy, defaulting to
+    /// `IfHealthyBudget`.
+    pub fn unhealthy_pod_eviction_policy(&self) -> &str {
+        self.spec
+            .as_ref()
+            .and_then(|spec| spec.unhealthy_pod_eviction_policy.as_deref())
+            .unwrap_or(UNHEALTHY_POD_EVICTION_POLICY_IF_HEALTHY_BUDGET)
+    }
+
+    /// Reports whether the budget covers `pod`: same namespace and a
+    /// selector matching its labels. A missing selector matches nothing and
+    /// an empty one matches every pod, as in policy/v1.
+    pub fn selects(&self, pod: &Pod) -> bool {
+        if self.metadata.namespace != pod.metadata.namespace {
+            return false;
+        }
+        let Some(selector) = self.spec.as_ref().and_then(|spec| spec.selector.as_ref()) else {
+            return false;
+        };
+        selector.matches(&pod.metadata.labels).unwrap_or(false)
+    }
+
+    /// Computes the expected and desired healthy counts for the selected
+    /// `pods`, like `getExpectedPodCount`. `controller_scale` returns the
+    /// desired replicas of the controller a pod's controller reference
+    /// points to, or `None` if it is not a scalable controller.
+    pub fn expected_pod_count(
+        &self,
+        pods: &[&Pod],
+        controller_scale: impl Fn(&OwnerReference) -> Option<i32>,
+    ) -> Result<ExpectedPodCount, String> {
+        let mut count = ExpectedPodCount::default();
+        if let Some(max_unavailable) = self.max_unavailable()? {
+            (count.expected, count.unmanaged_pods) = expected_scale(pods, controller_scale)?;
+            let max_unavailable = max_unavailable.get_scaled_value_from_int_or_percent(count.expected, true)?;
+            count.desired_healthy = (count.expected - max_unavailable).max(0);
+        } else if let Some(min_available) = self.min_available()? {
+            match min_available {
+                IntOrString::Int(value) => {
+                    count.desired_healthy = value;
+                    count.expected = pods.len() as i32;
+                }
+                IntOrString::String(_) => {
+                    (count.expected, count.unmanaged_pods) = expected_scale(pods, controller_scale)?;
+                    count.desired_healthy =
+                        min_available.get_scaled_value_from_int_or_percent(count.expected, true)?;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Computes the status the disruption controller would write for the
+    /// budget given the pods in the cluster. Failures to resolve the
+    /// expected count produce the fail-safe status that allows no
+    /// disruptions.
+    pub fn compute_status(
+        &self,
+        pods: &[Pod],
+        controller_scale: impl Fn(&OwnerReference) -> Option<i32>,
+        now: DateTime<Utc>,
+    ) -> PodDisruptionBudgetStatus {
+        let mut status = self.status.clone().unwrap_or_default();
+        let pods: Vec<&Pod> = pods.iter().filter(|pod| self.selects(pod)).collect();
+        let count = match self.expected_pod_count(&pods, controller_scale) {
+            Ok(count) => count,
+            Err(err) => {
+                status.disruptions_allowed = 0;
+                set_condition(
+                    &mut status.conditions,
+                    CONDITION_FALSE,
+                    SYNC_FAILED_REASON,
+                    &err,
+                    status.observed_generation.unwrap_or_default(),
+                    now,
+                );
+                return status;
+            }
+        };
+
+        let disrupted_pods = self.live_disrupted_pods(&pods, now);
+        let current_healthy = pods
+            .iter()
+            .filter(|pod| !is_deleting(pod) && !disrupted_pods.contains_key(&pod.metadata.name) && pod.is_ready())
+            .count() as i32;
+
+        // A budget that currently matches no pods must not allow disruptions,
+        // or its first pods could be evicted before the status catches up.
+        let mut disruptions_allowed = current_healthy - count.desired_healthy;
+        if count.expected <= 0 || disruptions_allowed <= 0 {
+            disruptions_allowed = 0;
+        }
+
+        status.current_healthy = current_healthy;
+        status.desired_healthy = count.desired_healthy;
+        status.expected_pods = count.expected;
+        status.disruptions_allowed = disruptions_allowed;
+        status.disrupted_pods = disrupted_pods;
+        status.observed_generation = Some(self.metadata.generation);
+        update_disruption_allowed_condition(&mut status, now);
+        status
+    }
+
+    /// Records a granted eviction of `pod` the way the eviction subresource
+    /// does: one fewer disruption allowed and the pod listed in
+    /// `disruptedPods` until the controller observes its deletion.
+    pub fn record_eviction(&mut self, pod: &Pod, now: DateTime<Utc>) {
+        let status = self.status.get_or_insert_with(Default::default);
+        status.disruptions_allowed -= 1;
+        if status.disruptions_allowed == 0 {
+            update_disruption_allowed_condition(status, now);
+        }
+        status
+            .disrupted_pods
+            .insert(pod.metadata.name.clone(), now.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+
+    /// Entries of `disruptedPods` still waiting on their deletion, like
+    /// `buildDisruptedPodMap`. Entries older than the deletion timeout are
+    /// dropped so the pods count as healthy again.
+    fn live_disrupted_pods(&self, pods: &[&Pod], now: DateTime<Utc>) -> BTreeMap<String, String> {
+        let Some(disrupted_pods) = self.status.as_ref().map(|status| &status.disrupted_pods) else {
+            return BTreeMap::new();
+        };
+        pods.iter()
+            .filter(|pod| !is_deleting(pod))
+            .filter_map(|pod| {
+                let (name, time) = disrupted_pods.get_key_value(&pod.metadata.name)?;
+                let disrupted_at = DateTime::parse_from_rfc3339(time).ok()?.with_timezone(&Utc);
+                (disrupted_at + DELETION_TIMEOUT >= now).then(|| (name.clone(), time.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Decides whether the eviction subresource would let `pod` be evicted
+/// given the budgets in the cluster. Refusals carry the Status the
+/// apiserver returns: 429 when the budget does not allow a disruption,
+/// 403 for an inconsistent budget and 500 when several budgets cover the
+/// pod.
+pub fn evaluate_eviction(pdbs: &[PodDisruptionBudget], pod: &Pod) -> Result<(), Box<Status>> {
+    // Terminal, pending or terminating pods have already stopped serving,
+    // so evicting them is a plain deletion.
+    let phase = pod.status.as_ref().map(|status| status.phase.as_str());
+    if matches!(phase, Some(POD_PHASE_SUCCEEDED | POD_PHASE_FAILED | POD_PHASE_PENDING)) || is_deleting(pod) {
+        return Ok(());
+    }
+
+    let matching: Vec<&PodDisruptionBudget> = pdbs.iter().filter(|pdb| pdb.selects(pod)).collect();
+    let pdb = match matching.as_slice() {
+        [] => return Ok(()),
+        [pdb] => *pdb,
+        _ => {
+            // The eviction handler builds this Status by hand, without a
+            // reason or the usual internal error prefix.
+            return Err(Box::new(Status {
+                type_meta: TypeMeta::new("v1", "Status"),
+                status: "Failure".to_string(),
+                message: "This pod has more than one PodDisruptionBudget, which the eviction subresource does not support."
+                    .to_string(),
+                code: 500,
+                ..Default::default()
+            }));
+        }
+    };
+
+    let status = pdb.status.clone().unwrap_or_default();
+    if !pod.is_ready() {
+        if pdb.unhealthy_pod_eviction_policy() == UNHEALTHY_POD_EVICTION_POLICY_ALWAYS_ALLOW {
+            return Ok(());
+        }
+        if status.current_healthy >= status.desired_healthy && status.desired_healthy > 0 {
+            return Ok(());
+        }
+    }
+
+    let name = &pdb.metadata.name;
+    if status.observed_generation.unwrap_or_default() < pdb.metadata.generation {
+        return Err(too_many_requests(
+            10,
+            format!("The disruption budget {} is still being processed by the server.", name),
+        ));
+    }
+    if status.disruptions_allowed < 0 {
+        return Err(forbidden(name, "pdb disruptions allowed is negative"));
+    }
+    if status.disrupted_pods.len() > MAX_DISRUPTED_POD_SIZE {
+        return Err(forbidden(
+            name,
+            "DisruptedPods map too big - too many evictions not confirmed by PDB controller",
+        ));
+    }
+    if status.disruptions_allowed == 0 {
+        return Err(too_many_requests(
+            0,
+            format!(
+                "The disruption budget {} needs {} healthy pods and has {} currently",
+                name, status.desired_healthy, status.current_healthy
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Sums the scale of the controllers owning `pods`, counting each
+/// controller once, like `getExpectedScale`.
+fn expected_scale(
+    pods: &[&Pod],
+    controller_scale: impl Fn(&OwnerReference) -> Option<i32>,
+) -> Result<(i32, Vec<String>), String> {
+    let mut seen = BTreeSet::new();
+    let mut expected = 0;
+    let mut unmanaged_pods = Vec::new();
+    for pod in pods {
+        let Some(controller) = pod.metadata.owner_references.iter().find(|owner| owner.controller == Some(true)) else {
+            unmanaged_pods.push(pod.metadata.name.clone());
+            continue;
+        };
+        if !seen.insert(controller.uid.as_str()) {
+            continue;
+        }
+        let Some(scale) = controller_scale(controller) else {
+            return Err(format!("found no controllers for pod {:?}", pod.metadata.name));
+        };
+        expected += scale;
+    }
+    Ok((expected, unmanaged_pods))
+}
+
+fn int_or_string(value: &Value) -> Result<IntOrString, String> {
+    match value {
+        Value::Number(number) => number
+            .as_i64()
+            .and_then(|number| i32::try_from(number).ok())
+            .map(IntOrString::Int)
+            .ok_or_else(|| format!("invalid value for IntOrString: {}", number)),
+        Value::String(value) => Ok(IntOrString::String(value.clone())),
+        other => Err(format!("invalid value for IntOrString: {}", other)),
+    }
+}
+
+fn is_deleting(pod: &Pod) -> bool {
+    pod.metadata.deletion_timestamp.as_ref().is_some_and(|time| !time.is_zero())
+}
+
+fn update_disruption_allowed_condition(status: &mut PodDisruptionBudgetStatus, now: DateTime<Utc>) {
+    let (condition_status, reason) = if status.disruptions_allowed > 0 {
+        (CONDITION_TRUE, SUFFICIENT_PODS_REASON)
+    } else {
+        (CONDITION_FALSE, INSUFFICIENT_PODS_REASON)
+    };
+    let observed_generation = status.observed_generation.unwrap_or_default();
+    set_condition(&mut status.conditions, condition_status, reason, "", observed_generation, now);
+}
+
+/// Sets the DisruptionAllowed condition like `meta.SetStatusCondition`,
+/// moving the transition time only when the status changes.
+fn set_condition(
+    conditions: &mut Vec<Condition>,
+    status: &str,
+    reason: &str,
+    message: &str,
+    observed_generation: i64,
+    now: DateTime<Utc>,
+) {
+    let index = match conditions.iter().position(|c| c.condition_type == DISRUPTION_ALLOWED_CONDITION) {
+        Some(index) => index,
+        None => {
+            conditions.push(Condition {
+                condition_type: DISRUPTION_ALLOWED_CONDITION.to_string(),
+                ..Default::default()
+            });
+            conditions.len() - 1
+        }
+    };
+    let condition = &mut conditions[index];
+    if condition.status != status || condition.last_transition_time.is_zero() {
+        condition.status = status.to_string();
+        condition.last_transition_time = Time(Some(now));
+    }
+    condition.reason = reason.to_string();
+    condition.message = message.to_string();
+    condition.observed_generation = observed_generation;
+}
+
+fn failure_status(code: i32, reason: &str, message: String, details: StatusDetails) -> Box<Status> {
+    Box::new(Status {
+        type_meta: TypeMeta::new("v1", "Status"),
+        status: "Failure".to_string(),
+        message,
+        reason: reason.to_string(),
+        details: Some(details),
+        code,
+        ..Default::default()
+    })
+}
+
+fn too_many_requests(retry_after_seconds: i32, cause: String) -> Box<Status> {
+    let details = StatusDetails {
+        causes: vec![StatusCause {
+            cause_type: DISRUPTION_BUDGET_CAUSE.to_string(),
+            message: cause,
+            ..Default::default()
+        }],
+        retry_after_seconds,
+        ..Default::default()
+    };
+    failure_status(429, "TooManyRequests", EVICTION_REFUSED_MESSAGE.to_string(), details)
+}
+
+fn forbidden(name: &str, err: &str) -> Box<Status> {
+    let details = StatusDetails {
+        name: name.to_string(),
+        group: "policy".to_string(),
+        kind: "poddisruptionbudget".to_string(),
+        ..Default::default()
+    };
+    let message = format!("poddisruptionbudget.policy {:?} is forbidden: {}", name, err);
+    failure_status(403, "Forbidden", message, details)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::{PodCondition, PodStatus, POD_CONDITION_READY, POD_PHASE_RUNNING};
+    use chrono::TimeZone;
+    use k8s_apimachinery::apis::meta::v1::{LabelSelector, ObjectMeta};
+
+    fn pod(name: &str, ready: bool, owner: Option<&str>) -> Pod {
+        Pod {
+            metadata: ObjectMeta {
+                name: name.to_string(),
+                namespace: "default".to_string(),
+                labels: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                owner_references: owner
+                    .map(|uid| OwnerReference {
+                        api_version: "apps/v1".to_string(),
+                        kind: "ReplicaSet".to_string(),
+                        name: uid.to_string(),
+                        uid: uid.to_string(),
+                        controller: Some(true),
+                        ..Default::default()
+                    })
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+            status: Some(PodStatus {
+                phase: POD_PHASE_RUNNING.to_string(),
+                conditions: vec![PodCondition {
+                    condition_type: POD_CONDITION_READY.to_string(),
+                    status: if ready { CONDITION_TRUE } else { CONDITION_FALSE }.to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn pdb(min_available: Option<Value>, max_unavailable: Option<Value>) -> PodDisruptionBudget {
+        PodDisruptionBudget {
+            metadata: ObjectMeta {
+                name: "web".to_string(),
+                namespace: "default".to_string(),
+                generation: 1,
+                ..Default::default()
+            },
+            spec: Some(PodDisruptionBudgetSpec {
+                min_available,
+                max_unavailable,
+                selector: Some(LabelSelector {
+                    match_labels: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_compute_status_percentages_use_controller_scale() {
+        let pods = vec![
+            pod("a", true, Some("rs")),
+            pod("b", true, Some("rs")),
+            pod("c", false, Some("rs")),
+            pod("d", true, None),
+        ];
+        let scale = |_: &OwnerReference| Some(5);
+
+        let budget = pdb(None, Some(Value::String("30%".to_string())));
+        let status = budget.compute_status(&pods, scale, now());
+        assert_eq!(status.expected_pods, 5);
+        assert_eq!(status.desired_healthy, 3);
+        assert_eq!(status.current_healthy, 3);
+        assert_eq!(status.disruptions_allowed, 0);
+        assert_eq!(status.conditions[0].reason, INSUFFICIENT_PODS_REASON);
+
+        let budget = pdb(Some(Value::from(2)), None);
+        let status = budget.compute_status(&pods, scale, now());
+        assert_eq!(status.expected_pods, 4);
+        assert_eq!(status.disruptions_allowed, 1);
+        assert_eq!(status.conditions[0].status, CONDITION_TRUE);
+
+        let budget = pdb(Some(Value::String("50%".to_string())), None);
+        let status = budget.compute_status(&pods, |_: &OwnerReference| None, now());
+        assert_eq!(status.disruptions_allowed, 0);
+        assert_eq!(status.conditions[0].reason, SYNC_FAILED_REASON);
+        assert_eq!(status.conditions[0].message, "found no controllers for pod \"a\"");
+    }
+
+    #[test]
+    fn test_compute_status_ignores_recently_disrupted_pods() {
+        let pods = vec![pod("a", true, Some("rs")), pod("b", true, Some("rs"))];
+        let mut budget = pdb(Some(Value::from(1)), None);
+        budget.status = Some(budget.compute_status(&pods, |_: &OwnerReference| Some(2), now()));
+        assert_eq!(evaluate_eviction(std::slice::from_ref(&budget), &pods[0]), Ok(()));
+
+        budget.record_eviction(&pods[0], now());
+        let status = budget.status.as_ref().unwrap();
+        assert_eq!(status.disruptions_allowed, 0);
+        assert_eq!(status.disrupted_pods["a"], "2024-03-01T12:00:00Z");
+
+        let status = budget.compute_status(&pods, |_: &OwnerReference| Some(2), now() + Duration::minutes(1));
+        assert_eq!(status.current_healthy, 1);
+        assert_eq!(status.disruptions_allowed, 0);
+
+        let status = budget.compute_status(&pods, |_: &OwnerReference| Some(2), now() + Duration::minutes(3));
+        assert_eq!(status.current_healthy, 2);
+        assert!(status.disrupted_pods.is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_eviction() {
+        let ready = pod("a", true, Some("rs"));
+        let unready = pod("b", false, Some("rs"));
+        let mut budget = pdb(Some(Value::from(2)), None);
+        budget.status = Some(PodDisruptionBudgetStatus {
+            observed_generation: Some(1),
+            current_healthy: 2,
+            desired_healthy: 2,
+            ..Default::default()
+        });
+
+        let refused = evaluate_eviction(std::slice::from_ref(&budget), &ready).unwrap_err();
+        assert_eq!(refused.code, 429);
+        assert_eq!(refused.reason, "TooManyRequests");
+        assert_eq!(refused.message, EVICTION_REFUSED_MESSAGE);
+        let details = refused.details.unwrap();
+        assert_eq!(details.causes[0].cause_type, DISRUPTION_BUDGET_CAUSE);
+        assert_eq!(
+            details.causes[0].message,
+            "The disruption budget web needs 2 healthy pods and has 2 currently"
+        );
+
+        // Unhealthy pods may go while the budget is healthy.
+        assert_eq!(evaluate_eviction(std::slice::from_ref(&budget), &unready), Ok(()));
+        budget.status.as_mut().unwrap().current_healthy = 1;
+        assert!(evaluate_eviction(std::slice::from_ref(&budget), &unready).is_err());
+        budget.spec.as_mut().unwrap().unhealthy_pod_eviction_policy =
+            Some(UNHEALTHY_POD_EVICTION_POLICY_ALWAYS_ALLOW.to_string());
+        assert_eq!(evaluate_eviction(std::slice::from_ref(&budget), &unready), Ok(()));
+
+        budget.metadata.generation = 2;
+        let refused = evaluate_eviction(std::slice::from_ref(&budget), &ready).unwrap_err();
+        let details = refused.details.unwrap();
+        assert_eq!(details.retry_after_seconds, 10);
+        assert_eq!(details.causes[0].message, "The disruption budget web is still being processed by the server.");
+
+        let refused = evaluate_eviction(&[budget.clone(), budget], &ready).unwrap_err();
+        assert_eq!(refused.status, "Failure");
+        assert_eq!(refused.code, 500);
+        assert_eq!(
+            refused.message,
+            "This pod has more than one PodDisruptionBudget, which the eviction subresource does not support."
+        );
+        assert_eq!(refused.reason, "");
+        assert_eq!(refused.details, None);
+    }
+}
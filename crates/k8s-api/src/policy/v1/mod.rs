@@ -2,8 +2,10 @@
 
 mod types;
 mod internal_conversion;
+mod disruption;
 
 pub use types::*;
+pub use disruption::*;
 
 pub trait InternalConversion: Sized + serde::Serialize + serde::de::DeserializeOwned {
     type Internal: serde::Serialize + serde::de::DeserializeOwned;
@@ -0,0 +1,116 @@
+//! Device taints and tolerations
+//!
+//! Devices are tainted like nodes; the tolerations of a device request
+//! decide whether the scheduler may allocate a device and when the device
+//! taint eviction controller evicts the pods using it. Matching is that of
+//! `core::v1::Toleration::tolerates`.
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::*;
+
+impl DeviceToleration {
+    /// Returns whether the toleration tolerates `taint`. An empty key with
+    /// operator `Exists` tolerates every taint, an empty effect every
+    /// effect, and an empty operator means `Equal`.
+    pub fn tolerates(&self, taint: &DeviceTaint) -> bool {
+        if !self.effect.is_empty() && self.effect != taint.effect {
+            return false;
+        }
+        if !self.key.is_empty() && self.key != taint.key {
+            return false;
+        }
+        match self.operator.as_str() {
+            "" | DEVICE_TOLERATION_OP_EQUAL => self.value == taint.value,
+            DEVICE_TOLERATION_OP_EXISTS => true,
+            _ => false,
+        }
+    }
+}
+
+/// Returns the first of the `taints` of a device that none of
+/// `tolerations` tolerates, which keeps the scheduler from allocating the
+/// device. Every device taint effect keeps devices from being allocated.
+pub fn find_untolerated_device_taint<'a>(
+    taints: &'a [DeviceTaint],
+    tolerations: &[DeviceToleration],
+) -> Option<&'a DeviceTaint> {
+    taints
+        .iter()
+        .find(|taint| !tolerations.iter().any(|toleration| toleration.tolerates(taint)))
+}
+
+/// Returns when pods using a device with `taints` allocated for a request
+/// with `tolerations` are evicted, or `None` if they are not. Pods not
+/// tolerating a `NoExecute` taint are evicted at `now`, and otherwise
+/// `tolerationSeconds` after the taint was added, or after `now` for taints
+/// without `timeAdded`.
+pub fn device_no_execute_eviction_time(
+    taints: &[DeviceTaint],
+    tolerations: &[DeviceToleration],
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let mut eviction: Option<DateTime<Utc>> = None;
+    for taint in taints.iter().filter(|taint| taint.effect == DEVICE_TAINT_EFFECT_NO_EXECUTE) {
+        let Some(toleration) = tolerations.iter().find(|toleration| toleration.tolerates(taint)) else {
+            return Some(now);
+        };
+        let Some(seconds) = toleration.toleration_seconds else {
+            continue;
+        };
+        let added = taint.time_added.as_ref().and_then(|time| time.0).unwrap_or(now);
+        let when = added + Duration::seconds(seconds.max(0));
+        eviction = Some(eviction.map_or(when, |eviction| eviction.min(when)));
+    }
+    eviction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_apimachinery::apis::meta::v1::Time;
+
+    fn taint(key: &str, value: &str, effect: &str, added: Option<DateTime<Utc>>) -> DeviceTaint {
+        DeviceTaint {
+            key: key.to_string(),
+            value: value.to_string(),
+            effect: effect.to_string(),
+            time_added: added.map(|added| Time(Some(added))),
+        }
+    }
+
+    fn toleration(key: &str, operator: &str, value: &str, seconds: Option<i64>) -> DeviceToleration {
+        DeviceToleration {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            value: value.to_string(),
+            effect: String::new(),
+            toleration_seconds: seconds,
+        }
+    }
+
+    #[test]
+    fn test_device_taints() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let added = now - Duration::seconds(100);
+        let taints = [
+            taint("example.com/unhealthy", "", DEVICE_TAINT_EFFECT_NO_EXECUTE, Some(added)),
+            taint("example.com/maintenance", "planned", DEVICE_TAINT_EFFECT_NO_SCHEDULE, None),
+        ];
+        assert_eq!(find_untolerated_device_taint(&taints, &[]), Some(&taints[0]));
+        let tolerations = [
+            toleration("example.com/unhealthy", "Exists", "", Some(300)),
+            toleration("example.com/maintenance", "Equal", "planned", None),
+        ];
+        assert_eq!(find_untolerated_device_taint(&taints, &tolerations), None);
+        assert_eq!(find_untolerated_device_taint(&taints, &tolerations[..1]), Some(&taints[1]));
+
+        assert_eq!(device_no_execute_eviction_time(&taints, &[], now), Some(now));
+        assert_eq!(
+            device_no_execute_eviction_time(&taints, &tolerations, now),
+            Some(added + Duration::seconds(300))
+        );
+        assert_eq!(device_no_execute_eviction_time(&taints, &[toleration("", "Exists", "", None)], now), None);
+        assert_eq!(device_no_execute_eviction_time(&taints[1..], &[], now), None);
+    }
+}
@@ -2,7 +2,9 @@
 
 mod types;
 mod internal_conversion;
+mod helper;
 
+pub use helper::*;
 pub use types::*;
 
 pub trait InternalConversion: Sized + serde::Serialize + serde::de::DeserializeOwned {
@@ -1,6 +1,17 @@
 //! Kubernetes API Extensions Server types
 //!
 //! This crate provides Rust type definitions for the apiextensions-apiserver,
-//! which includes CustomResourceDefinition types.
+//! which includes CustomResourceDefinition types, along with structural
+//! schema checks, pruning and defaulting for custom resources, and schema
+//! generation from Rust types.
+
+// Lets `#[derive(JsonSchema)]` refer to this crate by name from within it.
+extern crate self as k8s_apiextensions_apiserver;
 
 pub mod apis;
+pub mod schema;
+
+pub use schema::generate::{CustomResource, JsonSchema};
+
+#[cfg(feature = "derive")]
+pub use k8s_apiextensions_derive::JsonSchema;
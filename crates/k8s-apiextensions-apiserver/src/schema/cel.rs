@@ -0,0 +1,701 @@
+//! CEL validation rules for custom resources
+//!
+//! Compiles the `x-kubernetes-validations` rules of a structural schema,
+//! checking their result types and estimated cost, and evaluates them
+//! against custom resources with `self` bound to the value at the rule's
+//! location and `oldSelf` to the previous value for transition rules. This
+//! follows `apiextensions-apiserver/pkg/apiserver/schema/cel` in Go.
+//!
+//! Property names that are not CEL identifiers are escaped the way the API
+//! server escapes them: `-` as `__dash__`, `.` as `__dot__`, `/` as
+//! `__slash__`, `__` as `__underscores__`, and reserved words such as `in`
+//! as `__in__`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use base64::Engine;
+use k8s_cel::{Env, EvalError, Key, Program, SizeEstimate, SizeEstimator, StructType, Type, Value};
+use serde_json::Value as JsonValue;
+
+use super::{
+    additional_properties_schema, child_path, index_path, items_schema, key_path, SchemaError, SchemaErrorType,
+};
+use crate::apis::apiextensions::v1::{
+    JSONSchemaProps, ValidationRule, FIELD_VALUE_DUPLICATE, FIELD_VALUE_FORBIDDEN, FIELD_VALUE_REQUIRED,
+};
+
+/// Maximum cost of evaluating a single rule against a single value.
+pub const PER_CALL_LIMIT: u64 = 1_000_000;
+/// Maximum cost of evaluating all rules of a custom resource.
+pub const RUNTIME_COST_BUDGET: u64 = 10_000_000;
+/// Maximum estimated cost of a single rule, multiplied by the number of
+/// values it may be evaluated against.
+pub const STATIC_ESTIMATED_COST_LIMIT: u64 = 10_000_000;
+/// Maximum estimated cost of all rules in a schema.
+pub const STATIC_ESTIMATED_CRD_COST_LIMIT: u64 = 100_000_000;
+/// Maximum size of a request, which bounds strings, lists and maps that do
+/// not declare a maximum size.
+const MAX_REQUEST_SIZE_BYTES: u64 = 3 * 1024 * 1024;
+
+const SELF_VAR: &str = "self";
+const OLD_SELF_VAR: &str = "oldSelf";
+
+const RESERVED_WORDS: &[&str] = &[
+    "true", "false", "null", "in", "as", "break", "const", "continue", "else", "for", "function", "if", "import",
+    "let", "loop", "package", "namespace", "return", "var", "void", "while",
+];
+
+const COST_HINT: &str =
+    "(try simplifying the rule, or adding maxItems, maxProperties, and maxLength where arrays, maps, and strings are declared)";
+
+/// Returns the CEL field name for a property, or `None` if the property
+/// cannot be accessed from CEL.
+pub fn escape_property_name(name: &str) -> Option<String> {
+    if RESERVED_WORDS.contains(&name) {
+        return Some(format!("__{}__", name));
+    }
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || matches!(ch, '_' | '.' | '-' | '/'));
+    if !valid_start || !chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.' | '-' | '/')) {
+        return None;
+    }
+    Some(
+        name.replace("__", "__underscores__")
+            .replace('.', "__dot__")
+            .replace('-', "__dash__")
+            .replace('/', "__slash__"),
+    )
+}
+
+fn is_object_with_fields(schema: &JSONSchemaProps) -> bool {
+    schema.type_ == "object" && (!schema.properties.is_empty() || schema.x_embedded_resource == Some(true))
+}
+
+/// Returns the CEL type of values described by `schema`. `name` names the
+/// object type, e.g. `self.spec`.
+pub fn schema_type(name: &str, schema: &JSONSchemaProps) -> Type {
+    if schema.x_int_or_string == Some(true) {
+        return Type::Dyn;
+    }
+    match schema.type_.as_str() {
+        "object" if is_object_with_fields(schema) => {
+            let mut object = StructType::new(name);
+            if schema.x_embedded_resource == Some(true) {
+                object = object
+                    .with_field("apiVersion", Type::String)
+                    .with_field("kind", Type::String)
+                    .with_field("metadata", Type::Dyn);
+            }
+            let mut properties: Vec<_> = schema.properties.iter().collect();
+            properties.sort_by(|a, b| a.0.cmp(b.0));
+            for (property, property_schema) in properties {
+                if let Some(field) = escape_property_name(property) {
+                    let ty = schema_type(&format!("{}.{}", name, field), property_schema);
+                    object = object.with_field(field, ty);
+                }
+            }
+            Type::object(object)
+        }
+        "object" => match additional_properties_schema(schema) {
+            Some(values) => Type::map(Type::String, schema_type(&format!("{}.@values", name), values)),
+            None if schema.x_preserve_unknown_fields == Some(true) => Type::Dyn,
+            None => Type::map(Type::String, Type::Dyn),
+        },
+        "array" => Type::list(
+            items_schema(schema)
+                .map(|items| schema_type(&format!("{}.@items", name), items))
+                .unwrap_or(Type::Dyn),
+        ),
+        "string" => match schema.format.as_str() {
+            "byte" => Type::Bytes,
+            "duration" => Type::Duration,
+            "date" | "date-time" => Type::Timestamp,
+            _ => Type::String,
+        },
+        "integer" => Type::Int,
+        "number" => Type::Double,
+        "boolean" => Type::Bool,
+        _ => Type::Dyn,
+    }
+}
+
+/// Converts a custom resource value to a CEL value, escaping property names
+/// and decoding formatted strings as `schema` describes.
+pub fn to_cel_value(value: &JsonValue, schema: &JSONSchemaProps) -> Value {
+    match value {
+        JsonValue::Object(map) if is_object_with_fields(schema) => Value::map(
+            map.iter()
+                .filter_map(|(key, value)| {
+                    let field = escape_property_name(key)?;
+                    let value = match schema.properties.get(key) {
+                        Some(property_schema) => to_cel_value(value, property_schema),
+                        None => Value::from_json(value),
+                    };
+                    Some((Key::from(field.as_str()), value))
+                })
+                .collect(),
+        ),
+        JsonValue::Object(map) => match additional_properties_schema(schema) {
+            Some(values) => Value::map(
+                map.iter()
+                    .map(|(key, value)| (Key::from(key.as_str()), to_cel_value(value, values)))
+                    .collect(),
+            ),
+            None => Value::from_json(value),
+        },
+        JsonValue::Array(items) => match items_schema(schema) {
+            Some(items_schema) => Value::list(items.iter().map(|item| to_cel_value(item, items_schema)).collect()),
+            None => Value::from_json(value),
+        },
+        JsonValue::String(s) if schema.type_ == "string" => match schema.format.as_str() {
+            "byte" => base64::engine::general_purpose::STANDARD
+                .decode(s)
+                .map(|bytes| Value::Bytes(bytes.into()))
+                .unwrap_or_else(|_| Value::string(s)),
+            "duration" => k8s_cel::parse_duration(s)
+                .map(Value::Duration)
+                .unwrap_or_else(|_| Value::string(s)),
+            "date-time" => chrono::DateTime::parse_from_rfc3339(s)
+                .map(|ts| Value::Timestamp(ts.with_timezone(&chrono::Utc)))
+                .unwrap_or_else(|_| Value::string(s)),
+            "date" => chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|ts| Value::Timestamp(ts.and_utc()))
+                .unwrap_or_else(|| Value::string(s)),
+            _ => Value::string(s),
+        },
+        JsonValue::Number(n) if schema.type_ == "number" => Value::Double(n.as_f64().unwrap_or_default()),
+        _ => Value::from_json(value),
+    }
+}
+
+/// Estimates sizes from `maxLength`, `maxItems` and `maxProperties`,
+/// falling back to what fits in a request.
+struct SchemaSizeEstimator<'a> {
+    schema: &'a JSONSchemaProps,
+}
+
+/// Returns the minimum serialized size of a value, used to bound the number
+/// of elements in unbounded lists and maps.
+fn min_serialized_size(schema: &JSONSchemaProps) -> u64 {
+    match schema.type_.as_str() {
+        // `""`, `[]` and `{}`, plus a separator.
+        "string" | "array" | "object" => 3,
+        _ => 2,
+    }
+}
+
+fn max_size(schema: &JSONSchemaProps) -> SizeEstimate {
+    let declared = match schema.type_.as_str() {
+        "string" => schema.max_length,
+        "array" => schema.max_items,
+        "object" => schema.max_properties,
+        _ => None,
+    };
+    if let Some(max) = declared {
+        return SizeEstimate {
+            min: 0,
+            max: max.max(0) as u64,
+        };
+    }
+    let element = match schema.type_.as_str() {
+        "array" => items_schema(schema).map(min_serialized_size).unwrap_or(2),
+        "object" => additional_properties_schema(schema)
+            .map(|values| min_serialized_size(values) + 3)
+            .unwrap_or(5),
+        _ => 1,
+    };
+    SizeEstimate {
+        min: 0,
+        max: MAX_REQUEST_SIZE_BYTES / element,
+    }
+}
+
+impl SchemaSizeEstimator<'_> {
+    fn lookup(&self, path: &[String]) -> Option<&JSONSchemaProps> {
+        let (root, rest) = path.split_first()?;
+        if root != SELF_VAR && root != OLD_SELF_VAR {
+            return None;
+        }
+        let mut schema = self.schema;
+        for segment in rest {
+            schema = match segment.as_str() {
+                "@items" => items_schema(schema)?,
+                "@values" => additional_properties_schema(schema)?,
+                "@keys" => return None,
+                field => schema
+                    .properties
+                    .iter()
+                    .find(|(name, _)| escape_property_name(name).as_deref() == Some(field))
+                    .map(|(_, property)| property)?,
+            };
+        }
+        Some(schema)
+    }
+}
+
+impl SizeEstimator for SchemaSizeEstimator<'_> {
+    fn estimate_size(&self, path: &[String]) -> Option<SizeEstimate> {
+        self.lookup(path).map(max_size)
+    }
+}
+
+fn rule_env(schema: &JSONSchemaProps, optional_old_self: bool) -> Env {
+    let self_type = schema_type(SELF_VAR, schema);
+    let old_self_type = if optional_old_self {
+        Type::optional(self_type.clone())
+    } else {
+        self_type.clone()
+    };
+    Env::new()
+        .with_variable(SELF_VAR, self_type)
+        .with_variable(OLD_SELF_VAR, old_self_type)
+}
+
+fn cost_error_message(name: &str, cost: u64, limit: u64) -> String {
+    let factor = cost as f64 / limit as f64;
+    let factor = if factor > 100.0 {
+        "more than 100x".to_string()
+    } else {
+        format!("{:.1}x", factor)
+    };
+    format!("{} exceeds budget by factor of {} {}", name, factor, COST_HINT)
+}
+
+/// Compiles every `x-kubernetes-validations` rule in `schema`, reporting
+/// rules that fail to compile, do not evaluate to a bool, or are estimated
+/// to be too expensive. `path` is the field path of the schema itself.
+pub fn validate_validation_rules(path: &str, schema: &JSONSchemaProps) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    let mut total = 0u64;
+    compile_rules_at(path, schema, 1, &mut total, &mut errors);
+    if total > STATIC_ESTIMATED_CRD_COST_LIMIT {
+        errors.push(SchemaError::forbidden(
+            path,
+            cost_error_message(
+                "x-kubernetes-validations estimated rule cost total for entire OpenAPIv3 schema",
+                total,
+                STATIC_ESTIMATED_CRD_COST_LIMIT,
+            ),
+        ));
+    }
+    errors
+}
+
+fn compile_rules_at(
+    path: &str,
+    schema: &JSONSchemaProps,
+    cardinality: u64,
+    total: &mut u64,
+    errors: &mut Vec<SchemaError>,
+) {
+    let rules_path = child_path(path, "x-kubernetes-validations");
+    for (idx, rule) in schema.x_validations.iter().enumerate() {
+        let rule_path = index_path(&rules_path, idx);
+        let optional_old_self = rule.optional_old_self == Some(true);
+        let env = rule_env(schema, optional_old_self);
+        if rule.rule.trim().is_empty() {
+            errors.push(SchemaError::required(child_path(&rule_path, "rule"), "rule is not specified"));
+            continue;
+        }
+        let program = match env.compile(&rule.rule) {
+            Ok(program) => program,
+            Err(err) => {
+                errors.push(SchemaError::invalid(
+                    child_path(&rule_path, "rule"),
+                    &rule.rule,
+                    format!("compilation failed: {}", err),
+                ));
+                continue;
+            }
+        };
+        if !Type::Bool.is_assignable_from(program.result_type()) {
+            errors.push(SchemaError::invalid(
+                child_path(&rule_path, "rule"),
+                &rule.rule,
+                "cel expression must evaluate to a bool",
+            ));
+        }
+        if optional_old_self && !program.references(OLD_SELF_VAR) {
+            errors.push(SchemaError::invalid(
+                child_path(&rule_path, "optionalOldSelf"),
+                "true",
+                "may not be set if oldSelf is not referenced in rule",
+            ));
+        }
+
+        let sizes = SchemaSizeEstimator { schema };
+        let cost = program.estimate_cost(&sizes).max.saturating_mul(cardinality);
+        *total = total.saturating_add(cost);
+        if cost > STATIC_ESTIMATED_COST_LIMIT {
+            errors.push(SchemaError::forbidden(
+                child_path(&rule_path, "rule"),
+                cost_error_message("estimated rule cost", cost, STATIC_ESTIMATED_COST_LIMIT),
+            ));
+        }
+
+        if !rule.message_expression.is_empty() {
+            match env.compile(&rule.message_expression) {
+                Ok(message) if !Type::String.is_assignable_from(message.result_type()) => {
+                    errors.push(SchemaError::invalid(
+                        child_path(&rule_path, "messageExpression"),
+                        &rule.message_expression,
+                        "must evaluate to a string",
+                    ));
+                }
+                Ok(message) => {
+                    let cost = message.estimate_cost(&sizes).max.saturating_mul(cardinality);
+                    *total = total.saturating_add(cost);
+                    if cost > STATIC_ESTIMATED_COST_LIMIT {
+                        errors.push(SchemaError::forbidden(
+                            child_path(&rule_path, "messageExpression"),
+                            cost_error_message(
+                                "estimated messageExpression cost",
+                                cost,
+                                STATIC_ESTIMATED_COST_LIMIT,
+                            ),
+                        ));
+                    }
+                }
+                Err(err) => errors.push(SchemaError::invalid(
+                    child_path(&rule_path, "messageExpression"),
+                    &rule.message_expression,
+                    format!("messageExpression compilation failed: {}", err),
+                )),
+            }
+        }
+    }
+
+    let mut properties: Vec<_> = schema.properties.iter().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, property) in properties {
+        let property_path = key_path(&child_path(path, "properties"), name);
+        compile_rules_at(&property_path, property, cardinality, total, errors);
+    }
+    let element_cardinality = cardinality.saturating_mul(max_size(schema).max.max(1));
+    if let Some(items) = items_schema(schema) {
+        compile_rules_at(&child_path(path, "items"), items, element_cardinality, total, errors);
+    }
+    if let Some(values) = additional_properties_schema(schema) {
+        compile_rules_at(
+            &child_path(path, "additionalProperties"),
+            values,
+            element_cardinality,
+            total,
+            errors,
+        );
+    }
+}
+
+/// Evaluates the `x-kubernetes-validations` rules in `schema` against
+/// `value`. `old_value` is the previous version of the object on update;
+/// transition rules, which reference `oldSelf`, only run where an old value
+/// exists unless they set `optionalOldSelf`. `path` is the field path of
+/// `value`.
+pub fn validate_rules(
+    path: &str,
+    value: &JsonValue,
+    old_value: Option<&JsonValue>,
+    schema: &JSONSchemaProps,
+) -> Vec<SchemaError> {
+    let mut validator = RuleValidator {
+        budget: RUNTIME_COST_BUDGET,
+        programs: HashMap::new(),
+        errors: Vec::new(),
+    };
+    validator.validate(path, value, old_value, schema);
+    validator.errors
+}
+
+/// A compiled rule and its message expression, or `None` if the rule does
+/// not compile.
+type CompiledRule = Option<(Program, Option<Program>)>;
+
+struct RuleValidator {
+    budget: u64,
+    /// Compiled rules and message expressions, keyed by the address of the
+    /// schema that declares them.
+    programs: HashMap<(usize, usize, bool), CompiledRule>,
+    errors: Vec<SchemaError>,
+}
+
+fn rule_error(path: &str, type_: &str, rule: &ValidationRule, message: String) -> SchemaError {
+    let field = if rule.field_path.is_empty() {
+        path.to_string()
+    } else if rule.field_path.starts_with('.') {
+        format!("{}{}", path, rule.field_path)
+    } else {
+        child_path(path, &rule.field_path)
+    };
+    match rule.reason.as_deref() {
+        Some(FIELD_VALUE_REQUIRED) => SchemaError::required(field, message),
+        Some(FIELD_VALUE_FORBIDDEN) => SchemaError::forbidden(field, message),
+        Some(FIELD_VALUE_DUPLICATE) => SchemaError {
+            field,
+            message: format!("Duplicate value: {}", message),
+            error_type: SchemaErrorType::Invalid,
+        },
+        _ => SchemaError::invalid(field, type_, message),
+    }
+}
+
+fn default_message(rule: &ValidationRule) -> String {
+    if rule.message.is_empty() {
+        format!("failed rule: {}", rule.rule.trim())
+    } else {
+        rule.message.clone()
+    }
+}
+
+impl RuleValidator {
+    fn validate(&mut self, path: &str, value: &JsonValue, old_value: Option<&JsonValue>, schema: &JSONSchemaProps) {
+        if value.is_null() {
+            return;
+        }
+        if !schema.x_validations.is_empty() {
+            self.evaluate_rules(path, value, old_value, schema);
+        }
+
+        match value {
+            JsonValue::Object(map) if !schema.properties.is_empty() => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                for key in keys {
+                    if let Some(property) = schema.properties.get(key) {
+                        let old = old_value.and_then(|old| old.get(key));
+                        self.validate(&child_path(path, key), &map[key], old, property);
+                    }
+                }
+            }
+            JsonValue::Object(map) => {
+                if let Some(values) = additional_properties_schema(schema) {
+                    let mut keys: Vec<&String> = map.keys().collect();
+                    keys.sort();
+                    for key in keys {
+                        let old = old_value.and_then(|old| old.get(key));
+                        self.validate(&key_path(path, key), &map[key], old, values);
+                    }
+                }
+            }
+            JsonValue::Array(items) => {
+                if let Some(items_schema) = items_schema(schema) {
+                    let old_items = correlatable_items(schema, old_value);
+                    for (idx, item) in items.iter().enumerate() {
+                        let old = old_items.as_ref().and_then(|old| correlate(schema, item, old));
+                        self.validate(&index_path(path, idx), item, old, items_schema);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn evaluate_rules(&mut self, path: &str, value: &JsonValue, old_value: Option<&JsonValue>, schema: &JSONSchemaProps) {
+        let self_value = to_cel_value(value, schema);
+        let old_self_value = old_value.map(|old| to_cel_value(old, schema));
+        let schema_key = schema as *const JSONSchemaProps as usize;
+
+        for (idx, rule) in schema.x_validations.iter().enumerate() {
+            let optional_old_self = rule.optional_old_self == Some(true);
+            let compiled = self
+                .programs
+                .entry((schema_key, idx, optional_old_self))
+                .or_insert_with(|| {
+                    let env = rule_env(schema, optional_old_self);
+                    let program = env.compile(&rule.rule).ok()?;
+                    let message = (!rule.message_expression.is_empty())
+                        .then(|| env.compile(&rule.message_expression).ok())
+                        .flatten();
+                    Some((program, message))
+                })
+                .clone();
+            let Some((program, message_program)) = compiled else {
+                self.errors.push(SchemaError::invalid(
+                    path,
+                    &schema.type_,
+                    format!("rule compile error: {}", rule.rule),
+                ));
+                continue;
+            };
+
+            let mut activation = BTreeMap::from([(SELF_VAR.to_string(), self_value.clone())]);
+            if program.references(OLD_SELF_VAR) {
+                match (&old_self_value, optional_old_self) {
+                    (Some(old), false) => {
+                        activation.insert(OLD_SELF_VAR.to_string(), old.clone());
+                    }
+                    (old, true) => {
+                        activation.insert(OLD_SELF_VAR.to_string(), Value::optional(old.clone()));
+                    }
+                    (None, false) => continue,
+                }
+            }
+
+            let limit = self.budget.min(PER_CALL_LIMIT);
+            match program.evaluate_with_cost_limit(&activation, limit) {
+                Ok((result, cost)) => {
+                    self.budget -= cost;
+                    if result == Value::Bool(true) {
+                        continue;
+                    }
+                    if result != Value::Bool(false) {
+                        self.errors.push(SchemaError::invalid(
+                            path,
+                            &schema.type_,
+                            format!("validation rule '{}' did not evaluate to a bool", rule.rule.trim()),
+                        ));
+                        continue;
+                    }
+                    let message = self.message(&activation, message_program.as_ref(), rule);
+                    self.errors.push(rule_error(path, &schema.type_, rule, message));
+                }
+                Err(EvalError::CostLimitExceeded) if limit < PER_CALL_LIMIT => {
+                    self.errors.push(SchemaError::invalid(
+                        path,
+                        &schema.type_,
+                        "validation failed due to running out of cost budget, no further validation rules will be run",
+                    ));
+                    self.budget = 0;
+                    return;
+                }
+                Err(EvalError::CostLimitExceeded) => {
+                    self.errors.push(SchemaError::invalid(
+                        path,
+                        &schema.type_,
+                        format!("call cost exceeds limit for rule: {}", rule.rule.trim()),
+                    ));
+                }
+                Err(err) => {
+                    self.errors
+                        .push(SchemaError::invalid(path, &schema.type_, format!("{}: {}", default_message(rule), err)));
+                }
+            }
+        }
+    }
+
+    fn message(&mut self, activation: &BTreeMap<String, Value>, program: Option<&Program>, rule: &ValidationRule) -> String {
+        let Some(program) = program else {
+            return default_message(rule);
+        };
+        match program.evaluate_with_cost_limit(activation, self.budget.min(PER_CALL_LIMIT)) {
+            Ok((Value::String(message), cost)) => {
+                self.budget = self.budget.saturating_sub(cost);
+                if message.trim().is_empty() || message.contains('\n') {
+                    default_message(rule)
+                } else {
+                    message.to_string()
+                }
+            }
+            _ => default_message(rule),
+        }
+    }
+}
+
+/// Returns the old list items that new items can be matched against. Only
+/// items of `map` lists are correlated, by their keys, as in Go.
+fn correlatable_items<'a>(schema: &JSONSchemaProps, old_value: Option<&'a JsonValue>) -> Option<&'a Vec<JsonValue>> {
+    if schema.x_list_type.as_deref() != Some("map") || schema.x_list_map_keys.is_empty() {
+        return None;
+    }
+    old_value?.as_array()
+}
+
+fn correlate<'a>(schema: &JSONSchemaProps, item: &JsonValue, old_items: &'a [JsonValue]) -> Option<&'a JsonValue> {
+    old_items
+        .iter()
+        .find(|old| schema.x_list_map_keys.iter().all(|key| old.get(key) == item.get(key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(value: JsonValue) -> JSONSchemaProps {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_escape_property_name() {
+        assert_eq!(escape_property_name("replicas").as_deref(), Some("replicas"));
+        assert_eq!(escape_property_name("in").as_deref(), Some("__in__"));
+        assert_eq!(escape_property_name("a-b.c/d").as_deref(), Some("a__dash__b__dot__c__slash__d"));
+        assert_eq!(escape_property_name("a__b").as_deref(), Some("a__underscores__b"));
+        assert_eq!(escape_property_name("1abc"), None);
+    }
+
+    #[test]
+    fn test_validate_validation_rules() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "replicas": {"type": "integer"},
+                "names": {"type": "array", "items": {"type": "string"}},
+            },
+            "x-kubernetes-validations": [
+                {"rule": "self.replicas >= 0"},
+                {"rule": "self.replicas"},
+                {"rule": "self.missing > 0"},
+                {"rule": "self.names.all(a, self.names.all(b, a.contains(b)))"},
+                {"rule": "self.replicas > 1", "optionalOldSelf": true},
+            ],
+        }));
+        let errors = validate_validation_rules("openAPIV3Schema", &schema);
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "openAPIV3Schema.x-kubernetes-validations[1].rule",
+                "openAPIV3Schema.x-kubernetes-validations[2].rule",
+                "openAPIV3Schema.x-kubernetes-validations[3].rule",
+                "openAPIV3Schema.x-kubernetes-validations[4].optionalOldSelf",
+                "openAPIV3Schema",
+            ]
+        );
+        assert!(errors[0].message.contains("must evaluate to a bool"));
+        assert!(errors[1].message.contains("compilation failed: ERROR: <input>:1:5: undefined field 'missing'"));
+        assert!(errors[2].message.starts_with("estimated rule cost exceeds budget by factor of more than 100x"));
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "spec": {
+                    "type": "object",
+                    "properties": {
+                        "min-replicas": {"type": "integer"},
+                        "maxReplicas": {"type": "integer"},
+                        "timeout": {"type": "string", "format": "duration"},
+                    },
+                    "x-kubernetes-validations": [
+                        {"rule": "self.min__dash__replicas <= self.maxReplicas", "message": "min must not exceed max"},
+                        {"rule": "self.timeout < duration('1m')", "fieldPath": ".timeout",
+                         "messageExpression": "'timeout ' + string(self.timeout) + ' too long'"},
+                        {"rule": "self.maxReplicas >= oldSelf.maxReplicas", "reason": "FieldValueForbidden"},
+                    ],
+                },
+            },
+        }));
+        let object = json!({"spec": {"min-replicas": 5, "maxReplicas": 3, "timeout": "2m"}});
+        let errors = validate_rules("", &object, None, &schema);
+        assert_eq!(
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "spec: Invalid value: \"object\": min must not exceed max",
+                "spec.timeout: Invalid value: \"object\": timeout 120s too long",
+            ]
+        );
+
+        let old = json!({"spec": {"min-replicas": 1, "maxReplicas": 10, "timeout": "2s"}});
+        let new = json!({"spec": {"min-replicas": 1, "maxReplicas": 4, "timeout": "2s"}});
+        let errors = validate_rules("", &new, Some(&old), &schema);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error_type, SchemaErrorType::Forbidden);
+        assert_eq!(errors[0].message, "failed rule: self.maxReplicas >= oldSelf.maxReplicas");
+    }
+}
@@ -0,0 +1,279 @@
+//! Defaulting of custom resources
+//!
+//! Applies the `default` values of a structural schema to a custom resource
+//! at every level: object properties, array items and `additionalProperties`
+//! values. Non-nullable fields that are `null` are treated as unset. This
+//! follows `structuraldefaulting` in Go.
+
+use serde_json::Value;
+
+use super::pruning::prune;
+use super::validation::validate_value;
+use super::{additional_properties_schema, child_path, items_schema, key_path, SchemaError};
+use crate::apis::apiextensions::v1::{CustomResourceDefinitionVersion, JSONSchemaProps};
+
+fn is_non_nullable_null(value: &Value, schema: &JSONSchemaProps) -> bool {
+    value.is_null() && schema.nullable != Some(true)
+}
+
+/// Fills the defaults declared in `schema` into `obj`.
+///
+/// A field is defaulted when it is missing, or when it is `null` and its
+/// schema is not nullable. Defaults are applied top-down, so defaults nested
+/// in a defaulted value are filled in as well.
+pub fn apply_defaults(obj: &mut Value, schema: &JSONSchemaProps) {
+    match obj {
+        Value::Object(map) => {
+            for (name, property) in &schema.properties {
+                let Some(default) = &property.default else {
+                    continue;
+                };
+                let unset = map
+                    .get(name)
+                    .is_none_or(|value| is_non_nullable_null(value, property));
+                if unset {
+                    map.insert(name.clone(), default.clone());
+                }
+            }
+            let additional = additional_properties_schema(schema);
+            for (name, value) in map.iter_mut() {
+                if let Some(property) = schema.properties.get(name) {
+                    apply_defaults(value, property);
+                } else if let Some(additional) = additional {
+                    if let Some(default) = &additional.default {
+                        if is_non_nullable_null(value, additional) {
+                            *value = default.clone();
+                        }
+                    }
+                    apply_defaults(value, additional);
+                }
+            }
+        }
+        Value::Array(items) => {
+            let Some(item_schema) = items_schema(schema) else {
+                return;
+            };
+            for item in items.iter_mut() {
+                if let Some(default) = &item_schema.default {
+                    if is_non_nullable_null(item, item_schema) {
+                        *item = default.clone();
+                    }
+                }
+                apply_defaults(item, item_schema);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes object fields that are `null` although their schema is neither
+/// nullable nor has a default. Nulls in arrays are kept.
+pub fn prune_non_nullable_nulls_without_defaults(obj: &mut Value, schema: &JSONSchemaProps) {
+    match obj {
+        Value::Object(map) => {
+            let additional = additional_properties_schema(schema);
+            map.retain(|name, value| {
+                let field_schema = schema.properties.get(name).or(additional);
+                match field_schema {
+                    Some(field_schema) => {
+                        if is_non_nullable_null(value, field_schema) && field_schema.default.is_none() {
+                            return false;
+                        }
+                        prune_non_nullable_nulls_without_defaults(value, field_schema);
+                        true
+                    }
+                    None => true,
+                }
+            });
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = items_schema(schema) {
+                for item in items.iter_mut() {
+                    prune_non_nullable_nulls_without_defaults(item, item_schema);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Defaults and then prunes `obj` in the order the apiserver uses when it
+/// decodes a custom resource. Returns the sorted paths of pruned fields.
+pub fn default_and_prune(obj: &mut Value, schema: &JSONSchemaProps, is_resource_root: bool) -> Vec<String> {
+    prune_non_nullable_nulls_without_defaults(obj, schema);
+    apply_defaults(obj, schema);
+    prune(obj, schema, is_resource_root)
+}
+
+/// Defaults and prunes a custom resource using the schema of a CRD version.
+/// Objects of versions without a schema are left untouched.
+pub fn apply_defaults_custom_resource(
+    obj: &mut Value,
+    version: &CustomResourceDefinitionVersion,
+) -> Vec<String> {
+    match version
+        .schema
+        .as_ref()
+        .and_then(|validation| validation.open_apiv3_schema.as_ref())
+    {
+        Some(schema) => default_and_prune(obj, schema, true),
+        None => Vec::new(),
+    }
+}
+
+/// Validates every `default` declared in `schema`.
+///
+/// A default must not contain fields that would be pruned and, once its own
+/// nested defaults are applied, must validate against the schema it is
+/// declared on. `path` is the field path of the schema itself.
+pub fn validate_defaults(path: &str, schema: &JSONSchemaProps) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    validate_defaults_at(path, schema, &mut errors);
+    errors
+}
+
+fn validate_defaults_at(path: &str, schema: &JSONSchemaProps, errors: &mut Vec<SchemaError>) {
+    if let Some(default) = &schema.default {
+        let default_path = child_path(path, "default");
+        let mut defaulted = default.clone();
+        apply_defaults(&mut defaulted, schema);
+        let pruned = prune(&mut defaulted, schema, schema.x_embedded_resource == Some(true));
+        if pruned.is_empty() {
+            errors.extend(validate_value(&default_path, &defaulted, schema));
+        } else {
+            errors.push(SchemaError::invalid(
+                default_path,
+                default,
+                format!("must not have unknown fields, got: {}", pruned.join(", ")),
+            ));
+        }
+    }
+
+    let mut properties: Vec<_> = schema.properties.iter().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, property) in properties {
+        validate_defaults_at(&key_path(&child_path(path, "properties"), name), property, errors);
+    }
+    if let Some(items) = items_schema(schema) {
+        validate_defaults_at(&child_path(path, "items"), items, errors);
+    }
+    if let Some(additional) = additional_properties_schema(schema) {
+        validate_defaults_at(&child_path(path, "additionalProperties"), additional, errors);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(value: Value) -> JSONSchemaProps {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn nested_schema() -> JSONSchemaProps {
+        schema(json!({
+            "type": "object",
+            "properties": {
+                "spec": {
+                    "type": "object",
+                    "default": {},
+                    "properties": {
+                        "replicas": {"type": "integer", "default": 1},
+                        "mode": {"type": "string", "default": "auto"},
+                        "note": {"type": "string", "nullable": true, "default": "n"},
+                        "ports": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {"protocol": {"type": "string", "default": "TCP"}}
+                            }
+                        },
+                        "limits": {
+                            "type": "object",
+                            "additionalProperties": {
+                                "type": "object",
+                                "properties": {"max": {"type": "integer", "default": 10}}
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+    }
+
+    #[test]
+    fn test_apply_defaults_nested() {
+        let mut obj = json!({"spec": {"ports": [{}, {"protocol": "UDP"}], "limits": {"cpu": {}}}});
+        apply_defaults(&mut obj, &nested_schema());
+        assert_eq!(
+            obj,
+            json!({"spec": {
+                "replicas": 1,
+                "mode": "auto",
+                "note": "n",
+                "ports": [{"protocol": "TCP"}, {"protocol": "UDP"}],
+                "limits": {"cpu": {"max": 10}}
+            }})
+        );
+
+        let mut obj = json!({});
+        apply_defaults(&mut obj, &nested_schema());
+        assert_eq!(obj, json!({"spec": {"replicas": 1, "mode": "auto", "note": "n"}}));
+    }
+
+    #[test]
+    fn test_apply_defaults_nullable() {
+        let mut obj = json!({"spec": {"mode": null, "note": null}});
+        apply_defaults(&mut obj, &nested_schema());
+        assert_eq!(obj["spec"]["mode"], json!("auto"));
+        assert_eq!(obj["spec"]["note"], Value::Null);
+    }
+
+    #[test]
+    fn test_default_and_prune() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "spec": {
+                    "type": "object",
+                    "properties": {
+                        "a": {"type": "string"},
+                        "b": {"type": "string", "default": "x"}
+                    }
+                }
+            }
+        }));
+        let mut obj = json!({"kind": "Foo", "spec": {"a": null, "b": null, "c": 1}});
+        let pruned = default_and_prune(&mut obj, &schema, true);
+        assert_eq!(pruned, vec!["spec.c"]);
+        assert_eq!(obj, json!({"kind": "Foo", "spec": {"b": "x"}}));
+    }
+
+    #[test]
+    fn test_validate_defaults() {
+        assert!(validate_defaults("openAPIV3Schema", &nested_schema()).is_empty());
+
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "replicas": {"type": "integer", "minimum": 1, "default": 0},
+                "spec": {
+                    "type": "object",
+                    "properties": {"a": {"type": "string"}},
+                    "default": {"a": "x", "b": "y"}
+                }
+            }
+        }));
+        let errors = validate_defaults("openAPIV3Schema", &schema);
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "openAPIV3Schema.properties[replicas].default",
+                "openAPIV3Schema.properties[spec].default",
+            ]
+        );
+        assert!(errors[1].message.contains("must not have unknown fields, got: b"));
+    }
+}
@@ -0,0 +1,495 @@
+//! Schema generation from Rust types
+//!
+//! `JsonSchema` produces the OpenAPI v3 schema of a Rust type, and
+//! `CustomResource` wraps the schema of a custom resource type into a ready
+//! `CustomResourceDefinition`. Implement `JsonSchema` with
+//! `#[derive(JsonSchema)]` (feature `derive`), which reads doc comments and
+//! serde attributes, or by hand.
+//!
+//! `Option<T>` fields are optional and nullable, fields with `#[serde(default)]`
+//! are optional, and every other field is required.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::apis::apiextensions::v1::{
+    CustomResourceDefinition, CustomResourceDefinitionNames, CustomResourceDefinitionSpec,
+    CustomResourceDefinitionVersion, CustomResourceSubresourceStatus, CustomResourceSubresources,
+    CustomResourceValidation, JSONSchemaProps, JSONSchemaPropsOrArray, JSONSchemaPropsOrBool,
+    RESOURCE_SCOPE_NAMESPACE,
+};
+use k8s_apimachinery::apis::meta::v1::{ObjectMeta, TypeMeta};
+
+mod core_v1;
+
+/// The pattern the apiserver publishes for `resource.Quantity`.
+pub const QUANTITY_PATTERN: &str = r"^(\+|-)?(([0-9]+(\.[0-9]*)?)|(\.[0-9]+))(([KMGTPE]i)|[numkMGTPE]|([eE](\+|-)?(([0-9]+(\.[0-9]*)?)|(\.[0-9]+))))?$";
+
+/// JsonSchema is implemented by types that can describe their JSON encoding
+/// as an OpenAPI v3 schema.
+pub trait JsonSchema {
+    /// Returns the schema of the type.
+    fn json_schema() -> JSONSchemaProps;
+
+    /// Returns true if a field of this type may be omitted.
+    fn is_optional() -> bool {
+        false
+    }
+}
+
+/// CustomResource describes the API of a custom resource type, whose schema
+/// becomes the `openAPIV3Schema` of the generated CustomResourceDefinition.
+pub trait CustomResource: JsonSchema {
+    /// API group, e.g. `example.com`.
+    const GROUP: &'static str;
+    /// API version, e.g. `v1`.
+    const VERSION: &'static str;
+    /// Kind, e.g. `Foo`.
+    const KIND: &'static str;
+    /// Plural resource name, e.g. `foos`.
+    const PLURAL: &'static str;
+    /// `Namespaced` or `Cluster`.
+    const SCOPE: &'static str = RESOURCE_SCOPE_NAMESPACE;
+    /// Short names, e.g. `["fo"]`.
+    const SHORT_NAMES: &'static [&'static str] = &[];
+    /// Categories, e.g. `["all"]`.
+    const CATEGORIES: &'static [&'static str] = &[];
+
+    /// Returns `<group>/<version>`.
+    fn api_version() -> String {
+        format!("{}/{}", Self::GROUP, Self::VERSION)
+    }
+
+    /// Returns a CustomResourceDefinition serving this type as the only,
+    /// storage version. A `status` property enables the status subresource.
+    fn custom_resource_definition() -> CustomResourceDefinition {
+        let mut schema = Self::json_schema();
+        // The root metadata schema must not restrict the shared ObjectMeta.
+        if schema.properties.contains_key("metadata") {
+            schema.properties.insert("metadata".to_string(), object_schema(""));
+        }
+        for name in ["apiVersion", "kind", "metadata"] {
+            schema.required.retain(|required| required != name);
+        }
+        schema.nullable = None;
+        let subresources = schema.properties.contains_key("status").then_some(CustomResourceSubresources {
+            status: Some(CustomResourceSubresourceStatus {}),
+            scale: None,
+        });
+
+        CustomResourceDefinition {
+            type_meta: TypeMeta::new("apiextensions.k8s.io/v1", "CustomResourceDefinition"),
+            metadata: ObjectMeta::named(format!("{}.{}", Self::PLURAL, Self::GROUP)),
+            spec: CustomResourceDefinitionSpec {
+                group: Self::GROUP.to_string(),
+                names: CustomResourceDefinitionNames {
+                    plural: Self::PLURAL.to_string(),
+                    singular: Self::KIND.to_ascii_lowercase(),
+                    short_names: Self::SHORT_NAMES.iter().map(|s| s.to_string()).collect(),
+                    kind: Self::KIND.to_string(),
+                    list_kind: format!("{}List", Self::KIND),
+                    categories: Self::CATEGORIES.iter().map(|s| s.to_string()).collect(),
+                },
+                scope: Self::SCOPE.to_string(),
+                versions: vec![CustomResourceDefinitionVersion {
+                    name: Self::VERSION.to_string(),
+                    served: true,
+                    storage: true,
+                    schema: Some(CustomResourceValidation {
+                        open_apiv3_schema: Some(schema),
+                    }),
+                    subresources,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            status: None,
+        }
+    }
+}
+
+/// Returns `type: object` with the given description.
+pub fn object_schema(description: &str) -> JSONSchemaProps {
+    JSONSchemaProps {
+        type_: "object".to_string(),
+        description: description.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Returns `type: string` restricted to the given values.
+pub fn string_enum_schema(description: &str, values: &[&str]) -> JSONSchemaProps {
+    JSONSchemaProps {
+        type_: "string".to_string(),
+        description: description.to_string(),
+        enum_: values.iter().map(|v| serde_json::Value::String(v.to_string())).collect(),
+        ..Default::default()
+    }
+}
+
+fn typed_schema(type_: &str, format: &str) -> JSONSchemaProps {
+    JSONSchemaProps {
+        type_: type_.to_string(),
+        format: format.to_string(),
+        ..Default::default()
+    }
+}
+
+fn int_or_string_schema() -> JSONSchemaProps {
+    JSONSchemaProps {
+        x_int_or_string: Some(true),
+        any_of: vec![typed_schema("integer", ""), typed_schema("string", "")],
+        ..Default::default()
+    }
+}
+
+fn array_schema(items: JSONSchemaProps) -> JSONSchemaProps {
+    JSONSchemaProps {
+        type_: "array".to_string(),
+        items: Some(JSONSchemaPropsOrArray::Schema(Box::new(items))),
+        ..Default::default()
+    }
+}
+
+fn map_schema(values: JSONSchemaProps) -> JSONSchemaProps {
+    JSONSchemaProps {
+        type_: "object".to_string(),
+        additional_properties: Some(JSONSchemaPropsOrBool::Schema(Box::new(values))),
+        ..Default::default()
+    }
+}
+
+fn property(schema: &mut JSONSchemaProps, name: &str, property: JSONSchemaProps, required: bool) {
+    schema.properties.insert(name.to_string(), property);
+    if required {
+        schema.required.push(name.to_string());
+    }
+}
+
+// =============================================================================
+// Primitive and collection types
+// =============================================================================
+
+macro_rules! impl_json_schema {
+    ($type_:literal, $format:literal: $($t:ty),*) => {
+        $(
+            impl JsonSchema for $t {
+                fn json_schema() -> JSONSchemaProps {
+                    typed_schema($type_, $format)
+                }
+            }
+        )*
+    };
+}
+
+impl_json_schema!("boolean", "": bool);
+impl_json_schema!("integer", "int32": i8, i16, i32, u8, u16);
+impl_json_schema!("integer", "int64": i64, u32, u64, isize, usize);
+impl_json_schema!("number", "float": f32);
+impl_json_schema!("number", "double": f64);
+impl_json_schema!("string", "": String, str, char);
+
+impl<T: JsonSchema + ?Sized> JsonSchema for Box<T> {
+    fn json_schema() -> JSONSchemaProps {
+        T::json_schema()
+    }
+
+    fn is_optional() -> bool {
+        T::is_optional()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = T::json_schema();
+        schema.nullable = Some(true);
+        schema
+    }
+
+    fn is_optional() -> bool {
+        true
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn json_schema() -> JSONSchemaProps {
+        array_schema(T::json_schema())
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for BTreeSet<T> {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = array_schema(T::json_schema());
+        schema.x_list_type = Some("set".to_string());
+        schema
+    }
+}
+
+impl<T: JsonSchema, S> JsonSchema for HashSet<T, S> {
+    fn json_schema() -> JSONSchemaProps {
+        BTreeSet::<T>::json_schema()
+    }
+}
+
+impl<V: JsonSchema> JsonSchema for BTreeMap<String, V> {
+    fn json_schema() -> JSONSchemaProps {
+        map_schema(V::json_schema())
+    }
+}
+
+impl<V: JsonSchema, S> JsonSchema for HashMap<String, V, S> {
+    fn json_schema() -> JSONSchemaProps {
+        map_schema(V::json_schema())
+    }
+}
+
+impl JsonSchema for serde_json::Value {
+    fn json_schema() -> JSONSchemaProps {
+        JSONSchemaProps {
+            x_preserve_unknown_fields: Some(true),
+            ..Default::default()
+        }
+    }
+}
+
+// =============================================================================
+// Kubernetes types
+// =============================================================================
+
+impl JsonSchema for k8s_api_core::Quantity {
+    fn json_schema() -> JSONSchemaProps {
+        JSONSchemaProps {
+            pattern: QUANTITY_PATTERN.to_string(),
+            ..int_or_string_schema()
+        }
+    }
+}
+
+impl JsonSchema for k8s_api_core::IntOrString {
+    fn json_schema() -> JSONSchemaProps {
+        int_or_string_schema()
+    }
+}
+
+impl JsonSchema for k8s_apimachinery::apis::meta::v1::Time {
+    fn json_schema() -> JSONSchemaProps {
+        typed_schema("string", "date-time")
+    }
+}
+
+impl JsonSchema for k8s_apimachinery::apis::meta::v1::MicroTime {
+    fn json_schema() -> JSONSchemaProps {
+        typed_schema("string", "date-time")
+    }
+}
+
+impl JsonSchema for TypeMeta {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = object_schema("");
+        property(&mut schema, "apiVersion", typed_schema("string", ""), false);
+        property(&mut schema, "kind", typed_schema("string", ""), false);
+        schema
+    }
+}
+
+/// ObjectMeta is validated by the apiserver itself, so it is only typed as an object.
+impl JsonSchema for ObjectMeta {
+    fn json_schema() -> JSONSchemaProps {
+        object_schema("")
+    }
+}
+
+impl JsonSchema for k8s_apimachinery::apis::meta::v1::LabelSelectorRequirement {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = object_schema("");
+        property(&mut schema, "key", typed_schema("string", ""), true);
+        property(&mut schema, "operator", typed_schema("string", ""), true);
+        let mut values = Vec::<String>::json_schema();
+        values.x_list_type = Some("atomic".to_string());
+        property(&mut schema, "values", values, false);
+        schema
+    }
+}
+
+impl JsonSchema for k8s_apimachinery::apis::meta::v1::LabelSelector {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = object_schema("");
+        property(&mut schema, "matchLabels", BTreeMap::<String, String>::json_schema(), false);
+        let mut expressions =
+            Vec::<k8s_apimachinery::apis::meta::v1::LabelSelectorRequirement>::json_schema();
+        expressions.x_list_type = Some("atomic".to_string());
+        property(&mut schema, "matchExpressions", expressions, false);
+        schema.x_map_type = Some("atomic".to_string());
+        schema
+    }
+}
+
+impl JsonSchema for k8s_apimachinery::apis::meta::v1::Condition {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = object_schema("");
+        property(&mut schema, "type", typed_schema("string", ""), true);
+        property(&mut schema, "status", string_enum_schema("", &["True", "False", "Unknown"]), true);
+        property(&mut schema, "observedGeneration", i64::json_schema(), false);
+        property(&mut schema, "lastTransitionTime", typed_schema("string", "date-time"), true);
+        property(&mut schema, "reason", typed_schema("string", ""), false);
+        property(&mut schema, "message", typed_schema("string", ""), false);
+        schema
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use super::*;
+    use crate::schema::structural::validate_custom_resource_definition_structural;
+    use crate::JsonSchema;
+    use k8s_apimachinery::apis::meta::v1::Time;
+    use serde::{Deserialize, Serialize};
+
+    /// Foo is an example resource.
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct Foo {
+        #[serde(flatten)]
+        type_meta: TypeMeta,
+        #[serde(default)]
+        metadata: ObjectMeta,
+        spec: FooSpec,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<FooStatus>,
+    }
+
+    /// FooSpec is the desired state of a Foo.
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct FooSpec {
+        /// Number of desired replicas.
+        replicas: i32,
+        /// Resources consumed by each replica.
+        resource_limit: Option<k8s_api_core::Quantity>,
+        #[serde(rename = "template")]
+        pod_template: k8s_api::core::v1::PodTemplateSpec,
+        #[serde(default)]
+        #[schema(list_type = "map", list_map_key = "name")]
+        ports: Vec<FooPort>,
+        #[serde(default)]
+        mode: FooMode,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        cache: String,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct FooPort {
+        name: String,
+        port: k8s_api_core::IntOrString,
+    }
+
+    #[derive(Default, Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum FooMode {
+        #[default]
+        Auto,
+        Manual,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct FooStatus {
+        last_update_time: Option<Time>,
+        #[schema(list_type = "set")]
+        #[serde(default)]
+        nodes: Vec<String>,
+    }
+
+    impl CustomResource for Foo {
+        const GROUP: &'static str = "example.com";
+        const VERSION: &'static str = "v1";
+        const KIND: &'static str = "Foo";
+        const PLURAL: &'static str = "foos";
+    }
+
+    #[test]
+    fn test_derive_json_schema() {
+        let schema = FooSpec::json_schema();
+        assert_eq!(schema.type_, "object");
+        assert_eq!(schema.description, "FooSpec is the desired state of a Foo.");
+        let mut required = schema.required.clone();
+        required.sort();
+        assert_eq!(required, vec!["replicas", "template"]);
+
+        let replicas = &schema.properties["replicas"];
+        assert_eq!(replicas.type_, "integer");
+        assert_eq!(replicas.format, "int32");
+        assert_eq!(replicas.description, "Number of desired replicas.");
+
+        let limit = &schema.properties["resourceLimit"];
+        assert_eq!(limit.x_int_or_string, Some(true));
+        assert_eq!(limit.nullable, Some(true));
+        assert_eq!(limit.pattern, QUANTITY_PATTERN);
+
+        let ports = &schema.properties["ports"];
+        assert_eq!(ports.x_list_type.as_deref(), Some("map"));
+        assert_eq!(ports.x_list_map_keys, vec!["name"]);
+
+        assert_eq!(
+            schema.properties["mode"].enum_,
+            vec![serde_json::json!("auto"), serde_json::json!("manual")]
+        );
+        assert!(!schema.properties.contains_key("cache"));
+        let template = &schema.properties["template"];
+        assert_eq!(template.properties["metadata"].properties["labels"].type_, "object");
+        let pod_spec = &template.properties["spec"];
+        assert_eq!(pod_spec.x_preserve_unknown_fields, None);
+        assert_eq!(pod_spec.required, vec!["containers"]);
+        let container = pod_spec.properties["containers"].items.as_ref().unwrap();
+        let JSONSchemaPropsOrArray::Schema(container) = container else {
+            panic!("expected a container schema");
+        };
+        assert_eq!(container.required, vec!["name"]);
+        assert!(container.properties["livenessProbe"].properties.contains_key("httpGet"));
+
+        let status = FooStatus::json_schema();
+        assert_eq!(status.properties["lastUpdateTime"].format, "date-time");
+        assert_eq!(status.properties["nodes"].x_list_type.as_deref(), Some("set"));
+    }
+
+    #[test]
+    fn test_custom_resource_definition() {
+        let crd = Foo::custom_resource_definition();
+        assert_eq!(crd.metadata.name, "foos.example.com");
+        assert_eq!(crd.spec.names.singular, "foo");
+        assert_eq!(crd.spec.names.list_kind, "FooList");
+        assert_eq!(crd.spec.scope, "Namespaced");
+        assert_eq!(Foo::api_version(), "example.com/v1");
+
+        let version = &crd.spec.versions[0];
+        assert!(version.served && version.storage);
+        assert!(version.subresources.as_ref().unwrap().status.is_some());
+
+        let schema = version.schema.as_ref().unwrap().open_apiv3_schema.as_ref().unwrap();
+        assert_eq!(schema.description, "Foo is an example resource.");
+        assert!(schema.properties.contains_key("apiVersion"));
+        assert!(schema.properties.contains_key("kind"));
+        assert_eq!(schema.required, vec!["spec"]);
+
+        let errors = validate_custom_resource_definition_structural(&crd);
+        assert!(errors.is_empty(), "expected no errors: {:?}", errors);
+
+        // Pruning keeps the template labels the selector relies on.
+        let mut foo = serde_json::json!({
+            "apiVersion": "example.com/v1",
+            "kind": "Foo",
+            "metadata": {"name": "foo"},
+            "spec": {
+                "replicas": 1,
+                "template": {
+                    "metadata": {"labels": {"app": "foo"}, "annotations": {"note": "x"}},
+                    "spec": {"containers": [{"name": "app", "image": "nginx", "bogus": true}]}
+                }
+            }
+        });
+        let pruned = crate::schema::prune(&mut foo, schema, true);
+        assert_eq!(pruned, vec!["spec.template.spec.containers[0].bogus"]);
+        assert_eq!(foo["spec"]["template"]["metadata"]["labels"]["app"], "foo");
+        assert_eq!(foo["spec"]["template"]["spec"]["containers"][0]["image"], "nginx");
+    }
+}
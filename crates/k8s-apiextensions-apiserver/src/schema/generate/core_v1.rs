@@ -0,0 +1,888 @@
+//! Schemas of the core/v1 pod template types
+//!
+//! Written out field by field from the Go types, so that custom resources
+//! embedding a `PodTemplateSpec` get a structural schema and their
+//! templates survive pruning. Fields the Go types serialize without
+//! `omitempty` are required.
+
+use std::collections::BTreeMap;
+
+use k8s_api::core::v1::*;
+use k8s_api_core::{IntOrString, Quantity};
+use k8s_apimachinery::apis::meta::v1::LabelSelector;
+
+use super::{object_schema, property, typed_schema, JsonSchema};
+use crate::apis::apiextensions::v1::JSONSchemaProps;
+
+/// Implements `JsonSchema` for structs from their serialized field names
+/// and Rust field types, marking the fields preceded by `required`.
+macro_rules! object_json_schemas {
+    ($($t:ident { $($($required:ident)? $name:literal: $field:ty),* $(,)? })*) => {
+        $(
+            impl JsonSchema for $t {
+                fn json_schema() -> JSONSchemaProps {
+                    let mut schema = object_schema("");
+                    $(
+                        property(
+                            &mut schema,
+                            $name,
+                            <$field as JsonSchema>::json_schema(),
+                            object_json_schemas!(@required $($required)?),
+                        );
+                    )*
+                    schema
+                }
+            }
+        )*
+    };
+    (@required required) => {
+        true
+    };
+    (@required) => {
+        false
+    };
+}
+
+/// Metadata of objects created from a template. Unlike the metadata of the
+/// resource itself it is pruned like any other field, so the fields copied
+/// to the created objects are listed.
+fn template_metadata_schema() -> JSONSchemaProps {
+    let mut schema = object_schema("");
+    property(&mut schema, "name", typed_schema("string", ""), false);
+    property(&mut schema, "generateName", typed_schema("string", ""), false);
+    property(&mut schema, "namespace", typed_schema("string", ""), false);
+    property(&mut schema, "labels", BTreeMap::<String, String>::json_schema(), false);
+    property(&mut schema, "annotations", BTreeMap::<String, String>::json_schema(), false);
+    property(&mut schema, "finalizers", Vec::<String>::json_schema(), false);
+    schema
+}
+
+impl JsonSchema for PodTemplateSpec {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = object_schema("");
+        property(&mut schema, "metadata", template_metadata_schema(), false);
+        property(&mut schema, "spec", PodSpec::json_schema(), false);
+        schema
+    }
+}
+
+impl JsonSchema for PersistentVolumeClaimTemplate {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = object_schema("");
+        property(&mut schema, "metadata", template_metadata_schema(), false);
+        property(&mut schema, "spec", PersistentVolumeClaimSpec::json_schema(), true);
+        schema
+    }
+}
+
+impl JsonSchema for ResourceClaim {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = object_schema("");
+        property(&mut schema, "name", typed_schema("string", ""), true);
+        property(&mut schema, "request", typed_schema("string", ""), false);
+        schema
+    }
+}
+
+impl JsonSchema for ResourceRequirements {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = object_schema("");
+        let resources = BTreeMap::<String, Quantity>::json_schema();
+        property(&mut schema, "limits", resources.clone(), false);
+        property(&mut schema, "requests", resources, false);
+        let mut claims = Vec::<ResourceClaim>::json_schema();
+        claims.x_list_type = Some("map".to_string());
+        claims.x_list_map_keys = vec!["name".to_string()];
+        property(&mut schema, "claims", claims, false);
+        schema
+    }
+}
+
+/// The handler is inlined into the probe.
+impl JsonSchema for Probe {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = ProbeHandler::json_schema();
+        property(&mut schema, "initialDelaySeconds", Option::<i32>::json_schema(), false);
+        property(&mut schema, "timeoutSeconds", Option::<i32>::json_schema(), false);
+        property(&mut schema, "periodSeconds", Option::<i32>::json_schema(), false);
+        property(&mut schema, "successThreshold", Option::<i32>::json_schema(), false);
+        property(&mut schema, "failureThreshold", Option::<i32>::json_schema(), false);
+        property(&mut schema, "terminationGracePeriodSeconds", Option::<i64>::json_schema(), false);
+        schema
+    }
+}
+
+/// The volume source is inlined into the volume.
+impl JsonSchema for Volume {
+    fn json_schema() -> JSONSchemaProps {
+        let mut schema = VolumeSource::json_schema();
+        property(&mut schema, "name", typed_schema("string", ""), true);
+        schema
+    }
+}
+
+object_json_schemas! {
+    PodSpec {
+        required "containers": Vec<Container>,
+        "initContainers": Vec<Container>,
+        "ephemeralContainers": Vec<EphemeralContainer>,
+        "volumes": Vec<Volume>,
+        "restartPolicy": RestartPolicy,
+        "terminationGracePeriodSeconds": Option<i64>,
+        "activeDeadlineSeconds": Option<i64>,
+        "dnsPolicy": DNSPolicy,
+        "nodeSelector": BTreeMap<String, String>,
+        "serviceAccountName": String,
+        "serviceAccount": String,
+        "automountServiceAccountToken": Option<bool>,
+        "nodeName": String,
+        "hostNetwork": bool,
+        "hostPID": bool,
+        "hostIPC": bool,
+        "shareProcessNamespace": Option<bool>,
+        "securityContext": Option<PodSecurityContext>,
+        "imagePullSecrets": Vec<LocalObjectReference>,
+        "hostname": String,
+        "subdomain": String,
+        "affinity": Option<Affinity>,
+        "schedulerName": String,
+        "tolerations": Vec<Toleration>,
+        "hostAliases": Vec<HostAlias>,
+        "priorityClassName": String,
+        "priority": Option<i32>,
+        "dnsConfig": Option<PodDNSConfig>,
+        "readinessGates": Vec<PodReadinessGate>,
+        "runtimeClassName": Option<String>,
+        "enableServiceLinks": Option<bool>,
+        "preemptionPolicy": Option<PreemptionPolicy>,
+        "overhead": BTreeMap<ResourceName, Quantity>,
+        "topologySpreadConstraints": Vec<TopologySpreadConstraint>,
+        "setHostnameAsFQDN": Option<bool>,
+        "os": Option<PodOS>,
+        "hostUsers": Option<bool>,
+        "schedulingGates": Vec<PodSchedulingGate>,
+        "resourceClaims": Vec<PodResourceClaim>,
+        "resources": Option<ResourceRequirements>,
+        "hostnameOverride": Option<String>,
+    }
+
+    Container {
+        required "name": String,
+        "image": String,
+        "command": Vec<String>,
+        "args": Vec<String>,
+        "workingDir": String,
+        "ports": Vec<ContainerPort>,
+        "envFrom": Vec<EnvFromSource>,
+        "env": Vec<EnvVar>,
+        "resources": Option<ResourceRequirements>,
+        "resizePolicy": Vec<ContainerResizePolicy>,
+        "restartPolicy": Option<ContainerRestartPolicy>,
+        "restartPolicyRules": Vec<ContainerRestartRule>,
+        "volumeMounts": Vec<VolumeMount>,
+        "volumeDevices": Vec<VolumeDevice>,
+        "livenessProbe": Option<Probe>,
+        "readinessProbe": Option<Probe>,
+        "startupProbe": Option<Probe>,
+        "lifecycle": Option<Lifecycle>,
+        "terminationMessagePath": String,
+        "terminationMessagePolicy": TerminationMessagePolicy,
+        "imagePullPolicy": PullPolicy,
+        "securityContext": Option<SecurityContext>,
+        "stdin": bool,
+        "stdinOnce": bool,
+        "tty": bool,
+    }
+
+    ContainerPort {
+        "name": String,
+        "hostPort": Option<i32>,
+        required "containerPort": i32,
+        "protocol": Protocol,
+        "hostIP": String,
+    }
+
+    EnvFromSource {
+        "prefix": String,
+        "configMapRef": Option<ConfigMapEnvSource>,
+        "secretRef": Option<SecretEnvSource>,
+    }
+
+    ConfigMapEnvSource {
+        "name": String,
+        "optional": Option<bool>,
+    }
+
+    SecretEnvSource {
+        "name": String,
+        "optional": Option<bool>,
+    }
+
+    EnvVar {
+        required "name": String,
+        "value": String,
+        "valueFrom": Option<EnvVarSource>,
+    }
+
+    EnvVarSource {
+        "fieldRef": Option<ObjectFieldSelector>,
+        "resourceFieldRef": Option<ResourceFieldSelector>,
+        "configMapKeyRef": Option<ConfigMapKeySelector>,
+        "secretKeyRef": Option<SecretKeySelector>,
+        "fileKeyRef": Option<FileKeySelector>,
+    }
+
+    ObjectFieldSelector {
+        "apiVersion": String,
+        required "fieldPath": String,
+    }
+
+    ResourceFieldSelector {
+        "containerName": String,
+        required "resource": String,
+        "divisor": Option<Quantity>,
+    }
+
+    ConfigMapKeySelector {
+        "name": String,
+        required "key": String,
+        "optional": Option<bool>,
+    }
+
+    SecretKeySelector {
+        "name": String,
+        required "key": String,
+        "optional": Option<bool>,
+    }
+
+    FileKeySelector {
+        required "volumeName": String,
+        required "path": String,
+        required "key": String,
+        "optional": Option<bool>,
+    }
+
+    ContainerResizePolicy {
+        required "resourceName": ResourceName,
+        required "restartPolicy": ResourceResizeRestartPolicy,
+    }
+
+    ContainerRestartRule {
+        "action": ContainerRestartRuleAction,
+        "exitCodes": Option<ContainerRestartRuleOnExitCodes>,
+    }
+
+    ContainerRestartRuleOnExitCodes {
+        "operator": ContainerRestartRuleOnExitCodesOperator,
+        "values": Vec<i32>,
+    }
+
+    VolumeMount {
+        required "name": String,
+        required "mountPath": String,
+        "readOnly": bool,
+        "subPath": String,
+        "mountPropagation": Option<MountPropagationMode>,
+        "subPathExpr": String,
+        "recursiveReadOnly": Option<RecursiveReadOnlyMode>,
+    }
+
+    VolumeDevice {
+        required "name": String,
+        required "devicePath": String,
+    }
+
+    ProbeHandler {
+        "exec": Option<ExecAction>,
+        "httpGet": Option<HTTPGetAction>,
+        "tcpSocket": Option<TCPSocketAction>,
+        "grpc": Option<GRPCAction>,
+    }
+
+    ExecAction {
+        "command": Vec<String>,
+    }
+
+    HTTPGetAction {
+        "path": String,
+        required "port": IntOrString,
+        "host": String,
+        "scheme": URIScheme,
+        "httpHeaders": Vec<HTTPHeader>,
+    }
+
+    HTTPHeader {
+        required "name": String,
+        required "value": String,
+    }
+
+    TCPSocketAction {
+        required "port": IntOrString,
+        "host": String,
+    }
+
+    GRPCAction {
+        required "port": i32,
+        "service": Option<String>,
+    }
+
+    Lifecycle {
+        "postStart": Option<LifecycleHandler>,
+        "preStop": Option<LifecycleHandler>,
+        "stopSignal": Option<Signal>,
+    }
+
+    LifecycleHandler {
+        "exec": Option<ExecAction>,
+        "httpGet": Option<HTTPGetAction>,
+        "tcpSocket": Option<TCPSocketAction>,
+        "sleep": Option<SleepAction>,
+    }
+
+    SleepAction {
+        required "seconds": i64,
+    }
+
+    SecurityContext {
+        "capabilities": Option<Capabilities>,
+        "privileged": Option<bool>,
+        "seLinuxOptions": Option<SELinuxOptions>,
+        "windowsOptions": Option<WindowsSecurityContextOptions>,
+        "runAsUser": Option<i64>,
+        "runAsGroup": Option<i64>,
+        "runAsNonRoot": Option<bool>,
+        "readOnlyRootFilesystem": Option<bool>,
+        "allowPrivilegeEscalation": Option<bool>,
+        "procMount": Option<ProcMountType>,
+        "seccompProfile": Option<SeccompProfile>,
+        "appArmorProfile": Option<AppArmorProfile>,
+    }
+
+    Capabilities {
+        "add": Vec<Capability>,
+        "drop": Vec<Capability>,
+    }
+
+    SELinuxOptions {
+        "user": String,
+        "role": String,
+        "type": String,
+        "level": String,
+    }
+
+    WindowsSecurityContextOptions {
+        "gmsaCredentialSpecName": Option<String>,
+        "gmsaCredentialSpec": Option<String>,
+        "runAsUserName": Option<String>,
+        "hostProcess": Option<bool>,
+    }
+
+    SeccompProfile {
+        required "type": SeccompProfileType,
+        "localhostProfile": Option<String>,
+    }
+
+    AppArmorProfile {
+        required "type": AppArmorProfileType,
+        "localhostProfile": Option<String>,
+    }
+
+    EphemeralContainer {
+        required "name": String,
+        "image": String,
+        "command": Vec<String>,
+        "args": Vec<String>,
+        "workingDir": String,
+        "ports": Vec<ContainerPort>,
+        "envFrom": Vec<EnvFromSource>,
+        "env": Vec<EnvVar>,
+        "resources": Option<ResourceRequirements>,
+        "resizePolicy": Vec<ContainerResizePolicy>,
+        "restartPolicy": Option<ContainerRestartPolicy>,
+        "restartPolicyRules": Vec<ContainerRestartRule>,
+        "volumeMounts": Vec<VolumeMount>,
+        "volumeDevices": Vec<VolumeDevice>,
+        "livenessProbe": Option<Probe>,
+        "readinessProbe": Option<Probe>,
+        "startupProbe": Option<Probe>,
+        "lifecycle": Option<Lifecycle>,
+        "terminationMessagePath": String,
+        "terminationMessagePolicy": TerminationMessagePolicy,
+        "imagePullPolicy": PullPolicy,
+        "securityContext": Option<SecurityContext>,
+        "stdin": bool,
+        "stdinOnce": bool,
+        "tty": bool,
+        "targetContainerName": String,
+    }
+
+    VolumeSource {
+        "hostPath": Option<HostPathVolumeSource>,
+        "emptyDir": Option<EmptyDirVolumeSource>,
+        "secret": Option<SecretVolumeSource>,
+        "configMap": Option<ConfigMapVolumeSource>,
+        "persistentVolumeClaim": Option<PersistentVolumeClaimVolumeSource>,
+        "nfs": Option<NFSVolumeSource>,
+        "projected": Option<ProjectedVolumeSource>,
+        "downwardAPI": Option<DownwardAPIVolumeSource>,
+        "csi": Option<CSIVolumeSource>,
+        "gcePersistentDisk": Option<GCEPersistentDiskVolumeSource>,
+        "awsElasticBlockStore": Option<AWSElasticBlockStoreVolumeSource>,
+        "gitRepo": Option<GitRepoVolumeSource>,
+        "iscsi": Option<ISCSIVolumeSource>,
+        "glusterfs": Option<GlusterfsVolumeSource>,
+        "rbd": Option<RBDVolumeSource>,
+        "flexVolume": Option<FlexVolumeSource>,
+        "cinder": Option<CinderVolumeSource>,
+        "cephfs": Option<CephFSVolumeSource>,
+        "flocker": Option<FlockerVolumeSource>,
+        "fc": Option<FCVolumeSource>,
+        "azureFile": Option<AzureFileVolumeSource>,
+        "vsphereVolume": Option<VsphereVirtualDiskVolumeSource>,
+        "quobyte": Option<QuobyteVolumeSource>,
+        "azureDisk": Option<AzureDiskVolumeSource>,
+        "photonPersistentDisk": Option<PhotonPersistentDiskVolumeSource>,
+        "portworxVolume": Option<PortworxVolumeSource>,
+        "scaleIo": Option<ScaleIOVolumeSource>,
+        "storageos": Option<StorageOSVolumeSource>,
+        "ephemeral": Option<EphemeralVolumeSource>,
+        "image": Option<ImageVolumeSource>,
+    }
+
+    HostPathVolumeSource {
+        required "path": String,
+        "type": Option<HostPathType>,
+    }
+
+    EmptyDirVolumeSource {
+        "medium": StorageMedium,
+        "sizeLimit": Option<Quantity>,
+    }
+
+    SecretVolumeSource {
+        "secretName": String,
+        "items": Vec<KeyToPath>,
+        "defaultMode": Option<i32>,
+        "optional": Option<bool>,
+    }
+
+    KeyToPath {
+        required "key": String,
+        required "path": String,
+        "mode": Option<i32>,
+    }
+
+    ConfigMapVolumeSource {
+        "name": String,
+        "items": Vec<KeyToPath>,
+        "defaultMode": Option<i32>,
+        "optional": Option<bool>,
+    }
+
+    PersistentVolumeClaimVolumeSource {
+        required "claimName": String,
+        "readOnly": bool,
+    }
+
+    NFSVolumeSource {
+        required "server": String,
+        required "path": String,
+        "readOnly": bool,
+    }
+
+    ProjectedVolumeSource {
+        "sources": Vec<VolumeProjection>,
+        "defaultMode": Option<i32>,
+    }
+
+    VolumeProjection {
+        "secret": Option<SecretProjection>,
+        "configMap": Option<ConfigMapProjection>,
+        "downwardAPI": Option<DownwardAPIProjection>,
+        "serviceAccountToken": Option<ServiceAccountTokenProjection>,
+        "clusterTrustBundle": Option<ClusterTrustBundleProjection>,
+        "podCertificate": Option<PodCertificateProjection>,
+    }
+
+    SecretProjection {
+        "name": String,
+        "items": Vec<KeyToPath>,
+        "optional": Option<bool>,
+    }
+
+    ConfigMapProjection {
+        "name": String,
+        "items": Vec<KeyToPath>,
+        "optional": Option<bool>,
+    }
+
+    DownwardAPIProjection {
+        "items": Vec<DownwardAPIVolumeFile>,
+    }
+
+    DownwardAPIVolumeFile {
+        required "path": String,
+        "fieldRef": Option<ObjectFieldSelector>,
+        "resourceFieldRef": Option<ResourceFieldSelector>,
+        "mode": Option<i32>,
+    }
+
+    ServiceAccountTokenProjection {
+        required "path": String,
+        "audience": String,
+        "expirationSeconds": Option<i64>,
+    }
+
+    ClusterTrustBundleProjection {
+        "name": Option<String>,
+        "signerName": Option<String>,
+        "labelSelector": Option<LabelSelector>,
+        "optional": Option<bool>,
+        required "path": String,
+    }
+
+    PodCertificateProjection {
+        "signerName": String,
+        "keyType": String,
+        "maxExpirationSeconds": Option<i32>,
+        "credentialBundlePath": String,
+        "keyPath": String,
+        "certificateChainPath": String,
+    }
+
+    DownwardAPIVolumeSource {
+        "items": Vec<DownwardAPIVolumeFile>,
+        "defaultMode": Option<i32>,
+    }
+
+    CSIVolumeSource {
+        required "driver": String,
+        "readOnly": bool,
+        "fsType": Option<String>,
+        "volumeAttributes": BTreeMap<String, String>,
+        "nodePublishSecretRef": Option<LocalObjectReference>,
+    }
+
+    LocalObjectReference {
+        "name": String,
+    }
+
+    GCEPersistentDiskVolumeSource {
+        required "pdName": String,
+        "fsType": String,
+        "partition": Option<i32>,
+        "readOnly": bool,
+    }
+
+    AWSElasticBlockStoreVolumeSource {
+        required "volumeID": String,
+        "fsType": String,
+        "partition": Option<i32>,
+        "readOnly": bool,
+    }
+
+    GitRepoVolumeSource {
+        required "repository": String,
+        "revision": String,
+        "directory": String,
+    }
+
+    ISCSIVolumeSource {
+        required "targetPortal": String,
+        required "iqn": String,
+        required "lun": i32,
+        "iscsiInterface": String,
+        "fsType": String,
+        "readOnly": bool,
+        "portals": Vec<String>,
+        "chapAuthDiscovery": bool,
+        "chapAuthSession": bool,
+        "secretRef": Option<LocalObjectReference>,
+        "initiatorName": Option<String>,
+    }
+
+    GlusterfsVolumeSource {
+        required "endpoints": String,
+        required "path": String,
+        "readOnly": bool,
+    }
+
+    RBDVolumeSource {
+        required "monitors": Vec<String>,
+        required "image": String,
+        "fsType": String,
+        "pool": String,
+        "user": String,
+        "keyring": String,
+        "secretRef": Option<LocalObjectReference>,
+        "readOnly": bool,
+    }
+
+    FlexVolumeSource {
+        required "driver": String,
+        "fsType": String,
+        "secretRef": Option<LocalObjectReference>,
+        "readOnly": bool,
+        "options": BTreeMap<String, String>,
+    }
+
+    CinderVolumeSource {
+        required "volumeID": String,
+        "fsType": String,
+        "readOnly": bool,
+        "secretRef": Option<LocalObjectReference>,
+    }
+
+    CephFSVolumeSource {
+        required "monitors": Vec<String>,
+        "path": String,
+        "user": String,
+        "secretFile": String,
+        "secretRef": Option<LocalObjectReference>,
+        "readOnly": bool,
+    }
+
+    FlockerVolumeSource {
+        "datasetName": String,
+        "datasetUUID": String,
+    }
+
+    FCVolumeSource {
+        "targetWWNs": Vec<String>,
+        "lun": Option<i32>,
+        "fsType": String,
+        "readOnly": bool,
+        "wwids": Vec<String>,
+    }
+
+    AzureFileVolumeSource {
+        required "secretName": String,
+        required "shareName": String,
+        "readOnly": bool,
+    }
+
+    VsphereVirtualDiskVolumeSource {
+        required "volumePath": String,
+        "fsType": String,
+        "storagePolicyName": String,
+        "storagePolicyID": String,
+    }
+
+    QuobyteVolumeSource {
+        required "registry": String,
+        required "volume": String,
+        "readOnly": bool,
+        "user": String,
+        "group": String,
+        "tenant": String,
+    }
+
+    AzureDiskVolumeSource {
+        required "diskName": String,
+        required "diskURI": String,
+        "cachingMode": Option<AzureDataDiskCachingMode>,
+        "fsType": Option<String>,
+        "readOnly": bool,
+        "kind": Option<AzureDataDiskKind>,
+    }
+
+    PhotonPersistentDiskVolumeSource {
+        required "pdID": String,
+        "fsType": String,
+    }
+
+    PortworxVolumeSource {
+        required "volumeID": String,
+        "fsType": String,
+        "readOnly": bool,
+    }
+
+    ScaleIOVolumeSource {
+        required "gateway": String,
+        required "system": String,
+        required "secretRef": LocalObjectReference,
+        "sslEnabled": bool,
+        "protectionDomain": String,
+        "storagePool": String,
+        "storageMode": String,
+        "volumeName": String,
+        "fsType": String,
+        "readOnly": bool,
+    }
+
+    StorageOSVolumeSource {
+        "volumeName": String,
+        "volumeNamespace": String,
+        "fsType": String,
+        "readOnly": bool,
+        "secretRef": Option<LocalObjectReference>,
+    }
+
+    EphemeralVolumeSource {
+        "volumeClaimTemplate": Option<PersistentVolumeClaimTemplate>,
+    }
+
+    PersistentVolumeClaimSpec {
+        "accessModes": Vec<PersistentVolumeAccessMode>,
+        "selector": Option<LabelSelector>,
+        "resources": Option<VolumeResourceRequirements>,
+        "volumeName": String,
+        "storageClassName": Option<String>,
+        "volumeMode": Option<PersistentVolumeMode>,
+        "dataSource": Option<TypedLocalObjectReference>,
+        "dataSourceRef": Option<TypedObjectReference>,
+        "volumeAttributesClassName": Option<String>,
+    }
+
+    VolumeResourceRequirements {
+        "limits": BTreeMap<ResourceName, Quantity>,
+        "requests": BTreeMap<ResourceName, Quantity>,
+    }
+
+    TypedLocalObjectReference {
+        "apiGroup": Option<String>,
+        required "kind": String,
+        required "name": String,
+    }
+
+    TypedObjectReference {
+        "apiGroup": Option<String>,
+        required "kind": String,
+        required "name": String,
+        "namespace": Option<String>,
+    }
+
+    ImageVolumeSource {
+        "reference": String,
+        "pullPolicy": PullPolicy,
+    }
+
+    PodSecurityContext {
+        "seLinuxOptions": Option<SELinuxOptions>,
+        "windowsOptions": Option<WindowsSecurityContextOptions>,
+        "runAsUser": Option<i64>,
+        "runAsGroup": Option<i64>,
+        "runAsNonRoot": Option<bool>,
+        "supplementalGroups": Vec<i64>,
+        "fsGroup": Option<i64>,
+        "sysctls": Vec<Sysctl>,
+        "fsGroupChangePolicy": Option<PodFSGroupChangePolicy>,
+        "seccompProfile": Option<SeccompProfile>,
+        "appArmorProfile": Option<AppArmorProfile>,
+        "seLinuxChangePolicy": Option<PodSELinuxChangePolicy>,
+        "supplementalGroupsPolicy": Option<SupplementalGroupsPolicy>,
+    }
+
+    Sysctl {
+        required "name": String,
+        required "value": String,
+    }
+
+    Affinity {
+        "nodeAffinity": Option<NodeAffinity>,
+        "podAffinity": Option<PodAffinity>,
+        "podAntiAffinity": Option<PodAntiAffinity>,
+    }
+
+    NodeAffinity {
+        "requiredDuringSchedulingIgnoredDuringExecution": Option<NodeSelector>,
+        "preferredDuringSchedulingIgnoredDuringExecution": Vec<PreferredSchedulingTerm>,
+    }
+
+    NodeSelector {
+        required "nodeSelectorTerms": Vec<NodeSelectorTerm>,
+    }
+
+    NodeSelectorTerm {
+        "matchExpressions": Vec<NodeSelectorRequirement>,
+        "matchFields": Vec<NodeSelectorRequirement>,
+    }
+
+    NodeSelectorRequirement {
+        required "key": String,
+        required "operator": NodeSelectorOperator,
+        "values": Vec<String>,
+    }
+
+    PreferredSchedulingTerm {
+        required "weight": i32,
+        required "preference": NodeSelectorTerm,
+    }
+
+    PodAffinity {
+        "requiredDuringSchedulingIgnoredDuringExecution": Vec<PodAffinityTerm>,
+        "preferredDuringSchedulingIgnoredDuringExecution": Vec<WeightedPodAffinityTerm>,
+    }
+
+    PodAffinityTerm {
+        "labelSelector": Option<LabelSelector>,
+        "namespaces": Vec<String>,
+        required "topologyKey": String,
+        "namespaceSelector": Option<LabelSelector>,
+        "matchLabelKeys": Vec<String>,
+        "mismatchLabelKeys": Vec<String>,
+    }
+
+    WeightedPodAffinityTerm {
+        required "weight": i32,
+        required "podAffinityTerm": PodAffinityTerm,
+    }
+
+    PodAntiAffinity {
+        "requiredDuringSchedulingIgnoredDuringExecution": Vec<PodAffinityTerm>,
+        "preferredDuringSchedulingIgnoredDuringExecution": Vec<WeightedPodAffinityTerm>,
+    }
+
+    Toleration {
+        "key": String,
+        "operator": TolerationOperator,
+        "value": String,
+        "effect": TaintEffect,
+        "tolerationSeconds": Option<i64>,
+    }
+
+    HostAlias {
+        "ip": String,
+        "hostnames": Vec<String>,
+    }
+
+    PodDNSConfig {
+        "nameservers": Vec<String>,
+        "searches": Vec<String>,
+        "options": Vec<PodDNSConfigOption>,
+    }
+
+    PodDNSConfigOption {
+        "name": String,
+        "value": Option<String>,
+    }
+
+    PodReadinessGate {
+        required "conditionType": PodConditionType,
+    }
+
+    TopologySpreadConstraint {
+        required "maxSkew": i32,
+        required "topologyKey": String,
+        required "whenUnsatisfiable": UnsatisfiableConstraintAction,
+        "labelSelector": Option<LabelSelector>,
+        "minDomains": Option<i32>,
+        "nodeAffinityPolicy": Option<NodeInclusionPolicy>,
+        "nodeTaintsPolicy": Option<NodeInclusionPolicy>,
+        "matchLabelKeys": Vec<String>,
+    }
+
+    PodOS {
+        required "name": OSName,
+    }
+
+    PodSchedulingGate {
+        required "name": String,
+    }
+
+    PodResourceClaim {
+        required "name": String,
+        "resourceClaimName": Option<String>,
+        "resourceClaimTemplateName": Option<String>,
+    }
+}
@@ -0,0 +1,127 @@
+//! Structural schema handling for CustomResourceDefinitions
+//!
+//! This module mirrors `k8s.io/apiextensions-apiserver/pkg/apiserver/schema`:
+//! - `structural`: checks that a `JSONSchemaProps` is a structural schema
+//! - `pruning`: removes fields that are not specified by a structural schema
+//! - `defaulting`: applies schema defaults to custom resources
+//! - `validation`: validates values against a schema
+//! - `cel`: compiles and evaluates `x-kubernetes-validations` rules
+//! - `generate`: produces schemas and CustomResourceDefinitions from Rust types
+
+pub mod cel;
+pub mod defaulting;
+pub mod generate;
+pub mod pruning;
+pub mod structural;
+pub mod validation;
+
+pub use cel::{validate_rules, validate_validation_rules};
+pub use defaulting::{apply_defaults, default_and_prune, validate_defaults};
+pub use pruning::prune;
+pub use structural::{validate_custom_resource_definition_structural, validate_structural};
+pub use validation::validate_value;
+
+use thiserror::Error;
+
+use crate::apis::apiextensions::v1::{JSONSchemaProps, JSONSchemaPropsOrArray, JSONSchemaPropsOrBool};
+
+/// SchemaError describes a single problem found while checking a schema.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("{field}: {error_type}: {message}")]
+pub struct SchemaError {
+    pub field: String,
+    pub message: String,
+    pub error_type: SchemaErrorType,
+}
+
+/// SchemaErrorType categorizes a SchemaError like `field.ErrorType` does in Go.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SchemaErrorType {
+    Required,
+    Forbidden,
+    Invalid,
+    NotSupported,
+}
+
+impl std::fmt::Display for SchemaErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            SchemaErrorType::Required => "Required value",
+            SchemaErrorType::Forbidden => "Forbidden",
+            SchemaErrorType::Invalid => "Invalid value",
+            SchemaErrorType::NotSupported => "Unsupported value",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl SchemaError {
+    pub fn required(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+            error_type: SchemaErrorType::Required,
+        }
+    }
+
+    pub fn forbidden(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+            error_type: SchemaErrorType::Forbidden,
+        }
+    }
+
+    pub fn invalid(field: impl Into<String>, value: impl std::fmt::Display, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: format!("{:?}: {}", value.to_string(), message.into()),
+            error_type: SchemaErrorType::Invalid,
+        }
+    }
+
+    pub fn not_supported(field: impl Into<String>, value: impl Into<String>, supported: &[&str]) -> Self {
+        Self {
+            field: field.into(),
+            message: format!("unsupported value: {}, must be one of {:?}", value.into(), supported),
+            error_type: SchemaErrorType::NotSupported,
+        }
+    }
+}
+
+/// Builds the field path of a named child, e.g. `openAPIV3Schema.type`.
+pub(crate) fn child_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+/// Builds the field path of a map key, e.g. `properties[spec]`.
+pub(crate) fn key_path(path: &str, key: &str) -> String {
+    format!("{}[{}]", path, key)
+}
+
+/// Builds the field path of a list index, e.g. `allOf[0]`.
+pub(crate) fn index_path(path: &str, idx: usize) -> String {
+    format!("{}[{}]", path, idx)
+}
+
+/// Returns the schema of list items, unless `items` is absent or a list of
+/// schemas.
+pub(crate) fn items_schema(schema: &JSONSchemaProps) -> Option<&JSONSchemaProps> {
+    match &schema.items {
+        Some(JSONSchemaPropsOrArray::Schema(items)) => Some(items),
+        _ => None,
+    }
+}
+
+/// Returns the schema of `additionalProperties`, unless it is absent or a
+/// bool.
+pub(crate) fn additional_properties_schema(schema: &JSONSchemaProps) -> Option<&JSONSchemaProps> {
+    match &schema.additional_properties {
+        Some(JSONSchemaPropsOrBool::Schema(additional)) => Some(additional),
+        _ => None,
+    }
+}
@@ -0,0 +1,276 @@
+//! Pruning of unknown fields
+//!
+//! Removes every object field that is not specified by a structural schema,
+//! except below `x-kubernetes-preserve-unknown-fields: true`. Embedded
+//! resources (`x-kubernetes-embedded-resource: true`) and the resource root
+//! keep their `apiVersion`, `kind` and `metadata` fields. Fields allowed by
+//! `additionalProperties: true` are kept, but everything below them is
+//! pruned. This follows
+//! `pruning.Prune` in Go.
+
+use serde_json::Value;
+
+use super::{additional_properties_schema, child_path, index_path, items_schema};
+use crate::apis::apiextensions::v1::{JSONSchemaProps, JSONSchemaPropsOrBool};
+
+const META_FIELDS: &[&str] = &["apiVersion", "kind", "metadata"];
+
+/// Prunes all fields of `obj` that are not specified in `schema`.
+///
+/// When `is_resource_root` is true, `obj` is treated as a custom resource and
+/// keeps its `apiVersion`, `kind` and `metadata`. Returns the sorted paths of
+/// the pruned fields.
+pub fn prune(obj: &mut Value, schema: &JSONSchemaProps, is_resource_root: bool) -> Vec<String> {
+    let mut pruned = Vec::new();
+    if is_resource_root && schema.x_embedded_resource != Some(true) {
+        let mut root = schema.clone();
+        root.x_embedded_resource = Some(true);
+        prune_value(obj, Some(&root), "", &mut pruned);
+    } else {
+        prune_value(obj, Some(schema), "", &mut pruned);
+    }
+    pruned.sort();
+    pruned
+}
+
+/// Reports whether `additionalProperties: true` accepts fields that are not
+/// listed in `properties`, without specifying anything below them.
+fn allows_additional_properties(schema: &JSONSchemaProps) -> bool {
+    matches!(schema.additional_properties, Some(JSONSchemaPropsOrBool::Bool(true)))
+}
+
+/// Embedded resources keep their type and object metadata as long as the
+/// values have the expected JSON types.
+fn is_embedded_meta_field(schema: &JSONSchemaProps, key: &str, value: &Value) -> bool {
+    if schema.x_embedded_resource != Some(true) {
+        return false;
+    }
+    match key {
+        "apiVersion" | "kind" => value.is_string(),
+        "metadata" => value.is_object(),
+        _ => false,
+    }
+}
+
+fn prune_value(value: &mut Value, schema: Option<&JSONSchemaProps>, path: &str, pruned: &mut Vec<String>) {
+    if let Some(schema) = schema {
+        if schema.x_preserve_unknown_fields == Some(true) {
+            skip_prune(value, schema, path, pruned);
+            return;
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let Some(schema) = schema else {
+                pruned.extend(map.keys().map(|key| child_path(path, key)));
+                map.clear();
+                return;
+            };
+            let mut unknown = Vec::new();
+            for (key, field) in map.iter_mut() {
+                if is_embedded_meta_field(schema, key, field) {
+                    continue;
+                }
+                let field_path = child_path(path, key);
+                if let Some(property) = schema.properties.get(key) {
+                    prune_value(field, Some(property), &field_path, pruned);
+                } else if let Some(additional) = additional_properties_schema(schema) {
+                    prune_value(field, Some(additional), &field_path, pruned);
+                } else if allows_additional_properties(schema) {
+                    prune_value(field, None, &field_path, pruned);
+                } else {
+                    // apiVersion, kind and metadata of the resource root are
+                    // dropped silently when they have the wrong JSON type.
+                    if !path.is_empty() || !META_FIELDS.contains(&key.as_str()) {
+                        pruned.push(field_path);
+                    }
+                    unknown.push(key.clone());
+                }
+            }
+            for key in unknown {
+                map.remove(&key);
+            }
+        }
+        Value::Array(items) => {
+            let items_schema = schema.and_then(items_schema);
+            for (idx, item) in items.iter_mut().enumerate() {
+                prune_value(item, items_schema, &index_path(path, idx), pruned);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Keeps unknown fields, but still prunes below fields that are specified.
+fn skip_prune(value: &mut Value, schema: &JSONSchemaProps, path: &str, pruned: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if is_embedded_meta_field(schema, key, field) {
+                    continue;
+                }
+                let field_path = child_path(path, key);
+                if let Some(property) = schema.properties.get(key) {
+                    prune_value(field, Some(property), &field_path, pruned);
+                } else if let Some(additional) = additional_properties_schema(schema) {
+                    prune_value(field, Some(additional), &field_path, pruned);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(items_schema) = items_schema(schema) {
+                for (idx, item) in items.iter_mut().enumerate() {
+                    skip_prune(item, items_schema, &index_path(path, idx), pruned);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(value: Value) -> JSONSchemaProps {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_prune_unknown_fields() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "spec": {
+                    "type": "object",
+                    "properties": {
+                        "replicas": {"type": "integer"},
+                        "ports": {
+                            "type": "array",
+                            "items": {"type": "object", "properties": {"port": {"type": "integer"}}}
+                        }
+                    }
+                }
+            }
+        }));
+        let mut obj = json!({
+            "apiVersion": "example.com/v1",
+            "kind": "Foo",
+            "metadata": {"name": "foo"},
+            "spec": {"replicas": 1, "unknown": true, "ports": [{"port": 80, "name": "http"}]},
+            "status": {"ready": true}
+        });
+
+        let pruned = prune(&mut obj, &schema, true);
+        assert_eq!(pruned, vec!["spec.ports[0].name", "spec.unknown", "status"]);
+        assert_eq!(
+            obj,
+            json!({
+                "apiVersion": "example.com/v1",
+                "kind": "Foo",
+                "metadata": {"name": "foo"},
+                "spec": {"replicas": 1, "ports": [{"port": 80}]}
+            })
+        );
+    }
+
+    #[test]
+    fn test_prune_preserve_unknown_fields() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "spec": {
+                    "type": "object",
+                    "x-kubernetes-preserve-unknown-fields": true,
+                    "properties": {
+                        "known": {"type": "object", "properties": {"a": {"type": "string"}}}
+                    }
+                }
+            }
+        }));
+        let mut obj = json!({"spec": {"free": {"x": 1}, "known": {"a": "1", "b": "2"}}});
+
+        let pruned = prune(&mut obj, &schema, false);
+        assert_eq!(pruned, vec!["spec.known.b"]);
+        assert_eq!(obj, json!({"spec": {"free": {"x": 1}, "known": {"a": "1"}}}));
+    }
+
+    #[test]
+    fn test_prune_embedded_resource() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "template": {
+                    "type": "object",
+                    "x-kubernetes-embedded-resource": true,
+                    "properties": {"spec": {"type": "object"}}
+                }
+            }
+        }));
+        let mut obj = json!({
+            "template": {
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": {"name": "x"},
+                "spec": {"containers": []},
+                "extra": 1
+            }
+        });
+
+        let pruned = prune(&mut obj, &schema, false);
+        assert_eq!(pruned, vec!["template.extra", "template.spec.containers"]);
+        assert_eq!(
+            obj,
+            json!({"template": {"apiVersion": "v1", "kind": "Pod", "metadata": {"name": "x"}, "spec": {}}})
+        );
+    }
+
+    #[test]
+    fn test_prune_additional_properties() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "labels": {"type": "object", "additionalProperties": {"type": "string"}},
+                "nested": {
+                    "type": "object",
+                    "additionalProperties": {"type": "object", "properties": {"a": {"type": "string"}}}
+                }
+            }
+        }));
+        let mut obj = json!({
+            "labels": {"a": "b"},
+            "nested": {"x": {"a": "1", "b": "2"}},
+            "kind": 5
+        });
+
+        let pruned = prune(&mut obj, &schema, true);
+        assert_eq!(pruned, vec!["nested.x.b"]);
+        assert_eq!(obj, json!({"labels": {"a": "b"}, "nested": {"x": {"a": "1"}}}));
+    }
+
+    #[test]
+    fn test_prune_additional_properties_true() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "config": {
+                    "type": "object",
+                    "additionalProperties": true,
+                    "properties": {
+                        "known": {"type": "object", "properties": {"a": {"type": "string"}}}
+                    }
+                }
+            }
+        }));
+        let mut obj = json!({
+            "config": {"free": {"deep": [1, 2]}, "other": "x", "known": {"a": "1", "b": "2"}},
+            "extra": true
+        });
+
+        let pruned = prune(&mut obj, &schema, false);
+        assert_eq!(pruned, vec!["config.free.deep", "config.known.b", "extra"]);
+        assert_eq!(obj, json!({"config": {"free": {}, "other": "x", "known": {"a": "1"}}}));
+    }
+}
@@ -0,0 +1,946 @@
+//! Structural schema validation
+//!
+//! A structural schema specifies a non-empty type for every field, keeps
+//! generic information (type, description, defaults, x-kubernetes-*
+//! extensions) out of `allOf`/`anyOf`/`oneOf`/`not`, and only uses those
+//! logical junctors for value validation of fields that are also specified
+//! outside of them. This follows `ValidateStructural` in Go.
+
+use super::{additional_properties_schema, child_path, index_path, items_schema, key_path, SchemaError};
+use crate::apis::apiextensions::v1::{
+    CustomResourceDefinition, JSONSchemaProps, JSONSchemaPropsOrArray, JSONSchemaPropsOrBool,
+};
+
+const LIST_TYPE_ATOMIC: &str = "atomic";
+const LIST_TYPE_SET: &str = "set";
+const LIST_TYPE_MAP: &str = "map";
+const MAP_TYPE_ATOMIC: &str = "atomic";
+const MAP_TYPE_GRANULAR: &str = "granular";
+
+/// Level describes where in the schema tree a node lives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Root,
+    Item,
+    Field,
+}
+
+/// Validates that `schema` is a structural schema and does not use features
+/// that are unsupported for CustomResourceDefinitions.
+///
+/// `path` is the field path of the schema itself, e.g. `openAPIV3Schema`.
+/// Errors are returned sorted by field path.
+pub fn validate_structural(path: &str, schema: &JSONSchemaProps) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    validate_features(schema, path, &mut errors);
+    validate_structural_invariants(schema, Level::Root, path, &mut errors);
+    validate_structural_completeness(schema, path, &mut errors);
+    errors.sort_by(|a, b| a.field.cmp(&b.field).then_with(|| a.message.cmp(&b.message)));
+    errors.dedup();
+    errors
+}
+
+/// Validates the schema of every version of a CustomResourceDefinition.
+pub fn validate_custom_resource_definition_structural(
+    crd: &CustomResourceDefinition,
+) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    for (idx, version) in crd.spec.versions.iter().enumerate() {
+        let version_path = index_path("spec.versions", idx);
+        let schema = version
+            .schema
+            .as_ref()
+            .and_then(|validation| validation.open_apiv3_schema.as_ref());
+        match schema {
+            Some(schema) => errors.extend(validate_structural(
+                &child_path(&child_path(&version_path, "schema"), "openAPIV3Schema"),
+                schema,
+            )),
+            None => errors.push(SchemaError::required(
+                child_path(&version_path, "schema.openAPIV3Schema"),
+                "schemas are required",
+            )),
+        }
+    }
+    errors
+}
+
+/// Returns true if the schema is structural.
+pub fn is_structural(schema: &JSONSchemaProps) -> bool {
+    validate_structural("", schema).is_empty()
+}
+
+fn sorted_properties(schema: &JSONSchemaProps) -> Vec<(&String, &JSONSchemaProps)> {
+    let mut properties: Vec<_> = schema.properties.iter().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+    properties
+}
+
+// =============================================================================
+// Unsupported features and x-kubernetes-* extensions
+// =============================================================================
+
+fn validate_features(schema: &JSONSchemaProps, path: &str, errors: &mut Vec<SchemaError>) {
+    if !schema.id.is_empty() {
+        errors.push(SchemaError::forbidden(child_path(path, "id"), "id is not supported"));
+    }
+    if !schema.schema.is_empty() {
+        errors.push(SchemaError::forbidden(child_path(path, "$schema"), "$schema is not supported"));
+    }
+    if schema.ref_.is_some() {
+        errors.push(SchemaError::forbidden(child_path(path, "$ref"), "$ref is not supported"));
+    }
+    if !schema.definitions.is_empty() {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "definitions"),
+            "definitions are not supported",
+        ));
+    }
+    if !schema.pattern_properties.is_empty() {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "patternProperties"),
+            "patternProperties is not supported",
+        ));
+    }
+    if schema.additional_items.is_some() {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "additionalItems"),
+            "additionalItems is not supported",
+        ));
+    }
+    if schema.unique_items == Some(true) {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "uniqueItems"),
+            "uniqueItems cannot be set to true since the runtime complexity becomes quadratic",
+        ));
+    }
+    if let Some(JSONSchemaPropsOrArray::Schemas(_)) = &schema.items {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "items"),
+            "items must be a schema object and not an array",
+        ));
+    }
+    // Only `additionalProperties: true` may be combined with properties.
+    if !schema.properties.is_empty()
+        && schema.additional_properties.is_some()
+        && !matches!(schema.additional_properties, Some(JSONSchemaPropsOrBool::Bool(true)))
+    {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "additionalProperties"),
+            "additionalProperties and properties are mutual exclusive",
+        ));
+    }
+    if schema.x_preserve_unknown_fields == Some(false) {
+        errors.push(SchemaError::invalid(
+            child_path(path, "x-kubernetes-preserve-unknown-fields"),
+            false,
+            "must be true or undefined",
+        ));
+    }
+
+    validate_list_type(schema, path, errors);
+    validate_map_type(schema, path, errors);
+
+    for (name, property) in sorted_properties(schema) {
+        validate_features(property, &key_path(&child_path(path, "properties"), name), errors);
+    }
+    if let Some(items) = items_schema(schema) {
+        validate_features(items, &child_path(path, "items"), errors);
+    }
+    if let Some(additional) = additional_properties_schema(schema) {
+        validate_features(additional, &child_path(path, "additionalProperties"), errors);
+    }
+    for (junctor, schemas) in [
+        ("allOf", &schema.all_of),
+        ("anyOf", &schema.any_of),
+        ("oneOf", &schema.one_of),
+    ] {
+        for (idx, nested) in schemas.iter().enumerate() {
+            validate_features(nested, &index_path(&child_path(path, junctor), idx), errors);
+        }
+    }
+    if let Some(not) = &schema.not {
+        validate_features(not, &child_path(path, "not"), errors);
+    }
+}
+
+fn validate_list_type(schema: &JSONSchemaProps, path: &str, errors: &mut Vec<SchemaError>) {
+    let list_type = schema.x_list_type.as_deref();
+    if let Some(list_type) = list_type {
+        if ![LIST_TYPE_ATOMIC, LIST_TYPE_SET, LIST_TYPE_MAP].contains(&list_type) {
+            errors.push(SchemaError::not_supported(
+                child_path(path, "x-kubernetes-list-type"),
+                list_type,
+                &[LIST_TYPE_ATOMIC, LIST_TYPE_SET, LIST_TYPE_MAP],
+            ));
+        }
+        if schema.type_ != "array" {
+            errors.push(SchemaError::invalid(
+                child_path(path, "x-kubernetes-list-type"),
+                list_type,
+                "must only be used if type is array",
+            ));
+        }
+    }
+
+    if !schema.x_list_map_keys.is_empty() && list_type != Some(LIST_TYPE_MAP) {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "x-kubernetes-list-map-keys"),
+            "must only be used if x-kubernetes-list-type is map",
+        ));
+    }
+
+    if list_type == Some(LIST_TYPE_MAP) {
+        if schema.x_list_map_keys.is_empty() {
+            errors.push(SchemaError::required(
+                child_path(path, "x-kubernetes-list-map-keys"),
+                "must not be empty if x-kubernetes-list-type is map",
+            ));
+        }
+        match items_schema(schema) {
+            None if schema.items.is_none() => errors.push(SchemaError::required(
+                child_path(path, "items"),
+                "must have a schema if x-kubernetes-list-type is map",
+            )),
+            None => {}
+            Some(items) if items.type_ != "object" => errors.push(SchemaError::invalid(
+                child_path(&child_path(path, "items"), "type"),
+                &items.type_,
+                "must be object if parent array's x-kubernetes-list-type is map",
+            )),
+            Some(items) => {
+                let keys_path = child_path(path, "x-kubernetes-list-map-keys");
+                let mut seen = std::collections::HashSet::new();
+                for key in &schema.x_list_map_keys {
+                    match items.properties.get(key) {
+                        Some(property) if property.type_ == "array" || property.type_ == "object" => {
+                            errors.push(SchemaError::invalid(
+                                child_path(
+                                    &key_path(&child_path(&child_path(path, "items"), "properties"), key),
+                                    "type",
+                                ),
+                                &property.type_,
+                                "must be a scalar type if parent array's x-kubernetes-list-type is map",
+                            ))
+                        }
+                        Some(_) => {}
+                        None => errors.push(SchemaError::invalid(
+                            &keys_path,
+                            key,
+                            "entries must all be names of item properties",
+                        )),
+                    }
+                    if !seen.insert(key) {
+                        errors.push(SchemaError::invalid(
+                            &keys_path,
+                            key,
+                            "must not contain duplicate entries",
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    if list_type == Some(LIST_TYPE_SET) {
+        if let Some(items) = items_schema(schema) {
+            let items_path = child_path(path, "items");
+            match items.type_.as_str() {
+                "array" if items.x_list_type.as_deref().unwrap_or(LIST_TYPE_ATOMIC) != LIST_TYPE_ATOMIC => {
+                    errors.push(SchemaError::invalid(
+                        child_path(&items_path, "x-kubernetes-list-type"),
+                        items.x_list_type.as_deref().unwrap_or_default(),
+                        "must be atomic as item of a list with x-kubernetes-list-type=set",
+                    ))
+                }
+                "object" if items.x_map_type.as_deref() != Some(MAP_TYPE_ATOMIC) => {
+                    errors.push(SchemaError::invalid(
+                        child_path(&items_path, "x-kubernetes-map-type"),
+                        items.x_map_type.as_deref().unwrap_or_default(),
+                        "must be atomic as item of a list with x-kubernetes-list-type=set",
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn validate_map_type(schema: &JSONSchemaProps, path: &str, errors: &mut Vec<SchemaError>) {
+    let Some(map_type) = schema.x_map_type.as_deref() else {
+        return;
+    };
+    if schema.type_.is_empty() {
+        errors.push(SchemaError::required(
+            child_path(path, "type"),
+            "must be object if x-kubernetes-map-type is specified",
+        ));
+    } else if schema.type_ != "object" {
+        errors.push(SchemaError::invalid(
+            child_path(path, "type"),
+            &schema.type_,
+            "must be object if x-kubernetes-map-type is specified",
+        ));
+    }
+    if map_type != MAP_TYPE_ATOMIC && map_type != MAP_TYPE_GRANULAR {
+        errors.push(SchemaError::not_supported(
+            child_path(path, "x-kubernetes-map-type"),
+            map_type,
+            &[MAP_TYPE_ATOMIC, MAP_TYPE_GRANULAR],
+        ));
+    }
+}
+
+// =============================================================================
+// Structural invariants
+// =============================================================================
+
+fn validate_structural_invariants(
+    schema: &JSONSchemaProps,
+    level: Level,
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    if schema.type_ == "array" && schema.items.is_none() {
+        errors.push(SchemaError::required(child_path(path, "items"), "must be specified"));
+    }
+    if let Some(items) = items_schema(schema) {
+        validate_structural_invariants(items, Level::Item, &child_path(path, "items"), errors);
+    }
+    for (name, property) in sorted_properties(schema) {
+        validate_structural_invariants(
+            property,
+            Level::Field,
+            &key_path(&child_path(path, "properties"), name),
+            errors,
+        );
+    }
+
+    if schema.additional_properties.is_some() && level == Level::Root {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "additionalProperties"),
+            "must not be used at the root",
+        ));
+    }
+    if let Some(additional) = additional_properties_schema(schema) {
+        validate_structural_invariants(
+            additional,
+            Level::Field,
+            &child_path(path, "additionalProperties"),
+            errors,
+        );
+    }
+
+    let int_or_string = schema.x_int_or_string == Some(true);
+    let preserve_unknown_fields = schema.x_preserve_unknown_fields == Some(true);
+    let embedded_resource = schema.x_embedded_resource == Some(true);
+
+    if int_or_string && preserve_unknown_fields {
+        errors.push(SchemaError::invalid(
+            child_path(path, "x-kubernetes-preserve-unknown-fields"),
+            true,
+            "must be false if x-kubernetes-int-or-string is true",
+        ));
+    }
+    if int_or_string && embedded_resource {
+        errors.push(SchemaError::invalid(
+            child_path(path, "x-kubernetes-embedded-resource"),
+            true,
+            "must be false if x-kubernetes-int-or-string is true",
+        ));
+    }
+
+    let skip_any_of = int_or_string && is_int_or_string_any_of_pattern(schema);
+    let skip_first_all_of_any_of = int_or_string && is_int_or_string_all_of_pattern(schema);
+    validate_value_validation(schema, skip_any_of, skip_first_all_of_any_of, level, path, errors);
+
+    if embedded_resource && schema.type_ != "object" {
+        if schema.type_.is_empty() {
+            errors.push(SchemaError::required(
+                child_path(path, "type"),
+                "must be object if x-kubernetes-embedded-resource is true",
+            ));
+        } else {
+            errors.push(SchemaError::invalid(
+                child_path(path, "type"),
+                &schema.type_,
+                "must be object if x-kubernetes-embedded-resource is true",
+            ));
+        }
+    } else if schema.type_.is_empty() && !int_or_string && !preserve_unknown_fields {
+        let message = match level {
+            Level::Root => "must not be empty at the root",
+            Level::Item => "must not be empty for specified array items",
+            Level::Field => "must not be empty for specified object fields",
+        };
+        errors.push(SchemaError::required(child_path(path, "type"), message));
+    }
+    if embedded_resource && schema.additional_properties.is_some() {
+        errors.push(SchemaError::forbidden(
+            child_path(path, "additionalProperties"),
+            "must not be used if x-kubernetes-embedded-resource is set",
+        ));
+    }
+
+    if level == Level::Root && !schema.type_.is_empty() && schema.type_ != "object" {
+        errors.push(SchemaError::invalid(
+            child_path(path, "type"),
+            &schema.type_,
+            "must be object at the root",
+        ));
+    }
+
+    let properties_path = child_path(path, "properties");
+    let check_metadata = level == Level::Root || embedded_resource;
+    if check_metadata {
+        for (name, expected) in [("kind", "string"), ("apiVersion", "string"), ("metadata", "object")] {
+            if let Some(property) = schema.properties.get(name) {
+                if property.type_ != expected {
+                    errors.push(SchemaError::invalid(
+                        child_path(&key_path(&properties_path, name), "type"),
+                        &property.type_,
+                        format!("must be {}", expected),
+                    ));
+                }
+            }
+        }
+    }
+    if level == Level::Root {
+        if let Some(metadata) = schema.properties.get("metadata") {
+            if !is_restricted_metadata_schema(metadata) {
+                errors.push(SchemaError::forbidden(
+                    key_path(&properties_path, "metadata"),
+                    "must not specify anything other than name and generateName, but metadata is implicitly specified",
+                ));
+            }
+        }
+    }
+
+    if embedded_resource && !preserve_unknown_fields && schema.properties.is_empty() {
+        errors.push(SchemaError::required(
+            child_path(path, "properties"),
+            "must not be empty if x-kubernetes-embedded-resource is true without x-kubernetes-preserve-unknown-fields",
+        ));
+    }
+}
+
+/// Metadata at the resource root may only restrict `name` and `generateName`
+/// to strings; anything else would diverge from the shared ObjectMeta schema.
+fn is_restricted_metadata_schema(metadata: &JSONSchemaProps) -> bool {
+    let mut metadata = metadata.clone();
+    metadata.type_.clear();
+    metadata.default = None;
+    for name in ["name", "generateName"] {
+        if metadata.properties.get(name).is_some_and(|p| p.type_ == "string") {
+            metadata.properties.remove(name);
+        }
+    }
+    metadata == JSONSchemaProps::default()
+}
+
+fn int_or_string_any_of() -> [JSONSchemaProps; 2] {
+    [
+        JSONSchemaProps {
+            type_: "integer".to_string(),
+            ..Default::default()
+        },
+        JSONSchemaProps {
+            type_: "string".to_string(),
+            ..Default::default()
+        },
+    ]
+}
+
+/// Detects `anyOf: [{type: integer}, {type: string}]`.
+fn is_int_or_string_any_of_pattern(schema: &JSONSchemaProps) -> bool {
+    schema.any_of.as_slice() == int_or_string_any_of().as_slice()
+}
+
+/// Detects `allOf: [{anyOf: [{type: integer}, {type: string}]}, ...]`.
+fn is_int_or_string_all_of_pattern(schema: &JSONSchemaProps) -> bool {
+    schema
+        .all_of
+        .first()
+        .is_some_and(|first| first.any_of.as_slice() == int_or_string_any_of().as_slice())
+}
+
+fn validate_value_validation(
+    schema: &JSONSchemaProps,
+    skip_any_of: bool,
+    skip_first_all_of_any_of: bool,
+    level: Level,
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    if !skip_any_of {
+        for (idx, nested) in schema.any_of.iter().enumerate() {
+            validate_nested_value_validation(
+                nested,
+                false,
+                false,
+                level,
+                &index_path(&child_path(path, "anyOf"), idx),
+                errors,
+            );
+        }
+    }
+    for (idx, nested) in schema.all_of.iter().enumerate() {
+        validate_nested_value_validation(
+            nested,
+            skip_first_all_of_any_of && idx == 0,
+            false,
+            level,
+            &index_path(&child_path(path, "allOf"), idx),
+            errors,
+        );
+    }
+    for (idx, nested) in schema.one_of.iter().enumerate() {
+        validate_nested_value_validation(
+            nested,
+            false,
+            false,
+            level,
+            &index_path(&child_path(path, "oneOf"), idx),
+            errors,
+        );
+    }
+    if let Some(not) = &schema.not {
+        validate_nested_value_validation(not, false, false, level, &child_path(path, "not"), errors);
+    }
+}
+
+fn validate_nested_value_validation(
+    schema: &JSONSchemaProps,
+    skip_any_of: bool,
+    skip_all_of_any_of: bool,
+    level: Level,
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    validate_value_validation(schema, skip_any_of, skip_all_of_any_of, level, path, errors);
+    if let Some(items) = items_schema(schema) {
+        validate_nested_value_validation(items, false, false, level, &child_path(path, "items"), errors);
+    }
+    for (name, property) in sorted_properties(schema) {
+        validate_nested_value_validation(
+            property,
+            false,
+            false,
+            Level::Field,
+            &key_path(&child_path(path, "properties"), name),
+            errors,
+        );
+    }
+
+    let forbidden = |errors: &mut Vec<SchemaError>, field: &str, message: &str| {
+        errors.push(SchemaError::forbidden(child_path(path, field), message));
+    };
+    if !schema.type_.is_empty() {
+        forbidden(errors, "type", "must be empty to be structural");
+    }
+    if schema.additional_properties.is_some() {
+        forbidden(errors, "additionalProperties", "must be undefined to be structural");
+    }
+    if schema.default.is_some() {
+        forbidden(errors, "default", "must be undefined to be structural");
+    }
+    if !schema.title.is_empty() {
+        forbidden(errors, "title", "must be empty to be structural");
+    }
+    if !schema.description.is_empty() {
+        forbidden(errors, "description", "must be empty to be structural");
+    }
+    if schema.nullable == Some(true) {
+        forbidden(errors, "nullable", "must be false to be structural");
+    }
+    if schema.x_preserve_unknown_fields == Some(true) {
+        forbidden(errors, "x-kubernetes-preserve-unknown-fields", "must be false to be structural");
+    }
+    if schema.x_embedded_resource == Some(true) {
+        forbidden(errors, "x-kubernetes-embedded-resource", "must be false to be structural");
+    }
+    if schema.x_int_or_string == Some(true) {
+        forbidden(errors, "x-kubernetes-int-or-string", "must be false to be structural");
+    }
+    if !schema.x_list_map_keys.is_empty() {
+        forbidden(errors, "x-kubernetes-list-map-keys", "must be empty to be structural");
+    }
+    if schema.x_list_type.is_some() {
+        forbidden(errors, "x-kubernetes-list-type", "must be undefined to be structural");
+    }
+    if schema.x_map_type.is_some() {
+        forbidden(errors, "x-kubernetes-map-type", "must be undefined to be structural");
+    }
+    if !schema.x_validations.is_empty() {
+        forbidden(errors, "x-kubernetes-validations", "must be empty to be structural");
+    }
+
+    // Reasoning about metadata in value validations would lead to metadata
+    // restrictions that the shared ObjectMeta schema cannot express.
+    if level == Level::Root && schema.properties.contains_key("metadata") {
+        errors.push(SchemaError::forbidden(
+            key_path(&child_path(path, "properties"), "metadata"),
+            "must not be specified in a nested context",
+        ));
+    }
+}
+
+// =============================================================================
+// Completeness
+// =============================================================================
+
+/// Checks that every field or array specified inside a logical junctor is
+/// also specified in the structural part of the schema.
+fn validate_structural_completeness(schema: &JSONSchemaProps, path: &str, errors: &mut Vec<SchemaError>) {
+    if let Some(items) = items_schema(schema) {
+        validate_structural_completeness(items, &child_path(path, "items"), errors);
+    }
+    for (name, property) in sorted_properties(schema) {
+        validate_structural_completeness(property, &key_path(&child_path(path, "properties"), name), errors);
+    }
+    if let Some(additional) = additional_properties_schema(schema) {
+        validate_structural_completeness(additional, &child_path(path, "additionalProperties"), errors);
+    }
+    validate_value_validation_completeness(schema, schema, path, path, errors);
+}
+
+fn validate_value_validation_completeness(
+    value_validation: &JSONSchemaProps,
+    structural: &JSONSchemaProps,
+    structural_path: &str,
+    value_path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    if let Some(not) = &value_validation.not {
+        validate_nested_value_validation_completeness(
+            not,
+            Some(structural),
+            structural_path,
+            &child_path(value_path, "not"),
+            errors,
+        );
+    }
+    for (junctor, schemas) in [
+        ("allOf", &value_validation.all_of),
+        ("anyOf", &value_validation.any_of),
+        ("oneOf", &value_validation.one_of),
+    ] {
+        for (idx, nested) in schemas.iter().enumerate() {
+            validate_nested_value_validation_completeness(
+                nested,
+                Some(structural),
+                structural_path,
+                &index_path(&child_path(value_path, junctor), idx),
+                errors,
+            );
+        }
+    }
+}
+
+fn validate_nested_value_validation_completeness(
+    nested: &JSONSchemaProps,
+    structural: Option<&JSONSchemaProps>,
+    structural_path: &str,
+    value_path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    let Some(structural) = structural else {
+        errors.push(SchemaError::required(
+            structural_path,
+            format!("because it is defined in {}", value_path),
+        ));
+        return;
+    };
+
+    validate_value_validation_completeness(nested, structural, structural_path, value_path, errors);
+    if let Some(items) = items_schema(nested) {
+        validate_nested_value_validation_completeness(
+            items,
+            items_schema(structural),
+            &child_path(structural_path, "items"),
+            &child_path(value_path, "items"),
+            errors,
+        );
+    }
+    for (name, property) in sorted_properties(nested) {
+        let nested_path = key_path(&child_path(value_path, "properties"), name);
+        match structural.properties.get(name) {
+            Some(structural_property) => validate_nested_value_validation_completeness(
+                property,
+                Some(structural_property),
+                &key_path(&child_path(structural_path, "properties"), name),
+                &nested_path,
+                errors,
+            ),
+            None => match additional_properties_schema(structural) {
+                Some(additional) => validate_nested_value_validation_completeness(
+                    property,
+                    Some(additional),
+                    &child_path(structural_path, "additionalProperties"),
+                    &nested_path,
+                    errors,
+                ),
+                None => errors.push(SchemaError::required(
+                    key_path(&child_path(structural_path, "properties"), name),
+                    format!("because it is defined in {}", nested_path),
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn typed(type_: &str) -> JSONSchemaProps {
+        JSONSchemaProps {
+            type_: type_.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn object(properties: Vec<(&str, JSONSchemaProps)>) -> JSONSchemaProps {
+        JSONSchemaProps {
+            type_: "object".to_string(),
+            properties: properties
+                .into_iter()
+                .map(|(name, schema)| (name.to_string(), schema))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        }
+    }
+
+    fn fields(errors: &[SchemaError]) -> Vec<&str> {
+        errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn test_structural_schema_valid() {
+        let schema = object(vec![
+            ("spec", object(vec![("replicas", typed("integer"))])),
+            (
+                "status",
+                JSONSchemaProps {
+                    type_: "object".to_string(),
+                    x_preserve_unknown_fields: Some(true),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let errors = validate_structural("openAPIV3Schema", &schema);
+        assert!(errors.is_empty(), "expected no errors: {:?}", errors);
+        assert!(is_structural(&schema));
+    }
+
+    #[test]
+    fn test_structural_schema_missing_types() {
+        let mut schema = object(vec![("spec", JSONSchemaProps::default())]);
+        schema.properties.insert(
+            "list".to_string(),
+            JSONSchemaProps {
+                type_: "array".to_string(),
+                items: Some(JSONSchemaPropsOrArray::Schema(Box::default())),
+                ..Default::default()
+            },
+        );
+        schema.properties.insert("array".to_string(), typed("array"));
+
+        let errors = validate_structural("openAPIV3Schema", &schema);
+        assert_eq!(
+            fields(&errors),
+            vec![
+                "openAPIV3Schema.properties[array].items",
+                "openAPIV3Schema.properties[list].items.type",
+                "openAPIV3Schema.properties[spec].type",
+            ]
+        );
+        assert_eq!(
+            errors[2].to_string(),
+            "openAPIV3Schema.properties[spec].type: Required value: must not be empty for specified object fields"
+        );
+    }
+
+    #[test]
+    fn test_structural_schema_root_must_be_object() {
+        let errors = validate_structural("openAPIV3Schema", &typed("string"));
+        assert_eq!(fields(&errors), vec!["openAPIV3Schema.type"]);
+    }
+
+    #[test]
+    fn test_structural_schema_forbidden_in_junctors() {
+        let mut schema = object(vec![("spec", object(vec![("a", typed("string"))]))]);
+        schema.properties.get_mut("spec").unwrap().any_of = vec![
+            JSONSchemaProps {
+                required: vec!["a".to_string()],
+                description: "nope".to_string(),
+                ..Default::default()
+            },
+            JSONSchemaProps {
+                properties: HashMap::from([("b".to_string(), typed("string"))]),
+                ..Default::default()
+            },
+        ];
+
+        let errors = validate_structural("openAPIV3Schema", &schema);
+        assert_eq!(
+            fields(&errors),
+            vec![
+                "openAPIV3Schema.properties[spec].anyOf[0].description",
+                "openAPIV3Schema.properties[spec].anyOf[1].properties[b].type",
+                "openAPIV3Schema.properties[spec].properties[b]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structural_schema_int_or_string_patterns() {
+        let int_or_string = JSONSchemaProps {
+            x_int_or_string: Some(true),
+            any_of: int_or_string_any_of().to_vec(),
+            ..Default::default()
+        };
+        let schema = object(vec![("port", int_or_string.clone())]);
+        assert!(validate_structural("openAPIV3Schema", &schema).is_empty());
+
+        let mut without_extension = int_or_string;
+        without_extension.x_int_or_string = None;
+        let schema = object(vec![("port", without_extension)]);
+        let errors = validate_structural("openAPIV3Schema", &schema);
+        assert_eq!(
+            fields(&errors),
+            vec![
+                "openAPIV3Schema.properties[port].anyOf[0].type",
+                "openAPIV3Schema.properties[port].anyOf[1].type",
+                "openAPIV3Schema.properties[port].type",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structural_schema_embedded_resource() {
+        let embedded = JSONSchemaProps {
+            x_embedded_resource: Some(true),
+            ..Default::default()
+        };
+        let errors = validate_structural("s", &object(vec![("template", embedded)]));
+        assert_eq!(
+            fields(&errors),
+            vec!["s.properties[template].properties", "s.properties[template].type"]
+        );
+
+        let embedded = JSONSchemaProps {
+            type_: "object".to_string(),
+            x_embedded_resource: Some(true),
+            x_preserve_unknown_fields: Some(true),
+            ..Default::default()
+        };
+        assert!(validate_structural("s", &object(vec![("template", embedded)])).is_empty());
+    }
+
+    #[test]
+    fn test_structural_schema_metadata_restrictions() {
+        let metadata = object(vec![("name", typed("string")), ("labels", typed("object"))]);
+        let errors = validate_structural("s", &object(vec![("metadata", metadata)]));
+        assert_eq!(fields(&errors), vec!["s.properties[metadata]"]);
+
+        let metadata = object(vec![("name", typed("string")), ("generateName", typed("string"))]);
+        assert!(validate_structural("s", &object(vec![("metadata", metadata)])).is_empty());
+    }
+
+    #[test]
+    fn test_structural_schema_extension_misuse() {
+        let schema = object(vec![
+            (
+                "tags",
+                JSONSchemaProps {
+                    type_: "string".to_string(),
+                    x_list_type: Some("set".to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "ports",
+                JSONSchemaProps {
+                    type_: "array".to_string(),
+                    x_list_type: Some("map".to_string()),
+                    x_list_map_keys: vec!["name".to_string()],
+                    items: Some(JSONSchemaPropsOrArray::Schema(Box::new(object(vec![(
+                        "port",
+                        typed("integer"),
+                    )])))),
+                    ..Default::default()
+                },
+            ),
+            (
+                "free",
+                JSONSchemaProps {
+                    type_: "object".to_string(),
+                    x_preserve_unknown_fields: Some(false),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let errors = validate_structural("s", &schema);
+        assert_eq!(
+            fields(&errors),
+            vec![
+                "s.properties[free].x-kubernetes-preserve-unknown-fields",
+                "s.properties[ports].x-kubernetes-list-map-keys",
+                "s.properties[tags].x-kubernetes-list-type",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structural_schema_additional_properties_with_properties() {
+        let mut schema = object(vec![("spec", object(vec![("a", typed("string"))]))]);
+        let spec = schema.properties.get_mut("spec").unwrap();
+        spec.additional_properties = Some(JSONSchemaPropsOrBool::Bool(true));
+        assert!(validate_structural("s", &schema).is_empty());
+
+        let spec = schema.properties.get_mut("spec").unwrap();
+        spec.additional_properties = Some(JSONSchemaPropsOrBool::Bool(false));
+        assert_eq!(fields(&validate_structural("s", &schema)), vec!["s.properties[spec].additionalProperties"]);
+
+        let spec = schema.properties.get_mut("spec").unwrap();
+        spec.additional_properties = Some(JSONSchemaPropsOrBool::Schema(Box::new(typed("string"))));
+        assert_eq!(fields(&validate_structural("s", &schema)), vec!["s.properties[spec].additionalProperties"]);
+    }
+
+    #[test]
+    fn test_validate_crd_structural() {
+        use crate::apis::apiextensions::v1::{
+            CustomResourceDefinitionSpec, CustomResourceDefinitionVersion, CustomResourceValidation,
+        };
+
+        let crd = CustomResourceDefinition {
+            spec: CustomResourceDefinitionSpec {
+                versions: vec![
+                    CustomResourceDefinitionVersion {
+                        name: "v1".to_string(),
+                        schema: Some(CustomResourceValidation {
+                            open_apiv3_schema: Some(object(vec![("spec", JSONSchemaProps::default())])),
+                        }),
+                        ..Default::default()
+                    },
+                    CustomResourceDefinitionVersion {
+                        name: "v2".to_string(),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let errors = validate_custom_resource_definition_structural(&crd);
+        assert_eq!(
+            fields(&errors),
+            vec![
+                "spec.versions[0].schema.openAPIV3Schema.properties[spec].type",
+                "spec.versions[1].schema.openAPIV3Schema",
+            ]
+        );
+    }
+}
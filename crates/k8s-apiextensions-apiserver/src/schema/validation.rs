@@ -0,0 +1,332 @@
+//! Validation of custom resource values against a schema
+//!
+//! Checks a JSON value against the OpenAPI v3 value validations of a
+//! `JSONSchemaProps`: types, nullability, enums, numeric bounds, string and
+//! collection sizes, patterns, required fields and the logical junctors
+//! `allOf`/`anyOf`/`oneOf`/`not`. Formats and `x-kubernetes-validations`
+//! rules are not evaluated here.
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::{child_path, index_path, SchemaError};
+use crate::apis::apiextensions::v1::{JSONSchemaProps, JSONSchemaPropsOrArray, JSONSchemaPropsOrBool};
+
+/// Validates `value` against `schema`. `path` is the field path of the value.
+pub fn validate_value(path: &str, value: &Value, schema: &JSONSchemaProps) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    validate(path, value, schema, &mut errors);
+    errors
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &Value, type_: &str) -> bool {
+    match type_ {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        _ => false,
+    }
+}
+
+fn validate(path: &str, value: &Value, schema: &JSONSchemaProps, errors: &mut Vec<SchemaError>) {
+    if value.is_null() {
+        if schema.nullable != Some(true) && !schema.type_.is_empty() {
+            errors.push(SchemaError::invalid(
+                path,
+                "null",
+                format!("must be of type {}", schema.type_),
+            ));
+        }
+        return;
+    }
+
+    if schema.x_int_or_string == Some(true) {
+        if !matches_type(value, "integer") && !matches_type(value, "string") {
+            errors.push(SchemaError::invalid(
+                path,
+                json_type_name(value),
+                "must be of type integer or string",
+            ));
+            return;
+        }
+    } else if !schema.type_.is_empty() && !matches_type(value, &schema.type_) {
+        errors.push(SchemaError::invalid(
+            path,
+            json_type_name(value),
+            format!("must be of type {}", schema.type_),
+        ));
+        return;
+    }
+
+    if !schema.enum_.is_empty() && !schema.enum_.contains(value) {
+        let supported: Vec<String> = schema.enum_.iter().map(|v| v.to_string()).collect();
+        let supported: Vec<&str> = supported.iter().map(String::as_str).collect();
+        errors.push(SchemaError::not_supported(path, value.to_string(), &supported));
+    }
+
+    match value {
+        Value::Number(number) => validate_number(path, number.as_f64().unwrap_or_default(), schema, errors),
+        Value::String(string) => validate_string(path, string, schema, errors),
+        Value::Array(items) => validate_array(path, items, schema, errors),
+        Value::Object(map) => validate_object(path, map, schema, errors),
+        _ => {}
+    }
+
+    for nested in &schema.all_of {
+        validate(path, value, nested, errors);
+    }
+    if !schema.any_of.is_empty()
+        && !schema.any_of.iter().any(|nested| validate_value(path, value, nested).is_empty())
+    {
+        errors.push(SchemaError::invalid(
+            path,
+            value,
+            "must validate at least one schema (anyOf)",
+        ));
+    }
+    if !schema.one_of.is_empty() {
+        let matched = schema
+            .one_of
+            .iter()
+            .filter(|nested| validate_value(path, value, nested).is_empty())
+            .count();
+        if matched != 1 {
+            errors.push(SchemaError::invalid(
+                path,
+                value,
+                "must validate one and only one schema (oneOf)",
+            ));
+        }
+    }
+    if let Some(not) = &schema.not {
+        if validate_value(path, value, not).is_empty() {
+            errors.push(SchemaError::invalid(path, value, "must not validate the schema (not)"));
+        }
+    }
+}
+
+fn validate_number(path: &str, number: f64, schema: &JSONSchemaProps, errors: &mut Vec<SchemaError>) {
+    if let Some(maximum) = schema.maximum {
+        if schema.exclusive_maximum == Some(true) && number >= maximum {
+            errors.push(SchemaError::invalid(path, number, format!("should be less than {}", maximum)));
+        } else if number > maximum {
+            errors.push(SchemaError::invalid(
+                path,
+                number,
+                format!("should be less than or equal to {}", maximum),
+            ));
+        }
+    }
+    if let Some(minimum) = schema.minimum {
+        if schema.exclusive_minimum == Some(true) && number <= minimum {
+            errors.push(SchemaError::invalid(path, number, format!("should be greater than {}", minimum)));
+        } else if number < minimum {
+            errors.push(SchemaError::invalid(
+                path,
+                number,
+                format!("should be greater than or equal to {}", minimum),
+            ));
+        }
+    }
+    if let Some(multiple_of) = schema.multiple_of {
+        if multiple_of > 0.0 && (number / multiple_of).fract() != 0.0 {
+            errors.push(SchemaError::invalid(
+                path,
+                number,
+                format!("should be a multiple of {}", multiple_of),
+            ));
+        }
+    }
+}
+
+fn validate_string(path: &str, string: &str, schema: &JSONSchemaProps, errors: &mut Vec<SchemaError>) {
+    let length = string.chars().count() as i64;
+    if let Some(max_length) = schema.max_length {
+        if length > max_length {
+            errors.push(SchemaError::invalid(
+                path,
+                string,
+                format!("should be at most {} chars long", max_length),
+            ));
+        }
+    }
+    if let Some(min_length) = schema.min_length {
+        if length < min_length {
+            errors.push(SchemaError::invalid(
+                path,
+                string,
+                format!("should be at least {} chars long", min_length),
+            ));
+        }
+    }
+    if !schema.pattern.is_empty() {
+        match Regex::new(&schema.pattern) {
+            Ok(pattern) if !pattern.is_match(string) => errors.push(SchemaError::invalid(
+                path,
+                string,
+                format!("should match '{}'", schema.pattern),
+            )),
+            Ok(_) => {}
+            Err(err) => errors.push(SchemaError::invalid(
+                path,
+                &schema.pattern,
+                format!("pattern is not a valid regular expression: {}", err),
+            )),
+        }
+    }
+}
+
+fn validate_array(path: &str, items: &[Value], schema: &JSONSchemaProps, errors: &mut Vec<SchemaError>) {
+    let count = items.len() as i64;
+    if let Some(max_items) = schema.max_items {
+        if count > max_items {
+            errors.push(SchemaError::invalid(
+                path,
+                count,
+                format!("must have at most {} items", max_items),
+            ));
+        }
+    }
+    if let Some(min_items) = schema.min_items {
+        if count < min_items {
+            errors.push(SchemaError::invalid(
+                path,
+                count,
+                format!("should have at least {} items", min_items),
+            ));
+        }
+    }
+    match &schema.items {
+        Some(JSONSchemaPropsOrArray::Schema(items_schema)) => {
+            for (idx, item) in items.iter().enumerate() {
+                validate(&index_path(path, idx), item, items_schema, errors);
+            }
+        }
+        Some(JSONSchemaPropsOrArray::Schemas(schemas)) => {
+            for (idx, (item, item_schema)) in items.iter().zip(schemas).enumerate() {
+                validate(&index_path(path, idx), item, item_schema, errors);
+            }
+        }
+        None => {}
+    }
+}
+
+fn validate_object(
+    path: &str,
+    map: &serde_json::Map<String, Value>,
+    schema: &JSONSchemaProps,
+    errors: &mut Vec<SchemaError>,
+) {
+    let count = map.len() as i64;
+    if let Some(max_properties) = schema.max_properties {
+        if count > max_properties {
+            errors.push(SchemaError::invalid(
+                path,
+                count,
+                format!("must have at most {} properties", max_properties),
+            ));
+        }
+    }
+    if let Some(min_properties) = schema.min_properties {
+        if count < min_properties {
+            errors.push(SchemaError::invalid(
+                path,
+                count,
+                format!("should have at least {} properties", min_properties),
+            ));
+        }
+    }
+    for name in &schema.required {
+        if !map.contains_key(name) {
+            errors.push(SchemaError::required(child_path(path, name), "Required value"));
+        }
+    }
+
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    for key in keys {
+        let field_path = child_path(path, key);
+        if let Some(property) = schema.properties.get(key) {
+            validate(&field_path, &map[key], property, errors);
+        } else {
+            match &schema.additional_properties {
+                Some(JSONSchemaPropsOrBool::Schema(additional)) => {
+                    validate(&field_path, &map[key], additional, errors)
+                }
+                Some(JSONSchemaPropsOrBool::Bool(false)) => {
+                    errors.push(SchemaError::forbidden(field_path, "additional properties are not allowed"))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(value: Value) -> JSONSchemaProps {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_validate_value_types_and_bounds() {
+        let schema = schema(json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": {"type": "string", "maxLength": 3, "pattern": "^[a-z]+$"},
+                "replicas": {"type": "integer", "minimum": 1},
+                "mode": {"type": "string", "enum": ["a", "b"]},
+                "port": {"x-kubernetes-int-or-string": true},
+                "tags": {"type": "array", "maxItems": 1, "items": {"type": "string"}}
+            }
+        }));
+
+        let value = json!({"name": "abc", "replicas": 2, "mode": "a", "port": "http", "tags": ["x"]});
+        assert!(validate_value("spec", &value, &schema).is_empty());
+
+        let value = json!({"replicas": 0, "mode": "c", "port": true, "tags": ["x", 1]});
+        let errors = validate_value("spec", &value, &schema);
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["spec.name", "spec.mode", "spec.port", "spec.replicas", "spec.tags", "spec.tags[1]"]
+        );
+    }
+
+    #[test]
+    fn test_validate_value_nullable_and_junctors() {
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {
+                "a": {"type": "string", "nullable": true},
+                "b": {"type": "string"},
+                "c": {"type": "integer", "anyOf": [{"minimum": 10}, {"maximum": 0}]}
+            }
+        }));
+
+        assert!(validate_value("", &json!({"a": null, "c": 11}), &schema).is_empty());
+
+        let errors = validate_value("", &json!({"b": null, "c": 5}), &schema);
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["b", "c"]);
+    }
+}
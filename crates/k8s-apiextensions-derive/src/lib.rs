@@ -0,0 +1,390 @@
+//! Derive macro for `k8s_apiextensions_apiserver::schema::generate::JsonSchema`
+//!
+//! `#[derive(JsonSchema)]` produces the OpenAPI v3 schema of a struct or a
+//! unit-only enum. Doc comments become descriptions, serde `rename`,
+//! `rename_all`, `default`, `flatten`, `skip` and `transparent` attributes are
+//! honored, and `#[schema(...)]` adds x-kubernetes-* extensions:
+//!
+//! - `list_type = "atomic" | "set" | "map"`
+//! - `list_map_key = "name"` (repeatable)
+//! - `map_type = "atomic" | "granular"`
+//! - `preserve_unknown_fields`
+//! - `embedded_resource`
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::ParseStream;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, LitStr, Token};
+
+#[proc_macro_derive(JsonSchema, attributes(schema))]
+pub fn derive_json_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let container = SerdeAttrs::parse(&input.attrs)?;
+    let description = doc_comment(&input.attrs);
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) if !container.transparent => {
+                let mut statements = Vec::new();
+                for field in &fields.named {
+                    let serde = SerdeAttrs::parse(&field.attrs)?;
+                    if serde.skip {
+                        continue;
+                    }
+                    let ty = &field.ty;
+                    if serde.flatten {
+                        statements.push(quote! {
+                            let flattened = <#ty as __generate::JsonSchema>::json_schema();
+                            schema.properties.extend(flattened.properties);
+                            schema.required.extend(flattened.required);
+                        });
+                        continue;
+                    }
+                    let ident = field.ident.as_ref().expect("named field");
+                    let name = serde.rename.clone().unwrap_or_else(|| {
+                        let ident = ident.to_string();
+                        let ident = ident.strip_prefix("r#").unwrap_or(&ident).to_string();
+                        match &container.rename_all {
+                            Some(rule) => rename_field(&ident, rule),
+                            None => ident,
+                        }
+                    });
+                    let field_description = doc_comment(&field.attrs);
+                    let extensions = SchemaAttrs::parse(&field.attrs)?.to_tokens();
+                    let set_description = (!field_description.is_empty()).then(|| {
+                        quote! { field_schema.description = #field_description.to_string(); }
+                    });
+                    let push_required = (!serde.default && !container.default).then(|| {
+                        quote! {
+                            if !<#ty as __generate::JsonSchema>::is_optional() {
+                                schema.required.push(#name.to_string());
+                            }
+                        }
+                    });
+                    statements.push(quote! {
+                        #[allow(unused_mut)]
+                        let mut field_schema = <#ty as __generate::JsonSchema>::json_schema();
+                        #set_description
+                        #extensions
+                        schema.properties.insert(#name.to_string(), field_schema);
+                        #push_required
+                    });
+                }
+                quote! {
+                    #[allow(unused_mut)]
+                    let mut schema = __generate::object_schema(#description);
+                    #(#statements)*
+                    schema
+                }
+            }
+            Fields::Named(fields) if fields.named.len() == 1 => {
+                let ty = &fields.named[0].ty;
+                newtype_body(ty, &description)
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                newtype_body(ty, &description)
+            }
+            Fields::Unit => quote! { __generate::object_schema(#description) },
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "JsonSchema can only be derived for structs with named fields or a single field",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            let mut values = Vec::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        "JsonSchema can only be derived for enums with unit variants",
+                    ));
+                }
+                let serde = SerdeAttrs::parse(&variant.attrs)?;
+                if serde.skip {
+                    continue;
+                }
+                let name = serde.rename.clone().unwrap_or_else(|| {
+                    let ident = variant.ident.to_string();
+                    match &container.rename_all {
+                        Some(rule) => rename_variant(&ident, rule),
+                        None => ident,
+                    }
+                });
+                values.push(name);
+            }
+            quote! {
+                __generate::string_enum_schema(#description, &[#(#values),*])
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "JsonSchema cannot be derived for unions",
+            ))
+        }
+    };
+
+    let type_params: Vec<_> = input.generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = input.generics.make_where_clause();
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(#param: ::k8s_apiextensions_apiserver::schema::generate::JsonSchema));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        const _: () = {
+            use ::k8s_apiextensions_apiserver::schema::generate as __generate;
+
+            impl #impl_generics __generate::JsonSchema for #ident #ty_generics #where_clause {
+                fn json_schema() -> ::k8s_apiextensions_apiserver::apis::apiextensions::v1::JSONSchemaProps {
+                    #body
+                }
+            }
+        };
+    })
+}
+
+fn newtype_body(ty: &syn::Type, description: &str) -> TokenStream2 {
+    let set_description = (!description.is_empty()).then(|| {
+        quote! { schema.description = #description.to_string(); }
+    });
+    quote! {
+        #[allow(unused_mut)]
+        let mut schema = <#ty as __generate::JsonSchema>::json_schema();
+        #set_description
+        schema
+    }
+}
+
+/// Joins `///` doc comment lines into a description.
+fn doc_comment(attrs: &[Attribute]) -> String {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        if !attr.path().is_ident("doc") {
+            continue;
+        }
+        if let syn::Meta::NameValue(meta) = &attr.meta {
+            if let syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(lit),
+                ..
+            }) = &meta.value
+            {
+                let line = lit.value();
+                lines.push(line.strip_prefix(' ').unwrap_or(&line).trim_end().to_string());
+            }
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// The serde attributes that change the shape of the serialized value.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    default: bool,
+    flatten: bool,
+    skip: bool,
+    transparent: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = SerdeAttrs::default();
+        for attr in attrs {
+            if !attr.path().is_ident("serde") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    if meta.input.peek(Token![=]) {
+                        parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else {
+                        meta.parse_nested_meta(|nested| {
+                            let value = nested.value()?.parse::<LitStr>()?.value();
+                            if nested.path.is_ident("deserialize") {
+                                parsed.rename = Some(value);
+                            }
+                            Ok(())
+                        })?;
+                    }
+                } else if meta.path.is_ident("rename_all") {
+                    if meta.input.peek(Token![=]) {
+                        parsed.rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else {
+                        meta.parse_nested_meta(|nested| {
+                            let value = nested.value()?.parse::<LitStr>()?.value();
+                            if nested.path.is_ident("deserialize") {
+                                parsed.rename_all = Some(value);
+                            }
+                            Ok(())
+                        })?;
+                    }
+                } else if meta.path.is_ident("default") {
+                    parsed.default = true;
+                    skip_value(meta.input)?;
+                } else if meta.path.is_ident("flatten") {
+                    parsed.flatten = true;
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    parsed.skip = true;
+                } else if meta.path.is_ident("transparent") {
+                    parsed.transparent = true;
+                } else {
+                    skip_value(meta.input)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// Consumes `= value` or `(...)` of a serde attribute we do not interpret.
+fn skip_value(input: ParseStream) -> syn::Result<()> {
+    if input.peek(Token![=]) {
+        input.parse::<Token![=]>()?;
+        input.parse::<syn::Expr>()?;
+    } else if input.peek(syn::token::Paren) {
+        let _content;
+        syn::parenthesized!(_content in input);
+    }
+    Ok(())
+}
+
+/// The `#[schema(...)]` attributes that map to x-kubernetes-* extensions.
+#[derive(Default)]
+struct SchemaAttrs {
+    list_type: Option<String>,
+    list_map_keys: Vec<String>,
+    map_type: Option<String>,
+    preserve_unknown_fields: bool,
+    embedded_resource: bool,
+}
+
+impl SchemaAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = SchemaAttrs::default();
+        for attr in attrs {
+            if !attr.path().is_ident("schema") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("list_type") {
+                    let value = meta.value()?.parse::<LitStr>()?;
+                    if !["atomic", "set", "map"].contains(&value.value().as_str()) {
+                        return Err(syn::Error::new_spanned(value, "list_type must be atomic, set or map"));
+                    }
+                    parsed.list_type = Some(value.value());
+                } else if meta.path.is_ident("list_map_key") {
+                    parsed.list_map_keys.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("map_type") {
+                    let value = meta.value()?.parse::<LitStr>()?;
+                    if !["atomic", "granular"].contains(&value.value().as_str()) {
+                        return Err(syn::Error::new_spanned(value, "map_type must be atomic or granular"));
+                    }
+                    parsed.map_type = Some(value.value());
+                } else if meta.path.is_ident("preserve_unknown_fields") {
+                    parsed.preserve_unknown_fields = true;
+                } else if meta.path.is_ident("embedded_resource") {
+                    parsed.embedded_resource = true;
+                } else {
+                    return Err(meta.error("unsupported schema attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+
+    fn to_tokens(&self) -> TokenStream2 {
+        let mut tokens = TokenStream2::new();
+        if let Some(list_type) = &self.list_type {
+            tokens.extend(quote! { field_schema.x_list_type = Some(#list_type.to_string()); });
+        }
+        let keys = &self.list_map_keys;
+        if !keys.is_empty() {
+            tokens.extend(quote! {
+                field_schema.x_list_map_keys = vec![#(#keys.to_string()),*];
+            });
+        }
+        if let Some(map_type) = &self.map_type {
+            tokens.extend(quote! { field_schema.x_map_type = Some(#map_type.to_string()); });
+        }
+        if self.preserve_unknown_fields {
+            tokens.extend(quote! { field_schema.x_preserve_unknown_fields = Some(true); });
+        }
+        if self.embedded_resource {
+            tokens.extend(quote! { field_schema.x_embedded_resource = Some(true); });
+        }
+        tokens
+    }
+}
+
+/// Applies a serde `rename_all` rule to a snake_case field name.
+fn rename_field(field: &str, rule: &str) -> String {
+    let words: Vec<&str> = field.split('_').filter(|w| !w.is_empty()).collect();
+    match rule {
+        "lowercase" | "snake_case" => field.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_ascii_uppercase(),
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.replace('_', "-").to_ascii_uppercase(),
+        "PascalCase" => words.iter().map(|w| capitalize(w)).collect(),
+        "camelCase" => words
+            .iter()
+            .enumerate()
+            .map(|(idx, w)| if idx == 0 { w.to_string() } else { capitalize(w) })
+            .collect(),
+        _ => field.to_string(),
+    }
+}
+
+/// Applies a serde `rename_all` rule to a PascalCase variant name.
+fn rename_variant(variant: &str, rule: &str) -> String {
+    let snake = || {
+        let mut snake = String::new();
+        for (idx, ch) in variant.char_indices() {
+            if idx > 0 && ch.is_uppercase() {
+                snake.push('_');
+            }
+            snake.push(ch.to_ascii_lowercase());
+        }
+        snake
+    };
+    match rule {
+        "lowercase" => variant.to_ascii_lowercase(),
+        "UPPERCASE" => variant.to_ascii_uppercase(),
+        "camelCase" => {
+            let mut chars = variant.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        }
+        "snake_case" => snake(),
+        "SCREAMING_SNAKE_CASE" => snake().to_ascii_uppercase(),
+        "kebab-case" => snake().replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => snake().replace('_', "-").to_ascii_uppercase(),
+        _ => variant.to_string(),
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
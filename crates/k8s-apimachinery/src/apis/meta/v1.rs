@@ -253,6 +253,48 @@ pub struct LabelSelector {
     pub match_expressions: Vec<LabelSelectorRequirement>,
 }
 
+impl LabelSelector {
+    /// Returns true if the selector has no requirements and so matches
+    /// every set of labels.
+    pub fn is_empty(&self) -> bool {
+        self.match_labels.is_empty() && self.match_expressions.is_empty()
+    }
+
+    /// Reports whether `labels` satisfy every requirement of the selector,
+    /// like `LabelSelectorAsSelector(selector).Matches(labels)` in Go.
+    /// Fails if a requirement is malformed.
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> Result<bool, String> {
+        let mut matches = self
+            .match_labels
+            .iter()
+            .all(|(key, value)| labels.get(key) == Some(value));
+        for requirement in &self.match_expressions {
+            matches &= requirement.matches(labels)?;
+        }
+        Ok(matches)
+    }
+}
+
+impl LabelSelectorRequirement {
+    /// Reports whether `labels` satisfy the requirement.
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> Result<bool, String> {
+        let value = labels.get(&self.key);
+        match self.operator.as_str() {
+            "In" | "NotIn" if self.values.is_empty() => {
+                Err("for 'in', 'notin' operators, values set can't be empty".to_string())
+            }
+            "Exists" | "DoesNotExist" if !self.values.is_empty() => {
+                Err("values set must be empty for exists and does not exist".to_string())
+            }
+            "In" => Ok(value.is_some_and(|value| self.values.contains(value))),
+            "NotIn" => Ok(!value.is_some_and(|value| self.values.contains(value))),
+            "Exists" => Ok(value.is_some()),
+            "DoesNotExist" => Ok(value.is_none()),
+            operator => Err(format!("{:?} is not a valid label selector operator", operator)),
+        }
+    }
+}
+
 /// LabelSelectorRequirement is a selector that contains values, a key, and an operator.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -420,6 +462,35 @@ mod tests {
         assert!(json.contains("\"namespace\": \"default\""));
     }
 
+    #[test]
+    fn test_label_selector_matches() {
+        let labels: BTreeMap<String, String> = [("app", "web"), ("tier", "front")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let requirement = |key: &str, operator: &str, values: &[&str]| LabelSelectorRequirement {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+        };
+        let selector = LabelSelector {
+            match_labels: [("app".to_string(), "web".to_string())].into_iter().collect(),
+            match_expressions: vec![
+                requirement("tier", "In", &["front", "back"]),
+                requirement("env", "NotIn", &["prod"]),
+                requirement("env", "DoesNotExist", &[]),
+            ],
+        };
+        assert_eq!(selector.matches(&labels), Ok(true));
+        assert_eq!(LabelSelector::default().matches(&BTreeMap::new()), Ok(true));
+
+        let selector = LabelSelector {
+            match_expressions: vec![requirement("tier", "Exists", &["x"])],
+            ..Default::default()
+        };
+        assert!(selector.matches(&labels).is_err());
+    }
+
     #[test]
     fn test_type_meta() {
         let tm = TypeMeta::new("v1", "Pod");
@@ -8,6 +8,7 @@
 
 pub mod apis;
 pub mod types;
+pub mod util;
 
 pub use apis::meta::v1::{
     Condition, FieldSelectorOperator, FieldSelectorRequirement, LabelSelector, LabelSelectorRequirement,
@@ -0,0 +1,278 @@
+//! JSON Patch (RFC 6902)
+//!
+//! Admission webhooks and mutating admission policies describe changes to
+//! an object as a JSON Patch. `diff` computes the patch between two JSON
+//! documents and `apply` applies one.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A JSON Patch: operations applied in order.
+pub type Patch = Vec<PatchOperation>;
+
+/// A single JSON Patch operation. Paths are JSON Pointers (RFC 6901).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// PatchError is returned when a patch does not apply to a document.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PatchError {
+    #[error("invalid JSON pointer: {0:?}")]
+    InvalidPointer(String),
+    #[error("{op} operation does not apply: doc is missing path: {path:?}")]
+    MissingPath { op: &'static str, path: String },
+    #[error("move operation does not apply: cannot move {from:?} into its own child {path:?}")]
+    MoveIntoChild { from: String, path: String },
+    #[error("testing value {0:?} failed")]
+    TestFailed(String),
+}
+
+/// Escapes a key for use as a JSON Pointer reference token.
+pub fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, PatchError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PatchError::InvalidPointer(pointer.to_string()));
+    };
+    Ok(rest.split('/').map(|token| token.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+fn array_index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.bytes().all(|b| b.is_ascii_digit()).then(|| token.parse().ok())?
+}
+
+fn lookup<'a>(document: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    tokens.iter().try_fold(document, |value, token| match value {
+        Value::Object(map) => map.get_mut(token),
+        Value::Array(items) => items.get_mut(array_index(token)?),
+        _ => None,
+    })
+}
+
+fn add(document: &mut Value, path: &str, value: Value) -> Result<(), PatchError> {
+    let tokens = parse_pointer(path)?;
+    let missing = || PatchError::MissingPath {
+        op: "add",
+        path: path.to_string(),
+    };
+    let Some((last, parent)) = tokens.split_last() else {
+        *document = value;
+        return Ok(());
+    };
+    match lookup(document, parent).ok_or_else(missing)? {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+        }
+        Value::Array(items) if last == "-" => items.push(value),
+        Value::Array(items) => match array_index(last) {
+            Some(idx) if idx <= items.len() => items.insert(idx, value),
+            _ => return Err(missing()),
+        },
+        _ => return Err(missing()),
+    }
+    Ok(())
+}
+
+fn remove(document: &mut Value, path: &str, op: &'static str) -> Result<Value, PatchError> {
+    let tokens = parse_pointer(path)?;
+    let missing = || PatchError::MissingPath {
+        op,
+        path: path.to_string(),
+    };
+    let (last, parent) = tokens.split_last().ok_or_else(missing)?;
+    match lookup(document, parent).ok_or_else(missing)? {
+        Value::Object(map) => map.remove(last).ok_or_else(missing),
+        Value::Array(items) => match array_index(last) {
+            Some(idx) if idx < items.len() => Ok(items.remove(idx)),
+            _ => Err(missing()),
+        },
+        _ => Err(missing()),
+    }
+}
+
+fn get(document: &mut Value, path: &str, op: &'static str) -> Result<Value, PatchError> {
+    let tokens = parse_pointer(path)?;
+    lookup(document, &tokens).cloned().ok_or_else(|| PatchError::MissingPath {
+        op,
+        path: path.to_string(),
+    })
+}
+
+/// Applies `patch` to `document`. The document is left unchanged if any
+/// operation fails.
+pub fn apply(document: &mut Value, patch: &[PatchOperation]) -> Result<(), PatchError> {
+    let mut patched = document.clone();
+    for operation in patch {
+        match operation {
+            PatchOperation::Add { path, value } => add(&mut patched, path, value.clone())?,
+            PatchOperation::Remove { path } => {
+                remove(&mut patched, path, "remove")?;
+            }
+            PatchOperation::Replace { path, value } => {
+                let tokens = parse_pointer(path)?;
+                let target = lookup(&mut patched, &tokens).ok_or_else(|| PatchError::MissingPath {
+                    op: "replace",
+                    path: path.to_string(),
+                })?;
+                *target = value.clone();
+            }
+            PatchOperation::Move { from, path } => {
+                if path.starts_with(&format!("{}/", from)) {
+                    return Err(PatchError::MoveIntoChild {
+                        from: from.clone(),
+                        path: path.clone(),
+                    });
+                }
+                let value = remove(&mut patched, from, "move")?;
+                add(&mut patched, path, value)?;
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = get(&mut patched, from, "copy")?;
+                add(&mut patched, path, value)?;
+            }
+            PatchOperation::Test { path, value } => {
+                if get(&mut patched, path, "test")? != *value {
+                    return Err(PatchError::TestFailed(path.clone()));
+                }
+            }
+        }
+    }
+    *document = patched;
+    Ok(())
+}
+
+/// Returns a patch that turns `from` into `to`. Objects are compared key by
+/// key and arrays element by element, with elements added or removed at
+/// the end.
+pub fn diff(from: &Value, to: &Value) -> Patch {
+    let mut patch = Vec::new();
+    diff_into(String::new(), from, to, &mut patch);
+    patch
+}
+
+fn diff_into(path: String, from: &Value, to: &Value, patch: &mut Patch) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let child = format!("{}/{}", path, escape(key));
+                match new.get(key) {
+                    Some(new_value) => diff_into(child, old_value, new_value, patch),
+                    None => patch.push(PatchOperation::Remove { path: child }),
+                }
+            }
+            for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                patch.push(PatchOperation::Add {
+                    path: format!("{}/{}", path, escape(key)),
+                    value: new_value.clone(),
+                });
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for (idx, (old_value, new_value)) in old.iter().zip(new).enumerate() {
+                diff_into(format!("{}/{}", path, idx), old_value, new_value, patch);
+            }
+            // Remove from the end so that earlier indices stay valid.
+            for idx in (new.len()..old.len()).rev() {
+                patch.push(PatchOperation::Remove {
+                    path: format!("{}/{}", path, idx),
+                });
+            }
+            for (idx, value) in new.iter().enumerate().skip(old.len()) {
+                patch.push(PatchOperation::Add {
+                    path: format!("{}/{}", path, idx),
+                    value: value.clone(),
+                });
+            }
+        }
+        _ => patch.push(PatchOperation::Replace {
+            path,
+            value: to.clone(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_and_apply() {
+        let from = json!({
+            "metadata": {"labels": {"app": "web", "a/b": "x"}},
+            "spec": {"replicas": 1, "containers": [{"name": "a"}, {"name": "b"}]},
+        });
+        let to = json!({
+            "metadata": {"labels": {"app": "web", "tier": "front"}},
+            "spec": {"replicas": 3, "containers": [{"name": "a", "image": "nginx"}]},
+        });
+        let patch = diff(&from, &to);
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            json!([
+                {"op": "remove", "path": "/metadata/labels/a~1b"},
+                {"op": "add", "path": "/metadata/labels/tier", "value": "front"},
+                {"op": "add", "path": "/spec/containers/0/image", "value": "nginx"},
+                {"op": "remove", "path": "/spec/containers/1"},
+                {"op": "replace", "path": "/spec/replicas", "value": 3},
+            ])
+        );
+        let mut document = from.clone();
+        apply(&mut document, &patch).unwrap();
+        assert_eq!(document, to);
+        assert!(diff(&to, &to).is_empty());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut document = json!({"a": {"b": [1, 2]}, "c": "x"});
+        let patch: Patch = serde_json::from_value(json!([
+            {"op": "test", "path": "/c", "value": "x"},
+            {"op": "add", "path": "/a/b/-", "value": 3},
+            {"op": "add", "path": "/a/b/0", "value": 0},
+            {"op": "copy", "from": "/c", "path": "/d"},
+            {"op": "move", "from": "/c", "path": "/a/c"},
+        ]))
+        .unwrap();
+        apply(&mut document, &patch).unwrap();
+        assert_eq!(document, json!({"a": {"b": [0, 1, 2, 3], "c": "x"}, "d": "x"}));
+
+        let err = apply(&mut document, &[PatchOperation::Remove { path: "/missing".to_string() }]).unwrap_err();
+        assert_eq!(err.to_string(), "remove operation does not apply: doc is missing path: \"/missing\"");
+        let err = apply(
+            &mut document,
+            &[
+                PatchOperation::Add {
+                    path: "/e".to_string(),
+                    value: json!(1),
+                },
+                PatchOperation::Test {
+                    path: "/d".to_string(),
+                    value: json!("y"),
+                },
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err, PatchError::TestFailed("/d".to_string()));
+        assert!(document.get("e").is_none());
+    }
+}
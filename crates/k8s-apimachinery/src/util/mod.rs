@@ -0,0 +1,4 @@
+//! Utilities for working with API objects
+
+pub mod jsonpatch;
+pub mod selector;
@@ -0,0 +1,265 @@
+//! Label and field selector strings
+//!
+//! Parses the selectors of the `labelSelector` and `fieldSelector` query
+//! parameters, e.g. `app=web,tier in (front,back)` and
+//! `spec.nodeName=node-1`, into the requirements API objects carry.
+
+use crate::apis::meta::v1::{
+    FieldSelectorRequirement, LabelSelectorRequirement, FIELD_SELECTOR_OP_IN, FIELD_SELECTOR_OP_NOT_IN,
+};
+
+/// Parses a label selector. Equality requirements become `In` and `NotIn`
+/// requirements with a single value, `key` and `!key` become `Exists` and
+/// `DoesNotExist`, and `key > n` and `key < n` become `Gt` and `Lt`.
+pub fn parse_label_selector(selector: &str) -> Result<Vec<LabelSelectorRequirement>, String> {
+    let mut requirements = Vec::new();
+    for term in split_top_level(selector) {
+        let term = term.trim();
+        if term.is_empty() {
+            if selector.trim().is_empty() {
+                continue;
+            }
+            return Err(format!("unable to parse requirement: empty requirement in {:?}", selector));
+        }
+        requirements.push(parse_label_requirement(term)?);
+    }
+    Ok(requirements)
+}
+
+/// Splits at commas outside of parentheses.
+fn split_top_level(selector: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (idx, ch) in selector.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                terms.push(&selector[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    terms.push(&selector[start..]);
+    terms
+}
+
+fn parse_label_requirement(term: &str) -> Result<LabelSelectorRequirement, String> {
+    let requirement = |key: &str, operator: &str, values: Vec<String>| -> Result<LabelSelectorRequirement, String> {
+        validate_label_key(key)?;
+        Ok(LabelSelectorRequirement {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            values,
+        })
+    };
+    if let Some(key) = term.strip_prefix('!') {
+        return requirement(key.trim(), "DoesNotExist", Vec::new());
+    }
+    let end = term
+        .find(|ch: char| ch.is_whitespace() || "=!<>(".contains(ch))
+        .unwrap_or(term.len());
+    let (key, rest) = (&term[..end], term[end..].trim_start());
+    if rest.is_empty() {
+        return requirement(key, "Exists", Vec::new());
+    }
+    for (operator, name) in [("==", "In"), ("!=", "NotIn"), ("=", "In"), (">", "Gt"), ("<", "Lt")] {
+        if let Some(value) = rest.strip_prefix(operator) {
+            let value = value.trim();
+            if name == "Gt" || name == "Lt" {
+                value
+                    .parse::<i64>()
+                    .map_err(|_| format!("for 'Gt', 'Lt' operators, the value must be an integer: {:?}", value))?;
+            }
+            validate_label_value(value)?;
+            return requirement(key, name, vec![value.to_string()]);
+        }
+    }
+    for (operator, name) in [("notin", "NotIn"), ("in", "In")] {
+        let Some(set) = rest.strip_prefix(operator) else {
+            continue;
+        };
+        let set = set.trim();
+        let Some(inner) = set.strip_prefix('(').and_then(|set| set.strip_suffix(')')) else {
+            return Err(format!("unable to parse requirement: expected a set of values in {:?}", term));
+        };
+        let mut values = inner
+            .split(',')
+            .map(|value| value.trim().to_string())
+            .collect::<Vec<_>>();
+        if inner.trim().is_empty() {
+            return Err("for 'in', 'notin' operators, values set can't be empty".to_string());
+        }
+        for value in &values {
+            validate_label_value(value)?;
+        }
+        values.sort();
+        values.dedup();
+        return requirement(key, name, values);
+    }
+    Err(format!("unable to parse requirement: unknown operator in {:?}", term))
+}
+
+fn validate_label_key(key: &str) -> Result<(), String> {
+    let (prefix, name) = match key.rsplit_once('/') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, key),
+    };
+    let valid_name = !name.is_empty()
+        && name.len() <= 63
+        && name.starts_with(|ch: char| ch.is_ascii_alphanumeric())
+        && name.ends_with(|ch: char| ch.is_ascii_alphanumeric())
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric() || "-_.".contains(ch));
+    let valid_prefix = prefix.is_none_or(|prefix| {
+        !prefix.is_empty()
+            && prefix.len() <= 253
+            && prefix
+                .split('.')
+                .all(|label| !label.is_empty() && label.chars().all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-'))
+    });
+    if valid_name && valid_prefix {
+        Ok(())
+    } else {
+        Err(format!("invalid label key {:?}", key))
+    }
+}
+
+fn validate_label_value(value: &str) -> Result<(), String> {
+    let valid = value.is_empty()
+        || (value.len() <= 63
+            && value.starts_with(|ch: char| ch.is_ascii_alphanumeric())
+            && value.ends_with(|ch: char| ch.is_ascii_alphanumeric())
+            && value.chars().all(|ch| ch.is_ascii_alphanumeric() || "-_.".contains(ch)));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid label value: {:?}", value))
+    }
+}
+
+/// Parses a field selector: `=`, `==` and `!=` requirements separated by
+/// commas, with `\`-escaped `\`, `,` and `=` in values. Requirements are
+/// sorted, as Go sorts the terms it parses.
+pub fn parse_field_selector(selector: &str) -> Result<Vec<FieldSelectorRequirement>, String> {
+    let mut terms = split_escaped(selector);
+    terms.sort();
+    let mut requirements = Vec::new();
+    for term in terms.into_iter().filter(|term| !term.is_empty()) {
+        let Some((key, operator, value)) = split_field_term(&term) else {
+            return Err(format!("invalid selector: '{}'; can't understand '{}'", selector, term));
+        };
+        requirements.push(FieldSelectorRequirement {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            values: vec![unescape_field_value(value)?],
+        });
+    }
+    Ok(requirements)
+}
+
+/// Splits at commas not escaped with `\`, keeping escapes.
+fn split_escaped(selector: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let mut escaped = false;
+    for ch in selector.chars() {
+        match ch {
+            ',' if !escaped => terms.push(std::mem::take(&mut term)),
+            ch => {
+                escaped = !escaped && ch == '\\';
+                term.push(ch);
+            }
+        }
+    }
+    terms.push(term);
+    terms
+}
+
+fn split_field_term(term: &str) -> Option<(&str, &'static str, &str)> {
+    for idx in 0..term.len() {
+        let remaining = term.get(idx..)?;
+        for (operator, name) in [("!=", FIELD_SELECTOR_OP_NOT_IN), ("==", FIELD_SELECTOR_OP_IN), ("=", FIELD_SELECTOR_OP_IN)] {
+            if let Some(value) = remaining.strip_prefix(operator) {
+                return Some((&term[..idx], name, value));
+            }
+        }
+    }
+    None
+}
+
+fn unescape_field_value(value: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(escaped @ ('\\' | ',' | '=')) => unescaped.push(escaped),
+                Some(other) => return Err(format!("invalid escape sequence: \\{}", other)),
+                None => return Err("invalid escape sequence: trailing \\".to_string()),
+            },
+            ',' | '=' => return Err(format!("invalid selector value {:?}: unescaped {:?}", value, ch)),
+            ch => unescaped.push(ch),
+        }
+    }
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(key: &str, operator: &str, values: &[&str]) -> LabelSelectorRequirement {
+        LabelSelectorRequirement {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_label_selector() {
+        assert_eq!(parse_label_selector(" ").unwrap(), []);
+        assert_eq!(
+            parse_label_selector("app=web, tier in (front, back),!canary,env!=prod,example.com/owner,gen>2,x==").unwrap(),
+            [
+                label("app", "In", &["web"]),
+                label("tier", "In", &["back", "front"]),
+                label("canary", "DoesNotExist", &[]),
+                label("env", "NotIn", &["prod"]),
+                label("example.com/owner", "Exists", &[]),
+                label("gen", "Gt", &["2"]),
+                label("x", "In", &[""]),
+            ]
+        );
+        assert_eq!(parse_label_selector("a notin (x)").unwrap(), [label("a", "NotIn", &["x"])]);
+        for invalid in ["a in ()", "a in x", "-a", "a=b c", "a,,b", "a~b", "a>x"] {
+            assert!(parse_label_selector(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_field_selector() {
+        let field = |key: &str, operator: &str, value: &str| FieldSelectorRequirement {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            values: vec![value.to_string()],
+        };
+        assert_eq!(parse_field_selector("").unwrap(), []);
+        assert_eq!(
+            parse_field_selector("status.phase!=Running,spec.nodeName==node-1,metadata.name=a\\,b\\=c").unwrap(),
+            [
+                field("metadata.name", "In", "a,b=c"),
+                field("spec.nodeName", "In", "node-1"),
+                field("status.phase", "NotIn", "Running"),
+            ]
+        );
+        assert_eq!(
+            parse_field_selector("spec.nodeName").unwrap_err(),
+            "invalid selector: 'spec.nodeName'; can't understand 'spec.nodeName'"
+        );
+        assert!(parse_field_selector("a=b=c").is_err());
+        assert!(parse_field_selector("a=\\x").is_err());
+    }
+}
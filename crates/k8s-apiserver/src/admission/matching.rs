@@ -0,0 +1,340 @@
+//! Matching requests against admission rules
+//!
+//! Admission policies and webhooks select the requests they apply to with
+//! operation/resource rules, namespace and object label selectors and a
+//! match policy. This module implements those checks the way the API
+//! server does, including `matchPolicy: Equivalent`, under which a rule for
+//! one version of a resource also matches requests made through the other
+//! versions it is served as.
+
+use std::collections::BTreeMap;
+
+use k8s_api::admission::v1::{GroupVersionKind, GroupVersionResource, OPERATION_CREATE, OPERATION_UPDATE};
+use k8s_api::admissionregistration::v1::{
+    MatchResources, NamedRuleWithOperations, RuleWithOperations, MATCH_POLICY_EXACT, OPERATION_ALL,
+    SCOPE_ALL, SCOPE_CLUSTER, SCOPE_NAMESPACED,
+};
+use k8s_apimachinery::apis::meta::v1::LabelSelector;
+
+use super::Attributes;
+
+/// MatchError is returned when whether a request matches cannot be decided.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum MatchError {
+    #[error("namespace \"{0}\" not found")]
+    NamespaceNotFound(String),
+    #[error("{0}")]
+    InvalidSelector(String),
+}
+
+/// The resource and kind a request matched as. They differ from those of
+/// the request when it matched through an equivalent resource.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchedResource {
+    pub resource: GroupVersionResource,
+    pub kind: GroupVersionKind,
+}
+
+/// Returns the labels of an object, or none if it is absent.
+pub(crate) fn object_labels(object: Option<&serde_json::Value>) -> BTreeMap<String, String> {
+    object
+        .and_then(|object| object.pointer("/metadata/labels"))
+        .and_then(|labels| labels.as_object())
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_namespace_resource(resource: &GroupVersionResource) -> bool {
+    resource.group.is_empty() && resource.version == "v1" && resource.resource == "namespaces"
+}
+
+/// Reports whether the namespace of the request matches `selector`.
+/// Requests for cluster-scoped resources other than namespaces always
+/// match, and a namespace is matched by its own labels. An unset selector
+/// matches everything, as it does once defaulted.
+pub fn matches_namespace_selector(
+    selector: Option<&LabelSelector>,
+    attributes: &Attributes,
+) -> Result<bool, MatchError> {
+    let request = &attributes.request;
+    if request.namespace.is_empty() && request.resource.resource != "namespaces" {
+        return Ok(true);
+    }
+    let Some(selector) = selector.filter(|selector| !selector.is_empty()) else {
+        return Ok(true);
+    };
+    // A namespace being created or updated is not stored yet, so its labels
+    // come from the object under admission.
+    let labels = if request.resource.resource == "namespaces"
+        && request.sub_resource.is_empty()
+        && (request.operation == OPERATION_CREATE || request.operation == OPERATION_UPDATE)
+    {
+        object_labels(request.object.as_ref())
+    } else {
+        match &attributes.namespace_object {
+            Some(namespace) => object_labels(Some(namespace)),
+            None => return Err(MatchError::NamespaceNotFound(request.namespace.clone())),
+        }
+    };
+    selector.matches(&labels).map_err(MatchError::InvalidSelector)
+}
+
+/// Reports whether the object or the old object of the request matches
+/// `selector`. An unset selector matches everything.
+pub fn matches_object_selector(
+    selector: Option<&LabelSelector>,
+    attributes: &Attributes,
+) -> Result<bool, MatchError> {
+    let Some(selector) = selector.filter(|selector| !selector.is_empty()) else {
+        return Ok(true);
+    };
+    let request = &attributes.request;
+    for object in [&request.object, &request.old_object].into_iter().flatten() {
+        if selector.matches(&object_labels(Some(object))).map_err(MatchError::InvalidSelector)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Reports whether `rule` matches the request made to `resource`.
+/// `resource` is the request resource, or one equivalent to it.
+pub fn rule_matches(rule: &RuleWithOperations, attributes: &Attributes, resource: &GroupVersionResource) -> bool {
+    let request = &attributes.request;
+    let scope = match rule.scope.as_deref() {
+        None | Some(SCOPE_ALL) => true,
+        // Namespaces are cluster-scoped, although requests for them carry
+        // the namespace name.
+        Some(SCOPE_NAMESPACED) => !is_namespace_resource(resource) && !request.namespace.is_empty(),
+        Some(SCOPE_CLUSTER) => is_namespace_resource(resource) || request.namespace.is_empty(),
+        Some(_) => false,
+    };
+    let any = |values: &[String], value: &str| values.iter().any(|v| v == "*" || v == value);
+    scope
+        && rule
+            .operations
+            .iter()
+            .any(|op| op == OPERATION_ALL || *op == request.operation)
+        && any(&rule.api_groups, &resource.group)
+        && any(&rule.api_versions, &resource.version)
+        && rule.resources.iter().any(|pattern| {
+            let (res, sub) = pattern.split_once('/').unwrap_or((pattern, ""));
+            (res == "*" || res == resource.resource) && (sub == "*" || sub == request.sub_resource)
+        })
+}
+
+/// Finds the resource the request matches `rules` as: the request
+/// resource if any rule accepts it, else, unless `match_policy` is `Exact`,
+/// the first equivalent resource accepted by the earliest rule. An unset
+/// match policy defaults to `Equivalent`.
+fn match_with_policy<R>(
+    rules: &[R],
+    match_policy: Option<&str>,
+    attributes: &Attributes,
+    matches: impl Fn(&R, &GroupVersionResource) -> bool,
+) -> Option<MatchedResource> {
+    let request = &attributes.request;
+    if rules.iter().any(|rule| matches(rule, &request.resource)) {
+        return Some(MatchedResource {
+            resource: request.resource.clone(),
+            kind: request.kind.clone(),
+        });
+    }
+    if match_policy == Some(MATCH_POLICY_EXACT) {
+        return None;
+    }
+    let equivalents = || {
+        attributes
+            .equivalent_resources
+            .iter()
+            .filter(|(resource, _)| *resource != request.resource)
+    };
+    rules.iter().find_map(|rule| {
+        equivalents()
+            .find(|(resource, _)| matches(rule, resource))
+            .map(|(resource, kind)| MatchedResource {
+                resource: resource.clone(),
+                kind: kind.clone(),
+            })
+    })
+}
+
+/// Finds the resource the request matches the rules of a webhook as.
+pub fn matches_rules(
+    rules: &[RuleWithOperations],
+    match_policy: Option<&str>,
+    attributes: &Attributes,
+) -> Option<MatchedResource> {
+    match_with_policy(rules, match_policy, attributes, |rule, resource| {
+        rule_matches(rule, attributes, resource)
+    })
+}
+
+/// Finds the resource the request matches the rules of a policy as. Rules
+/// listing `resourceNames` only match requests for those names.
+pub fn matches_resource_rules(
+    rules: &[NamedRuleWithOperations],
+    match_policy: Option<&str>,
+    attributes: &Attributes,
+) -> Option<MatchedResource> {
+    match_with_policy(rules, match_policy, attributes, |rule, resource| {
+        rule_matches(&rule.rule_with_operations, attributes, resource)
+            && (rule.resource_names.is_empty() || rule.resource_names.contains(&attributes.request.name))
+    })
+}
+
+/// Reports whether the request matches the `matchResources` of a policy or
+/// binding, and the resource it matched as. Empty `resourceRules` match
+/// every resource. Selector errors are only reported for requests the
+/// rules would otherwise match.
+pub fn matches(criteria: &MatchResources, attributes: &Attributes) -> Result<Option<MatchedResource>, MatchError> {
+    let namespace = matches_namespace_selector(criteria.namespace_selector.as_ref(), attributes);
+    if let Ok(false) = namespace {
+        return Ok(None);
+    }
+    let object = matches_object_selector(criteria.object_selector.as_ref(), attributes);
+    if let Ok(false) = object {
+        return Ok(None);
+    }
+    let match_policy = criteria.match_policy.as_deref();
+    if matches_resource_rules(&criteria.exclude_resource_rules, match_policy, attributes).is_some() {
+        return Ok(None);
+    }
+    let matched = if criteria.resource_rules.is_empty() {
+        Some(MatchedResource {
+            resource: attributes.request.resource.clone(),
+            kind: attributes.request.kind.clone(),
+        })
+    } else {
+        matches_resource_rules(&criteria.resource_rules, match_policy, attributes)
+    };
+    if matched.is_none() {
+        return Ok(None);
+    }
+    namespace?;
+    object?;
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_api::admission::v1::AdmissionRequest;
+    use serde_json::json;
+
+    fn attributes(operation: &str, resource: GroupVersionResource, namespace: &str) -> Attributes {
+        Attributes::new(AdmissionRequest {
+            kind: GroupVersionKind::new(&resource.group, &resource.version, "Deployment"),
+            resource,
+            namespace: namespace.to_string(),
+            name: "web".to_string(),
+            operation: operation.to_string(),
+            object: Some(json!({"metadata": {"name": "web", "labels": {"app": "web"}}})),
+            ..Default::default()
+        })
+    }
+
+    fn rule(resources: &[&str], scope: Option<&str>) -> RuleWithOperations {
+        RuleWithOperations {
+            operations: vec!["CREATE".to_string()],
+            api_groups: vec!["apps".to_string()],
+            api_versions: vec!["*".to_string()],
+            resources: resources.iter().map(|r| r.to_string()).collect(),
+            scope: scope.map(str::to_string),
+        }
+    }
+
+    fn selector(key: &str, value: &str) -> LabelSelector {
+        LabelSelector {
+            match_labels: [(key.to_string(), value.to_string())].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let deployments = GroupVersionResource::new("apps", "v1", "deployments");
+        let attrs = attributes("CREATE", deployments.clone(), "default");
+        assert!(rule_matches(&rule(&["deployments"], None), &attrs, &deployments));
+        assert!(rule_matches(&rule(&["*"], Some("Namespaced")), &attrs, &deployments));
+        assert!(!rule_matches(&rule(&["*"], Some("Cluster")), &attrs, &deployments));
+        assert!(!rule_matches(&rule(&["*/scale"], None), &attrs, &deployments));
+
+        let mut scale = attrs.clone();
+        scale.request.sub_resource = "scale".to_string();
+        assert!(!rule_matches(&rule(&["*"], None), &scale, &deployments));
+        assert!(rule_matches(&rule(&["*/*"], None), &scale, &deployments));
+        assert!(rule_matches(&rule(&["deployments/scale"], None), &scale, &deployments));
+
+        let mut update = attrs.clone();
+        update.request.operation = "UPDATE".to_string();
+        assert!(!rule_matches(&rule(&["deployments"], None), &update, &deployments));
+    }
+
+    #[test]
+    fn test_matches_equivalent_resources() {
+        let v1 = GroupVersionResource::new("apps", "v1", "deployments");
+        let v1beta1 = GroupVersionResource::new("apps", "v1beta1", "deployments");
+        let attrs = attributes("CREATE", v1.clone(), "default")
+            .with_equivalent_resource(v1beta1.clone(), GroupVersionKind::new("apps", "v1beta1", "Deployment"));
+        let mut only_beta = rule(&["deployments"], None);
+        only_beta.api_versions = vec!["v1beta1".to_string()];
+        let mut criteria = MatchResources {
+            resource_rules: vec![NamedRuleWithOperations {
+                resource_names: Vec::new(),
+                rule_with_operations: only_beta,
+            }],
+            ..Default::default()
+        };
+        let matched = matches(&criteria, &attrs).unwrap().unwrap();
+        assert_eq!(matched.resource, v1beta1);
+        assert_eq!(matched.kind.version, "v1beta1");
+
+        criteria.match_policy = Some("Exact".to_string());
+        assert_eq!(matches(&criteria, &attrs), Ok(None));
+    }
+
+    #[test]
+    fn test_matches_selectors() {
+        let deployments = GroupVersionResource::new("apps", "v1", "deployments");
+        let attrs = attributes("CREATE", deployments, "default")
+            .with_namespace_object(json!({"metadata": {"name": "default", "labels": {"env": "prod"}}}));
+        let mut criteria = MatchResources {
+            namespace_selector: Some(selector("env", "prod")),
+            object_selector: Some(selector("app", "web")),
+            ..Default::default()
+        };
+        assert!(matches(&criteria, &attrs).unwrap().is_some());
+
+        criteria.object_selector = Some(selector("app", "db"));
+        assert_eq!(matches(&criteria, &attrs), Ok(None));
+
+        criteria.object_selector = None;
+        criteria.exclude_resource_rules = vec![NamedRuleWithOperations {
+            resource_names: vec!["web".to_string()],
+            rule_with_operations: rule(&["deployments"], None),
+        }];
+        assert_eq!(matches(&criteria, &attrs), Ok(None));
+
+        let mut missing = attrs.clone();
+        missing.namespace_object = None;
+        assert_eq!(
+            matches_namespace_selector(criteria.namespace_selector.as_ref(), &missing),
+            Err(MatchError::NamespaceNotFound("default".to_string()))
+        );
+
+        let namespace = Attributes::new(AdmissionRequest {
+            resource: GroupVersionResource::new("", "v1", "namespaces"),
+            namespace: "team-a".to_string(),
+            operation: "CREATE".to_string(),
+            object: Some(json!({"metadata": {"name": "team-a", "labels": {"env": "prod"}}})),
+            ..Default::default()
+        });
+        assert_eq!(matches_namespace_selector(criteria.namespace_selector.as_ref(), &namespace), Ok(true));
+    }
+}
@@ -0,0 +1,105 @@
+//! Admission control
+//!
+//! Admission plugins see a request through `Attributes`: the
+//! `AdmissionRequest` itself plus what the API server knows about it that
+//! the request does not carry, such as the namespace it is in and the other
+//! versions its resource is served as.
+
+pub mod matching;
+pub mod policy;
+pub mod webhook;
+
+use std::fmt;
+use std::sync::Arc;
+
+use k8s_api::admission::v1::{AdmissionRequest, GroupVersionKind, GroupVersionResource};
+use k8s_cel::{AuthorizationAttributes, AuthorizationDecision, Authorizer};
+
+/// Attributes of a request under admission.
+#[derive(Clone)]
+pub struct Attributes {
+    pub request: AdmissionRequest,
+    /// The namespace object the request is in. Namespace selectors and the
+    /// `namespaceObject` variable of policies read it; it is unused for
+    /// cluster-scoped requests.
+    pub namespace_object: Option<serde_json::Value>,
+    /// Other resources serving the same objects as the request resource,
+    /// with their kinds, e.g. `extensions/v1beta1 deployments` for
+    /// `apps/v1 deployments`. Rules with `matchPolicy: Equivalent` match
+    /// requests through these.
+    pub equivalent_resources: Vec<(GroupVersionResource, GroupVersionKind)>,
+    /// Answers the `authorizer` checks of CEL expressions.
+    pub authorizer: Option<Arc<dyn Authorizer>>,
+}
+
+impl fmt::Debug for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attributes")
+            .field("request", &self.request)
+            .field("namespace_object", &self.namespace_object)
+            .field("equivalent_resources", &self.equivalent_resources)
+            .field("authorizer", &self.authorizer.is_some())
+            .finish()
+    }
+}
+
+impl Attributes {
+    pub fn new(request: AdmissionRequest) -> Self {
+        Self {
+            request,
+            namespace_object: None,
+            equivalent_resources: Vec::new(),
+            authorizer: None,
+        }
+    }
+
+    pub fn with_namespace_object(mut self, namespace: serde_json::Value) -> Self {
+        self.namespace_object = Some(namespace);
+        self
+    }
+
+    pub fn with_equivalent_resource(mut self, resource: GroupVersionResource, kind: GroupVersionKind) -> Self {
+        self.equivalent_resources.push((resource, kind));
+        self
+    }
+
+    pub fn with_authorizer(mut self, authorizer: Arc<dyn Authorizer>) -> Self {
+        self.authorizer = Some(authorizer);
+        self
+    }
+
+    /// Returns the authorizer to expose to CEL expressions. Checks that do
+    /// not name a user are made for the requesting user.
+    pub(crate) fn request_authorizer(&self) -> Option<Arc<dyn Authorizer>> {
+        let authorizer = self.authorizer.clone()?;
+        Some(Arc::new(RequestAuthorizer {
+            authorizer,
+            user: self.request.user_info.username.clone(),
+        }))
+    }
+
+    /// Returns the attributes of `authorizer.requestResource`.
+    pub(crate) fn request_resource_attributes(&self) -> AuthorizationAttributes {
+        AuthorizationAttributes {
+            group: self.request.resource.group.clone(),
+            resource: self.request.resource.resource.clone(),
+            subresource: self.request.sub_resource.clone(),
+            namespace: self.request.namespace.clone(),
+            name: self.request.name.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+struct RequestAuthorizer {
+    authorizer: Arc<dyn Authorizer>,
+    user: String,
+}
+
+impl Authorizer for RequestAuthorizer {
+    fn authorize(&self, attributes: &AuthorizationAttributes) -> AuthorizationDecision {
+        let mut attributes = attributes.clone();
+        attributes.user.get_or_insert_with(|| self.user.clone());
+        self.authorizer.authorize(&attributes)
+    }
+}
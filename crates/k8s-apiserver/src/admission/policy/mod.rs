@@ -0,0 +1,370 @@
+//! CEL admission policies
+//!
+//! What validating and mutating admission policies share: the environment
+//! their expressions are compiled in, composited `variables` evaluated on
+//! first use, the cost limits of evaluation, param lookup for bindings and
+//! `matchConditions`.
+
+pub mod mutating;
+pub mod validating;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use k8s_api::admissionregistration::v1::{
+    ParamKind, ParamRef, Variable, FAILURE_POLICY_IGNORE, PARAMETER_NOT_FOUND_ACTION_DENY,
+};
+use k8s_apimachinery::apis::meta::v1::{Status, StatusCause, StatusDetails};
+use k8s_cel::types::{AUTHORIZER_TYPE, RESOURCE_CHECK_TYPE};
+use k8s_cel::{Activation, CompileError, Env, EvalError, Program, StructType, Type, Value};
+
+use super::matching::MatchedResource;
+use super::Attributes;
+
+/// Maximum cost of evaluating a single expression.
+pub const PER_CALL_LIMIT: u64 = 1_000_000;
+
+/// Maximum cost of evaluating all the expressions of a policy for one
+/// binding and param.
+pub const RUNTIME_COST_BUDGET: u64 = 10_000_000;
+
+pub(crate) type Compiled = Result<Program, CompileError>;
+
+struct CompiledVariable {
+    name: String,
+    program: Compiled,
+}
+
+/// Compiles the expressions of a policy. Composited variables are compiled
+/// in order, and each is visible to the ones after it and to every other
+/// expression as `variables.<name>`.
+pub(crate) struct Compiler {
+    env: Env,
+    env_with_authorizer: Env,
+    variables: Vec<CompiledVariable>,
+}
+
+impl Compiler {
+    pub(crate) fn new(has_params: bool, variables: &[Variable]) -> Self {
+        Self::with_env(Env::new(), has_params, variables)
+    }
+
+    /// Returns a compiler for the expressions of a mutating policy, which
+    /// may also construct `Object` apply configurations and `JSONPatch`
+    /// operations.
+    pub(crate) fn mutating(has_params: bool, variables: &[Variable]) -> Self {
+        let json_patch = StructType::new("JSONPatch")
+            .with_field("op", Type::String)
+            .with_field("path", Type::String)
+            .with_field("from", Type::String)
+            .with_field("value", Type::Dyn);
+        let env = Env::new().with_dynamic_object_type("Object").with_type(json_patch);
+        Self::with_env(env, has_params, variables)
+    }
+
+    fn with_env(env: Env, has_params: bool, variables: &[Variable]) -> Self {
+        let mut env = env
+            .with_variable("object", Type::Dyn)
+            .with_variable("oldObject", Type::Dyn)
+            .with_variable("request", Type::Dyn)
+            .with_variable("namespaceObject", Type::Dyn);
+        if has_params {
+            env.add_variable("params", Type::Dyn);
+        }
+        let mut env_with_authorizer = env
+            .clone()
+            .with_variable("authorizer", Type::Opaque(AUTHORIZER_TYPE))
+            .with_variable("authorizer.requestResource", Type::Opaque(RESOURCE_CHECK_TYPE));
+        let mut compiled = Vec::with_capacity(variables.len());
+        for variable in variables {
+            let program = env_with_authorizer.compile(&variable.expression);
+            let ty = program.as_ref().map_or(Type::Dyn, |program| program.result_type().clone());
+            let name = format!("variables.{}", variable.name);
+            env.add_variable(name.clone(), ty.clone());
+            env_with_authorizer.add_variable(name, ty);
+            compiled.push(CompiledVariable {
+                name: variable.name.clone(),
+                program,
+            });
+        }
+        Self {
+            env,
+            env_with_authorizer,
+            variables: compiled,
+        }
+    }
+
+    /// Compiles an expression. Only validations, match conditions and
+    /// variables may use the `authorizer`.
+    pub(crate) fn compile(&self, expression: &str, authorizer: bool) -> Compiled {
+        if authorizer {
+            self.env_with_authorizer.compile(expression)
+        } else {
+            self.env.compile(expression)
+        }
+    }
+
+    /// Returns an evaluator binding the variables of the policy for a
+    /// request, with a fresh cost budget.
+    pub(crate) fn evaluator(
+        &self,
+        attributes: &Attributes,
+        matched: &MatchedResource,
+        params: Option<&serde_json::Value>,
+    ) -> Evaluator<'_> {
+        let json = |value: Option<&serde_json::Value>| value.map_or(Value::Null, Value::from_json);
+        let request = &attributes.request;
+        let mut values = HashMap::from([
+            ("object".to_string(), json(request.object.as_ref())),
+            ("oldObject".to_string(), json(request.old_object.as_ref())),
+            ("request".to_string(), request_value(attributes, matched)),
+            ("params".to_string(), json(params)),
+        ]);
+        let namespace = match request.namespace.is_empty() {
+            true => Value::Null,
+            false => json(attributes.namespace_object.as_ref()),
+        };
+        values.insert("namespaceObject".to_string(), namespace);
+        if let Some(authorizer) = attributes.request_authorizer() {
+            values.insert(
+                "authorizer.requestResource".to_string(),
+                Value::authorizer_request_resource(authorizer.clone(), attributes.request_resource_attributes()),
+            );
+            values.insert("authorizer".to_string(), Value::authorizer(authorizer));
+        }
+        Evaluator {
+            activation: PolicyActivation {
+                values,
+                variables: &self.variables,
+                cache: RefCell::default(),
+            },
+            remaining: RUNTIME_COST_BUDGET,
+        }
+    }
+}
+
+/// Returns the `request` variable: the admission request without its
+/// objects, for the resource and kind it matched as.
+fn request_value(attributes: &Attributes, matched: &MatchedResource) -> Value {
+    let original = &attributes.request;
+    let mut request = original.clone();
+    request.uid = String::new();
+    request.object = None;
+    request.old_object = None;
+    request.kind = matched.kind.clone();
+    request.resource = matched.resource.clone();
+    request.request_kind = Some(original.kind.clone());
+    request.request_resource = Some(original.resource.clone());
+    request.request_sub_resource = original.sub_resource.clone();
+    request.dry_run = Some(original.dry_run.unwrap_or(false));
+    serde_json::to_value(&request).map_or(Value::Null, |json| Value::from_json(&json))
+}
+
+struct PolicyActivation<'a> {
+    values: HashMap<String, Value>,
+    variables: &'a [CompiledVariable],
+    cache: RefCell<HashMap<String, Result<Value, EvalError>>>,
+}
+
+impl Activation for PolicyActivation<'_> {
+    fn resolve(&self, name: &str) -> Option<Result<Value, EvalError>> {
+        let Some(name) = name.strip_prefix("variables.") else {
+            return self.values.get(name).cloned().map(Ok);
+        };
+        if let Some(result) = self.cache.borrow().get(name) {
+            return Some(result.clone());
+        }
+        let variable = self.variables.iter().find(|variable| variable.name == name)?;
+        let result = match &variable.program {
+            Ok(program) => program
+                .evaluate_with_cost_limit(self, PER_CALL_LIMIT)
+                .map(|(value, _)| value)
+                .map_err(|err| EvalError::new(format!("composited variable \"{}\" fails to evaluate: {}", name, err))),
+            Err(err) => Err(EvalError::new(format!(
+                "composited variable \"{}\" fails to compile: {}",
+                name, err
+            ))),
+        };
+        self.cache.borrow_mut().insert(name.to_string(), result.clone());
+        Some(result)
+    }
+}
+
+/// EvaluationError is returned when an expression cannot be evaluated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum EvaluationError {
+    Failed(String),
+    /// The expressions evaluated so far used up the cost budget.
+    OutOfBudget,
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvaluationError::Failed(message) => f.write_str(message),
+            EvaluationError::OutOfBudget => f.write_str(
+                "validation failed due to running out of cost budget, no further validation rules will be run",
+            ),
+        }
+    }
+}
+
+/// Evaluates the expressions of a policy for one request, charging them to
+/// a shared cost budget.
+pub(crate) struct Evaluator<'a> {
+    activation: PolicyActivation<'a>,
+    remaining: u64,
+}
+
+impl Evaluator<'_> {
+    pub(crate) fn evaluate(&mut self, compiled: &Compiled) -> Result<Value, EvaluationError> {
+        let program = compiled
+            .as_ref()
+            .map_err(|err| EvaluationError::Failed(format!("compilation error: {}", err)))?;
+        let (value, cost) = program
+            .evaluate_with_cost_limit(&self.activation, PER_CALL_LIMIT.min(self.remaining))
+            .map_err(|err| match err {
+                EvalError::CostLimitExceeded if self.remaining < PER_CALL_LIMIT => EvaluationError::OutOfBudget,
+                err => EvaluationError::Failed(format!(
+                    "expression '{}' resulted in error: {}",
+                    program.source(),
+                    err
+                )),
+            })?;
+        self.remaining -= cost;
+        Ok(value)
+    }
+
+    /// Evaluates `matchConditions`, given as name and compiled expression.
+    /// The request matches unless a condition is false. Conditions that
+    /// fail to evaluate deny the request under failure policy `Fail` and
+    /// skip it under `Ignore`.
+    pub(crate) fn match_conditions<'c>(
+        &mut self,
+        conditions: impl IntoIterator<Item = &'c Compiled>,
+        failure_policy: Option<&str>,
+    ) -> Result<bool, String> {
+        let mut errors = Vec::new();
+        for condition in conditions {
+            match self.evaluate(condition) {
+                Ok(Value::Bool(false)) => return Ok(false),
+                Ok(_) => {}
+                Err(EvaluationError::OutOfBudget) => {
+                    errors = vec![EvaluationError::OutOfBudget.to_string()];
+                    break;
+                }
+                Err(err) => errors.push(err.to_string()),
+            }
+        }
+        match errors.len() {
+            0 => Ok(true),
+            _ if failure_policy == Some(FAILURE_POLICY_IGNORE) => Ok(false),
+            1 => Err(errors.remove(0)),
+            _ => Err(format!("[{}]", errors.join(", "))),
+        }
+    }
+}
+
+/// Collects the params a binding evaluates its policy with. A policy
+/// without a `paramKind` is evaluated once, with null params.
+pub(crate) fn collect_params<'p>(
+    param_kind: Option<&ParamKind>,
+    param_ref: Option<&ParamRef>,
+    namespace: &str,
+    params: &'p [serde_json::Value],
+) -> Result<Vec<Option<&'p serde_json::Value>>, String> {
+    let Some(param_kind) = param_kind else {
+        return Ok(vec![None]);
+    };
+    let Some(param_ref) = param_ref else {
+        return Err("paramRef is required when paramKind is set".to_string());
+    };
+    let namespace = if param_ref.namespace.is_empty() {
+        namespace
+    } else {
+        param_ref.namespace.as_str()
+    };
+    let field = |param: &serde_json::Value, pointer: &str| {
+        param.pointer(pointer).and_then(|value| value.as_str()).unwrap_or_default().to_string()
+    };
+    let mut found = Vec::new();
+    for param in params {
+        if field(param, "/apiVersion") != param_kind.api_version || field(param, "/kind") != param_kind.kind {
+            continue;
+        }
+        let param_namespace = field(param, "/metadata/namespace");
+        if !param_namespace.is_empty() && param_namespace != namespace {
+            continue;
+        }
+        let selected = if !param_ref.name.is_empty() {
+            field(param, "/metadata/name") == param_ref.name
+        } else if let Some(selector) = &param_ref.selector {
+            selector.matches(&super::matching::object_labels(Some(param)))?
+        } else {
+            false
+        };
+        if selected {
+            found.push(Some(param));
+        }
+    }
+    if found.is_empty() && param_ref.parameter_not_found_action.as_deref() == Some(PARAMETER_NOT_FOUND_ACTION_DENY) {
+        return Err("no params found for policy binding with `Deny` parameterNotFoundAction".to_string());
+    }
+    Ok(found)
+}
+
+/// Returns the HTTP status code for the `reason` of a policy denial.
+fn reason_to_code(reason: &str) -> i32 {
+    match reason {
+        "Forbidden" => 403,
+        "Unauthorized" => 401,
+        "RequestEntityTooLarge" => 413,
+        _ => 422,
+    }
+}
+
+/// Returns the Forbidden error denying the request with `message`, with the
+/// code of `reason`.
+pub(crate) fn forbidden(attributes: &Attributes, message: String, reason: &str) -> Status {
+    let request = &attributes.request;
+    let name = match request.name.as_str() {
+        "" => request
+            .object
+            .as_ref()
+            .and_then(|object| object.pointer("/metadata/name"))
+            .and_then(|name| name.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        name => name.to_string(),
+    };
+    let resource = &request.resource;
+    let qualified = match resource.group.as_str() {
+        "" => resource.resource.clone(),
+        group => format!("{}.{}", resource.resource, group),
+    };
+    let full_message = if qualified.is_empty() {
+        format!("forbidden: {}", message)
+    } else if name.is_empty() {
+        format!("{} is forbidden: {}", qualified, message)
+    } else {
+        format!("{} {:?} is forbidden: {}", qualified, name, message)
+    };
+    Status {
+        status: "Failure".to_string(),
+        message: full_message,
+        code: reason_to_code(reason),
+        reason: reason.to_string(),
+        details: Some(StatusDetails {
+            name,
+            group: resource.group.clone(),
+            kind: resource.resource.clone(),
+            causes: vec![StatusCause {
+                message,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,623 @@
+//! MutatingAdmissionPolicy evaluation
+//!
+//! `mutate` applies a set of policies to the object of a request, the way
+//! the API server's MutatingAdmissionPolicy plugin does. Policies and their
+//! bindings match requests as validating policies do; every matching
+//! binding and param evaluates the policy's `mutations` in order, each
+//! seeing the object as mutated so far. An `ApplyConfiguration` mutation
+//! merges the `Object` it evaluates to into the object, a `JSONPatch`
+//! mutation applies the operations it evaluates to.
+//!
+//! Policies with `reinvocationPolicy: IfNeeded` are evaluated once more if
+//! policies after them changed the object.
+
+use k8s_api::admission::v1::AdmissionResponse;
+use k8s_api::admissionregistration::v1;
+use k8s_api::admissionregistration::v1alpha1::{
+    self, MutatingAdmissionPolicy, MutatingAdmissionPolicyBinding,
+    MutatingAdmissionPolicySpec, FAILURE_POLICY_IGNORE, PATCH_TYPE_APPLY_CONFIGURATION, PATCH_TYPE_JSON_PATCH,
+    REINVOCATION_POLICY_IF_NEEDED,
+};
+use k8s_apimachinery::apis::meta::v1::Status;
+use k8s_apimachinery::util::jsonpatch::{self, Patch, PatchOperation};
+use k8s_cel::Value;
+use serde_json::Map;
+
+use super::{collect_params, Compiled, Compiler};
+use crate::admission::matching::{self, MatchedResource};
+use crate::admission::Attributes;
+
+/// MutationOutcome is the result of admitting a request against
+/// MutatingAdmissionPolicies.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MutationOutcome {
+    /// The mutated object. It is the object of the request if no policy
+    /// changed it or the request was denied.
+    pub object: Option<serde_json::Value>,
+    /// The patch turning the object of the request into `object`.
+    pub patch: Patch,
+    /// Policies evaluated a second time because policies after them
+    /// changed the object.
+    pub reinvoked: Vec<String>,
+    /// The error returned to the client if a policy failing under failure
+    /// policy `Fail` denies the request.
+    pub denied: Option<Status>,
+}
+
+impl MutationOutcome {
+    pub fn allowed(&self) -> bool {
+        self.denied.is_none()
+    }
+
+    /// Returns the outcome as the response to the admission request `uid`.
+    pub fn to_response(&self, uid: impl Into<String>) -> AdmissionResponse {
+        let response = match &self.denied {
+            Some(status) => AdmissionResponse::deny(status.clone()),
+            None => AdmissionResponse::allow().with_patch(&self.patch),
+        };
+        AdmissionResponse {
+            uid: uid.into(),
+            ..response
+        }
+    }
+}
+
+/// A policy with its expressions compiled and its match types converted to
+/// those the matcher works with.
+struct CompiledPolicy<'a> {
+    name: &'a str,
+    spec: &'a MutatingAdmissionPolicySpec,
+    fail: bool,
+    param_kind: Option<v1::ParamKind>,
+    match_constraints: v1::MatchResources,
+    compiler: Compiler,
+    match_conditions: Vec<Compiled>,
+    mutations: Vec<CompiledMutation>,
+}
+
+enum CompiledMutation {
+    ApplyConfiguration(Compiled),
+    JsonPatch(Compiled),
+    Invalid(String),
+}
+
+/// Admits the request described by `attributes` against `policies`.
+/// Bindings refer to their policy by name, and `params` holds the objects
+/// bindings with a `paramRef` select from. Requests without an object,
+/// such as DELETE, are not mutated.
+pub fn mutate(
+    attributes: &Attributes,
+    policies: &[MutatingAdmissionPolicy],
+    bindings: &[MutatingAdmissionPolicyBinding],
+    params: &[serde_json::Value],
+) -> MutationOutcome {
+    let original = attributes.request.object.clone();
+    let mut outcome = MutationOutcome {
+        object: original.clone(),
+        ..Default::default()
+    };
+    let Some(mut object) = original.clone() else {
+        return outcome;
+    };
+
+    let mut compiled = Vec::new();
+    for policy in policies {
+        let Some(spec) = &policy.spec else {
+            continue;
+        };
+        let Some(constraints) = &spec.match_constraints else {
+            continue;
+        };
+        compiled.push(compile(&policy.metadata.name, spec, constraints));
+    }
+
+    // The object each IfNeeded policy left behind, to tell whether later
+    // policies changed it.
+    let mut reinvocations = Vec::new();
+    for policy in &compiled {
+        let before = object.clone();
+        if let Err(message) = apply_policy(attributes, policy, bindings, params, &mut object) {
+            outcome.denied = Some(super::forbidden(attributes, message, "Forbidden"));
+            return outcome;
+        }
+        if policy.spec.reinvocation_policy == REINVOCATION_POLICY_IF_NEEDED && object != before {
+            reinvocations.push((policy, object.clone()));
+        }
+    }
+    for (policy, after) in reinvocations {
+        if object == after {
+            continue;
+        }
+        outcome.reinvoked.push(policy.name.to_string());
+        if let Err(message) = apply_policy(attributes, policy, bindings, params, &mut object) {
+            outcome.denied = Some(super::forbidden(attributes, message, "Forbidden"));
+            return outcome;
+        }
+    }
+
+    outcome.patch = jsonpatch::diff(original.as_ref().unwrap_or(&serde_json::Value::Null), &object);
+    outcome.object = Some(object);
+    outcome
+}
+
+fn compile<'a>(
+    name: &'a str,
+    spec: &'a MutatingAdmissionPolicySpec,
+    constraints: &v1alpha1::MatchResources,
+) -> CompiledPolicy<'a> {
+    let param_kind = spec.param_kind.as_ref().map(|kind| v1::ParamKind {
+        api_version: kind.api_version.clone(),
+        kind: kind.kind.clone(),
+    });
+    let variables = spec
+        .variables
+        .iter()
+        .map(|variable| v1::Variable {
+            name: variable.name.clone(),
+            expression: variable.expression.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    let compiler = Compiler::mutating(param_kind.is_some(), &variables);
+    let match_conditions = spec
+        .match_conditions
+        .iter()
+        .map(|condition| compiler.compile(&condition.expression, true))
+        .collect();
+    let mutations = spec
+        .mutations
+        .iter()
+        .map(|mutation| match mutation.patch_type.as_str() {
+            PATCH_TYPE_APPLY_CONFIGURATION => match &mutation.apply_configuration {
+                Some(apply) => match compiler.compile(&apply.expression, false) {
+                    // Objects of the dynamic `Object` types check as dyn;
+                    // anything else, such as a map literal, is not an
+                    // apply configuration.
+                    Ok(program) if !program.result_type().is_dyn_or_error() => CompiledMutation::Invalid(format!(
+                        "ApplyConfiguration expression must evaluate to an Object, got {}",
+                        program.result_type()
+                    )),
+                    compiled => CompiledMutation::ApplyConfiguration(compiled),
+                },
+                None => CompiledMutation::Invalid("applyConfiguration is required for patchType ApplyConfiguration".to_string()),
+            },
+            PATCH_TYPE_JSON_PATCH => match &mutation.json_patch {
+                Some(patch) => CompiledMutation::JsonPatch(compiler.compile(&patch.expression, false)),
+                None => CompiledMutation::Invalid("jsonPatch is required for patchType JSONPatch".to_string()),
+            },
+            other => CompiledMutation::Invalid(format!("unsupported patchType {:?}", other)),
+        })
+        .collect();
+    CompiledPolicy {
+        name,
+        spec,
+        fail: spec.failure_policy.as_deref() != Some(FAILURE_POLICY_IGNORE),
+        param_kind,
+        match_constraints: match_resources(constraints),
+        compiler,
+        match_conditions,
+        mutations,
+    }
+}
+
+/// Returns the v1 equivalent of v1alpha1 match resources, which the
+/// matcher works with.
+fn match_resources(resources: &v1alpha1::MatchResources) -> v1::MatchResources {
+    let rules = |rules: &[v1alpha1::NamedRuleWithOperations]| {
+        rules
+            .iter()
+            .map(|rule| v1::NamedRuleWithOperations {
+                resource_names: rule.resource_names.clone(),
+                rule_with_operations: v1::RuleWithOperations {
+                    operations: rule.operations.clone(),
+                    api_groups: rule.api_groups.clone(),
+                    api_versions: rule.api_versions.clone(),
+                    resources: rule.resources.clone(),
+                    scope: rule.scope.clone(),
+                },
+            })
+            .collect()
+    };
+    v1::MatchResources {
+        namespace_selector: resources.namespace_selector.clone(),
+        object_selector: resources.object_selector.clone(),
+        resource_rules: rules(&resources.resource_rules),
+        exclude_resource_rules: rules(&resources.exclude_resource_rules),
+        match_policy: resources.match_policy.clone(),
+    }
+}
+
+/// Applies one policy through each of its bindings. Errors under failure
+/// policy `Ignore` leave the object as the failed binding found it, errors
+/// under `Fail` are returned as the message denying the request.
+fn apply_policy(
+    attributes: &Attributes,
+    policy: &CompiledPolicy<'_>,
+    bindings: &[MutatingAdmissionPolicyBinding],
+    params: &[serde_json::Value],
+    object: &mut serde_json::Value,
+) -> Result<(), String> {
+    let mut current = attributes.clone();
+    current.request.object = Some(object.clone());
+    let matched = match matching::matches(&policy.match_constraints, &current) {
+        Ok(Some(matched)) => matched,
+        Ok(None) => return Ok(()),
+        Err(err) if policy.fail => {
+            let message = format!("failed to configure policy: {}", err);
+            return Err(denial(policy.name, None, message));
+        }
+        Err(_) => return Ok(()),
+    };
+
+    for binding in bindings {
+        let Some(spec) = binding.spec.as_ref().filter(|spec| spec.policy_name == policy.name) else {
+            continue;
+        };
+        let binding_name = binding.metadata.name.as_str();
+        let result = apply_binding(&mut current, policy, &matched, spec, params);
+        match result {
+            Ok(mutated) => current.request.object = Some(mutated),
+            Err(message) if policy.fail => return Err(denial(policy.name, Some(binding_name), message)),
+            Err(_) => {}
+        }
+    }
+    if let Some(mutated) = current.request.object {
+        *object = mutated;
+    }
+    Ok(())
+}
+
+/// Evaluates the mutations of a policy for one binding, once per param,
+/// and returns the mutated object.
+fn apply_binding(
+    current: &mut Attributes,
+    policy: &CompiledPolicy<'_>,
+    matched: &MatchedResource,
+    spec: &v1alpha1::MutatingAdmissionPolicyBindingSpec,
+    params: &[serde_json::Value],
+) -> Result<serde_json::Value, String> {
+    let configure = |err: String| format!("failed to configure binding: {}", err);
+    if let Some(resources) = &spec.match_resources {
+        match matching::matches(&match_resources(resources), current) {
+            Ok(Some(_)) => {}
+            Ok(None) => return Ok(current.request.object.clone().unwrap_or_default()),
+            Err(err) => return Err(configure(err.to_string())),
+        }
+    }
+    let param_ref = spec.param_ref.as_ref().map(|param_ref| v1::ParamRef {
+        name: param_ref.name.clone(),
+        namespace: param_ref.namespace.clone(),
+        selector: param_ref.selector.clone(),
+        parameter_not_found_action: param_ref.parameter_not_found_action.clone(),
+    });
+    let selected = collect_params(
+        policy.param_kind.as_ref(),
+        param_ref.as_ref(),
+        &current.request.namespace,
+        params,
+    )
+    .map_err(configure)?;
+
+    let mut object = current.request.object.clone().unwrap_or_default();
+    for param in selected {
+        let mut evaluator = policy.compiler.evaluator(current, matched, param);
+        if !evaluator.match_conditions(&policy.match_conditions, policy.spec.failure_policy.as_deref())? {
+            continue;
+        }
+        // Each mutation sees the object as mutated so far, but all of them
+        // share the cost budget of the evaluation.
+        let mut remaining = evaluator.remaining;
+        for mutation in &policy.mutations {
+            current.request.object = Some(object.clone());
+            let mut evaluator = policy.compiler.evaluator(current, matched, param);
+            evaluator.remaining = remaining;
+            match mutation {
+                CompiledMutation::ApplyConfiguration(compiled) => {
+                    let value = evaluator.evaluate(compiled).map_err(|err| err.to_string())?;
+                    let Value::Map(_) = value else {
+                        return Err(format!(
+                            "ApplyConfiguration expression must evaluate to an Object, got {}",
+                            value.type_of()
+                        ));
+                    };
+                    let configuration = value.to_json().map_err(|err| err.to_string())?;
+                    merge(&mut object, configuration);
+                }
+                CompiledMutation::JsonPatch(compiled) => {
+                    let value = evaluator.evaluate(compiled).map_err(|err| err.to_string())?;
+                    let patch = json_patch(&value)?;
+                    jsonpatch::apply(&mut object, &patch).map_err(|err| format!("JSON Patch: {}", err))?;
+                }
+                CompiledMutation::Invalid(message) => return Err(message.clone()),
+            }
+            remaining = evaluator.remaining;
+        }
+    }
+    Ok(object)
+}
+
+/// Converts the result of a `JSONPatch` expression into patch operations.
+fn json_patch(value: &Value) -> Result<Patch, String> {
+    let Value::List(operations) = value else {
+        return Err(format!(
+            "JSONPatch expression must evaluate to a list of JSONPatch, got {}",
+            value.type_of()
+        ));
+    };
+    operations
+        .iter()
+        .map(|operation| {
+            let json = operation.to_json().map_err(|err| err.to_string())?;
+            serde_json::from_value::<PatchOperation>(json).map_err(|err| format!("invalid JSONPatch: {}", err))
+        })
+        .collect()
+}
+
+/// Merges an apply configuration into `object` the way server-side apply
+/// does without a schema: objects are merged field by field, a null field
+/// removes the field, and lists replace the list of the object. Lists of
+/// objects that all have a `name` are merged by name instead, as the named
+/// lists of the built-in types are, e.g. containers, volumes and env.
+pub fn merge(object: &mut serde_json::Value, configuration: serde_json::Value) {
+    use serde_json::Value as Json;
+    match (object, configuration) {
+        (Json::Object(fields), Json::Object(configured)) => {
+            for (key, value) in configured {
+                if value.is_null() {
+                    fields.remove(&key);
+                } else if let Some(field) = fields.get_mut(&key) {
+                    merge(field, value);
+                } else {
+                    fields.insert(key, value);
+                }
+            }
+        }
+        (Json::Array(items), Json::Array(configured)) if is_named_list(items) && is_named_list(&configured) => {
+            for value in configured {
+                let name = value.get("name").cloned();
+                match items.iter_mut().find(|item| item.get("name") == name.as_ref()) {
+                    Some(item) => merge(item, value),
+                    None => items.push(value),
+                }
+            }
+        }
+        (object, configuration) => *object = without_nulls(configuration),
+    }
+}
+
+fn is_named_list(items: &[serde_json::Value]) -> bool {
+    !items.is_empty() && items.iter().all(|item| item.get("name").is_some_and(|name| name.is_string()))
+}
+
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(fields) => serde_json::Value::Object(
+            fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, without_nulls(value)))
+                .collect::<Map<_, _>>(),
+        ),
+        value => value,
+    }
+}
+
+/// Returns the message denying a request for an error evaluating `policy`.
+fn denial(policy: &str, binding: Option<&str>, message: String) -> String {
+    match binding {
+        Some(binding) => format!(
+            "MutatingAdmissionPolicy '{}' with binding '{}' denied request: {}",
+            policy, binding, message
+        ),
+        None => format!("MutatingAdmissionPolicy '{}' denied request: {}", policy, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_api::admission::v1::{AdmissionRequest, GroupVersionKind, GroupVersionResource};
+    use k8s_api::admissionregistration::v1alpha1::{
+        ApplyConfiguration, MatchResources, MutatingAdmissionPolicyBindingSpec, Mutation, NamedRuleWithOperations,
+        JSONPatch, ParamKind, ParamRef, Variable,
+    };
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+    use serde_json::json;
+
+    fn request() -> Attributes {
+        Attributes::new(AdmissionRequest {
+            uid: "1".to_string(),
+            kind: GroupVersionKind::new("apps", "v1", "Deployment"),
+            resource: GroupVersionResource::new("apps", "v1", "deployments"),
+            name: "web".to_string(),
+            namespace: "default".to_string(),
+            operation: "CREATE".to_string(),
+            object: Some(json!({
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "metadata": {"name": "web", "namespace": "default", "labels": {"app": "web"}},
+                "spec": {
+                    "replicas": 1,
+                    "template": {"spec": {"containers": [{"name": "web", "image": "nginx"}]}},
+                },
+            })),
+            ..Default::default()
+        })
+        .with_namespace_object(json!({"metadata": {"name": "default"}}))
+    }
+
+    fn apply(expression: &str) -> Mutation {
+        Mutation {
+            patch_type: PATCH_TYPE_APPLY_CONFIGURATION.to_string(),
+            apply_configuration: Some(ApplyConfiguration {
+                expression: expression.to_string(),
+            }),
+            json_patch: None,
+        }
+    }
+
+    fn json_patch(expression: &str) -> Mutation {
+        Mutation {
+            patch_type: PATCH_TYPE_JSON_PATCH.to_string(),
+            apply_configuration: None,
+            json_patch: Some(JSONPatch {
+                expression: expression.to_string(),
+            }),
+        }
+    }
+
+    fn policy(name: &str, mutations: Vec<Mutation>) -> MutatingAdmissionPolicy {
+        MutatingAdmissionPolicy {
+            metadata: ObjectMeta::named(name),
+            spec: Some(MutatingAdmissionPolicySpec {
+                match_constraints: Some(MatchResources {
+                    resource_rules: vec![NamedRuleWithOperations {
+                        operations: vec!["CREATE".to_string()],
+                        api_groups: vec!["apps".to_string()],
+                        api_versions: vec!["v1".to_string()],
+                        resources: vec!["deployments".to_string()],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }),
+                mutations,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn binding(policy: &str) -> MutatingAdmissionPolicyBinding {
+        MutatingAdmissionPolicyBinding {
+            metadata: ObjectMeta::named(format!("{}-binding", policy)),
+            spec: Some(MutatingAdmissionPolicyBindingSpec {
+                policy_name: policy.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mutate_apply_configuration() {
+        let mut sidecar = policy(
+            "sidecar",
+            vec![
+                apply(
+                    "Object{spec: Object.spec{template: Object.spec.template{spec: Object.spec.template.spec{containers: [
+                        Object.spec.template.spec.containers{name: 'web', imagePullPolicy: 'Always'},
+                        Object.spec.template.spec.containers{name: params.data.sidecar, image: variables.image}]}}}}",
+                ),
+                apply("Object{metadata: Object.metadata{labels: {'app': null, 'injected': 'true'}}}"),
+            ],
+        );
+        let spec = sidecar.spec.as_mut().unwrap();
+        spec.param_kind = Some(ParamKind {
+            api_version: "v1".to_string(),
+            kind: "ConfigMap".to_string(),
+        });
+        spec.variables = vec![Variable {
+            name: "image".to_string(),
+            expression: "'envoy:' + params.data.version".to_string(),
+        }];
+        let mut binding = binding("sidecar");
+        binding.spec.as_mut().unwrap().param_ref = Some(ParamRef {
+            name: "sidecar".to_string(),
+            ..Default::default()
+        });
+        let params = [json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {"name": "sidecar", "namespace": "default"},
+            "data": {"sidecar": "proxy", "version": "1.30"},
+        })];
+
+        let outcome = mutate(&request(), &[sidecar], &[binding], &params);
+        assert!(outcome.allowed(), "{:?}", outcome.denied);
+        let object = outcome.object.as_ref().unwrap();
+        assert_eq!(object["metadata"]["labels"], json!({"injected": "true"}));
+        assert_eq!(
+            object["spec"]["template"]["spec"]["containers"],
+            json!([
+                {"name": "web", "image": "nginx", "imagePullPolicy": "Always"},
+                {"name": "proxy", "image": "envoy:1.30"},
+            ])
+        );
+        let mut patched = request().request.object.unwrap();
+        jsonpatch::apply(&mut patched, &outcome.patch).unwrap();
+        assert_eq!(&patched, object);
+
+        let response = outcome.to_response("1");
+        assert!(response.allowed);
+        assert_eq!(response.uid, "1");
+        assert!(response.patch.is_some());
+    }
+
+    #[test]
+    fn test_mutate_json_patch_and_failures() {
+        let labels = policy(
+            "labels",
+            vec![json_patch(
+                "[JSONPatch{op: 'add', path: '/metadata/labels/' + jsonpatch.escapeKey('example.com/owner'), value: request.userInfo.username},
+                  JSONPatch{op: 'replace', path: '/spec/replicas', value: object.spec.replicas + 2}]",
+            )],
+        );
+        let mut attributes = request();
+        attributes.request.user_info.username = "alice".to_string();
+        let outcome = mutate(&attributes, &[labels], &[binding("labels")], &[]);
+        let object = outcome.object.unwrap();
+        assert_eq!(object["metadata"]["labels"]["example.com/owner"], "alice");
+        assert_eq!(object["spec"]["replicas"], 3);
+        assert_eq!(
+            serde_json::to_value(&outcome.patch).unwrap(),
+            json!([
+                {"op": "add", "path": "/metadata/labels/example.com~1owner", "value": "alice"},
+                {"op": "replace", "path": "/spec/replicas", "value": 3},
+            ])
+        );
+
+        let broken = policy("broken", vec![json_patch("[JSONPatch{op: 'remove', path: '/spec/missing'}]")]);
+        let outcome = mutate(&request(), std::slice::from_ref(&broken), &[binding("broken")], &[]);
+        let status = outcome.denied.unwrap();
+        assert_eq!(status.code, 403);
+        assert_eq!(
+            status.message,
+            "deployments.apps \"web\" is forbidden: MutatingAdmissionPolicy 'broken' with binding 'broken-binding' \
+             denied request: JSON Patch: remove operation does not apply: doc is missing path: \"/spec/missing\""
+        );
+        assert_eq!(outcome.object, request().request.object);
+
+        let mut ignored = broken;
+        ignored.spec.as_mut().unwrap().failure_policy = Some(FAILURE_POLICY_IGNORE.to_string());
+        let outcome = mutate(&request(), &[ignored], &[binding("broken")], &[]);
+        assert!(outcome.allowed());
+        assert!(outcome.patch.is_empty());
+
+        let not_object = policy("not-object", vec![apply("{'spec': {}}")]);
+        let outcome = mutate(&request(), &[not_object], &[binding("not-object")], &[]);
+        let message = outcome.denied.unwrap().message;
+        assert!(message.ends_with("ApplyConfiguration expression must evaluate to an Object, got map(string, map(dyn, dyn))"), "{}", message);
+    }
+
+    #[test]
+    fn test_mutate_reinvocation() {
+        // `replicas` copies the replica count into a label, and `scale`
+        // changes the count after it.
+        let mut replicas = policy(
+            "replicas",
+            vec![apply("Object{metadata: Object.metadata{labels: {'replicas': string(object.spec.replicas)}}}")],
+        );
+        replicas.spec.as_mut().unwrap().reinvocation_policy = REINVOCATION_POLICY_IF_NEEDED.to_string();
+        let scale = policy("scale", vec![apply("Object{spec: Object.spec{replicas: 3}}")]);
+        let bindings = [binding("replicas"), binding("scale")];
+
+        let outcome = mutate(&request(), &[replicas.clone(), scale.clone()], &bindings, &[]);
+        assert_eq!(outcome.reinvoked, ["replicas"]);
+        assert_eq!(outcome.object.unwrap()["metadata"]["labels"]["replicas"], "3");
+
+        replicas.spec.as_mut().unwrap().reinvocation_policy = String::new();
+        let outcome = mutate(&request(), &[replicas, scale], &bindings, &[]);
+        assert!(outcome.reinvoked.is_empty());
+        assert_eq!(outcome.object.unwrap()["metadata"]["labels"]["replicas"], "1");
+    }
+}
@@ -0,0 +1,580 @@
+//! ValidatingAdmissionPolicy evaluation
+//!
+//! `validate` admits a request against a set of policies, their bindings
+//! and the param objects bindings refer to, the way the API server's
+//! ValidatingAdmissionPolicy plugin does: a policy applies to requests
+//! matched by its `matchConstraints`, each binding of it further narrows the
+//! requests with its own `matchResources` and evaluates the policy once per
+//! param it selects. Failed validations are enforced according to the
+//! binding's `validationActions`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use k8s_api::admission::v1::AdmissionResponse;
+use k8s_api::admissionregistration::v1::{
+    ValidatingAdmissionPolicy, ValidatingAdmissionPolicyBinding, ValidatingAdmissionPolicyBindingSpec,
+    FAILURE_POLICY_IGNORE, VALIDATION_ACTION_AUDIT, VALIDATION_ACTION_DENY, VALIDATION_ACTION_WARN,
+};
+use k8s_apimachinery::apis::meta::v1::Status;
+use k8s_cel::Value;
+use serde::Serialize;
+
+use super::{collect_params, Compiled, Compiler, EvaluationError, Evaluator};
+use crate::admission::matching;
+use crate::admission::Attributes;
+
+/// Audit annotation recording validation failures of bindings with the
+/// `Audit` action.
+pub const VALIDATION_FAILURE_ANNOTATION: &str = "validation.policy.admission.k8s.io/validation_failure";
+
+const MAX_MESSAGE_EXPRESSION_LENGTH: usize = 5 * 1024;
+const MAX_AUDIT_ANNOTATION_VALUE_LENGTH: usize = 10 * 1024;
+
+/// A validation that failed, or a policy or binding that could not be
+/// evaluated under failure policy `Fail`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationFailure {
+    pub policy: String,
+    /// The binding, unless the policy itself could not be evaluated.
+    pub binding: Option<String>,
+    pub message: String,
+    pub reason: String,
+    /// The actions taken for the failure: the `validationActions` of the
+    /// binding, or `Deny` for evaluation errors.
+    pub actions: Vec<String>,
+}
+
+/// ValidationOutcome is the result of admitting a request against
+/// ValidatingAdmissionPolicies.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationOutcome {
+    /// Every failure, in the order policies and bindings were evaluated.
+    pub failures: Vec<ValidationFailure>,
+    /// The error returned to the client if a failure denies the request.
+    pub denied: Option<Status>,
+    pub warnings: Vec<String>,
+    pub audit_annotations: BTreeMap<String, String>,
+}
+
+impl ValidationOutcome {
+    pub fn allowed(&self) -> bool {
+        self.denied.is_none()
+    }
+
+    /// Returns the outcome as the response to the admission request `uid`.
+    pub fn to_response(&self, uid: impl Into<String>) -> AdmissionResponse {
+        AdmissionResponse {
+            uid: uid.into(),
+            allowed: self.allowed(),
+            status: self.denied.clone(),
+            audit_annotations: self.audit_annotations.clone().into_iter().collect(),
+            warnings: self.warnings.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn deny(&mut self, policy: &str, binding: Option<&str>, message: String, reason: String) {
+        self.failures.push(ValidationFailure {
+            policy: policy.to_string(),
+            binding: binding.map(str::to_string),
+            message,
+            reason,
+            actions: vec![VALIDATION_ACTION_DENY.to_string()],
+        });
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidationFailureValue<'a> {
+    message: &'a str,
+    policy: &'a str,
+    binding: &'a str,
+    expression_index: usize,
+    validation_actions: &'a [String],
+}
+
+/// A failed validation of one evaluation of a policy.
+struct Decision {
+    index: usize,
+    /// False if the validation errored under failure policy `Ignore`.
+    deny: bool,
+    message: String,
+    reason: String,
+}
+
+struct CompiledValidation<'a> {
+    validation: &'a k8s_api::admissionregistration::v1::Validation,
+    program: Compiled,
+    message_program: Option<Compiled>,
+}
+
+/// Admits the request described by `attributes` against `policies`.
+/// Bindings refer to their policy by name, and `params` holds the objects
+/// bindings with a `paramRef` select from.
+pub fn validate(
+    attributes: &Attributes,
+    policies: &[ValidatingAdmissionPolicy],
+    bindings: &[ValidatingAdmissionPolicyBinding],
+    params: &[serde_json::Value],
+) -> ValidationOutcome {
+    let mut outcome = ValidationOutcome::default();
+    for policy in policies {
+        let Some(spec) = &policy.spec else {
+            continue;
+        };
+        let Some(constraints) = &spec.match_constraints else {
+            continue;
+        };
+        let name = policy.metadata.name.as_str();
+        let fail = spec.failure_policy.as_deref() != Some(FAILURE_POLICY_IGNORE);
+        let matched = match matching::matches(constraints, attributes) {
+            Ok(Some(matched)) => matched,
+            Ok(None) => continue,
+            Err(err) => {
+                if fail {
+                    let message = format!("failed to configure policy: {}", err);
+                    outcome.deny(name, None, message, String::new());
+                }
+                continue;
+            }
+        };
+
+        let compiler = Compiler::new(spec.param_kind.is_some(), &spec.variables);
+        let match_conditions: Vec<Compiled> = spec
+            .match_conditions
+            .iter()
+            .map(|condition| compiler.compile(&condition.expression, true))
+            .collect();
+        let validations: Vec<CompiledValidation> = spec
+            .validations
+            .iter()
+            .map(|validation| CompiledValidation {
+                validation,
+                program: compiler.compile(&validation.expression, true),
+                message_program: (!validation.message_expression.is_empty())
+                    .then(|| compiler.compile(&validation.message_expression, false)),
+            })
+            .collect();
+        let audit_annotations: Vec<Compiled> = spec
+            .audit_annotations
+            .iter()
+            .map(|annotation| compiler.compile(&annotation.value_expression, false))
+            .collect();
+
+        let mut annotations: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+        for binding in bindings {
+            let Some(binding_spec) = binding.spec.as_ref().filter(|spec| spec.policy_name == name) else {
+                continue;
+            };
+            let binding_name = binding.metadata.name.as_str();
+            let configure_error = |outcome: &mut ValidationOutcome, err: String| {
+                if fail {
+                    let message = format!("failed to configure binding: {}", err);
+                    outcome.deny(name, Some(binding_name), message, String::new());
+                }
+            };
+            if let Some(match_resources) = &binding_spec.match_resources {
+                match matching::matches(match_resources, attributes) {
+                    Ok(Some(_)) => {}
+                    Ok(None) => continue,
+                    Err(err) => {
+                        configure_error(&mut outcome, err.to_string());
+                        continue;
+                    }
+                }
+            }
+            let selected = match collect_params(
+                spec.param_kind.as_ref(),
+                binding_spec.param_ref.as_ref(),
+                &attributes.request.namespace,
+                params,
+            ) {
+                Ok(selected) => selected,
+                Err(err) => {
+                    configure_error(&mut outcome, err);
+                    continue;
+                }
+            };
+
+            for param in selected {
+                let mut evaluator = compiler.evaluator(attributes, &matched, param);
+                let decisions = match evaluator.match_conditions(&match_conditions, spec.failure_policy.as_deref()) {
+                    Ok(false) => continue,
+                    Ok(true) => evaluate_validations(&mut evaluator, &validations, fail),
+                    Err(message) => vec![Decision {
+                        index: 0,
+                        deny: fail,
+                        message,
+                        reason: String::new(),
+                    }],
+                };
+                for decision in decisions.into_iter().filter(|decision| decision.deny) {
+                    apply_actions(&mut outcome, name, binding_name, binding_spec, decision);
+                }
+
+                for (annotation, compiled) in spec.audit_annotations.iter().zip(&audit_annotations) {
+                    let error = match evaluator.evaluate(compiled) {
+                        Ok(Value::String(value)) => {
+                            let mut value = value.trim().to_string();
+                            if !value.is_empty() {
+                                truncate(&mut value, MAX_AUDIT_ANNOTATION_VALUE_LENGTH);
+                                annotations.entry(annotation.key.as_str()).or_default().insert(value);
+                            }
+                            continue;
+                        }
+                        Ok(Value::Null) => continue,
+                        Ok(value) => format!(
+                            "valueExpression '{}' resulted in unsupported return type: {}. Return type must be either string or null.",
+                            annotation.value_expression,
+                            value.type_of()
+                        ),
+                        Err(err) => err.to_string(),
+                    };
+                    if fail {
+                        outcome.deny(name, Some(binding_name), error, String::new());
+                    }
+                }
+            }
+        }
+        // Bindings evaluating the policy with different params may produce
+        // different values for an annotation, which are then listed together.
+        for (key, values) in annotations {
+            let value = values.into_iter().collect::<Vec<_>>().join(", ");
+            outcome.audit_annotations.insert(format!("{}/{}", name, key), value);
+        }
+    }
+
+    let denial = outcome
+        .failures
+        .iter()
+        .find(|failure| failure.actions.iter().any(|action| action == VALIDATION_ACTION_DENY));
+    outcome.denied = denial.map(|failure| forbidden(attributes, failure));
+    outcome
+}
+
+fn evaluate_validations(evaluator: &mut Evaluator<'_>, validations: &[CompiledValidation<'_>], fail: bool) -> Vec<Decision> {
+    let mut decisions = Vec::new();
+    for (index, compiled) in validations.iter().enumerate() {
+        let message = match evaluator.evaluate(&compiled.program) {
+            Ok(Value::Bool(true)) => continue,
+            Ok(_) => failure_message(evaluator, compiled),
+            Err(EvaluationError::OutOfBudget) => {
+                // Running out of budget fails the evaluation as a whole.
+                return vec![Decision {
+                    index: 0,
+                    deny: fail,
+                    message: EvaluationError::OutOfBudget.to_string(),
+                    reason: String::new(),
+                }];
+            }
+            Err(err) => {
+                decisions.push(Decision {
+                    index,
+                    deny: fail,
+                    message: err.to_string(),
+                    reason: String::new(),
+                });
+                continue;
+            }
+        };
+        decisions.push(Decision {
+            index,
+            deny: true,
+            message,
+            reason: compiled.validation.reason.clone().unwrap_or_default(),
+        });
+    }
+    decisions
+}
+
+/// Returns the message of a failed validation: the result of its
+/// `messageExpression` if that is a non-empty, single-line string, else its
+/// `message`, else the expression.
+fn failure_message(evaluator: &mut Evaluator<'_>, compiled: &CompiledValidation<'_>) -> String {
+    if let Some(program) = &compiled.message_program {
+        if let Ok(Value::String(message)) = evaluator.evaluate(program) {
+            let message = message.trim();
+            if !message.is_empty() && message.len() <= MAX_MESSAGE_EXPRESSION_LENGTH && !message.contains('\n') {
+                return message.to_string();
+            }
+        }
+    }
+    let message = compiled.validation.message.trim();
+    if !message.is_empty() {
+        return message.to_string();
+    }
+    format!("failed expression: {}", compiled.validation.expression.trim())
+}
+
+fn apply_actions(
+    outcome: &mut ValidationOutcome,
+    policy: &str,
+    binding: &str,
+    spec: &ValidatingAdmissionPolicyBindingSpec,
+    decision: Decision,
+) {
+    for action in &spec.validation_actions {
+        match action.as_str() {
+            VALIDATION_ACTION_WARN => {
+                let warning = format!(
+                    "Validation failed for ValidatingAdmissionPolicy '{}' with binding '{}': {}",
+                    policy, binding, decision.message
+                );
+                if !outcome.warnings.contains(&warning) {
+                    outcome.warnings.push(warning);
+                }
+            }
+            VALIDATION_ACTION_AUDIT => {
+                let value = serde_json::to_string(&[ValidationFailureValue {
+                    message: &decision.message,
+                    policy,
+                    binding,
+                    expression_index: decision.index,
+                    validation_actions: &spec.validation_actions,
+                }])
+                .unwrap_or_default();
+                // The first failure wins, as the annotation cannot be
+                // overwritten.
+                outcome
+                    .audit_annotations
+                    .entry(VALIDATION_FAILURE_ANNOTATION.to_string())
+                    .or_insert(value);
+            }
+            _ => {}
+        }
+    }
+    outcome.failures.push(ValidationFailure {
+        policy: policy.to_string(),
+        binding: Some(binding.to_string()),
+        message: decision.message,
+        reason: decision.reason,
+        actions: spec.validation_actions.clone(),
+    });
+}
+
+fn truncate(value: &mut String, max: usize) {
+    if value.len() > max {
+        let mut end = max;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value.truncate(end);
+    }
+}
+
+/// Returns the error denying the request for `failure`, with the reason
+/// and code of the validation.
+fn forbidden(attributes: &Attributes, failure: &ValidationFailure) -> Status {
+    let message = match &failure.binding {
+        Some(binding) => format!(
+            "ValidatingAdmissionPolicy '{}' with binding '{}' denied request: {}",
+            failure.policy, binding, failure.message
+        ),
+        None => format!("ValidatingAdmissionPolicy '{}' denied request: {}", failure.policy, failure.message),
+    };
+    let reason = match failure.reason.as_str() {
+        "" => "Invalid",
+        reason => reason,
+    };
+    super::forbidden(attributes, message, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_api::admission::v1::{AdmissionRequest, GroupVersionKind, GroupVersionResource};
+    use k8s_api::admissionregistration::v1::{
+        AuditAnnotation, MatchCondition, MatchResources, NamedRuleWithOperations, ParamKind, ParamRef,
+        RuleWithOperations, Validation, ValidatingAdmissionPolicySpec, Variable,
+    };
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+    use k8s_cel::{AuthorizationDecision, StaticAuthorizer};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn request(replicas: i64) -> Attributes {
+        Attributes::new(AdmissionRequest {
+            uid: "1".to_string(),
+            kind: GroupVersionKind::new("apps", "v1", "Deployment"),
+            resource: GroupVersionResource::new("apps", "v1", "deployments"),
+            name: "web".to_string(),
+            namespace: "default".to_string(),
+            operation: "CREATE".to_string(),
+            object: Some(json!({
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "metadata": {"name": "web", "namespace": "default"},
+                "spec": {"replicas": replicas},
+            })),
+            ..Default::default()
+        })
+        .with_namespace_object(json!({"metadata": {"name": "default", "labels": {"env": "prod"}}}))
+    }
+
+    fn policy(validations: Vec<Validation>) -> ValidatingAdmissionPolicy {
+        ValidatingAdmissionPolicy {
+            metadata: ObjectMeta::named("replicas"),
+            spec: Some(ValidatingAdmissionPolicySpec {
+                match_constraints: Some(MatchResources {
+                    resource_rules: vec![NamedRuleWithOperations {
+                        resource_names: Vec::new(),
+                        rule_with_operations: RuleWithOperations {
+                            operations: vec!["CREATE".to_string(), "UPDATE".to_string()],
+                            api_groups: vec!["apps".to_string()],
+                            api_versions: vec!["v1".to_string()],
+                            resources: vec!["deployments".to_string()],
+                            scope: None,
+                        },
+                    }],
+                    ..Default::default()
+                }),
+                validations,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn validation(expression: &str) -> Validation {
+        Validation {
+            expression: expression.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn binding(actions: &[&str]) -> ValidatingAdmissionPolicyBinding {
+        ValidatingAdmissionPolicyBinding {
+            metadata: ObjectMeta::named("replicas-binding"),
+            spec: Some(ValidatingAdmissionPolicyBindingSpec {
+                policy_name: "replicas".to_string(),
+                validation_actions: actions.iter().map(|a| a.to_string()).collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_deny() {
+        let mut check = validation("object.spec.replicas <= 5");
+        check.message_expression = "'replicas must be at most 5, got ' + string(object.spec.replicas)".to_string();
+        check.reason = Some("Forbidden".to_string());
+        let policies = [policy(vec![check])];
+        let bindings = [binding(&["Deny"])];
+
+        let outcome = validate(&request(3), &policies, &bindings, &[]);
+        assert!(outcome.allowed());
+        assert!(outcome.failures.is_empty());
+
+        let outcome = validate(&request(10), &policies, &bindings, &[]);
+        let status = outcome.denied.as_ref().unwrap();
+        assert_eq!(
+            status.message,
+            "deployments.apps \"web\" is forbidden: ValidatingAdmissionPolicy 'replicas' with binding 'replicas-binding' denied request: replicas must be at most 5, got 10"
+        );
+        assert_eq!((status.reason.as_str(), status.code), ("Forbidden", 403));
+        let response = outcome.to_response("1");
+        assert!(!response.allowed);
+    }
+
+    #[test]
+    fn test_validate_warn_and_audit() {
+        let mut check = validation("object.spec.replicas <= 5");
+        check.message = "too many replicas".to_string();
+        let policies = [policy(vec![validation("true"), check])];
+        let outcome = validate(&request(10), &policies, &[binding(&["Warn", "Audit"])], &[]);
+        assert!(outcome.allowed());
+        assert_eq!(
+            outcome.warnings,
+            ["Validation failed for ValidatingAdmissionPolicy 'replicas' with binding 'replicas-binding': too many replicas"]
+        );
+        assert_eq!(
+            outcome.audit_annotations[VALIDATION_FAILURE_ANNOTATION],
+            r#"[{"message":"too many replicas","policy":"replicas","binding":"replicas-binding","expressionIndex":1,"validationActions":["Warn","Audit"]}]"#
+        );
+
+        let outcome = validate(&request(10), &[policy(vec![validation("object.spec.replicas < 5")])], &[binding(&["Warn"])], &[]);
+        assert!(outcome.warnings[0].ends_with(": failed expression: object.spec.replicas < 5"));
+    }
+
+    #[test]
+    fn test_validate_params_and_variables() {
+        let mut policy = policy(vec![validation("variables.replicas <= params.data.maxReplicas")]);
+        let spec = policy.spec.as_mut().unwrap();
+        spec.param_kind = Some(ParamKind {
+            api_version: "v1".to_string(),
+            kind: "ConfigMap".to_string(),
+        });
+        spec.variables = vec![Variable {
+            name: "replicas".to_string(),
+            expression: "object.spec.replicas".to_string(),
+        }];
+        spec.audit_annotations = vec![AuditAnnotation {
+            key: "limit".to_string(),
+            value_expression: "'limit ' + string(params.data.maxReplicas)".to_string(),
+        }];
+        let mut bound = binding(&["Deny"]);
+        bound.spec.as_mut().unwrap().param_ref = Some(ParamRef {
+            name: "limits".to_string(),
+            parameter_not_found_action: Some("Deny".to_string()),
+            ..Default::default()
+        });
+        let params = [json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {"name": "limits", "namespace": "default"},
+            "data": {"maxReplicas": 5},
+        })];
+        let policies = [policy];
+        let bindings = [bound];
+
+        let outcome = validate(&request(3), &policies, &bindings, &params);
+        assert!(outcome.allowed());
+        assert_eq!(outcome.audit_annotations["replicas/limit"], "limit 5");
+        assert!(!validate(&request(6), &policies, &bindings, &params).allowed());
+
+        let outcome = validate(&request(3), &policies, &bindings, &[]);
+        assert_eq!(
+            outcome.failures[0].message,
+            "failed to configure binding: no params found for policy binding with `Deny` parameterNotFoundAction"
+        );
+    }
+
+    #[test]
+    fn test_validate_errors_and_match_conditions() {
+        let mut policies = [policy(vec![validation("object.spec.missing > 1")])];
+        let bindings = [binding(&["Deny"])];
+        let outcome = validate(&request(3), &policies, &bindings, &[]);
+        assert_eq!(
+            outcome.failures[0].message,
+            "expression 'object.spec.missing > 1' resulted in error: no such key: missing"
+        );
+        assert_eq!(outcome.denied.as_ref().unwrap().code, 422);
+
+        let spec = policies[0].spec.as_mut().unwrap();
+        spec.failure_policy = Some("Ignore".to_string());
+        assert!(validate(&request(3), &policies, &bindings, &[]).failures.is_empty());
+
+        let spec = policies[0].spec.as_mut().unwrap();
+        spec.failure_policy = None;
+        spec.match_conditions = vec![MatchCondition {
+            name: "prod-only".to_string(),
+            expression: "namespaceObject.metadata.labels.env == 'staging'".to_string(),
+        }];
+        assert!(validate(&request(3), &policies, &bindings, &[]).allowed());
+    }
+
+    #[test]
+    fn test_validate_authorizer() {
+        let policies = [policy(vec![validation(
+            "authorizer.group('apps').resource('deployments').namespace('default').check('scale').allowed()",
+        )])];
+        let bindings = [binding(&["Deny"])];
+        let allow = request(3).with_authorizer(Arc::new(StaticAuthorizer(AuthorizationDecision::allow(""))));
+        let outcome = validate(&allow, &policies, &bindings, &[]);
+        assert!(outcome.allowed(), "{:?}", outcome.failures);
+        let deny = request(3).with_authorizer(Arc::new(StaticAuthorizer(AuthorizationDecision::deny(""))));
+        assert!(!validate(&deny, &policies, &bindings, &[]).allowed());
+    }
+}
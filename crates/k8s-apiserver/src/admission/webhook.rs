@@ -0,0 +1,326 @@
+//! Admission webhook selection
+//!
+//! `plan` works out which webhooks of a set of Mutating and
+//! ValidatingWebhookConfigurations the API server would call for a request,
+//! and in which order: configurations are sorted by name, mutating webhooks
+//! are called one after another before the validating ones, and mutating
+//! webhooks with `reinvocationPolicy: IfNeeded` are called again if a later
+//! webhook changed the object.
+
+use k8s_api::admission::v1::{GroupVersionKind, GroupVersionResource};
+use k8s_api::admissionregistration::v1::{
+    MatchCondition, MutatingWebhookConfiguration, RuleWithOperations, ValidatingWebhookConfiguration,
+    FAILURE_POLICY_FAIL, REINVOCATION_POLICY_IF_NEEDED, REINVOCATION_POLICY_NEVER,
+};
+use k8s_apimachinery::apis::meta::v1::LabelSelector;
+
+use super::matching::{self, MatchedResource};
+use super::policy::Compiler;
+use super::Attributes;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebhookType {
+    Mutating,
+    Validating,
+}
+
+/// A call to a webhook.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebhookCall {
+    pub configuration: String,
+    pub webhook: String,
+    pub webhook_type: WebhookType,
+    /// The resource and kind the object is sent as. They differ from those
+    /// of the request when the webhook matched through an equivalent
+    /// resource.
+    pub resource: GroupVersionResource,
+    pub kind: GroupVersionKind,
+    pub failure_policy: String,
+    /// `Never` or `IfNeeded`; always `Never` for validating webhooks.
+    pub reinvocation_policy: String,
+}
+
+/// A webhook that rejects the request without being called, because it
+/// could not be decided whether it matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebhookError {
+    pub configuration: String,
+    pub webhook: String,
+    pub message: String,
+}
+
+/// The webhooks called for a request, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallPlan {
+    pub mutating: Vec<WebhookCall>,
+    /// The mutating webhooks called a second time, in order, if the object
+    /// changed after they were first called.
+    pub reinvocations: Vec<WebhookCall>,
+    /// Validating webhooks, which are called in parallel.
+    pub validating: Vec<WebhookCall>,
+    pub errors: Vec<WebhookError>,
+}
+
+impl CallPlan {
+    /// Iterates over every call, mutating webhooks first.
+    pub fn calls(&self) -> impl Iterator<Item = &WebhookCall> {
+        self.mutating.iter().chain(&self.reinvocations).chain(&self.validating)
+    }
+}
+
+/// The fields shared by mutating and validating webhooks.
+struct Webhook<'a> {
+    name: &'a str,
+    rules: &'a [RuleWithOperations],
+    failure_policy: Option<&'a str>,
+    match_policy: Option<&'a str>,
+    namespace_selector: Option<&'a LabelSelector>,
+    object_selector: Option<&'a LabelSelector>,
+    match_conditions: &'a [MatchCondition],
+}
+
+/// Decides whether `webhook` is called for the request and as which
+/// resource. Selector errors only reject requests the rules match.
+fn should_call(webhook: &Webhook<'_>, attributes: &Attributes) -> Result<Option<MatchedResource>, String> {
+    let namespace = matching::matches_namespace_selector(webhook.namespace_selector, attributes);
+    if let Ok(false) = namespace {
+        return Ok(None);
+    }
+    let object = matching::matches_object_selector(webhook.object_selector, attributes);
+    if let Ok(false) = object {
+        return Ok(None);
+    }
+    let Some(matched) = matching::matches_rules(webhook.rules, webhook.match_policy, attributes) else {
+        return Ok(None);
+    };
+    namespace.map_err(|err| err.to_string())?;
+    object.map_err(|err| err.to_string())?;
+
+    if webhook.match_conditions.is_empty() {
+        return Ok(Some(matched));
+    }
+    let compiler = Compiler::new(false, &[]);
+    let conditions: Vec<_> = webhook
+        .match_conditions
+        .iter()
+        .map(|condition| compiler.compile(&condition.expression, true))
+        .collect();
+    let mut evaluator = compiler.evaluator(attributes, &matched, None);
+    match evaluator.match_conditions(&conditions, webhook.failure_policy) {
+        Ok(true) => Ok(Some(matched)),
+        Ok(false) => Ok(None),
+        Err(err) => Err(forbidden(attributes, &err)),
+    }
+}
+
+/// Formats the Forbidden error the API server returns when match
+/// conditions fail to evaluate.
+fn forbidden(attributes: &Attributes, err: &str) -> String {
+    let request = &attributes.request;
+    let resource = match request.resource.group.as_str() {
+        "" => request.resource.resource.clone(),
+        group => format!("{}.{}", request.resource.resource, group),
+    };
+    match request.name.as_str() {
+        "" => format!("{} is forbidden: {}", resource, err),
+        name => format!("{} {:?} is forbidden: {}", resource, name, err),
+    }
+}
+
+/// Returns the webhooks called for the request described by `attributes`.
+pub fn plan(
+    attributes: &Attributes,
+    mutating: &[MutatingWebhookConfiguration],
+    validating: &[ValidatingWebhookConfiguration],
+) -> CallPlan {
+    let mut plan = CallPlan::default();
+    let mut add = |configuration: &str, webhook: Webhook<'_>, webhook_type, reinvocation_policy: Option<&str>| {
+        let matched = match should_call(&webhook, attributes) {
+            Ok(Some(matched)) => matched,
+            Ok(None) => return,
+            Err(message) => {
+                plan.errors.push(WebhookError {
+                    configuration: configuration.to_string(),
+                    webhook: webhook.name.to_string(),
+                    message,
+                });
+                return;
+            }
+        };
+        let call = WebhookCall {
+            configuration: configuration.to_string(),
+            webhook: webhook.name.to_string(),
+            webhook_type,
+            resource: matched.resource,
+            kind: matched.kind,
+            failure_policy: webhook.failure_policy.unwrap_or(FAILURE_POLICY_FAIL).to_string(),
+            reinvocation_policy: reinvocation_policy.unwrap_or(REINVOCATION_POLICY_NEVER).to_string(),
+        };
+        match webhook_type {
+            WebhookType::Mutating => {
+                if call.reinvocation_policy == REINVOCATION_POLICY_IF_NEEDED {
+                    plan.reinvocations.push(call.clone());
+                }
+                plan.mutating.push(call);
+            }
+            WebhookType::Validating => plan.validating.push(call),
+        }
+    };
+
+    let mut mutating: Vec<_> = mutating.iter().collect();
+    mutating.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+    for configuration in mutating {
+        for webhook in &configuration.webhooks {
+            let accessor = Webhook {
+                name: &webhook.name,
+                rules: &webhook.rules,
+                failure_policy: webhook.failure_policy.as_deref(),
+                match_policy: webhook.match_policy.as_deref(),
+                namespace_selector: webhook.namespace_selector.as_ref(),
+                object_selector: webhook.object_selector.as_ref(),
+                match_conditions: &webhook.match_conditions,
+            };
+            add(
+                &configuration.metadata.name,
+                accessor,
+                WebhookType::Mutating,
+                webhook.reinvocation_policy.as_deref(),
+            );
+        }
+    }
+    let mut validating: Vec<_> = validating.iter().collect();
+    validating.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+    for configuration in validating {
+        for webhook in &configuration.webhooks {
+            let accessor = Webhook {
+                name: &webhook.name,
+                rules: &webhook.rules,
+                failure_policy: webhook.failure_policy.as_deref(),
+                match_policy: webhook.match_policy.as_deref(),
+                namespace_selector: webhook.namespace_selector.as_ref(),
+                object_selector: webhook.object_selector.as_ref(),
+                match_conditions: &webhook.match_conditions,
+            };
+            add(&configuration.metadata.name, accessor, WebhookType::Validating, None);
+        }
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_api::admission::v1::AdmissionRequest;
+    use k8s_api::admissionregistration::v1::{MutatingWebhook, ValidatingWebhook};
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+    use serde_json::json;
+
+    fn attributes() -> Attributes {
+        Attributes::new(AdmissionRequest {
+            kind: GroupVersionKind::new("apps", "v1", "Deployment"),
+            resource: GroupVersionResource::new("apps", "v1", "deployments"),
+            name: "web".to_string(),
+            namespace: "default".to_string(),
+            operation: "CREATE".to_string(),
+            object: Some(json!({"metadata": {"name": "web", "labels": {"app": "web"}}})),
+            ..Default::default()
+        })
+        .with_namespace_object(json!({"metadata": {"name": "default", "labels": {"env": "prod"}}}))
+        .with_equivalent_resource(
+            GroupVersionResource::new("extensions", "v1beta1", "deployments"),
+            GroupVersionKind::new("extensions", "v1beta1", "Deployment"),
+        )
+    }
+
+    fn rule(group: &str, version: &str, resources: &[&str]) -> RuleWithOperations {
+        RuleWithOperations {
+            operations: vec!["*".to_string()],
+            api_groups: vec![group.to_string()],
+            api_versions: vec![version.to_string()],
+            resources: resources.iter().map(|r| r.to_string()).collect(),
+            scope: None,
+        }
+    }
+
+    fn mutating(name: &str, webhooks: Vec<MutatingWebhook>) -> MutatingWebhookConfiguration {
+        MutatingWebhookConfiguration {
+            metadata: ObjectMeta::named(name),
+            webhooks,
+            ..Default::default()
+        }
+    }
+
+    fn mutating_webhook(name: &str, rules: Vec<RuleWithOperations>) -> MutatingWebhook {
+        MutatingWebhook {
+            name: name.to_string(),
+            rules,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_order_and_reinvocation() {
+        let mut reinvoked = mutating_webhook("b.example.com", vec![rule("apps", "*", &["*"])]);
+        reinvoked.reinvocation_policy = Some("IfNeeded".to_string());
+        let configurations = [
+            mutating("z-config", vec![mutating_webhook("a.example.com", vec![rule("*", "*", &["*/*"])])]),
+            mutating(
+                "a-config",
+                vec![reinvoked, mutating_webhook("pods.example.com", vec![rule("", "v1", &["pods"])])],
+            ),
+        ];
+        let validating = [ValidatingWebhookConfiguration {
+            metadata: ObjectMeta::named("validate"),
+            webhooks: vec![ValidatingWebhook {
+                name: "v.example.com".to_string(),
+                rules: vec![rule("apps", "v1", &["deployments"])],
+                failure_policy: Some("Ignore".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let plan = plan(&attributes(), &configurations, &validating);
+        let names: Vec<_> = plan.calls().map(|call| call.webhook.as_str()).collect();
+        assert_eq!(names, ["b.example.com", "a.example.com", "b.example.com", "v.example.com"]);
+        assert_eq!(plan.reinvocations[0].reinvocation_policy, "IfNeeded");
+        assert_eq!(plan.validating[0].failure_policy, "Ignore");
+        assert_eq!(plan.mutating[1].failure_policy, "Fail");
+    }
+
+    #[test]
+    fn test_plan_matching() {
+        let mut equivalent = mutating_webhook("legacy.example.com", vec![rule("extensions", "v1beta1", &["deployments"])]);
+        let mut exact = equivalent.clone();
+        exact.name = "exact.example.com".to_string();
+        exact.match_policy = Some("Exact".to_string());
+        equivalent.match_policy = Some("Equivalent".to_string());
+
+        let mut selected = mutating_webhook("selected.example.com", vec![rule("apps", "v1", &["deployments"])]);
+        selected.namespace_selector = Some(LabelSelector {
+            match_labels: [("env".to_string(), "staging".to_string())].into_iter().collect(),
+            ..Default::default()
+        });
+        let mut conditioned = mutating_webhook("conditioned.example.com", vec![rule("apps", "v1", &["deployments"])]);
+        conditioned.match_conditions = vec![MatchCondition {
+            name: "not-web".to_string(),
+            expression: "object.metadata.name != 'web'".to_string(),
+        }];
+        let mut failing = conditioned.clone();
+        failing.name = "failing.example.com".to_string();
+        failing.match_conditions[0].expression = "object.metadata.missing == 'x'".to_string();
+
+        let configurations = [mutating("config", vec![equivalent, exact, selected, conditioned, failing])];
+        let plan = plan(&attributes(), &configurations, &[]);
+        assert_eq!(plan.mutating.len(), 1);
+        assert_eq!(plan.mutating[0].webhook, "legacy.example.com");
+        assert_eq!(plan.mutating[0].kind, GroupVersionKind::new("extensions", "v1beta1", "Deployment"));
+        assert_eq!(
+            plan.errors,
+            [WebhookError {
+                configuration: "config".to_string(),
+                webhook: "failing.example.com".to_string(),
+                message: "deployments.apps \"web\" is forbidden: expression 'object.metadata.missing == 'x'' resulted in error: no such key: missing".to_string(),
+            }]
+        );
+    }
+}
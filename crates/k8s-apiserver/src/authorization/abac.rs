@@ -0,0 +1,266 @@
+//! ABAC authorization
+//!
+//! Authorizes requests with the policies of an ABAC policy file, one JSON
+//! policy per line. Lines are `v1beta1` policies, or legacy `v0` policies
+//! when they have no `apiVersion` and `kind`; both are converted to the
+//! internal version, which spells out the defaults of `v0`.
+
+use std::path::Path;
+
+use k8s_api::abac::internal::Policy;
+use k8s_api::abac::{v0, v1beta1};
+use k8s_api::authentication::v1::UserInfo;
+use k8s_api::authorization::v1::{NonResourceRule, ResourceRule, SubjectRulesReviewStatus};
+
+use super::{Attributes, AuthorizationDecision, Authorizer, RuleResolver};
+
+const GROUP: &str = "abac.authorization.kubernetes.io";
+
+/// An authorizer allowing the requests any of its policies matches.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AbacAuthorizer {
+    pub policies: Vec<Policy>,
+    /// The number of `v0` policies without `apiVersion` and `kind` in the
+    /// policy file, which the API server warns about.
+    pub unversioned_lines: usize,
+}
+
+impl AbacAuthorizer {
+    pub fn new(policies: Vec<Policy>) -> Self {
+        Self {
+            policies,
+            unversioned_lines: 0,
+        }
+    }
+
+    /// Loads the policy file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("error reading policy file {}: {}", path.display(), err))?;
+        Self::from_policy_file(&path.display().to_string(), &contents)
+    }
+
+    /// Parses the `contents` of the policy file at `path`, which only
+    /// appears in errors. Blank lines and lines starting with `#` are
+    /// skipped.
+    pub fn from_policy_file(path: &str, contents: &str) -> Result<Self, String> {
+        let mut authorizer = Self::default();
+        for (idx, line) in contents.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let (policy, unversioned) = decode_policy(line).map_err(|err| {
+                format!("error reading policy file {}, line {}: {}: {}", path, idx + 1, line, err)
+            })?;
+            authorizer.policies.push(policy);
+            if unversioned {
+                authorizer.unversioned_lines += 1;
+            }
+        }
+        Ok(authorizer)
+    }
+}
+
+/// Decodes a line of a policy file, returning whether it is an unversioned
+/// `v0` policy.
+fn decode_policy(line: &str) -> Result<(Policy, bool), String> {
+    let value: serde_json::Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    let field = |name: &str| value.get(name).and_then(|value| value.as_str()).unwrap_or_default();
+    let (api_version, kind) = (field("apiVersion").to_string(), field("kind").to_string());
+    let v0 = format!("{}/v0", GROUP);
+    let v1beta1 = format!("{}/v1beta1", GROUP);
+    let unversioned = api_version.is_empty() || kind.is_empty();
+    let policy = if unversioned || (api_version == v0 && kind == "Policy") {
+        let policy: v0::Policy = serde_json::from_value(value).map_err(|err| err.to_string())?;
+        v0::InternalConversion::into_internal(&policy)
+    } else if api_version == v1beta1 && kind == "Policy" {
+        let policy: v1beta1::Policy = serde_json::from_value(value).map_err(|err| err.to_string())?;
+        v1beta1::InternalConversion::into_internal(&policy)
+    } else {
+        return Err(format!("no kind {:?} is registered for version {:?}", kind, api_version));
+    };
+    policy.map(|policy| (policy, unversioned)).map_err(|err| err.to_string())
+}
+
+/// Returns whether `policy` applies to `user`: a policy with a user or
+/// group only applies to that user or the members of that group, `*`
+/// matching everyone, and a policy with neither applies to no one.
+pub fn subject_matches(policy: &Policy, user: &UserInfo) -> bool {
+    let spec = &policy.spec;
+    let mut matched = false;
+    if !spec.user.is_empty() {
+        matched = spec.user == "*" || spec.user == user.username;
+        if !matched {
+            return false;
+        }
+    }
+    if !spec.group.is_empty() {
+        matched = spec.group == "*" || user.groups.contains(&spec.group);
+        if !matched {
+            return false;
+        }
+    }
+    matched
+}
+
+/// Returns whether `policy` matches the request of `attributes`. Policies
+/// match every read-only request, and other requests unless `readonly`;
+/// namespaces, resources and API groups match exactly or by `*`, and
+/// non-resource paths also by a trailing `*`.
+pub fn policy_matches(policy: &Policy, attributes: &Attributes) -> bool {
+    let spec = &policy.spec;
+    if !subject_matches(policy, &attributes.user) || (spec.read_only && !is_read_only(&attributes.verb)) {
+        return false;
+    }
+    if attributes.resource_request {
+        glob_matches(&spec.namespace, &attributes.namespace)
+            && glob_matches(&spec.resource, &attributes.resource)
+            && glob_matches(&spec.api_group, &attributes.api_group)
+    } else {
+        glob_matches(&spec.non_resource_path, &attributes.path)
+            || spec
+                .non_resource_path
+                .strip_suffix('*')
+                .is_some_and(|prefix| attributes.path.starts_with(prefix))
+    }
+}
+
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    pattern == "*" || pattern == value
+}
+
+fn is_read_only(verb: &str) -> bool {
+    matches!(verb, "get" | "list" | "watch")
+}
+
+fn verbs(read_only: bool) -> Vec<String> {
+    let verbs: &[&str] = if read_only { &["get", "list", "watch"] } else { &["*"] };
+    verbs.iter().map(|verb| verb.to_string()).collect()
+}
+
+impl Authorizer for AbacAuthorizer {
+    fn authorize(&self, attributes: &Attributes) -> AuthorizationDecision {
+        if self.policies.iter().any(|policy| policy_matches(policy, attributes)) {
+            AuthorizationDecision::allow("")
+        } else {
+            AuthorizationDecision::no_opinion("No policy matched.")
+        }
+    }
+}
+
+impl RuleResolver for AbacAuthorizer {
+    fn rules_for(&self, user: &UserInfo, namespace: &str) -> SubjectRulesReviewStatus {
+        let mut status = SubjectRulesReviewStatus::default();
+        for policy in &self.policies {
+            let spec = &policy.spec;
+            if !subject_matches(policy, user) || !glob_matches(&spec.namespace, namespace) {
+                continue;
+            }
+            if !spec.resource.is_empty() {
+                status.resource_rules.push(ResourceRule {
+                    verbs: verbs(spec.read_only),
+                    api_groups: vec![spec.api_group.clone()],
+                    resources: vec![spec.resource.clone()],
+                    resource_names: Vec::new(),
+                });
+            }
+            if !spec.non_resource_path.is_empty() {
+                status.non_resource_rules.push(NonResourceRule {
+                    verbs: verbs(spec.read_only),
+                    non_resource_urls: vec![spec.non_resource_path.clone()],
+                });
+            }
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorization::Decision;
+
+    const POLICIES: &str = r#"
+# Legacy policies
+{"user":"admin"}
+{"user":"scheduler", "readonly": true, "resource": "pods"}
+{"group":"*", "namespace": "projectCaribou"}
+
+{"apiVersion": "abac.authorization.kubernetes.io/v1beta1", "kind": "Policy", "spec": {"user": "bob", "namespace": "dev", "resource": "*", "apiGroup": "apps", "readonly": true}}
+{"apiVersion": "abac.authorization.kubernetes.io/v1beta1", "kind": "Policy", "spec": {"group": "system:authenticated", "nonResourcePath": "/version/*", "readonly": true}}
+"#;
+
+    fn user(name: &str, groups: &[&str]) -> UserInfo {
+        UserInfo {
+            username: name.to_string(),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_policy_file() {
+        let authorizer = AbacAuthorizer::from_policy_file("policy.jsonl", POLICIES).unwrap();
+        assert_eq!(authorizer.policies.len(), 5);
+        assert_eq!(authorizer.unversioned_lines, 3);
+        let admin = &authorizer.policies[0].spec;
+        assert_eq!(
+            (&*admin.user, &*admin.group, &*admin.namespace, &*admin.resource, &*admin.api_group, &*admin.non_resource_path),
+            ("admin", "", "*", "*", "*", "*")
+        );
+        let caribou = &authorizer.policies[2].spec;
+        assert_eq!((&*caribou.user, &*caribou.group, &*caribou.non_resource_path), ("", "system:authenticated", ""));
+
+        assert_eq!(
+            AbacAuthorizer::from_policy_file("policy.jsonl", "{\"user\":\"a\"}\n{\"user\":").unwrap_err(),
+            "error reading policy file policy.jsonl, line 2: {\"user\":: EOF while parsing a value at line 1 column 8"
+        );
+        assert_eq!(
+            AbacAuthorizer::from_policy_file("policy.jsonl", r#"{"apiVersion":"v1","kind":"Policy"}"#).unwrap_err(),
+            "error reading policy file policy.jsonl, line 1: {\"apiVersion\":\"v1\",\"kind\":\"Policy\"}: \
+             no kind \"Policy\" is registered for version \"v1\""
+        );
+    }
+
+    #[test]
+    fn test_authorize() {
+        let authorizer = AbacAuthorizer::from_policy_file("policy.jsonl", POLICIES).unwrap();
+        let decide = |attributes: Attributes| authorizer.authorize(&attributes).decision;
+        let scheduler = user("scheduler", &["system:authenticated"]);
+        let bob = user("bob", &["system:authenticated"]);
+
+        assert_eq!(decide(Attributes::resource(user("admin", &[]), "delete", "apps", "deployments")), Decision::Allow);
+        assert_eq!(decide(Attributes::non_resource(user("admin", &[]), "get", "/healthz")), Decision::Allow);
+        assert_eq!(decide(Attributes::resource(scheduler.clone(), "list", "", "pods")), Decision::Allow);
+        assert_eq!(decide(Attributes::resource(scheduler.clone(), "create", "", "pods")), Decision::NoOpinion);
+        assert_eq!(
+            decide(Attributes::resource(scheduler.clone(), "create", "", "pods").with_namespace("projectCaribou")),
+            Decision::Allow
+        );
+        assert_eq!(decide(Attributes::resource(bob.clone(), "get", "apps", "deployments").with_namespace("dev")), Decision::Allow);
+        assert_eq!(decide(Attributes::resource(bob.clone(), "get", "", "pods").with_namespace("dev")), Decision::NoOpinion);
+        assert_eq!(decide(Attributes::non_resource(bob.clone(), "get", "/version/")), Decision::Allow);
+        assert_eq!(decide(Attributes::non_resource(bob.clone(), "get", "/versions")), Decision::NoOpinion);
+        assert_eq!(
+            authorizer.authorize(&Attributes::non_resource(user("eve", &[]), "get", "/version/")).reason,
+            "No policy matched."
+        );
+
+        let rules = authorizer.rules_for(&bob, "dev");
+        assert_eq!(
+            rules.resource_rules,
+            [ResourceRule {
+                verbs: verbs(true),
+                api_groups: vec!["apps".to_string()],
+                resources: vec!["*".to_string()],
+                resource_names: Vec::new(),
+            }]
+        );
+        // Like resource rules, non-resource rules are only listed for
+        // policies matching the namespace.
+        assert!(rules.non_resource_rules.is_empty());
+        assert_eq!(authorizer.rules_for(&scheduler, "projectCaribou").resource_rules.len(), 2);
+    }
+}
@@ -0,0 +1,237 @@
+//! Authorization
+//!
+//! Authorizers decide whether a user may make a request, described by
+//! `Attributes`: either a verb on an API resource or a verb on a
+//! non-resource path such as `/healthz`. `Authorizer` and `RuleResolver`
+//! are implemented by `rbac::RbacAuthorizer` and `abac::AbacAuthorizer`
+//! and can be combined with `UnionAuthorizer` and `UnionRuleResolver`;
+//! `review` answers SubjectAccessReviews and SelfSubjectRulesReviews with
+//! them.
+
+pub mod abac;
+pub mod rbac;
+pub mod review;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use k8s_api::authentication::v1::UserInfo;
+use k8s_api::authorization::v1::SubjectRulesReviewStatus;
+use k8s_apimachinery::apis::meta::v1::{FieldSelectorRequirement, LabelSelectorRequirement};
+use k8s_apimachinery::util::selector;
+
+pub use k8s_cel::{AuthorizationDecision, Decision};
+
+/// The group of all service accounts.
+pub const ALL_SERVICE_ACCOUNTS_GROUP: &str = "system:serviceaccounts";
+
+const SERVICE_ACCOUNT_USERNAME_PREFIX: &str = "system:serviceaccount:";
+
+/// Returns the username of a service account,
+/// `system:serviceaccount:<namespace>:<name>`.
+pub fn service_account_username(namespace: &str, name: &str) -> String {
+    format!("{}{}:{}", SERVICE_ACCOUNT_USERNAME_PREFIX, namespace, name)
+}
+
+/// Splits a service account username into namespace and name.
+pub fn split_service_account_username(username: &str) -> Option<(&str, &str)> {
+    let (namespace, name) = username.strip_prefix(SERVICE_ACCOUNT_USERNAME_PREFIX)?.split_once(':')?;
+    (!namespace.is_empty() && !name.is_empty() && !name.contains(':')).then_some((namespace, name))
+}
+
+/// Returns the user a service account authenticates as: its username and
+/// the groups `system:serviceaccounts` and `system:serviceaccounts:<namespace>`.
+pub fn service_account_user(namespace: &str, name: &str) -> UserInfo {
+    UserInfo {
+        username: service_account_username(namespace, name),
+        groups: vec![
+            ALL_SERVICE_ACCOUNTS_GROUP.to_string(),
+            format!("{}:{}", ALL_SERVICE_ACCOUNTS_GROUP, namespace),
+        ],
+        ..Default::default()
+    }
+}
+
+/// Attributes of a request to authorize.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Attributes {
+    pub user: UserInfo,
+    pub verb: String,
+    /// True for requests for an API resource, false for requests for a
+    /// non-resource `path`.
+    pub resource_request: bool,
+    pub namespace: String,
+    pub api_group: String,
+    pub api_version: String,
+    pub resource: String,
+    pub subresource: String,
+    pub name: String,
+    pub path: String,
+    /// The requirements of the field selector of list, watch and
+    /// deletecollection requests.
+    pub field_selector: Vec<FieldSelectorRequirement>,
+    /// The error parsing the field selector, if it could not be parsed.
+    pub field_selector_error: Option<String>,
+    /// The requirements of the label selector of list, watch and
+    /// deletecollection requests.
+    pub label_selector: Vec<LabelSelectorRequirement>,
+    /// The error parsing the label selector, if it could not be parsed.
+    pub label_selector_error: Option<String>,
+}
+
+impl Attributes {
+    /// Returns the attributes of a request for `resource` in `api_group`.
+    pub fn resource(user: UserInfo, verb: impl Into<String>, api_group: impl Into<String>, resource: impl Into<String>) -> Self {
+        Self {
+            user,
+            verb: verb.into(),
+            resource_request: true,
+            api_group: api_group.into(),
+            resource: resource.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the attributes of a request for a non-resource path.
+    pub fn non_resource(user: UserInfo, verb: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            user,
+            verb: verb.into(),
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_subresource(mut self, subresource: impl Into<String>) -> Self {
+        self.subresource = subresource.into();
+        self
+    }
+
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = api_version.into();
+        self
+    }
+
+    /// Sets the field selector from its query parameter form, e.g.
+    /// `spec.nodeName=node-1`.
+    pub fn with_field_selector(mut self, raw: &str) -> Self {
+        match selector::parse_field_selector(raw) {
+            Ok(requirements) => self.field_selector = requirements,
+            Err(err) => self.field_selector_error = Some(err),
+        }
+        self
+    }
+
+    /// Sets the label selector from its query parameter form, e.g.
+    /// `app=web,tier in (front)`.
+    pub fn with_label_selector(mut self, raw: &str) -> Self {
+        match selector::parse_label_selector(raw) {
+            Ok(requirements) => self.label_selector = requirements,
+            Err(err) => self.label_selector_error = Some(err),
+        }
+        self
+    }
+}
+
+/// Authorizer decides whether to allow a request. Authorizers that neither
+/// allow nor deny a request have no opinion, leaving the decision to the
+/// next authorizer; a request no authorizer allows is denied.
+pub trait Authorizer: Send + Sync {
+    fn authorize(&self, attributes: &Attributes) -> AuthorizationDecision;
+}
+
+/// RuleResolver lists what a user may do in a namespace, as far as an
+/// authorizer can tell. Rules it cannot enumerate, such as those of a
+/// webhook, make the list incomplete.
+pub trait RuleResolver: Send + Sync {
+    fn rules_for(&self, user: &UserInfo, namespace: &str) -> SubjectRulesReviewStatus;
+}
+
+/// Asks authorizers in order until one allows or denies a request.
+#[derive(Clone, Default)]
+pub struct UnionAuthorizer {
+    pub authorizers: Vec<Arc<dyn Authorizer>>,
+}
+
+impl UnionAuthorizer {
+    pub fn new(authorizers: Vec<Arc<dyn Authorizer>>) -> Self {
+        Self { authorizers }
+    }
+}
+
+impl Authorizer for UnionAuthorizer {
+    /// Returns the first decision to allow or deny. Without one, the
+    /// reasons and errors of all authorizers are returned together.
+    fn authorize(&self, attributes: &Attributes) -> AuthorizationDecision {
+        let mut reasons = Vec::new();
+        let mut errors = Vec::new();
+        for authorizer in &self.authorizers {
+            let decision = authorizer.authorize(attributes);
+            if decision.decision != Decision::NoOpinion {
+                return decision;
+            }
+            if !decision.reason.is_empty() {
+                reasons.push(decision.reason);
+            }
+            errors.extend(decision.error);
+        }
+        AuthorizationDecision {
+            decision: Decision::NoOpinion,
+            reason: reasons.join("\n"),
+            error: (!errors.is_empty()).then(|| aggregate(errors)),
+        }
+    }
+}
+
+/// Lists the rules of several rule resolvers together.
+#[derive(Clone, Default)]
+pub struct UnionRuleResolver {
+    pub resolvers: Vec<Arc<dyn RuleResolver>>,
+}
+
+impl UnionRuleResolver {
+    pub fn new(resolvers: Vec<Arc<dyn RuleResolver>>) -> Self {
+        Self { resolvers }
+    }
+}
+
+impl RuleResolver for UnionRuleResolver {
+    fn rules_for(&self, user: &UserInfo, namespace: &str) -> SubjectRulesReviewStatus {
+        let mut status = SubjectRulesReviewStatus::default();
+        let mut errors = Vec::new();
+        for resolver in &self.resolvers {
+            let rules = resolver.rules_for(user, namespace);
+            status.resource_rules.extend(rules.resource_rules);
+            status.non_resource_rules.extend(rules.non_resource_rules);
+            status.incomplete |= rules.incomplete;
+            if !rules.evaluation_error.is_empty() {
+                errors.push(rules.evaluation_error);
+            }
+        }
+        if !errors.is_empty() {
+            status.evaluation_error = aggregate(errors);
+        }
+        status
+    }
+}
+
+/// Formats errors the way Go's aggregate errors do: a single error as is,
+/// several as a bracketed list without duplicates.
+pub(crate) fn aggregate(mut errors: Vec<String>) -> String {
+    let mut seen = HashSet::new();
+    errors.retain(|err| seen.insert(err.clone()));
+    match errors.len() {
+        1 => errors.remove(0),
+        _ => format!("[{}]", errors.join(", ")),
+    }
+}
@@ -0,0 +1,119 @@
+//! ClusterRole aggregation
+//!
+//! A ClusterRole with an `aggregationRule` holds the rules of the
+//! ClusterRoles its selectors select, as maintained by the
+//! clusterrole-aggregation controller.
+
+use k8s_api::rbac::v1::{AggregationRule, ClusterRole, PolicyRule};
+
+/// Returns the rules of the ClusterRole `name` with aggregation rule
+/// `rule`: the rules of the other ClusterRoles its selectors select, per
+/// selector in order of role name, without duplicates.
+pub fn aggregated_rules(name: &str, rule: &AggregationRule, cluster_roles: &[ClusterRole]) -> Result<Vec<PolicyRule>, String> {
+    let mut sorted: Vec<&ClusterRole> = cluster_roles.iter().collect();
+    sorted.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+    let mut rules: Vec<PolicyRule> = Vec::new();
+    for selector in &rule.cluster_role_selectors {
+        for role in &sorted {
+            if role.metadata.name == name || !selector.matches(&role.metadata.labels)? {
+                continue;
+            }
+            for rule in &role.rules {
+                if !rules.contains(rule) {
+                    rules.push(rule.clone());
+                }
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// Returns `cluster_roles` with the rules of aggregated ClusterRoles
+/// filled in. Aggregated roles may select other aggregated roles, whose
+/// rules are aggregated first, as the controller eventually does.
+pub fn aggregate_cluster_roles(cluster_roles: &[ClusterRole]) -> Result<Vec<ClusterRole>, String> {
+    let mut roles = cluster_roles.to_vec();
+    // Rules only propagate along selections, so every role has its final
+    // rules after as many rounds as there are roles.
+    for _ in 0..roles.len() {
+        let mut changed = false;
+        for idx in 0..roles.len() {
+            let Some(rule) = &roles[idx].aggregation_rule else {
+                continue;
+            };
+            let rules = aggregated_rules(&roles[idx].metadata.name, rule, &roles)?;
+            if rules != roles[idx].rules {
+                roles[idx].rules = rules;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    Ok(roles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_apimachinery::apis::meta::v1::{LabelSelector, LabelSelectorRequirement, ObjectMeta};
+    use std::collections::BTreeMap;
+
+    fn role(name: &str, labels: &[(&str, &str)], verbs: &[&str]) -> ClusterRole {
+        let mut metadata = ObjectMeta::named(name);
+        metadata.labels = labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        ClusterRole {
+            metadata,
+            rules: verbs
+                .iter()
+                .map(|verb| PolicyRule {
+                    verbs: vec![verb.to_string()],
+                    api_groups: vec![String::new()],
+                    resources: vec!["pods".to_string()],
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn aggregating(name: &str, labels: &[(&str, &str)], selects: &str) -> ClusterRole {
+        let mut role = role(name, labels, &["stale"]);
+        role.aggregation_rule = Some(AggregationRule {
+            cluster_role_selectors: vec![LabelSelector {
+                match_labels: BTreeMap::from([(selects.to_string(), "true".to_string())]),
+                ..Default::default()
+            }],
+        });
+        role
+    }
+
+    #[test]
+    fn test_aggregate_cluster_roles() {
+        let roles = vec![
+            aggregating("admin", &[], "aggregate-to-admin"),
+            aggregating("edit", &[("aggregate-to-admin", "true")], "aggregate-to-edit"),
+            role("view", &[("aggregate-to-edit", "true"), ("aggregate-to-admin", "true")], &["get", "list"]),
+            role("write", &[("aggregate-to-edit", "true")], &["update", "get"]),
+            role("other", &[], &["delete"]),
+        ];
+        let aggregated = aggregate_cluster_roles(&roles).unwrap();
+        let verbs = |role: &ClusterRole| role.rules.iter().map(|rule| rule.verbs[0].clone()).collect::<Vec<_>>();
+        // admin selects edit before view by name, and edit has the rules of
+        // view and write by then.
+        assert_eq!(verbs(&aggregated[0]), ["get", "list", "update"]);
+        assert_eq!(verbs(&aggregated[1]), ["get", "list", "update"]);
+        assert_eq!(aggregated[2..], roles[2..]);
+
+        let mut invalid = aggregating("invalid", &[], "x");
+        invalid.aggregation_rule.as_mut().unwrap().cluster_role_selectors[0]
+            .match_expressions
+            .push(LabelSelectorRequirement {
+                key: "x".to_string(),
+                operator: "In".to_string(),
+                values: Vec::new(),
+            });
+        assert!(aggregate_cluster_roles(&[invalid, role("view", &[], &["get"])]).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! Privilege escalation checks
+//!
+//! Users may only create roles and bindings granting permissions they hold
+//! themselves, unless they have the `escalate` or `bind` verbs. `rules_cover`
+//! compares the rules a user holds with the rules they want to grant, like
+//! Go's `validation.Covers`.
+
+use std::collections::BTreeSet;
+
+use k8s_api::authentication::v1::UserInfo;
+use k8s_api::rbac::v1::PolicyRule;
+
+use super::{RbacAuthorizer, ALL};
+
+/// Returns the parts of `requested` that `owner` rules do not cover, broken
+/// down into rules of a single verb on a single resource or URL. All of
+/// `requested` is covered if the result is empty.
+pub fn rules_cover(owner: &[PolicyRule], requested: &[PolicyRule]) -> Vec<PolicyRule> {
+    requested
+        .iter()
+        .flat_map(breakdown_rule)
+        .filter(|subrule| !owner.iter().any(|rule| rule_covers(rule, subrule)))
+        .collect()
+}
+
+/// Splits a rule into rules of a single API group, resource, verb and
+/// resource name, or of a single non-resource URL and verb.
+pub fn breakdown_rule(rule: &PolicyRule) -> Vec<PolicyRule> {
+    let single = |value: &String| vec![value.clone()];
+    let mut subrules = Vec::new();
+    for group in &rule.api_groups {
+        for resource in &rule.resources {
+            for verb in &rule.verbs {
+                let subrule = PolicyRule {
+                    verbs: single(verb),
+                    api_groups: single(group),
+                    resources: single(resource),
+                    ..Default::default()
+                };
+                if rule.resource_names.is_empty() {
+                    subrules.push(subrule);
+                    continue;
+                }
+                for name in &rule.resource_names {
+                    subrules.push(PolicyRule {
+                        resource_names: single(name),
+                        ..subrule.clone()
+                    });
+                }
+            }
+        }
+    }
+    // Non-resource URLs only combine with verbs.
+    for url in &rule.non_resource_urls {
+        for verb in &rule.verbs {
+            subrules.push(PolicyRule {
+                verbs: single(verb),
+                non_resource_urls: single(url),
+                ..Default::default()
+            });
+        }
+    }
+    subrules
+}
+
+fn has_all(set: &[String], values: &[String]) -> bool {
+    values.iter().all(|value| set.contains(value))
+}
+
+fn resources_cover(owner: &[String], resources: &[String]) -> bool {
+    if owner.iter().any(|resource| resource == ALL) || has_all(owner, resources) {
+        return true;
+    }
+    resources.iter().all(|resource| {
+        owner.contains(resource)
+            || resource
+                .split_once('/')
+                .is_some_and(|(_, subresource)| owner.contains(&format!("*/{}", subresource)))
+    })
+}
+
+fn non_resource_url_covers(owner: &str, path: &str) -> bool {
+    owner == path || (owner.ends_with('*') && path.starts_with(owner.trim_end_matches('*')))
+}
+
+fn rule_covers(owner: &PolicyRule, rule: &PolicyRule) -> bool {
+    let verbs = owner.verbs.iter().any(|verb| verb == ALL) || has_all(&owner.verbs, &rule.verbs);
+    let groups = owner.api_groups.iter().any(|group| group == ALL) || has_all(&owner.api_groups, &rule.api_groups);
+    let urls = rule
+        .non_resource_urls
+        .iter()
+        .all(|path| owner.non_resource_urls.iter().any(|url| non_resource_url_covers(url, path)));
+    let names = owner.resource_names.is_empty()
+        || (!rule.resource_names.is_empty() && has_all(&owner.resource_names, &rule.resource_names));
+    verbs && groups && resources_cover(&owner.resources, &rule.resources) && names && urls
+}
+
+/// Merges the verbs of rules for the same single resource, and resource
+/// name if any, as the rules `rules_cover` returns are.
+pub fn compact_rules(rules: &[PolicyRule]) -> Vec<PolicyRule> {
+    let mut compacted: Vec<PolicyRule> = Vec::new();
+    let mut simple: Vec<PolicyRule> = Vec::new();
+    for rule in rules {
+        let is_simple = rule.non_resource_urls.is_empty()
+            && rule.api_groups.len() == 1
+            && rule.resources.len() == 1
+            && rule.resource_names.len() <= 1;
+        if !is_simple {
+            compacted.push(rule.clone());
+            continue;
+        }
+        let existing = simple.iter_mut().find(|existing| {
+            existing.api_groups == rule.api_groups
+                && existing.resources == rule.resources
+                && existing.resource_names == rule.resource_names
+        });
+        match existing {
+            Some(existing) => existing.verbs.extend(rule.verbs.iter().cloned()),
+            None => simple.push(rule.clone()),
+        }
+    }
+    compacted.extend(simple);
+    compacted
+}
+
+/// Formats a rule the way RBAC errors describe it, e.g.
+/// `{APIGroups:["apps"], Resources:["deployments"], Verbs:["get"]}`.
+pub fn compact_string(rule: &PolicyRule) -> String {
+    let fields = [
+        ("APIGroups", &rule.api_groups),
+        ("Resources", &rule.resources),
+        ("NonResourceURLs", &rule.non_resource_urls),
+        ("ResourceNames", &rule.resource_names),
+        ("Verbs", &rule.verbs),
+    ];
+    let parts: Vec<String> = fields
+        .iter()
+        .filter(|(_, values)| !values.is_empty())
+        .map(|(name, values)| format!("{}:{}", name, quoted_list(values)))
+        .collect();
+    format!("{{{}}}", parts.join(", "))
+}
+
+/// Formats strings like Go's `%q` does a string slice: `["a" "b"]`.
+fn quoted_list(values: &[String]) -> String {
+    let quoted: Vec<String> = values.iter().map(|value| format!("{:?}", value)).collect();
+    format!("[{}]", quoted.join(" "))
+}
+
+impl RbacAuthorizer {
+    /// Checks that `user` holds every permission of `rules` in `namespace`,
+    /// as required to create a role or binding granting them. The error
+    /// lists the missing permissions.
+    pub fn confirm_no_escalation(&self, user: &UserInfo, namespace: &str, rules: &[PolicyRule]) -> Result<(), String> {
+        let (owner, errors) = self.rules_for(user, namespace);
+        let missing = rules_cover(&owner, rules);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let descriptions: BTreeSet<String> = compact_rules(&missing).iter().map(compact_string).collect();
+        let mut message = format!(
+            "user {:?} (groups={}) is attempting to grant RBAC permissions not currently held:\n{}",
+            user.username,
+            quoted_list(&user.groups),
+            descriptions.into_iter().collect::<Vec<_>>().join("\n")
+        );
+        if !errors.is_empty() {
+            message.push_str(&format!("; resolution errors: [{}]", errors.join(" ")));
+        }
+        Err(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(verbs: &[&str], groups: &[&str], resources: &[&str], names: &[&str]) -> PolicyRule {
+        let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        PolicyRule {
+            verbs: strings(verbs),
+            api_groups: strings(groups),
+            resources: strings(resources),
+            resource_names: strings(names),
+            ..Default::default()
+        }
+    }
+
+    fn url(verbs: &[&str], urls: &[&str]) -> PolicyRule {
+        PolicyRule {
+            verbs: verbs.iter().map(|verb| verb.to_string()).collect(),
+            non_resource_urls: urls.iter().map(|url| url.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rules_cover() {
+        let owner = [
+            rule(&["get", "list"], &["", "apps"], &["pods", "deployments", "*/status"], &[]),
+            rule(&["*"], &["apps"], &["deployments/scale"], &[]),
+            rule(&["update"], &[""], &["configmaps"], &["settings", "flags"]),
+            url(&["get"], &["/healthz", "/metrics*"]),
+        ];
+        let covered = [
+            rule(&["get"], &["apps"], &["deployments", "replicasets/status"], &[]),
+            rule(&["patch", "update"], &["apps"], &["deployments/scale"], &["web"]),
+            rule(&["update"], &[""], &["configmaps"], &["flags"]),
+            url(&["get"], &["/metrics/cadvisor", "/healthz"]),
+        ];
+        assert!(rules_cover(&owner, &covered).is_empty());
+
+        let requested = [
+            rule(&["get", "delete"], &["apps"], &["deployments"], &[]),
+            rule(&["update"], &[""], &["configmaps"], &[]),
+            url(&["post"], &["/healthz"]),
+        ];
+        assert_eq!(
+            rules_cover(&owner, &requested),
+            [
+                rule(&["delete"], &["apps"], &["deployments"], &[]),
+                rule(&["update"], &[""], &["configmaps"], &[]),
+                url(&["post"], &["/healthz"]),
+            ]
+        );
+        assert!(rules_cover(&[rule(&["*"], &["*"], &["*"], &[])], &requested[..2]).is_empty());
+    }
+
+    #[test]
+    fn test_confirm_no_escalation() {
+        use k8s_api::rbac::v1::{Role, RoleBinding, RoleRef, Subject};
+        use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+
+        let authorizer = RbacAuthorizer::new(
+            vec![Role {
+                metadata: ObjectMeta::namespaced("dev", "pods"),
+                rules: vec![rule(&["get", "list"], &[""], &["pods"], &[])],
+                ..Default::default()
+            }],
+            vec![RoleBinding {
+                metadata: ObjectMeta::namespaced("dev", "pods"),
+                subjects: vec![Subject {
+                    kind: "User".to_string(),
+                    name: "alice".to_string(),
+                    ..Default::default()
+                }],
+                role_ref: RoleRef {
+                    kind: "Role".to_string(),
+                    name: "pods".to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            Vec::new(),
+            Vec::new(),
+        );
+        let alice = UserInfo {
+            username: "alice".to_string(),
+            groups: vec!["system:authenticated".to_string()],
+            ..Default::default()
+        };
+        let grant = [rule(&["get"], &[""], &["pods"], &[])];
+        assert_eq!(authorizer.confirm_no_escalation(&alice, "dev", &grant), Ok(()));
+        let escalate = [rule(&["get", "delete", "create"], &[""], &["pods", "secrets"], &[])];
+        assert_eq!(
+            authorizer.confirm_no_escalation(&alice, "dev", &escalate).unwrap_err(),
+            "user \"alice\" (groups=[\"system:authenticated\"]) is attempting to grant RBAC permissions not currently held:\n\
+             {APIGroups:[\"\"], Resources:[\"pods\"], Verbs:[\"delete\" \"create\"]}\n\
+             {APIGroups:[\"\"], Resources:[\"secrets\"], Verbs:[\"get\" \"delete\" \"create\"]}"
+        );
+        assert!(authorizer.confirm_no_escalation(&alice, "prod", &grant).is_err());
+    }
+}
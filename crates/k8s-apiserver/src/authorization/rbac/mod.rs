@@ -0,0 +1,500 @@
+//! Role-based access control
+//!
+//! `RbacAuthorizer` answers requests from a set of Roles, ClusterRoles and
+//! their bindings, the way the API server's RBAC authorizer does: a request
+//! is allowed if a rule of a role bound to the user allows it. RBAC only
+//! grants permissions, so requests no rule allows get no opinion rather
+//! than a denial, leaving the decision to the next authorizer.
+//!
+//! `aggregation` computes the rules of aggregated ClusterRoles and
+//! `escalation` checks that users only grant permissions they hold.
+
+pub mod aggregation;
+pub mod escalation;
+
+use std::fmt;
+
+use k8s_api::authentication::v1::UserInfo;
+use k8s_api::rbac::v1::{ClusterRole, ClusterRoleBinding, PolicyRule, Role, RoleBinding, RoleRef, Subject};
+
+use k8s_api::authorization::v1::{NonResourceRule, ResourceRule, SubjectRulesReviewStatus};
+
+use super::{aggregate, split_service_account_username, Attributes, AuthorizationDecision, Authorizer, RuleResolver};
+
+/// Matches all verbs, API groups, resources or non-resource URLs.
+pub const ALL: &str = "*";
+
+pub const USER_KIND: &str = "User";
+pub const GROUP_KIND: &str = "Group";
+pub const SERVICE_ACCOUNT_KIND: &str = "ServiceAccount";
+
+/// The binding, role and subject a rule was granted through.
+#[derive(Clone, Copy, Debug)]
+pub enum RuleSource<'a> {
+    ClusterRoleBinding {
+        binding: &'a ClusterRoleBinding,
+        subject: &'a Subject,
+    },
+    RoleBinding {
+        binding: &'a RoleBinding,
+        subject: &'a Subject,
+    },
+}
+
+impl fmt::Display for RuleSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleSource::ClusterRoleBinding { binding, subject } => write!(
+                f,
+                "ClusterRoleBinding {:?} of {} {:?} to {}",
+                binding.metadata.name,
+                binding.role_ref.kind,
+                binding.role_ref.name,
+                describe_subject(subject, "")
+            ),
+            RuleSource::RoleBinding { binding, subject } => write!(
+                f,
+                "RoleBinding {:?} of {} {:?} to {}",
+                format!("{}/{}", binding.metadata.name, binding.metadata.namespace),
+                binding.role_ref.kind,
+                binding.role_ref.name,
+                describe_subject(subject, &binding.metadata.namespace)
+            ),
+        }
+    }
+}
+
+/// A rule granted to a user with its source, or the error resolving the
+/// role of a binding that applies to the user.
+pub type VisitedRule<'a> = Result<(RuleSource<'a>, &'a PolicyRule), String>;
+
+fn describe_subject(subject: &Subject, binding_namespace: &str) -> String {
+    match subject.kind.as_str() {
+        SERVICE_ACCOUNT_KIND => {
+            let namespace = match subject.namespace.as_str() {
+                "" => binding_namespace,
+                subject_namespace => subject_namespace,
+            };
+            format!("{} {:?}", subject.kind, format!("{}/{}", subject.name, namespace))
+        }
+        _ => format!("{} {:?}", subject.kind, subject.name),
+    }
+}
+
+/// Authorizes requests against in-memory RBAC objects.
+#[derive(Clone, Debug, Default)]
+pub struct RbacAuthorizer {
+    pub roles: Vec<Role>,
+    pub role_bindings: Vec<RoleBinding>,
+    pub cluster_roles: Vec<ClusterRole>,
+    pub cluster_role_bindings: Vec<ClusterRoleBinding>,
+}
+
+impl RbacAuthorizer {
+    pub fn new(
+        roles: Vec<Role>,
+        role_bindings: Vec<RoleBinding>,
+        cluster_roles: Vec<ClusterRole>,
+        cluster_role_bindings: Vec<ClusterRoleBinding>,
+    ) -> Self {
+        Self {
+            roles,
+            role_bindings,
+            cluster_roles,
+            cluster_role_bindings,
+        }
+    }
+
+    /// Authorizes a request. The reason of an allowed request names the
+    /// binding that allowed it; the reason of any other request lists the
+    /// bindings whose role could not be found.
+    pub fn authorize(&self, attributes: &Attributes) -> AuthorizationDecision {
+        let mut allowed_by = None;
+        let mut errors = Vec::new();
+        self.visit_rules_for(&attributes.user, &attributes.namespace, &mut |visited| {
+            match visited {
+                Ok((source, rule)) if rule_allows(attributes, rule) => {
+                    allowed_by = Some(format!("RBAC: allowed by {}", source));
+                    return false;
+                }
+                Ok(_) => {}
+                Err(err) => errors.push(err),
+            }
+            true
+        });
+        match allowed_by {
+            Some(reason) => AuthorizationDecision::allow(reason),
+            None if errors.is_empty() => AuthorizationDecision::no_opinion(""),
+            None => AuthorizationDecision::no_opinion(format!("RBAC: {}", aggregate(errors))),
+        }
+    }
+
+    /// Returns the rules granted to `user` in `namespace`, with the errors
+    /// resolving the roles of bindings that apply to the user. The rules
+    /// are incomplete if there are errors.
+    pub fn rules_for(&self, user: &UserInfo, namespace: &str) -> (Vec<PolicyRule>, Vec<String>) {
+        let mut rules = Vec::new();
+        let mut errors = Vec::new();
+        self.visit_rules_for(user, namespace, &mut |visited| {
+            match visited {
+                Ok((_, rule)) => rules.push(rule.clone()),
+                Err(err) => errors.push(err),
+            }
+            true
+        });
+        (rules, errors)
+    }
+
+    /// Calls `visitor` with every rule granted to `user` in `namespace`,
+    /// with the binding it is granted through: first those of
+    /// ClusterRoleBindings, then those of RoleBindings in the namespace.
+    /// Bindings whose role cannot be resolved are reported as an error
+    /// instead. Visiting stops when `visitor` returns false.
+    pub fn visit_rules_for(
+        &self,
+        user: &UserInfo,
+        namespace: &str,
+        visitor: &mut dyn FnMut(VisitedRule<'_>) -> bool,
+    ) {
+        for binding in &self.cluster_role_bindings {
+            let Some(subject) = applies_to(user, &binding.subjects, "") else {
+                continue;
+            };
+            match self.role_reference_rules(&binding.role_ref, "") {
+                Ok(rules) => {
+                    let source = RuleSource::ClusterRoleBinding { binding, subject };
+                    for rule in rules {
+                        if !visitor(Ok((source, rule))) {
+                            return;
+                        }
+                    }
+                }
+                Err(err) => {
+                    if !visitor(Err(err)) {
+                        return;
+                    }
+                }
+            }
+        }
+        if namespace.is_empty() {
+            return;
+        }
+        for binding in self.role_bindings.iter().filter(|binding| binding.metadata.namespace == namespace) {
+            let Some(subject) = applies_to(user, &binding.subjects, namespace) else {
+                continue;
+            };
+            match self.role_reference_rules(&binding.role_ref, namespace) {
+                Ok(rules) => {
+                    let source = RuleSource::RoleBinding { binding, subject };
+                    for rule in rules {
+                        if !visitor(Ok((source, rule))) {
+                            return;
+                        }
+                    }
+                }
+                Err(err) => {
+                    if !visitor(Err(err)) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the rules of the role a binding in `namespace` refers to.
+    /// ClusterRoleBindings refer to roles with an empty namespace.
+    pub fn role_reference_rules(&self, role_ref: &RoleRef, namespace: &str) -> Result<&[PolicyRule], String> {
+        match role_ref.kind.as_str() {
+            "Role" => self
+                .roles
+                .iter()
+                .find(|role| role.metadata.namespace == namespace && role.metadata.name == role_ref.name)
+                .map(|role| role.rules.as_slice())
+                .ok_or_else(|| format!("roles.rbac.authorization.k8s.io {:?} not found", role_ref.name)),
+            "ClusterRole" => self
+                .cluster_roles
+                .iter()
+                .find(|role| role.metadata.name == role_ref.name)
+                .map(|role| role.rules.as_slice())
+                .ok_or_else(|| format!("clusterroles.rbac.authorization.k8s.io {:?} not found", role_ref.name)),
+            kind => Err(format!("unsupported role reference kind: {:?}", kind)),
+        }
+    }
+}
+
+impl Authorizer for RbacAuthorizer {
+    fn authorize(&self, attributes: &Attributes) -> AuthorizationDecision {
+        RbacAuthorizer::authorize(self, attributes)
+    }
+}
+
+impl RuleResolver for RbacAuthorizer {
+    /// Lists the rules granted to the user. RBAC always knows all of them,
+    /// but may fail to resolve the roles of some bindings.
+    fn rules_for(&self, user: &UserInfo, namespace: &str) -> SubjectRulesReviewStatus {
+        let (rules, errors) = RbacAuthorizer::rules_for(self, user, namespace);
+        let mut status = SubjectRulesReviewStatus::default();
+        for rule in rules {
+            if !rule.resources.is_empty() {
+                status.resource_rules.push(ResourceRule {
+                    verbs: rule.verbs.clone(),
+                    api_groups: rule.api_groups.clone(),
+                    resources: rule.resources.clone(),
+                    resource_names: rule.resource_names.clone(),
+                });
+            }
+            if !rule.non_resource_urls.is_empty() {
+                status.non_resource_rules.push(NonResourceRule {
+                    verbs: rule.verbs,
+                    non_resource_urls: rule.non_resource_urls,
+                });
+            }
+        }
+        if !errors.is_empty() {
+            status.evaluation_error = aggregate(errors);
+        }
+        status
+    }
+}
+
+/// Returns the first of `subjects` that `user` is, if any. Service accounts
+/// without a namespace are in `namespace`, the namespace of the binding.
+pub fn applies_to<'a>(user: &UserInfo, subjects: &'a [Subject], namespace: &str) -> Option<&'a Subject> {
+    subjects.iter().find(|subject| applies_to_user(user, subject, namespace))
+}
+
+fn applies_to_user(user: &UserInfo, subject: &Subject, namespace: &str) -> bool {
+    match subject.kind.as_str() {
+        USER_KIND => user.username == subject.name,
+        GROUP_KIND => user.groups.contains(&subject.name),
+        SERVICE_ACCOUNT_KIND => {
+            let namespace = match subject.namespace.as_str() {
+                "" => namespace,
+                subject_namespace => subject_namespace,
+            };
+            !namespace.is_empty()
+                && split_service_account_username(&user.username) == Some((namespace, subject.name.as_str()))
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if `rule` allows the request.
+pub fn rule_allows(attributes: &Attributes, rule: &PolicyRule) -> bool {
+    if !attributes.resource_request {
+        return verb_matches(rule, &attributes.verb) && non_resource_url_matches(rule, &attributes.path);
+    }
+    let combined = match attributes.subresource.as_str() {
+        "" => attributes.resource.clone(),
+        subresource => format!("{}/{}", attributes.resource, subresource),
+    };
+    verb_matches(rule, &attributes.verb)
+        && api_group_matches(rule, &attributes.api_group)
+        && resource_matches(rule, &combined, &attributes.subresource)
+        && resource_name_matches(rule, &attributes.name)
+}
+
+pub fn verb_matches(rule: &PolicyRule, verb: &str) -> bool {
+    rule.verbs.iter().any(|rule_verb| rule_verb == ALL || rule_verb == verb)
+}
+
+pub fn api_group_matches(rule: &PolicyRule, group: &str) -> bool {
+    rule.api_groups.iter().any(|rule_group| rule_group == ALL || rule_group == group)
+}
+
+/// Returns true if the rule allows `resource`, which includes the
+/// subresource, if any, as in `pods/log`. Rules may also allow a
+/// subresource of all resources with `*/<subresource>`.
+pub fn resource_matches(rule: &PolicyRule, resource: &str, subresource: &str) -> bool {
+    rule.resources.iter().any(|rule_resource| {
+        rule_resource == ALL
+            || rule_resource == resource
+            || (!subresource.is_empty() && rule_resource.strip_prefix("*/") == Some(subresource))
+    })
+}
+
+/// Returns true if the rule allows the object `name`. Rules without
+/// `resourceNames` allow all names.
+pub fn resource_name_matches(rule: &PolicyRule, name: &str) -> bool {
+    rule.resource_names.is_empty() || rule.resource_names.iter().any(|rule_name| rule_name == name)
+}
+
+/// Returns true if the rule allows the non-resource `path`. A trailing `*`
+/// in a rule URL matches any path with that prefix.
+pub fn non_resource_url_matches(rule: &PolicyRule, path: &str) -> bool {
+    rule.non_resource_urls.iter().any(|url| {
+        url == ALL
+            || url == path
+            || url.strip_suffix('*').is_some_and(|prefix| path.starts_with(prefix))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorization::{service_account_user, Decision};
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+
+    fn rule(verbs: &[&str], groups: &[&str], resources: &[&str]) -> PolicyRule {
+        let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        PolicyRule {
+            verbs: strings(verbs),
+            api_groups: strings(groups),
+            resources: strings(resources),
+            ..Default::default()
+        }
+    }
+
+    fn subject(kind: &str, name: &str, namespace: &str) -> Subject {
+        Subject {
+            kind: kind.to_string(),
+            api_group: if kind == SERVICE_ACCOUNT_KIND { "" } else { "rbac.authorization.k8s.io" }.to_string(),
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+        }
+    }
+
+    fn role_ref(kind: &str, name: &str) -> RoleRef {
+        RoleRef {
+            api_group: "rbac.authorization.k8s.io".to_string(),
+            kind: kind.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn user(name: &str, groups: &[&str]) -> UserInfo {
+        UserInfo {
+            username: name.to_string(),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn authorizer() -> RbacAuthorizer {
+        let mut metrics = rule(&["get"], &[], &[]);
+        metrics.non_resource_urls = vec!["/metrics".to_string(), "/debug/*".to_string()];
+        let mut named = rule(&["update"], &[""], &["configmaps"]);
+        named.resource_names = vec!["settings".to_string()];
+        RbacAuthorizer::new(
+            vec![Role {
+                metadata: ObjectMeta::namespaced("dev", "editor"),
+                rules: vec![rule(&["get", "list"], &["", "apps"], &["pods", "deployments", "*/scale"]), named],
+                ..Default::default()
+            }],
+            vec![
+                RoleBinding {
+                    metadata: ObjectMeta::namespaced("dev", "editors"),
+                    subjects: vec![subject(USER_KIND, "alice", ""), subject(SERVICE_ACCOUNT_KIND, "builder", "")],
+                    role_ref: role_ref("Role", "editor"),
+                    ..Default::default()
+                },
+                RoleBinding {
+                    metadata: ObjectMeta::namespaced("dev", "broken"),
+                    subjects: vec![subject(GROUP_KIND, "system:serviceaccounts:dev", "")],
+                    role_ref: role_ref("ClusterRole", "missing"),
+                    ..Default::default()
+                },
+            ],
+            vec![
+                ClusterRole {
+                    metadata: ObjectMeta::named("view-logs"),
+                    rules: vec![rule(&["get"], &[""], &["pods/log"])],
+                    ..Default::default()
+                },
+                ClusterRole {
+                    metadata: ObjectMeta::named("monitoring"),
+                    rules: vec![metrics],
+                    ..Default::default()
+                },
+            ],
+            vec![
+                ClusterRoleBinding {
+                    metadata: ObjectMeta::named("ops-logs"),
+                    subjects: vec![subject(GROUP_KIND, "ops", "")],
+                    role_ref: role_ref("ClusterRole", "view-logs"),
+                    ..Default::default()
+                },
+                ClusterRoleBinding {
+                    metadata: ObjectMeta::named("prometheus"),
+                    subjects: vec![subject(SERVICE_ACCOUNT_KIND, "prometheus", "monitoring")],
+                    role_ref: role_ref("ClusterRole", "monitoring"),
+                    ..Default::default()
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn test_authorize_resources() {
+        let authorizer = authorizer();
+        let alice = user("alice", &["system:authenticated"]);
+
+        let decision = authorizer.authorize(&Attributes::resource(alice.clone(), "list", "apps", "deployments").with_namespace("dev"));
+        assert_eq!(decision.decision, Decision::Allow);
+        assert_eq!(
+            decision.reason,
+            "RBAC: allowed by RoleBinding \"editors/dev\" of Role \"editor\" to User \"alice\""
+        );
+        let scale = Attributes::resource(alice.clone(), "get", "apps", "deployments")
+            .with_namespace("dev")
+            .with_subresource("scale");
+        assert_eq!(authorizer.authorize(&scale).decision, Decision::Allow);
+        let status = scale.clone().with_subresource("status");
+        assert_eq!(authorizer.authorize(&status).decision, Decision::NoOpinion);
+        let other = Attributes::resource(alice.clone(), "list", "apps", "deployments").with_namespace("prod");
+        assert_eq!(authorizer.authorize(&other), AuthorizationDecision::no_opinion(""));
+
+        let configmap = Attributes::resource(alice.clone(), "update", "", "configmaps").with_namespace("dev");
+        assert_eq!(authorizer.authorize(&configmap.clone().with_name("settings")).decision, Decision::Allow);
+        assert_eq!(authorizer.authorize(&configmap.with_name("other")).decision, Decision::NoOpinion);
+
+        let logs = Attributes::resource(user("bob", &["ops"]), "get", "", "pods")
+            .with_namespace("prod")
+            .with_name("web")
+            .with_subresource("log");
+        let decision = authorizer.authorize(&logs);
+        assert_eq!(
+            decision.reason,
+            "RBAC: allowed by ClusterRoleBinding \"ops-logs\" of ClusterRole \"view-logs\" to Group \"ops\""
+        );
+        assert_eq!(authorizer.authorize(&logs.with_subresource("exec")).decision, Decision::NoOpinion);
+    }
+
+    #[test]
+    fn test_authorize_service_accounts_and_non_resource() {
+        let authorizer = authorizer();
+
+        // The builder is bound by name, and the broken binding to all
+        // service accounts of dev refers to a missing role.
+        let builder = service_account_user("dev", "builder");
+        let pods = Attributes::resource(builder.clone(), "get", "", "pods").with_namespace("dev");
+        let decision = authorizer.authorize(&pods);
+        assert_eq!(
+            decision.reason,
+            "RBAC: allowed by RoleBinding \"editors/dev\" of Role \"editor\" to ServiceAccount \"builder/dev\""
+        );
+        let decision = authorizer.authorize(&Attributes::resource(builder, "delete", "", "pods").with_namespace("dev"));
+        assert_eq!(
+            decision,
+            AuthorizationDecision::no_opinion("RBAC: clusterroles.rbac.authorization.k8s.io \"missing\" not found")
+        );
+        let other = service_account_user("prod", "builder");
+        assert_eq!(
+            authorizer.authorize(&Attributes::resource(other, "get", "", "pods").with_namespace("dev")),
+            AuthorizationDecision::no_opinion("")
+        );
+
+        let prometheus = service_account_user("monitoring", "prometheus");
+        let decision = authorizer.authorize(&Attributes::non_resource(prometheus.clone(), "get", "/metrics"));
+        assert_eq!(
+            decision.reason,
+            "RBAC: allowed by ClusterRoleBinding \"prometheus\" of ClusterRole \"monitoring\" to ServiceAccount \"prometheus/monitoring\""
+        );
+        let debug = Attributes::non_resource(prometheus.clone(), "get", "/debug/pprof/heap");
+        assert_eq!(authorizer.authorize(&debug).decision, Decision::Allow);
+        let post = Attributes::non_resource(prometheus.clone(), "post", "/metrics");
+        assert_eq!(authorizer.authorize(&post).decision, Decision::NoOpinion);
+        let healthz = Attributes::non_resource(prometheus, "get", "/healthz");
+        assert_eq!(authorizer.authorize(&healthz).decision, Decision::NoOpinion);
+    }
+}
@@ -0,0 +1,466 @@
+//! Access reviews
+//!
+//! Answers the reviews of the `authorization.k8s.io` API the way the API
+//! server does: SubjectAccessReviews and their local and self variants ask
+//! an `Authorizer` about a single request, SelfSubjectRulesReviews ask a
+//! `RuleResolver` for everything the requesting user may do in a
+//! namespace. Authorization webhooks receive SubjectAccessReviews too.
+
+use k8s_api::authentication::v1::UserInfo;
+use k8s_api::authorization::v1::{
+    LocalSubjectAccessReview, NonResourceAttributes, ResourceAttributes, SelfSubjectAccessReview,
+    SelfSubjectRulesReview, SubjectAccessReview, SubjectAccessReviewSpec, SubjectAccessReviewStatus,
+    SubjectRulesReviewStatus,
+};
+use k8s_apimachinery::apis::meta::v1::{
+    FieldSelectorRequirement, LabelSelectorRequirement, FIELD_SELECTOR_OP_DOES_NOT_EXIST, FIELD_SELECTOR_OP_EXISTS,
+    FIELD_SELECTOR_OP_IN, FIELD_SELECTOR_OP_NOT_IN,
+};
+
+use super::{Attributes, Authorizer, Decision, RuleResolver};
+
+/// Returns the attributes of the request a SubjectAccessReview asks about.
+/// Resource requests without a version are for all versions.
+pub fn attributes_from_spec(spec: &SubjectAccessReviewSpec) -> Attributes {
+    let user = UserInfo {
+        username: spec.user.clone(),
+        uid: spec.uid.clone(),
+        groups: spec.groups.clone(),
+        extra: spec.extra.clone(),
+    };
+    match (&spec.resource_attributes, &spec.non_resource_attributes) {
+        (Some(resource), _) => resource_attributes(user, resource),
+        (None, Some(non_resource)) => non_resource_attributes(user, non_resource),
+        (None, None) => Attributes {
+            user,
+            ..Default::default()
+        },
+    }
+}
+
+fn resource_attributes(user: UserInfo, resource: &ResourceAttributes) -> Attributes {
+    let mut attributes = Attributes::resource(user, &resource.verb, &resource.group, &resource.resource)
+        .with_namespace(&resource.namespace)
+        .with_name(&resource.name)
+        .with_subresource(&resource.subresource)
+        .with_api_version(match resource.version.as_str() {
+            "" => "*",
+            version => version,
+        });
+    // Parsed requirements take precedence over the raw selector.
+    if let Some(field_selector) = &resource.field_selector {
+        if !field_selector.requirements.is_empty() {
+            match field_requirements(&field_selector.requirements) {
+                Ok(requirements) => attributes.field_selector = requirements,
+                Err(err) => attributes.field_selector_error = Some(err),
+            }
+        } else if !field_selector.raw_selector.is_empty() {
+            attributes = attributes.with_field_selector(&field_selector.raw_selector);
+        }
+    }
+    if let Some(label_selector) = &resource.label_selector {
+        if !label_selector.requirements.is_empty() {
+            match label_requirements(&label_selector.requirements) {
+                Ok(requirements) => attributes.label_selector = requirements,
+                Err(err) => attributes.label_selector_error = Some(err),
+            }
+        } else if !label_selector.raw_selector.is_empty() {
+            attributes = attributes.with_label_selector(&label_selector.raw_selector);
+        }
+    }
+    attributes
+}
+
+fn non_resource_attributes(user: UserInfo, non_resource: &NonResourceAttributes) -> Attributes {
+    Attributes::non_resource(user, &non_resource.verb, &non_resource.path)
+}
+
+/// Checks field selector requirements: authorizers only understand
+/// equality, as `In` or `NotIn` with a single value.
+fn field_requirements(requirements: &[FieldSelectorRequirement]) -> Result<Vec<FieldSelectorRequirement>, String> {
+    for requirement in requirements {
+        match requirement.operator.as_str() {
+            FIELD_SELECTOR_OP_IN | FIELD_SELECTOR_OP_NOT_IN if requirement.values.len() != 1 => {
+                return Err(format!(
+                    "fieldSelectors {} must have one value",
+                    requirement.operator.to_lowercase()
+                ));
+            }
+            FIELD_SELECTOR_OP_IN | FIELD_SELECTOR_OP_NOT_IN => {}
+            FIELD_SELECTOR_OP_EXISTS | FIELD_SELECTOR_OP_DOES_NOT_EXIST => {
+                return Err(format!("fieldSelectors do not yet support {}", requirement.operator));
+            }
+            operator => return Err(format!("{:?} is not a valid field selector operator", operator)),
+        }
+    }
+    Ok(requirements.to_vec())
+}
+
+fn label_requirements(requirements: &[LabelSelectorRequirement]) -> Result<Vec<LabelSelectorRequirement>, String> {
+    for requirement in requirements {
+        match requirement.operator.as_str() {
+            "In" | "NotIn" if requirement.values.is_empty() => {
+                return Err("for 'in', 'notin' operators, values set can't be empty".to_string());
+            }
+            "Exists" | "DoesNotExist" if !requirement.values.is_empty() => {
+                return Err("values set must be empty for exists and does not exist".to_string());
+            }
+            "In" | "NotIn" | "Exists" | "DoesNotExist" => {}
+            operator => return Err(format!("{:?} is not a valid label selector operator", operator)),
+        }
+    }
+    Ok(requirements.to_vec())
+}
+
+/// Returns the field errors of the attributes a review asks about.
+fn validate_attributes(
+    resource: Option<&ResourceAttributes>,
+    non_resource: Option<&NonResourceAttributes>,
+    errors: &mut Vec<String>,
+) {
+    match (resource, non_resource) {
+        (None, None) => errors.push(
+            "spec.resourceAttributes: Required value: exactly one of nonResourceAttributes or resourceAttributes must be specified"
+                .to_string(),
+        ),
+        (Some(_), Some(_)) => errors.push(
+            "spec.nonResourceAttributes: Invalid value: \"object\": cannot be specified in combination with resourceAttributes"
+                .to_string(),
+        ),
+        _ => {}
+    }
+    let Some(resource) = resource else {
+        return;
+    };
+    let selectors = [
+        (
+            "fieldSelector",
+            resource.field_selector.as_ref().map(|s| (&s.raw_selector, s.requirements.is_empty())),
+        ),
+        (
+            "labelSelector",
+            resource.label_selector.as_ref().map(|s| (&s.raw_selector, s.requirements.is_empty())),
+        ),
+    ];
+    for (name, selector) in selectors {
+        let path = format!("spec.resourceAttributes.{}", name);
+        match selector {
+            Some((raw, false)) if !raw.is_empty() => errors.push(format!(
+                "{}.rawSelector: Invalid value: {:?}: may not specified at the same time as requirements",
+                path, raw
+            )),
+            Some((raw, true)) if raw.is_empty() => errors.push(format!(
+                "{}.requirements: Required value: when {} is specified, requirements or rawSelector is required",
+                path, path
+            )),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the Invalid error of a review of `kind` with field `errors`.
+fn invalid(kind: &str, errors: Vec<String>) -> Result<(), String> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(format!("{}.authorization.k8s.io \"\" is invalid: {}", kind, errors[0])),
+        _ => Err(format!("{}.authorization.k8s.io \"\" is invalid: [{}]", kind, errors.join(", "))),
+    }
+}
+
+fn status(authorizer: &dyn Authorizer, attributes: &Attributes) -> SubjectAccessReviewStatus {
+    let decision = authorizer.authorize(attributes);
+    SubjectAccessReviewStatus {
+        allowed: decision.decision == Decision::Allow,
+        denied: decision.decision == Decision::Deny,
+        reason: decision.reason,
+        evaluation_error: decision.error.unwrap_or_default(),
+    }
+}
+
+/// Answers a SubjectAccessReview: may the user of the spec make the
+/// request it describes?
+pub fn review_subject_access(
+    authorizer: &dyn Authorizer,
+    review: &SubjectAccessReview,
+) -> Result<SubjectAccessReviewStatus, String> {
+    let spec = &review.spec;
+    let mut errors = Vec::new();
+    validate_attributes(spec.resource_attributes.as_ref(), spec.non_resource_attributes.as_ref(), &mut errors);
+    if spec.user.is_empty() && spec.groups.is_empty() {
+        errors.push("spec.user: Invalid value: \"\": at least one of user or group must be specified".to_string());
+    }
+    invalid("SubjectAccessReview", errors)?;
+    Ok(status(authorizer, &attributes_from_spec(spec)))
+}
+
+/// Answers a LocalSubjectAccessReview, a SubjectAccessReview about a
+/// request in the namespace of the review.
+pub fn review_local_subject_access(
+    authorizer: &dyn Authorizer,
+    review: &LocalSubjectAccessReview,
+) -> Result<SubjectAccessReviewStatus, String> {
+    let spec = &review.spec;
+    let mut errors = Vec::new();
+    validate_attributes(spec.resource_attributes.as_ref(), spec.non_resource_attributes.as_ref(), &mut errors);
+    if spec.non_resource_attributes.is_some() {
+        errors.push(
+            "spec.nonResourceAttributes: Invalid value: \"object\": disallowed on this kind of request".to_string(),
+        );
+    }
+    if spec.user.is_empty() && spec.groups.is_empty() {
+        errors.push("spec.user: Invalid value: \"\": at least one of user or group must be specified".to_string());
+    }
+    invalid("LocalSubjectAccessReview", errors)?;
+    let namespace = &review.metadata.namespace;
+    if spec.resource_attributes.as_ref().is_some_and(|resource| &resource.namespace != namespace) {
+        return Err(format!("spec.resourceAttributes.namespace must match namespace: {}", namespace));
+    }
+    Ok(status(authorizer, &attributes_from_spec(spec)))
+}
+
+/// Answers a SelfSubjectAccessReview: may `user`, who made the review,
+/// make the request it describes?
+pub fn review_self_subject_access(
+    authorizer: &dyn Authorizer,
+    user: &UserInfo,
+    review: &SelfSubjectAccessReview,
+) -> Result<SubjectAccessReviewStatus, String> {
+    let spec = &review.spec;
+    let mut errors = Vec::new();
+    validate_attributes(spec.resource_attributes.as_ref(), spec.non_resource_attributes.as_ref(), &mut errors);
+    invalid("SelfSubjectAccessReview", errors)?;
+    let attributes = match (&spec.resource_attributes, &spec.non_resource_attributes) {
+        (Some(resource), _) => resource_attributes(user.clone(), resource),
+        (None, Some(non_resource)) => non_resource_attributes(user.clone(), non_resource),
+        (None, None) => unreachable!("validated above"),
+    };
+    Ok(status(authorizer, &attributes))
+}
+
+/// Answers a SelfSubjectRulesReview: what may `user`, who made the review,
+/// do in the namespace of its spec?
+pub fn review_self_subject_rules(
+    resolver: &dyn RuleResolver,
+    user: &UserInfo,
+    review: &SelfSubjectRulesReview,
+) -> Result<SubjectRulesReviewStatus, String> {
+    if review.spec.namespace.is_empty() {
+        return Err("no namespace on request".to_string());
+    }
+    Ok(resolver.rules_for(user, &review.spec.namespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorization::rbac::RbacAuthorizer;
+    use crate::authorization::{AuthorizationDecision, UnionAuthorizer};
+    use k8s_api::authorization::v1::{
+        FieldSelectorAttributes, LabelSelectorAttributes, NonResourceRule, ResourceRule, SelfSubjectRulesReviewSpec,
+    };
+    use k8s_api::rbac::v1::{ClusterRole, ClusterRoleBinding, PolicyRule, RoleRef, Subject};
+    use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+    use std::sync::{Arc, Mutex};
+
+    fn rbac() -> RbacAuthorizer {
+        RbacAuthorizer::new(
+            Vec::new(),
+            Vec::new(),
+            vec![ClusterRole {
+                metadata: ObjectMeta::named("reader"),
+                rules: vec![
+                    PolicyRule {
+                        verbs: vec!["get".to_string(), "list".to_string()],
+                        api_groups: vec![String::new()],
+                        resources: vec!["pods".to_string()],
+                        ..Default::default()
+                    },
+                    PolicyRule {
+                        verbs: vec!["get".to_string()],
+                        non_resource_urls: vec!["/healthz".to_string()],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            vec![
+                ClusterRoleBinding {
+                    metadata: ObjectMeta::named("readers"),
+                    subjects: vec![Subject {
+                        kind: "Group".to_string(),
+                        name: "readers".to_string(),
+                        ..Default::default()
+                    }],
+                    role_ref: RoleRef {
+                        kind: "ClusterRole".to_string(),
+                        name: "reader".to_string(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ClusterRoleBinding {
+                    metadata: ObjectMeta::named("broken"),
+                    subjects: vec![Subject {
+                        kind: "Group".to_string(),
+                        name: "readers".to_string(),
+                        ..Default::default()
+                    }],
+                    role_ref: RoleRef {
+                        kind: "ClusterRole".to_string(),
+                        name: "missing".to_string(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ],
+        )
+    }
+
+    /// Records the attributes it is asked about and denies secrets.
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Attributes>>);
+
+    impl Authorizer for Recorder {
+        fn authorize(&self, attributes: &Attributes) -> AuthorizationDecision {
+            self.0.lock().unwrap().push(attributes.clone());
+            match attributes.resource.as_str() {
+                "secrets" => AuthorizationDecision::deny("secrets are off limits"),
+                _ => AuthorizationDecision::no_opinion("no opinion on anything else"),
+            }
+        }
+    }
+
+    fn resource(verb: &str, resource: &str) -> ResourceAttributes {
+        ResourceAttributes {
+            namespace: "dev".to_string(),
+            verb: verb.to_string(),
+            resource: resource.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_review_subject_access() {
+        let recorder = Arc::new(Recorder::default());
+        let authorizer = UnionAuthorizer::new(vec![recorder.clone(), Arc::new(rbac())]);
+        let mut review = SubjectAccessReview {
+            spec: SubjectAccessReviewSpec {
+                resource_attributes: Some(resource("list", "pods")),
+                groups: vec!["readers".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let status = review_subject_access(&authorizer, &review).unwrap();
+        assert!(status.allowed && !status.denied);
+        assert_eq!(
+            status.reason,
+            "RBAC: allowed by ClusterRoleBinding \"readers\" of ClusterRole \"reader\" to Group \"readers\""
+        );
+
+        review.spec.resource_attributes = Some(resource("get", "secrets"));
+        let status = review_subject_access(&authorizer, &review).unwrap();
+        assert!(!status.allowed && status.denied);
+        assert_eq!(status.reason, "secrets are off limits");
+
+        review.spec.resource_attributes = Some(resource("delete", "pods"));
+        let status = review_subject_access(&authorizer, &review).unwrap();
+        assert!(!status.allowed && !status.denied);
+        assert_eq!(
+            status.reason,
+            "no opinion on anything else\nRBAC: clusterroles.rbac.authorization.k8s.io \"missing\" not found"
+        );
+
+        let mut selected = resource("list", "pods");
+        selected.field_selector = Some(FieldSelectorAttributes {
+            raw_selector: "spec.nodeName=node-1".to_string(),
+            ..Default::default()
+        });
+        selected.label_selector = Some(LabelSelectorAttributes {
+            raw_selector: "app in (web".to_string(),
+            ..Default::default()
+        });
+        review.spec.resource_attributes = Some(selected);
+        review_subject_access(&authorizer, &review).unwrap();
+        let attributes = recorder.0.lock().unwrap().pop().unwrap();
+        assert_eq!(attributes.api_version, "*");
+        assert_eq!(attributes.field_selector[0].key, "spec.nodeName");
+        assert!(attributes.label_selector.is_empty() && attributes.label_selector_error.is_some());
+
+        review.spec.non_resource_attributes = Some(NonResourceAttributes {
+            path: "/healthz".to_string(),
+            verb: "get".to_string(),
+        });
+        review.spec.groups.clear();
+        assert_eq!(
+            review_subject_access(&authorizer, &review).unwrap_err(),
+            "SubjectAccessReview.authorization.k8s.io \"\" is invalid: [spec.nonResourceAttributes: Invalid value: \
+             \"object\": cannot be specified in combination with resourceAttributes, spec.user: Invalid value: \"\": \
+             at least one of user or group must be specified]"
+        );
+    }
+
+    #[test]
+    fn test_review_local_and_self() {
+        let authorizer = rbac();
+        let reader = UserInfo {
+            username: "alice".to_string(),
+            groups: vec!["readers".to_string()],
+            ..Default::default()
+        };
+        let review = SelfSubjectAccessReview {
+            spec: k8s_api::authorization::v1::SelfSubjectAccessReviewSpec {
+                non_resource_attributes: Some(NonResourceAttributes {
+                    path: "/healthz".to_string(),
+                    verb: "get".to_string(),
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(review_self_subject_access(&authorizer, &reader, &review).unwrap().allowed);
+
+        let mut local = LocalSubjectAccessReview {
+            metadata: ObjectMeta::namespaced("prod", ""),
+            spec: SubjectAccessReviewSpec {
+                resource_attributes: Some(resource("get", "pods")),
+                user: "alice".to_string(),
+                groups: vec!["readers".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            review_local_subject_access(&authorizer, &local).unwrap_err(),
+            "spec.resourceAttributes.namespace must match namespace: prod"
+        );
+        local.metadata.namespace = "dev".to_string();
+        assert!(review_local_subject_access(&authorizer, &local).unwrap().allowed);
+
+        let rules = SelfSubjectRulesReview {
+            spec: SelfSubjectRulesReviewSpec {
+                namespace: "dev".to_string(),
+            },
+            ..Default::default()
+        };
+        let status = review_self_subject_rules(&authorizer, &reader, &rules).unwrap();
+        assert_eq!(
+            status.resource_rules,
+            [ResourceRule {
+                verbs: vec!["get".to_string(), "list".to_string()],
+                api_groups: vec![String::new()],
+                resources: vec!["pods".to_string()],
+                resource_names: Vec::new(),
+            }]
+        );
+        assert_eq!(
+            status.non_resource_rules,
+            [NonResourceRule {
+                verbs: vec!["get".to_string()],
+                non_resource_urls: vec!["/healthz".to_string()],
+            }]
+        );
+        assert!(!status.incomplete);
+        assert_eq!(status.evaluation_error, "clusterroles.rbac.authorization.k8s.io \"missing\" not found");
+    }
+}
@@ -0,0 +1,8 @@
+//! Kubernetes API server libraries
+//!
+//! This crate provides the request-time logic of the Kubernetes API server
+//! that is useful outside of it, such as matching and evaluating admission
+//! policies against admission requests and authorizing requests with RBAC.
+
+pub mod admission;
+pub mod authorization;
@@ -0,0 +1,166 @@
+//! Abstract syntax tree of parsed expressions
+//!
+//! Operators are represented as calls to functions with the names cel-go
+//! uses (`_+_`, `_[_]`, `_?_:_`, `@in`, ...), and macros are expanded into
+//! comprehensions by the parser.
+
+use std::collections::HashMap;
+
+pub type ExprId = u64;
+
+/// Name of the accumulator variable introduced by macro expansion.
+pub const ACCUMULATOR_VAR: &str = "@result";
+
+pub mod operators {
+    pub const CONDITIONAL: &str = "_?_:_";
+    pub const LOGICAL_AND: &str = "_&&_";
+    pub const LOGICAL_OR: &str = "_||_";
+    pub const LOGICAL_NOT: &str = "!_";
+    pub const NOT_STRICTLY_FALSE: &str = "@not_strictly_false";
+    pub const EQUALS: &str = "_==_";
+    pub const NOT_EQUALS: &str = "_!=_";
+    pub const LESS: &str = "_<_";
+    pub const LESS_EQUALS: &str = "_<=_";
+    pub const GREATER: &str = "_>_";
+    pub const GREATER_EQUALS: &str = "_>=_";
+    pub const ADD: &str = "_+_";
+    pub const SUBTRACT: &str = "_-_";
+    pub const MULTIPLY: &str = "_*_";
+    pub const DIVIDE: &str = "_/_";
+    pub const MODULO: &str = "_%_";
+    pub const NEGATE: &str = "-_";
+    pub const INDEX: &str = "_[_]";
+    pub const OPT_INDEX: &str = "_[?_]";
+    pub const OPT_SELECT: &str = "_?._";
+    pub const IN: &str = "@in";
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    pub id: ExprId,
+    pub kind: ExprKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
+    Const(Constant),
+    Ident(String),
+    Select {
+        operand: Box<Expr>,
+        field: String,
+        /// Set for `has(a.b)`, which tests for presence instead of selecting.
+        test_only: bool,
+    },
+    Call {
+        target: Option<Box<Expr>>,
+        function: String,
+        args: Vec<Expr>,
+    },
+    List {
+        elements: Vec<Expr>,
+        /// Indices of `?elem` entries, which are only added when present.
+        optional_indices: Vec<usize>,
+    },
+    Map {
+        entries: Vec<MapEntry>,
+    },
+    Struct {
+        type_name: String,
+        fields: Vec<StructField>,
+    },
+    Comprehension(Box<Comprehension>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapEntry {
+    pub key: Expr,
+    pub value: Expr,
+    pub optional: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructField {
+    pub name: String,
+    pub value: Expr,
+    pub optional: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comprehension {
+    pub iter_var: String,
+    pub iter_range: Expr,
+    pub accu_var: String,
+    pub accu_init: Expr,
+    pub loop_condition: Expr,
+    pub loop_step: Expr,
+    pub result: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+/// Ast is a parsed expression together with the source offsets of its nodes.
+#[derive(Clone, Debug)]
+pub struct Ast {
+    pub expr: Expr,
+    pub(crate) offsets: HashMap<ExprId, usize>,
+}
+
+impl Ast {
+    /// Returns the source offset of the node with `id`.
+    pub fn offset(&self, id: ExprId) -> usize {
+        self.offsets.get(&id).copied().unwrap_or(0)
+    }
+}
+
+impl Expr {
+    /// Returns the dotted name of an identifier or a chain of field
+    /// selections on an identifier, e.g. `a.b.c`.
+    pub(crate) fn qualified_name(&self) -> Option<String> {
+        match &self.kind {
+            ExprKind::Ident(name) => Some(name.clone()),
+            ExprKind::Select {
+                operand,
+                field,
+                test_only: false,
+            } => operand.qualified_name().map(|prefix| format!("{}.{}", prefix, field)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the identifier `name` appears anywhere in the
+    /// expression.
+    pub fn references(&self, name: &str) -> bool {
+        match &self.kind {
+            ExprKind::Const(_) => false,
+            ExprKind::Ident(ident) => ident == name,
+            ExprKind::Select { operand, .. } => operand.references(name),
+            ExprKind::Call { target, args, .. } => {
+                target.as_ref().is_some_and(|target| target.references(name))
+                    || args.iter().any(|arg| arg.references(name))
+            }
+            ExprKind::List { elements, .. } => elements.iter().any(|element| element.references(name)),
+            ExprKind::Map { entries } => entries
+                .iter()
+                .any(|entry| entry.key.references(name) || entry.value.references(name)),
+            ExprKind::Struct { fields, .. } => fields.iter().any(|field| field.value.references(name)),
+            ExprKind::Comprehension(comprehension) => [
+                &comprehension.iter_range,
+                &comprehension.accu_init,
+                &comprehension.loop_condition,
+                &comprehension.loop_step,
+                &comprehension.result,
+            ]
+            .iter()
+            .any(|expr| expr.references(name)),
+        }
+    }
+}
@@ -0,0 +1,377 @@
+//! Type checker
+//!
+//! Infers the type of every node, reports undeclared references, unknown
+//! fields and calls without a matching overload, and resolves qualified
+//! names: `optional.none()` becomes a call to the global `optional.none`,
+//! and `variables.foo` becomes a reference to a variable declared with that
+//! dotted name. `dyn` checks against everything.
+
+use std::collections::HashMap;
+
+use crate::ast::{operators, Ast, Constant, Expr, ExprId, ExprKind};
+use crate::error::CompileError;
+use crate::library::DECLARATIONS;
+use crate::types::{type_identifier, Type};
+use crate::Env;
+
+/// Checks `ast` in `env`, returning the type of every node.
+pub(crate) fn check(ast: &mut Ast, env: &Env, errors: &mut CompileError) -> HashMap<ExprId, Type> {
+    let mut checker = Checker {
+        env,
+        offsets: &ast.offsets,
+        errors,
+        scopes: Vec::new(),
+        types: HashMap::new(),
+    };
+    checker.check(&mut ast.expr);
+    checker.types
+}
+
+struct Checker<'a> {
+    env: &'a Env,
+    offsets: &'a HashMap<ExprId, usize>,
+    errors: &'a mut CompileError,
+    scopes: Vec<(String, Type)>,
+    types: HashMap<ExprId, Type>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, id: ExprId, message: impl Into<String>) -> Type {
+        let offset = self.offsets.get(&id).copied().unwrap_or(0);
+        self.errors.push(offset, message);
+        Type::Error
+    }
+
+    fn local(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find(|(local, _)| local == name).map(|(_, ty)| ty)
+    }
+
+    /// Returns true if `name` refers to a local or declared variable, so it
+    /// must not be reinterpreted as part of a qualified name.
+    fn is_variable(&self, name: &str) -> bool {
+        self.local(name).is_some() || self.env.variables.contains_key(name)
+    }
+
+    fn root_is_variable(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Ident(name) => self.is_variable(name),
+            ExprKind::Select { operand, .. } => self.root_is_variable(operand),
+            _ => true,
+        }
+    }
+
+    fn check(&mut self, expr: &mut Expr) -> Type {
+        let ty = self.check_kind(expr);
+        self.types.insert(expr.id, ty.clone());
+        ty
+    }
+
+    fn check_kind(&mut self, expr: &mut Expr) -> Type {
+        let id = expr.id;
+        match &mut expr.kind {
+            ExprKind::Const(constant) => match constant {
+                Constant::Null => Type::Null,
+                Constant::Bool(_) => Type::Bool,
+                Constant::Int(_) => Type::Int,
+                Constant::Uint(_) => Type::Uint,
+                Constant::Double(_) => Type::Double,
+                Constant::String(_) => Type::String,
+                Constant::Bytes(_) => Type::Bytes,
+            },
+            ExprKind::Ident(name) => {
+                let name = name.clone();
+                if let Some(ty) = self.local(&name) {
+                    return ty.clone();
+                }
+                if let Some(ty) = self.env.variables.get(&name) {
+                    return ty.clone();
+                }
+                if let Some(ty) = type_identifier(&name) {
+                    return Type::Type(Box::new(ty));
+                }
+                if let Some(struct_type) = self.env.types.get(&name) {
+                    return Type::Type(Box::new(Type::Struct(struct_type.clone())));
+                }
+                self.error(id, format!("undeclared reference to '{}' (in container '')", name))
+            }
+            ExprKind::Select { .. } => {
+                if let Some(qualified) = expr.qualified_name() {
+                    // A declared dotted name such as `authorizer.requestResource`
+                    // wins over selecting a field of its root, unless the root
+                    // is a comprehension variable.
+                    let root_is_local = qualified
+                        .split('.')
+                        .next()
+                        .is_some_and(|root| self.local(root).is_some());
+                    if !root_is_local {
+                        if let Some(ty) = self.env.variables.get(&qualified).cloned() {
+                            expr.kind = ExprKind::Ident(qualified);
+                            return ty;
+                        }
+                    }
+                    if !self.root_is_variable(expr) {
+                        if let Some(ty) = type_identifier(&qualified) {
+                            expr.kind = ExprKind::Ident(qualified);
+                            return Type::Type(Box::new(ty));
+                        }
+                    }
+                }
+                let ExprKind::Select {
+                    operand,
+                    field,
+                    test_only,
+                } = &mut expr.kind
+                else {
+                    unreachable!("checked above");
+                };
+                let field = field.clone();
+                let test_only = *test_only;
+                let operand_type = self.check(operand);
+                let field_type = self.select_field(id, &operand_type, &field);
+                if test_only {
+                    Type::Bool
+                } else {
+                    field_type
+                }
+            }
+            ExprKind::Call { .. } => self.check_call(expr),
+            ExprKind::List {
+                elements,
+                optional_indices,
+            } => {
+                let optional_indices = optional_indices.clone();
+                let mut elem_type: Option<Type> = None;
+                for (idx, element) in elements.iter_mut().enumerate() {
+                    let mut ty = self.check(element);
+                    if optional_indices.contains(&idx) {
+                        ty = match ty {
+                            Type::Optional(inner) => *inner,
+                            Type::Dyn | Type::Error => Type::Dyn,
+                            other => self.error(
+                                element.id,
+                                format!("expected type 'optional_type' but found '{}'", other),
+                            ),
+                        };
+                    }
+                    elem_type = Some(match elem_type {
+                        Some(current) => current.join(&ty),
+                        None => ty,
+                    });
+                }
+                Type::list(elem_type.unwrap_or(Type::Dyn))
+            }
+            ExprKind::Map { entries } => {
+                let mut key_type: Option<Type> = None;
+                let mut value_type: Option<Type> = None;
+                for entry in entries.iter_mut() {
+                    let key = self.check(&mut entry.key);
+                    if !matches!(key, Type::Bool | Type::Int | Type::Uint | Type::String | Type::Dyn | Type::Error) {
+                        self.error(entry.key.id, format!("unsupported map key type: {}", key));
+                    }
+                    let mut value = self.check(&mut entry.value);
+                    if entry.optional {
+                        value = match value {
+                            Type::Optional(inner) => *inner,
+                            _ => Type::Dyn,
+                        };
+                    }
+                    key_type = Some(key_type.map(|k| k.join(&key)).unwrap_or(key));
+                    value_type = Some(value_type.map(|v| v.join(&value)).unwrap_or(value));
+                }
+                Type::map(key_type.unwrap_or(Type::Dyn), value_type.unwrap_or(Type::Dyn))
+            }
+            ExprKind::Struct { type_name, fields } => {
+                let type_name = type_name.clone();
+                let struct_type = self.env.types.get(&type_name).cloned();
+                let dynamic = self.env.is_dynamic_object_type(&type_name);
+                for field in fields.iter_mut() {
+                    let mut ty = self.check(&mut field.value);
+                    if field.optional {
+                        ty = match ty {
+                            Type::Optional(inner) => *inner,
+                            _ => Type::Dyn,
+                        };
+                    }
+                    if let Some(struct_type) = &struct_type {
+                        match struct_type.fields.get(&field.name) {
+                            Some(expected) if !expected.is_assignable_from(&ty) => {
+                                self.error(
+                                    field.value.id,
+                                    format!(
+                                        "expected type of field '{}' is '{}' but provided type is '{}'",
+                                        field.name, expected, ty
+                                    ),
+                                );
+                            }
+                            Some(_) => {}
+                            None => {
+                                self.error(field.value.id, format!("undefined field '{}'", field.name));
+                            }
+                        }
+                    }
+                }
+                match struct_type {
+                    Some(struct_type) => Type::Struct(struct_type),
+                    None if dynamic => Type::Dyn,
+                    None => self.error(id, format!("undeclared reference to '{}' (in container '')", type_name)),
+                }
+            }
+            ExprKind::Comprehension(comprehension) => {
+                let range_type = self.check(&mut comprehension.iter_range);
+                let iter_type = match &range_type {
+                    Type::List(elem) => (**elem).clone(),
+                    Type::Map(key, _) => (**key).clone(),
+                    Type::Dyn | Type::Error => Type::Dyn,
+                    other => self.error(
+                        comprehension.iter_range.id,
+                        format!(
+                            "expression of type '{}' cannot be range of a comprehension (must be list, map, or dynamic)",
+                            other
+                        ),
+                    ),
+                };
+                let mut accu_type = self.check(&mut comprehension.accu_init);
+                self.scopes.push((comprehension.accu_var.clone(), accu_type.clone()));
+                self.scopes.push((comprehension.iter_var.clone(), iter_type));
+                self.check(&mut comprehension.loop_condition);
+                let step_type = self.check(&mut comprehension.loop_step);
+                self.scopes.pop();
+                self.scopes.pop();
+                if matches!(&accu_type, Type::List(elem) if elem.is_dyn_or_error()) {
+                    if let Type::List(_) = step_type {
+                        accu_type = step_type;
+                    }
+                }
+                self.scopes.push((comprehension.accu_var.clone(), accu_type));
+                let result = self.check(&mut comprehension.result);
+                self.scopes.pop();
+                result
+            }
+        }
+    }
+
+    fn select_field(&mut self, id: ExprId, operand: &Type, field: &str) -> Type {
+        match operand {
+            Type::Struct(struct_type) => match struct_type.fields.get(field) {
+                Some(ty) => ty.clone(),
+                None => self.error(id, format!("undefined field '{}'", field)),
+            },
+            Type::Map(key, value) if matches!(**key, Type::String | Type::Dyn | Type::Param(_)) => (**value).clone(),
+            Type::Optional(inner) => {
+                let inner = self.select_field(id, inner, field);
+                Type::optional(inner)
+            }
+            Type::Dyn | Type::Error => Type::Dyn,
+            other => self.error(id, format!("type '{}' does not support field selection", other)),
+        }
+    }
+
+    fn check_call(&mut self, expr: &mut Expr) -> Type {
+        let id = expr.id;
+        // Namespaced global functions, e.g. `optional.none()` or `sets.contains(a, b)`.
+        if let ExprKind::Call {
+            target: Some(target),
+            function,
+            args,
+        } = &mut expr.kind
+        {
+            if let Some(namespace) = target.qualified_name() {
+                let qualified = format!("{}.{}", namespace, function);
+                if !self.root_is_variable(target) && DECLARATIONS.has_global(&qualified) {
+                    let args = std::mem::take(args);
+                    expr.kind = ExprKind::Call {
+                        target: None,
+                        function: qualified,
+                        args,
+                    };
+                }
+            }
+        }
+
+        let ExprKind::Call { target, function, args } = &mut expr.kind else {
+            unreachable!("check_call is only called for calls");
+        };
+        let function = function.clone();
+
+        match function.as_str() {
+            operators::CONDITIONAL if target.is_none() && args.len() == 3 => {
+                let condition = self.check(&mut args[0]);
+                if !Type::Bool.is_assignable_from(&condition) {
+                    self.error(args[0].id, format!("expected type 'bool' but found '{}'", condition));
+                }
+                let truthy = self.check(&mut args[1]);
+                let falsy = self.check(&mut args[2]);
+                return truthy.join(&falsy);
+            }
+            operators::OPT_SELECT if target.is_none() && args.len() == 2 => {
+                let operand = self.check(&mut args[0]);
+                self.check(&mut args[1]);
+                let field = match &args[1].kind {
+                    ExprKind::Const(Constant::String(field)) => field.clone(),
+                    _ => return self.error(id, "unsupported optional field selection"),
+                };
+                let field_type = match operand {
+                    Type::Optional(inner) => self.select_field(id, &inner, &field),
+                    other => self.select_field(id, &other, &field),
+                };
+                return match field_type {
+                    Type::Optional(_) => field_type,
+                    Type::Error => Type::Error,
+                    other => Type::optional(other),
+                };
+            }
+            _ => {}
+        }
+
+        let mut arg_types = Vec::with_capacity(args.len() + 1);
+        let receiver = target.is_some();
+        if let Some(target) = target {
+            arg_types.push(self.check(target));
+        }
+        for arg in args.iter_mut() {
+            arg_types.push(self.check(arg));
+        }
+        self.resolve_overload(id, &function, receiver, &arg_types)
+    }
+
+    fn resolve_overload(&mut self, id: ExprId, function: &str, receiver: bool, arg_types: &[Type]) -> Type {
+        let Some(overloads) = DECLARATIONS.overloads(function) else {
+            return self.error(id, format!("undeclared reference to '{}' (in container '')", function));
+        };
+        let mut result: Option<Type> = None;
+        for overload in overloads {
+            if overload.receiver != receiver || overload.params.len() != arg_types.len() {
+                continue;
+            }
+            let mut bindings = HashMap::new();
+            if overload
+                .params
+                .iter()
+                .zip(arg_types)
+                .all(|(param, arg)| param.unify(arg, &mut bindings))
+            {
+                let ty = overload.result.substitute(&bindings);
+                result = Some(match result {
+                    Some(current) => current.join(&ty),
+                    None => ty,
+                });
+            }
+        }
+        if let Some(result) = result {
+            return result;
+        }
+        if arg_types.contains(&Type::Error) {
+            return Type::Error;
+        }
+        let format_types = |types: &[Type]| types.iter().map(Type::to_string).collect::<Vec<_>>().join(", ");
+        let applied = if receiver {
+            format!("{}.({})", arg_types[0], format_types(&arg_types[1..]))
+        } else {
+            format!("({})", format_types(arg_types))
+        };
+        self.error(
+            id,
+            format!("found no matching overload for '{}' applied to '{}'", function, applied),
+        )
+    }
+}
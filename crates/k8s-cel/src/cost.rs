@@ -0,0 +1,407 @@
+//! Runtime cost tracking and static cost estimation
+//!
+//! Costs follow cel-go's model: identifiers, field selections and most
+//! function calls cost 1, creating lists, maps and objects has a fixed base
+//! cost, and functions that traverse strings or lists cost in proportion to
+//! the size of their arguments. The static estimate bounds the cost of an
+//! expression using size estimates for its inputs, which the caller supplies
+//! through a `SizeEstimator`, e.g. from `maxLength` and `maxItems` in a
+//! schema.
+
+use std::collections::HashMap;
+
+use crate::ast::{operators, Constant, Expr, ExprId, ExprKind};
+use crate::types::Type;
+use crate::value::Value;
+
+pub(crate) const LIST_CREATE_COST: u64 = 10;
+pub(crate) const MAP_CREATE_COST: u64 = 30;
+pub(crate) const STRUCT_CREATE_COST: u64 = 40;
+/// Cost of an authorization check, which calls out to an authorizer.
+pub(crate) const AUTHZ_CHECK_COST: u64 = 350_000;
+
+const STRING_TRAVERSAL_FACTOR: f64 = 0.1;
+const REGEX_FACTOR: f64 = 0.25;
+
+fn traversal(size: u64, factor: f64) -> u64 {
+    if size == u64::MAX {
+        return u64::MAX;
+    }
+    (size as f64 * factor).ceil() as u64
+}
+
+fn value_size(value: &Value) -> u64 {
+    match value {
+        Value::String(s) => s.len() as u64,
+        Value::Bytes(b) => b.len() as u64,
+        Value::List(items) => items.len() as u64,
+        Value::Map(map) => map.len() as u64,
+        _ => 1,
+    }
+}
+
+/// Cost of one call, given the argument sizes (receiver first).
+fn function_cost(function: &str, sizes: &[u64], first_is_string: bool) -> u64 {
+    let size = |idx: usize| sizes.get(idx).copied().unwrap_or(0);
+    let cost = match function {
+        operators::ADD if first_is_string => traversal(size(0).saturating_add(size(1)), STRING_TRAVERSAL_FACTOR),
+        operators::EQUALS | operators::NOT_EQUALS if first_is_string => {
+            traversal(size(0).min(size(1)), STRING_TRAVERSAL_FACTOR)
+        }
+        operators::IN => size(1),
+        "contains" | "startsWith" | "endsWith" | "lowerAscii" | "upperAscii" | "trim" | "reverse" | "substring"
+        | "charAt" | "replace" | "split" | "format" | "strings.quote" | "url" | "isURL" | "quantity"
+        | "isQuantity" | "ip" | "isIP" | "cidr" | "isCIDR" | "ip.isCanonical" => {
+            traversal(size(0), STRING_TRAVERSAL_FACTOR)
+        }
+        "indexOf" | "lastIndexOf" | "string" | "bytes" if first_is_string => {
+            traversal(size(0), STRING_TRAVERSAL_FACTOR)
+        }
+        "matches" | "find" | "findAll" => {
+            traversal(size(0), STRING_TRAVERSAL_FACTOR).saturating_mul(traversal(size(1), REGEX_FACTOR).max(1))
+        }
+        "join" => traversal(size(0), STRING_TRAVERSAL_FACTOR).saturating_mul(10),
+        "isSorted" | "sum" | "min" | "max" | "indexOf" | "lastIndexOf" => size(0),
+        "sets.contains" | "sets.equivalent" | "sets.intersects" => size(0).saturating_mul(size(1)),
+        "check" => AUTHZ_CHECK_COST,
+        _ => 1,
+    };
+    cost.max(1)
+}
+
+/// Runtime cost of calling `function` on `args`.
+pub(crate) fn call_cost(function: &str, args: &[Value]) -> u64 {
+    match function {
+        operators::EQUALS | operators::NOT_EQUALS => {
+            if let (Some(Value::List(a)), Some(Value::List(b))) = (args.first(), args.get(1)) {
+                return (a.len().min(b.len()) as u64).max(1);
+            }
+        }
+        operators::ADD => {
+            if let (Some(Value::List(a)), Some(Value::List(b))) = (args.first(), args.get(1)) {
+                return traversal((a.len() + b.len()) as u64, STRING_TRAVERSAL_FACTOR).max(1);
+            }
+        }
+        _ => {}
+    }
+    let sizes: Vec<u64> = args.iter().map(value_size).collect();
+    let first_is_string = matches!(args.first(), Some(Value::String(_) | Value::Bytes(_)));
+    function_cost(function, &sizes, first_is_string)
+}
+
+/// CostEstimate bounds the cost of evaluating an expression.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CostEstimate {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl CostEstimate {
+    fn exact(cost: u64) -> Self {
+        Self { min: cost, max: cost }
+    }
+
+    fn add(self, other: CostEstimate) -> Self {
+        Self {
+            min: self.min.saturating_add(other.min),
+            max: self.max.saturating_add(other.max),
+        }
+    }
+
+    fn multiply(self, size: SizeEstimate) -> Self {
+        Self {
+            min: self.min.saturating_mul(size.min),
+            max: self.max.saturating_mul(size.max),
+        }
+    }
+}
+
+/// SizeEstimate bounds the size of a string, bytes, list or map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeEstimate {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl SizeEstimate {
+    pub fn exact(size: u64) -> Self {
+        Self { min: size, max: size }
+    }
+
+    pub fn unknown() -> Self {
+        Self { min: 0, max: u64::MAX }
+    }
+
+    fn add(self, other: SizeEstimate) -> Self {
+        Self {
+            min: self.min.saturating_add(other.min),
+            max: self.max.saturating_add(other.max),
+        }
+    }
+
+    fn union(self, other: SizeEstimate) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+/// SizeEstimator provides size estimates for the inputs of an expression.
+///
+/// Paths name a value starting at a variable: `["self", "spec", "items"]`.
+/// Elements of a list are named `@items`, values of a map `@values` and
+/// keys of a map `@keys`, e.g. `["self", "spec", "items", "@items", "name"]`.
+pub trait SizeEstimator {
+    fn estimate_size(&self, path: &[String]) -> Option<SizeEstimate>;
+}
+
+/// A SizeEstimator that knows nothing, so every input may be unbounded.
+impl SizeEstimator for () {
+    fn estimate_size(&self, _path: &[String]) -> Option<SizeEstimate> {
+        None
+    }
+}
+
+struct Estimate {
+    cost: CostEstimate,
+    size: Option<SizeEstimate>,
+    path: Option<Vec<String>>,
+}
+
+impl Estimate {
+    fn size(&self) -> SizeEstimate {
+        self.size.unwrap_or_else(SizeEstimate::unknown)
+    }
+}
+
+pub(crate) struct Estimator<'a> {
+    pub(crate) sizes: &'a dyn SizeEstimator,
+    pub(crate) types: &'a HashMap<ExprId, Type>,
+    scopes: Vec<(String, Option<Vec<String>>, Option<SizeEstimate>)>,
+}
+
+impl<'a> Estimator<'a> {
+    pub(crate) fn new(sizes: &'a dyn SizeEstimator, types: &'a HashMap<ExprId, Type>) -> Self {
+        Self {
+            sizes,
+            types,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn estimate(&mut self, expr: &Expr) -> CostEstimate {
+        self.walk(expr).cost
+    }
+
+    fn with_path(&self, cost: CostEstimate, path: Vec<String>) -> Estimate {
+        Estimate {
+            cost,
+            size: self.sizes.estimate_size(&path),
+            path: Some(path),
+        }
+    }
+
+    fn element_path(&self, container: &Expr, path: Option<Vec<String>>, map_element: &str) -> Option<Vec<String>> {
+        let mut path = path?;
+        let segment = match self.types.get(&container.id) {
+            Some(Type::Map(..)) => map_element,
+            _ => "@items",
+        };
+        path.push(segment.to_string());
+        Some(path)
+    }
+
+    fn walk(&mut self, expr: &Expr) -> Estimate {
+        match &expr.kind {
+            ExprKind::Const(constant) => Estimate {
+                cost: CostEstimate::default(),
+                size: Some(SizeEstimate::exact(match constant {
+                    Constant::String(s) => s.len() as u64,
+                    Constant::Bytes(b) => b.len() as u64,
+                    _ => 1,
+                })),
+                path: None,
+            },
+            ExprKind::Ident(name) => {
+                if let Some((_, path, size)) = self.scopes.iter().rev().find(|(local, _, _)| local == name) {
+                    let size = (*size).or_else(|| path.as_ref().and_then(|path| self.sizes.estimate_size(path)));
+                    return Estimate {
+                        cost: CostEstimate::exact(1),
+                        size,
+                        path: path.clone(),
+                    };
+                }
+                self.with_path(CostEstimate::exact(1), vec![name.clone()])
+            }
+            ExprKind::Select {
+                operand,
+                field,
+                test_only,
+            } => {
+                let operand = self.walk(operand);
+                let cost = operand.cost.add(CostEstimate::exact(1));
+                match operand.path {
+                    Some(mut path) if !test_only => {
+                        path.push(field.clone());
+                        self.with_path(cost, path)
+                    }
+                    _ => Estimate {
+                        cost,
+                        size: None,
+                        path: None,
+                    },
+                }
+            }
+            ExprKind::List { elements, .. } => {
+                let mut cost = CostEstimate::exact(LIST_CREATE_COST);
+                for element in elements {
+                    cost = cost.add(self.walk(element).cost);
+                }
+                Estimate {
+                    cost,
+                    size: Some(SizeEstimate::exact(elements.len() as u64)),
+                    path: None,
+                }
+            }
+            ExprKind::Map { entries } => {
+                let mut cost = CostEstimate::exact(MAP_CREATE_COST);
+                for entry in entries {
+                    cost = cost.add(self.walk(&entry.key).cost).add(self.walk(&entry.value).cost);
+                }
+                Estimate {
+                    cost,
+                    size: Some(SizeEstimate::exact(entries.len() as u64)),
+                    path: None,
+                }
+            }
+            ExprKind::Struct { fields, .. } => {
+                let mut cost = CostEstimate::exact(STRUCT_CREATE_COST);
+                for field in fields {
+                    cost = cost.add(self.walk(&field.value).cost);
+                }
+                Estimate {
+                    cost,
+                    size: Some(SizeEstimate::exact(fields.len() as u64)),
+                    path: None,
+                }
+            }
+            ExprKind::Comprehension(comprehension) => {
+                let range = self.walk(&comprehension.iter_range);
+                let range_size = range.size();
+                let iter_path = self.element_path(&comprehension.iter_range, range.path.clone(), "@keys");
+                let init = self.walk(&comprehension.accu_init);
+                self.scopes.push((comprehension.accu_var.clone(), None, Some(range_size.union(init.size()))));
+                self.scopes.push((comprehension.iter_var.clone(), iter_path, None));
+                let condition = self.walk(&comprehension.loop_condition);
+                let step = self.walk(&comprehension.loop_step);
+                self.scopes.pop();
+                let result = self.walk(&comprehension.result);
+                self.scopes.pop();
+                let per_iteration = condition.cost.add(step.cost);
+                Estimate {
+                    cost: range
+                        .cost
+                        .add(init.cost)
+                        .add(per_iteration.multiply(range_size))
+                        .add(result.cost),
+                    size: result.size,
+                    path: None,
+                }
+            }
+            ExprKind::Call { target, function, args } => self.walk_call(expr, target.as_deref(), function, args),
+        }
+    }
+
+    fn walk_call(&mut self, expr: &Expr, target: Option<&Expr>, function: &str, args: &[Expr]) -> Estimate {
+        match (function, args) {
+            (operators::LOGICAL_AND | operators::LOGICAL_OR, [left, right]) => {
+                let left = self.walk(left);
+                let right = self.walk(right);
+                return Estimate {
+                    cost: CostEstimate {
+                        min: left.cost.min,
+                        max: left.cost.max.saturating_add(right.cost.max),
+                    },
+                    size: None,
+                    path: None,
+                };
+            }
+            (operators::CONDITIONAL, [condition, truthy, falsy]) => {
+                let condition = self.walk(condition);
+                let truthy = self.walk(truthy);
+                let falsy = self.walk(falsy);
+                return Estimate {
+                    cost: CostEstimate {
+                        min: condition.cost.min.saturating_add(truthy.cost.min.min(falsy.cost.min)),
+                        max: condition.cost.max.saturating_add(truthy.cost.max.max(falsy.cost.max)),
+                    },
+                    size: match (truthy.size, falsy.size) {
+                        (Some(a), Some(b)) => Some(a.union(b)),
+                        _ => None,
+                    },
+                    path: None,
+                };
+            }
+            (operators::NOT_STRICTLY_FALSE, [arg]) => return self.walk(arg),
+            (operators::OPT_SELECT, [operand, field]) => {
+                let operand = self.walk(operand);
+                let cost = operand.cost.add(CostEstimate::exact(1));
+                return match (operand.path, &field.kind) {
+                    (Some(mut path), ExprKind::Const(Constant::String(field))) => {
+                        path.push(field.clone());
+                        self.with_path(cost, path)
+                    }
+                    _ => Estimate {
+                        cost,
+                        size: None,
+                        path: None,
+                    },
+                };
+            }
+            _ => {}
+        }
+
+        let mut estimates = Vec::with_capacity(args.len() + 1);
+        let mut arg_exprs = Vec::with_capacity(args.len() + 1);
+        if let Some(target) = target {
+            estimates.push(self.walk(target));
+            arg_exprs.push(target);
+        }
+        for arg in args {
+            estimates.push(self.walk(arg));
+            arg_exprs.push(arg);
+        }
+        let mut cost = estimates
+            .iter()
+            .fold(CostEstimate::default(), |cost, estimate| cost.add(estimate.cost));
+
+        let first_is_string = arg_exprs
+            .first()
+            .and_then(|arg| self.types.get(&arg.id))
+            .is_some_and(|ty| matches!(ty, Type::String | Type::Bytes));
+        let min_sizes: Vec<u64> = estimates.iter().map(|e| e.size().min).collect();
+        let max_sizes: Vec<u64> = estimates.iter().map(|e| e.size().max).collect();
+        cost = cost.add(CostEstimate {
+            min: function_cost(function, &min_sizes, first_is_string),
+            max: function_cost(function, &max_sizes, first_is_string),
+        });
+
+        let result_type = self.types.get(&expr.id);
+        let (size, path) = match function {
+            operators::INDEX | operators::OPT_INDEX => {
+                let container = estimates.swap_remove(0);
+                let path = self.element_path(arg_exprs[0], container.path, "@values");
+                (path.as_ref().and_then(|path| self.sizes.estimate_size(path)), path)
+            }
+            operators::ADD if matches!(result_type, Some(Type::String | Type::Bytes | Type::List(_))) => {
+                (Some(estimates[0].size().add(estimates[1].size())), None)
+            }
+            "lowerAscii" | "upperAscii" | "trim" | "reverse" | "substring" | "replace" | "split" | "findAll"
+            | "find" | "string" => (estimates.first().map(Estimate::size), None),
+            "size" | "int" | "uint" | "double" | "bool" => (Some(SizeEstimate::exact(1)), None),
+            _ => (None, None),
+        };
+        Estimate { cost, size, path }
+    }
+}
@@ -0,0 +1,98 @@
+//! Errors reported while compiling and evaluating expressions
+
+use thiserror::Error;
+
+/// Issue is a single problem found while parsing or checking an expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    /// 1-based line of the offending token.
+    pub line: usize,
+    /// 1-based column of the offending token, counted in characters.
+    pub column: usize,
+    pub message: String,
+}
+
+/// CompileError collects every issue found in an expression, formatted the
+/// way cel-go reports them, e.g. `ERROR: <input>:1:6: undeclared reference to 'x'`.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub struct CompileError {
+    pub issues: Vec<Issue>,
+    expression: String,
+}
+
+impl CompileError {
+    pub(crate) fn new(source: &str) -> Self {
+        Self {
+            issues: Vec::new(),
+            expression: source.to_string(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, offset: usize, message: impl Into<String>) {
+        let (line, column) = line_column(&self.expression, offset);
+        let message = message.into();
+        if !self
+            .issues
+            .iter()
+            .any(|issue| issue.line == line && issue.column == column && issue.message == message)
+        {
+            self.issues.push(Issue { line, column, message });
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, issue) in self.issues.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "ERROR: <input>:{}:{}: {}", issue.line, issue.column, issue.message)?;
+            if let Some(line) = self.expression.lines().nth(issue.line - 1) {
+                write!(f, "\n | {}\n | {}^", line, ".".repeat(issue.column - 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for (idx, ch) in source.char_indices() {
+        if idx >= offset {
+            break;
+        }
+        if ch == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+/// EvalError is returned when evaluating a program fails.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum EvalError {
+    #[error("{0}")]
+    Message(String),
+    #[error("operation cancelled: actual cost limit exceeded")]
+    CostLimitExceeded,
+}
+
+impl EvalError {
+    pub fn new(message: impl Into<String>) -> Self {
+        EvalError::Message(message.into())
+    }
+
+    pub(crate) fn no_such_overload(function: &str, args: &[crate::Value]) -> Self {
+        let types: Vec<String> = args.iter().map(|arg| arg.type_of().to_string()).collect();
+        EvalError::Message(format!("no such overload: {}({})", function, types.join(", ")))
+    }
+}
@@ -0,0 +1,329 @@
+//! Tree-walking evaluator
+//!
+//! `&&` and `||` are commutative with respect to errors, as in cel-go: an
+//! error on one side is absorbed when the other side decides the result.
+//! Comprehension accumulators may hold errors for the same reason, so that
+//! `all()` and `exists()` can absorb them.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ast::{operators, Constant, Expr, ExprKind};
+use crate::cost;
+use crate::error::EvalError;
+use crate::library;
+use crate::types::type_identifier;
+use crate::value::{display_value, Key, Value};
+
+/// Activation resolves the variables referenced by an expression.
+pub trait Activation {
+    /// Returns the value of `name`, `None` if it is not bound, or an error if
+    /// computing the value failed.
+    fn resolve(&self, name: &str) -> Option<Result<Value, EvalError>>;
+}
+
+impl Activation for HashMap<String, Value> {
+    fn resolve(&self, name: &str) -> Option<Result<Value, EvalError>> {
+        self.get(name).cloned().map(Ok)
+    }
+}
+
+impl Activation for BTreeMap<String, Value> {
+    fn resolve(&self, name: &str) -> Option<Result<Value, EvalError>> {
+        self.get(name).cloned().map(Ok)
+    }
+}
+
+impl Activation for () {
+    fn resolve(&self, _name: &str) -> Option<Result<Value, EvalError>> {
+        None
+    }
+}
+
+pub(crate) struct Interpreter<'a> {
+    activation: &'a dyn Activation,
+    scopes: Vec<(&'a str, Result<Value, EvalError>)>,
+    pub(crate) cost: u64,
+    limit: Option<u64>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub(crate) fn new(activation: &'a dyn Activation, limit: Option<u64>) -> Self {
+        Self {
+            activation,
+            scopes: Vec::new(),
+            cost: 0,
+            limit,
+        }
+    }
+
+    fn charge(&mut self, amount: u64) -> Result<(), EvalError> {
+        self.cost = self.cost.saturating_add(amount);
+        match self.limit {
+            Some(limit) if self.cost > limit => Err(EvalError::CostLimitExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn eval(&mut self, expr: &'a Expr) -> Result<Value, EvalError> {
+        match &expr.kind {
+            ExprKind::Const(constant) => Ok(match constant {
+                Constant::Null => Value::Null,
+                Constant::Bool(b) => Value::Bool(*b),
+                Constant::Int(i) => Value::Int(*i),
+                Constant::Uint(u) => Value::Uint(*u),
+                Constant::Double(d) => Value::Double(*d),
+                Constant::String(s) => Value::string(s),
+                Constant::Bytes(b) => Value::Bytes(b.as_slice().into()),
+            }),
+            ExprKind::Ident(name) => {
+                self.charge(1)?;
+                self.resolve(name)
+            }
+            ExprKind::Select {
+                operand,
+                field,
+                test_only,
+            } => {
+                self.charge(1)?;
+                let operand = self.eval(operand)?;
+                if *test_only {
+                    return has_field(&operand, field);
+                }
+                select(&operand, field)
+            }
+            ExprKind::Call { target, function, args } => self.eval_call(target.as_deref(), function, args),
+            ExprKind::List {
+                elements,
+                optional_indices,
+            } => {
+                self.charge(cost::LIST_CREATE_COST)?;
+                let mut items = Vec::with_capacity(elements.len());
+                for (idx, element) in elements.iter().enumerate() {
+                    let value = self.eval(element)?;
+                    if optional_indices.contains(&idx) {
+                        match value {
+                            Value::Optional(Some(inner)) => items.push(*inner),
+                            Value::Optional(None) => {}
+                            other => return Err(EvalError::no_such_overload("optional list element", &[other])),
+                        }
+                    } else {
+                        items.push(value);
+                    }
+                }
+                Ok(Value::list(items))
+            }
+            ExprKind::Map { entries } => {
+                self.charge(cost::MAP_CREATE_COST)?;
+                let mut map = BTreeMap::new();
+                for entry in entries {
+                    let key = self.eval(&entry.key)?;
+                    let key = key
+                        .to_key()
+                        .ok_or_else(|| EvalError::new(format!("unsupported key type: {}", key.type_of())))?;
+                    let value = self.eval(&entry.value)?;
+                    let value = if entry.optional {
+                        match value {
+                            Value::Optional(Some(inner)) => *inner,
+                            Value::Optional(None) => continue,
+                            other => return Err(EvalError::no_such_overload("optional map entry", &[other])),
+                        }
+                    } else {
+                        value
+                    };
+                    if map.insert(key.clone(), value).is_some() {
+                        return Err(EvalError::new(format!(
+                            "Failed with repeated key: {}",
+                            display_value(&key.to_value())
+                        )));
+                    }
+                }
+                Ok(Value::map(map))
+            }
+            ExprKind::Struct { fields, .. } => {
+                self.charge(cost::STRUCT_CREATE_COST)?;
+                let mut map = BTreeMap::new();
+                for field in fields {
+                    let value = self.eval(&field.value)?;
+                    let value = if field.optional {
+                        match value {
+                            Value::Optional(Some(inner)) => *inner,
+                            Value::Optional(None) => continue,
+                            other => return Err(EvalError::no_such_overload("optional field", &[other])),
+                        }
+                    } else {
+                        value
+                    };
+                    map.insert(Key::from(field.name.as_str()), value);
+                }
+                Ok(Value::map(map))
+            }
+            ExprKind::Comprehension(comprehension) => {
+                let range = self.eval(&comprehension.iter_range)?;
+                let items: Vec<Value> = match &range {
+                    Value::List(items) => items.iter().cloned().collect(),
+                    Value::Map(map) => map.keys().map(Key::to_value).collect(),
+                    other => return Err(EvalError::no_such_overload("comprehension", std::slice::from_ref(other))),
+                };
+                let mut accu = self.eval(&comprehension.accu_init);
+                if let Err(EvalError::CostLimitExceeded) = accu {
+                    return Err(EvalError::CostLimitExceeded);
+                }
+                for item in items {
+                    self.scopes.push((&comprehension.accu_var, accu));
+                    self.scopes.push((&comprehension.iter_var, Ok(item)));
+                    let proceed = self.eval(&comprehension.loop_condition);
+                    let proceed = match proceed {
+                        Ok(Value::Bool(proceed)) => proceed,
+                        Ok(other) => {
+                            self.scopes.truncate(self.scopes.len() - 2);
+                            return Err(EvalError::no_such_overload("loop condition", &[other]));
+                        }
+                        Err(err) => {
+                            self.scopes.truncate(self.scopes.len() - 2);
+                            return Err(err);
+                        }
+                    };
+                    if !proceed {
+                        self.scopes.pop();
+                        accu = self.scopes.pop().map(|(_, accu)| accu).unwrap_or(Ok(Value::Null));
+                        break;
+                    }
+                    let step = self.eval(&comprehension.loop_step);
+                    self.scopes.truncate(self.scopes.len() - 2);
+                    if let Err(EvalError::CostLimitExceeded) = step {
+                        return Err(EvalError::CostLimitExceeded);
+                    }
+                    accu = step;
+                }
+                self.scopes.push((&comprehension.accu_var, accu));
+                let result = self.eval(&comprehension.result);
+                self.scopes.pop();
+                result
+            }
+        }
+    }
+
+    fn resolve(&mut self, name: &str) -> Result<Value, EvalError> {
+        if let Some((_, value)) = self.scopes.iter().rev().find(|(local, _)| *local == name) {
+            return value.clone();
+        }
+        if let Some(value) = self.activation.resolve(name) {
+            return value;
+        }
+        if let Some(ty) = type_identifier(name) {
+            return Ok(Value::Type(ty));
+        }
+        Err(EvalError::new(format!("no such attribute(s): {}", name)))
+    }
+
+    fn eval_call(&mut self, target: Option<&'a Expr>, function: &str, args: &'a [Expr]) -> Result<Value, EvalError> {
+        match (function, target, args) {
+            (operators::LOGICAL_AND, None, [left, right]) => {
+                return self.eval_logical(left, right, false);
+            }
+            (operators::LOGICAL_OR, None, [left, right]) => {
+                return self.eval_logical(left, right, true);
+            }
+            (operators::CONDITIONAL, None, [condition, truthy, falsy]) => {
+                return match self.eval(condition)? {
+                    Value::Bool(true) => self.eval(truthy),
+                    Value::Bool(false) => self.eval(falsy),
+                    other => Err(EvalError::no_such_overload(function, &[other])),
+                };
+            }
+            (operators::NOT_STRICTLY_FALSE, None, [arg]) => {
+                return match self.eval(arg) {
+                    Ok(Value::Bool(b)) => Ok(Value::Bool(b)),
+                    Err(EvalError::CostLimitExceeded) => Err(EvalError::CostLimitExceeded),
+                    _ => Ok(Value::Bool(true)),
+                };
+            }
+            (operators::OPT_SELECT, None, [operand, field]) => {
+                self.charge(1)?;
+                let operand = self.eval(operand)?;
+                let ExprKind::Const(Constant::String(field)) = &field.kind else {
+                    return Err(EvalError::new("unsupported optional field selection"));
+                };
+                return optional_select(&operand, field);
+            }
+            ("or" | "orValue", Some(target), [alternative]) => {
+                self.charge(1)?;
+                match self.eval(target)? {
+                    Value::Optional(Some(value)) => {
+                        return Ok(if function == "or" {
+                            Value::Optional(Some(value))
+                        } else {
+                            *value
+                        });
+                    }
+                    Value::Optional(None) => return self.eval(alternative),
+                    other => return Err(EvalError::no_such_overload(function, &[other])),
+                }
+            }
+            _ => {}
+        }
+
+        let mut values = Vec::with_capacity(args.len() + 1);
+        if let Some(target) = target {
+            values.push(self.eval(target)?);
+        }
+        for arg in args {
+            values.push(self.eval(arg)?);
+        }
+        self.charge(cost::call_cost(function, &values))?;
+        library::call(function, target.is_some(), &values)
+    }
+
+    fn eval_logical(&mut self, left: &'a Expr, right: &'a Expr, is_or: bool) -> Result<Value, EvalError> {
+        let function = if is_or { operators::LOGICAL_OR } else { operators::LOGICAL_AND };
+        let left = match self.eval(left) {
+            Ok(Value::Bool(b)) if b == is_or => return Ok(Value::Bool(b)),
+            Ok(Value::Bool(b)) => Ok(b),
+            Ok(other) => Err(EvalError::no_such_overload(function, &[other])),
+            Err(EvalError::CostLimitExceeded) => return Err(EvalError::CostLimitExceeded),
+            Err(err) => Err(err),
+        };
+        match self.eval(right) {
+            Ok(Value::Bool(b)) if b == is_or => Ok(Value::Bool(b)),
+            Ok(Value::Bool(b)) => left.map(|_| Value::Bool(b)),
+            Ok(other) => left.and(Err(EvalError::no_such_overload(function, &[other]))),
+            Err(EvalError::CostLimitExceeded) => Err(EvalError::CostLimitExceeded),
+            Err(err) => left.and(Err(err)),
+        }
+    }
+}
+
+fn select(operand: &Value, field: &str) -> Result<Value, EvalError> {
+    match operand {
+        Value::Map(map) => Value::map_get(map, &Value::string(field))
+            .cloned()
+            .ok_or_else(|| EvalError::new(format!("no such key: {}", field))),
+        Value::Optional(_) => optional_select(operand, field),
+        other => Err(EvalError::new(format!(
+            "type '{}' does not support field selection",
+            other.type_of()
+        ))),
+    }
+}
+
+fn optional_select(operand: &Value, field: &str) -> Result<Value, EvalError> {
+    match operand {
+        Value::Map(map) => Ok(Value::optional(Value::map_get(map, &Value::string(field)).cloned())),
+        Value::Optional(None) => Ok(Value::Optional(None)),
+        Value::Optional(Some(inner)) => optional_select(inner, field),
+        other => Err(EvalError::new(format!(
+            "type '{}' does not support field selection",
+            other.type_of()
+        ))),
+    }
+}
+
+fn has_field(operand: &Value, field: &str) -> Result<Value, EvalError> {
+    match operand {
+        Value::Map(map) => Ok(Value::Bool(Value::map_get(map, &Value::string(field)).is_some())),
+        other => Err(EvalError::new(format!(
+            "invalid type for field selection: {}",
+            other.type_of()
+        ))),
+    }
+}
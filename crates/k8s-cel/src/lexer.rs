@@ -0,0 +1,427 @@
+//! Tokenizer for CEL source text
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token {
+    Ident(String),
+    /// Integer literal magnitude; the sign is applied by the parser so that
+    /// `-9223372036854775808` can be represented.
+    Int(u64),
+    Uint(u64),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    True,
+    False,
+    Null,
+    In,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Dot,
+    Comma,
+    Colon,
+    Question,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Bang,
+    EqEq,
+    NotEq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    AndAnd,
+    OrOr,
+    Eof,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Token::Ident(name) => return write!(f, "{}", name),
+            Token::Int(value) | Token::Uint(value) => return write!(f, "{}", value),
+            Token::Double(value) => return write!(f, "{}", value),
+            Token::String(value) => return write!(f, "{:?}", value),
+            Token::Bytes(_) => "bytes",
+            Token::True => "true",
+            Token::False => "false",
+            Token::Null => "null",
+            Token::In => "in",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::Dot => ".",
+            Token::Comma => ",",
+            Token::Colon => ":",
+            Token::Question => "?",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Bang => "!",
+            Token::EqEq => "==",
+            Token::NotEq => "!=",
+            Token::Lt => "<",
+            Token::Le => "<=",
+            Token::Gt => ">",
+            Token::Ge => ">=",
+            Token::AndAnd => "&&",
+            Token::OrOr => "||",
+            Token::Eof => "<EOF>",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Words that cannot be used as identifiers, although they may name fields
+/// and member functions, e.g. `authorizer.namespace('ns')`.
+pub(crate) const RESERVED: &[&str] = &[
+    "as", "break", "const", "continue", "else", "for", "function", "if", "import", "let", "loop", "package",
+    "namespace", "return", "var", "void", "while",
+];
+
+/// Splits `source` into tokens paired with their byte offsets. The final
+/// token is always `Token::Eof`.
+pub(crate) fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, (usize, String)> {
+    let mut lexer = Lexer {
+        chars: source.char_indices().collect(),
+        pos: 0,
+        len: source.len(),
+    };
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token()?;
+        let done = token.0 == Token::Eof;
+        tokens.push(token);
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer {
+    chars: Vec<(usize, char)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|(_, ch)| *ch)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.pos + ahead).map(|(_, ch)| *ch)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars.get(self.pos).map(|(offset, _)| *offset).unwrap_or(self.len)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            match self.peek() {
+                Some(ch) if ch.is_whitespace() => self.pos += 1,
+                Some('/') if self.peek_at(1) == Some('/') => {
+                    while let Some(ch) = self.peek() {
+                        if ch == '\n' {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<(Token, usize), (usize, String)> {
+        self.skip_whitespace_and_comments();
+        let start = self.offset();
+        let Some(ch) = self.peek() else {
+            return Ok((Token::Eof, start));
+        };
+
+        if ch.is_ascii_digit() {
+            return self.number(start).map(|token| (token, start));
+        }
+        if ch == '"' || ch == '\'' {
+            let value = self.string(false, false, start)?;
+            return Ok((string_token(value, false, start)?, start));
+        }
+        if ch.is_alphabetic() || ch == '_' {
+            // String prefixes: r"..", b"..", rb"..", br"..".
+            let mut raw = false;
+            let mut bytes = false;
+            let mut idx = 0;
+            while let Some(prefix) = self.peek_at(idx) {
+                match prefix {
+                    'r' | 'R' if !raw => raw = true,
+                    'b' | 'B' if !bytes => bytes = true,
+                    _ => break,
+                }
+                idx += 1;
+            }
+            if idx > 0 && matches!(self.peek_at(idx), Some('"') | Some('\'')) {
+                self.pos += idx;
+                let value = self.string(raw, bytes, start)?;
+                return Ok((string_token(value, bytes, start)?, start));
+            }
+
+            let mut name = String::new();
+            while let Some(ch) = self.peek() {
+                if ch.is_alphanumeric() || ch == '_' {
+                    name.push(ch);
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+            let token = match name.as_str() {
+                "true" => Token::True,
+                "false" => Token::False,
+                "null" => Token::Null,
+                "in" => Token::In,
+                _ => Token::Ident(name),
+            };
+            return Ok((token, start));
+        }
+
+        self.pos += 1;
+        let next = self.peek();
+        let mut two = |token: Token| {
+            self.pos += 1;
+            token
+        };
+        let token = match (ch, next) {
+            ('=', Some('=')) => two(Token::EqEq),
+            ('!', Some('=')) => two(Token::NotEq),
+            ('<', Some('=')) => two(Token::Le),
+            ('>', Some('=')) => two(Token::Ge),
+            ('&', Some('&')) => two(Token::AndAnd),
+            ('|', Some('|')) => two(Token::OrOr),
+            ('(', _) => Token::LParen,
+            (')', _) => Token::RParen,
+            ('[', _) => Token::LBracket,
+            (']', _) => Token::RBracket,
+            ('{', _) => Token::LBrace,
+            ('}', _) => Token::RBrace,
+            ('.', _) => Token::Dot,
+            (',', _) => Token::Comma,
+            (':', _) => Token::Colon,
+            ('?', _) => Token::Question,
+            ('+', _) => Token::Plus,
+            ('-', _) => Token::Minus,
+            ('*', _) => Token::Star,
+            ('/', _) => Token::Slash,
+            ('%', _) => Token::Percent,
+            ('!', _) => Token::Bang,
+            ('<', _) => Token::Lt,
+            ('>', _) => Token::Gt,
+            _ => return Err((start, format!("Syntax error: token recognition error at: '{}'", ch))),
+        };
+        Ok((token, start))
+    }
+
+    fn number(&mut self, start: usize) -> Result<Token, (usize, String)> {
+        if self.peek() == Some('0') && matches!(self.peek_at(1), Some('x') | Some('X')) {
+            self.pos += 2;
+            let mut digits = String::new();
+            while let Some(ch) = self.peek().filter(|ch| ch.is_ascii_hexdigit()) {
+                digits.push(ch);
+                self.pos += 1;
+            }
+            let value = u64::from_str_radix(&digits, 16).map_err(|_| (start, "invalid int literal".to_string()))?;
+            return Ok(self.int_suffix(value));
+        }
+
+        let mut text = String::new();
+        let mut is_double = false;
+        while let Some(ch) = self.peek().filter(|ch| ch.is_ascii_digit()) {
+            text.push(ch);
+            self.pos += 1;
+        }
+        if self.peek() == Some('.') && self.peek_at(1).is_some_and(|ch| ch.is_ascii_digit()) {
+            is_double = true;
+            text.push('.');
+            self.pos += 1;
+            while let Some(ch) = self.peek().filter(|ch| ch.is_ascii_digit()) {
+                text.push(ch);
+                self.pos += 1;
+            }
+        }
+        if matches!(self.peek(), Some('e') | Some('E')) {
+            let sign = matches!(self.peek_at(1), Some('+') | Some('-'));
+            let digit_at = if sign { 2 } else { 1 };
+            if self.peek_at(digit_at).is_some_and(|ch| ch.is_ascii_digit()) {
+                is_double = true;
+                text.push('e');
+                self.pos += 1;
+                if sign {
+                    text.push(self.peek().unwrap_or('+'));
+                    self.pos += 1;
+                }
+                while let Some(ch) = self.peek().filter(|ch| ch.is_ascii_digit()) {
+                    text.push(ch);
+                    self.pos += 1;
+                }
+            }
+        }
+        if is_double {
+            return text
+                .parse::<f64>()
+                .map(Token::Double)
+                .map_err(|_| (start, "invalid double literal".to_string()));
+        }
+        let value = text.parse::<u64>().map_err(|_| (start, "invalid int literal".to_string()))?;
+        Ok(self.int_suffix(value))
+    }
+
+    fn int_suffix(&mut self, value: u64) -> Token {
+        if matches!(self.peek(), Some('u') | Some('U')) {
+            self.pos += 1;
+            Token::Uint(value)
+        } else {
+            Token::Int(value)
+        }
+    }
+
+    /// Reads a quoted literal, returning its decoded bytes. Non-bytes literals
+    /// are always valid UTF-8.
+    fn string(&mut self, raw: bool, bytes: bool, start: usize) -> Result<Vec<u8>, (usize, String)> {
+        let quote = self.peek().unwrap_or('"');
+        let triple = self.peek_at(1) == Some(quote) && self.peek_at(2) == Some(quote);
+        self.pos += if triple { 3 } else { 1 };
+        let unterminated = || (start, "Syntax error: unterminated string literal".to_string());
+
+        let mut out = Vec::new();
+        loop {
+            let ch = self.peek().ok_or_else(unterminated)?;
+            if ch == quote {
+                if !triple {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                if self.peek_at(1) == Some(quote) && self.peek_at(2) == Some(quote) {
+                    self.pos += 3;
+                    return Ok(out);
+                }
+            }
+            if ch == '\n' && !triple {
+                return Err(unterminated());
+            }
+            self.pos += 1;
+            if ch != '\\' || raw {
+                push_char(&mut out, ch);
+                continue;
+            }
+
+            let escape = self.peek().ok_or_else(unterminated)?;
+            self.pos += 1;
+            let simple = match escape {
+                'a' => Some('\u{07}'),
+                'b' => Some('\u{08}'),
+                'f' => Some('\u{0c}'),
+                'n' => Some('\n'),
+                'r' => Some('\r'),
+                't' => Some('\t'),
+                'v' => Some('\u{0b}'),
+                '\\' | '\'' | '"' | '`' | '?' => Some(escape),
+                _ => None,
+            };
+            if let Some(simple) = simple {
+                push_char(&mut out, simple);
+                continue;
+            }
+            let invalid = || (start, format!("Syntax error: invalid escape sequence '\\{}'", escape));
+            let (digits, radix) = match escape {
+                'x' | 'X' => (2, 16),
+                'u' if !bytes => (4, 16),
+                'U' if !bytes => (8, 16),
+                '0'..='3' => {
+                    self.pos -= 1;
+                    (3, 8)
+                }
+                _ => return Err(invalid()),
+            };
+            let mut text = String::new();
+            for _ in 0..digits {
+                text.push(self.peek().ok_or_else(invalid)?);
+                self.pos += 1;
+            }
+            let code = u32::from_str_radix(&text, radix).map_err(|_| invalid())?;
+            if bytes && code <= 0xff {
+                out.push(code as u8);
+            } else {
+                let ch = char::from_u32(code).ok_or_else(invalid)?;
+                push_char(&mut out, ch);
+            }
+        }
+    }
+}
+
+fn push_char(out: &mut Vec<u8>, ch: char) {
+    let mut buf = [0u8; 4];
+    out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+}
+
+fn string_token(value: Vec<u8>, bytes: bool, start: usize) -> Result<Token, (usize, String)> {
+    if bytes {
+        return Ok(Token::Bytes(value));
+    }
+    String::from_utf8(value)
+        .map(Token::String)
+        .map_err(|_| (start, "Syntax error: invalid UTF-8 in string literal".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        tokenize(source).unwrap().into_iter().map(|(token, _)| token).collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokens("a.b >= 1u && !c[0x10] // comment"),
+            vec![
+                Token::Ident("a".into()),
+                Token::Dot,
+                Token::Ident("b".into()),
+                Token::Ge,
+                Token::Uint(1),
+                Token::AndAnd,
+                Token::Bang,
+                Token::Ident("c".into()),
+                Token::LBracket,
+                Token::Int(16),
+                Token::RBracket,
+                Token::Eof,
+            ]
+        );
+        assert_eq!(tokens("1.5e3 2e-1"), vec![Token::Double(1500.0), Token::Double(0.2), Token::Eof]);
+        assert_eq!(
+            tokens(r#"'a\n' r'\n' b'\xff' """x"y""""#),
+            vec![
+                Token::String("a\n".into()),
+                Token::String("\\n".into()),
+                Token::Bytes(vec![0xff]),
+                Token::String("x\"y".into()),
+                Token::Eof,
+            ]
+        );
+        assert!(tokenize("'abc").is_err());
+    }
+}
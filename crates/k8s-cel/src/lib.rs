@@ -0,0 +1,268 @@
+//! Common Expression Language (CEL)
+//!
+//! This crate provides a parser, type checker and evaluator for CEL
+//! expressions, with the extension libraries the Kubernetes API server makes
+//! available to validation rules and admission policies: strings, lists,
+//! sets, regex, URLs, quantities, IPs and CIDRs, and the authorizer. It also
+//! tracks the cost of evaluation and estimates the worst-case cost of an
+//! expression ahead of time.
+//!
+//! ```
+//! use k8s_cel::{Env, Type, Value};
+//! use std::collections::HashMap;
+//!
+//! let env = Env::new().with_variable("replicas", Type::Int);
+//! let program = env.compile("replicas <= 10").unwrap();
+//! let activation = HashMap::from([("replicas".to_string(), Value::Int(3))]);
+//! assert_eq!(program.evaluate(&activation).unwrap(), Value::Bool(true));
+//! ```
+
+pub mod ast;
+mod checker;
+pub mod cost;
+mod error;
+mod interpreter;
+mod lexer;
+pub mod library;
+mod parser;
+pub mod types;
+mod value;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+pub use cost::{CostEstimate, SizeEstimate, SizeEstimator};
+pub use error::{CompileError, EvalError, Issue};
+pub use interpreter::Activation;
+pub use library::authz::{AuthorizationAttributes, AuthorizationDecision, Authorizer, Decision, StaticAuthorizer};
+pub use parser::parse;
+pub use types::{StructType, Type};
+pub use value::{format_duration, format_timestamp, parse_duration, Key, Value};
+
+use ast::{Expr, ExprId};
+use interpreter::Interpreter;
+
+/// Env declares the variables and object types an expression may refer to.
+#[derive(Clone, Debug, Default)]
+pub struct Env {
+    pub(crate) variables: BTreeMap<String, Type>,
+    pub(crate) types: BTreeMap<String, Arc<StructType>>,
+    dynamic_object_types: Vec<String>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a variable. Dotted names such as `variables.foo` declare a
+    /// variable that is referenced by its qualified name.
+    pub fn with_variable(mut self, name: impl Into<String>, ty: Type) -> Self {
+        self.add_variable(name, ty);
+        self
+    }
+
+    pub fn add_variable(&mut self, name: impl Into<String>, ty: Type) {
+        self.variables.insert(name.into(), ty);
+    }
+
+    /// Declares an object type that can be constructed by name, e.g.
+    /// `Object.spec{replicas: 1}`.
+    pub fn with_type(mut self, struct_type: StructType) -> Self {
+        self.add_type(struct_type);
+        self
+    }
+
+    pub fn add_type(&mut self, struct_type: StructType) {
+        self.types.insert(struct_type.name.clone(), Arc::new(struct_type));
+    }
+
+    /// Allows constructing objects of any type named `prefix` or nested
+    /// under it, e.g. `Object` and `Object.spec.template`, with unchecked
+    /// fields. Mutating admission policies use this for apply configurations.
+    pub fn with_dynamic_object_type(mut self, prefix: impl Into<String>) -> Self {
+        self.dynamic_object_types.push(prefix.into());
+        self
+    }
+
+    pub(crate) fn is_dynamic_object_type(&self, name: &str) -> bool {
+        self.dynamic_object_types.iter().any(|prefix| {
+            name.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    }
+
+    /// Returns the type of a declared variable.
+    pub fn variable(&self, name: &str) -> Option<&Type> {
+        self.variables.get(name)
+    }
+
+    /// Parses and type-checks `source`.
+    pub fn compile(&self, source: &str) -> Result<Program, CompileError> {
+        let mut ast = parse(source)?;
+        let mut errors = CompileError::new(source);
+        let types = checker::check(&mut ast, self, &mut errors);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let result_type = types.get(&ast.expr.id).cloned().unwrap_or(Type::Dyn);
+        Ok(Program {
+            source: source.to_string(),
+            expr: ast.expr,
+            result_type,
+            types,
+        })
+    }
+}
+
+/// Program is a compiled expression, ready to evaluate.
+#[derive(Clone, Debug)]
+pub struct Program {
+    source: String,
+    expr: Expr,
+    result_type: Type,
+    types: HashMap<ExprId, Type>,
+}
+
+impl Program {
+    /// Returns the expression this program was compiled from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the type the expression evaluates to.
+    pub fn result_type(&self) -> &Type {
+        &self.result_type
+    }
+
+    /// Returns true if the expression refers to the variable `name`, e.g. to
+    /// tell transition rules that use `oldSelf` apart from other rules.
+    pub fn references(&self, name: &str) -> bool {
+        self.expr.references(name)
+    }
+
+    pub fn evaluate(&self, activation: &dyn Activation) -> Result<Value, EvalError> {
+        Interpreter::new(activation, None).eval(&self.expr)
+    }
+
+    /// Evaluates the program, failing once the cost of evaluation exceeds
+    /// `limit`. Returns the value and the actual cost.
+    pub fn evaluate_with_cost_limit(
+        &self,
+        activation: &dyn Activation,
+        limit: u64,
+    ) -> Result<(Value, u64), EvalError> {
+        let mut interpreter = Interpreter::new(activation, Some(limit));
+        let value = interpreter.eval(&self.expr)?;
+        Ok((value, interpreter.cost))
+    }
+
+    /// Estimates the cost of evaluating the program given size estimates for
+    /// its inputs.
+    pub fn estimate_cost(&self, sizes: &dyn SizeEstimator) -> CostEstimate {
+        cost::Estimator::new(sizes, &self.types).estimate(&self.expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> Result<Value, EvalError> {
+        let env = Env::new()
+            .with_variable("self", Type::Dyn)
+            .with_variable("variables.limit", Type::Int);
+        let program = env.compile(source).unwrap_or_else(|err| panic!("{}", err));
+        let object = Value::from_json(&serde_json::json!({
+            "spec": {"replicas": 3, "items": ["a", "b", "c"], "labels": {"app": "web"}},
+        }));
+        let activation = HashMap::from([
+            ("self".to_string(), object),
+            ("variables.limit".to_string(), Value::Int(5)),
+        ]);
+        program.evaluate(&activation)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let cases = [
+            "self.spec.replicas <= variables.limit",
+            "self.spec.items.all(i, i.size() == 1)",
+            "self.spec.items.exists_one(i, i == 'b')",
+            "self.spec.items.map(i, i.upperAscii()) == ['A', 'B', 'C']",
+            "self.spec.items.filter(i, i != 'a').size() == 2",
+            "has(self.spec.labels) && !has(self.spec.selector)",
+            "'app' in self.spec.labels && self.spec.labels.app == 'web'",
+            "self.spec.?selector.orValue('none') == 'none'",
+            "cel.bind(x, self.spec.replicas * 2, x + x) == 12",
+            "quantity('1Gi').isGreaterThan(quantity('1G'))",
+            "cidr('10.0.0.0/8').containsIP(ip('10.1.2.3'))",
+            "url('https://example.com:8080/a').getPort() == '8080'",
+            "'a,b'.split(',') == ['a', 'b'] && 'x-%d'.format([1]) == 'x-1'",
+            "'abc123'.matches('^[a-z]+[0-9]+$') && 'abc123'.find('[0-9]+') == '123'",
+            "[3, 1, 2].max() == 3 && sets.contains([1, 2, 3], [2])",
+            "jsonpatch.escapeKey('k8s.io/a~b') == 'k8s.io~1a~0b'",
+            "timestamp('2024-01-01T00:00:00Z') + duration('1h') > timestamp('2024-01-01T00:30:00Z')",
+            "dyn(1) == 1.0 && dyn(1u) == 1 && dyn(1) != 'a'",
+            "(1 / 0 > 0) || true",
+        ];
+        for case in cases {
+            assert_eq!(eval(case).unwrap(), Value::Bool(true), "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        assert_eq!(eval("1 / 0 == 0").unwrap_err().to_string(), "division by zero");
+        assert_eq!(eval("self.spec.missing == 1").unwrap_err().to_string(), "no such key: missing");
+        assert_eq!(
+            eval("[1][3] == 1").unwrap_err().to_string(),
+            "index out of bounds: 3"
+        );
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let env = Env::new().with_variable("self", Type::Int);
+        let err = env.compile("self + 'a'").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERROR: <input>:1:6: found no matching overload for '_+_' applied to '(int, string)'\n | self + 'a'\n | .....^"
+        );
+        let err = env.compile("other > 1").unwrap_err();
+        assert!(err.to_string().contains("undeclared reference to 'other'"), "{}", err);
+        assert!(env.compile("self >").is_err());
+        assert_eq!(env.compile("self > 1").unwrap().result_type(), &Type::Bool);
+    }
+
+    #[test]
+    fn test_cost() {
+        struct Sizes;
+        impl SizeEstimator for Sizes {
+            fn estimate_size(&self, path: &[String]) -> Option<SizeEstimate> {
+                match path.last().map(String::as_str) {
+                    Some("items") => Some(SizeEstimate { min: 0, max: 100 }),
+                    Some("@items") => Some(SizeEstimate { min: 0, max: 64 }),
+                    _ => None,
+                }
+            }
+        }
+        let env = Env::new().with_variable("self", Type::Dyn);
+        let program = env.compile("self.items.all(i, i.startsWith('x'))").unwrap();
+        let estimate = program.estimate_cost(&Sizes);
+        assert!(estimate.max >= 100 * 7, "{:?}", estimate);
+        assert!(estimate.max < 10_000, "{:?}", estimate);
+
+        let activation = HashMap::from([(
+            "self".to_string(),
+            Value::from_json(&serde_json::json!({"items": ["x1", "x2"]})),
+        )]);
+        let (value, cost) = program.evaluate_with_cost_limit(&activation, 100).unwrap();
+        assert_eq!(value, Value::Bool(true));
+        assert!(cost > 0 && cost <= estimate.max);
+        assert!(matches!(
+            program.evaluate_with_cost_limit(&activation, 3),
+            Err(EvalError::CostLimitExceeded)
+        ));
+    }
+}
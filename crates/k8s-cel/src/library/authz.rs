@@ -0,0 +1,246 @@
+//! The Kubernetes authorizer library.
+//!
+//! Expressions build checks such as
+//! `authorizer.group('apps').resource('deployments').namespace('ns').check('get')`
+//! and inspect the resulting decision. The decision itself comes from an
+//! `Authorizer` supplied by the caller through the `authorizer` variable.
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::{string_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::{
+    Type, AUTHORIZER_TYPE, DECISION_TYPE, GROUP_CHECK_TYPE, PATH_CHECK_TYPE, RESOURCE_CHECK_TYPE,
+};
+use crate::value::Value;
+
+/// AuthorizationAttributes describes the request an expression asks about.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuthorizationAttributes {
+    /// The user to check for, when it is not the requesting user, e.g. for
+    /// `authorizer.serviceAccount(ns, name)`.
+    pub user: Option<String>,
+    pub verb: String,
+    /// Set for non-resource checks built with `path()`.
+    pub path: Option<String>,
+    pub group: String,
+    pub resource: String,
+    pub subresource: String,
+    pub namespace: String,
+    pub name: String,
+    pub field_selector: String,
+    pub label_selector: String,
+}
+
+/// Decision is the outcome of an authorization check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny,
+    NoOpinion,
+}
+
+/// AuthorizationDecision is what an `Authorizer` returns for a check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthorizationDecision {
+    pub decision: Decision,
+    pub reason: String,
+    pub error: Option<String>,
+}
+
+impl AuthorizationDecision {
+    pub fn allow(reason: impl Into<String>) -> Self {
+        Self {
+            decision: Decision::Allow,
+            reason: reason.into(),
+            error: None,
+        }
+    }
+
+    pub fn deny(reason: impl Into<String>) -> Self {
+        Self {
+            decision: Decision::Deny,
+            reason: reason.into(),
+            error: None,
+        }
+    }
+
+    pub fn no_opinion(reason: impl Into<String>) -> Self {
+        Self {
+            decision: Decision::NoOpinion,
+            reason: reason.into(),
+            error: None,
+        }
+    }
+}
+
+/// Authorizer answers the checks made by the authorizer library.
+pub trait Authorizer: Send + Sync {
+    fn authorize(&self, attributes: &AuthorizationAttributes) -> AuthorizationDecision;
+}
+
+/// An authorizer that answers every check with the same decision, for
+/// evaluating policies offline.
+#[derive(Clone, Debug)]
+pub struct StaticAuthorizer(pub AuthorizationDecision);
+
+impl Authorizer for StaticAuthorizer {
+    fn authorize(&self, _attributes: &AuthorizationAttributes) -> AuthorizationDecision {
+        self.0.clone()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Stage {
+    Authorizer,
+    PathCheck,
+    GroupCheck,
+    ResourceCheck,
+    Decision(AuthorizationDecision),
+}
+
+/// AuthzValue is the runtime value of the authorizer library types.
+#[derive(Clone)]
+pub struct AuthzValue {
+    authorizer: Arc<dyn Authorizer>,
+    stage: Stage,
+    attributes: AuthorizationAttributes,
+}
+
+impl fmt::Debug for AuthzValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthzValue")
+            .field("stage", &self.stage)
+            .field("attributes", &self.attributes)
+            .finish()
+    }
+}
+
+impl AuthzValue {
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self.stage {
+            Stage::Authorizer => AUTHORIZER_TYPE,
+            Stage::PathCheck => PATH_CHECK_TYPE,
+            Stage::GroupCheck => GROUP_CHECK_TYPE,
+            Stage::ResourceCheck => RESOURCE_CHECK_TYPE,
+            Stage::Decision(_) => DECISION_TYPE,
+        }
+    }
+
+    fn next(&self, stage: Stage, update: impl FnOnce(&mut AuthorizationAttributes)) -> Value {
+        let mut attributes = self.attributes.clone();
+        update(&mut attributes);
+        Value::Authz(Arc::new(AuthzValue {
+            authorizer: self.authorizer.clone(),
+            stage,
+            attributes,
+        }))
+    }
+}
+
+impl Value {
+    /// Returns the value to bind to the `authorizer` variable.
+    pub fn authorizer(authorizer: Arc<dyn Authorizer>) -> Value {
+        Value::Authz(Arc::new(AuthzValue {
+            authorizer,
+            stage: Stage::Authorizer,
+            attributes: AuthorizationAttributes::default(),
+        }))
+    }
+
+    /// Returns the value to bind to `authorizer.requestResource`: a resource
+    /// check preset with the group, resource, subresource, namespace and
+    /// name of the request being admitted.
+    pub fn authorizer_request_resource(
+        authorizer: Arc<dyn Authorizer>,
+        attributes: AuthorizationAttributes,
+    ) -> Value {
+        Value::Authz(Arc::new(AuthzValue {
+            authorizer,
+            stage: Stage::ResourceCheck,
+            attributes,
+        }))
+    }
+}
+
+pub(super) fn declare(decls: &mut Declarations) {
+    use Type::{Bool, Opaque, String};
+    let authorizer = Opaque(AUTHORIZER_TYPE);
+    let path_check = Opaque(PATH_CHECK_TYPE);
+    let group_check = Opaque(GROUP_CHECK_TYPE);
+    let resource_check = Opaque(RESOURCE_CHECK_TYPE);
+    let decision = Opaque(DECISION_TYPE);
+
+    decls.member("path", vec![authorizer.clone(), String], path_check.clone());
+    decls.member("group", vec![authorizer.clone(), String], group_check.clone());
+    decls.member("serviceAccount", vec![authorizer.clone(), String, String], authorizer);
+    decls.member("resource", vec![group_check, String], resource_check.clone());
+    for name in ["subresource", "namespace", "name", "fieldSelector", "labelSelector"] {
+        decls.member(name, vec![resource_check.clone(), String], resource_check.clone());
+    }
+    decls.member("check", vec![path_check, String], decision.clone());
+    decls.member("check", vec![resource_check, String], decision.clone());
+    decls.member("allowed", vec![decision.clone()], Bool);
+    decls.member("errored", vec![decision.clone()], Bool);
+    decls.member("reason", vec![decision.clone()], String);
+    decls.member("error", vec![decision], String);
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    if !receiver {
+        return None;
+    }
+    let Value::Authz(authz) = args.first()? else {
+        return None;
+    };
+    let arg = |idx: usize| args.get(idx).and_then(string_arg).map(str::to_string);
+    let value = match (&authz.stage, function) {
+        (Stage::Authorizer, "path") => {
+            let path = arg(1)?;
+            authz.next(Stage::PathCheck, |a| a.path = Some(path))
+        }
+        (Stage::Authorizer, "group") => {
+            let group = arg(1)?;
+            authz.next(Stage::GroupCheck, |a| a.group = group)
+        }
+        (Stage::Authorizer, "serviceAccount") => {
+            let namespace = arg(1)?;
+            let name = arg(2)?;
+            authz.next(Stage::Authorizer, |a| {
+                a.user = Some(format!("system:serviceaccount:{}:{}", namespace, name))
+            })
+        }
+        (Stage::GroupCheck, "resource") => {
+            let resource = arg(1)?;
+            authz.next(Stage::ResourceCheck, |a| a.resource = resource)
+        }
+        (Stage::ResourceCheck, "subresource" | "namespace" | "name" | "fieldSelector" | "labelSelector") => {
+            let value = arg(1)?;
+            authz.next(Stage::ResourceCheck, |a| match function {
+                "subresource" => a.subresource = value,
+                "namespace" => a.namespace = value,
+                "name" => a.name = value,
+                "fieldSelector" => a.field_selector = value,
+                _ => a.label_selector = value,
+            })
+        }
+        (Stage::PathCheck | Stage::ResourceCheck, "check") => {
+            let verb = arg(1)?;
+            let mut attributes = authz.attributes.clone();
+            attributes.verb = verb;
+            let decision = authz.authorizer.authorize(&attributes);
+            Value::Authz(Arc::new(AuthzValue {
+                authorizer: authz.authorizer.clone(),
+                stage: Stage::Decision(decision),
+                attributes,
+            }))
+        }
+        (Stage::Decision(decision), "allowed") => Value::Bool(decision.decision == Decision::Allow),
+        (Stage::Decision(decision), "errored") => Value::Bool(decision.error.is_some()),
+        (Stage::Decision(decision), "reason") => Value::string(&decision.reason),
+        (Stage::Decision(decision), "error") => Value::string(decision.error.as_deref().unwrap_or_default()),
+        _ => return None,
+    };
+    Some(Ok(value))
+}
@@ -0,0 +1,186 @@
+//! The Kubernetes IP and CIDR libraries.
+//!
+//! Like the apiserver, IPv4-mapped IPv6 addresses and zones are rejected.
+
+use std::net::IpAddr;
+
+use super::{string_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::{Type, CIDR_TYPE, IP_TYPE};
+use crate::value::Value;
+
+pub(super) fn declare(decls: &mut Declarations) {
+    use Type::{Bool, Int, Opaque, String};
+    let ip = Opaque(IP_TYPE);
+    let cidr = Opaque(CIDR_TYPE);
+    decls.global("ip", vec![String], ip.clone());
+    decls.global("isIP", vec![String], Bool);
+    decls.global("ip.isCanonical", vec![String], Bool);
+    decls.global("string", vec![ip.clone()], String);
+    decls.member("family", vec![ip.clone()], Int);
+    for name in [
+        "isUnspecified",
+        "isLoopback",
+        "isLinkLocalMulticast",
+        "isLinkLocalUnicast",
+        "isGlobalUnicast",
+    ] {
+        decls.member(name, vec![ip.clone()], Bool);
+    }
+
+    decls.global("cidr", vec![String], cidr.clone());
+    decls.global("isCIDR", vec![String], Bool);
+    decls.global("string", vec![cidr.clone()], String);
+    decls.member("containsIP", vec![cidr.clone(), ip.clone()], Bool);
+    decls.member("containsIP", vec![cidr.clone(), String], Bool);
+    decls.member("containsCIDR", vec![cidr.clone(), cidr.clone()], Bool);
+    decls.member("containsCIDR", vec![cidr.clone(), String], Bool);
+    decls.member("ip", vec![cidr.clone()], ip);
+    decls.member("masked", vec![cidr.clone()], cidr.clone());
+    decls.member("prefixLength", vec![cidr], Int);
+}
+
+/// Parses an IP address the way the Kubernetes IP library does.
+pub(crate) fn parse_ip(input: &str) -> Result<IpAddr, String> {
+    if input.contains('%') {
+        return Err(format!("IP address with zone value is not allowed: {}", input));
+    }
+    let ip: IpAddr = input
+        .parse()
+        .map_err(|_| format!("IP Address {:?} parse error during conversion from string", input))?;
+    if let IpAddr::V6(v6) = ip {
+        if v6.to_ipv4_mapped().is_some() {
+            return Err(format!("IPv4-mapped IPv6 address {:?} is not allowed", input));
+        }
+    }
+    Ok(ip)
+}
+
+/// Parses a CIDR such as `10.0.0.0/8`, keeping the address unmasked.
+pub(crate) fn parse_cidr(input: &str) -> Result<(IpAddr, u8), String> {
+    let invalid = || format!("network address {:?} parse error during conversion from string", input);
+    let (addr, prefix) = input.split_once('/').ok_or_else(invalid)?;
+    let ip = parse_ip(addr).map_err(|_| invalid())?;
+    if prefix.is_empty() || (prefix.len() > 1 && prefix.starts_with('0')) {
+        return Err(invalid());
+    }
+    let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    if prefix > max {
+        return Err(invalid());
+    }
+    Ok((ip, prefix))
+}
+
+fn to_bits(ip: &IpAddr) -> (u128, u8) {
+    match ip {
+        IpAddr::V4(v4) => (u128::from(u32::from(*v4)), 32),
+        IpAddr::V6(v6) => (u128::from(*v6), 128),
+    }
+}
+
+fn mask(ip: &IpAddr, prefix: u8) -> IpAddr {
+    let (bits, width) = to_bits(ip);
+    let host_bits = u32::from(width - prefix);
+    let masked = if host_bits >= 128 { 0 } else { bits >> host_bits << host_bits };
+    match ip {
+        IpAddr::V4(_) => IpAddr::from((masked as u32).to_be_bytes()),
+        IpAddr::V6(_) => IpAddr::from(masked.to_be_bytes()),
+    }
+}
+
+/// Returns true if `ip` is inside the network `network/prefix`.
+pub(crate) fn cidr_contains(network: &IpAddr, prefix: u8, ip: &IpAddr) -> bool {
+    network.is_ipv4() == ip.is_ipv4() && mask(network, prefix) == mask(ip, prefix)
+}
+
+fn is_link_local_unicast(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_link_local(),
+        IpAddr::V6(v6) => v6.segments()[0] & 0xffc0 == 0xfe80,
+    }
+}
+
+fn is_link_local_multicast(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.octets()[..3] == [224, 0, 0],
+        IpAddr::V6(v6) => v6.segments()[0] & 0xff0f == 0xff02,
+    }
+}
+
+fn is_global_unicast(ip: &IpAddr) -> bool {
+    let broadcast = matches!(ip, IpAddr::V4(v4) if v4.is_broadcast());
+    !(ip.is_unspecified() || ip.is_loopback() || ip.is_multicast() || is_link_local_unicast(ip) || broadcast)
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    if !receiver {
+        let s = string_arg(args.first()?)?;
+        let result = match function {
+            "ip" => parse_ip(s).map(Value::Ip).map_err(EvalError::new),
+            "isIP" => Ok(Value::Bool(parse_ip(s).is_ok())),
+            "ip.isCanonical" => parse_ip(s)
+                .map(|ip| Value::Bool(ip.to_string() == s))
+                .map_err(EvalError::new),
+            "cidr" => parse_cidr(s)
+                .map(|(ip, prefix)| Value::Cidr(ip, prefix))
+                .map_err(EvalError::new),
+            "isCIDR" => Ok(Value::Bool(parse_cidr(s).is_ok())),
+            _ => return None,
+        };
+        return Some(result);
+    }
+
+    let result = match (function, args) {
+        ("family", [Value::Ip(ip)]) => Ok(Value::Int(if ip.is_ipv4() { 4 } else { 6 })),
+        ("isUnspecified", [Value::Ip(ip)]) => Ok(Value::Bool(ip.is_unspecified())),
+        ("isLoopback", [Value::Ip(ip)]) => Ok(Value::Bool(ip.is_loopback())),
+        ("isLinkLocalMulticast", [Value::Ip(ip)]) => Ok(Value::Bool(is_link_local_multicast(ip))),
+        ("isLinkLocalUnicast", [Value::Ip(ip)]) => Ok(Value::Bool(is_link_local_unicast(ip))),
+        ("isGlobalUnicast", [Value::Ip(ip)]) => Ok(Value::Bool(is_global_unicast(ip))),
+        ("containsIP", [Value::Cidr(network, prefix), other]) => {
+            let ip = match other {
+                Value::Ip(ip) => Ok(*ip),
+                Value::String(s) => parse_ip(s).map_err(EvalError::new),
+                _ => return None,
+            };
+            ip.map(|ip| Value::Bool(cidr_contains(network, *prefix, &ip)))
+        }
+        ("containsCIDR", [Value::Cidr(network, prefix), other]) => {
+            let cidr = match other {
+                Value::Cidr(ip, other_prefix) => Ok((*ip, *other_prefix)),
+                Value::String(s) => parse_cidr(s).map_err(EvalError::new),
+                _ => return None,
+            };
+            cidr.map(|(ip, other_prefix)| {
+                Value::Bool(other_prefix >= *prefix && cidr_contains(network, *prefix, &ip))
+            })
+        }
+        ("ip", [Value::Cidr(ip, _)]) => Ok(Value::Ip(*ip)),
+        ("masked", [Value::Cidr(ip, prefix)]) => Ok(Value::Cidr(mask(ip, *prefix), *prefix)),
+        ("prefixLength", [Value::Cidr(_, prefix)]) => Ok(Value::Int(i64::from(*prefix))),
+        _ => return None,
+    };
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ip_and_cidr() {
+        assert!(parse_ip("10.0.0.1").is_ok());
+        assert!(parse_ip("::ffff:10.0.0.1").is_err());
+        assert!(parse_ip("fe80::1%eth0").is_err());
+        assert!(parse_ip("010.0.0.1").is_err());
+
+        let (network, prefix) = parse_cidr("10.1.0.0/16").unwrap();
+        assert!(cidr_contains(&network, prefix, &parse_ip("10.1.2.3").unwrap()));
+        assert!(!cidr_contains(&network, prefix, &parse_ip("10.2.0.1").unwrap()));
+        assert!(!cidr_contains(&network, prefix, &parse_ip("::1").unwrap()));
+        assert_eq!(mask(&parse_ip("10.1.2.3").unwrap(), 8), parse_ip("10.0.0.0").unwrap());
+        assert!(parse_cidr("10.0.0.0/33").is_err());
+        assert!(parse_cidr("10.0.0.0").is_err());
+    }
+}
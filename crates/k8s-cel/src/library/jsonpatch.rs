@@ -0,0 +1,19 @@
+//! The Kubernetes JSON Patch library: `jsonpatch.escapeKey`, which escapes a
+//! map key for use in the path of a JSON Patch operation.
+
+use super::{string_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::Type;
+use crate::value::Value;
+
+pub(super) fn declare(decls: &mut Declarations) {
+    decls.global("jsonpatch.escapeKey", vec![Type::String], Type::String);
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    if function != "jsonpatch.escapeKey" || receiver || args.len() != 1 {
+        return None;
+    }
+    let key = string_arg(&args[0])?;
+    Some(Ok(Value::string(key.replace('~', "~0").replace('/', "~1"))))
+}
@@ -0,0 +1,89 @@
+//! The Kubernetes lists library: `isSorted`, `sum`, `min`, `max`, `indexOf`
+//! and `lastIndexOf` on lists.
+
+use std::cmp::Ordering;
+
+use super::{list_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::Type;
+use crate::value::Value;
+
+pub(super) fn declare(decls: &mut Declarations) {
+    use Type::{Bool, Bytes, Double, Duration, Int, String, Timestamp, Uint};
+    let comparable = [Int, Uint, Double, Bool, String, Bytes, Duration, Timestamp];
+    for ty in &comparable {
+        decls.member("isSorted", vec![Type::list(ty.clone())], Bool);
+        decls.member("min", vec![Type::list(ty.clone())], ty.clone());
+        decls.member("max", vec![Type::list(ty.clone())], ty.clone());
+    }
+    for ty in [Int, Uint, Double, Duration] {
+        decls.member("sum", vec![Type::list(ty.clone())], ty);
+    }
+    let elem = Type::Param("A");
+    decls.member("indexOf", vec![Type::list(elem.clone()), elem.clone()], Int);
+    decls.member("lastIndexOf", vec![Type::list(elem.clone()), elem], Int);
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    if !receiver {
+        return None;
+    }
+    let items = list_arg(args.first()?)?;
+    let result = match (function, &args[1..]) {
+        ("isSorted", []) => {
+            let mut sorted = true;
+            for pair in items.windows(2) {
+                match pair[0].compare(&pair[1]) {
+                    Some(Ordering::Greater) => {
+                        sorted = false;
+                        break;
+                    }
+                    Some(_) => {}
+                    None => return Some(Err(EvalError::no_such_overload("isSorted", pair))),
+                }
+            }
+            Ok(Value::Bool(sorted))
+        }
+        ("sum", []) => sum(items),
+        ("min", []) | ("max", []) => {
+            let wanted = if function == "min" { Ordering::Less } else { Ordering::Greater };
+            let mut iter = items.iter();
+            let Some(mut best) = iter.next() else {
+                return Some(Err(EvalError::new(format!("{} called on empty list", function))));
+            };
+            for item in iter {
+                match item.compare(best) {
+                    Some(ordering) if ordering == wanted => best = item,
+                    Some(_) => {}
+                    None => return Some(Err(EvalError::no_such_overload(function, &[best.clone(), item.clone()]))),
+                }
+            }
+            Ok(best.clone())
+        }
+        ("indexOf", [elem]) => Ok(Value::Int(
+            items.iter().position(|item| item == elem).map(|idx| idx as i64).unwrap_or(-1),
+        )),
+        ("lastIndexOf", [elem]) => Ok(Value::Int(
+            items.iter().rposition(|item| item == elem).map(|idx| idx as i64).unwrap_or(-1),
+        )),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn sum(items: &[Value]) -> Result<Value, EvalError> {
+    let Some(first) = items.first() else {
+        return Ok(Value::Int(0));
+    };
+    let mut total = match first {
+        Value::Int(_) => Value::Int(0),
+        Value::Uint(_) => Value::Uint(0),
+        Value::Double(_) => Value::Double(0.0),
+        Value::Duration(_) => Value::Duration(chrono::Duration::zero()),
+        other => return Err(EvalError::no_such_overload("sum", std::slice::from_ref(other))),
+    };
+    for item in items {
+        total = super::call(crate::ast::operators::ADD, false, &[total, item.clone()])?;
+    }
+    Ok(total)
+}
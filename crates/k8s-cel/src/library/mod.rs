@@ -0,0 +1,127 @@
+//! Function libraries
+//!
+//! Each library declares the overloads the checker resolves calls against
+//! and implements them for the interpreter. Runtime dispatch is on the
+//! dynamic values, so a call that checked against `dyn` arguments still
+//! finds its implementation.
+//!
+//! - `stdlib`: operators, conversions, timestamps and optionals
+//! - `strings`: the cel-go strings extension
+//! - `lists`, `sets`, `regex`, `urls`, `quantity`, `ip`, `authz`,
+//!   `jsonpatch`: the Kubernetes libraries from
+//!   `k8s.io/apiserver/pkg/cel/library`
+
+pub mod authz;
+mod ip;
+mod jsonpatch;
+mod lists;
+mod quantity;
+mod regex;
+mod sets;
+mod stdlib;
+mod strings;
+pub mod urls;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+
+use crate::error::EvalError;
+use crate::types::Type;
+use crate::value::Value;
+
+/// Overload is one signature of a function. For receiver-style overloads
+/// the receiver is the first parameter.
+#[derive(Clone, Debug)]
+pub(crate) struct Overload {
+    pub receiver: bool,
+    pub params: Vec<Type>,
+    pub result: Type,
+}
+
+#[derive(Default)]
+pub(crate) struct Declarations {
+    functions: HashMap<&'static str, Vec<Overload>>,
+}
+
+impl Declarations {
+    pub fn global(&mut self, name: &'static str, params: Vec<Type>, result: Type) {
+        self.functions.entry(name).or_default().push(Overload {
+            receiver: false,
+            params,
+            result,
+        });
+    }
+
+    pub fn member(&mut self, name: &'static str, params: Vec<Type>, result: Type) {
+        self.functions.entry(name).or_default().push(Overload {
+            receiver: true,
+            params,
+            result,
+        });
+    }
+
+    pub fn overloads(&self, name: &str) -> Option<&[Overload]> {
+        self.functions.get(name).map(Vec::as_slice)
+    }
+
+    pub fn has_global(&self, name: &str) -> bool {
+        self.overloads(name)
+            .is_some_and(|overloads| overloads.iter().any(|overload| !overload.receiver))
+    }
+}
+
+pub(crate) static DECLARATIONS: Lazy<Declarations> = Lazy::new(|| {
+    let mut decls = Declarations::default();
+    stdlib::declare(&mut decls);
+    strings::declare(&mut decls);
+    lists::declare(&mut decls);
+    sets::declare(&mut decls);
+    regex::declare(&mut decls);
+    urls::declare(&mut decls);
+    quantity::declare(&mut decls);
+    ip::declare(&mut decls);
+    authz::declare(&mut decls);
+    jsonpatch::declare(&mut decls);
+    decls
+});
+
+/// Calls `function` on already evaluated arguments. For receiver-style calls
+/// the receiver is `args[0]`.
+pub(crate) fn call(function: &str, receiver: bool, args: &[Value]) -> Result<Value, EvalError> {
+    stdlib::call(function, receiver, args)
+        .or_else(|| strings::call(function, receiver, args))
+        .or_else(|| lists::call(function, receiver, args))
+        .or_else(|| sets::call(function, receiver, args))
+        .or_else(|| regex::call(function, receiver, args))
+        .or_else(|| urls::call(function, receiver, args))
+        .or_else(|| quantity::call(function, receiver, args))
+        .or_else(|| ip::call(function, receiver, args))
+        .or_else(|| authz::call(function, receiver, args))
+        .or_else(|| jsonpatch::call(function, receiver, args))
+        .unwrap_or_else(|| Err(EvalError::no_such_overload(function, args)))
+}
+
+pub(crate) fn string_arg(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+pub(crate) fn int_arg(value: &Value) -> Option<i64> {
+    match value {
+        Value::Int(i) => Some(*i),
+        _ => None,
+    }
+}
+
+pub(crate) use stdlib::format_double;
+
+pub(crate) fn list_arg(value: &Value) -> Option<&Arc<Vec<Value>>> {
+    match value {
+        Value::List(items) => Some(items),
+        _ => None,
+    }
+}
@@ -0,0 +1,88 @@
+//! The Kubernetes quantity library: `quantity()`, `isQuantity()` and
+//! arithmetic and comparisons on `kubernetes.Quantity` values.
+
+use std::cmp::Ordering;
+
+use k8s_api_core::resource::Quantity;
+
+use super::{string_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::{Type, QUANTITY_TYPE};
+use crate::value::Value;
+
+pub(super) fn declare(decls: &mut Declarations) {
+    use Type::{Bool, Double, Int, Opaque, String};
+    let quantity = Opaque(QUANTITY_TYPE);
+    decls.global("quantity", vec![String], quantity.clone());
+    decls.global("isQuantity", vec![String], Bool);
+    decls.member("sign", vec![quantity.clone()], Int);
+    decls.member("isInteger", vec![quantity.clone()], Bool);
+    decls.member("asInteger", vec![quantity.clone()], Int);
+    decls.member("asApproximateFloat", vec![quantity.clone()], Double);
+    decls.member("isGreaterThan", vec![quantity.clone(), quantity.clone()], Bool);
+    decls.member("isLessThan", vec![quantity.clone(), quantity.clone()], Bool);
+    decls.member("compareTo", vec![quantity.clone(), quantity.clone()], Int);
+    for name in ["add", "sub"] {
+        decls.member(name, vec![quantity.clone(), quantity.clone()], quantity.clone());
+        decls.member(name, vec![quantity.clone(), Int], quantity.clone());
+    }
+}
+
+fn nanos(quantity: &Quantity) -> Result<i128, EvalError> {
+    quantity.to_nanos().map_err(|err| EvalError::new(err.to_string()))
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    if !receiver {
+        let s = string_arg(args.first()?)?;
+        return match function {
+            "quantity" => Some(
+                Quantity::new(s)
+                    .to_nanos()
+                    .map(|_| Value::Quantity(Quantity::new(s)))
+                    .map_err(|err| EvalError::new(err.to_string())),
+            ),
+            "isQuantity" => Some(Ok(Value::Bool(Quantity::new(s).to_nanos().is_ok()))),
+            _ => None,
+        };
+    }
+    let Value::Quantity(quantity) = args.first()? else {
+        return None;
+    };
+    let result = match (function, &args[1..]) {
+        ("sign", []) => nanos(quantity).map(|n| Value::Int(n.signum() as i64)),
+        ("isInteger", []) => nanos(quantity)
+            .map(|n| Value::Bool(n % 1_000_000_000 == 0 && i64::try_from(n / 1_000_000_000).is_ok())),
+        ("asInteger", []) => nanos(quantity).and_then(|n| {
+            if n % 1_000_000_000 != 0 {
+                return Err(EvalError::new("cannot convert value to integer"));
+            }
+            i64::try_from(n / 1_000_000_000)
+                .map(Value::Int)
+                .map_err(|_| EvalError::new("cannot convert value to integer"))
+        }),
+        ("asApproximateFloat", []) => nanos(quantity).map(|n| Value::Double(n as f64 / 1e9)),
+        ("isGreaterThan" | "isLessThan" | "compareTo", [Value::Quantity(other)]) => {
+            nanos(quantity).and_then(|a| nanos(other).map(|b| a.cmp(&b))).map(|ordering| match function {
+                "isGreaterThan" => Value::Bool(ordering == Ordering::Greater),
+                "isLessThan" => Value::Bool(ordering == Ordering::Less),
+                _ => Value::Int(ordering as i64),
+            })
+        }
+        ("add" | "sub", [other]) => {
+            let other = match other {
+                Value::Quantity(other) => other.clone(),
+                Value::Int(i) => Quantity::new(i.to_string()),
+                _ => return None,
+            };
+            let result = if function == "add" {
+                quantity.checked_add(&other)
+            } else {
+                quantity.checked_sub(&other)
+            };
+            result.map(Value::Quantity).map_err(|err| EvalError::new(err.to_string()))
+        }
+        _ => return None,
+    };
+    Some(result)
+}
@@ -0,0 +1,69 @@
+//! The Kubernetes regex library: `find` and `findAll` on strings.
+//!
+//! Compiled patterns are cached, since rules typically evaluate the same
+//! literal pattern for every object.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use ::regex::Regex;
+use once_cell::sync::Lazy;
+
+use super::{int_arg, string_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::Type;
+use crate::value::Value;
+
+const MAX_CACHED_PATTERNS: usize = 256;
+
+static CACHE: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Compiles `pattern`, reusing a cached regex when possible.
+pub(crate) fn compile(pattern: &str) -> Result<Regex, EvalError> {
+    if let Some(regex) = CACHE.lock().ok().and_then(|cache| cache.get(pattern).cloned()) {
+        return Ok(regex);
+    }
+    let regex = Regex::new(pattern).map_err(|err| EvalError::new(format!("invalid regular expression: {}", err)))?;
+    if let Ok(mut cache) = CACHE.lock() {
+        if cache.len() >= MAX_CACHED_PATTERNS {
+            cache.clear();
+        }
+        cache.insert(pattern.to_string(), regex.clone());
+    }
+    Ok(regex)
+}
+
+pub(super) fn declare(decls: &mut Declarations) {
+    use Type::{Int, String};
+    decls.member("find", vec![String, String], String);
+    decls.member("findAll", vec![String, String], Type::list(String));
+    decls.member("findAll", vec![String, String, Int], Type::list(String));
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    if !receiver || !matches!(function, "find" | "findAll") {
+        return None;
+    }
+    let s = string_arg(args.first()?)?;
+    let pattern = string_arg(args.get(1)?)?;
+    let limit = match args.get(2) {
+        Some(limit) => int_arg(limit)?,
+        None => -1,
+    };
+    let regex = match compile(pattern) {
+        Ok(regex) => regex,
+        Err(err) => return Some(Err(err)),
+    };
+    Some(Ok(match function {
+        "find" => Value::string(regex.find(s).map(|m| m.as_str()).unwrap_or_default()),
+        _ => {
+            let matches = regex.find_iter(s).map(|m| m.as_str().to_string());
+            let matches: Vec<std::string::String> = if limit < 0 {
+                matches.collect()
+            } else {
+                matches.take(limit as usize).collect()
+            };
+            Value::from(matches)
+        }
+    }))
+}
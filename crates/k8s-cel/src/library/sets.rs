@@ -0,0 +1,30 @@
+//! The cel-go sets extension: `sets.contains`, `sets.equivalent` and
+//! `sets.intersects` over lists.
+
+use super::{list_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::Type;
+use crate::value::Value;
+
+pub(super) fn declare(decls: &mut Declarations) {
+    let list = Type::list(Type::Param("A"));
+    for name in ["sets.contains", "sets.equivalent", "sets.intersects"] {
+        decls.global(name, vec![list.clone(), list.clone()], Type::Bool);
+    }
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    if receiver || args.len() != 2 {
+        return None;
+    }
+    let first = list_arg(&args[0])?;
+    let second = list_arg(&args[1])?;
+    let contains_all = |a: &[Value], b: &[Value]| b.iter().all(|item| a.contains(item));
+    let result = match function {
+        "sets.contains" => contains_all(first, second),
+        "sets.equivalent" => contains_all(first, second) && contains_all(second, first),
+        "sets.intersects" => second.iter().any(|item| first.contains(item)),
+        _ => return None,
+    };
+    Some(Ok(Value::Bool(result)))
+}
@@ -0,0 +1,565 @@
+//! Standard definitions: operators, conversions, timestamps and optionals
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, Offset, TimeZone, Timelike, Utc};
+
+use super::{string_arg, Declarations};
+use crate::ast::operators;
+use crate::error::EvalError;
+use crate::types::Type;
+use crate::value::{display_value, format_duration, format_timestamp, parse_duration, Value};
+
+fn a() -> Type {
+    Type::Param("A")
+}
+
+fn k() -> Type {
+    Type::Param("K")
+}
+
+fn v() -> Type {
+    Type::Param("V")
+}
+
+pub(super) fn declare(decls: &mut Declarations) {
+    use Type::{Bool, Bytes, Double, Duration, Dyn, Int, String, Timestamp, Uint};
+
+    for (op, types) in [
+        (operators::ADD, vec![Int, Uint, Double, String, Bytes]),
+        (operators::SUBTRACT, vec![Int, Uint, Double]),
+        (operators::MULTIPLY, vec![Int, Uint, Double]),
+        (operators::DIVIDE, vec![Int, Uint, Double]),
+        (operators::MODULO, vec![Int, Uint]),
+    ] {
+        for ty in types {
+            decls.global(op, vec![ty.clone(), ty.clone()], ty);
+        }
+    }
+    decls.global(operators::ADD, vec![Type::list(a()), Type::list(a())], Type::list(a()));
+    decls.global(operators::ADD, vec![Timestamp, Duration], Timestamp);
+    decls.global(operators::ADD, vec![Duration, Timestamp], Timestamp);
+    decls.global(operators::ADD, vec![Duration, Duration], Duration);
+    decls.global(operators::SUBTRACT, vec![Timestamp, Timestamp], Duration);
+    decls.global(operators::SUBTRACT, vec![Timestamp, Duration], Timestamp);
+    decls.global(operators::SUBTRACT, vec![Duration, Duration], Duration);
+    decls.global(operators::NEGATE, vec![Int], Int);
+    decls.global(operators::NEGATE, vec![Double], Double);
+    decls.global(operators::LOGICAL_NOT, vec![Bool], Bool);
+    decls.global(operators::LOGICAL_AND, vec![Bool, Bool], Bool);
+    decls.global(operators::LOGICAL_OR, vec![Bool, Bool], Bool);
+    decls.global(operators::NOT_STRICTLY_FALSE, vec![Bool], Bool);
+    decls.global(operators::EQUALS, vec![a(), a()], Bool);
+    decls.global(operators::NOT_EQUALS, vec![a(), a()], Bool);
+
+    let numeric = [Int, Uint, Double];
+    for op in [
+        operators::LESS,
+        operators::LESS_EQUALS,
+        operators::GREATER,
+        operators::GREATER_EQUALS,
+    ] {
+        for ty in [Bool, String, Bytes, Timestamp, Duration] {
+            decls.global(op, vec![ty.clone(), ty], Bool);
+        }
+        for left in &numeric {
+            for right in &numeric {
+                decls.global(op, vec![left.clone(), right.clone()], Bool);
+            }
+        }
+    }
+
+    decls.global(operators::INDEX, vec![Type::list(a()), Int], a());
+    decls.global(operators::INDEX, vec![Type::map(k(), v()), k()], v());
+    decls.global(operators::OPT_INDEX, vec![Type::list(a()), Int], Type::optional(a()));
+    decls.global(operators::OPT_INDEX, vec![Type::map(k(), v()), k()], Type::optional(v()));
+    decls.global(
+        operators::OPT_INDEX,
+        vec![Type::optional(Type::list(a())), Int],
+        Type::optional(a()),
+    );
+    decls.global(
+        operators::OPT_INDEX,
+        vec![Type::optional(Type::map(k(), v())), k()],
+        Type::optional(v()),
+    );
+    decls.global(operators::IN, vec![a(), Type::list(a())], Bool);
+    decls.global(operators::IN, vec![k(), Type::map(k(), v())], Bool);
+
+    for ty in [String, Bytes, Type::list(a()), Type::map(k(), v())] {
+        decls.global("size", vec![ty.clone()], Int);
+        decls.member("size", vec![ty], Int);
+    }
+
+    for ty in [Int, Uint, Double, String, Timestamp] {
+        decls.global("int", vec![ty], Int);
+    }
+    for ty in [Int, Uint, Double, String] {
+        decls.global("uint", vec![ty.clone()], Uint);
+        decls.global("double", vec![ty], Double);
+    }
+    for ty in [Int, Uint, Double, String, Bytes, Bool, Timestamp, Duration] {
+        decls.global("string", vec![ty], String);
+    }
+    decls.global("bytes", vec![String], Bytes);
+    decls.global("bytes", vec![Bytes], Bytes);
+    decls.global("bool", vec![Bool], Bool);
+    decls.global("bool", vec![String], Bool);
+    decls.global("dyn", vec![a()], Dyn);
+    decls.global("type", vec![a()], Type::Type(Box::new(a())));
+    decls.global("duration", vec![String], Duration);
+    decls.global("duration", vec![Duration], Duration);
+    decls.global("timestamp", vec![String], Timestamp);
+    decls.global("timestamp", vec![Int], Timestamp);
+    decls.global("timestamp", vec![Timestamp], Timestamp);
+
+    for name in ["contains", "startsWith", "endsWith", "matches"] {
+        decls.member(name, vec![String, String], Bool);
+    }
+    decls.global("matches", vec![String, String], Bool);
+
+    for name in [
+        "getFullYear",
+        "getMonth",
+        "getDate",
+        "getDayOfMonth",
+        "getDayOfWeek",
+        "getDayOfYear",
+        "getHours",
+        "getMinutes",
+        "getSeconds",
+        "getMilliseconds",
+    ] {
+        decls.member(name, vec![Timestamp], Int);
+        decls.member(name, vec![Timestamp, String], Int);
+    }
+    for name in ["getHours", "getMinutes", "getSeconds", "getMilliseconds"] {
+        decls.member(name, vec![Duration], Int);
+    }
+
+    decls.global("optional.of", vec![a()], Type::optional(a()));
+    decls.global("optional.ofNonZeroValue", vec![a()], Type::optional(a()));
+    decls.global("optional.none", vec![], Type::optional(Dyn));
+    decls.member("hasValue", vec![Type::optional(a())], Bool);
+    decls.member("value", vec![Type::optional(a())], a());
+    decls.member("or", vec![Type::optional(a()), Type::optional(a())], Type::optional(a()));
+    decls.member("orValue", vec![Type::optional(a()), a()], a());
+}
+
+fn overflow() -> EvalError {
+    EvalError::new("integer overflow")
+}
+
+fn uint_overflow() -> EvalError {
+    EvalError::new("unsigned integer overflow")
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    let result = match (function, args) {
+        (operators::ADD, [l, r]) => add(l, r)?,
+        (operators::SUBTRACT, [l, r]) => subtract(l, r)?,
+        (operators::MULTIPLY, [l, r]) => multiply(l, r)?,
+        (operators::DIVIDE, [l, r]) => divide(l, r)?,
+        (operators::MODULO, [l, r]) => modulo(l, r)?,
+        (operators::NEGATE, [Value::Int(i)]) => i.checked_neg().map(Value::Int).ok_or_else(overflow),
+        (operators::NEGATE, [Value::Double(d)]) => Ok(Value::Double(-d)),
+        (operators::LOGICAL_NOT, [Value::Bool(b)]) => Ok(Value::Bool(!b)),
+        (operators::EQUALS, [l, r]) => Ok(Value::Bool(l == r)),
+        (operators::NOT_EQUALS, [l, r]) => Ok(Value::Bool(l != r)),
+        (operators::LESS, [l, r]) => compare(l, r, |o| o == Ordering::Less)?,
+        (operators::LESS_EQUALS, [l, r]) => compare(l, r, |o| o != Ordering::Greater)?,
+        (operators::GREATER, [l, r]) => compare(l, r, |o| o == Ordering::Greater)?,
+        (operators::GREATER_EQUALS, [l, r]) => compare(l, r, |o| o != Ordering::Less)?,
+        (operators::INDEX, [container, key]) => index(container, key)?,
+        (operators::OPT_INDEX, [container, index]) => optional_index(container, index)?,
+        (operators::IN, [elem, container]) => contains(container, elem)?,
+        ("size", [value]) => size(value)?,
+        ("int", [value]) if !receiver => to_int(value)?,
+        ("uint", [value]) if !receiver => to_uint(value)?,
+        ("double", [value]) if !receiver => to_double(value)?,
+        ("string", [value]) if !receiver => to_string(value)?,
+        ("bytes", [Value::String(s)]) if !receiver => Ok(Value::Bytes(Arc::from(s.as_bytes()))),
+        ("bytes", [value @ Value::Bytes(_)]) if !receiver => Ok(value.clone()),
+        ("bool", [value]) if !receiver => to_bool(value)?,
+        ("dyn", [value]) if !receiver => Ok(value.clone()),
+        ("type", [value]) if !receiver => Ok(Value::Type(value.type_of())),
+        ("duration", [Value::String(s)]) if !receiver => parse_duration(s).map(Value::Duration),
+        ("duration", [value @ Value::Duration(_)]) if !receiver => Ok(value.clone()),
+        ("timestamp", [Value::String(s)]) if !receiver => parse_timestamp(s).map(Value::Timestamp),
+        ("timestamp", [Value::Int(seconds)]) if !receiver => DateTime::from_timestamp(*seconds, 0)
+            .map(Value::Timestamp)
+            .ok_or_else(|| EvalError::new("timestamp out of range")),
+        ("timestamp", [value @ Value::Timestamp(_)]) if !receiver => Ok(value.clone()),
+        ("contains", [Value::String(s), Value::String(sub)]) => Ok(Value::Bool(s.contains(&**sub))),
+        ("startsWith", [Value::String(s), Value::String(prefix)]) => Ok(Value::Bool(s.starts_with(&**prefix))),
+        ("endsWith", [Value::String(s), Value::String(suffix)]) => Ok(Value::Bool(s.ends_with(&**suffix))),
+        ("matches", [Value::String(s), Value::String(pattern)]) => {
+            super::regex::compile(pattern).map(|re| Value::Bool(re.is_match(s)))
+        }
+        ("getHours", [Value::Duration(d)]) if receiver => Ok(Value::Int(d.num_hours())),
+        ("getMinutes", [Value::Duration(d)]) if receiver => Ok(Value::Int(d.num_minutes())),
+        ("getSeconds", [Value::Duration(d)]) if receiver => Ok(Value::Int(d.num_seconds())),
+        ("getMilliseconds", [Value::Duration(d)]) if receiver => Ok(Value::Int(d.num_milliseconds() % 1000)),
+        (
+            "getFullYear" | "getMonth" | "getDate" | "getDayOfMonth" | "getDayOfWeek" | "getDayOfYear" | "getHours"
+            | "getMinutes" | "getSeconds" | "getMilliseconds",
+            [Value::Timestamp(ts), rest @ ..],
+        ) if receiver && rest.len() <= 1 => {
+            let zone = rest.first().map(string_arg);
+            match zone {
+                Some(None) => return None,
+                Some(Some(zone)) => timezone_offset(ts, zone).and_then(|offset| {
+                    timestamp_field(function, &ts.with_timezone(&offset).naive_local())
+                }),
+                None => timestamp_field(function, &ts.naive_utc()),
+            }
+        }
+        ("optional.of", [value]) if !receiver => Ok(Value::optional(Some(value.clone()))),
+        ("optional.ofNonZeroValue", [value]) if !receiver => {
+            Ok(Value::optional((!is_zero_value(value)).then(|| value.clone())))
+        }
+        ("optional.none", []) if !receiver => Ok(Value::Optional(None)),
+        ("hasValue", [Value::Optional(opt)]) => Ok(Value::Bool(opt.is_some())),
+        ("value", [Value::Optional(opt)]) => match opt {
+            Some(value) => Ok((**value).clone()),
+            None => Err(EvalError::new("optional.none() dereference")),
+        },
+        ("or", [Value::Optional(opt), other @ Value::Optional(_)]) => match opt {
+            Some(_) => Ok(args[0].clone()),
+            None => Ok(other.clone()),
+        },
+        ("orValue", [Value::Optional(opt), other]) => match opt {
+            Some(value) => Ok((**value).clone()),
+            None => Ok(other.clone()),
+        },
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn add(l: &Value, r: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match (l, r) {
+        (Value::Int(a), Value::Int(b)) => a.checked_add(*b).map(Value::Int).ok_or_else(overflow),
+        (Value::Uint(a), Value::Uint(b)) => a.checked_add(*b).map(Value::Uint).ok_or_else(uint_overflow),
+        (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a + b)),
+        (Value::String(a), Value::String(b)) => Ok(Value::string(format!("{}{}", a, b))),
+        (Value::Bytes(a), Value::Bytes(b)) => Ok(Value::Bytes(Arc::from([&a[..], &b[..]].concat()))),
+        (Value::List(a), Value::List(b)) => {
+            if b.is_empty() {
+                return Some(Ok(l.clone()));
+            }
+            let mut items = Vec::with_capacity(a.len() + b.len());
+            items.extend(a.iter().cloned());
+            items.extend(b.iter().cloned());
+            Ok(Value::list(items))
+        }
+        (Value::Timestamp(t), Value::Duration(d)) | (Value::Duration(d), Value::Timestamp(t)) => t
+            .checked_add_signed(*d)
+            .map(Value::Timestamp)
+            .ok_or_else(|| EvalError::new("timestamp overflow")),
+        (Value::Duration(a), Value::Duration(b)) => a.checked_add(b).map(Value::Duration).ok_or_else(overflow),
+        _ => return None,
+    })
+}
+
+fn subtract(l: &Value, r: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match (l, r) {
+        (Value::Int(a), Value::Int(b)) => a.checked_sub(*b).map(Value::Int).ok_or_else(overflow),
+        (Value::Uint(a), Value::Uint(b)) => a.checked_sub(*b).map(Value::Uint).ok_or_else(uint_overflow),
+        (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a - b)),
+        (Value::Timestamp(a), Value::Timestamp(b)) => Ok(Value::Duration(a.signed_duration_since(*b))),
+        (Value::Timestamp(t), Value::Duration(d)) => t
+            .checked_sub_signed(*d)
+            .map(Value::Timestamp)
+            .ok_or_else(|| EvalError::new("timestamp overflow")),
+        (Value::Duration(a), Value::Duration(b)) => a.checked_sub(b).map(Value::Duration).ok_or_else(overflow),
+        _ => return None,
+    })
+}
+
+fn multiply(l: &Value, r: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match (l, r) {
+        (Value::Int(a), Value::Int(b)) => a.checked_mul(*b).map(Value::Int).ok_or_else(overflow),
+        (Value::Uint(a), Value::Uint(b)) => a.checked_mul(*b).map(Value::Uint).ok_or_else(uint_overflow),
+        (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a * b)),
+        _ => return None,
+    })
+}
+
+fn divide(l: &Value, r: &Value) -> Option<Result<Value, EvalError>> {
+    let by_zero = || EvalError::new("division by zero");
+    Some(match (l, r) {
+        (Value::Int(_), Value::Int(0)) | (Value::Uint(_), Value::Uint(0)) => Err(by_zero()),
+        (Value::Int(a), Value::Int(b)) => a.checked_div(*b).map(Value::Int).ok_or_else(overflow),
+        (Value::Uint(a), Value::Uint(b)) => Ok(Value::Uint(a / b)),
+        (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a / b)),
+        _ => return None,
+    })
+}
+
+fn modulo(l: &Value, r: &Value) -> Option<Result<Value, EvalError>> {
+    let by_zero = || EvalError::new("modulus by zero");
+    Some(match (l, r) {
+        (Value::Int(_), Value::Int(0)) | (Value::Uint(_), Value::Uint(0)) => Err(by_zero()),
+        (Value::Int(a), Value::Int(b)) => a.checked_rem(*b).map(Value::Int).ok_or_else(overflow),
+        (Value::Uint(a), Value::Uint(b)) => Ok(Value::Uint(a % b)),
+        _ => return None,
+    })
+}
+
+fn compare(l: &Value, r: &Value, accept: fn(Ordering) -> bool) -> Option<Result<Value, EvalError>> {
+    match l.compare(r) {
+        Some(ordering) => Some(Ok(Value::Bool(accept(ordering)))),
+        // NaN compares false against everything.
+        None if matches!((l, r), (Value::Double(_), _) | (_, Value::Double(_)))
+            && matches!(l, Value::Int(_) | Value::Uint(_) | Value::Double(_))
+            && matches!(r, Value::Int(_) | Value::Uint(_) | Value::Double(_)) =>
+        {
+            Some(Ok(Value::Bool(false)))
+        }
+        None => None,
+    }
+}
+
+fn list_position(index: &Value, len: usize) -> Option<Result<Option<usize>, EvalError>> {
+    let position = match index {
+        Value::Int(i) => *i,
+        Value::Uint(u) => i64::try_from(*u).unwrap_or(i64::MAX),
+        Value::Double(d) if d.fract() == 0.0 => *d as i64,
+        Value::Double(d) => return Some(Err(EvalError::new(format!("unsupported index value {}", d)))),
+        _ => return None,
+    };
+    if position < 0 || position as u64 >= len as u64 {
+        return Some(Ok(None));
+    }
+    Some(Ok(Some(position as usize)))
+}
+
+fn index(container: &Value, index: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match container {
+        Value::List(items) => match list_position(index, items.len())? {
+            Ok(Some(pos)) => Ok(items[pos].clone()),
+            Ok(None) => Err(EvalError::new(format!(
+                "index out of bounds: {}",
+                display_value(index)
+            ))),
+            Err(err) => Err(err),
+        },
+        Value::Map(map) => match Value::map_get(map, index) {
+            Some(value) => Ok(value.clone()),
+            None => Err(EvalError::new(format!(
+                "no such key: {}",
+                display_value(index)
+            ))),
+        },
+        Value::Optional(None) => Ok(Value::Optional(None)),
+        _ => return None,
+    })
+}
+
+fn optional_index(container: &Value, index: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match container {
+        Value::List(items) => match list_position(index, items.len())? {
+            Ok(pos) => Ok(Value::optional(pos.map(|pos| items[pos].clone()))),
+            Err(err) => Err(err),
+        },
+        Value::Map(map) => Ok(Value::optional(Value::map_get(map, index).cloned())),
+        Value::Optional(None) => Ok(Value::Optional(None)),
+        Value::Optional(Some(inner)) => return optional_index(inner, index),
+        _ => return None,
+    })
+}
+
+fn contains(container: &Value, elem: &Value) -> Option<Result<Value, EvalError>> {
+    Some(Ok(Value::Bool(match container {
+        Value::List(items) => items.iter().any(|item| item == elem),
+        Value::Map(map) => Value::map_get(map, elem).is_some(),
+        _ => return None,
+    })))
+}
+
+fn size(value: &Value) -> Option<Result<Value, EvalError>> {
+    let size = match value {
+        Value::String(s) => s.chars().count(),
+        Value::Bytes(b) => b.len(),
+        Value::List(items) => items.len(),
+        Value::Map(map) => map.len(),
+        _ => return None,
+    };
+    Some(Ok(Value::Int(size as i64)))
+}
+
+fn range_error(value: &Value, ty: &str) -> EvalError {
+    EvalError::new(format!(
+        "range error converting {} to {}",
+        display_value(value),
+        ty
+    ))
+}
+
+fn to_int(value: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match value {
+        Value::Int(i) => Ok(Value::Int(*i)),
+        Value::Uint(u) => i64::try_from(*u).map(Value::Int).map_err(|_| range_error(value, "int")),
+        Value::Double(d) => {
+            if d.is_nan() || *d <= -9.223_372_036_854_776e18 || *d >= 9.223_372_036_854_776e18 {
+                Err(range_error(value, "int"))
+            } else {
+                Ok(Value::Int(*d as i64))
+            }
+        }
+        Value::String(s) => s
+            .parse::<i64>()
+            .map(Value::Int)
+            .map_err(|_| EvalError::new(format!("cannot convert {:?} to int", s))),
+        Value::Timestamp(ts) => Ok(Value::Int(ts.timestamp())),
+        _ => return None,
+    })
+}
+
+fn to_uint(value: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match value {
+        Value::Uint(u) => Ok(Value::Uint(*u)),
+        Value::Int(i) => u64::try_from(*i).map(Value::Uint).map_err(|_| range_error(value, "uint")),
+        Value::Double(d) => {
+            if d.is_nan() || *d < 0.0 || *d >= 1.844_674_407_370_955_2e19 {
+                Err(range_error(value, "uint"))
+            } else {
+                Ok(Value::Uint(*d as u64))
+            }
+        }
+        Value::String(s) => s
+            .parse::<u64>()
+            .map(Value::Uint)
+            .map_err(|_| EvalError::new(format!("cannot convert {:?} to uint", s))),
+        _ => return None,
+    })
+}
+
+fn to_double(value: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match value {
+        Value::Double(d) => Ok(Value::Double(*d)),
+        Value::Int(i) => Ok(Value::Double(*i as f64)),
+        Value::Uint(u) => Ok(Value::Double(*u as f64)),
+        Value::String(s) => s
+            .parse::<f64>()
+            .map(Value::Double)
+            .map_err(|_| EvalError::new(format!("cannot convert {:?} to double", s))),
+        _ => return None,
+    })
+}
+
+fn to_bool(value: &Value) -> Option<Result<Value, EvalError>> {
+    Some(match value {
+        Value::Bool(b) => Ok(Value::Bool(*b)),
+        Value::String(s) => match &**s {
+            "1" | "t" | "T" | "true" | "TRUE" | "True" => Ok(Value::Bool(true)),
+            "0" | "f" | "F" | "false" | "FALSE" | "False" => Ok(Value::Bool(false)),
+            _ => Err(EvalError::new(format!("cannot convert {:?} to bool", s))),
+        },
+        _ => return None,
+    })
+}
+
+fn to_string(value: &Value) -> Option<Result<Value, EvalError>> {
+    Some(Ok(Value::string(match value {
+        Value::String(_) => return Some(Ok(value.clone())),
+        Value::Int(i) => i.to_string(),
+        Value::Uint(u) => u.to_string(),
+        Value::Double(d) => format_double(*d),
+        Value::Bool(b) => b.to_string(),
+        Value::Bytes(b) => match std::str::from_utf8(b) {
+            Ok(s) => s.to_string(),
+            Err(_) => return Some(Err(EvalError::new("invalid UTF-8 in bytes, cannot convert to string"))),
+        },
+        Value::Timestamp(ts) => format_timestamp(ts),
+        Value::Duration(d) => format_duration(d),
+        Value::Ip(ip) => ip.to_string(),
+        Value::Cidr(ip, prefix) => format!("{}/{}", ip, prefix),
+        _ => return None,
+    })))
+}
+
+/// Formats a double like Go's `strconv.FormatFloat(d, 'g', -1, 64)`.
+pub(crate) fn format_double(d: f64) -> String {
+    if d.is_nan() {
+        return "NaN".to_string();
+    }
+    if d.is_infinite() {
+        return if d > 0.0 { "+Inf" } else { "-Inf" }.to_string();
+    }
+    let abs = d.abs();
+    if abs != 0.0 && !(1e-4..1e21).contains(&abs) {
+        let formatted = format!("{:e}", d);
+        return match formatted.split_once('e') {
+            Some((mantissa, exp)) if exp.starts_with('-') => {
+                format!("{}e-{:0>2}", mantissa, exp.trim_start_matches('-'))
+            }
+            Some((mantissa, exp)) => format!("{}e+{:0>2}", mantissa, exp),
+            None => formatted,
+        };
+    }
+    format!("{}", d)
+}
+
+fn parse_timestamp(input: &str) -> Result<DateTime<Utc>, EvalError> {
+    DateTime::parse_from_rfc3339(input)
+        .map(|ts| ts.with_timezone(&Utc))
+        .map_err(|_| EvalError::new(format!("cannot parse {:?} as timestamp", input)))
+}
+
+fn timezone_offset(ts: &DateTime<Utc>, zone: &str) -> Result<FixedOffset, EvalError> {
+    let invalid = || EvalError::new(format!("invalid timezone {:?}", zone));
+    if zone == "UTC" || zone == "Z" {
+        return FixedOffset::east_opt(0).ok_or_else(invalid);
+    }
+    let signed = zone
+        .strip_prefix('+')
+        .map(|rest| (1, rest))
+        .or_else(|| zone.strip_prefix('-').map(|rest| (-1, rest)));
+    if let Some((sign, rest)) = signed {
+        let (hours, minutes) = rest.split_once(':').ok_or_else(invalid)?;
+        let hours: i32 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+        return FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid);
+    }
+    let tz: chrono_tz::Tz = zone.parse().map_err(|_| invalid())?;
+    Ok(tz.offset_from_utc_datetime(&ts.naive_utc()).fix())
+}
+
+fn timestamp_field(function: &str, local: &NaiveDateTime) -> Result<Value, EvalError> {
+    let value = match function {
+        "getFullYear" => local.year() as i64,
+        "getMonth" => local.month0() as i64,
+        "getDate" => local.day() as i64,
+        "getDayOfMonth" => local.day0() as i64,
+        "getDayOfWeek" => local.weekday().num_days_from_sunday() as i64,
+        "getDayOfYear" => local.ordinal0() as i64,
+        "getHours" => local.hour() as i64,
+        "getMinutes" => local.minute() as i64,
+        "getSeconds" => local.second() as i64,
+        _ => (local.nanosecond() / 1_000_000) as i64,
+    };
+    Ok(Value::Int(value))
+}
+
+/// Returns true for the zero value of the value's type, as used by
+/// `optional.ofNonZeroValue`.
+pub(crate) fn is_zero_value(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Bool(b) => !b,
+        Value::Int(i) => *i == 0,
+        Value::Uint(u) => *u == 0,
+        Value::Double(d) => *d == 0.0,
+        Value::String(s) => s.is_empty(),
+        Value::Bytes(b) => b.is_empty(),
+        Value::List(items) => items.is_empty(),
+        Value::Map(map) => map.is_empty(),
+        Value::Duration(d) => d.is_zero(),
+        Value::Timestamp(ts) => ts.timestamp() == 0 && ts.timestamp_subsec_nanos() == 0,
+        Value::Optional(opt) => opt.is_none(),
+        _ => false,
+    }
+}
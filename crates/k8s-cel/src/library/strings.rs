@@ -0,0 +1,283 @@
+//! The cel-go strings extension
+//!
+//! Indices and lengths are counted in code points, as in cel-go.
+
+use super::{int_arg, list_arg, string_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::Type;
+use crate::value::{display_value, Value};
+
+pub(super) fn declare(decls: &mut Declarations) {
+    use Type::{Dyn, Int, String};
+    decls.member("charAt", vec![String, Int], String);
+    for name in ["indexOf", "lastIndexOf"] {
+        decls.member(name, vec![String, String], Int);
+        decls.member(name, vec![String, String, Int], Int);
+    }
+    for name in ["lowerAscii", "upperAscii", "trim", "reverse"] {
+        decls.member(name, vec![String], String);
+    }
+    decls.member("replace", vec![String, String, String], String);
+    decls.member("replace", vec![String, String, String, Int], String);
+    decls.member("split", vec![String, String], Type::list(String));
+    decls.member("split", vec![String, String, Int], Type::list(String));
+    decls.member("substring", vec![String, Int], String);
+    decls.member("substring", vec![String, Int, Int], String);
+    decls.member("join", vec![Type::list(String)], String);
+    decls.member("join", vec![Type::list(String), String], String);
+    decls.member("format", vec![String, Type::list(Dyn)], String);
+    decls.global("strings.quote", vec![String], String);
+}
+
+fn index_error(index: i64) -> EvalError {
+    EvalError::new(format!("index out of range: {}", index))
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    if function == "strings.quote" && !receiver {
+        return Some(Ok(Value::string(quote(string_arg(args.first()?)?))));
+    }
+    if !receiver {
+        return None;
+    }
+    if function == "join" {
+        let items = list_arg(args.first()?)?;
+        let separator = match args.get(1) {
+            Some(separator) => string_arg(separator)?,
+            None => "",
+        };
+        let mut parts = Vec::with_capacity(items.len());
+        for item in items.iter() {
+            match item {
+                Value::String(s) => parts.push(s.to_string()),
+                other => return Some(Err(EvalError::no_such_overload("join", std::slice::from_ref(other)))),
+            }
+        }
+        return Some(Ok(Value::string(parts.join(separator))));
+    }
+
+    let s = string_arg(args.first()?)?;
+    let result = match (function, &args[1..]) {
+        ("charAt", [index]) => {
+            let index = int_arg(index)?;
+            let chars: Vec<char> = s.chars().collect();
+            if index < 0 || index as usize > chars.len() {
+                Err(index_error(index))
+            } else {
+                Ok(Value::string(chars.get(index as usize).map(|c| c.to_string()).unwrap_or_default()))
+            }
+        }
+        ("indexOf", [sub, rest @ ..]) if rest.len() <= 1 => {
+            let sub = string_arg(sub)?;
+            let start = match rest.first() {
+                Some(start) => int_arg(start)?,
+                None => 0,
+            };
+            index_of(s, sub, start, false)
+        }
+        ("lastIndexOf", [sub, rest @ ..]) if rest.len() <= 1 => {
+            let sub = string_arg(sub)?;
+            let start = match rest.first() {
+                Some(start) => int_arg(start)?,
+                None => s.chars().count() as i64,
+            };
+            index_of(s, sub, start, true)
+        }
+        ("lowerAscii", []) => Ok(Value::string(s.to_ascii_lowercase())),
+        ("upperAscii", []) => Ok(Value::string(s.to_ascii_uppercase())),
+        ("trim", []) => Ok(Value::string(s.trim())),
+        ("reverse", []) => Ok(Value::string(s.chars().rev().collect::<String>())),
+        ("replace", [from, to, rest @ ..]) if rest.len() <= 1 => {
+            let from = string_arg(from)?;
+            let to = string_arg(to)?;
+            let limit = match rest.first() {
+                Some(limit) => int_arg(limit)?,
+                None => -1,
+            };
+            Ok(Value::string(if limit < 0 {
+                s.replace(from, to)
+            } else {
+                s.replacen(from, to, limit as usize)
+            }))
+        }
+        ("split", [separator, rest @ ..]) if rest.len() <= 1 => {
+            let separator = string_arg(separator)?;
+            let limit = match rest.first() {
+                Some(limit) => int_arg(limit)?,
+                None => -1,
+            };
+            Ok(Value::from(split(s, separator, limit)))
+        }
+        ("substring", [start, rest @ ..]) if rest.len() <= 1 => {
+            let chars: Vec<char> = s.chars().collect();
+            let start = int_arg(start)?;
+            let end = match rest.first() {
+                Some(end) => int_arg(end)?,
+                None => chars.len() as i64,
+            };
+            if start < 0 || start as usize > chars.len() {
+                Err(index_error(start))
+            } else if end < 0 || end as usize > chars.len() {
+                Err(index_error(end))
+            } else if start > end {
+                Err(EvalError::new(format!("invalid substring range. start: {}, end: {}", start, end)))
+            } else {
+                Ok(Value::string(chars[start as usize..end as usize].iter().collect::<String>()))
+            }
+        }
+        ("format", [values]) => format(s, list_arg(values)?),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn index_of(s: &str, sub: &str, start: i64, last: bool) -> Result<Value, EvalError> {
+    let chars: Vec<char> = s.chars().collect();
+    let sub: Vec<char> = sub.chars().collect();
+    if start < 0 || start as usize > chars.len() {
+        return Err(index_error(start));
+    }
+    let start = start as usize;
+    let matches_at = |idx: usize| idx + sub.len() <= chars.len() && chars[idx..idx + sub.len()] == sub[..];
+    let found = if last {
+        (0..=start).rev().find(|idx| matches_at(*idx))
+    } else {
+        (start..=chars.len()).find(|idx| matches_at(*idx))
+    };
+    Ok(Value::Int(found.map(|idx| idx as i64).unwrap_or(-1)))
+}
+
+fn split(s: &str, separator: &str, limit: i64) -> Vec<String> {
+    match limit {
+        0 => Vec::new(),
+        1 => vec![s.to_string()],
+        _ if separator.is_empty() => {
+            let chars: Vec<String> = s.chars().map(String::from).collect();
+            if limit > 0 && chars.len() > limit as usize {
+                let mut head: Vec<String> = chars[..limit as usize - 1].to_vec();
+                head.push(chars[limit as usize - 1..].concat());
+                head
+            } else {
+                chars
+            }
+        }
+        _ if limit < 0 => s.split(separator).map(String::from).collect(),
+        _ => s.splitn(limit as usize, separator).map(String::from).collect(),
+    }
+}
+
+/// Quotes a string using CEL string literal escapes.
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '\u{07}' => out.push_str("\\a"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{0b}' => out.push_str("\\v"),
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Implements `"...".format([args])` with the `%s`, `%d`, `%f`, `%e`, `%x`,
+/// `%X`, `%o` and `%b` verbs, and an optional precision such as `%.2f`.
+fn format(template: &str, args: &[Value]) -> Result<Value, EvalError> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    let mut next_arg = args.iter();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            out.push(ch);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            out.push('%');
+            continue;
+        }
+        let mut precision: Option<usize> = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            let mut digits = String::new();
+            while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                digits.push(*digit);
+                chars.next();
+            }
+            precision = Some(digits.parse().unwrap_or(0));
+        }
+        let verb = chars
+            .next()
+            .ok_or_else(|| EvalError::new("unexpected end of format string"))?;
+        let arg = next_arg
+            .next()
+            .ok_or_else(|| EvalError::new(format!("index {} out of range", args.len())))?;
+        let formatted = match (verb, arg) {
+            ('s', value) => display_value(value),
+            ('d', Value::Int(i)) => i.to_string(),
+            ('d', Value::Uint(u)) => u.to_string(),
+            ('f', value) => format!("{:.*}", precision.unwrap_or(6), as_f64(value)?),
+            ('e', value) => format_exponent(as_f64(value)?, precision.unwrap_or(6)),
+            ('x', Value::String(s)) => s.bytes().map(|b| format!("{:02x}", b)).collect(),
+            ('X', Value::String(s)) => s.bytes().map(|b| format!("{:02X}", b)).collect(),
+            ('x', Value::Int(i)) => format_signed(*i, |u| format!("{:x}", u)),
+            ('X', Value::Int(i)) => format_signed(*i, |u| format!("{:X}", u)),
+            ('o', Value::Int(i)) => format_signed(*i, |u| format!("{:o}", u)),
+            ('b', Value::Int(i)) => format_signed(*i, |u| format!("{:b}", u)),
+            ('x', Value::Uint(u)) => format!("{:x}", u),
+            ('X', Value::Uint(u)) => format!("{:X}", u),
+            ('o', Value::Uint(u)) => format!("{:o}", u),
+            ('b', Value::Uint(u)) => format!("{:b}", u),
+            (verb, value) => {
+                return Err(EvalError::new(format!(
+                    "error during formatting: unrecognized formatting clause \"{}\" for {}",
+                    verb,
+                    value.type_of()
+                )))
+            }
+        };
+        out.push_str(&formatted);
+    }
+    Ok(Value::string(out))
+}
+
+fn as_f64(value: &Value) -> Result<f64, EvalError> {
+    match value {
+        Value::Double(d) => Ok(*d),
+        Value::Int(i) => Ok(*i as f64),
+        Value::Uint(u) => Ok(*u as f64),
+        other => Err(EvalError::new(format!(
+            "error during formatting: fixed-point clause can only be used on doubles, was given {}",
+            other.type_of()
+        ))),
+    }
+}
+
+fn format_signed(value: i64, format: impl Fn(u64) -> String) -> String {
+    if value < 0 {
+        format!("-{}", format(value.unsigned_abs()))
+    } else {
+        format(value as u64)
+    }
+}
+
+fn format_exponent(value: f64, precision: usize) -> String {
+    let formatted = format!("{:.*e}", precision, value);
+    match formatted.split_once('e') {
+        Some((mantissa, exp)) => {
+            let (sign, digits) = match exp.strip_prefix('-') {
+                Some(digits) => ('-', digits),
+                None => ('+', exp),
+            };
+            format!("{}e{}{:0>2}", mantissa, sign, digits)
+        }
+        None => formatted,
+    }
+}
@@ -0,0 +1,297 @@
+//! The Kubernetes URL library: `url()`, `isURL()` and the URL accessors.
+//!
+//! Parsing follows Go's `net/url`: `url()` accepts what `ParseRequestURI`
+//! accepts, i.e. absolute URLs and absolute paths.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{string_arg, Declarations};
+use crate::error::EvalError;
+use crate::types::{Type, URL_TYPE};
+use crate::value::{Key, Value};
+
+/// Url is a parsed URL, keeping the components in their escaped form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Url {
+    raw: String,
+    pub scheme: String,
+    pub opaque: String,
+    pub user_info: Option<String>,
+    pub host: String,
+    pub path: String,
+    pub raw_query: String,
+    pub fragment: Option<String>,
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+fn parse_error(input: &str, message: impl fmt::Display) -> String {
+    format!("parse {:?}: {}", input, message)
+}
+
+fn validate_escapes(input: &str, part: &str) -> Result<(), String> {
+    let bytes = part.as_bytes();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let valid = bytes.get(idx + 1..idx + 3).is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit));
+            if !valid {
+                let end = (idx + 3).min(part.len());
+                return Err(parse_error(input, format!("invalid URL escape {:?}", &part[idx..end])));
+            }
+            idx += 3;
+        } else {
+            idx += 1;
+        }
+    }
+    Ok(())
+}
+
+impl Url {
+    /// Parses `input` like Go's `url.ParseRequestURI`, but keeps a trailing
+    /// fragment separate the way `url.Parse` does.
+    pub fn parse(input: &str) -> Result<Url, String> {
+        let (rest, fragment) = match input.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment.to_string())),
+            None => (input, None),
+        };
+        if rest.is_empty() {
+            return Err(parse_error(input, "empty url"));
+        }
+        if rest.chars().any(|ch| ch.is_ascii_control()) {
+            return Err(parse_error(input, "net/url: invalid control character in URL"));
+        }
+
+        let mut scheme = String::new();
+        let mut rest = rest;
+        if let Some(colon) = rest.find(':') {
+            let candidate = &rest[..colon];
+            let valid = candidate.chars().next().is_some_and(|ch| ch.is_ascii_alphabetic())
+                && candidate
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '-' | '.'));
+            if colon == 0 {
+                return Err(parse_error(input, "missing protocol scheme"));
+            }
+            if valid {
+                scheme = candidate.to_ascii_lowercase();
+                rest = &rest[colon + 1..];
+            }
+        }
+
+        let (rest, raw_query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, query.to_string()),
+            None => (rest, String::new()),
+        };
+
+        if scheme.is_empty() && !rest.starts_with('/') {
+            return Err(parse_error(input, "invalid URI for request"));
+        }
+        if !scheme.is_empty() && !rest.starts_with('/') {
+            return Ok(Url {
+                raw: input.to_string(),
+                scheme,
+                opaque: rest.to_string(),
+                user_info: None,
+                host: String::new(),
+                path: String::new(),
+                raw_query,
+                fragment,
+            });
+        }
+
+        let mut user_info = None;
+        let mut host = String::new();
+        let mut path = rest;
+        if !scheme.is_empty() && rest.starts_with("//") {
+            let authority_and_path = &rest[2..];
+            let authority_end = authority_and_path.find('/').unwrap_or(authority_and_path.len());
+            let mut authority = &authority_and_path[..authority_end];
+            path = &authority_and_path[authority_end..];
+            if let Some(at) = authority.rfind('@') {
+                user_info = Some(authority[..at].to_string());
+                authority = &authority[at + 1..];
+            }
+            validate_host(input, authority)?;
+            host = authority.to_string();
+        }
+        validate_escapes(input, path)?;
+        validate_escapes(input, &raw_query)?;
+
+        Ok(Url {
+            raw: input.to_string(),
+            scheme,
+            opaque: String::new(),
+            user_info,
+            host,
+            path: path.to_string(),
+            raw_query,
+            fragment,
+        })
+    }
+
+    /// Returns the host without the port, and without brackets for IPv6.
+    pub fn hostname(&self) -> &str {
+        if let Some(stripped) = self.host.strip_prefix('[') {
+            return stripped.split(']').next().unwrap_or_default();
+        }
+        self.host.split(':').next().unwrap_or_default()
+    }
+
+    /// Returns the port, or an empty string if the URL has none.
+    pub fn port(&self) -> &str {
+        let after_host = match self.host.rfind(']') {
+            Some(idx) => &self.host[idx + 1..],
+            None => self.host.find(':').map(|idx| &self.host[idx..]).unwrap_or_default(),
+        };
+        after_host.strip_prefix(':').unwrap_or_default()
+    }
+
+    /// Returns the escaped path, like Go's `URL.EscapedPath`.
+    pub fn escaped_path(&self) -> String {
+        let mut out = String::new();
+        for ch in self.path.chars() {
+            if ch.is_ascii_alphanumeric() || "-_.~$&+,/:;=@%!*'()".contains(ch) {
+                out.push(ch);
+            } else {
+                let mut buf = [0u8; 4];
+                for byte in ch.encode_utf8(&mut buf).bytes() {
+                    out.push_str(&format!("%{:02X}", byte));
+                }
+            }
+        }
+        out
+    }
+
+    /// Parses the query into its decoded keys and values.
+    pub fn query(&self) -> BTreeMap<String, Vec<String>> {
+        let mut query: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for pair in self.raw_query.split(['&', ';']).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            query.entry(unescape_query(key)).or_default().push(unescape_query(value));
+        }
+        query
+    }
+}
+
+fn validate_host(input: &str, host: &str) -> Result<(), String> {
+    let port = if let Some(stripped) = host.strip_prefix('[') {
+        let Some(end) = stripped.find(']') else {
+            return Err(parse_error(input, "missing ']' in host"));
+        };
+        &stripped[end + 1..]
+    } else {
+        match host.rfind(':') {
+            Some(idx) => &host[idx..],
+            None => "",
+        }
+    };
+    let valid_port = port
+        .strip_prefix(':')
+        .is_some_and(|digits| digits.chars().all(|ch| ch.is_ascii_digit()));
+    if !port.is_empty() && !valid_port {
+        return Err(parse_error(input, format!("invalid port {:?} after host", port)));
+    }
+    if host.contains(' ') {
+        return Err(parse_error(input, "invalid character \" \" in host name"));
+    }
+    Ok(())
+}
+
+fn unescape_query(part: &str) -> String {
+    let bytes = part.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let decoded = bytes
+                    .get(idx + 1..idx + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match decoded {
+                    Some(byte) => {
+                        out.push(byte);
+                        idx += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        idx += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub(super) fn declare(decls: &mut Declarations) {
+    use Type::{Bool, Opaque, String};
+    let url = Opaque(URL_TYPE);
+    decls.global("url", vec![String], url.clone());
+    decls.global("isURL", vec![String], Bool);
+    for name in ["getScheme", "getHost", "getHostname", "getPort", "getEscapedPath"] {
+        decls.member(name, vec![url.clone()], String);
+    }
+    decls.member("getQuery", vec![url], Type::map(String, Type::list(String)));
+}
+
+pub(super) fn call(function: &str, receiver: bool, args: &[Value]) -> Option<Result<Value, EvalError>> {
+    match (function, receiver, args) {
+        ("url", false, [value]) => {
+            let s = string_arg(value)?;
+            Some(
+                Url::parse(s)
+                    .map(|url| Value::Url(Arc::new(url)))
+                    .map_err(|err| EvalError::new(format!("URL parse error during conversion from string: {}", err))),
+            )
+        }
+        ("isURL", false, [value]) => Some(Ok(Value::Bool(Url::parse(string_arg(value)?).is_ok()))),
+        (_, true, [Value::Url(url)]) => {
+            let result = match function {
+                "getScheme" => Value::string(&url.scheme),
+                "getHost" => Value::string(&url.host),
+                "getHostname" => Value::string(url.hostname()),
+                "getPort" => Value::string(url.port()),
+                "getEscapedPath" => Value::string(url.escaped_path()),
+                "getQuery" => Value::map(
+                    url.query()
+                        .into_iter()
+                        .map(|(key, values)| (Key::from(key.as_str()), Value::from(values)))
+                        .collect(),
+                ),
+                _ => return None,
+            };
+            Some(Ok(result))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let url = Url::parse("https://user@[::1]:80/a%20b/c d?x=1&x=2&y=a+b#frag").unwrap();
+        assert_eq!(url.scheme, "https");
+        assert_eq!(url.host, "[::1]:80");
+        assert_eq!(url.hostname(), "::1");
+        assert_eq!(url.port(), "80");
+        assert_eq!(url.escaped_path(), "/a%20b/c%20d");
+        assert_eq!(url.query()["x"], vec!["1", "2"]);
+        assert_eq!(url.query()["y"], vec!["a b"]);
+
+        assert_eq!(Url::parse("/path").unwrap().host, "");
+        assert!(Url::parse("example.com").is_err());
+        assert!(Url::parse("https://host:abc").is_err());
+        assert!(Url::parse("https://host/%zz").is_err());
+    }
+}
@@ -0,0 +1,721 @@
+//! Recursive-descent parser producing an `Ast`, with macro expansion
+
+use std::collections::HashMap;
+
+use crate::ast::{operators, Ast, Comprehension, Constant, Expr, ExprId, ExprKind, MapEntry, StructField, ACCUMULATOR_VAR};
+use crate::error::CompileError;
+use crate::lexer::{tokenize, Token, RESERVED};
+
+const MAX_RECURSION_DEPTH: usize = 250;
+
+/// Parses `source` into an AST.
+pub fn parse(source: &str) -> Result<Ast, CompileError> {
+    let mut errors = CompileError::new(source);
+    let tokens = match tokenize(source) {
+        Ok(tokens) => tokens,
+        Err((offset, message)) => {
+            errors.push(offset, message);
+            return Err(errors);
+        }
+    };
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        next_id: 1,
+        depth: 0,
+        offsets: HashMap::new(),
+    };
+    let result = parser.expr().and_then(|expr| match parser.peek() {
+        Token::Eof => Ok(expr),
+        token => Err((parser.offset(), format!("Syntax error: extraneous input '{}'", token))),
+    });
+    match result {
+        Ok(expr) => Ok(Ast {
+            expr,
+            offsets: parser.offsets,
+        }),
+        Err((offset, message)) => {
+            errors.push(offset, message);
+            Err(errors)
+        }
+    }
+}
+
+type ParseResult<T> = Result<T, (usize, String)>;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    next_id: ExprId,
+    depth: usize,
+    offsets: HashMap<ExprId, usize>,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn peek_at(&self, ahead: usize) -> &Token {
+        let idx = (self.pos + ahead).min(self.tokens.len() - 1);
+        &self.tokens[idx].0
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> ParseResult<()> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", token)))
+        }
+    }
+
+    fn unexpected(&self, expecting: &str) -> (usize, String) {
+        let message = match self.peek() {
+            Token::Eof => format!("Syntax error: mismatched input '<EOF>' expecting {}", expecting),
+            token => format!("Syntax error: mismatched input '{}' expecting {}", token, expecting),
+        };
+        (self.offset(), message)
+    }
+
+    fn new_expr(&mut self, offset: usize, kind: ExprKind) -> Expr {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.offsets.insert(id, offset);
+        Expr { id, kind }
+    }
+
+    fn call(&mut self, offset: usize, function: &str, args: Vec<Expr>) -> Expr {
+        self.new_expr(
+            offset,
+            ExprKind::Call {
+                target: None,
+                function: function.to_string(),
+                args,
+            },
+        )
+    }
+
+    fn ident(&mut self, offset: usize, name: &str) -> Expr {
+        self.new_expr(offset, ExprKind::Ident(name.to_string()))
+    }
+
+    fn constant(&mut self, offset: usize, constant: Constant) -> Expr {
+        self.new_expr(offset, ExprKind::Const(constant))
+    }
+
+    fn enter(&mut self) -> ParseResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_RECURSION_DEPTH {
+            return Err((self.offset(), "expression recursion limit exceeded".to_string()));
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self) -> ParseResult<Expr> {
+        self.enter()?;
+        let offset = self.offset();
+        let condition = self.conditional_or()?;
+        let result = if self.eat(&Token::Question) {
+            let truthy = self.conditional_or()?;
+            self.expect(Token::Colon)?;
+            let falsy = self.expr()?;
+            Ok(self.call(offset, operators::CONDITIONAL, vec![condition, truthy, falsy]))
+        } else {
+            Ok(condition)
+        };
+        self.depth -= 1;
+        result
+    }
+
+    fn conditional_or(&mut self) -> ParseResult<Expr> {
+        let mut left = self.conditional_and()?;
+        while *self.peek() == Token::OrOr {
+            let offset = self.offset();
+            self.advance();
+            let right = self.conditional_and()?;
+            left = self.call(offset, operators::LOGICAL_OR, vec![left, right]);
+        }
+        Ok(left)
+    }
+
+    fn conditional_and(&mut self) -> ParseResult<Expr> {
+        let mut left = self.relation()?;
+        while *self.peek() == Token::AndAnd {
+            let offset = self.offset();
+            self.advance();
+            let right = self.relation()?;
+            left = self.call(offset, operators::LOGICAL_AND, vec![left, right]);
+        }
+        Ok(left)
+    }
+
+    fn relation(&mut self) -> ParseResult<Expr> {
+        let mut left = self.addition()?;
+        loop {
+            let function = match self.peek() {
+                Token::Lt => operators::LESS,
+                Token::Le => operators::LESS_EQUALS,
+                Token::Gt => operators::GREATER,
+                Token::Ge => operators::GREATER_EQUALS,
+                Token::EqEq => operators::EQUALS,
+                Token::NotEq => operators::NOT_EQUALS,
+                Token::In => operators::IN,
+                _ => return Ok(left),
+            };
+            let offset = self.offset();
+            self.advance();
+            let right = self.addition()?;
+            left = self.call(offset, function, vec![left, right]);
+        }
+    }
+
+    fn addition(&mut self) -> ParseResult<Expr> {
+        let mut left = self.multiplication()?;
+        loop {
+            let function = match self.peek() {
+                Token::Plus => operators::ADD,
+                Token::Minus => operators::SUBTRACT,
+                _ => return Ok(left),
+            };
+            let offset = self.offset();
+            self.advance();
+            let right = self.multiplication()?;
+            left = self.call(offset, function, vec![left, right]);
+        }
+    }
+
+    fn multiplication(&mut self) -> ParseResult<Expr> {
+        let mut left = self.unary()?;
+        loop {
+            let function = match self.peek() {
+                Token::Star => operators::MULTIPLY,
+                Token::Slash => operators::DIVIDE,
+                Token::Percent => operators::MODULO,
+                _ => return Ok(left),
+            };
+            let offset = self.offset();
+            self.advance();
+            let right = self.unary()?;
+            left = self.call(offset, function, vec![left, right]);
+        }
+    }
+
+    fn unary(&mut self) -> ParseResult<Expr> {
+        let offset = self.offset();
+        match self.peek() {
+            Token::Bang => {
+                let mut count = 0;
+                while self.eat(&Token::Bang) {
+                    count += 1;
+                }
+                let mut expr = self.member()?;
+                for _ in 0..count {
+                    expr = self.call(offset, operators::LOGICAL_NOT, vec![expr]);
+                }
+                Ok(expr)
+            }
+            Token::Minus => {
+                let mut count = 0;
+                while self.eat(&Token::Minus) {
+                    count += 1;
+                }
+                // A single minus directly applied to a numeric literal folds
+                // into the literal, which allows the minimum int64.
+                let mut expr = match (self.peek().clone(), count % 2) {
+                    (Token::Int(value), 1) if !self.is_member_suffix(1) => {
+                        self.advance();
+                        count -= 1;
+                        let value = if value == 1u64 << 63 {
+                            i64::MIN
+                        } else {
+                            -i64::try_from(value).map_err(|_| (offset, "invalid int literal".to_string()))?
+                        };
+                        self.constant(offset, Constant::Int(value))
+                    }
+                    (Token::Double(value), 1) if !self.is_member_suffix(1) => {
+                        self.advance();
+                        count -= 1;
+                        self.constant(offset, Constant::Double(-value))
+                    }
+                    _ => self.member()?,
+                };
+                for _ in 0..count {
+                    expr = self.call(offset, operators::NEGATE, vec![expr]);
+                }
+                Ok(expr)
+            }
+            _ => self.member(),
+        }
+    }
+
+    fn is_member_suffix(&self, ahead: usize) -> bool {
+        matches!(self.peek_at(ahead), Token::Dot | Token::LBracket)
+    }
+
+    fn member(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            let offset = self.offset();
+            match self.peek() {
+                Token::Dot => {
+                    self.advance();
+                    let optional = self.eat(&Token::Question);
+                    let field = self.identifier()?;
+                    if optional {
+                        let field = self.constant(offset, Constant::String(field));
+                        expr = self.call(offset, operators::OPT_SELECT, vec![expr, field]);
+                    } else if *self.peek() == Token::LParen {
+                        self.advance();
+                        let args = self.arguments(Token::RParen)?;
+                        expr = self.receiver_call(offset, expr, &field, args)?;
+                    } else if *self.peek() == Token::LBrace && expr.qualified_name().is_some() {
+                        let type_name = format!("{}.{}", expr.qualified_name().unwrap_or_default(), field);
+                        expr = self.struct_literal(offset, type_name)?;
+                    } else {
+                        expr = self.new_expr(
+                            offset,
+                            ExprKind::Select {
+                                operand: Box::new(expr),
+                                field,
+                                test_only: false,
+                            },
+                        );
+                    }
+                }
+                Token::LBracket => {
+                    self.advance();
+                    let optional = self.eat(&Token::Question);
+                    let index = self.expr()?;
+                    self.expect(Token::RBracket)?;
+                    let function = if optional { operators::OPT_INDEX } else { operators::INDEX };
+                    expr = self.call(offset, function, vec![expr, index]);
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> ParseResult<String> {
+        match self.peek().clone() {
+            Token::Ident(name) => {
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("IDENTIFIER")),
+        }
+    }
+
+    fn arguments(&mut self, close: Token) -> ParseResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        if self.eat(&close) {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr()?);
+            if self.eat(&Token::Comma) {
+                if self.eat(&close) {
+                    return Ok(args);
+                }
+                continue;
+            }
+            self.expect(close.clone())?;
+            return Ok(args);
+        }
+    }
+
+    fn primary(&mut self) -> ParseResult<Expr> {
+        self.enter()?;
+        let offset = self.offset();
+        let expr = match self.advance() {
+            Token::Int(value) => {
+                let value = i64::try_from(value).map_err(|_| (offset, "invalid int literal".to_string()))?;
+                self.constant(offset, Constant::Int(value))
+            }
+            Token::Uint(value) => self.constant(offset, Constant::Uint(value)),
+            Token::Double(value) => self.constant(offset, Constant::Double(value)),
+            Token::String(value) => self.constant(offset, Constant::String(value)),
+            Token::Bytes(value) => self.constant(offset, Constant::Bytes(value)),
+            Token::True => self.constant(offset, Constant::Bool(true)),
+            Token::False => self.constant(offset, Constant::Bool(false)),
+            Token::Null => self.constant(offset, Constant::Null),
+            Token::LParen => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                expr
+            }
+            Token::LBracket => self.list_literal(offset)?,
+            Token::LBrace => self.map_literal(offset)?,
+            Token::Dot => {
+                // Leading dot: a name resolved from the root scope.
+                let name = self.identifier()?;
+                self.named_primary(offset, format!(".{}", name))?
+            }
+            Token::Ident(name) if RESERVED.contains(&name.as_str()) => {
+                return Err((offset, format!("reserved identifier: {}", name)));
+            }
+            Token::Ident(name) => self.named_primary(offset, name)?,
+            token => {
+                let message = match token {
+                    Token::Eof => "Syntax error: mismatched input '<EOF>' expecting an expression".to_string(),
+                    token => format!("Syntax error: mismatched input '{}' expecting an expression", token),
+                };
+                return Err((offset, message));
+            }
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn named_primary(&mut self, offset: usize, name: String) -> ParseResult<Expr> {
+        match self.peek() {
+            Token::LParen => {
+                self.advance();
+                let args = self.arguments(Token::RParen)?;
+                self.global_call(offset, name.trim_start_matches('.'), args)
+            }
+            Token::LBrace => self.struct_literal(offset, name.trim_start_matches('.').to_string()),
+            _ => Ok(self.ident(offset, name.trim_start_matches('.'))),
+        }
+    }
+
+    fn list_literal(&mut self, offset: usize) -> ParseResult<Expr> {
+        let mut elements = Vec::new();
+        let mut optional_indices = Vec::new();
+        while *self.peek() != Token::RBracket {
+            if self.eat(&Token::Question) {
+                optional_indices.push(elements.len());
+            }
+            elements.push(self.expr()?);
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(Token::RBracket)?;
+        Ok(self.new_expr(
+            offset,
+            ExprKind::List {
+                elements,
+                optional_indices,
+            },
+        ))
+    }
+
+    fn map_literal(&mut self, offset: usize) -> ParseResult<Expr> {
+        let mut entries = Vec::new();
+        while *self.peek() != Token::RBrace {
+            let optional = self.eat(&Token::Question);
+            let key = self.expr()?;
+            self.expect(Token::Colon)?;
+            let value = self.expr()?;
+            entries.push(MapEntry { key, value, optional });
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(Token::RBrace)?;
+        Ok(self.new_expr(offset, ExprKind::Map { entries }))
+    }
+
+    fn struct_literal(&mut self, offset: usize, type_name: String) -> ParseResult<Expr> {
+        self.expect(Token::LBrace)?;
+        let mut fields = Vec::new();
+        while *self.peek() != Token::RBrace {
+            let optional = self.eat(&Token::Question);
+            let name = self.identifier()?;
+            self.expect(Token::Colon)?;
+            let value = self.expr()?;
+            fields.push(StructField { name, value, optional });
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(Token::RBrace)?;
+        Ok(self.new_expr(offset, ExprKind::Struct { type_name, fields }))
+    }
+
+    fn global_call(&mut self, offset: usize, function: &str, mut args: Vec<Expr>) -> ParseResult<Expr> {
+        if function == "has" && args.len() == 1 {
+            let arg = args.remove(0);
+            return match arg.kind {
+                ExprKind::Select { operand, field, .. } => Ok(self.new_expr(
+                    offset,
+                    ExprKind::Select {
+                        operand,
+                        field,
+                        test_only: true,
+                    },
+                )),
+                _ => Err((offset, "invalid argument to has() macro".to_string())),
+            };
+        }
+        Ok(self.call(offset, function, args))
+    }
+
+    fn receiver_call(&mut self, offset: usize, target: Expr, function: &str, mut args: Vec<Expr>) -> ParseResult<Expr> {
+        let is_cel_namespace = matches!(&target.kind, ExprKind::Ident(name) if name == "cel");
+        let expanded = match (function, args.len()) {
+            ("all", 2) | ("exists", 2) | ("exists_one", 2) | ("existsOne", 2) | ("map", 2) | ("map", 3)
+            | ("filter", 2) | ("optMap", 2) | ("optFlatMap", 2) => true,
+            ("bind", 3) => is_cel_namespace,
+            _ => false,
+        };
+        if !expanded {
+            return Ok(self.new_expr(
+                offset,
+                ExprKind::Call {
+                    target: Some(Box::new(target)),
+                    function: function.to_string(),
+                    args,
+                },
+            ));
+        }
+
+        let var = match &args[0].kind {
+            ExprKind::Ident(name) => name.clone(),
+            _ => return Err((self.offsets.get(&args[0].id).copied().unwrap_or(offset), "argument must be a simple name".to_string())),
+        };
+        args.remove(0);
+        let accu = || ACCUMULATOR_VAR.to_string();
+
+        let comprehension = match function {
+            "all" => {
+                let init = self.constant(offset, Constant::Bool(true));
+                let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                let condition = self.call(offset, operators::NOT_STRICTLY_FALSE, vec![accu_ref]);
+                let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                let step = self.call(offset, operators::LOGICAL_AND, vec![accu_ref, args.remove(0)]);
+                let result = self.ident(offset, ACCUMULATOR_VAR);
+                Comprehension {
+                    iter_var: var,
+                    iter_range: target,
+                    accu_var: accu(),
+                    accu_init: init,
+                    loop_condition: condition,
+                    loop_step: step,
+                    result,
+                }
+            }
+            "exists" => {
+                let init = self.constant(offset, Constant::Bool(false));
+                let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                let not_accu = self.call(offset, operators::LOGICAL_NOT, vec![accu_ref]);
+                let condition = self.call(offset, operators::NOT_STRICTLY_FALSE, vec![not_accu]);
+                let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                let step = self.call(offset, operators::LOGICAL_OR, vec![accu_ref, args.remove(0)]);
+                let result = self.ident(offset, ACCUMULATOR_VAR);
+                Comprehension {
+                    iter_var: var,
+                    iter_range: target,
+                    accu_var: accu(),
+                    accu_init: init,
+                    loop_condition: condition,
+                    loop_step: step,
+                    result,
+                }
+            }
+            "exists_one" | "existsOne" => {
+                let init = self.constant(offset, Constant::Int(0));
+                let condition = self.constant(offset, Constant::Bool(true));
+                let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                let one = self.constant(offset, Constant::Int(1));
+                let incremented = self.call(offset, operators::ADD, vec![accu_ref, one]);
+                let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                let step = self.call(offset, operators::CONDITIONAL, vec![args.remove(0), incremented, accu_ref]);
+                let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                let one = self.constant(offset, Constant::Int(1));
+                let result = self.call(offset, operators::EQUALS, vec![accu_ref, one]);
+                Comprehension {
+                    iter_var: var,
+                    iter_range: target,
+                    accu_var: accu(),
+                    accu_init: init,
+                    loop_condition: condition,
+                    loop_step: step,
+                    result,
+                }
+            }
+            "map" | "filter" => {
+                let (filter, transform) = match (function, args.len()) {
+                    ("map", 1) => (None, args.remove(0)),
+                    ("map", _) => {
+                        let filter = args.remove(0);
+                        (Some(filter), args.remove(0))
+                    }
+                    _ => {
+                        let element = self.ident(offset, &var);
+                        (Some(args.remove(0)), element)
+                    }
+                };
+                let init = self.new_expr(
+                    offset,
+                    ExprKind::List {
+                        elements: Vec::new(),
+                        optional_indices: Vec::new(),
+                    },
+                );
+                let condition = self.constant(offset, Constant::Bool(true));
+                let single = self.new_expr(
+                    offset,
+                    ExprKind::List {
+                        elements: vec![transform],
+                        optional_indices: Vec::new(),
+                    },
+                );
+                let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                let appended = self.call(offset, operators::ADD, vec![accu_ref, single]);
+                let step = match filter {
+                    Some(filter) => {
+                        let accu_ref = self.ident(offset, ACCUMULATOR_VAR);
+                        self.call(offset, operators::CONDITIONAL, vec![filter, appended, accu_ref])
+                    }
+                    None => appended,
+                };
+                let result = self.ident(offset, ACCUMULATOR_VAR);
+                Comprehension {
+                    iter_var: var,
+                    iter_range: target,
+                    accu_var: accu(),
+                    accu_init: init,
+                    loop_condition: condition,
+                    loop_step: step,
+                    result,
+                }
+            }
+            "bind" => {
+                let init = args.remove(0);
+                let body = args.remove(0);
+                let range = self.new_expr(
+                    offset,
+                    ExprKind::List {
+                        elements: Vec::new(),
+                        optional_indices: Vec::new(),
+                    },
+                );
+                let condition = self.constant(offset, Constant::Bool(false));
+                let step = self.ident(offset, &var);
+                Comprehension {
+                    iter_var: "#unused".to_string(),
+                    iter_range: range,
+                    accu_var: var,
+                    accu_init: init,
+                    loop_condition: condition,
+                    loop_step: step,
+                    result: body,
+                }
+            }
+            _ => {
+                // optMap / optFlatMap:
+                //   target.hasValue() ? <wrap>(cel.bind(var, target.value(), body)) : optional.none()
+                let body = args.remove(0);
+                let has_value = self.new_expr(
+                    offset,
+                    ExprKind::Call {
+                        target: Some(Box::new(target.clone())),
+                        function: "hasValue".to_string(),
+                        args: Vec::new(),
+                    },
+                );
+                let value = self.new_expr(
+                    offset,
+                    ExprKind::Call {
+                        target: Some(Box::new(target)),
+                        function: "value".to_string(),
+                        args: Vec::new(),
+                    },
+                );
+                let range = self.new_expr(
+                    offset,
+                    ExprKind::List {
+                        elements: Vec::new(),
+                        optional_indices: Vec::new(),
+                    },
+                );
+                let condition = self.constant(offset, Constant::Bool(false));
+                let step = self.ident(offset, &var);
+                let bound = Comprehension {
+                    iter_var: "#unused".to_string(),
+                    iter_range: range,
+                    accu_var: var,
+                    accu_init: value,
+                    loop_condition: condition,
+                    loop_step: step,
+                    result: body,
+                };
+                let bound = self.new_expr(offset, ExprKind::Comprehension(Box::new(bound)));
+                let some = if function == "optMap" {
+                    self.call(offset, "optional.of", vec![bound])
+                } else {
+                    bound
+                };
+                let none = self.call(offset, "optional.none", Vec::new());
+                return Ok(self.call(offset, operators::CONDITIONAL, vec![has_value, some, none]));
+            }
+        };
+        Ok(self.new_expr(offset, ExprKind::Comprehension(Box::new(comprehension))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_kind(source: &str) -> ExprKind {
+        parse(source).unwrap().expr.kind
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let ExprKind::Call { function, args, .. } = parse_kind("a || b && c == 1 + 2 * 3") else {
+            panic!("expected call");
+        };
+        assert_eq!(function, operators::LOGICAL_OR);
+        let ExprKind::Call { function, .. } = &args[1].kind else {
+            panic!("expected call");
+        };
+        assert_eq!(function, operators::LOGICAL_AND);
+
+        assert_eq!(parse_kind("-9223372036854775808"), ExprKind::Const(Constant::Int(i64::MIN)));
+        assert!(parse("9223372036854775808").is_err());
+    }
+
+    #[test]
+    fn test_parse_macros_and_literals() {
+        assert!(matches!(parse_kind("has(a.b)"), ExprKind::Select { test_only: true, .. }));
+        assert!(matches!(parse_kind("[1, 2].all(x, x > 0)"), ExprKind::Comprehension(_)));
+        assert!(matches!(parse_kind("cel.bind(x, 1, x + 1)"), ExprKind::Comprehension(_)));
+        assert!(matches!(parse_kind("Object.spec{replicas: 1}"), ExprKind::Struct { type_name, .. } if type_name == "Object.spec"));
+        assert!(matches!(parse_kind("{'a': 1, ?'b': x}"), ExprKind::Map { entries } if entries[1].optional));
+        assert!(matches!(parse_kind("a.?b"), ExprKind::Call { function, .. } if function == operators::OPT_SELECT));
+
+        let err = parse("has(a)").unwrap_err();
+        assert_eq!(err.issues[0].message, "invalid argument to has() macro");
+        let err = parse("a.all(x.y, true)").unwrap_err();
+        assert_eq!(err.issues[0].message, "argument must be a simple name");
+        let err = parse("a +").unwrap_err();
+        assert_eq!((err.issues[0].line, err.issues[0].column), (1, 4));
+        let err = parse("namespace == 'a'").unwrap_err();
+        assert_eq!(err.issues[0].message, "reserved identifier: namespace");
+        assert!(parse("a.namespace('ns').check('get')").is_ok());
+    }
+}
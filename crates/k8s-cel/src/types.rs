@@ -0,0 +1,222 @@
+//! Types used by the checker and for declaring variables
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+/// Type is the static type of an expression or declaration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    /// A value whose type is only known at runtime.
+    Dyn,
+    Null,
+    Bool,
+    Int,
+    Uint,
+    Double,
+    String,
+    Bytes,
+    Timestamp,
+    Duration,
+    List(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Optional(Box<Type>),
+    /// The type of a type value, e.g. `int` used as an identifier.
+    Type(Box<Type>),
+    /// A named object type with declared fields.
+    Struct(Arc<StructType>),
+    /// An extension type such as `kubernetes.Quantity` or `net.IP`.
+    Opaque(&'static str),
+    /// A type parameter of a function overload.
+    Param(&'static str),
+    /// The type of an expression that failed to check.
+    Error,
+}
+
+/// StructType describes an object type and its fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructType {
+    pub name: String,
+    pub fields: BTreeMap<String, Type>,
+}
+
+impl StructType {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_field(mut self, name: impl Into<String>, ty: Type) -> Self {
+        self.fields.insert(name.into(), ty);
+        self
+    }
+}
+
+pub const QUANTITY_TYPE: &str = "kubernetes.Quantity";
+pub const IP_TYPE: &str = "net.IP";
+pub const CIDR_TYPE: &str = "net.CIDR";
+pub const URL_TYPE: &str = "kubernetes.URL";
+pub const AUTHORIZER_TYPE: &str = "kubernetes.authorization.Authorizer";
+pub const PATH_CHECK_TYPE: &str = "kubernetes.authorization.PathCheck";
+pub const GROUP_CHECK_TYPE: &str = "kubernetes.authorization.GroupCheck";
+pub const RESOURCE_CHECK_TYPE: &str = "kubernetes.authorization.ResourceCheck";
+pub const DECISION_TYPE: &str = "kubernetes.authorization.Decision";
+
+impl Type {
+    pub fn list(elem: Type) -> Type {
+        Type::List(Box::new(elem))
+    }
+
+    pub fn map(key: Type, value: Type) -> Type {
+        Type::Map(Box::new(key), Box::new(value))
+    }
+
+    pub fn optional(inner: Type) -> Type {
+        Type::Optional(Box::new(inner))
+    }
+
+    pub fn object(struct_type: StructType) -> Type {
+        Type::Struct(Arc::new(struct_type))
+    }
+
+    pub fn is_dyn_or_error(&self) -> bool {
+        matches!(self, Type::Dyn | Type::Error)
+    }
+
+    /// Returns true if a value of type `other` can be used where `self` is
+    /// expected. `dyn` is assignable in both directions.
+    pub fn is_assignable_from(&self, other: &Type) -> bool {
+        match (self, other) {
+            (a, b) if a.is_dyn_or_error() || b.is_dyn_or_error() => true,
+            (Type::List(a), Type::List(b)) => a.is_assignable_from(b),
+            (Type::Map(ak, av), Type::Map(bk, bv)) => ak.is_assignable_from(bk) && av.is_assignable_from(bv),
+            (Type::Optional(a), Type::Optional(b)) => a.is_assignable_from(b),
+            (Type::Type(_), Type::Type(_)) => true,
+            (Type::Struct(a), Type::Struct(b)) => a.name == b.name,
+            (Type::Struct(_), Type::Null) => true,
+            (a, b) => a == b,
+        }
+    }
+
+    /// Returns the common type of two branches, falling back to `dyn`.
+    pub(crate) fn join(&self, other: &Type) -> Type {
+        match (self, other) {
+            (Type::Error, t) | (t, Type::Error) => t.clone(),
+            (Type::Null, t) | (t, Type::Null) if matches!(t, Type::Struct(_) | Type::Null) => t.clone(),
+            (Type::List(a), Type::List(b)) => Type::list(a.join(b)),
+            (Type::Map(ak, av), Type::Map(bk, bv)) => Type::map(ak.join(bk), av.join(bv)),
+            (Type::Optional(a), Type::Optional(b)) => Type::optional(a.join(b)),
+            (a, b) if a == b => a.clone(),
+            _ => Type::Dyn,
+        }
+    }
+
+    /// Binds type parameters of `self` (a parameter type) against `arg`.
+    pub(crate) fn unify(&self, arg: &Type, bindings: &mut HashMap<&'static str, Type>) -> bool {
+        match (self, arg) {
+            (Type::Param(name), _) => match bindings.get(name).cloned() {
+                Some(bound) => {
+                    if bound.is_dyn_or_error() {
+                        bindings.insert(name, arg.clone());
+                        true
+                    } else if bound.is_assignable_from(arg) {
+                        true
+                    } else if arg.is_assignable_from(&bound) && matches!(bound, Type::Null) {
+                        bindings.insert(name, arg.clone());
+                        true
+                    } else {
+                        false
+                    }
+                }
+                None => {
+                    bindings.insert(name, arg.clone());
+                    true
+                }
+            },
+            (_, a) if a.is_dyn_or_error() => {
+                self.bind_dyn(bindings);
+                true
+            }
+            (Type::Dyn, _) => true,
+            (Type::List(p), Type::List(a)) => p.unify(a, bindings),
+            (Type::Map(pk, pv), Type::Map(ak, av)) => pk.unify(ak, bindings) && pv.unify(av, bindings),
+            (Type::Optional(p), Type::Optional(a)) => p.unify(a, bindings),
+            (Type::Type(_), Type::Type(_)) => true,
+            (p, a) => p.is_assignable_from(a),
+        }
+    }
+
+    fn bind_dyn(&self, bindings: &mut HashMap<&'static str, Type>) {
+        match self {
+            Type::Param(name) => {
+                bindings.entry(name).or_insert(Type::Dyn);
+            }
+            Type::List(elem) | Type::Optional(elem) | Type::Type(elem) => elem.bind_dyn(bindings),
+            Type::Map(key, value) => {
+                key.bind_dyn(bindings);
+                value.bind_dyn(bindings);
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces type parameters with their bindings.
+    pub(crate) fn substitute(&self, bindings: &HashMap<&'static str, Type>) -> Type {
+        match self {
+            Type::Param(name) => bindings.get(name).cloned().unwrap_or(Type::Dyn),
+            Type::List(elem) => Type::list(elem.substitute(bindings)),
+            Type::Map(key, value) => Type::map(key.substitute(bindings), value.substitute(bindings)),
+            Type::Optional(inner) => Type::optional(inner.substitute(bindings)),
+            Type::Type(inner) => Type::Type(Box::new(inner.substitute(bindings))),
+            other => other.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Dyn => write!(f, "dyn"),
+            Type::Null => write!(f, "null_type"),
+            Type::Bool => write!(f, "bool"),
+            Type::Int => write!(f, "int"),
+            Type::Uint => write!(f, "uint"),
+            Type::Double => write!(f, "double"),
+            Type::String => write!(f, "string"),
+            Type::Bytes => write!(f, "bytes"),
+            Type::Timestamp => write!(f, "google.protobuf.Timestamp"),
+            Type::Duration => write!(f, "google.protobuf.Duration"),
+            Type::List(elem) => write!(f, "list({})", elem),
+            Type::Map(key, value) => write!(f, "map({}, {})", key, value),
+            Type::Optional(inner) => write!(f, "optional_type({})", inner),
+            Type::Type(inner) => write!(f, "type({})", inner),
+            Type::Struct(struct_type) => write!(f, "{}", struct_type.name),
+            Type::Opaque(name) => write!(f, "{}", name),
+            Type::Param(name) => write!(f, "{}", name),
+            Type::Error => write!(f, "*error*"),
+        }
+    }
+}
+
+/// Returns the type denoted by a type identifier such as `int` or `list`.
+pub(crate) fn type_identifier(name: &str) -> Option<Type> {
+    let ty = match name {
+        "int" => Type::Int,
+        "uint" => Type::Uint,
+        "double" => Type::Double,
+        "bool" => Type::Bool,
+        "string" => Type::String,
+        "bytes" => Type::Bytes,
+        "list" => Type::list(Type::Dyn),
+        "map" => Type::map(Type::Dyn, Type::Dyn),
+        "null_type" => Type::Null,
+        "type" => Type::Type(Box::new(Type::Dyn)),
+        "optional_type" => Type::optional(Type::Dyn),
+        "google.protobuf.Timestamp" => Type::Timestamp,
+        "google.protobuf.Duration" => Type::Duration,
+        _ => return None,
+    };
+    Some(ty)
+}
@@ -0,0 +1,469 @@
+//! Runtime values
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use base64::Engine;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use k8s_api_core::resource::Quantity;
+
+use crate::error::EvalError;
+use crate::library::authz::AuthzValue;
+use crate::library::urls::Url;
+use crate::types::{self, Type};
+
+/// Key is a map key: CEL only allows bool, int, uint and string keys.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Key {
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    String(Arc<str>),
+}
+
+impl Key {
+    pub fn to_value(&self) -> Value {
+        match self {
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Int(i) => Value::Int(*i),
+            Key::Uint(u) => Value::Uint(*u),
+            Key::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+impl From<&str> for Key {
+    fn from(value: &str) -> Self {
+        Key::String(Arc::from(value))
+    }
+}
+
+/// Value is the result of evaluating an expression.
+#[derive(Clone, Debug)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Double(f64),
+    String(Arc<str>),
+    Bytes(Arc<[u8]>),
+    List(Arc<Vec<Value>>),
+    Map(Arc<BTreeMap<Key, Value>>),
+    Type(Type),
+    Timestamp(DateTime<Utc>),
+    Duration(Duration),
+    Optional(Option<Box<Value>>),
+    Quantity(Quantity),
+    Ip(IpAddr),
+    /// A CIDR as written, i.e. the address is not masked.
+    Cidr(IpAddr, u8),
+    Url(Arc<Url>),
+    Authz(Arc<AuthzValue>),
+}
+
+impl Value {
+    pub fn string(value: impl AsRef<str>) -> Value {
+        Value::String(Arc::from(value.as_ref()))
+    }
+
+    pub fn list(values: Vec<Value>) -> Value {
+        Value::List(Arc::new(values))
+    }
+
+    pub fn map(entries: BTreeMap<Key, Value>) -> Value {
+        Value::Map(Arc::new(entries))
+    }
+
+    pub fn optional(value: Option<Value>) -> Value {
+        Value::Optional(value.map(Box::new))
+    }
+
+    /// Returns the runtime type of the value.
+    pub fn type_of(&self) -> Type {
+        match self {
+            Value::Null => Type::Null,
+            Value::Bool(_) => Type::Bool,
+            Value::Int(_) => Type::Int,
+            Value::Uint(_) => Type::Uint,
+            Value::Double(_) => Type::Double,
+            Value::String(_) => Type::String,
+            Value::Bytes(_) => Type::Bytes,
+            Value::List(_) => Type::list(Type::Dyn),
+            Value::Map(_) => Type::map(Type::Dyn, Type::Dyn),
+            Value::Type(_) => Type::Type(Box::new(Type::Dyn)),
+            Value::Timestamp(_) => Type::Timestamp,
+            Value::Duration(_) => Type::Duration,
+            Value::Optional(_) => Type::optional(Type::Dyn),
+            Value::Quantity(_) => Type::Opaque(types::QUANTITY_TYPE),
+            Value::Ip(_) => Type::Opaque(types::IP_TYPE),
+            Value::Cidr(..) => Type::Opaque(types::CIDR_TYPE),
+            Value::Url(_) => Type::Opaque(types::URL_TYPE),
+            Value::Authz(authz) => Type::Opaque(authz.type_name()),
+        }
+    }
+
+    /// Returns the value as a map key, if it can be one.
+    pub fn to_key(&self) -> Option<Key> {
+        match self {
+            Value::Bool(b) => Some(Key::Bool(*b)),
+            Value::Int(i) => Some(Key::Int(*i)),
+            Value::Uint(u) => Some(Key::Uint(*u)),
+            Value::String(s) => Some(Key::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` in a map, treating numerically equal int, uint and
+    /// double keys as the same key.
+    pub(crate) fn map_get<'a>(map: &'a BTreeMap<Key, Value>, key: &Value) -> Option<&'a Value> {
+        match key {
+            Value::Int(i) => map
+                .get(&Key::Int(*i))
+                .or_else(|| u64::try_from(*i).ok().and_then(|u| map.get(&Key::Uint(u)))),
+            Value::Uint(u) => map
+                .get(&Key::Uint(*u))
+                .or_else(|| i64::try_from(*u).ok().and_then(|i| map.get(&Key::Int(i)))),
+            Value::Double(d) if d.fract() == 0.0 => {
+                if *d >= 0.0 && *d < u64::MAX as f64 {
+                    map.get(&Key::Uint(*d as u64)).or_else(|| map.get(&Key::Int(*d as i64)))
+                } else if *d >= i64::MIN as f64 && *d < 0.0 {
+                    map.get(&Key::Int(*d as i64))
+                } else {
+                    None
+                }
+            }
+            other => other.to_key().and_then(|key| map.get(&key)),
+        }
+    }
+
+    /// Compares two values the way CEL's ordering operators do. Numbers of
+    /// different types compare by value.
+    pub(crate) fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Bytes(a), Value::Bytes(b)) => Some(a.cmp(b)),
+            (Value::Timestamp(a), Value::Timestamp(b)) => Some(a.cmp(b)),
+            (Value::Duration(a), Value::Duration(b)) => Some(a.cmp(b)),
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::Uint(a), Value::Uint(b)) => Some(a.cmp(b)),
+            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b),
+            (Value::Int(a), Value::Uint(b)) => Some(compare_int_uint(*a, *b)),
+            (Value::Uint(a), Value::Int(b)) => Some(compare_int_uint(*b, *a).reverse()),
+            (Value::Int(a), Value::Double(b)) => compare_int_double(*a, *b),
+            (Value::Double(a), Value::Int(b)) => compare_int_double(*b, *a).map(Ordering::reverse),
+            (Value::Uint(a), Value::Double(b)) => compare_uint_double(*a, *b),
+            (Value::Double(a), Value::Uint(b)) => compare_uint_double(*b, *a).map(Ordering::reverse),
+            _ => None,
+        }
+    }
+
+    /// Converts a JSON value. Integral numbers become `int` (or `uint` when
+    /// they exceed the int range) and other numbers become `double`.
+    pub fn from_json(value: &serde_json::Value) -> Value {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(*b),
+            serde_json::Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    Value::Int(i)
+                } else if let Some(u) = n.as_u64() {
+                    Value::Uint(u)
+                } else {
+                    Value::Double(n.as_f64().unwrap_or_default())
+                }
+            }
+            serde_json::Value::String(s) => Value::string(s),
+            serde_json::Value::Array(items) => Value::list(items.iter().map(Value::from_json).collect()),
+            serde_json::Value::Object(map) => Value::map(
+                map.iter()
+                    .map(|(key, value)| (Key::from(key.as_str()), Value::from_json(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Converts the value to JSON. Timestamps, durations and extension
+    /// values are written in their string form and bytes are base64-encoded.
+    pub fn to_json(&self) -> Result<serde_json::Value, EvalError> {
+        use serde_json::Value as Json;
+        Ok(match self {
+            Value::Null => Json::Null,
+            Value::Bool(b) => Json::Bool(*b),
+            Value::Int(i) => Json::from(*i),
+            Value::Uint(u) => Json::from(*u),
+            Value::Double(d) => serde_json::Number::from_f64(*d)
+                .map(Json::Number)
+                .ok_or_else(|| EvalError::new(format!("cannot convert {} to JSON", d)))?,
+            Value::String(s) => Json::String(s.to_string()),
+            Value::Bytes(b) => Json::String(base64::engine::general_purpose::STANDARD.encode(b)),
+            Value::List(items) => Json::Array(items.iter().map(Value::to_json).collect::<Result<_, _>>()?),
+            Value::Map(map) => {
+                let mut object = serde_json::Map::new();
+                for (key, value) in map.iter() {
+                    let key = match key {
+                        Key::String(s) => s.to_string(),
+                        Key::Bool(b) => b.to_string(),
+                        Key::Int(i) => i.to_string(),
+                        Key::Uint(u) => u.to_string(),
+                    };
+                    object.insert(key, value.to_json()?);
+                }
+                Json::Object(object)
+            }
+            Value::Timestamp(ts) => Json::String(format_timestamp(ts)),
+            Value::Duration(d) => Json::String(format_duration(d)),
+            Value::Quantity(q) => Json::String(q.to_string()),
+            Value::Ip(ip) => Json::String(ip.to_string()),
+            Value::Cidr(ip, prefix) => Json::String(format!("{}/{}", ip, prefix)),
+            Value::Url(url) => Json::String(url.to_string()),
+            Value::Optional(Some(inner)) => inner.to_json()?,
+            Value::Optional(None) => Json::Null,
+            Value::Type(_) | Value::Authz(_) => {
+                return Err(EvalError::new(format!("cannot convert {} to JSON", self.type_of())));
+            }
+        })
+    }
+}
+
+fn compare_int_uint(a: i64, b: u64) -> Ordering {
+    if a < 0 {
+        Ordering::Less
+    } else {
+        (a as u64).cmp(&b)
+    }
+}
+
+fn compare_int_double(a: i64, b: f64) -> Option<Ordering> {
+    if b.is_nan() {
+        return None;
+    }
+    if b >= 9.223_372_036_854_776e18 {
+        return Some(Ordering::Less);
+    }
+    if b < -9.223_372_036_854_776e18 {
+        return Some(Ordering::Greater);
+    }
+    (a as f64).partial_cmp(&b)
+}
+
+fn compare_uint_double(a: u64, b: f64) -> Option<Ordering> {
+    if b.is_nan() {
+        return None;
+    }
+    if b < 0.0 {
+        return Some(Ordering::Greater);
+    }
+    if b >= 1.844_674_407_370_955_2e19 {
+        return Some(Ordering::Less);
+    }
+    (a as f64).partial_cmp(&b)
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::List(a), Value::List(b)) => a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x == y),
+            (Value::Map(a), Value::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(key, value)| Value::map_get(b, &key.to_value()).is_some_and(|other| value == other))
+            }
+            (Value::Type(a), Value::Type(b)) => a.to_string() == b.to_string(),
+            (Value::Optional(a), Value::Optional(b)) => a == b,
+            (Value::Quantity(a), Value::Quantity(b)) => match (a.to_nanos(), b.to_nanos()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => a == b,
+            },
+            (Value::Ip(a), Value::Ip(b)) => a == b,
+            (Value::Cidr(a, ap), Value::Cidr(b, bp)) => a == b && ap == bp,
+            (Value::Url(a), Value::Url(b)) => a == b,
+            (Value::Authz(_), Value::Authz(_)) => false,
+            (a, b) => a.compare(b) == Some(Ordering::Equal),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Uint(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Double(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::string(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::string(value)
+    }
+}
+
+impl From<&serde_json::Value> for Value {
+    fn from(value: &serde_json::Value) -> Self {
+        Value::from_json(value)
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        Value::from_json(&value)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Value::list(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Formats a value for error messages: strings are written without quotes
+/// and other values in their CEL literal form.
+pub(crate) fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Uint(u) => format!("{}u", u),
+        Value::Double(d) => crate::library::format_double(*d),
+        Value::String(s) => s.to_string(),
+        Value::Bytes(b) => format!("b{:?}", String::from_utf8_lossy(b)),
+        Value::List(items) => format!("[{}]", items.iter().map(display_value).collect::<Vec<_>>().join(", ")),
+        Value::Map(map) => format!(
+            "{{{}}}",
+            map.iter()
+                .map(|(key, value)| format!("{}: {}", display_value(&key.to_value()), display_value(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Type(ty) => ty.to_string(),
+        Value::Timestamp(ts) => format_timestamp(ts),
+        Value::Duration(d) => format_duration(d),
+        Value::Optional(Some(inner)) => format!("optional.of({})", display_value(inner)),
+        Value::Optional(None) => "optional.none()".to_string(),
+        Value::Quantity(q) => q.to_string(),
+        Value::Ip(ip) => ip.to_string(),
+        Value::Cidr(ip, prefix) => format!("{}/{}", ip, prefix),
+        Value::Url(url) => url.to_string(),
+        Value::Authz(authz) => authz.type_name().to_string(),
+    }
+}
+
+/// Formats a timestamp as RFC 3339 in UTC, e.g. `2024-01-02T03:04:05Z`.
+pub fn format_timestamp(ts: &DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Formats a duration the way protobuf JSON does, e.g. `3600s` or `1.5s`.
+pub fn format_duration(d: &Duration) -> String {
+    let nanos = d.num_nanoseconds().map(i128::from).unwrap_or_else(|| i128::from(d.num_seconds()) * 1_000_000_000);
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.unsigned_abs();
+    let seconds = nanos / 1_000_000_000;
+    let fraction = nanos % 1_000_000_000;
+    if fraction == 0 {
+        format!("{}{}s", sign, seconds)
+    } else {
+        let fraction = format!("{:09}", fraction);
+        format!("{}{}.{}s", sign, seconds, fraction.trim_end_matches('0'))
+    }
+}
+
+/// Parses a Go duration string such as `1h30m`, `1.5s` or `-300ms`.
+pub fn parse_duration(input: &str) -> Result<Duration, EvalError> {
+    let invalid = || EvalError::new(format!("invalid duration {:?}", input));
+    let (negative, mut rest) = match input.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, input.strip_prefix('+').unwrap_or(input)),
+    };
+    if rest == "0" {
+        return Ok(Duration::zero());
+    }
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut total: f64 = 0.0;
+    let mut total_nanos: i128 = 0;
+    while !rest.is_empty() {
+        let number_len = rest.find(|ch: char| !(ch.is_ascii_digit() || ch == '.')).ok_or_else(invalid)?;
+        if number_len == 0 {
+            return Err(invalid());
+        }
+        let number: f64 = rest[..number_len].parse().map_err(|_| invalid())?;
+        rest = &rest[number_len..];
+        let unit_len = rest.find(|ch: char| ch.is_ascii_digit() || ch == '.').unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "ns" => 1.0,
+            "us" | "µs" | "μs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return Err(invalid()),
+        };
+        rest = &rest[unit_len..];
+        total += number * unit;
+        total_nanos += (number * unit).round() as i128;
+    }
+    if total > i64::MAX as f64 {
+        return Err(invalid());
+    }
+    let nanos = i64::try_from(total_nanos).map_err(|_| invalid())?;
+    Ok(Duration::nanoseconds(if negative { -nanos } else { nanos }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_value_equality() {
+        assert_eq!(Value::Int(1), Value::Double(1.0));
+        assert_eq!(Value::Uint(1), Value::Int(1));
+        assert_ne!(Value::Int(1), Value::string("1"));
+        assert_ne!(Value::Double(f64::NAN), Value::Double(f64::NAN));
+        assert_eq!(Value::from(json!({"a": [1, 2]})), Value::from(json!({"a": [1.0, 2u64]})));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let doc = json!({"a": [1, "b", null, true, 1.5], "c": {"d": {}}});
+        assert_eq!(Value::from_json(&doc).to_json().unwrap(), doc);
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("-1.5s").unwrap(), Duration::milliseconds(-1500));
+        assert_eq!(parse_duration("0").unwrap(), Duration::zero());
+        assert!(parse_duration("1x").is_err());
+        assert_eq!(format_duration(&Duration::minutes(90)), "5400s");
+        assert_eq!(format_duration(&Duration::milliseconds(1500)), "1.5s");
+    }
+}
@@ -0,0 +1,161 @@
+//! Scheduling framework
+//!
+//! The cluster state a pod is scheduled against, a `Snapshot` of nodes
+//! and the pods assigned to them, and the `FilterPlugin`s deciding whether
+//! a pod fits a node.
+
+use std::collections::BTreeMap;
+
+use k8s_api::core::v1::{
+    pod_requests, Node, Pod, PodResourcesOptions, ResourceList, POD_PHASE_FAILED, POD_PHASE_SUCCEEDED, RESOURCE_CPU,
+    RESOURCE_EPHEMERAL_STORAGE, RESOURCE_MEMORY, RESOURCE_PODS,
+};
+
+/// Amounts of resources in the units the scheduler compares them in:
+/// millicores of CPU, pods and other resources as integers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Resource {
+    pub milli_cpu: i64,
+    pub memory: i64,
+    pub ephemeral_storage: i64,
+    pub allowed_pod_number: i64,
+    /// Extended resources, hugepages and attachable volumes.
+    pub scalar_resources: BTreeMap<String, i64>,
+}
+
+impl Resource {
+    /// Converts a resource list, rounding quantities up. Quantities that do
+    /// not parse count as zero, as validation rejects them.
+    pub fn from_list(list: &ResourceList) -> Self {
+        let mut resource = Self::default();
+        for (name, quantity) in list {
+            let value = || quantity.value().unwrap_or_default();
+            match name.as_str() {
+                RESOURCE_CPU => resource.milli_cpu += quantity.milli_value().unwrap_or_default(),
+                RESOURCE_MEMORY => resource.memory += value(),
+                RESOURCE_EPHEMERAL_STORAGE => resource.ephemeral_storage += value(),
+                RESOURCE_PODS => resource.allowed_pod_number += value(),
+                _ => *resource.scalar_resources.entry(name.clone()).or_default() += value(),
+            }
+        }
+        resource
+    }
+
+    pub fn add(&mut self, other: &Resource) {
+        self.milli_cpu += other.milli_cpu;
+        self.memory += other.memory;
+        self.ephemeral_storage += other.ephemeral_storage;
+        self.allowed_pod_number += other.allowed_pod_number;
+        for (name, value) in &other.scalar_resources {
+            *self.scalar_resources.entry(name.clone()).or_default() += value;
+        }
+    }
+}
+
+/// Returns the resources the scheduler reserves for a pod: its effective
+/// requests, accounting for in-place resizes and pod-level resources.
+pub fn pod_request(pod: &Pod) -> Resource {
+    let opts = PodResourcesOptions {
+        use_status_resources: true,
+        ..Default::default()
+    };
+    Resource::from_list(&pod_requests(pod, &opts))
+}
+
+/// A node and the pods assigned to it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NodeInfo {
+    pub node: Node,
+    pub pods: Vec<Pod>,
+    /// The allocatable resources of the node.
+    pub allocatable: Resource,
+    /// The resources reserved for the pods of the node.
+    pub requested: Resource,
+}
+
+impl NodeInfo {
+    pub fn new(node: Node, pods: Vec<Pod>) -> Self {
+        let allocatable = node
+            .status
+            .as_ref()
+            .map(|status| Resource::from_list(&status.allocatable))
+            .unwrap_or_default();
+        let mut requested = Resource::default();
+        for pod in &pods {
+            requested.add(&pod_request(pod));
+        }
+        Self {
+            node,
+            pods,
+            allocatable,
+            requested,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.node.metadata.name
+    }
+}
+
+/// The nodes of a cluster with their pods, and the labels of its
+/// namespaces for namespace selectors.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub nodes: Vec<NodeInfo>,
+    pub namespace_labels: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Snapshot {
+    /// Returns the snapshot of `nodes` running `pods`. Pods not assigned to
+    /// any of the nodes and pods that ended are left out, as the scheduler
+    /// does not account for them.
+    pub fn new(nodes: Vec<Node>, pods: Vec<Pod>) -> Self {
+        let mut assigned: BTreeMap<String, Vec<Pod>> = BTreeMap::new();
+        for pod in pods {
+            let phase = pod.status.as_ref().map(|status| status.phase.as_str()).unwrap_or_default();
+            let node_name = pod.spec.as_ref().map(|spec| spec.node_name.clone()).unwrap_or_default();
+            if node_name.is_empty() || phase == POD_PHASE_SUCCEEDED || phase == POD_PHASE_FAILED {
+                continue;
+            }
+            assigned.entry(node_name).or_default().push(pod);
+        }
+        let nodes = nodes
+            .into_iter()
+            .map(|node| {
+                let pods = assigned.remove(&node.metadata.name).unwrap_or_default();
+                NodeInfo::new(node, pods)
+            })
+            .collect();
+        Self {
+            nodes,
+            namespace_labels: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the labels of a namespace.
+    pub fn with_namespace(mut self, namespace: impl Into<String>, labels: BTreeMap<String, String>) -> Self {
+        self.namespace_labels.insert(namespace.into(), labels);
+        self
+    }
+
+    /// Returns the labels of a namespace, empty for unknown namespaces.
+    pub fn namespace_labels(&self, namespace: &str) -> BTreeMap<String, String> {
+        self.namespace_labels.get(namespace).cloned().unwrap_or_default()
+    }
+}
+
+/// A plugin rejecting the nodes a pod does not fit.
+pub trait FilterPlugin {
+    fn name(&self) -> &'static str;
+
+    /// Prepares the filtering of the nodes of `snapshot` for `pod`. An
+    /// error fails the scheduling of the pod.
+    fn pre_filter(&mut self, snapshot: &Snapshot, pod: &Pod) -> Result<(), String> {
+        let _ = (snapshot, pod);
+        Ok(())
+    }
+
+    /// Checks whether `pod` fits `node`, returning the reasons it does not
+    /// in the scheduler's wording.
+    fn filter(&self, pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>>;
+}
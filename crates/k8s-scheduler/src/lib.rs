@@ -0,0 +1,14 @@
+//! Kubernetes scheduler libraries
+//!
+//! This crate provides the node filtering of the Kubernetes scheduler, so
+//! that whether and where a pod fits can be checked offline against a
+//! snapshot of nodes and the pods running on them.
+
+pub mod framework;
+pub mod plugins;
+pub mod simulator;
+#[cfg(test)]
+mod testing;
+
+pub use framework::{FilterPlugin, NodeInfo, Resource, Snapshot};
+pub use simulator::{simulate, simulate_with, Rejection, Simulation};
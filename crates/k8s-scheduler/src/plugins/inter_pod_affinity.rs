@@ -0,0 +1,292 @@
+//! InterPodAffinity
+//!
+//! Pods only fit nodes satisfying their required pod affinity and
+//! anti-affinity, and the required anti-affinity of the pods already
+//! running. Terms are evaluated per topology domain, the nodes sharing the
+//! value of the term's `topologyKey` label.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use k8s_api::core::v1::{Pod, PodAffinityTerm};
+use k8s_apimachinery::apis::meta::v1::{LabelSelector, LabelSelectorRequirement};
+
+use crate::framework::{FilterPlugin, NodeInfo, Snapshot};
+
+pub const NAME: &str = "InterPodAffinity";
+
+pub const ERR_REASON_EXISTING_ANTI_AFFINITY_RULES_NOT_MATCH: &str =
+    "node(s) didn't satisfy existing pods anti-affinity rules";
+pub const ERR_REASON_AFFINITY_RULES_NOT_MATCH: &str = "node(s) didn't match pod affinity rules";
+pub const ERR_REASON_ANTI_AFFINITY_RULES_NOT_MATCH: &str = "node(s) didn't match pod anti-affinity rules";
+
+/// A pod affinity term, with the namespaces and label selector it selects
+/// pods by resolved for the pod it belongs to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AffinityTerm {
+    pub namespaces: BTreeSet<String>,
+    pub namespace_selector: Option<LabelSelector>,
+    pub selector: Option<LabelSelector>,
+    pub topology_key: String,
+}
+
+impl AffinityTerm {
+    /// Resolves a term of `pod`: a term without namespaces or a namespace
+    /// selector selects pods in the namespace of `pod`, and the values of
+    /// its `matchLabelKeys` and `mismatchLabelKeys` labels are added to the
+    /// label selector.
+    pub fn new(pod: &Pod, term: &PodAffinityTerm) -> Self {
+        let mut namespaces: BTreeSet<String> = term.namespaces.iter().cloned().collect();
+        if namespaces.is_empty() && term.namespace_selector.is_none() {
+            namespaces.insert(pod.metadata.namespace.clone());
+        }
+        let selector = term.label_selector.clone().map(|mut selector| {
+            let keys = [(&term.match_label_keys, "In"), (&term.mismatch_label_keys, "NotIn")];
+            for (keys, operator) in keys {
+                for key in keys {
+                    if let Some(value) = pod.metadata.labels.get(key) {
+                        selector.match_expressions.push(LabelSelectorRequirement {
+                            key: key.clone(),
+                            operator: operator.to_string(),
+                            values: vec![value.clone()],
+                        });
+                    }
+                }
+            }
+            selector
+        });
+        Self {
+            namespaces,
+            namespace_selector: term.namespace_selector.clone(),
+            selector,
+            topology_key: term.topology_key.clone(),
+        }
+    }
+
+    /// Returns whether the term selects `pod`, whose namespace has
+    /// `namespace_labels`. A term without a label selector selects no pod.
+    pub fn matches(&self, pod: &Pod, namespace_labels: &BTreeMap<String, String>) -> Result<bool, String> {
+        let in_namespace = self.namespaces.contains(&pod.metadata.namespace)
+            || match &self.namespace_selector {
+                Some(selector) => selector.matches(namespace_labels)?,
+                None => false,
+            };
+        match &self.selector {
+            Some(selector) if in_namespace => selector.matches(&pod.metadata.labels),
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Returns the resolved required affinity and anti-affinity terms of a pod.
+fn required_terms(pod: &Pod) -> (Vec<AffinityTerm>, Vec<AffinityTerm>) {
+    let affinity = pod.spec.as_ref().and_then(|spec| spec.affinity.as_ref());
+    let affinity_terms = affinity
+        .and_then(|affinity| affinity.pod_affinity.as_ref())
+        .map(|pod_affinity| &pod_affinity.required_during_scheduling_ignored_during_execution[..])
+        .unwrap_or_default();
+    let anti_affinity_terms = affinity
+        .and_then(|affinity| affinity.pod_anti_affinity.as_ref())
+        .map(|anti_affinity| &anti_affinity.required_during_scheduling_ignored_during_execution[..])
+        .unwrap_or_default();
+    let resolve = |terms: &[PodAffinityTerm]| terms.iter().map(|term| AffinityTerm::new(pod, term)).collect();
+    (resolve(affinity_terms), resolve(anti_affinity_terms))
+}
+
+type TopologyPair = (String, String);
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct State {
+    affinity_terms: Vec<AffinityTerm>,
+    anti_affinity_terms: Vec<AffinityTerm>,
+    /// Whether the pod matches all of its own affinity terms.
+    matches_own_affinity: bool,
+    /// Pods matching all affinity terms of the pod, per domain.
+    affinity_counts: HashMap<TopologyPair, i64>,
+    /// Pods matching an anti-affinity term of the pod, per domain.
+    anti_affinity_counts: HashMap<TopologyPair, i64>,
+    /// Pods with anti-affinity terms matching the pod, per domain.
+    existing_anti_affinity_counts: HashMap<TopologyPair, i64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InterPodAffinity {
+    state: State,
+}
+
+impl FilterPlugin for InterPodAffinity {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn pre_filter(&mut self, snapshot: &Snapshot, pod: &Pod) -> Result<(), String> {
+        let (affinity_terms, anti_affinity_terms) = required_terms(pod);
+        let pod_namespace_labels = snapshot.namespace_labels(&pod.metadata.namespace);
+        let mut state = State {
+            matches_own_affinity: matches_all(&affinity_terms, pod, &pod_namespace_labels)?,
+            affinity_terms,
+            anti_affinity_terms,
+            ..Default::default()
+        };
+        for node in &snapshot.nodes {
+            let labels = &node.node.metadata.labels;
+            let count = |counts: &mut HashMap<TopologyPair, i64>, topology_key: &str| {
+                if let Some(value) = labels.get(topology_key) {
+                    *counts.entry((topology_key.to_string(), value.clone())).or_default() += 1;
+                }
+            };
+            for existing in &node.pods {
+                let namespace_labels = snapshot.namespace_labels(&existing.metadata.namespace);
+                if matches_all(&state.affinity_terms, existing, &namespace_labels)? {
+                    for term in &state.affinity_terms {
+                        count(&mut state.affinity_counts, &term.topology_key);
+                    }
+                }
+                for term in &state.anti_affinity_terms {
+                    if term.matches(existing, &namespace_labels)? {
+                        count(&mut state.anti_affinity_counts, &term.topology_key);
+                    }
+                }
+                // Invalid terms of running pods select nothing rather than
+                // failing the pod.
+                for term in required_terms(existing).1 {
+                    if term.matches(pod, &pod_namespace_labels).unwrap_or(false) {
+                        count(&mut state.existing_anti_affinity_counts, &term.topology_key);
+                    }
+                }
+            }
+        }
+        self.state = state;
+        Ok(())
+    }
+
+    fn filter(&self, _pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>> {
+        let state = &self.state;
+        let labels = &node.node.metadata.labels;
+        let pair = |topology_key: &str| labels.get(topology_key).map(|value| (topology_key.to_string(), value.clone()));
+
+        // All topology labels must exist on the node. A pod may be the first
+        // of a series with affinity to each other, so it fits if no pod
+        // matches its terms yet and it matches them itself.
+        let mut pods_exist = true;
+        for term in &state.affinity_terms {
+            match pair(&term.topology_key) {
+                Some(pair) => pods_exist &= state.affinity_counts.get(&pair).copied().unwrap_or_default() > 0,
+                None => return Err(vec![ERR_REASON_AFFINITY_RULES_NOT_MATCH.to_string()]),
+            }
+        }
+        let first_of_group = state.affinity_counts.is_empty() && state.matches_own_affinity;
+        if !pods_exist && !first_of_group {
+            return Err(vec![ERR_REASON_AFFINITY_RULES_NOT_MATCH.to_string()]);
+        }
+
+        let anti_affinity_violated = state.anti_affinity_terms.iter().any(|term| {
+            pair(&term.topology_key).is_some_and(|pair| state.anti_affinity_counts.get(&pair).copied().unwrap_or_default() > 0)
+        });
+        if anti_affinity_violated {
+            return Err(vec![ERR_REASON_ANTI_AFFINITY_RULES_NOT_MATCH.to_string()]);
+        }
+
+        let existing_anti_affinity_violated = labels.iter().any(|(key, value)| {
+            state
+                .existing_anti_affinity_counts
+                .get(&(key.clone(), value.clone()))
+                .is_some_and(|count| *count > 0)
+        });
+        if existing_anti_affinity_violated {
+            return Err(vec![ERR_REASON_EXISTING_ANTI_AFFINITY_RULES_NOT_MATCH.to_string()]);
+        }
+        Ok(())
+    }
+}
+
+fn matches_all(terms: &[AffinityTerm], pod: &Pod, namespace_labels: &BTreeMap<String, String>) -> Result<bool, String> {
+    if terms.is_empty() {
+        return Ok(false);
+    }
+    for term in terms {
+        if !term.matches(pod, namespace_labels)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{labeled_node, labeled_pod, on_node};
+    use k8s_api::core::v1::{Affinity, PodAffinity, PodAntiAffinity};
+
+    fn term(app: &str, topology_key: &str) -> PodAffinityTerm {
+        PodAffinityTerm {
+            label_selector: Some(LabelSelector {
+                match_labels: BTreeMap::from([("app".to_string(), app.to_string())]),
+                ..Default::default()
+            }),
+            topology_key: topology_key.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn with_affinity(mut pod: Pod, affinity: Vec<PodAffinityTerm>, anti_affinity: Vec<PodAffinityTerm>) -> Pod {
+        pod.spec.as_mut().unwrap().affinity = Some(Affinity {
+            pod_affinity: Some(PodAffinity {
+                required_during_scheduling_ignored_during_execution: affinity,
+                ..Default::default()
+            }),
+            pod_anti_affinity: Some(PodAntiAffinity {
+                required_during_scheduling_ignored_during_execution: anti_affinity,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        pod
+    }
+
+    fn filter(snapshot: &Snapshot, pod: &Pod) -> Vec<Result<(), Vec<String>>> {
+        let mut plugin = InterPodAffinity::default();
+        plugin.pre_filter(snapshot, pod).unwrap();
+        snapshot.nodes.iter().map(|node| plugin.filter(pod, node)).collect()
+    }
+
+    #[test]
+    fn test_inter_pod_affinity() {
+        let nodes = vec![
+            labeled_node("a1", &[("zone", "a")]),
+            labeled_node("a2", &[("zone", "a")]),
+            labeled_node("b1", &[("zone", "b")]),
+            labeled_node("none", &[]),
+        ];
+        let cache = on_node(labeled_pod("cache", &[("app", "cache")]), "a1");
+        let snapshot = Snapshot::new(nodes.clone(), vec![cache]);
+        let affinity = Err(vec![ERR_REASON_AFFINITY_RULES_NOT_MATCH.to_string()]);
+        let anti_affinity = Err(vec![ERR_REASON_ANTI_AFFINITY_RULES_NOT_MATCH.to_string()]);
+
+        let web = with_affinity(labeled_pod("web", &[("app", "web")]), vec![term("cache", "zone")], vec![]);
+        assert_eq!(filter(&snapshot, &web), [Ok(()), Ok(()), affinity.clone(), affinity.clone()]);
+
+        let spread = with_affinity(labeled_pod("web", &[("app", "web")]), vec![], vec![term("cache", "zone")]);
+        assert_eq!(filter(&snapshot, &spread), [anti_affinity.clone(), anti_affinity, Ok(()), Ok(())]);
+
+        // The first pod of a group with affinity to itself fits any node
+        // with the topology label.
+        let first = with_affinity(labeled_pod("db", &[("app", "db")]), vec![term("db", "zone")], vec![]);
+        assert_eq!(filter(&snapshot, &first), [Ok(()), Ok(()), Ok(()), affinity]);
+
+        // Pods in other namespaces are not selected by default.
+        let mut other = web.clone();
+        other.metadata.namespace = "other".to_string();
+        assert!(filter(&snapshot, &other)[0].is_err());
+
+        let lonely = on_node(
+            with_affinity(labeled_pod("lonely", &[("app", "lonely")]), vec![], vec![term("web", "zone")]),
+            "b1",
+        );
+        let snapshot = Snapshot::new(nodes, vec![lonely]);
+        let existing = Err(vec![ERR_REASON_EXISTING_ANTI_AFFINITY_RULES_NOT_MATCH.to_string()]);
+        assert_eq!(
+            filter(&snapshot, &labeled_pod("web", &[("app", "web")])),
+            [Ok(()), Ok(()), existing, Ok(())]
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! Filter plugins
+//!
+//! The filter plugins of the scheduler's default profile, in the order the
+//! scheduler runs them. A node is rejected by the first plugin it fails.
+
+pub mod inter_pod_affinity;
+pub mod node_affinity;
+pub mod node_name;
+pub mod node_ports;
+pub mod node_resources_fit;
+pub mod node_unschedulable;
+pub mod pod_topology_spread;
+pub mod taint_toleration;
+
+use crate::framework::FilterPlugin;
+
+/// Returns the filter plugins of the default profile.
+pub fn default_plugins() -> Vec<Box<dyn FilterPlugin>> {
+    vec![
+        Box::new(node_unschedulable::NodeUnschedulable),
+        Box::new(node_name::NodeName),
+        Box::new(taint_toleration::TaintToleration),
+        Box::new(node_affinity::NodeAffinity),
+        Box::new(node_ports::NodePorts),
+        Box::new(node_resources_fit::NodeResourcesFit),
+        Box::new(pod_topology_spread::PodTopologySpread::default()),
+        Box::new(inter_pod_affinity::InterPodAffinity::default()),
+    ]
+}
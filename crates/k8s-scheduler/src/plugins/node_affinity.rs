@@ -0,0 +1,31 @@
+//! NodeAffinity
+//!
+//! Pods only fit nodes with the labels of their `nodeSelector` that match
+//! their required node affinity.
+
+use k8s_api::core::v1::{required_node_affinity_matches, Pod};
+
+use crate::framework::{FilterPlugin, NodeInfo};
+
+pub const NAME: &str = "NodeAffinity";
+
+pub const ERR_REASON_POD: &str = "node(s) didn't match Pod's node affinity/selector";
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodeAffinity;
+
+impl FilterPlugin for NodeAffinity {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn filter(&self, pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>> {
+        // Invalid terms match no node rather than failing the pod, for
+        // backwards compatibility.
+        if required_node_affinity_matches(pod, &node.node).unwrap_or(false) {
+            Ok(())
+        } else {
+            Err(vec![ERR_REASON_POD.to_string()])
+        }
+    }
+}
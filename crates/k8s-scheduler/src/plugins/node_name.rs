@@ -0,0 +1,29 @@
+//! NodeName
+//!
+//! Pods with `spec.nodeName` only fit that node.
+
+use k8s_api::core::v1::Pod;
+
+use crate::framework::{FilterPlugin, NodeInfo};
+
+pub const NAME: &str = "NodeName";
+
+pub const ERR_REASON: &str = "node(s) didn't match the requested node name";
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodeName;
+
+impl FilterPlugin for NodeName {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn filter(&self, pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>> {
+        let node_name = pod.spec.as_ref().map(|spec| spec.node_name.as_str()).unwrap_or_default();
+        if node_name.is_empty() || node_name == node.name() {
+            Ok(())
+        } else {
+            Err(vec![ERR_REASON.to_string()])
+        }
+    }
+}
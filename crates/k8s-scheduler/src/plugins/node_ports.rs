@@ -0,0 +1,112 @@
+//! NodePorts
+//!
+//! Pods only fit nodes where the host ports they ask for are free. A port
+//! on `0.0.0.0` conflicts with the same port and protocol on any address.
+
+use k8s_api::core::v1::{ContainerPort, Pod, CONTAINER_RESTART_POLICY_ALWAYS, PROTOCOL_TCP};
+
+use crate::framework::{FilterPlugin, NodeInfo};
+
+pub const NAME: &str = "NodePorts";
+
+pub const ERR_REASON: &str = "node(s) didn't have free ports for the requested pod ports";
+
+const DEFAULT_BIND_ALL_HOST_IP: &str = "0.0.0.0";
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodePorts;
+
+/// Returns the host ports of the containers and sidecars of a pod, which
+/// run for as long as the pod does.
+pub fn host_ports(pod: &Pod) -> Vec<&ContainerPort> {
+    let Some(spec) = &pod.spec else {
+        return Vec::new();
+    };
+    let sidecars = spec
+        .init_containers
+        .iter()
+        .filter(|container| container.restart_policy.as_deref() == Some(CONTAINER_RESTART_POLICY_ALWAYS));
+    sidecars
+        .chain(&spec.containers)
+        .flat_map(|container| &container.ports)
+        .filter(|port| port.host_port.is_some_and(|host_port| host_port > 0))
+        .collect()
+}
+
+/// Returns the address, protocol and port a host port binds, with the
+/// defaults filled in.
+fn binding(port: &ContainerPort) -> (&str, &str, i32) {
+    let ip = if port.host_i_p.is_empty() { DEFAULT_BIND_ALL_HOST_IP } else { &port.host_i_p };
+    let protocol = if port.protocol.is_empty() { PROTOCOL_TCP } else { &port.protocol };
+    (ip, protocol, port.host_port.unwrap_or_default())
+}
+
+fn conflicts(a: &ContainerPort, b: &ContainerPort) -> bool {
+    let (a_ip, a_protocol, a_port) = binding(a);
+    let (b_ip, b_protocol, b_port) = binding(b);
+    a_protocol == b_protocol
+        && a_port == b_port
+        && (a_ip == b_ip || a_ip == DEFAULT_BIND_ALL_HOST_IP || b_ip == DEFAULT_BIND_ALL_HOST_IP)
+}
+
+impl FilterPlugin for NodePorts {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn filter(&self, pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>> {
+        let wanted = host_ports(pod);
+        if wanted.is_empty() {
+            return Ok(());
+        }
+        let used: Vec<&ContainerPort> = node.pods.iter().flat_map(host_ports).collect();
+        if wanted.iter().any(|port| used.iter().any(|used| conflicts(port, used))) {
+            Err(vec![ERR_REASON.to_string()])
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{node, pod};
+
+    fn port(ip: &str, protocol: &str, host_port: i32) -> ContainerPort {
+        ContainerPort {
+            host_i_p: ip.to_string(),
+            protocol: protocol.to_string(),
+            host_port: Some(host_port),
+            container_port: host_port,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_node_ports() {
+        let with_ports = |ports: Vec<ContainerPort>| {
+            let mut pod = pod("p");
+            pod.spec.as_mut().unwrap().containers[0].ports = ports;
+            pod
+        };
+        let existing = with_ports(vec![port("127.0.0.1", "", 8080), port("", "UDP", 53)]);
+        let node = NodeInfo::new(node("n"), vec![existing]);
+        let cases = [
+            (vec![port("127.0.0.2", "TCP", 8080)], true),
+            (vec![port("127.0.0.1", "UDP", 8080)], true),
+            (vec![port("", "", 8080)], false),
+            (vec![port("127.0.0.1", "TCP", 8080)], false),
+            (vec![port("10.0.0.1", "UDP", 53)], false),
+            (vec![port("10.0.0.1", "TCP", 53)], true),
+        ];
+        for (ports, fits) in cases {
+            let result = NodePorts.filter(&with_ports(ports.clone()), &node);
+            assert_eq!(result.is_ok(), fits, "{:?}", ports);
+        }
+        assert_eq!(
+            NodePorts.filter(&with_ports(vec![port("", "", 8080)]), &node),
+            Err(vec![ERR_REASON.to_string()])
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! NodeResourcesFit
+//!
+//! Pods only fit nodes with enough allocatable resources left for their
+//! requests, and room for another pod.
+
+use k8s_api::core::v1::Pod;
+
+use crate::framework::{pod_request, FilterPlugin, NodeInfo};
+
+pub const NAME: &str = "NodeResourcesFit";
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodeResourcesFit;
+
+/// A resource a node does not have enough of for a pod.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InsufficientResource {
+    pub resource_name: String,
+    /// Why the pod does not fit, e.g. `Insufficient cpu`.
+    pub reason: String,
+    pub requested: i64,
+    pub used: i64,
+    pub capacity: i64,
+}
+
+/// Returns the resources `node` does not have enough of for `pod`: pods
+/// first, then CPU, memory, ephemeral storage and other resources by name.
+pub fn fits_request(pod: &Pod, node: &NodeInfo) -> Vec<InsufficientResource> {
+    let mut insufficient = Vec::new();
+    let allowed_pod_number = node.allocatable.allowed_pod_number;
+    if node.pods.len() as i64 + 1 > allowed_pod_number {
+        insufficient.push(InsufficientResource {
+            resource_name: "pods".to_string(),
+            reason: "Too many pods".to_string(),
+            requested: 1,
+            used: node.pods.len() as i64,
+            capacity: allowed_pod_number,
+        });
+    }
+    let request = pod_request(pod);
+    let mut check = |name: &str, requested: i64, allocatable: i64, used: i64| {
+        if requested > 0 && requested > allocatable - used {
+            insufficient.push(InsufficientResource {
+                resource_name: name.to_string(),
+                reason: format!("Insufficient {}", name),
+                requested,
+                used,
+                capacity: allocatable,
+            });
+        }
+    };
+    check("cpu", request.milli_cpu, node.allocatable.milli_cpu, node.requested.milli_cpu);
+    check("memory", request.memory, node.allocatable.memory, node.requested.memory);
+    check(
+        "ephemeral-storage",
+        request.ephemeral_storage,
+        node.allocatable.ephemeral_storage,
+        node.requested.ephemeral_storage,
+    );
+    for (name, requested) in &request.scalar_resources {
+        let allocatable = node.allocatable.scalar_resources.get(name).copied().unwrap_or_default();
+        let used = node.requested.scalar_resources.get(name).copied().unwrap_or_default();
+        check(name, *requested, allocatable, used);
+    }
+    insufficient
+}
+
+impl FilterPlugin for NodeResourcesFit {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn filter(&self, pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>> {
+        let insufficient = fits_request(pod, node);
+        if insufficient.is_empty() {
+            Ok(())
+        } else {
+            Err(insufficient.into_iter().map(|resource| resource.reason).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{node, pod, with_allocatable, with_requests};
+    use k8s_api::core::v1::{Container, CONTAINER_RESTART_POLICY_ALWAYS};
+
+    #[test]
+    fn test_fits_request() {
+        let node_with = |pods| {
+            NodeInfo::new(
+                with_allocatable(node("n"), &[("cpu", "2"), ("memory", "4Gi"), ("pods", "2"), ("example.com/gpu", "1")]),
+                pods,
+            )
+        };
+        let existing = with_requests(pod("existing"), &[("cpu", "1500m"), ("memory", "1Gi")]);
+        let node = node_with(vec![existing.clone()]);
+
+        assert_eq!(NodeResourcesFit.filter(&with_requests(pod("small"), &[("cpu", "500m")]), &node), Ok(()));
+        assert_eq!(
+            NodeResourcesFit.filter(
+                &with_requests(pod("big"), &[("cpu", "501m"), ("memory", "3Gi"), ("example.com/gpu", "2")]),
+                &node
+            ),
+            Err(vec!["Insufficient cpu".to_string(), "Insufficient example.com/gpu".to_string()])
+        );
+        let full = node_with(vec![existing.clone(), pod("other")]);
+        assert_eq!(NodeResourcesFit.filter(&pod("empty"), &full), Err(vec!["Too many pods".to_string()]));
+
+        // An init container needing more than the app containers raises the
+        // request, sidecars add to it, and so does the overhead.
+        let mut init = with_requests(pod("init"), &[("cpu", "100m")]);
+        let spec = init.spec.as_mut().unwrap();
+        let container = |name: &str, cpu: &str, sidecar: bool| {
+            let mut container = with_requests(pod(name), &[("cpu", cpu)]).spec.unwrap().containers.remove(0);
+            if sidecar {
+                container.restart_policy = Some(CONTAINER_RESTART_POLICY_ALWAYS.to_string());
+            }
+            container
+        };
+        let init_containers: Vec<Container> = vec![container("sidecar", "100m", true), container("setup", "300m", false)];
+        spec.init_containers = init_containers;
+        spec.overhead.insert("cpu".to_string(), "50m".parse().unwrap());
+        assert_eq!(pod_request(&init).milli_cpu, 450);
+        assert_eq!(NodeResourcesFit.filter(&init, &node), Ok(()));
+        spec_cpu(&mut init, "101m");
+        assert_eq!(pod_request(&init).milli_cpu, 450);
+        spec_cpu(&mut init, "460m");
+        assert_eq!(
+            fits_request(&init, &node),
+            [InsufficientResource {
+                resource_name: "cpu".to_string(),
+                reason: "Insufficient cpu".to_string(),
+                requested: 610,
+                used: 1500,
+                capacity: 2000,
+            }]
+        );
+    }
+
+    fn spec_cpu(pod: &mut Pod, cpu: &str) {
+        let container = &mut pod.spec.as_mut().unwrap().containers[0];
+        container.resources.as_mut().unwrap().requests.insert("cpu".to_string(), cpu.parse().unwrap());
+    }
+}
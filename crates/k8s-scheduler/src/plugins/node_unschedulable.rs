@@ -0,0 +1,41 @@
+//! NodeUnschedulable
+//!
+//! Cordoned nodes, with `spec.unschedulable`, only fit pods tolerating the
+//! `node.kubernetes.io/unschedulable` taint.
+
+use k8s_api::core::v1::{tolerations_tolerate_taint, Pod, Taint, TAINT_EFFECT_NO_SCHEDULE};
+
+use crate::framework::{FilterPlugin, NodeInfo};
+
+pub const NAME: &str = "NodeUnschedulable";
+
+pub const ERR_REASON_UNSCHEDULABLE: &str = "node(s) were unschedulable";
+
+/// The taint of cordoned nodes.
+pub const TAINT_NODE_UNSCHEDULABLE: &str = "node.kubernetes.io/unschedulable";
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodeUnschedulable;
+
+impl FilterPlugin for NodeUnschedulable {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn filter(&self, pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>> {
+        if !node.node.spec.as_ref().is_some_and(|spec| spec.unschedulable) {
+            return Ok(());
+        }
+        let taint = Taint {
+            key: TAINT_NODE_UNSCHEDULABLE.to_string(),
+            effect: TAINT_EFFECT_NO_SCHEDULE.to_string(),
+            ..Default::default()
+        };
+        let tolerations = pod.spec.as_ref().map(|spec| spec.tolerations.as_slice()).unwrap_or_default();
+        if tolerations_tolerate_taint(tolerations, &taint) {
+            Ok(())
+        } else {
+            Err(vec![ERR_REASON_UNSCHEDULABLE.to_string()])
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! PodTopologySpread
+//!
+//! Pods only fit nodes where placing them keeps the number of matching
+//! pods per topology domain within `maxSkew` of the domain with the fewest,
+//! for their `DoNotSchedule` topology spread constraints.
+
+use std::collections::BTreeMap;
+
+use k8s_api::core::v1::{
+    do_not_schedule_taints, find_untolerated_taint, required_node_affinity_matches, Pod, NODE_INCLUSION_POLICY_HONOR,
+    UNSATISFIABLE_CONSTRAINT_ACTION_DO_NOT_SCHEDULE,
+};
+use k8s_apimachinery::apis::meta::v1::{LabelSelector, LabelSelectorRequirement};
+
+use crate::framework::{FilterPlugin, NodeInfo, Snapshot};
+
+pub const NAME: &str = "PodTopologySpread";
+
+pub const ERR_REASON_CONSTRAINTS_NOT_MATCH: &str = "node(s) didn't match pod topology spread constraints";
+pub const ERR_REASON_NODE_LABEL_NOT_MATCH: &str =
+    "node(s) didn't match pod topology spread constraints (missing required label)";
+
+#[derive(Clone, Debug, PartialEq)]
+struct Constraint {
+    max_skew: i64,
+    topology_key: String,
+    /// The label selector, with the `matchLabelKeys` labels of the pod
+    /// added. No selector selects no pod.
+    selector: Option<LabelSelector>,
+    min_domains: i64,
+    honor_node_affinity: bool,
+    honor_node_taints: bool,
+    /// Matching pods per domain of the nodes the constraint counts.
+    counts: BTreeMap<String, i64>,
+}
+
+impl Constraint {
+    fn matches(&self, pod: &Pod) -> Result<bool, String> {
+        match &self.selector {
+            Some(selector) => selector.matches(&pod.metadata.labels),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the fewest matching pods of a domain; zero if there are
+    /// fewer domains than `minDomains`.
+    fn min_match_num(&self) -> i64 {
+        if (self.counts.len() as i64) < self.min_domains {
+            return 0;
+        }
+        self.counts.values().copied().min().unwrap_or(i64::from(i32::MAX))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PodTopologySpread {
+    constraints: Vec<Constraint>,
+}
+
+impl FilterPlugin for PodTopologySpread {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn pre_filter(&mut self, snapshot: &Snapshot, pod: &Pod) -> Result<(), String> {
+        let Some(spec) = &pod.spec else {
+            self.constraints.clear();
+            return Ok(());
+        };
+        let mut constraints: Vec<Constraint> = spec
+            .topology_spread_constraints
+            .iter()
+            .filter(|constraint| constraint.when_unsatisfiable == UNSATISFIABLE_CONSTRAINT_ACTION_DO_NOT_SCHEDULE)
+            .map(|constraint| {
+                let selector = constraint.label_selector.clone().map(|mut selector| {
+                    for key in &constraint.match_label_keys {
+                        if let Some(value) = pod.metadata.labels.get(key) {
+                            selector.match_expressions.push(LabelSelectorRequirement {
+                                key: key.clone(),
+                                operator: "In".to_string(),
+                                values: vec![value.clone()],
+                            });
+                        }
+                    }
+                    selector
+                });
+                Constraint {
+                    max_skew: i64::from(constraint.max_skew),
+                    topology_key: constraint.topology_key.clone(),
+                    selector,
+                    min_domains: i64::from(constraint.min_domains.unwrap_or(1)),
+                    // Node affinity is honored and taints are ignored by
+                    // default.
+                    honor_node_affinity: constraint
+                        .node_affinity_policy
+                        .as_deref()
+                        .is_none_or(|policy| policy == NODE_INCLUSION_POLICY_HONOR),
+                    honor_node_taints: constraint.node_taints_policy.as_deref() == Some(NODE_INCLUSION_POLICY_HONOR),
+                    counts: BTreeMap::new(),
+                }
+            })
+            .collect();
+
+        let tolerations = &spec.tolerations;
+        for node in &snapshot.nodes {
+            let labels = &node.node.metadata.labels;
+            // Nodes count only if they have the labels of every constraint.
+            if !constraints.iter().all(|constraint| labels.contains_key(&constraint.topology_key)) {
+                continue;
+            }
+            let taints = node.node.spec.as_ref().map(|spec| spec.taints.as_slice()).unwrap_or_default();
+            for constraint in &mut constraints {
+                if constraint.honor_node_affinity && !required_node_affinity_matches(pod, &node.node).unwrap_or(false) {
+                    continue;
+                }
+                if constraint.honor_node_taints && find_untolerated_taint(taints, tolerations, do_not_schedule_taints).is_some() {
+                    continue;
+                }
+                let mut count = 0;
+                for existing in &node.pods {
+                    let terminating = existing.metadata.deletion_timestamp.is_some();
+                    if !terminating && existing.metadata.namespace == pod.metadata.namespace && constraint.matches(existing)? {
+                        count += 1;
+                    }
+                }
+                *constraint.counts.entry(labels[&constraint.topology_key].clone()).or_default() += count;
+            }
+        }
+        self.constraints = constraints;
+        Ok(())
+    }
+
+    fn filter(&self, pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>> {
+        for constraint in &self.constraints {
+            let Some(value) = node.node.metadata.labels.get(&constraint.topology_key) else {
+                return Err(vec![ERR_REASON_NODE_LABEL_NOT_MATCH.to_string()]);
+            };
+            let self_match = i64::from(constraint.matches(pod).unwrap_or(false));
+            let match_num = constraint.counts.get(value).copied().unwrap_or_default();
+            let skew = match_num + self_match - constraint.min_match_num();
+            if skew > constraint.max_skew {
+                return Err(vec![ERR_REASON_CONSTRAINTS_NOT_MATCH.to_string()]);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{labeled_node, labeled_pod, on_node};
+    use k8s_api::core::v1::TopologySpreadConstraint;
+
+    fn spread(max_skew: i32, min_domains: Option<i32>) -> Pod {
+        let mut pod = labeled_pod("web", &[("app", "web")]);
+        pod.spec.as_mut().unwrap().topology_spread_constraints = vec![TopologySpreadConstraint {
+            max_skew,
+            topology_key: "zone".to_string(),
+            when_unsatisfiable: UNSATISFIABLE_CONSTRAINT_ACTION_DO_NOT_SCHEDULE.to_string(),
+            label_selector: Some(LabelSelector {
+                match_labels: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                ..Default::default()
+            }),
+            min_domains,
+            ..Default::default()
+        }];
+        pod
+    }
+
+    fn filter(snapshot: &Snapshot, pod: &Pod) -> Vec<bool> {
+        let mut plugin = PodTopologySpread::default();
+        plugin.pre_filter(snapshot, pod).unwrap();
+        snapshot.nodes.iter().map(|node| plugin.filter(pod, node).is_ok()).collect()
+    }
+
+    #[test]
+    fn test_pod_topology_spread() {
+        let nodes = vec![
+            labeled_node("a", &[("zone", "a")]),
+            labeled_node("b", &[("zone", "b")]),
+            labeled_node("c", &[("zone", "c")]),
+            labeled_node("none", &[]),
+        ];
+        let mut terminating = on_node(labeled_pod("web-3", &[("app", "web")]), "b");
+        terminating.metadata.deletion_timestamp = Some(Default::default());
+        let pods = vec![
+            on_node(labeled_pod("web-1", &[("app", "web")]), "a"),
+            on_node(labeled_pod("web-2", &[("app", "web")]), "a"),
+            on_node(labeled_pod("web-4", &[("app", "web")]), "b"),
+            terminating,
+            on_node(labeled_pod("db", &[("app", "db")]), "c"),
+        ];
+        let snapshot = Snapshot::new(nodes, pods);
+        // Zones have 2, 1 and 0 matching pods.
+        assert_eq!(filter(&snapshot, &spread(1, None)), [false, false, true, false]);
+        assert_eq!(filter(&snapshot, &spread(2, None)), [false, true, true, false]);
+        // With fewer domains than minDomains the minimum is zero.
+        assert_eq!(filter(&snapshot, &spread(2, Some(4))), [false, true, true, false]);
+
+        let mut plugin = PodTopologySpread::default();
+        let pod = spread(1, None);
+        plugin.pre_filter(&snapshot, &pod).unwrap();
+        assert_eq!(plugin.filter(&pod, &snapshot.nodes[3]), Err(vec![ERR_REASON_NODE_LABEL_NOT_MATCH.to_string()]));
+        assert_eq!(plugin.filter(&pod, &snapshot.nodes[0]), Err(vec![ERR_REASON_CONSTRAINTS_NOT_MATCH.to_string()]));
+    }
+}
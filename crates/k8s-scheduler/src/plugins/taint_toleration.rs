@@ -0,0 +1,31 @@
+//! TaintToleration
+//!
+//! Pods only fit nodes whose `NoSchedule` and `NoExecute` taints they
+//! tolerate.
+
+use k8s_api::core::v1::{do_not_schedule_taints, find_untolerated_taint, Pod};
+
+use crate::framework::{FilterPlugin, NodeInfo};
+
+pub const NAME: &str = "TaintToleration";
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaintToleration;
+
+impl FilterPlugin for TaintToleration {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn filter(&self, pod: &Pod, node: &NodeInfo) -> Result<(), Vec<String>> {
+        let taints = node.node.spec.as_ref().map(|spec| spec.taints.as_slice()).unwrap_or_default();
+        let tolerations = pod.spec.as_ref().map(|spec| spec.tolerations.as_slice()).unwrap_or_default();
+        match find_untolerated_taint(taints, tolerations, do_not_schedule_taints) {
+            Some(taint) => Err(vec![format!(
+                "node(s) had untolerated taint {{{}: {}}}",
+                taint.key, taint.value
+            )]),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Fit simulation
+//!
+//! Runs the filter plugins over every node of a snapshot for a pod, telling
+//! which nodes the pod fits and why the scheduler rejects the others, as
+//! the `FailedScheduling` event of an unschedulable pod does.
+
+use std::collections::BTreeMap;
+
+use k8s_api::core::v1::Pod;
+
+use crate::framework::{FilterPlugin, Snapshot};
+use crate::plugins::default_plugins;
+
+/// Why a node was rejected: the first plugin the pod failed and its
+/// reasons.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejection {
+    pub plugin: &'static str,
+    pub reasons: Vec<String>,
+}
+
+/// The outcome of filtering the nodes of a snapshot for a pod.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Simulation {
+    pub num_nodes: usize,
+    /// The nodes the pod fits, in snapshot order.
+    pub feasible_nodes: Vec<String>,
+    /// The rejections of the other nodes by node name.
+    pub rejections: BTreeMap<String, Rejection>,
+}
+
+impl Simulation {
+    pub fn is_schedulable(&self) -> bool {
+        !self.feasible_nodes.is_empty()
+    }
+
+    /// Returns the message the scheduler reports for a pod no node fits,
+    /// e.g. `0/3 nodes are available: 1 Insufficient cpu, 2 node(s) were
+    /// unschedulable.`, or `None` if a node fits.
+    pub fn fit_error(&self) -> Option<String> {
+        if self.is_schedulable() {
+            return None;
+        }
+        let mut histogram: BTreeMap<&str, usize> = BTreeMap::new();
+        for rejection in self.rejections.values() {
+            for reason in &rejection.reasons {
+                *histogram.entry(reason).or_default() += 1;
+            }
+        }
+        let mut reasons: Vec<String> = histogram.iter().map(|(reason, count)| format!("{} {}", count, reason)).collect();
+        reasons.sort();
+        let mut message = format!("0/{} nodes are available:", self.num_nodes);
+        if !reasons.is_empty() {
+            message.push_str(&format!(" {}.", reasons.join(", ")));
+        }
+        Some(message)
+    }
+}
+
+/// Filters the nodes of `snapshot` for `pod` with the default plugins.
+pub fn simulate(snapshot: &Snapshot, pod: &Pod) -> Result<Simulation, String> {
+    simulate_with(snapshot, pod, &mut default_plugins())
+}
+
+/// Filters the nodes of `snapshot` for `pod` with `plugins`, run in order.
+/// Fails if a plugin fails to prepare for the pod.
+pub fn simulate_with(snapshot: &Snapshot, pod: &Pod, plugins: &mut [Box<dyn FilterPlugin>]) -> Result<Simulation, String> {
+    for plugin in plugins.iter_mut() {
+        plugin
+            .pre_filter(snapshot, pod)
+            .map_err(|err| format!("running PreFilter plugin {:?}: {}", plugin.name(), err))?;
+    }
+    let mut simulation = Simulation {
+        num_nodes: snapshot.nodes.len(),
+        ..Default::default()
+    };
+    for node in &snapshot.nodes {
+        let rejection = plugins.iter().find_map(|plugin| {
+            plugin.filter(pod, node).err().map(|reasons| Rejection {
+                plugin: plugin.name(),
+                reasons,
+            })
+        });
+        match rejection {
+            Some(rejection) => {
+                simulation.rejections.insert(node.name().to_string(), rejection);
+            }
+            None => simulation.feasible_nodes.push(node.name().to_string()),
+        }
+    }
+    Ok(simulation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{labeled_node, node, pod, with_allocatable, with_requests};
+    use k8s_api::core::v1::{NodeSpec, Taint, TAINT_EFFECT_NO_SCHEDULE};
+
+    #[test]
+    fn test_simulate() {
+        let mut cordoned = node("cordoned");
+        cordoned.spec = Some(NodeSpec {
+            unschedulable: true,
+            ..Default::default()
+        });
+        let mut tainted = node("tainted");
+        tainted.spec = Some(NodeSpec {
+            taints: vec![Taint {
+                key: "dedicated".to_string(),
+                value: "gpu".to_string(),
+                effect: TAINT_EFFECT_NO_SCHEDULE.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let nodes = vec![
+            cordoned,
+            tainted,
+            with_allocatable(node("small-1"), &[("cpu", "1")]),
+            with_allocatable(node("small-2"), &[("cpu", "1")]),
+            with_allocatable(labeled_node("big", &[("size", "big")]), &[("cpu", "8")]),
+        ];
+        let snapshot = Snapshot::new(nodes.clone(), vec![]);
+        let pod = with_requests(pod("web"), &[("cpu", "2")]);
+
+        let simulation = simulate(&snapshot, &pod).unwrap();
+        assert_eq!(simulation.feasible_nodes, ["big"]);
+        assert_eq!(simulation.rejections["cordoned"].plugin, "NodeUnschedulable");
+        assert_eq!(simulation.fit_error(), None);
+
+        let snapshot = Snapshot::new(nodes[..4].to_vec(), vec![]);
+        let simulation = simulate(&snapshot, &pod).unwrap();
+        assert_eq!(
+            simulation.fit_error().unwrap(),
+            "0/4 nodes are available: 1 node(s) had untolerated taint {dedicated: gpu}, \
+             1 node(s) were unschedulable, 2 Insufficient cpu."
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! Builders of the pods and nodes used in tests.
+
+use k8s_api::core::v1::{Container, Node, NodeStatus, Pod, PodSpec, ResourceRequirements};
+use k8s_apimachinery::apis::meta::v1::ObjectMeta;
+
+/// Returns a pod in the `default` namespace with a single container.
+pub fn pod(name: &str) -> Pod {
+    Pod {
+        metadata: ObjectMeta {
+            name: name.to_string(),
+            namespace: "default".to_string(),
+            ..Default::default()
+        },
+        spec: Some(PodSpec {
+            containers: vec![Container {
+                name: "app".to_string(),
+                image: "app".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn labeled_pod(name: &str, labels: &[(&str, &str)]) -> Pod {
+    let mut pod = pod(name);
+    pod.metadata.labels = labels.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+    pod
+}
+
+/// Assigns a pod to a node.
+pub fn on_node(mut pod: Pod, node_name: &str) -> Pod {
+    pod.spec.as_mut().unwrap().node_name = node_name.to_string();
+    pod
+}
+
+/// Sets the requests of the container of a pod.
+pub fn with_requests(mut pod: Pod, requests: &[(&str, &str)]) -> Pod {
+    let container = &mut pod.spec.as_mut().unwrap().containers[0];
+    container.resources = Some(ResourceRequirements {
+        requests: requests
+            .iter()
+            .map(|(name, quantity)| (name.to_string(), quantity.parse().unwrap()))
+            .collect(),
+        ..Default::default()
+    });
+    pod
+}
+
+/// Returns a node with room for 110 pods.
+pub fn node(name: &str) -> Node {
+    let node = Node {
+        metadata: ObjectMeta {
+            name: name.to_string(),
+            ..Default::default()
+        },
+        status: Some(NodeStatus::default()),
+        ..Default::default()
+    };
+    with_allocatable(node, &[("pods", "110")])
+}
+
+pub fn labeled_node(name: &str, labels: &[(&str, &str)]) -> Node {
+    let mut node = node(name);
+    node.metadata.labels = labels.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+    node
+}
+
+/// Sets allocatable resources of a node.
+pub fn with_allocatable(mut node: Node, allocatable: &[(&str, &str)]) -> Node {
+    let status = node.status.get_or_insert_with(Default::default);
+    for (name, quantity) in allocatable {
+        status.allocatable.insert(name.to_string(), quantity.parse().unwrap());
+    }
+    node
+}